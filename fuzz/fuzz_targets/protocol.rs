@@ -0,0 +1,44 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use libp2p::kad::{Record, RecordKey};
+use sn_protocol::{
+    messages::{Request, Response},
+    storage::{try_deserialize_record, Chunk, RecordHeader},
+};
+
+#[derive(Debug, Arbitrary)]
+enum Input<'a> {
+    Request(&'a [u8]),
+    Response(&'a [u8]),
+    Record(&'a [u8]),
+}
+
+fuzz_target!(|input: Input| {
+    match input {
+        Input::Request(bytes) => {
+            if let Ok(request) = Request::try_from_bytes(bytes) {
+                let bytes = request.to_bytes().expect("a read request to be written");
+                assert_eq!(Request::try_from_bytes(&bytes).ok(), Some(request));
+            }
+        }
+        Input::Response(bytes) => {
+            let _ = Response::try_from_bytes(bytes);
+        }
+        Input::Record(bytes) => {
+            let record = Record::new(RecordKey::new(&[]), bytes.to_vec());
+            if RecordHeader::from_record(&record).is_ok() {
+                let _ = try_deserialize_record::<Chunk>(&record);
+            }
+        }
+    }
+});
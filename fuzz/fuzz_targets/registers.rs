@@ -0,0 +1,38 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use sn_registers::{RegisterAddress, RegisterOp, SignedRegister};
+
+#[derive(Debug, Arbitrary)]
+enum Input<'a> {
+    RegisterOp(&'a [u8]),
+    SignedRegister(&'a [u8]),
+    AddressHex(&'a str),
+}
+
+fuzz_target!(|input: Input| {
+    match input {
+        Input::RegisterOp(bytes) => {
+            if let Ok(op) = RegisterOp::try_from_bytes(bytes) {
+                let _ = op.verify_signature(&op.source());
+            }
+        }
+        Input::SignedRegister(bytes) => {
+            if let Ok(register) = SignedRegister::try_from_bytes(bytes) {
+                let _ = register.verify();
+            }
+        }
+        Input::AddressHex(hex) => {
+            let _ = RegisterAddress::from_hex(hex);
+        }
+    }
+});
@@ -0,0 +1,50 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use sn_transfers::{CashNote, SignedSpend, Transfer};
+
+#[derive(Debug, Arbitrary)]
+enum Input<'a> {
+    CashNote(&'a [u8]),
+    CashNoteHex(&'a str),
+    Transfer(&'a [u8]),
+    TransferHex(&'a str),
+    SignedSpend(&'a [u8]),
+}
+
+fuzz_target!(|input: Input| {
+    match input {
+        Input::CashNote(bytes) => {
+            if let Ok(cash_note) = CashNote::try_from_bytes(bytes) {
+                let bytes = cash_note
+                    .to_bytes()
+                    .expect("a read cash_note to be written");
+                assert_eq!(CashNote::try_from_bytes(&bytes).ok(), Some(cash_note));
+            }
+        }
+        Input::CashNoteHex(hex) => {
+            let _ = CashNote::from_hex(hex);
+        }
+        Input::Transfer(bytes) => {
+            if let Ok(transfer) = Transfer::try_from_bytes(bytes) {
+                let bytes = transfer.to_bytes().expect("a read transfer to be written");
+                assert_eq!(Transfer::try_from_bytes(&bytes).ok(), Some(transfer));
+            }
+        }
+        Input::TransferHex(hex) => {
+            let _ = Transfer::from_hex(hex);
+        }
+        Input::SignedSpend(bytes) => {
+            let _ = SignedSpend::try_from_bytes(bytes);
+        }
+    }
+});
@@ -11,7 +11,7 @@ mod change_tracking;
 use change_tracking::*;
 
 use super::{
-    files::{download_file, FilesUploader},
+    files::{download_file, DownloadCfg, FilesUploader, OverwritePolicy},
     ChunkManager,
 };
 
@@ -20,7 +20,7 @@ use sn_client::{
     protocol::storage::{Chunk, RegisterAddress, RetryStrategy},
     registers::EntryHash,
     transfers::{DerivationIndex, MainSecretKey},
-    Client, FilesApi, FolderEntry, FoldersApi, Metadata, UploadCfg, WalletClient,
+    Client, FilesApi, FilesDownload, FolderEntry, FoldersApi, Metadata, UploadCfg, WalletClient,
 };
 
 use bls::PublicKey;
@@ -31,7 +31,7 @@ use color_eyre::{
 use std::{
     collections::{
         btree_map::{Entry, OccupiedEntry},
-        BTreeMap,
+        BTreeMap, BTreeSet,
     },
     ffi::OsString,
     fs::{create_dir_all, remove_dir_all, remove_file, File},
@@ -39,7 +39,7 @@ use std::{
     path::{Path, PathBuf},
 };
 use tokio::task::JoinSet;
-use tracing::trace;
+use tracing::{debug, trace, warn};
 use walkdir::{DirEntry, WalkDir};
 use xor_name::XorName;
 
@@ -260,8 +260,14 @@ impl AccountPacket {
     /// Sync local changes made to files and folder with their version on the network,
     /// both pushing and pulling changes to/form the network.
     pub async fn sync(&mut self, upload_cfg: UploadCfg, make_data_public: bool) -> Result<()> {
-        let ChangesToApply { folders, mutations } =
-            self.scan_files_and_folders_for_changes(make_data_public)?;
+        let ChangesToApply {
+            folders,
+            mut mutations,
+            changed_files_chunks,
+        } = self.scan_files_and_folders_for_changes(make_data_public)?;
+        let existing_chunks = self
+            .find_reused_chunks(&mut mutations, &changed_files_chunks)
+            .await;
 
         if mutations.is_empty() {
             println!("No local changes made to files/folders to be pushed to network.");
@@ -270,9 +276,15 @@ impl AccountPacket {
             mutations.iter().for_each(|m| println!("{m}"));
         }
 
+        // the chunks cached for a file when it was last uploaded are stale once its content changed
+        let chunk_manager = ChunkManager::new(&self.wallet_dir);
+        for file_path in changed_files_chunks.keys() {
+            chunk_manager.remove_cached_chunks(file_path)?;
+        }
+
         println!("Paying for folders hierarchy and uploading...");
         let synced_folders = self
-            .pay_and_sync_folders(folders, upload_cfg, make_data_public)
+            .pay_and_sync_folders(folders, upload_cfg, make_data_public, existing_chunks)
             .await?;
 
         // mark root folder as created if it wasn't already
@@ -362,6 +374,7 @@ impl AccountPacket {
             meta_xorname,
             metadata,
             entry_hash,
+            chunk_reuse,
         }: MetadataTrackingInfo,
     ) -> Result<()> {
         let metadata_file_path = self.meta_dir.join(hex::encode(meta_xorname));
@@ -374,6 +387,7 @@ impl AccountPacket {
             meta_xorname,
             metadata,
             entry_hash,
+            chunk_reuse,
         };
 
         meta_file.write_all(&rmp_serde::to_vec(&tracking_info)?)?;
@@ -449,12 +463,74 @@ impl AccountPacket {
         removed
     }
 
+    // Find the chunks the new content of every changed file shares with its previous version,
+    // recording their number in the tracking info of the file. Self-encrypted chunks are addressed
+    // by their content, so those are on the network already and need neither be paid for nor
+    // uploaded again. Files are cut into segments by their content before being self-encrypted,
+    // so inserting or removing bytes only changes the chunks of the segments around the edit.
+    async fn find_reused_chunks(
+        &self,
+        mutations: &mut [Mutation],
+        changed_files_chunks: &BTreeMap<PathBuf, BTreeSet<XorName>>,
+    ) -> BTreeSet<XorName> {
+        let mut files_download =
+            FilesDownload::new(FilesApi::new(self.client.clone(), self.wallet_dir.clone()));
+        let mut existing_chunks = BTreeSet::new();
+        for mutation in mutations.iter_mut() {
+            let Mutation::FileContentChanged((_, tracking_info)) = mutation else {
+                continue;
+            };
+            let Some(new_chunks) = changed_files_chunks.get(&tracking_info.file_path) else {
+                continue;
+            };
+            let previous_chunks = match self.get_tracking_info(&tracking_info.file_path) {
+                Ok(Some(MetadataTrackingInfo {
+                    metadata:
+                        Metadata {
+                            content: FolderEntry::File(data_map_chunk),
+                            ..
+                        },
+                    ..
+                })) => files_download.chunk_addresses(data_map_chunk.clone()).await,
+                _ => continue,
+            };
+            let previous_chunks: BTreeSet<XorName> = match previous_chunks {
+                Ok(addresses) => addresses.into_iter().collect(),
+                Err(err) => {
+                    // the whole new content is uploaded then, as it was before chunk reuse
+                    warn!(
+                        "Could not read the previous data map of {:?}: {err:?}",
+                        tracking_info.file_path
+                    );
+                    continue;
+                }
+            };
+
+            let reused: BTreeSet<XorName> =
+                new_chunks.intersection(&previous_chunks).copied().collect();
+            debug!(
+                "Reusing {} of the {} chunks of {:?}",
+                reused.len(),
+                new_chunks.len(),
+                tracking_info.file_path
+            );
+            tracking_info.chunk_reuse = Some(ChunkReuse {
+                reused: reused.len(),
+                total: new_chunks.len(),
+            });
+            existing_chunks.extend(reused);
+        }
+        existing_chunks
+    }
+
     // Scan existing files and folders on disk, generating a report of all the detected
     // changes based on the tracking info kept locally.
     // If make_data_public is false the metadata chunks are encrypted.
     fn scan_files_and_folders_for_changes(&self, make_data_public: bool) -> Result<ChangesToApply> {
         // we don't use the local cache in order to realise of any changes made to files content.
         let mut chunk_manager = ChunkManager::new(&self.tracking_info_dir);
+        chunk_manager.set_content_defined_chunking(true);
+        chunk_manager.set_fixed_size_files(self.fixed_size_files());
         chunk_manager.chunk_with_iter(self.iter_only_files(), false, false)?;
 
         let encryption_pk = if make_data_public {
@@ -494,8 +570,17 @@ impl AccountPacket {
                                         meta_xorname,
                                         metadata,
                                         entry_hash,
+                                        chunk_reuse: None,
                                     },
                                 )));
+                                let _ = changes.changed_files_chunks.insert(
+                                    file_path.to_path_buf(),
+                                    chunked_file
+                                        .chunks
+                                        .iter()
+                                        .map(|(xorname, _)| *xorname)
+                                        .collect(),
+                                );
                             }
                         }
                         FolderEntry::Folder(_) => {
@@ -514,6 +599,7 @@ impl AccountPacket {
                                     meta_xorname,
                                     metadata,
                                     entry_hash,
+                                    chunk_reuse: None,
                                 }));
                         }
                     },
@@ -533,6 +619,7 @@ impl AccountPacket {
                                 meta_xorname,
                                 metadata,
                                 entry_hash,
+                                chunk_reuse: None,
                             }));
                     }
                     Err(err) => {
@@ -615,6 +702,7 @@ impl AccountPacket {
                             meta_xorname,
                             metadata,
                             entry_hash,
+                            chunk_reuse: None,
                         }));
                 }
             }
@@ -623,6 +711,24 @@ impl AccountPacket {
         Ok(changes)
     }
 
+    // The tracked files uploaded in fixed size chunks, before content defined chunking, which keep
+    // being chunked so for an unchanged file not to be seen as changed.
+    fn fixed_size_files(&self) -> BTreeSet<PathBuf> {
+        self.curr_tracking_info
+            .iter()
+            .filter_map(
+                |(path, tracking_info)| match &tracking_info.metadata.content {
+                    FolderEntry::File(data_map_chunk)
+                        if !FilesApi::is_content_defined(data_map_chunk) =>
+                    {
+                        Some(self.files_dir.join(path))
+                    }
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
     // Read local tracking info for given file/folder item
     fn get_tracking_info(&self, path: &Path) -> Result<Option<&MetadataTrackingInfo>> {
         let path = self.get_relative_path(path)?;
@@ -676,10 +782,13 @@ impl AccountPacket {
         folders: Folders,
         upload_cfg: UploadCfg,
         make_data_public: bool,
+        existing_chunks: BTreeSet<XorName>,
     ) -> Result<Folders> {
         let files_uploader = FilesUploader::new(self.client.clone(), self.wallet_dir.clone())
             .set_upload_cfg(upload_cfg)
             .set_make_data_public(make_data_public)
+            .set_existing_chunks(existing_chunks)
+            .set_content_defined_chunking(true, self.fixed_size_files())
             .insert_entries(self.iter_only_files());
         let _summary = files_uploader.start_upload().await?;
 
@@ -782,17 +891,25 @@ impl AccountPacket {
         }
 
         let files_api: FilesApi = FilesApi::new(self.client.clone(), self.files_dir.clone());
+        // the files to download have already been created locally as placeholders
+        let download_cfg = DownloadCfg {
+            batch_size,
+            retry_strategy,
+            overwrite_policy: OverwritePolicy::Overwrite,
+            ..Default::default()
+        };
         for (file_name, data_map_chunk, path) in files_to_download {
-            download_file(
+            if let Err(err) = download_file(
                 files_api.clone(),
                 *data_map_chunk.name(),
                 (file_name, Some(data_map_chunk)),
                 &path,
-                false,
-                batch_size,
-                retry_strategy,
+                download_cfg,
             )
-            .await;
+            .await
+            {
+                println!("{err}");
+            }
         }
 
         Ok(updated_folders)
@@ -843,6 +960,7 @@ impl AccountPacket {
                 meta_xorname,
                 metadata,
                 entry_hash,
+                chunk_reuse: None,
             })?;
         }
 
@@ -930,7 +1048,7 @@ mod tests {
     };
     use rand::{thread_rng, Rng};
     use sn_client::{
-        protocol::storage::{Chunk, RetryStrategy},
+        protocol::storage::{Chunk, PaymentStrategy, RetryStrategy},
         registers::{EntryHash, RegisterAddress},
         test_utils::{get_funded_wallet, get_new_client, random_file_chunk},
         transfers::MainSecretKey,
@@ -956,6 +1074,9 @@ mod tests {
             show_holders: false,
             max_repayments_for_failed_data: 1,
             collect_registers: false,
+            reuse_existing_payments: false,
+            collect_payment_receipts: false,
+            payment_strategy: PaymentStrategy::CheapestMajority,
         };
         let make_data_public = false;
         (cfg, make_data_public)
@@ -1015,6 +1136,7 @@ mod tests {
                 meta_xorname,
                 metadata: dummy_metadata.clone(),
                 entry_hash: EntryHash::default(),
+                chunk_reuse: None,
             })?;
             assert!(acc_packet.meta_dir.join(hex::encode(meta_xorname)).exists());
         }
@@ -1202,6 +1324,46 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_acc_packet_sync_reuses_unchanged_chunks() -> Result<()> {
+        let client = get_new_client(SecretKey::random()).await?;
+        let root_sk = MainSecretKey::random();
+
+        let tmp_dir = tempfile::tempdir()?;
+        let wallet_dir = tmp_dir.path();
+        let _ = get_funded_wallet(&client, wallet_dir).await?;
+
+        let files_path = tmp_dir.path().join("myaccpackettosync");
+        create_dir_all(&files_path)?;
+        // large enough to be cut into several segments
+        let mut content = vec![0u8; 20 * 1024 * 1024];
+        thread_rng().fill(&mut content[..]);
+        let file_path = files_path.join("dataset.bin");
+        std::fs::write(&file_path, &content)?;
+
+        let mut acc_packet =
+            AccountPacket::init(client.clone(), wallet_dir, &files_path, &root_sk, None)?;
+        acc_packet.sync(SYNC_OPTS.0, SYNC_OPTS.1).await?;
+
+        // let's insert bytes near the start of the file, shifting all the content after them: only
+        // the chunks of the segment they are inserted in change
+        let _ = content.splice(100..100, [1; 10]);
+        std::fs::write(&file_path, &content)?;
+        acc_packet.sync(SYNC_OPTS.0, SYNC_OPTS.1).await?;
+
+        let tracking_info = read_tracking_info_from_disk(&acc_packet.meta_dir)?;
+        let chunk_reuse = tracking_info
+            .get(Path::new("dataset.bin"))
+            .and_then(|tracking_info| tracking_info.chunk_reuse)
+            .ok_or(eyre!("Chunk reuse was not recorded for the changed file"))?;
+        assert!(
+            chunk_reuse.reused > 0 && chunk_reuse.reused < chunk_reuse.total,
+            "Unexpected chunk reuse: {chunk_reuse:?}"
+        );
+
+        Ok(())
+    }
+
     // Acc-packets can be moved to different locations on local disk without affecting their tracking info.
     // We disable this test for Windows since in CI the use of std::fs::rename gives a permissions issue.
     #[cfg(any(target_os = "linux", target_os = "linux"))]
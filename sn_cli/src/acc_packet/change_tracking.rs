@@ -21,7 +21,7 @@ use color_eyre::{
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt,
     fs::{create_dir_all, File},
     io::Write,
@@ -83,6 +83,8 @@ impl FolderChange {
 pub(super) struct ChangesToApply {
     pub folders: Folders,
     pub mutations: Vec<Mutation>,
+    // The chunks of the new content of every file whose content changed, by file path.
+    pub changed_files_chunks: BTreeMap<PathBuf, BTreeSet<XorName>>,
 }
 
 // Type of mutation detected locally.
@@ -103,7 +105,11 @@ impl fmt::Display for Mutation {
             }
             Self::FileRemoved((path, _)) => write!(f, "File removed: {path:?}"),
             Self::FileContentChanged((_, tracking_info)) => {
-                write!(f, "File content changed: {:?}", tracking_info.file_path)
+                write!(f, "File content changed: {:?}", tracking_info.file_path)?;
+                if let Some(ChunkReuse { reused, total }) = tracking_info.chunk_reuse {
+                    write!(f, " ({reused} of its {total} chunks unchanged)")?;
+                }
+                Ok(())
             }
             Self::NewFolder(tracking_info) => {
                 write!(f, "New folder: {:?}", tracking_info.file_path)
@@ -122,6 +128,18 @@ pub(super) struct MetadataTrackingInfo {
     pub meta_xorname: XorName,
     pub metadata: Metadata,
     pub entry_hash: EntryHash,
+    // Set when a change of the file content is synced; absent from the tracking info stored
+    // before chunk reuse was recorded.
+    #[serde(default)]
+    pub chunk_reuse: Option<ChunkReuse>,
+}
+
+// How many of the chunks of a file were shared with its previous version when the change of its
+// content was synced. Those were neither paid for nor uploaded again.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub(super) struct ChunkReuse {
+    pub reused: usize,
+    pub total: usize,
 }
 
 // Build absolute paths for the different dirs to be used for locally tracking changes
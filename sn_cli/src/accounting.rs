@@ -0,0 +1,503 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::eyre, Result};
+use sn_client::transfers::{HistoryEntry, HistoryKind, MainPubkey, NanoTokens};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// The formats the wallet history can be exported to, to be imported by accounting software.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryFormat {
+    /// One row per entry, the amounts signed from the wallet's point of view.
+    Csv,
+    /// An OFX 2.2 bank statement of the wallet.
+    Ofx,
+    /// Beancount transactions, balancing the wallet account against the mapped ones.
+    Beancount,
+    /// ledger-cli transactions, balancing the wallet account against the mapped ones.
+    Ledger,
+}
+
+impl FromStr for HistoryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "ofx" => Ok(Self::Ofx),
+            "beancount" => Ok(Self::Beancount),
+            "ledger" | "ledger-cli" => Ok(Self::Ledger),
+            _ => Err(format!(
+                "Unknown history format {s}, expected one of: csv, ofx, beancount, ledger"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for HistoryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv => write!(f, "csv"),
+            Self::Ofx => write!(f, "ofx"),
+            Self::Beancount => write!(f, "beancount"),
+            Self::Ledger => write!(f, "ledger"),
+        }
+    }
+}
+
+/// The accounts the entries of the history are booked to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountMapping {
+    /// The account holding the tokens of the wallet
+    pub wallet: String,
+    /// Where the received tokens come from
+    pub income: String,
+    /// Where the tokens sent to other wallets go
+    pub transfers: String,
+    /// Where the storage payments go
+    pub storage: String,
+    /// Where the network royalties go
+    pub fees: String,
+    /// The commodity, or currency code, of the amounts
+    pub commodity: String,
+}
+
+impl Default for AccountMapping {
+    fn default() -> Self {
+        Self {
+            wallet: "Assets:SAFE:Wallet".to_string(),
+            income: "Income:SAFE".to_string(),
+            transfers: "Expenses:SAFE:Transfers".to_string(),
+            storage: "Expenses:SAFE:Storage".to_string(),
+            fees: "Expenses:SAFE:Royalties".to_string(),
+            commodity: "SNT".to_string(),
+        }
+    }
+}
+
+impl AccountMapping {
+    /// The default mapping, overridden by `key=account` pairs, e.g. "storage=Expenses:Hosting".
+    pub fn with_overrides(overrides: &[String]) -> Result<Self> {
+        let mut mapping = Self::default();
+        for pair in overrides {
+            let (key, account) = pair
+                .split_once('=')
+                .ok_or_else(|| eyre!("Invalid account mapping {pair:?}, expected 'key=account'"))?;
+            let account = account.trim().to_string();
+            if account.is_empty() || account.contains(char::is_whitespace) {
+                return Err(eyre!("Invalid account {account:?} in {pair:?}"));
+            }
+            match key.trim() {
+                "wallet" => mapping.wallet = account,
+                "income" => mapping.income = account,
+                "transfers" => mapping.transfers = account,
+                "storage" => mapping.storage = account,
+                "fees" => mapping.fees = account,
+                "commodity" => mapping.commodity = account,
+                key => {
+                    return Err(eyre!(
+                        "Unknown account key {key:?}, expected one of: wallet, income, transfers, \
+                         storage, fees, commodity"
+                    ))
+                }
+            }
+        }
+        Ok(mapping)
+    }
+
+    /// The account the amount of an entry is booked to, against the wallet.
+    fn counter_account(&self, kind: HistoryKind) -> &str {
+        match kind {
+            HistoryKind::Received => &self.income,
+            HistoryKind::Sent => &self.transfers,
+            HistoryKind::StoragePayment => &self.storage,
+        }
+    }
+}
+
+/// Writes the history of the wallet at `address`, whose current balance is `balance`.
+pub fn export_history<W: Write>(
+    entries: &[HistoryEntry],
+    address: MainPubkey,
+    balance: NanoTokens,
+    format: HistoryFormat,
+    mapping: &AccountMapping,
+    writer: &mut W,
+) -> io::Result<()> {
+    match format {
+        HistoryFormat::Csv => write_csv(entries, mapping, writer),
+        HistoryFormat::Ofx => write_ofx(entries, address, balance, mapping, writer),
+        HistoryFormat::Beancount => write_beancount(entries, mapping, writer),
+        HistoryFormat::Ledger => write_ledger(entries, mapping, writer),
+    }
+}
+
+/// The amount followed by the fee, both from the wallet's point of view.
+fn wallet_amounts(entry: &HistoryEntry) -> (String, String) {
+    match entry.kind {
+        HistoryKind::Received => (entry.amount.to_string(), entry.fee.to_string()),
+        HistoryKind::Sent | HistoryKind::StoragePayment => {
+            (negative(entry.amount), negative(entry.fee))
+        }
+    }
+}
+
+fn negative(amount: NanoTokens) -> String {
+    if amount.is_zero() {
+        amount.to_string()
+    } else {
+        format!("-{amount}")
+    }
+}
+
+fn description(entry: &HistoryEntry) -> String {
+    match (entry.kind, entry.counterparty) {
+        (HistoryKind::Received, _) => "Received".to_string(),
+        (HistoryKind::Sent, Some(recipient)) => format!("Sent to {}", recipient.to_hex()),
+        (HistoryKind::Sent, None) => "Sent".to_string(),
+        (HistoryKind::StoragePayment, _) => "Storage payment".to_string(),
+    }
+}
+
+/// A stable identifier of the entry, for the importers to skip the entries imported already.
+fn reference(entry: &HistoryEntry) -> String {
+    entry
+        .cash_notes
+        .first()
+        .map(|unique_pubkey| unique_pubkey.to_hex())
+        .unwrap_or_else(|| entry.time.to_string())
+}
+
+fn datetime(time: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp(time as i64, 0).unwrap_or_default()
+}
+
+fn write_csv<W: Write>(
+    entries: &[HistoryEntry],
+    mapping: &AccountMapping,
+    writer: &mut W,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "date,kind,amount,fee,commodity,account,counterparty,reference"
+    )?;
+    for entry in entries {
+        let (amount, fee) = wallet_amounts(entry);
+        let kind = match entry.kind {
+            HistoryKind::Received => "received",
+            HistoryKind::Sent => "sent",
+            HistoryKind::StoragePayment => "storage_payment",
+        };
+        let counterparty = entry
+            .counterparty
+            .map(|recipient| recipient.to_hex())
+            .unwrap_or_default();
+        writeln!(
+            writer,
+            "{},{kind},{amount},{fee},{},{},{counterparty},{}",
+            datetime(entry.time).format("%Y-%m-%dT%H:%M:%SZ"),
+            csv_escape(&mapping.commodity),
+            csv_escape(mapping.counter_account(entry.kind)),
+            reference(entry)
+        )?;
+    }
+    Ok(())
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_ofx<W: Write>(
+    entries: &[HistoryEntry],
+    address: MainPubkey,
+    balance: NanoTokens,
+    mapping: &AccountMapping,
+    writer: &mut W,
+) -> io::Result<()> {
+    let ofx_time = |time: u64| datetime(time).format("%Y%m%d%H%M%S").to_string();
+    let start = entries.first().map(|entry| entry.time).unwrap_or_default();
+    let end = entries.last().map(|entry| entry.time).unwrap_or_default();
+    // the account id is limited to 22 characters
+    let account_id: String = address.to_hex().chars().take(22).collect();
+
+    writeln!(
+        writer,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#
+    )?;
+    writeln!(
+        writer,
+        r#"<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>"#
+    )?;
+    writeln!(writer, "<OFX>")?;
+    writeln!(writer, "<SIGNONMSGSRSV1><SONRS>")?;
+    writeln!(
+        writer,
+        "<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>"
+    )?;
+    writeln!(writer, "<DTSERVER>{}</DTSERVER>", ofx_time(end))?;
+    writeln!(writer, "<LANGUAGE>ENG</LANGUAGE>")?;
+    writeln!(writer, "</SONRS></SIGNONMSGSRSV1>")?;
+    writeln!(writer, "<BANKMSGSRSV1><STMTTRNRS>")?;
+    writeln!(writer, "<TRNUID>0</TRNUID>")?;
+    writeln!(
+        writer,
+        "<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>"
+    )?;
+    writeln!(writer, "<STMTRS>")?;
+    writeln!(
+        writer,
+        "<CURDEF>{}</CURDEF>",
+        xml_escape(&mapping.commodity)
+    )?;
+    writeln!(
+        writer,
+        "<BANKACCTFROM><BANKID>SAFE</BANKID><ACCTID>{account_id}</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE></BANKACCTFROM>"
+    )?;
+    writeln!(writer, "<BANKTRANLIST>")?;
+    writeln!(writer, "<DTSTART>{}</DTSTART>", ofx_time(start))?;
+    writeln!(writer, "<DTEND>{}</DTEND>", ofx_time(end))?;
+    for entry in entries {
+        let (amount, fee) = wallet_amounts(entry);
+        let kind = match entry.kind {
+            HistoryKind::Received => "CREDIT",
+            HistoryKind::Sent | HistoryKind::StoragePayment => "DEBIT",
+        };
+        writeln!(writer, "<STMTTRN>")?;
+        writeln!(writer, "<TRNTYPE>{kind}</TRNTYPE>")?;
+        writeln!(writer, "<DTPOSTED>{}</DTPOSTED>", ofx_time(entry.time))?;
+        writeln!(writer, "<TRNAMT>{amount}</TRNAMT>")?;
+        writeln!(writer, "<FITID>{}</FITID>", reference(entry))?;
+        writeln!(writer, "<NAME>{}</NAME>", xml_escape(&description(entry)))?;
+        writeln!(
+            writer,
+            "<MEMO>{}</MEMO>",
+            xml_escape(mapping.counter_account(entry.kind))
+        )?;
+        writeln!(writer, "</STMTTRN>")?;
+        // the fees are separate transactions, as OFX has a single amount per transaction
+        if !entry.fee.is_zero() {
+            writeln!(writer, "<STMTTRN>")?;
+            writeln!(writer, "<TRNTYPE>FEE</TRNTYPE>")?;
+            writeln!(writer, "<DTPOSTED>{}</DTPOSTED>", ofx_time(entry.time))?;
+            writeln!(writer, "<TRNAMT>{fee}</TRNAMT>")?;
+            writeln!(writer, "<FITID>{}-fee</FITID>", reference(entry))?;
+            writeln!(writer, "<NAME>Network royalties</NAME>")?;
+            writeln!(writer, "<MEMO>{}</MEMO>", xml_escape(&mapping.fees))?;
+            writeln!(writer, "</STMTTRN>")?;
+        }
+    }
+    writeln!(writer, "</BANKTRANLIST>")?;
+    writeln!(
+        writer,
+        "<LEDGERBAL><BALAMT>{balance}</BALAMT><DTASOF>{}</DTASOF></LEDGERBAL>",
+        ofx_time(end)
+    )?;
+    writeln!(writer, "</STMTRS>")?;
+    writeln!(writer, "</STMTTRNRS></BANKMSGSRSV1>")?;
+    writeln!(writer, "</OFX>")
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// The postings of an entry, as account and amount from the point of view of the account.
+fn postings<'a>(entry: &HistoryEntry, mapping: &'a AccountMapping) -> Vec<(&'a str, String)> {
+    let mut postings = vec![];
+    match entry.kind {
+        HistoryKind::Received => {
+            postings.push((mapping.wallet.as_str(), entry.amount.to_string()));
+            postings.push((mapping.counter_account(entry.kind), negative(entry.amount)));
+        }
+        HistoryKind::Sent | HistoryKind::StoragePayment => {
+            let total = entry.amount.checked_add(entry.fee).unwrap_or(entry.amount);
+            postings.push((mapping.wallet.as_str(), negative(total)));
+            postings.push((
+                mapping.counter_account(entry.kind),
+                entry.amount.to_string(),
+            ));
+            if !entry.fee.is_zero() {
+                postings.push((mapping.fees.as_str(), entry.fee.to_string()));
+            }
+        }
+    }
+    postings
+}
+
+fn write_beancount<W: Write>(
+    entries: &[HistoryEntry],
+    mapping: &AccountMapping,
+    writer: &mut W,
+) -> io::Result<()> {
+    // the accounts have to be opened before they are used
+    if let Some(first) = entries.first() {
+        let date = datetime(first.time).format("%Y-%m-%d");
+        let accounts: BTreeSet<_> = entries
+            .iter()
+            .flat_map(|entry| postings(entry, mapping))
+            .map(|(account, _)| account)
+            .collect();
+        for account in accounts {
+            writeln!(writer, "{date} open {account} {}", mapping.commodity)?;
+        }
+    }
+    for entry in entries {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{} * \"{}\"",
+            datetime(entry.time).format("%Y-%m-%d"),
+            description(entry)
+        )?;
+        writeln!(writer, "  reference: \"{}\"", reference(entry))?;
+        for (account, amount) in postings(entry, mapping) {
+            writeln!(writer, "  {account}  {amount} {}", mapping.commodity)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_ledger<W: Write>(
+    entries: &[HistoryEntry],
+    mapping: &AccountMapping,
+    writer: &mut W,
+) -> io::Result<()> {
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(
+            writer,
+            "{} * {}",
+            datetime(entry.time).format("%Y/%m/%d"),
+            description(entry)
+        )?;
+        writeln!(writer, "    ; reference: {}", reference(entry))?;
+        for (account, amount) in postings(entry, mapping) {
+            writeln!(writer, "    {account}  {amount} {}", mapping.commodity)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_client::transfers::{MainSecretKey, UniquePubkey};
+
+    fn entries(recipient: MainPubkey) -> Vec<HistoryEntry> {
+        let unique_pubkey = || UniquePubkey::new(bls::SecretKey::random().public_key());
+        vec![
+            HistoryEntry {
+                time: 1_714_564_800,
+                kind: HistoryKind::Received,
+                amount: NanoTokens::from(2_000_000_000),
+                fee: NanoTokens::zero(),
+                counterparty: None,
+                cash_notes: vec![unique_pubkey()],
+                reason_hash: Default::default(),
+                created_cash_notes: vec![],
+            },
+            HistoryEntry {
+                time: 1_714_651_200,
+                kind: HistoryKind::Sent,
+                amount: NanoTokens::from(500_000_000),
+                fee: NanoTokens::zero(),
+                counterparty: Some(recipient),
+                cash_notes: vec![unique_pubkey()],
+                reason_hash: Default::default(),
+                created_cash_notes: vec![],
+            },
+            HistoryEntry {
+                time: 1_714_737_600,
+                kind: HistoryKind::StoragePayment,
+                amount: NanoTokens::from(100),
+                fee: NanoTokens::from(15),
+                counterparty: None,
+                cash_notes: vec![unique_pubkey()],
+                reason_hash: Default::default(),
+                created_cash_notes: vec![],
+            },
+        ]
+    }
+
+    fn export(format: HistoryFormat, mapping: &AccountMapping) -> Result<String> {
+        let address = MainSecretKey::random().main_pubkey();
+        let recipient = MainSecretKey::random().main_pubkey();
+        let mut exported = vec![];
+        export_history(
+            &entries(recipient),
+            address,
+            NanoTokens::from(1_499_999_885),
+            format,
+            mapping,
+            &mut exported,
+        )?;
+        Ok(String::from_utf8(exported)?)
+    }
+
+    #[test]
+    fn csv_should_have_a_row_per_entry() -> Result<()> {
+        let csv = export(HistoryFormat::Csv, &AccountMapping::default())?;
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[1]
+            .starts_with("2024-05-01T12:00:00Z,received,2.000000000,0.000000000,SNT,Income:SAFE,"));
+        assert!(rows[2].contains(",sent,-0.500000000,0.000000000,SNT,Expenses:SAFE:Transfers,"));
+        assert!(rows[3].contains(",storage_payment,-0.000000100,-0.000000015,"));
+        Ok(())
+    }
+
+    #[test]
+    fn ledger_formats_should_balance_with_the_mapped_accounts() -> Result<()> {
+        let mapping = AccountMapping::with_overrides(&[
+            "storage=Expenses:Hosting".to_string(),
+            "commodity=SAFE".to_string(),
+        ])?;
+
+        let beancount = export(HistoryFormat::Beancount, &mapping)?;
+        assert!(beancount.contains("2024-05-01 open Expenses:Hosting SAFE"));
+        assert!(beancount.contains("2024-05-03 * \"Storage payment\""));
+        assert!(beancount.contains("  Assets:SAFE:Wallet  -0.000000115 SAFE"));
+        assert!(beancount.contains("  Expenses:Hosting  0.000000100 SAFE"));
+        assert!(beancount.contains("  Expenses:SAFE:Royalties  0.000000015 SAFE"));
+
+        let ledger = export(HistoryFormat::Ledger, &mapping)?;
+        assert!(ledger.starts_with("2024/05/01 * Received\n"));
+        assert!(ledger.contains("    Income:SAFE  -2.000000000 SAFE"));
+
+        assert!(AccountMapping::with_overrides(&["bank=Assets:Bank".to_string()]).is_err());
+        assert!(AccountMapping::with_overrides(&["wallet".to_string()]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ofx_should_list_the_fees_separately() -> Result<()> {
+        let ofx = export(HistoryFormat::Ofx, &AccountMapping::default())?;
+        assert_eq!(ofx.matches("<STMTTRN>").count(), 4);
+        assert!(ofx.contains("<TRNTYPE>FEE</TRNTYPE>"));
+        assert!(ofx.contains("<TRNAMT>-0.500000000</TRNAMT>"));
+        assert!(ofx.contains("<DTSTART>20240501120000</DTSTART>"));
+        assert!(ofx.contains("<BALAMT>1.499999885</BALAMT>"));
+        Ok(())
+    }
+}
@@ -12,11 +12,16 @@ extern crate tracing;
 mod subcommands;
 
 use subcommands::{
-    files::files_cmds,
+    config::config_cmds,
+    files::{files_cmds, files_cmds_without_client, FilesCmds, WatchArgs},
     folders::folders_cmds,
+    names::names_cmds,
+    network::{bootstrap_source, network_cmds, NetworkStatus},
+    pin::{pin_cmds, pin_cmds_without_client, PinCmds},
     register::register_cmds,
     wallet::{
         hot_wallet::{wallet_cmds, wallet_cmds_without_client, WalletCmds},
+        list_wallets, wallet_root_dir,
         wo_wallet::{wo_wallet_cmds, wo_wallet_cmds_without_client, WatchOnlyWalletCmds},
     },
     Opt, SubCmd,
@@ -24,21 +29,37 @@ use subcommands::{
 
 use bls::SecretKey;
 use clap::Parser;
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Report, Result};
 use indicatif::ProgressBar;
+use sn_cli::{check_network_marker, CliConfig, ErrorReport, OutputFormat};
 use sn_client::transfers::bls_secret_from_hex;
-use sn_client::{Client, ClientEvent, ClientEventsBroadcaster, ClientEventsReceiver};
+use sn_client::{
+    Client, ClientEvent, ClientEventsBroadcaster, ClientEventsReceiver, ClientStore, HedgeCfg,
+};
 #[cfg(feature = "metrics")]
 use sn_logging::{metrics::init_metrics, Level, LogBuilder, LogFormat};
-use std::{io, path::PathBuf, time::Duration};
+use std::{io, path::PathBuf, process::ExitCode, time::Duration};
 use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
 
 const CLIENT_KEY: &str = "clientkey";
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    color_eyre::install()?;
+async fn main() -> ExitCode {
     let opt = Opt::parse();
+    // Until the config file is read, only `--json` tells how a failure has to be reported
+    let mut output_format = if opt.json {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    };
+    match run(opt, &mut output_format).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => report_error(&err, output_format),
+    }
+}
+
+async fn run(mut opt: Opt, output_format: &mut OutputFormat) -> Result<()> {
+    color_eyre::install()?;
     let logging_targets = vec![
         // TODO: Reset to nice and clean defaults once we have a better idea of what we want
         ("sn_networking".to_string(), Level::INFO),
@@ -55,6 +76,14 @@ async fn main() -> Result<()> {
     let mut log_builder = LogBuilder::new(logging_targets);
     log_builder.output_dest(opt.log_output_dest);
     log_builder.format(opt.log_format.unwrap_or(LogFormat::Default));
+    if let Some(log_level) = &opt.log_level {
+        log_builder.log_levels(sn_logging::parse_log_levels(log_level)?);
+    }
+    #[cfg(feature = "otlp")]
+    log_builder.otlp_config(sn_logging::OtlpConfig {
+        endpoint: opt.otlp_endpoint.clone(),
+        service_name: opt.otlp_service_name.clone(),
+    });
     let _log_handles = log_builder.initialize()?;
 
     #[cfg(feature = "metrics")]
@@ -72,21 +101,76 @@ async fn main() -> Result<()> {
         sn_build_info::git_info()
     );
 
-    let client_data_dir_path = get_client_data_dir_path()?;
+    let config_path = CliConfig::default_path()?;
+    let config = CliConfig::load_from(&config_path)?;
+    if !opt.json {
+        *output_format = config.output_format.unwrap_or_default();
+    }
+    let output_format = *output_format;
+
+    // The network given on the command line takes precedence over the one in the config file.
+    // Its keys and version have to be set before anything reads them.
+    let profile = opt.network.or(config.network_profile).unwrap_or_default();
+    let profile_settings = config.profile_settings(profile);
+    profile_settings.apply()?;
+    info!("Running against the {profile} network");
+
+    let client_data_dir_path = profile.data_dir(&get_client_data_dir_path()?);
+    // The wallet given on the command line takes precedence over the one in the config file
+    let wallet_name = match &opt.cmd {
+        SubCmd::Wallet(WalletCmds::Create {
+            name: Some(name), ..
+        }) => Some(name.clone()),
+        _ => opt.wallet.clone().or(config.default_wallet.clone()),
+    };
+    let root_dir = wallet_root_dir(&client_data_dir_path, wallet_name.as_deref())?;
     // Perform actions that do not require us connecting to the network and return early
+    if let SubCmd::Config(cmds) = &opt.cmd {
+        config_cmds(cmds, &config_path, output_format)?;
+        return Ok(());
+    }
+    check_network_marker(&client_data_dir_path, profile)?;
+
+    if let SubCmd::Wallet(WalletCmds::List) = &opt.cmd {
+        list_wallets(&client_data_dir_path, wallet_name.as_deref(), output_format)?;
+        return Ok(());
+    }
+
     if let SubCmd::Wallet(cmds) = &opt.cmd {
         if let WalletCmds::Address { .. }
         | WalletCmds::Balance { .. }
         | WalletCmds::Create { .. }
         | WalletCmds::Sign { .. }
+        | WalletCmds::SignMessage { .. }
+        | WalletCmds::VerifyMessage { .. }
         | WalletCmds::Status { .. }
-        | WalletCmds::Encrypt { .. } = cmds
+        | WalletCmds::History { .. }
+        | WalletCmds::ExportHistory { .. }
+        | WalletCmds::Encrypt { .. }
+        | WalletCmds::ChangePassword
+        | WalletCmds::Decrypt
+        | WalletCmds::Mnemonic
+        | WalletCmds::FileFormat { .. }
+        | WalletCmds::SpendApproval { .. }
+        | WalletCmds::AddressBook(_)
+        | WalletCmds::Approve { .. }
+        | WalletCmds::Sweep { online: false, .. } = cmds
         {
-            wallet_cmds_without_client(cmds, &client_data_dir_path).await?;
+            wallet_cmds_without_client(cmds, &root_dir).await?;
             return Ok(());
         }
     }
 
+    if let SubCmd::Files(cmds @ FilesCmds::Watch(WatchArgs { dry_run: true, .. })) = &opt.cmd {
+        files_cmds_without_client(cmds, &root_dir).await?;
+        return Ok(());
+    }
+
+    if let SubCmd::Pin(cmds @ (PinCmds::List | PinCmds::Remove { .. })) = &opt.cmd {
+        pin_cmds_without_client(cmds, &root_dir)?;
+        return Ok(());
+    }
+
     if let SubCmd::WatchOnlyWallet(cmds) = &opt.cmd {
         if let WatchOnlyWalletCmds::Addresses
         | WatchOnlyWalletCmds::Balance { .. }
@@ -94,20 +178,70 @@ async fn main() -> Result<()> {
         | WatchOnlyWalletCmds::Create { .. }
         | WatchOnlyWalletCmds::Transaction { .. } = cmds
         {
-            wo_wallet_cmds_without_client(cmds, &client_data_dir_path).await?;
+            wo_wallet_cmds_without_client(cmds, &root_dir).await?;
             return Ok(());
         }
     }
 
+    #[cfg(feature = "network-contacts")]
+    if opt.peers.network_contacts_url.is_none() {
+        if let Some(url) = &profile_settings.network_contacts_url {
+            opt.peers.network_contacts_url = Some(url.parse().map_err(|err| {
+                eyre!("Invalid network_contacts_url {url:?} of the {profile} profile: {err}")
+            })?);
+        }
+    }
+
+    // Reported by `safe network status`, it has to be checked before merging in the config file
+    let bootstrap_source = bootstrap_source(&opt.peers, &config, profile);
+
+    // Values given on the command line take precedence over the ones in the config file
+    opt.cmd.apply_config(&config);
+    if opt.peers.peers.is_empty() && !opt.peers.first {
+        opt.peers.peers = config.network_contacts(profile)?;
+    }
+    let connection_timeout = opt
+        .connection_timeout
+        .or(config.connection_timeout.map(Duration::from_secs));
+
     println!("Instantiating a SAFE client...");
     let secret_key = get_client_secret_key(&client_data_dir_path)?;
 
-    let bootstrap_peers = opt.peers.get_peers().await?;
+    // the peers of the last session are bootstrapped from as well, in case the contacts are stale
+    let client_store = ClientStore::open(&client_data_dir_path)?;
+    let cached_peers = client_store.peers()?;
+    let peers = if profile.fetches_network_contacts() {
+        opt.peers.get_peers().await
+    } else {
+        opt.peers.get_peers_exclude_network_contacts().await
+    };
+    let bootstrap_peers = match peers {
+        // an empty list is returned if the `local-discovery` flag is provided
+        Ok(peers) if peers.is_empty() => peers,
+        Ok(mut peers) => {
+            for peer in cached_peers {
+                if !peers.contains(&peer) {
+                    peers.push(peer);
+                }
+            }
+            peers
+        }
+        Err(err) if !cached_peers.is_empty() => {
+            warn!("Could not get the network contacts, using the cached peers: {err:?}");
+            println!(
+                "Could not get the network contacts, using the {} peers of the last session",
+                cached_peers.len()
+            );
+            cached_peers
+        }
+        Err(err) => return Err(err.into()),
+    };
 
     println!(
         "Connecting to the network with {} peers",
         bootstrap_peers.len(),
     );
+    let mut network_status = NetworkStatus::new(bootstrap_source, bootstrap_peers.len());
 
     let bootstrap_peers = if bootstrap_peers.is_empty() {
         // empty vec is returned if `local-discovery` flag is provided
@@ -118,25 +252,33 @@ async fn main() -> Result<()> {
 
     // get the broadcaster as we want to have our own progress bar.
     let broadcaster = ClientEventsBroadcaster::default();
+    let mut network_events = broadcaster.subscribe();
     let (progress_bar, progress_bar_handler) =
         spawn_connection_progress_bar(broadcaster.subscribe());
 
     let result = Client::new(
         secret_key,
         bootstrap_peers,
-        opt.connection_timeout,
+        connection_timeout,
         Some(broadcaster),
     )
     .await;
-    let client = match result {
+    let mut client = match result {
         Ok(client) => client,
         Err(err) => {
             // clean up progress bar
             progress_bar.finish_with_message("Could not connect to the network");
+            if let SubCmd::Network(_) = opt.cmd {
+                network_status.drain_events(&mut network_events);
+                network_status.print(output_format)?;
+            }
             return Err(err.into());
         }
     };
     progress_bar_handler.await?;
+    if opt.hedge {
+        client.set_chunk_hedging(Some(HedgeCfg::default()));
+    }
 
     // default to verifying storage
     let should_verify_store = !opt.no_verify;
@@ -146,24 +288,60 @@ async fn main() -> Result<()> {
     let cmd_str = format!("{:?}", opt.cmd);
     let result = match opt.cmd {
         SubCmd::Wallet(cmds) => {
-            wallet_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
+            wallet_cmds(cmds, &client, &root_dir, should_verify_store, output_format).await
         }
         SubCmd::WatchOnlyWallet(cmds) => {
-            wo_wallet_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
-        }
-        SubCmd::Files(cmds) => {
-            files_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
-        }
-        SubCmd::Folders(cmds) => {
-            folders_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
+            wo_wallet_cmds(cmds, &client, &root_dir, should_verify_store).await
         }
+        SubCmd::Files(cmds) => files_cmds(cmds, &client, &root_dir, should_verify_store).await,
+        SubCmd::Folders(cmds) => folders_cmds(cmds, &client, &root_dir, should_verify_store).await,
         SubCmd::Register(cmds) => {
-            register_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
+            register_cmds(cmds, &client, &root_dir, should_verify_store).await
         }
+        SubCmd::Network(cmds) => {
+            network_cmds(
+                cmds,
+                &client,
+                network_status,
+                &mut network_events,
+                output_format,
+            )
+            .await
+        }
+        SubCmd::Names(cmds) => names_cmds(cmds, &client, &root_dir, should_verify_store).await,
+        SubCmd::Pin(cmds) => pin_cmds(cmds, &client, &root_dir).await,
+        #[cfg(feature = "gateway")]
+        SubCmd::Gateway(args) => subcommands::gateway::serve(args, &client, &root_dir).await,
+        SubCmd::Config(cmds) => Err(eyre!(
+            "{cmds:?} has to be processed before connecting to the network"
+        )),
     };
-    println!("Completed with {result:?} of execute {cmd_str:?}");
+    debug!("Completed with {result:?} of execute {cmd_str:?}");
+    if let Err(err) = client_store.store_peers_of(&client).await {
+        warn!("Could not cache the peers of the session: {err:?}");
+    }
+    if output_format == OutputFormat::Text {
+        println!("Completed with {result:?} of execute {cmd_str:?}");
+    }
+
+    result
+}
 
-    Ok(())
+/// Prints a failed command's error, as an `{"error": ..}` object in `--json` mode, and returns the
+/// exit code of its kind.
+fn report_error(err: &Report, output_format: OutputFormat) -> ExitCode {
+    let report = ErrorReport::from(err);
+    match output_format {
+        OutputFormat::Json => {
+            let json = serde_json::json!({ "error": report });
+            match serde_json::to_string_pretty(&json) {
+                Ok(json) => println!("{json}"),
+                Err(_) => eprintln!("Error: {err:?}"),
+            }
+        }
+        OutputFormat::Text => eprintln!("Error: {err:?}"),
+    }
+    ExitCode::from(report.exit_code)
 }
 
 /// Helper to subscribe to the client events broadcaster and spin up a progress bar that terminates when the
@@ -255,8 +433,10 @@ fn get_stdin_password_response(prompt: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::subcommands::wallet::hot_wallet::{wallet_cmds_without_client, WalletCmds};
-    use crate::subcommands::wallet::WalletApiHelper;
+    use crate::subcommands::wallet::hot_wallet::{
+        wallet_cmds_without_client, AddressBookCmds, WalletCmds,
+    };
+    use crate::subcommands::wallet::{wallet_root_dir, WalletApiHelper, DEFAULT_WALLET_NAME};
     use bls::SecretKey;
     use color_eyre::Result;
     use sn_client::acc_packet::{load_or_create_mnemonic, secret_key_from_mnemonic};
@@ -302,9 +482,11 @@ mod tests {
         let root_dir = tmp_dir.path().to_path_buf();
 
         let cmds = WalletCmds::Create {
+            name: None,
             no_replace: false,
             no_password: true,
             key: None,
+            mnemonic: None,
             derivation_passphrase: None,
             password: None,
         };
@@ -327,9 +509,11 @@ mod tests {
         let secret_key_hex = secret_key.to_hex();
 
         let cmds = WalletCmds::Create {
+            name: None,
             no_replace: false,
             no_password: true,
             key: Some(secret_key_hex),
+            mnemonic: None,
             derivation_passphrase: None,
             password: None,
         };
@@ -349,4 +533,93 @@ mod tests {
             panic!("Did not expect a watch only wallet");
         }
     }
+
+    #[tokio::test]
+    async fn test_wallet_create_command_with_mnemonic() -> Result<()> {
+        let mnemonic = sn_client::acc_packet::user_secret::random_mnemonic(12)?;
+        let mut addresses = vec![];
+        for _ in 0..2 {
+            let tmp_dir = tempfile::tempdir()?;
+            let cmds = WalletCmds::Create {
+                name: None,
+                no_replace: false,
+                no_password: true,
+                key: None,
+                mnemonic: Some(mnemonic.to_string()),
+                derivation_passphrase: None,
+                password: None,
+            };
+            wallet_cmds_without_client(&cmds, tmp_dir.path()).await?;
+            assert_eq!(
+                sn_client::acc_packet::wallet_mnemonic(tmp_dir.path())?,
+                mnemonic
+            );
+            addresses.push(HotWallet::load_from(tmp_dir.path())?.address());
+        }
+        // the wallet restored from the mnemonic is the same
+        assert_eq!(addresses[0], addresses[1]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn named_wallets_should_be_kept_apart() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let client_data_dir = tmp_dir.path();
+
+        assert_eq!(wallet_root_dir(client_data_dir, None)?, client_data_dir);
+        assert_eq!(
+            wallet_root_dir(client_data_dir, Some(DEFAULT_WALLET_NAME))?,
+            client_data_dir
+        );
+        assert!(wallet_root_dir(client_data_dir, Some("../escape")).is_err());
+        assert!(wallet_root_dir(client_data_dir, Some("")).is_err());
+
+        let default_wallet = create_wallet(client_data_dir, None)?;
+        let savings_dir = wallet_root_dir(client_data_dir, Some("savings"))?;
+        let cmds = WalletCmds::Create {
+            name: Some("savings".to_string()),
+            no_replace: false,
+            no_password: true,
+            key: Some(SecretKey::random().to_hex()),
+            mnemonic: None,
+            derivation_passphrase: None,
+            password: None,
+        };
+        wallet_cmds_without_client(&cmds, &savings_dir).await?;
+
+        let savings_wallet = HotWallet::load_from(&savings_dir)?;
+        assert_ne!(savings_wallet.address(), default_wallet.address());
+        assert_eq!(
+            HotWallet::load_from(client_data_dir)?.address(),
+            default_wallet.address()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn address_book_labels_should_be_added_and_removed() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let root_dir = tmp_dir.path();
+        let wallet = create_wallet(root_dir, None)?;
+        let recipient = SecretKey::random().public_key();
+
+        let cmds = WalletCmds::AddressBook(AddressBookCmds::Add {
+            label: "alice".to_string(),
+            address: recipient.to_hex(),
+        });
+        wallet_cmds_without_client(&cmds, root_dir).await?;
+        let cmds = WalletCmds::AddressBook(AddressBookCmds::List);
+        wallet_cmds_without_client(&cmds, root_dir).await?;
+        assert_eq!(
+            wallet.address_book()?.get("alice").map(|pk| pk.to_hex()),
+            Some(recipient.to_hex())
+        );
+
+        let cmds = WalletCmds::AddressBook(AddressBookCmds::Remove {
+            label: "alice".to_string(),
+        });
+        wallet_cmds_without_client(&cmds, root_dir).await?;
+        assert!(wallet.address_book()?.is_empty());
+        Ok(())
+    }
 }
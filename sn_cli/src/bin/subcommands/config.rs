@@ -0,0 +1,76 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use clap::Parser;
+use color_eyre::Result;
+use sn_cli::{CliConfig, OutputFormat, CONFIG_KEYS};
+use std::path::Path;
+
+// Please do not remove the blank lines in these doc comments.
+// They are used for inserting line breaks when the help menu is rendered in the UI.
+#[derive(Parser, Debug)]
+pub enum ConfigCmds {
+    /// Set a value in the config file.
+    ///
+    /// Valid keys are 'network_profile' ('mainnet', 'testnet' or 'local'), 'network_contacts'
+    /// (comma-separated, of the mainnet), 'default_wallet', 'batch_size',
+    /// 'connection_timeout' (in seconds), 'output_format' ('text' or 'json') and
+    /// 'password_cache_secs' (how long an encrypted wallet's password is kept in the OS keyring).
+    ///
+    /// An empty value removes the key from the file. The settings of the other networks are edited
+    /// in the `[profiles.<name>]` tables of the file.
+    Set {
+        /// The key to set.
+        #[clap(name = "key")]
+        key: String,
+        /// The value to store.
+        #[clap(name = "value")]
+        value: String,
+    },
+    /// Print a value from the config file.
+    ///
+    /// If no key is provided, all the keys are printed.
+    Get {
+        /// The key to read.
+        #[clap(name = "key")]
+        key: Option<String>,
+    },
+}
+
+pub(crate) fn config_cmds(
+    cmds: &ConfigCmds,
+    config_path: &Path,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let mut config = CliConfig::load_from(config_path)?;
+    match cmds {
+        ConfigCmds::Set { key, value } => {
+            config.set(key, value)?;
+            config.save_to(config_path)?;
+            println!("Config updated at {}", config_path.display());
+        }
+        ConfigCmds::Get { key: Some(key) } => match (config.get(key)?, output_format) {
+            (value, OutputFormat::Json) => {
+                println!("{}", serde_json::json!({ key.as_str(): value }));
+            }
+            (Some(value), OutputFormat::Text) => println!("{value}"),
+            (None, OutputFormat::Text) => println!("{key} is not set"),
+        },
+        ConfigCmds::Get { key: None } => match output_format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&config)?),
+            OutputFormat::Text => {
+                println!("Config file: {}", config_path.display());
+                for key in CONFIG_KEYS {
+                    let value = config.get(key)?.unwrap_or_else(|| "<not set>".to_string());
+                    println!("{key} = {value}");
+                }
+            }
+        },
+    }
+    Ok(())
+}
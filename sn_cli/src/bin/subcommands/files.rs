@@ -6,23 +6,32 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use clap::Parser;
+use super::wallet::helpers::fiat_rate;
+use chrono::{DateTime, Local};
+use clap::{Args, Parser};
 use color_eyre::{
     eyre::{bail, eyre},
     Help, Result,
 };
 use sn_cli::{
-    download_file, download_files, ChunkManager, Estimator, FilesUploader, UploadedFile,
-    UPLOADED_FILES,
+    download_file, download_files, download_folder, ChunkManager, DownloadCfg, Estimator,
+    FileState, FilesUploader, FolderWatcher, IncompleteUpload, LedgerEntry, OverwritePolicy,
+    UploadSession, UploadedFile, WatchLedger, WatchOutcome, UPLOADED_FILES, WATCH_LEDGER,
 };
 use sn_client::{
-    protocol::storage::{Chunk, ChunkAddress, RetryStrategy},
-    UploadCfg,
+    protocol::{
+        messages::SignedPaymentReceipt,
+        storage::{Chunk, ChunkAddress, PaymentStrategy, RegisterAddress, RetryStrategy},
+        NetworkAddress,
+    },
+    ErasureCoding, UploadCfg,
 };
 use sn_client::{Client, FilesApi, BATCH_SIZE};
 use std::{
+    collections::BTreeMap,
     ffi::OsString,
     path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
 use walkdir::WalkDir;
 use xor_name::XorName;
@@ -36,17 +45,28 @@ pub enum FilesCmds {
         /// Should the file be made accessible to all. (This is irreversible)
         #[clap(long, name = "make_public", default_value = "false", short = 'p')]
         make_data_public: bool,
+        /// Also print the estimated amounts in a fiat currency, e.g. 'usd'.
+        #[clap(long, value_name = "CURRENCY")]
+        fiat: Option<String>,
     },
     Upload {
         /// The location of the file(s) to upload.
         ///
         /// Can be a file or a directory.
-        #[clap(name = "path", value_name = "PATH")]
-        file_path: PathBuf,
+        #[clap(name = "path", value_name = "PATH", required_unless_present = "resume")]
+        file_path: Option<PathBuf>,
+        /// Resume an interrupted upload, using the id of its session.
+        ///
+        /// The chunks that were already uploaded are skipped, and the ones that were paid for are not
+        /// paid for again. Use 'files sessions' to list the pending sessions.
+        #[clap(long, value_name = "SESSION_ID", conflicts_with_all = ["path", "make_public", "erasure_coding"])]
+        resume: Option<String>,
         /// The batch_size to split chunks into parallel handling batches
         /// during payment and upload processing.
-        #[clap(long, default_value_t = BATCH_SIZE, short='b')]
-        batch_size: usize,
+        ///
+        /// Defaults to the `batch_size` set in the config file, or 16 if it isn't set.
+        #[clap(long, short = 'b')]
+        batch_size: Option<usize>,
         /// Should the file be made accessible to all. (This is irreversible)
         #[clap(long, name = "make_public", default_value = "false", short = 'p')]
         make_data_public: bool,
@@ -56,7 +76,33 @@ pub enum FilesCmds {
         /// to 'persistent' (most effort).
         #[clap(long, default_value_t = RetryStrategy::Quick, short = 'r', help = "Sets the retry strategy on upload failure. Options: 'quick' for minimal effort, 'balanced' for moderate effort, or 'persistent' for maximum effort.")]
         retry_strategy: RetryStrategy,
+        /// Erasure code the chunks of the files, given as DATA:PARITY, e.g. '8:2'.
+        ///
+        /// Every DATA consecutive chunks of a file get PARITY parity chunks, and any DATA chunks of
+        /// such a group are enough to recover the others when downloading. The parity chunks are
+        /// paid for as well, so '8:2' adds a quarter to the storage cost.
+        #[clap(long, value_name = "DATA:PARITY", value_parser = parse_erasure_coding)]
+        erasure_coding: Option<ErasureCoding>,
+        /// Collect the receipts signed by the paid nodes and write them to this file.
+        ///
+        /// Each receipt is the proof that a node accepted the payment for a chunk.
+        #[clap(long, value_name = "PATH")]
+        receipts: Option<PathBuf>,
+        /// Set the strategy to choose which nodes to pay for each chunk, when they quote different costs.
+        ///
+        /// Choose 'cheapest' to pay the cheapest node of the close group majority, 'median' to pay the
+        /// node quoting the median cost, or 'all' to pay every one of them. Registers are always paid
+        /// to the cheapest node.
+        #[clap(long, default_value_t = PaymentStrategy::CheapestMajority)]
+        payment_strategy: PaymentStrategy,
     },
+    /// List the uploads that have not completed yet and can be resumed.
+    Sessions,
+    /// Watch a directory and upload its new and changed files, until interrupted.
+    ///
+    /// The uploads are paid from the loaded wallet and recorded in the watch ledger of the client
+    /// data dir, so that a restarted watch only uploads what has changed in the meantime.
+    Watch(WatchArgs),
     Download {
         /// The name to apply to the downloaded file.
         ///
@@ -72,13 +118,35 @@ pub enum FilesCmds {
         /// If neither are, all the files uploaded by the current user will be downloaded again.
         #[clap(name = "address")]
         file_addr: Option<String>,
+        /// The hex address of a Folder to download, along with all its files and subfolders.
+        ///
+        /// Only Folders that were synced with public data can be downloaded this way.
+        #[clap(long, value_name = "ADDRESS", conflicts_with_all = ["name", "address"])]
+        folder: Option<String>,
+        /// Where to download to.
+        ///
+        /// When downloading a single file, this is the path of the file, unless an existing directory
+        /// is given. Otherwise it is the directory to download the files into.
+        #[clap(long, short = 'o', value_name = "PATH")]
+        out: Option<PathBuf>,
+        /// Overwrite the files that already exist locally.
+        #[clap(long, conflicts_with = "no_clobber")]
+        force: bool,
+        /// Skip the files that already exist locally instead of failing.
+        #[clap(long)]
+        no_clobber: bool,
+        /// Re-chunk each downloaded file and check it matches the data map it was downloaded from.
+        #[clap(long)]
+        verify: bool,
         /// Flagging whether to show the holders of the uploaded chunks.
         /// Default to be not showing.
         #[clap(long, name = "show_holders", default_value = "false")]
         show_holders: bool,
         /// The batch_size for parallel downloading
-        #[clap(long, default_value_t = BATCH_SIZE , short='b')]
-        batch_size: usize,
+        ///
+        /// Defaults to the `batch_size` set in the config file, or 16 if it isn't set.
+        #[clap(long, short = 'b')]
+        batch_size: Option<usize>,
         /// Set the strategy to use on downloads failure.
         ///
         /// Choose a retry strategy based on effort level, from 'quick' (least effort), through 'balanced',
@@ -88,6 +156,49 @@ pub enum FilesCmds {
     },
 }
 
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// The directory to watch.
+    #[clap(name = "path", value_name = "PATH")]
+    pub path: PathBuf,
+    /// How often to look for changes, in seconds.
+    #[clap(long, default_value_t = 10)]
+    pub interval: u64,
+    /// How long a file has to stay unchanged before it is uploaded, in seconds.
+    #[clap(long, default_value_t = 5)]
+    pub debounce: u64,
+    /// A glob of the files to skip, relative to the watched directory, e.g. '*.tmp' or '.git/**'.
+    ///
+    /// This argument can be provided multiple times.
+    #[clap(long, value_name = "PATTERN")]
+    pub ignore: Vec<String>,
+    /// Only print the files that would be uploaded, without connecting to the network.
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Should the files be made accessible to all. (This is irreversible)
+    #[clap(long, name = "make_public", default_value = "false", short = 'p')]
+    pub make_data_public: bool,
+    /// The batch_size to split chunks into parallel handling batches
+    /// during payment and upload processing.
+    ///
+    /// Defaults to the `batch_size` set in the config file, or 16 if it isn't set.
+    #[clap(long, short = 'b')]
+    pub batch_size: Option<usize>,
+    /// Set the strategy to use on chunk upload failure.
+    ///
+    /// Choose a retry strategy based on effort level, from 'quick' (least effort), through 'balanced',
+    /// to 'persistent' (most effort).
+    #[clap(long, default_value_t = RetryStrategy::Quick, short = 'r')]
+    pub retry_strategy: RetryStrategy,
+}
+
+pub(crate) async fn files_cmds_without_client(cmds: &FilesCmds, root_dir: &Path) -> Result<()> {
+    match cmds {
+        FilesCmds::Watch(args) if args.dry_run => watch_folder(args, None, root_dir, false).await,
+        cmd => Err(eyre!("{cmd:?} requires us to be connected to the Network")),
+    }
+}
+
 pub(crate) async fn files_cmds(
     cmds: FilesCmds,
     client: &Client,
@@ -98,19 +209,38 @@ pub(crate) async fn files_cmds(
         FilesCmds::Estimate {
             path,
             make_data_public,
+            fiat,
         } => {
+            let rate = fiat_rate(fiat.as_deref()).await?;
             let files_api = FilesApi::build(client.clone(), root_dir.to_path_buf())?;
             let chunk_manager = ChunkManager::new(root_dir);
             Estimator::new(chunk_manager, files_api)
-                .estimate_cost(path, make_data_public, root_dir)
+                .estimate_cost(path, make_data_public, root_dir, rate.as_ref())
                 .await?
         }
         FilesCmds::Upload {
             file_path,
+            resume,
             batch_size,
             retry_strategy,
             make_data_public,
+            erasure_coding,
+            receipts,
+            payment_strategy,
         } => {
+            let session = match (resume, file_path) {
+                (Some(session_id), _) => {
+                    let session = UploadSession::read(root_dir, &session_id)?;
+                    println!("Resuming upload session {session_id} of {:?}", session.path);
+                    session
+                }
+                (None, Some(file_path)) => UploadSession {
+                    erasure_coding,
+                    ..UploadSession::new(&file_path, make_data_public)
+                },
+                (None, None) => bail!("Either a path or a session id to resume is required"),
+            };
+            let file_path = session.path.clone();
             let files_count = count_files_in_path_recursively(&file_path);
 
             if files_count == 0 {
@@ -124,25 +254,108 @@ pub(crate) async fn files_cmds(
                 }
             }
             let upload_cfg = UploadCfg {
-                batch_size,
+                batch_size: batch_size.unwrap_or(BATCH_SIZE),
                 verify_store,
                 retry_strategy,
+                reuse_existing_payments: true,
+                collect_payment_receipts: receipts.is_some(),
+                payment_strategy,
                 ..Default::default()
             };
+            session.write(root_dir)?;
             let files_uploader = FilesUploader::new(client.clone(), root_dir.to_path_buf())
-                .set_make_data_public(make_data_public)
+                .set_make_data_public(session.make_data_public)
+                .set_erasure_coding(session.erasure_coding)
                 .set_upload_cfg(upload_cfg)
                 .insert_path(&file_path);
 
-            let _summary = files_uploader.start_upload().await?;
+            let result = files_uploader.start_upload().await;
+            if let (Some(path), Ok(summary)) = (receipts, &result) {
+                write_payment_receipts(&path, &summary.upload_summary.payment_receipts)?;
+            }
+            match result {
+                Ok(summary) if summary.incomplete_files.is_empty() => session.remove(root_dir)?,
+                Ok(summary) => {
+                    print_resume_hint(&session);
+                    return Err(IncompleteUpload {
+                        incomplete_files: summary.incomplete_files.len(),
+                    }
+                    .into());
+                }
+                Err(err) => {
+                    print_resume_hint(&session);
+                    return Err(err);
+                }
+            }
+        }
+        FilesCmds::Watch(args) => {
+            let client = (!args.dry_run).then_some(client);
+            watch_folder(&args, client, root_dir, verify_store).await?
+        }
+        FilesCmds::Sessions => {
+            let sessions = UploadSession::list(root_dir)?;
+            if sessions.is_empty() {
+                println!("There are no pending upload sessions.");
+            }
+            for session in sessions {
+                let started_at: DateTime<Local> = session.started_at.into();
+                println!(
+                    "{}  started {}  {}{:?}",
+                    session.id,
+                    started_at.format("%Y-%m-%d %H:%M:%S"),
+                    if session.make_data_public {
+                        "(public) "
+                    } else {
+                        ""
+                    },
+                    session.path
+                );
+            }
         }
         FilesCmds::Download {
             file_name,
             file_addr,
+            folder,
+            out,
+            force,
+            no_clobber,
+            verify,
             show_holders,
             batch_size,
             retry_strategy,
         } => {
+            let overwrite_policy = if force {
+                OverwritePolicy::Overwrite
+            } else if no_clobber {
+                OverwritePolicy::Skip
+            } else {
+                OverwritePolicy::Fail
+            };
+            let download_cfg = DownloadCfg {
+                batch_size: batch_size.unwrap_or(BATCH_SIZE),
+                show_holders,
+                retry_strategy,
+                overwrite_policy,
+                verify,
+            };
+
+            if let Some(folder) = folder {
+                let folder_addr = RegisterAddress::from_hex(&folder)
+                    .map_err(|err| eyre!("Invalid Folder address {folder:?}: {err}"))?;
+                let download_path = match out {
+                    Some(out) => out,
+                    None => std::env::current_dir()?.join(format!("folder_{}", &folder[..6])),
+                };
+                return download_folder(
+                    client,
+                    root_dir,
+                    folder_addr,
+                    &download_path,
+                    download_cfg,
+                )
+                .await;
+            }
+
             if (file_name.is_some() && file_addr.is_none())
                 || (file_addr.is_some() && file_name.is_none())
             {
@@ -167,11 +380,20 @@ pub(crate) async fn files_cmds(
                 //
                 // a directory relative to root of the filesystem (absolute filename)
                 // eg safe files download /home/me/mydir/myfile.txt ADDRESS
-                let file_name_path = Path::new(&file_name);
+                //
+                // or anywhere else when --out is used
+                // eg safe files download myfile.txt ADDRESS --out /home/me/mydir/
+                let file_name_path = match &out {
+                    Some(out) if out.is_dir() => out.join(&file_name),
+                    Some(out) => out.clone(),
+                    None => PathBuf::from(&file_name),
+                };
                 if file_name_path.is_dir() {
-                    return Err(eyre!("Cannot download file to path: {:?}", file_name));
+                    return Err(eyre!("Cannot download file to path: {:?}", file_name_path));
                 }
-                let file_name_dir = file_name_path.parent();
+                let file_name_dir = file_name_path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty());
                 if file_name_dir.is_none() {
                     // just a filename, use the current_dir
                     download_dir = std::env::current_dir().unwrap_or(root_dir.to_path_buf());
@@ -181,17 +403,17 @@ pub(crate) async fn files_cmds(
                     if let Some(relative_dir) = file_name_dir {
                         let current_dir = std::env::current_dir().unwrap_or(root_dir.to_path_buf());
                         download_dir = current_dir.join(relative_dir);
-                        if !download_dir.exists() {
-                            return Err(eyre!("Directory does not exist: {:?}", download_dir));
-                        }
-                        if let Some(path_file_name) = file_name_path.file_name() {
-                            download_file_name = Some(OsString::from(path_file_name));
-                        }
                     }
                 } else {
                     // absolute dir
                     download_dir = file_name_dir.unwrap_or(root_dir).to_path_buf();
                 }
+                if !download_dir.exists() {
+                    return Err(eyre!("Directory does not exist: {:?}", download_dir));
+                }
+                if let Some(path_file_name) = file_name_path.file_name() {
+                    download_file_name = Some(OsString::from(path_file_name));
+                }
             }
             let files_api: FilesApi = FilesApi::new(client.clone(), download_dir.clone());
 
@@ -226,26 +448,151 @@ pub(crate) async fn files_cmds(
                         xor_name_provided,
                         (download_file_name, local_data_map),
                         &download_dir,
-                        show_holders,
-                        batch_size,
-                        retry_strategy,
+                        download_cfg,
                     )
-                    .await
+                    .await?
                 }
                 _ => {
                     println!("Attempting to download all files uploaded by the current user...");
-                    download_files(
-                        &files_api,
-                        root_dir,
-                        show_holders,
-                        batch_size,
-                        retry_strategy,
-                    )
-                    .await?
+                    download_files(&files_api, root_dir, out.as_deref(), download_cfg).await?
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Uploads the new and changed files of the watched directory every `interval`, forever.
+/// Without a client, the files are only printed.
+async fn watch_folder(
+    args: &WatchArgs,
+    client: Option<&Client>,
+    root_dir: &Path,
+    verify_store: bool,
+) -> Result<()> {
+    let mut watcher =
+        FolderWatcher::new(&args.path, &args.ignore, Duration::from_secs(args.debounce))?;
+    let mut ledger = WatchLedger::load(root_dir)?;
+    let chunk_manager = ChunkManager::new(root_dir);
+    // The state of each file when its upload was last attempted. A file that failed to upload is
+    // retried with the chunks already made, unless it has changed in the meantime.
+    let mut attempted: BTreeMap<PathBuf, FileState> = BTreeMap::new();
+
+    println!(
+        "Watching {:?} for changes{}. Press Ctrl+C to stop.",
+        watcher.dir(),
+        if client.is_none() { " (dry run)" } else { "" }
+    );
+    if client.is_some() {
+        println!(
+            "The uploads are recorded in {:?}",
+            root_dir.join(WATCH_LEDGER)
+        );
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(args.interval.max(1)));
+    loop {
+        interval.tick().await;
+        let ready = watcher.scan(&ledger);
+
+        let Some(client) = client else {
+            for (entry, state) in ready {
+                if attempted.get(entry.path()) != Some(&state) {
+                    println!("Would upload {:?}", entry.path());
+                    attempted.insert(entry.into_path(), state);
                 }
             }
+            continue;
+        };
+        if ready.is_empty() {
+            continue;
+        }
+
+        println!("Uploading {} new or changed file(s)...", ready.len());
+        let mut states = BTreeMap::new();
+        for (entry, state) in &ready {
+            if attempted.get(entry.path()) != Some(state) {
+                // the cached chunks would be the ones of a previous version of the file
+                chunk_manager.remove_cached_chunks(entry.path())?;
+            }
+            attempted.insert(entry.path().to_path_buf(), *state);
+            states.insert(entry.path().to_path_buf(), *state);
+        }
+
+        let upload_cfg = UploadCfg {
+            batch_size: args.batch_size.unwrap_or(BATCH_SIZE),
+            verify_store,
+            retry_strategy: args.retry_strategy,
+            reuse_existing_payments: true,
+            ..Default::default()
+        };
+        let files_uploader = FilesUploader::new(client.clone(), root_dir.to_path_buf())
+            .set_make_data_public(args.make_data_public)
+            .set_upload_cfg(upload_cfg)
+            .insert_entries(ready.into_iter().map(|(entry, _)| entry));
+
+        let (completed_files, incomplete_files) = match files_uploader.start_upload().await {
+            Ok(summary) => (
+                summary.completed_files,
+                summary
+                    .incomplete_files
+                    .into_iter()
+                    .map(|(path, ..)| path)
+                    .collect(),
+            ),
+            Err(err) => {
+                error!("Failed to upload the changes of {:?}: {err}", watcher.dir());
+                println!("Failed to upload the changes: {err}. Retrying on the next check.");
+                (vec![], states.keys().cloned().collect::<Vec<_>>())
+            }
+        };
+
+        let uploaded = completed_files
+            .into_iter()
+            .map(|(path, _, address)| (path, WatchOutcome::Uploaded, Some(address.to_hex())));
+        let failed = incomplete_files
+            .into_iter()
+            .map(|path| (path, WatchOutcome::Failed, None));
+        for (path, outcome, address) in uploaded.chain(failed) {
+            let Some(state) = states.get(&path) else {
+                continue;
+            };
+            ledger.append(&LedgerEntry {
+                path,
+                state: *state,
+                outcome,
+                address,
+                recorded_at: SystemTime::now(),
+            })?;
         }
     }
+}
+
+fn parse_erasure_coding(value: &str) -> Result<ErasureCoding> {
+    let (data_shards, parity_shards) = value
+        .split_once(':')
+        .ok_or_else(|| eyre!("Expected DATA:PARITY, e.g. '8:2', found {value:?}"))?;
+    Ok(ErasureCoding::new(
+        data_shards.trim().parse()?,
+        parity_shards.trim().parse()?,
+    )?)
+}
+
+fn print_resume_hint(session: &UploadSession) {
+    println!(
+        "The upload did not complete. Run 'safe files upload --resume {}' to resume it.",
+        session.id
+    );
+}
+
+/// Write the receipts of the paid nodes as JSON, to be kept as proofs of the payments.
+fn write_payment_receipts(
+    path: &Path,
+    receipts: &BTreeMap<NetworkAddress, SignedPaymentReceipt>,
+) -> Result<()> {
+    let receipts: Vec<_> = receipts.iter().collect();
+    std::fs::write(path, serde_json::to_vec_pretty(&receipts)?)?;
+    println!("Wrote {} payment receipts to {path:?}", receipts.len());
     Ok(())
 }
 
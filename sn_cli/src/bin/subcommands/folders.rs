@@ -41,8 +41,10 @@ pub enum FoldersCmds {
         #[clap(name = "recovery key")]
         root_sk: Option<String>,
         /// The batch_size for parallel downloading
-        #[clap(long, default_value_t = BATCH_SIZE , short='b')]
-        batch_size: usize,
+        ///
+        /// Defaults to the `batch_size` set in the config file, or 16 if it isn't set.
+        #[clap(long, short = 'b')]
+        batch_size: Option<usize>,
         /// Set the strategy to use on downloads failure.
         ///
         /// Choose a retry strategy based on effort level, from 'quick' (least effort), through 'balanced',
@@ -63,8 +65,10 @@ pub enum FoldersCmds {
         path: Option<PathBuf>,
         /// The batch_size to split chunks into parallel handling batches
         /// during payment and upload processing.
-        #[clap(long, default_value_t = BATCH_SIZE, short='b')]
-        batch_size: usize,
+        ///
+        /// Defaults to the `batch_size` set in the config file, or 16 if it isn't set.
+        #[clap(long, short = 'b')]
+        batch_size: Option<usize>,
         /// Should the files be made accessible to all. (This is irreversible)
         #[clap(long, name = "make_public", default_value = "false", short = 'p')]
         make_data_public: bool,
@@ -97,6 +101,7 @@ pub(crate) async fn folders_cmds(
             batch_size,
             retry_strategy,
         } => {
+            let batch_size = batch_size.unwrap_or(BATCH_SIZE);
             let root_sk = get_recovery_secret_sk(root_sk, false)?;
             let root_sk_hex = root_sk.main_pubkey().to_hex();
             let download_folder_name = format!(
@@ -135,7 +140,7 @@ pub(crate) async fn folders_cmds(
 
             let options = UploadCfg {
                 verify_store,
-                batch_size,
+                batch_size: batch_size.unwrap_or(BATCH_SIZE),
                 retry_strategy,
                 ..Default::default()
             };
@@ -0,0 +1,86 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use clap::Parser;
+use color_eyre::Result;
+use sn_client::{
+    BandwidthQuota, Client, Gateway, GatewayAccess, RateLimit, DEFAULT_CHUNK_CACHE_SIZE,
+};
+use std::{net::SocketAddr, path::Path, time::Duration};
+
+const MIB: usize = 1024 * 1024;
+const MINUTE: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Parser, Debug)]
+pub struct GatewayArgs {
+    /// The address the gateway listens on.
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+    /// The size of the cache of the chunks read, in MiB.
+    #[clap(long, default_value_t = DEFAULT_CHUNK_CACHE_SIZE / MIB)]
+    cache_size: usize,
+    /// The requests each client IP can make per minute, unlimited if not set.
+    #[clap(long)]
+    rate_limit: Option<u32>,
+    /// The MiB of files each client IP can be sent per day, unlimited if not set.
+    #[clap(long)]
+    daily_quota: Option<u64>,
+    /// Only serve the addresses starting with this hex prefix. Can be repeated.
+    ///
+    /// A registered name is served if the address it points to has an allowed prefix.
+    #[clap(long = "allow-prefix", value_name = "HEX")]
+    allowed_prefixes: Vec<String>,
+    /// A token exempting its bearer from the rate limit and the daily quota. Can be repeated.
+    ///
+    /// It is given as `Authorization: Bearer <token>`, or as `?token=<token>` in links.
+    #[clap(
+        long = "access-token",
+        value_name = "TOKEN",
+        env = "SAFE_GATEWAY_ACCESS_TOKENS",
+        value_delimiter = ','
+    )]
+    access_tokens: Vec<String>,
+    /// Only serve the bearers of an access token.
+    #[clap(long, requires = "access_tokens")]
+    require_token: bool,
+    /// Take the client IP from the `X-Forwarded-For` header, when behind a reverse proxy.
+    #[clap(long)]
+    trust_forwarded_for: bool,
+}
+
+/// Serve the public files of the network over HTTP, until interrupted.
+pub(crate) async fn serve(args: GatewayArgs, client: &Client, root_dir: &Path) -> Result<()> {
+    println!(
+        "Serving the files of the network at http://{}/<address>",
+        args.listen
+    );
+    let access = GatewayAccess {
+        rate_limit: args.rate_limit.map(|requests| RateLimit {
+            requests,
+            per: MINUTE,
+        }),
+        bandwidth_quota: args.daily_quota.map(|quota| BandwidthQuota {
+            bytes: quota.saturating_mul(MIB as u64),
+            per: DAY,
+        }),
+        allowed_prefixes: args.allowed_prefixes,
+        access_tokens: args.access_tokens.into_iter().collect(),
+        require_token: args.require_token,
+        trust_forwarded_for: args.trust_forwarded_for,
+    };
+    Gateway::new(
+        client.clone(),
+        root_dir.to_path_buf(),
+        args.cache_size.saturating_mul(MIB),
+    )
+    .with_access(access)
+    .serve(args.listen)
+    .await?;
+    Ok(())
+}
@@ -6,22 +6,39 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+pub(crate) mod config;
 pub(crate) mod files;
 pub(crate) mod folders;
+#[cfg(feature = "gateway")]
+pub(crate) mod gateway;
+pub(crate) mod names;
+pub(crate) mod network;
+pub(crate) mod pin;
 pub(crate) mod register;
 pub(crate) mod wallet;
 
 use clap::Parser;
 use clap::Subcommand;
 use color_eyre::Result;
+use sn_cli::{CliConfig, NetworkProfile};
 use sn_logging::{LogFormat, LogOutputDest};
 use sn_peers_acquisition::PeersArgs;
 use std::time::Duration;
 
+/// Listed at the end of `safe --help`, the codes are given by `sn_cli::ErrorKind`.
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  general failure
+  2  the network could not be reached
+  3  insufficient funds
+  4  payment failure
+  5  verification failure
+  6  partial upload, some files could not be uploaded";
+
 // Please do not remove the blank lines in these doc comments.
 // They are used for inserting line breaks when the help menu is rendered in the UI.
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_long_help = EXIT_CODES_HELP)]
 pub(crate) struct Opt {
     /// Specify the logging output destination.
     ///
@@ -44,6 +61,26 @@ pub(crate) struct Opt {
     #[clap(long, value_parser = LogFormat::parse_from_str, verbatim_doc_comment)]
     pub log_format: Option<LogFormat>,
 
+    /// Set the log levels of some modules, over the defaults and the `SN_LOG` env variable.
+    ///
+    /// Example: --log-level sn_networking=DEBUG,sn_client=INFO
+    #[clap(long, verbatim_doc_comment)]
+    pub log_level: Option<String>,
+
+    /// The endpoint of the OpenTelemetry collector the traces are exported to.
+    ///
+    /// Defaults to the `OTEL_EXPORTER_OTLP_ENDPOINT` env variable.
+    #[cfg(feature = "otlp")]
+    #[clap(long, verbatim_doc_comment)]
+    pub otlp_endpoint: Option<String>,
+
+    /// The name of the service the traces are exported under.
+    ///
+    /// Defaults to the `OTLP_SERVICE_NAME` env variable, or a random name.
+    #[cfg(feature = "otlp")]
+    #[clap(long, verbatim_doc_comment)]
+    pub otlp_service_name: Option<String>,
+
     #[command(flatten)]
     pub(crate) peers: PeersArgs,
 
@@ -60,6 +97,38 @@ pub(crate) struct Opt {
     /// This may increase operation speed, but offers no guarantees that operations were successful.
     #[clap(global = true, long = "no-verify", short = 'x')]
     pub no_verify: bool,
+
+    /// Race the chunk downloads slower than most of the recent ones with a request to another
+    /// holder, keeping the first chunk received.
+    ///
+    /// This cuts the time taken by the slowest downloads, at the cost of up to one extra request
+    /// every ten chunks.
+    #[clap(global = true, long)]
+    pub hedge: bool,
+
+    /// The network to run against.
+    ///
+    /// Valid values are "mainnet", "testnet" or "local". Each has its own network contacts,
+    /// protocol version, keys and client data dir, set in the `[profiles.<name>]` tables of the
+    /// config file.
+    ///
+    /// This overrides the `network_profile` set in the config file.
+    #[clap(global = true, long, env = "SAFE_NETWORK", value_name = "PROFILE", value_parser = |p: &str| -> Result<NetworkProfile> { p.parse() }, verbatim_doc_comment)]
+    pub network: Option<NetworkProfile>,
+
+    /// The name of the wallet to use, as listed by 'wallet list'.
+    ///
+    /// This overrides the `default_wallet` set in the config file.
+    #[clap(global = true, long, value_name = "NAME")]
+    pub wallet: Option<String>,
+
+    /// Print results in JSON format.
+    ///
+    /// A failure is then printed to stdout as an object with its kind, exit code and message.
+    ///
+    /// This overrides the `output_format` set in the config file.
+    #[clap(global = true, long)]
+    pub json: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -82,4 +151,44 @@ pub(super) enum SubCmd {
     #[clap(name = "register", subcommand)]
     /// Commands for register management
     Register(register::RegisterCmds),
+    #[clap(name = "config", subcommand)]
+    /// Commands for reading and writing the CLI config file
+    Config(config::ConfigCmds),
+    #[clap(name = "network", subcommand)]
+    /// Commands for diagnosing the connection to the network
+    Network(network::NetworkCmds),
+    #[clap(name = "names", subcommand)]
+    /// Commands for registering and resolving human-readable names, as in safe://mysite
+    Names(names::NamesCmds),
+    #[clap(name = "pin", subcommand)]
+    /// Commands for keeping files stored on the network, by repairing their under-replicated chunks
+    Pin(pin::PinCmds),
+    #[cfg(feature = "gateway")]
+    #[clap(name = "gateway")]
+    /// Serve the public files of the network over HTTP, for browsers to fetch them
+    Gateway(gateway::GatewayArgs),
+}
+
+impl SubCmd {
+    /// Applies the values from the config file to the arguments that weren't explicitly provided.
+    pub(crate) fn apply_config(&mut self, config: &CliConfig) {
+        let Some(default_batch_size) = config.batch_size else {
+            return;
+        };
+        let batch_size = match self {
+            SubCmd::Files(
+                files::FilesCmds::Upload { batch_size, .. }
+                | files::FilesCmds::Download { batch_size, .. }
+                | files::FilesCmds::Watch(files::WatchArgs { batch_size, .. }),
+            )
+            | SubCmd::Folders(
+                folders::FoldersCmds::Download { batch_size, .. }
+                | folders::FoldersCmds::Sync { batch_size, .. },
+            ) => batch_size,
+            _ => return,
+        };
+        if batch_size.is_none() {
+            *batch_size = Some(default_batch_size);
+        }
+    }
 }
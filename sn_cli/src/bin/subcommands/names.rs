@@ -0,0 +1,113 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use bls::PublicKey;
+use clap::{Args, Subcommand};
+use color_eyre::{eyre::eyre, Result};
+use sn_client::{
+    protocol::storage::{ChunkAddress, RegisterAddress},
+    Client, NameTarget, NamesApi, NAME_REGISTRANT_SEPARATOR, NAME_SCHEME,
+};
+use std::path::Path;
+use xor_name::XorName;
+
+#[derive(Subcommand, Debug)]
+pub enum NamesCmds {
+    /// Register a name, for 'safe://<name>@<key>' to point to a file, a folder or a public key.
+    ///
+    /// A name is scoped to the current client key, which is the only one able to update it and is
+    /// given along with the name to resolve it.
+    Register {
+        /// The name to register, made of letters, digits and inner hyphens.
+        #[clap(name = "name")]
+        name: String,
+        #[command(flatten)]
+        target: TargetArgs,
+    },
+    /// Point a name registered with the current client key to another target.
+    Update {
+        #[clap(name = "name")]
+        name: String,
+        #[command(flatten)]
+        target: TargetArgs,
+    },
+    /// Print the target a name points to.
+    Resolve {
+        /// The name, with or without the 'safe://' scheme, followed by '@' and the hex encoded key
+        /// of its registrant, else registered with the current client key.
+        #[clap(name = "name")]
+        name: String,
+    },
+}
+
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct TargetArgs {
+    /// The hex address of a file, as printed once uploaded.
+    #[clap(long)]
+    file: Option<String>,
+    /// The hex address of a folder, as printed once uploaded.
+    #[clap(long)]
+    folder: Option<String>,
+    /// A hex encoded public key.
+    #[clap(long)]
+    key: Option<String>,
+}
+
+impl TargetArgs {
+    fn target(self) -> Result<NameTarget> {
+        match (self.file, self.folder, self.key) {
+            (Some(file), _, _) => {
+                let bytes: [u8; xor_name::XOR_NAME_LEN] = hex::decode(&file)?
+                    .try_into()
+                    .map_err(|_| eyre!("Invalid file address {file}"))?;
+                Ok(NameTarget::File(ChunkAddress::new(XorName(bytes))))
+            }
+            (_, Some(folder), _) => Ok(NameTarget::Folder(RegisterAddress::from_hex(&folder)?)),
+            (_, _, Some(key)) => Ok(NameTarget::PublicKey(PublicKey::from_hex(&key)?)),
+            (None, None, None) => Err(eyre!("A file, folder or key is required")),
+        }
+    }
+}
+
+pub(crate) async fn names_cmds(
+    cmds: NamesCmds,
+    client: &Client,
+    root_dir: &Path,
+    verify_store: bool,
+) -> Result<()> {
+    let names_api = NamesApi::new(client.clone(), root_dir.to_path_buf());
+    match cmds {
+        NamesCmds::Register { name, target } => {
+            let (address, cost) = names_api
+                .register(&name, target.target()?, verify_store)
+                .await?;
+            println!(
+                "Registered {NAME_SCHEME}{}{NAME_REGISTRANT_SEPARATOR}{} at {} for {cost}",
+                NamesApi::parse_name(&name)?,
+                client.signer_pk().to_hex(),
+                address.to_hex()
+            );
+        }
+        NamesCmds::Update { name, target } => {
+            let version = names_api
+                .update(&name, target.target()?, verify_store)
+                .await?;
+            println!(
+                "Updated {NAME_SCHEME}{} to version {version}",
+                NamesApi::parse_name(&name)?
+            );
+        }
+        NamesCmds::Resolve { name } => match names_api.resolve(&name).await? {
+            NameTarget::File(address) => println!("file {}", hex::encode(address.xorname())),
+            NameTarget::Folder(address) => println!("folder {}", address.to_hex()),
+            NameTarget::PublicKey(key) => println!("key {}", key.to_hex()),
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,239 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use clap::Parser;
+use color_eyre::Result;
+use futures::future::join_all;
+use serde::Serialize;
+use sn_cli::{CliConfig, NetworkProfile, OutputFormat};
+use sn_client::{
+    networking::multiaddr_is_global,
+    protocol::version::{
+        IDENTIFY_CLIENT_VERSION_STR, IDENTIFY_PROTOCOL_STR, REQ_RESPONSE_VERSION_STR,
+    },
+    Client, ClientEvent, ClientEventsReceiver,
+};
+use sn_peers_acquisition::{PeersArgs, SAFE_PEERS_ENV};
+use tokio::sync::broadcast::error::TryRecvError;
+
+/// The default number of connected peers the latency is sampled from.
+const DEFAULT_LATENCY_SAMPLES: usize = 5;
+
+#[derive(Parser, Debug)]
+pub enum NetworkCmds {
+    /// Print connectivity information: where the bootstrap contacts came from, the number of
+    /// connected peers, sampled latencies, NAT status and the protocol version.
+    ///
+    /// Please include its output when reporting issues connecting to the network.
+    Status {
+        /// The number of connected peers to sample the latency from.
+        #[clap(long, default_value_t = DEFAULT_LATENCY_SAMPLES)]
+        samples: usize,
+    },
+}
+
+/// The connectivity information printed by `safe network status`.
+#[derive(Debug, Serialize)]
+pub(crate) struct NetworkStatus {
+    bootstrap_source: String,
+    bootstrap_peers: usize,
+    connected: bool,
+    connected_peers: usize,
+    latencies: Vec<PeerLatency>,
+    nat_status: String,
+    client_version: String,
+    protocol_version: String,
+    req_response_version: String,
+    unsupported_protocols: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PeerLatency {
+    peer: String,
+    protocol_version: Option<String>,
+    latency_ms: Option<u128>,
+    error: Option<String>,
+}
+
+impl NetworkStatus {
+    /// The status before connecting to the network.
+    pub(crate) fn new(bootstrap_source: String, bootstrap_peers: usize) -> Self {
+        Self {
+            bootstrap_source,
+            bootstrap_peers,
+            connected: false,
+            connected_peers: 0,
+            latencies: vec![],
+            nat_status: "unknown".to_string(),
+            client_version: IDENTIFY_CLIENT_VERSION_STR.clone(),
+            protocol_version: IDENTIFY_PROTOCOL_STR.clone(),
+            req_response_version: REQ_RESPONSE_VERSION_STR.clone(),
+            unsupported_protocols: vec![],
+        }
+    }
+
+    /// Records the peers found to be running an incompatible protocol version while connecting.
+    pub(crate) fn drain_events(&mut self, events: &mut ClientEventsReceiver) {
+        loop {
+            match events.try_recv() {
+                Ok(ClientEvent::PeerWithUnsupportedProtocol { their_protocol, .. }) => {
+                    if !self.unsupported_protocols.contains(&their_protocol) {
+                        self.unsupported_protocols.push(their_protocol);
+                    }
+                }
+                Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+    }
+
+    pub(crate) fn print(&self, output_format: OutputFormat) -> Result<()> {
+        if output_format == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(self)?);
+            return Ok(());
+        }
+
+        println!(
+            "Bootstrap contacts: {} ({})",
+            self.bootstrap_source, self.bootstrap_peers
+        );
+        println!("Connected: {}", if self.connected { "yes" } else { "no" });
+        println!("Connected peers: {}", self.connected_peers);
+        if !self.latencies.is_empty() {
+            println!("Sampled latencies:");
+            for sample in &self.latencies {
+                let version = sample
+                    .protocol_version
+                    .as_deref()
+                    .unwrap_or("unknown version");
+                match (sample.latency_ms, &sample.error) {
+                    (Some(latency_ms), _) => {
+                        println!("  {} ({version}): {latency_ms}ms", sample.peer)
+                    }
+                    (None, Some(err)) => println!("  {} ({version}): failed ({err})", sample.peer),
+                    (None, None) => println!("  {} ({version}): failed", sample.peer),
+                }
+            }
+        }
+        println!("NAT status: {}", self.nat_status);
+        println!("Client version: {}", self.client_version);
+        println!("Protocol version: {}", self.protocol_version);
+        println!("Request/response version: {}", self.req_response_version);
+        if !self.unsupported_protocols.is_empty() {
+            println!(
+                "Peers with an unsupported protocol were found: {}",
+                self.unsupported_protocols.join(", ")
+            );
+            println!("Your client may be outdated, please consider upgrading it.");
+        }
+        Ok(())
+    }
+
+    async fn sample_connection(&mut self, client: &Client, samples: usize) -> Result<()> {
+        let state = client.get_swarm_local_state().await?;
+        self.connected = true;
+        self.connected_peers = state.connected_peers.len();
+
+        self.nat_status = if state.listeners.is_empty() {
+            "not listening, outbound connections only".to_string()
+        } else if state.listeners.iter().any(multiaddr_is_global) {
+            "publicly reachable".to_string()
+        } else {
+            "private, behind NAT or on a local network".to_string()
+        };
+
+        let peer_protocol_versions = state.peer_protocol_versions;
+        let peers = state.connected_peers.into_iter().take(samples);
+        let latencies = join_all(peers.map(|peer| async move {
+            let result = client.measure_peer_latency(peer).await;
+            (peer, result)
+        }))
+        .await;
+        self.latencies = latencies
+            .into_iter()
+            .map(|(peer, result)| {
+                let protocol_version = peer_protocol_versions.get(&peer).cloned();
+                match result {
+                    Ok(latency) => PeerLatency {
+                        peer: peer.to_string(),
+                        protocol_version,
+                        latency_ms: Some(latency.as_millis()),
+                        error: None,
+                    },
+                    Err(err) => PeerLatency {
+                        peer: peer.to_string(),
+                        protocol_version,
+                        latency_ms: None,
+                        error: Some(err.to_string()),
+                    },
+                }
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+/// Describes where the bootstrap contacts are obtained from, following the precedence used by
+/// `PeersArgs::get_peers` and the config file.
+pub(crate) fn bootstrap_source(
+    peers: &PeersArgs,
+    config: &CliConfig,
+    profile: NetworkProfile,
+) -> String {
+    if peers.first {
+        "none, --first was given".to_string()
+    } else if !peers.peers.is_empty() {
+        if std::env::var(SAFE_PEERS_ENV).is_ok() {
+            format!("{SAFE_PEERS_ENV} environment variable")
+        } else {
+            "--peer argument".to_string()
+        }
+    } else if config
+        .network_contacts(profile)
+        .is_ok_and(|contacts| !contacts.is_empty())
+    {
+        format!("config file, {profile} profile")
+    } else if cfg!(feature = "local-discovery") {
+        "local discovery (mDNS)".to_string()
+    } else if !profile.fetches_network_contacts() {
+        format!("none, the {profile} network has no network contacts url")
+    } else {
+        network_contacts_source(peers)
+    }
+}
+
+#[cfg(feature = "network-contacts")]
+fn network_contacts_source(peers: &PeersArgs) -> String {
+    let url = peers
+        .network_contacts_url
+        .as_ref()
+        .map(|url| url.to_string())
+        .unwrap_or_else(|| sn_peers_acquisition::NETWORK_CONTACTS_URL.to_string());
+    format!("network contacts from {url}")
+}
+
+#[cfg(not(feature = "network-contacts"))]
+fn network_contacts_source(_peers: &PeersArgs) -> String {
+    "none".to_string()
+}
+
+pub(crate) async fn network_cmds(
+    cmds: NetworkCmds,
+    client: &Client,
+    mut status: NetworkStatus,
+    events: &mut ClientEventsReceiver,
+    output_format: OutputFormat,
+) -> Result<()> {
+    match cmds {
+        NetworkCmds::Status { samples } => {
+            status.sample_connection(client, samples).await?;
+            status.drain_events(events);
+            status.print(output_format)
+        }
+    }
+}
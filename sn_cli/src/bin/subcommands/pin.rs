@@ -0,0 +1,173 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use chrono::{DateTime, Local};
+use clap::Parser;
+use color_eyre::{eyre::eyre, Result};
+use sn_client::{
+    protocol::storage::ChunkAddress, Client, ClientStore, FilesApi, PinCfg, PinEvent, PinScheduler,
+    DEFAULT_CHECK_INTERVAL, DEFAULT_MIN_REPLICAS,
+};
+use std::{path::Path, time::Duration};
+use xor_name::XorName;
+
+#[derive(Parser, Debug)]
+pub enum PinCmds {
+    /// Pin a file, for 'pin check' to keep its chunks stored on the network.
+    Add {
+        /// The hex address of the file, as printed once uploaded.
+        #[clap(name = "address")]
+        address: String,
+    },
+    /// Unpin a file, or a single chunk.
+    Remove {
+        /// The hex address of the file or chunk.
+        #[clap(name = "address")]
+        address: String,
+    },
+    /// List the pinned chunks, along with their state as of their last check.
+    List,
+    /// Check the pinned chunks which are due to be, paying for and uploading again those held by
+    /// too few nodes.
+    ///
+    /// The repairs are paid from the loaded wallet.
+    Check {
+        /// Check all the pinned chunks, whether they are due to be or not.
+        #[clap(long)]
+        all: bool,
+        /// Keep checking the pinned chunks as they get due, until interrupted.
+        #[clap(long, conflicts_with = "all")]
+        watch: bool,
+        /// A chunk held by fewer of its close nodes is repaired.
+        #[clap(long, default_value_t = DEFAULT_MIN_REPLICAS)]
+        min_replicas: usize,
+        /// The time between two checks of a pinned chunk, in seconds.
+        #[clap(long, default_value_t = DEFAULT_CHECK_INTERVAL.as_secs())]
+        interval: u64,
+    },
+}
+
+pub(crate) fn pin_cmds_without_client(cmds: &PinCmds, root_dir: &Path) -> Result<()> {
+    let mut pin_set = ClientStore::open(root_dir)?.pins()?;
+    match cmds {
+        PinCmds::Remove { address } => {
+            let count = pin_set.unpin(&parse_address(address)?);
+            pin_set.store()?;
+            println!("Unpinned {count} chunks");
+        }
+        PinCmds::List => {
+            if pin_set.is_empty() {
+                println!("No chunk is pinned");
+            }
+            for (address, pin) in pin_set.iter() {
+                let state = match (pin.last_checked, pin.replicas) {
+                    (Some(last_checked), Some(replicas)) => format!(
+                        "held by {replicas} nodes as of {}",
+                        DateTime::<Local>::from(last_checked).format("%Y-%m-%d %H:%M:%S")
+                    ),
+                    _ => "not checked yet".to_string(),
+                };
+                println!(
+                    "{}: {state}, repaired {} times",
+                    hex::encode(address),
+                    pin.repairs
+                );
+            }
+        }
+        cmd => return Err(eyre!("{cmd:?} requires us to be connected to the Network")),
+    }
+    Ok(())
+}
+
+pub(crate) async fn pin_cmds(cmds: PinCmds, client: &Client, root_dir: &Path) -> Result<()> {
+    let pin_set = ClientStore::open(root_dir)?.pins()?;
+    match cmds {
+        PinCmds::Add { address } => {
+            let files_api = FilesApi::build(client.clone(), root_dir.to_path_buf())?;
+            let mut scheduler = PinScheduler::new(files_api, pin_set, PinCfg::default());
+            let count = scheduler
+                .pin_file(ChunkAddress::new(parse_address(&address)?))
+                .await?;
+            println!("Pinned {count} chunks of file {address}");
+        }
+        PinCmds::Check {
+            all,
+            watch,
+            min_replicas,
+            interval,
+        } => {
+            let files_api = FilesApi::build(client.clone(), root_dir.to_path_buf())?;
+            let cfg = PinCfg {
+                min_replicas,
+                check_interval: Duration::from_secs(interval),
+            };
+            let mut scheduler = PinScheduler::new(files_api, pin_set, cfg);
+            let mut events = scheduler.get_event_receiver();
+            let printer = tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    print_event(&event);
+                }
+            });
+
+            if watch {
+                println!("Checking the pinned chunks as they get due. Press Ctrl+C to stop.");
+                scheduler.run().await?;
+            } else {
+                let summary = if all {
+                    scheduler.check_all().await?
+                } else {
+                    scheduler.check_due().await?
+                };
+                drop(scheduler);
+                printer.await?;
+                println!(
+                    "Checked {} chunks: {} healthy, {} repaired for {}, {} failed, {} lost",
+                    summary.checked(),
+                    summary.healthy,
+                    summary.repaired,
+                    summary.cost,
+                    summary.failed,
+                    summary.lost
+                );
+            }
+        }
+        cmds => pin_cmds_without_client(&cmds, root_dir)?,
+    }
+    Ok(())
+}
+
+fn print_event(event: &PinEvent) {
+    match event {
+        PinEvent::Healthy { .. } => {}
+        PinEvent::UnderReplicated { address, replicas } => {
+            println!(
+                "Chunk {} is only held by {replicas} nodes, repairing it",
+                hex::encode(address)
+            );
+        }
+        PinEvent::Repaired { address, cost } => {
+            println!("Repaired chunk {} for {cost}", hex::encode(address));
+        }
+        PinEvent::RepairFailed { address, error } => {
+            println!("Could not repair chunk {}: {error}", hex::encode(address));
+        }
+        PinEvent::CheckFailed { address, error } => {
+            println!("Could not check chunk {}: {error}", hex::encode(address));
+        }
+        PinEvent::Lost(address) => {
+            println!("Chunk {} could not be retrieved", hex::encode(address));
+        }
+    }
+}
+
+fn parse_address(address: &str) -> Result<XorName> {
+    let bytes: [u8; xor_name::XOR_NAME_LEN] = hex::decode(address)?
+        .try_into()
+        .map_err(|_| eyre!("Invalid address {address}"))?;
+    Ok(XorName(bytes))
+}
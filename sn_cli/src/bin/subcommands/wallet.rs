@@ -9,14 +9,24 @@
 mod audit;
 pub(crate) mod helpers;
 pub(crate) mod hot_wallet;
+pub(crate) mod password;
 pub(crate) mod wo_wallet;
 
-use sn_client::transfers::{CashNote, HotWallet, MainPubkey, NanoTokens, WatchOnlyWallet};
+use sn_client::transfers::{
+    CashNote, HotWallet, MainPubkey, NanoTokens, WalletManager, WatchOnlyWallet, WALLET_DIR_NAME,
+};
 use sn_protocol::storage::SpendAddress;
 
-use crate::get_stdin_password_response;
 use color_eyre::Result;
-use std::{collections::BTreeSet, io::Read, path::Path};
+use serde::Serialize;
+use sn_cli::OutputFormat;
+use std::{
+    collections::BTreeSet,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+pub(crate) use sn_client::transfers::DEFAULT_WALLET_NAME;
 
 // TODO: convert this into a Trait part of the wallet APIs.
 pub(crate) enum WalletApiHelper {
@@ -33,11 +43,25 @@ impl WalletApiHelper {
     pub fn load_from(root_dir: &Path) -> Result<Self> {
         let wallet = if HotWallet::is_encrypted(root_dir) {
             println!("Wallet is encrypted. It needs a password to unlock.");
-            let password = get_stdin_password_response("Enter password: ");
-            let mut wallet = HotWallet::load_encrypted_from_path(root_dir, password.to_owned())?;
-            // Authenticate so that a user doesn't have to immediately provide the password again
-            wallet.authenticate_with_password(password)?;
-            wallet
+            let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+            let password = password::get_wallet_password(&wallet_dir, "Enter password: ");
+            let wallet = HotWallet::load_encrypted_from_path(root_dir, password.to_owned())
+                .and_then(|mut wallet| {
+                    // Authenticate so that a user doesn't have to immediately provide the password again
+                    wallet.authenticate_with_password(password.to_owned())?;
+                    Ok(wallet)
+                });
+            match wallet {
+                Ok(wallet) => {
+                    password::cache_password(&wallet_dir, &password);
+                    wallet
+                }
+                Err(err) => {
+                    // the cached password may be stale
+                    password::forget_password(&wallet_dir);
+                    return Err(err.into());
+                }
+            }
         } else {
             HotWallet::load_from(root_dir)?
         };
@@ -50,6 +74,19 @@ impl WalletApiHelper {
         Ok(())
     }
 
+    pub fn change_password(root_dir: &Path, password: &str, new_password: &str) -> Result<()> {
+        HotWallet::change_password(root_dir, password, new_password)?;
+        // a cached password is stale from now on
+        password::forget_password(&root_dir.join(WALLET_DIR_NAME));
+        Ok(())
+    }
+
+    pub fn decrypt(root_dir: &Path, password: &str) -> Result<()> {
+        HotWallet::decrypt(root_dir, password)?;
+        password::forget_password(&root_dir.join(WALLET_DIR_NAME));
+        Ok(())
+    }
+
     pub fn balance(&self) -> NanoTokens {
         match self {
             Self::WatchOnlyWallet(w) => w.balance(),
@@ -183,8 +220,13 @@ impl WalletApiHelper {
             WalletApiHelper::WatchOnlyWallet(_) => Ok(()),
             WalletApiHelper::HotWallet(w) => {
                 if w.authenticate().is_err() {
-                    let password = get_stdin_password_response("Wallet password: ");
-                    w.authenticate_with_password(password)?;
+                    let wallet_dir = w.api().wallet_dir().to_path_buf();
+                    let password = password::get_wallet_password(&wallet_dir, "Wallet password: ");
+                    if let Err(err) = w.authenticate_with_password(password.to_owned()) {
+                        password::forget_password(&wallet_dir);
+                        return Err(err.into());
+                    }
+                    password::cache_password(&wallet_dir, &password);
                     Ok(())
                 } else {
                     Ok(())
@@ -194,14 +236,81 @@ impl WalletApiHelper {
     }
 }
 
+/// The root dir of the wallet with the given name, i.e. the dir its `wallet` dir, uploaded files
+/// and chunk artifacts are kept in. Without a name, the client data dir itself is used.
+pub(crate) fn wallet_root_dir(client_data_dir: &Path, name: Option<&str>) -> Result<PathBuf> {
+    let manager = WalletManager::new(client_data_dir);
+    Ok(manager.wallet_root_dir(name.unwrap_or(DEFAULT_WALLET_NAME))?)
+}
+
+#[derive(Serialize)]
+struct WalletSummary {
+    name: String,
+    selected: bool,
+    address: String,
+    balance: String,
+    encrypted: bool,
+}
+
+/// Prints the wallets found in the client data dir, marking the `selected` one.
+pub(crate) fn list_wallets(
+    client_data_dir: &Path,
+    selected: Option<&str>,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let manager = WalletManager::new(client_data_dir);
+    let selected = selected.unwrap_or(DEFAULT_WALLET_NAME);
+
+    let mut wallets = vec![];
+    for name in manager.list()? {
+        // an unreadable wallet does not hide the others
+        let wallet = match manager.open_watch_only(&name) {
+            Ok(wallet) => wallet,
+            Err(err) => {
+                eprintln!("Could not read wallet {name:?}: {err}");
+                continue;
+            }
+        };
+        wallets.push(WalletSummary {
+            selected: name == selected,
+            encrypted: HotWallet::is_encrypted(&manager.wallet_root_dir(&name)?),
+            address: wallet.address().to_hex(),
+            balance: wallet.balance().to_string(),
+            name,
+        });
+    }
+
+    match output_format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&wallets)?),
+        OutputFormat::Text if wallets.is_empty() => {
+            println!("No wallet found. Use 'wallet create' to create one.")
+        }
+        OutputFormat::Text => {
+            for wallet in wallets {
+                println!(
+                    "{} {}  {}  balance: {}{}",
+                    if wallet.selected { "*" } else { " " },
+                    wallet.name,
+                    wallet.address,
+                    wallet.balance,
+                    if wallet.encrypted {
+                        "  (encrypted)"
+                    } else {
+                        ""
+                    },
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
 fn watch_only_wallet_from_pk(main_pk: MainPubkey, root_dir: &Path) -> Result<WatchOnlyWallet> {
-    let pk_hex = main_pk.to_hex();
-    let folder_name = format!("pk_{}_{}", &pk_hex[..6], &pk_hex[pk_hex.len() - 6..]);
-    let wallet_dir = root_dir.join(folder_name);
+    let wallet_dir = WatchOnlyWallet::wallet_dir_of(root_dir, main_pk);
     println!(
         "Loading watch-only local wallet from: {}",
         wallet_dir.display()
     );
-    let wallet = WatchOnlyWallet::load_from(&wallet_dir, main_pk)?;
+    let wallet = WatchOnlyWallet::load_from_root_dir(root_dir, main_pk)?;
     Ok(wallet)
 }
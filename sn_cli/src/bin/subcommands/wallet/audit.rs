@@ -6,17 +6,23 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use std::path::Path;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use bls::SecretKey;
 use color_eyre::eyre::bail;
 use color_eyre::Result;
 use sn_client::acc_packet::load_account_wallet_or_create_with_mnemonic;
-use sn_client::transfers::{CashNoteRedemption, SpendAddress, Transfer, GENESIS_SPEND_UNIQUE_KEY};
-use sn_client::{Client, SpendDag};
+use sn_client::transfers::{
+    CashNoteRedemption, SpendAddress, Transfer, GENESIS_SPEND_UNIQUE_KEY, NETWORK_ROYALTIES_PK,
+};
+use sn_client::{Client, DagExportFilter, DagExportFormat, RoyaltyReport, SpendDag};
 
 const SPEND_DAG_FILENAME: &str = "spend_dag";
+const ROYALTY_REPORT_FILENAME: &str = "royalty_report";
 const SPENDS_PROCESSING_BUFFER_SIZE: usize = 4096;
 
 async fn step_by_step_spend_dag_gathering(client: &Client, mut dag: SpendDag) -> Result<SpendDag> {
@@ -94,26 +100,54 @@ async fn gather_spend_dag(client: &Client, root_dir: &Path, fast_mode: bool) ->
     Ok(dag)
 }
 
+/// Which part of the DAG to export, in which format and where
+pub struct DagExport {
+    pub format: DagExportFormat,
+    pub filter: DagExportFilter,
+    /// Stdout if not set
+    pub path: Option<PathBuf>,
+}
+
 pub async fn audit(
     client: &Client,
     to_dot: bool,
     royalties: bool,
+    royalty_stats: bool,
     root_dir: &Path,
     foundation_sk: Option<SecretKey>,
+    export: Option<DagExport>,
 ) -> Result<()> {
-    let fast_mode = to_dot || royalties || foundation_sk.is_some();
+    let fast_mode =
+        to_dot || royalties || royalty_stats || foundation_sk.is_some() || export.is_some();
     let dag = gather_spend_dag(client, root_dir, fast_mode).await?;
 
     if to_dot {
         println!("==========================   spends DAG digraph   ==========================");
         println!("{}", dag.dump_dot_format());
     }
+    if let Some(export) = export {
+        match &export.path {
+            Some(path) => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                dag.export(export.format, &export.filter, &mut writer)?;
+                writer.flush()?;
+                println!(
+                    "Exported the spends DAG in {} format to {path:?}",
+                    export.format
+                );
+            }
+            None => dag.export(export.format, &export.filter, std::io::stdout().lock())?,
+        }
+    }
     if let Some(sk) = foundation_sk {
         println!(
             "==========================   payment forward statistics  =========================="
         );
         println!("{}", dag.dump_payment_forward_statistics(&sk));
     }
+    if royalty_stats {
+        print_royalty_stats(&dag, root_dir)?;
+    }
     if royalties {
         let royalties = dag.all_royalties()?;
         redeem_royalties(royalties, client, root_dir).await?;
@@ -123,6 +157,35 @@ pub async fn audit(
     Ok(())
 }
 
+/// Count the royalties of the spends gathered since the last audit, and display the statistics
+fn print_royalty_stats(dag: &SpendDag, root_dir: &Path) -> Result<()> {
+    let report_path = root_dir.join(ROYALTY_REPORT_FILENAME);
+    let mut report = match RoyaltyReport::load_from_file(&report_path) {
+        Ok(report) => report,
+        Err(err) => {
+            info!("Starting a new royalty report as failed to load it from disk: {err}");
+            RoyaltyReport::new()
+        }
+    };
+    let ended_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let period = report.update(dag, &[*NETWORK_ROYALTIES_PK], ended_at);
+    println!("==========================   royalty statistics   ==========================");
+    println!(
+        "Since the last audit: {} royalties paid, for a total of {}",
+        period.payments, period.total
+    );
+    println!(
+        "Over {} audits: a total of {}",
+        report.periods.len(),
+        report.total()
+    );
+    for (payee, total) in report.per_payee() {
+        println!("  to {}: {total}", payee.to_hex());
+    }
+    report.dump_to_file(report_path)?;
+    Ok(())
+}
+
 /// Redeem royalties from the Network and deposit them into the wallet
 /// Only works if the wallet has the private key for the royalties
 async fn redeem_royalties(
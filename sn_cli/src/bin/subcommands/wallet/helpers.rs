@@ -8,13 +8,87 @@
 
 #[cfg(feature = "distribution")]
 use base64::Engine;
-use color_eyre::Result;
+use color_eyre::{eyre::bail, Result};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sn_cli::{
+    pricing::{ExchangeRate, Pricing},
+    CliConfig, OutputFormat,
+};
 use sn_client::acc_packet::load_account_wallet_or_create_with_mnemonic;
-use sn_client::transfers::Transfer;
+use sn_client::transfers::{NanoTokens, SpendAddress, Transfer, NETWORK_ROYALTIES_PK};
 use sn_client::Client;
-use std::path::Path;
+use std::{path::Path, time::Duration};
 use url::Url;
 
+/// The longest wait between two attempts to claim from the faucet.
+const FAUCET_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How a claim from the faucet ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ClaimStatus {
+    /// The tokens were received, verified and deposited to the wallet.
+    Claimed,
+    /// The faucet refused the claim, retrying wouldn't help.
+    Rejected,
+    /// The claim could not be completed after all the retries.
+    Failed,
+}
+
+/// The outcome of `wallet get-faucet`, as printed with `--json`.
+#[derive(Debug, Serialize)]
+struct FaucetClaim {
+    status: ClaimStatus,
+    wallet_address: String,
+    attempts: u32,
+    amount: Option<String>,
+    balance: Option<String>,
+    error: Option<String>,
+}
+
+impl FaucetClaim {
+    fn new(wallet_address: String) -> Self {
+        Self {
+            status: ClaimStatus::Failed,
+            wallet_address,
+            attempts: 0,
+            amount: None,
+            balance: None,
+            error: None,
+        }
+    }
+
+    fn report(self, output_format: OutputFormat) -> Result<()> {
+        match output_format {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&self)?),
+            OutputFormat::Text => match (&self.status, &self.error) {
+                (ClaimStatus::Claimed, _) => {
+                    println!(
+                        "Successfully got {} tokens from faucet.",
+                        self.amount.as_deref().unwrap_or_default()
+                    );
+                    println!(
+                        "New balance: {}",
+                        self.balance.as_deref().unwrap_or_default()
+                    );
+                }
+                (_, error) => println!(
+                    "Failed to get tokens from faucet after {} attempt(s): {}",
+                    self.attempts,
+                    error.as_deref().unwrap_or_default()
+                ),
+            },
+        }
+
+        match self.status {
+            ClaimStatus::Claimed => Ok(()),
+            ClaimStatus::Rejected => bail!("The faucet rejected the claim"),
+            ClaimStatus::Failed => bail!("Could not get tokens from the faucet"),
+        }
+    }
+}
+
 #[cfg(feature = "distribution")]
 pub async fn get_faucet(
     root_dir: &Path,
@@ -22,19 +96,18 @@ pub async fn get_faucet(
     url: String,
     address: Option<String>,
     signature: Option<String>,
+    retries: u32,
+    output_format: OutputFormat,
 ) -> Result<()> {
     if address.is_some() ^ signature.is_some() {
-        println!("Address and signature must both be specified.");
-        return Ok(());
+        bail!("Address and signature must both be specified.");
     }
-    if address.is_none() && signature.is_none() {
-        get_faucet_fixed_amount(root_dir, client, url).await?;
-    } else if let Some(addr) = address {
-        if let Some(sig) = signature {
-            get_faucet_distribution(root_dir, client, url, addr, sig).await?;
+    match (address, signature) {
+        (Some(addr), Some(sig)) => {
+            get_faucet_distribution(root_dir, client, url, addr, sig, retries, output_format).await
         }
+        _ => get_faucet_fixed_amount(root_dir, client, url, retries, output_format).await,
     }
-    Ok(())
 }
 
 #[cfg(not(feature = "distribution"))]
@@ -44,31 +117,37 @@ pub async fn get_faucet(
     url: String,
     _address: Option<String>,
     _signature: Option<String>,
+    retries: u32,
+    output_format: OutputFormat,
 ) -> Result<()> {
-    get_faucet_fixed_amount(root_dir, client, url).await
+    get_faucet_fixed_amount(root_dir, client, url, retries, output_format).await
 }
 
-pub async fn get_faucet_fixed_amount(root_dir: &Path, client: &Client, url: String) -> Result<()> {
+pub async fn get_faucet_fixed_amount(
+    root_dir: &Path,
+    client: &Client,
+    url: String,
+    retries: u32,
+    output_format: OutputFormat,
+) -> Result<()> {
     let wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
     let address_hex = wallet.address().to_hex();
-    let url = if !url.contains("://") {
-        format!("{}://{}", "http", url)
-    } else {
-        url
-    };
-    let req_url = Url::parse(&format!("{url}/{address_hex}"))?;
-    println!("Requesting token for wallet address: {address_hex}");
-
-    let response = reqwest::get(req_url).await?;
-    let is_ok = response.status().is_success();
-    let body = response.text().await?;
-    if is_ok {
-        receive(body, false, client, root_dir).await?;
-        println!("Successfully got tokens from faucet.");
-    } else {
-        println!("Failed to get tokens from faucet, server responded with: {body:?}");
+    let req_url = Url::parse(&format!("{}/{address_hex}", with_scheme(url)))?;
+    if output_format == OutputFormat::Text {
+        println!("Requesting token for wallet address: {address_hex}");
     }
-    Ok(())
+
+    let mut claim = FaucetClaim::new(address_hex);
+    claim_from_faucet(
+        &mut claim,
+        req_url,
+        client,
+        root_dir,
+        retries,
+        output_format,
+    )
+    .await;
+    claim.report(output_format)
 }
 
 #[cfg(feature = "distribution")]
@@ -78,37 +157,180 @@ pub async fn get_faucet_distribution(
     url: String,
     address: String,
     signature: String,
+    retries: u32,
+    output_format: OutputFormat,
 ) -> Result<()> {
-    // submit the details to the faucet to get the distribution
-    let url = if !url.contains("://") {
-        format!("{}://{}", "http", url)
-    } else {
-        url
-    };
     // receive to the current local wallet
     let wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?
         .address()
         .to_hex();
-    println!("Requesting distribution for maid address {address} to local wallet {wallet}");
+    if output_format == OutputFormat::Text {
+        println!("Requesting distribution for maid address {address} to local wallet {wallet}");
+    }
     // base64 uses + and / as the delimiters which doesn't go well in the query
     // string, so the signature is encoded using url safe characters.
     let sig_bytes = base64::engine::general_purpose::STANDARD.decode(signature)?;
     let sig_url = base64::engine::general_purpose::URL_SAFE.encode(sig_bytes);
+    // submit the details to the faucet to get the distribution
     let req_url = Url::parse(&format!(
-        "{url}/distribution?address={address}&wallet={wallet}&signature={sig_url}"
+        "{}/distribution?address={address}&wallet={wallet}&signature={sig_url}",
+        with_scheme(url)
     ))?;
-    let response = reqwest::get(req_url).await?;
-    let is_ok = response.status().is_success();
-    let transfer_hex = response.text().await?;
-    if !is_ok {
+
+    let mut claim = FaucetClaim::new(wallet);
+    claim_from_faucet(
+        &mut claim,
+        req_url,
+        client,
+        root_dir,
+        retries,
+        output_format,
+    )
+    .await;
+    claim.report(output_format)
+}
+
+fn with_scheme(url: String) -> String {
+    if !url.contains("://") {
+        format!("{}://{}", "http", url)
+    } else {
+        url
+    }
+}
+
+/// Requests a transfer from the faucet, then verifies and deposits it to the local wallet.
+///
+/// Both steps are retried with an exponential backoff, up to `retries` times each. The faucet is
+/// not asked again once it has replied with a transfer, so a claim can't be received twice.
+async fn claim_from_faucet(
+    claim: &mut FaucetClaim,
+    req_url: Url,
+    client: &Client,
+    root_dir: &Path,
+    retries: u32,
+    output_format: OutputFormat,
+) {
+    let transfer_hex = loop {
+        claim.attempts += 1;
+        let (should_retry, error) = match reqwest::get(req_url.clone()).await {
+            Ok(response) => {
+                let status = response.status();
+                match response.text().await {
+                    Ok(body) if status.is_success() => break body.trim().to_string(),
+                    Ok(body) => (
+                        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+                        format!("server responded with {status}: {body:?}"),
+                    ),
+                    Err(err) => (true, format!("failed to read the response: {err}")),
+                }
+            }
+            Err(err) => (true, format!("request failed: {err}")),
+        };
+
+        warn!("Faucet claim attempt {} failed: {error}", claim.attempts);
+        claim.error = Some(error);
+        if !should_retry {
+            claim.status = ClaimStatus::Rejected;
+            return;
+        }
+        if !backoff(claim, retries, output_format).await {
+            return;
+        }
+    };
+
+    let transfer = match Transfer::from_hex(&transfer_hex) {
+        Ok(transfer) => transfer,
+        Err(err) => {
+            claim.error = Some(format!("failed to parse transfer {transfer_hex:?}: {err}"));
+            return;
+        }
+    };
+    if output_format == OutputFormat::Text {
+        println!("Verifying transfer with the Network...");
+    }
+
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match redeem(&transfer, client, root_dir).await {
+            Ok((amount, balance)) => {
+                claim.status = ClaimStatus::Claimed;
+                claim.amount = Some(amount.to_string());
+                claim.balance = Some(balance.to_string());
+                claim.error = None;
+                return;
+            }
+            Err(err) => {
+                warn!("Failed to verify and redeem the faucet transfer: {err:?}");
+                claim.error = Some(format!("failed to verify and redeem transfer: {err}"));
+                if attempts > retries {
+                    return;
+                }
+                let delay = backoff_delay(attempts);
+                if output_format == OutputFormat::Text {
+                    println!("Could not verify the transfer yet, retrying in {delay:?}...");
+                }
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// Waits before the next attempt, returning false if there are no retries left.
+async fn backoff(claim: &FaucetClaim, retries: u32, output_format: OutputFormat) -> bool {
+    if claim.attempts > retries {
+        return false;
+    }
+    let delay = backoff_delay(claim.attempts);
+    if output_format == OutputFormat::Text {
         println!(
-            "Failed to get distribution from faucet, server responded with:\n{transfer_hex:?}"
+            "Faucet claim failed: {}. Retrying in {delay:?}...",
+            claim.error.as_deref().unwrap_or_default()
         );
-        return Ok(());
     }
-    println!("Receiving transfer for maid address {address}:\n{transfer_hex}");
-    receive(transfer_hex, false, client, root_dir).await?;
-    Ok(())
+    tokio::time::sleep(delay).await;
+    true
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    let delay = Duration::from_secs(1u64 << attempt.saturating_sub(1).min(5));
+    delay.min(FAUCET_MAX_BACKOFF)
+}
+
+/// The rate of the currency given with `--fiat`, from the providers set in the config file.
+///
+/// The amounts are still printed when no rate can be obtained, only without their estimate.
+pub async fn fiat_rate(currency: Option<&str>) -> Result<Option<ExchangeRate>> {
+    let Some(currency) = currency else {
+        return Ok(None);
+    };
+    let config = CliConfig::load_from(&CliConfig::default_path()?)?;
+    match Pricing::from_config(&config)?.rate(currency).await {
+        Ok(rate) => Ok(Some(rate)),
+        Err(err) => {
+            println!("Could not get the {currency} exchange rate: {err}");
+            Ok(None)
+        }
+    }
+}
+
+/// Verifies the transfer with the network and deposits it to the local wallet, returning the
+/// amount received and the new balance.
+async fn redeem(
+    transfer: &Transfer,
+    client: &Client,
+    root_dir: &Path,
+) -> Result<(NanoTokens, NanoTokens)> {
+    let mut wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
+    let cashnotes = client.receive(transfer, &wallet).await?;
+
+    let old_balance = wallet.balance();
+    wallet.deposit_and_store_to_disk(&cashnotes)?;
+    let new_balance = wallet.balance();
+    let amount = new_balance
+        .checked_sub(old_balance)
+        .unwrap_or(NanoTokens::zero());
+    Ok((amount, new_balance))
 }
 
 pub async fn receive(
@@ -154,3 +376,136 @@ pub async fn receive(
 
     Ok(())
 }
+
+/// What could be learnt about a transfer without depositing it, as printed by `inspect-transfer`.
+#[derive(Debug, Serialize)]
+struct TransferInspection {
+    kind: &'static str,
+    redemptions: usize,
+    /// Whether the transfer is addressed to the local wallet.
+    for_local_wallet: bool,
+    claimed: Vec<ClaimedRedemption>,
+}
+
+#[derive(Debug, Serialize)]
+struct ClaimedRedemption {
+    unique_pubkey: String,
+    /// The amount the parent spends on the network give to the redemption.
+    amount: Option<String>,
+    parent_spends: Vec<ParentSpend>,
+    /// Whether the resulting CashNote has been spent already.
+    already_spent: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ParentSpend {
+    address: String,
+    exists: bool,
+}
+
+/// Decodes a transfer and checks it against the local wallet and the network, without depositing it.
+pub async fn inspect_transfer(
+    transfer: String,
+    is_file: bool,
+    client: &Client,
+    root_dir: &Path,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let transfer = if is_file {
+        std::fs::read_to_string(transfer)?.trim().to_string()
+    } else {
+        transfer
+    };
+    let transfer = Transfer::from_hex(&transfer)?;
+    let wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
+
+    let (kind, redemptions, recipient) = match &transfer {
+        Transfer::Encrypted(cyphers) => ("encrypted", cyphers.len(), wallet.address()),
+        Transfer::NetworkRoyalties(redemptions) => (
+            "network_royalties",
+            redemptions.len(),
+            *NETWORK_ROYALTIES_PK,
+        ),
+        Transfer::Unencrypted(redemptions) => ("unencrypted", redemptions.len(), wallet.address()),
+    };
+    // only the recipient can decrypt the redemptions of an encrypted transfer
+    let decrypted = transfer.cashnote_redemptions(wallet.key());
+    let mut inspection = TransferInspection {
+        kind,
+        redemptions,
+        for_local_wallet: decrypted.is_ok() && recipient == wallet.address(),
+        claimed: vec![],
+    };
+
+    for redemption in decrypted.unwrap_or_default() {
+        let unique_pubkey = recipient.new_unique_pubkey(&redemption.derivation_index);
+        // like `CashNote::value`, the amount is the sum of what each parent spend gives to it
+        let mut amount: Option<u64> = None;
+        let mut parent_spends = vec![];
+        for address in &redemption.parent_spends {
+            let spend = client.get_spend_from_network(*address).await.ok();
+            if let Some(value) = spend
+                .as_ref()
+                .and_then(|spend| spend.spend.get_output_amount(&unique_pubkey))
+            {
+                amount = Some(amount.unwrap_or_default() + value.as_nano());
+            }
+            parent_spends.push(ParentSpend {
+                address: address.to_hex(),
+                exists: spend.is_some(),
+            });
+        }
+        let already_spent = client
+            .peek_a_spend(SpendAddress::from_unique_pubkey(&unique_pubkey))
+            .await
+            .is_ok();
+
+        inspection.claimed.push(ClaimedRedemption {
+            unique_pubkey: unique_pubkey.to_hex(),
+            amount: amount.map(|amount| NanoTokens::from(amount).to_string()),
+            parent_spends,
+            already_spent,
+        });
+    }
+
+    if output_format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&inspection)?);
+        return Ok(());
+    }
+
+    println!("Transfer kind: {}", inspection.kind);
+    println!("Redemptions: {}", inspection.redemptions);
+    if !inspection.for_local_wallet {
+        println!(
+            "This transfer is not addressed to the local wallet {}",
+            wallet.address().to_hex()
+        );
+        if inspection.claimed.is_empty() {
+            return Ok(());
+        }
+    } else {
+        println!("This transfer is addressed to the local wallet.");
+    }
+    for (i, claimed) in inspection.claimed.iter().enumerate() {
+        println!("\nRedemption #{i}: {}", claimed.unique_pubkey);
+        match &claimed.amount {
+            Some(amount) => println!("\tClaimed amount: {amount}"),
+            None => println!("\tClaimed amount: unknown, no parent spend found on the network"),
+        }
+        for parent in &claimed.parent_spends {
+            println!(
+                "\tParent spend {}: {}",
+                parent.address,
+                if parent.exists {
+                    "found on the network"
+                } else {
+                    "NOT found on the network"
+                }
+            );
+        }
+        if claimed.already_spent {
+            println!("\tThe CashNote has already been spent.");
+        }
+    }
+    Ok(())
+}
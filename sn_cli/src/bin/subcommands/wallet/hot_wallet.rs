@@ -7,26 +7,44 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    audit::{audit, verify_spend_at},
-    helpers::{get_faucet, receive},
-    WalletApiHelper,
+    audit::{audit, verify_spend_at, DagExport},
+    helpers::{fiat_rate, get_faucet, inspect_transfer, receive},
+    password, WalletApiHelper,
 };
 use crate::{get_stdin_password_response, get_stdin_response};
 
-use bls::SecretKey;
+use bls::{SecretKey, Signature, SIG_SIZE};
 use clap::Parser;
-use color_eyre::{eyre::eyre, Result};
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
 use dialoguer::Confirm;
-use sn_cli::utils::is_valid_key_hex;
-use sn_client::acc_packet::{load_or_create_mnemonic, secret_key_from_mnemonic};
+use sn_cli::{
+    accounting::{export_history, AccountMapping, HistoryFormat},
+    pricing::annotate,
+    utils::is_valid_key_hex,
+    OutputFormat,
+};
+use sn_client::acc_packet::{
+    load_or_create_mnemonic, secret_key_from_mnemonic,
+    user_secret::{parse_mnemonic, write_mnemonic_to_disk},
+    wallet_mnemonic,
+};
 use sn_client::transfers::{
-    HotWallet, MainPubkey, MainSecretKey, NanoTokens, Transfer, TransferError, UnsignedTransaction,
-    WalletError,
+    verify_message, HistoryFilter, HotWallet, MainPubkey, MainSecretKey, NanoTokens, Recipient,
+    SpendAddress, SpendApprovalConfig, Transfer, TransferError, UnsignedTransaction, WalletError,
+    WalletFileFormat, WatchOnlyWallet, DEFAULT_APPROVAL_TIMEOUT, WALLET_DIR_NAME,
 };
 use sn_client::{
-    acc_packet::load_account_wallet_or_create_with_mnemonic, Client, Error as ClientError,
+    acc_packet::load_account_wallet_or_create_with_mnemonic, deliver_to_inbox, Client,
+    DagExportFilter, DagExportFormat, Error as ClientError, Inbox, InboxEvent,
+    DEFAULT_INBOX_POLL_INTERVAL,
+};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
 };
-use std::{path::Path, str::FromStr};
 
 // Please do not remove the blank lines in these doc comments.
 // They are used for inserting line breaks when the help menu is rendered in the UI.
@@ -41,9 +59,22 @@ pub enum WalletCmds {
         /// in order to read the balance of multiple nodes at once.
         #[clap(long)]
         peer_id: Vec<String>,
+        /// Also print the estimated value of the balance in a fiat currency, e.g. 'usd'.
+        ///
+        /// The exchange rates are fetched from the providers set in the config file, CoinGecko by
+        /// default. See 'fiat_rates' and 'fiat_rate_url' with `safe config set`.
+        #[clap(long, value_name = "CURRENCY")]
+        fiat: Option<String>,
     },
+    /// List the wallets, marking the one selected with '--wallet' or the 'default_wallet' config.
+    List,
     /// Create a hot wallet.
     Create {
+        /// The name of the wallet to create, for use with the '--wallet' flag.
+        ///
+        /// If not provided, the wallet selected with '--wallet' (or the default one) is created.
+        #[clap(name = "name")]
+        name: Option<String>,
         /// Optional flag to not replace existing wallet.
         #[clap(long, action)]
         no_replace: bool,
@@ -53,6 +84,12 @@ pub enum WalletCmds {
         /// Optional hex-encoded main secret key.
         #[clap(long, short, name = "key")]
         key: Option<String>,
+        /// Optional mnemonic of 12 or 24 words to restore the wallet from, e.g. "word1 word2 ...".
+        ///
+        /// If neither this nor a key is provided, the existing mnemonic of the wallet is used, or a
+        /// new one is generated. It can be exported with 'wallet mnemonic'.
+        #[clap(long, short, name = "mnemonic", conflicts_with = "key")]
+        mnemonic: Option<String>,
         /// Optional derivation passphrase to protect the mnemonic,
         /// it's not the source of the entropy for the mnemonic generation.
         /// The mnemonic+passphrase will be the seed. See detail at
@@ -60,6 +97,9 @@ pub enum WalletCmds {
         #[clap(long, short, name = "derivation")]
         derivation_passphrase: Option<String>,
         /// Optional password to encrypt the wallet with.
+        ///
+        /// If not provided, it is read from the SAFE_WALLET_PASSWORD env var or stdin (when piped),
+        /// before prompting for it.
         #[clap(long, short)]
         password: Option<String>,
     },
@@ -74,19 +114,53 @@ pub enum WalletCmds {
         /// A signature of the safe wallet address, made by the maidsafecoin
         /// address.
         signature: Option<String>,
+        /// How many times to retry a failed claim, waiting longer after each attempt.
+        ///
+        /// The faucet is only asked again if it couldn't be reached or had a server error. Verifying
+        /// the received transfer with the network is retried separately.
+        #[clap(long, default_value_t = 3)]
+        retries: u32,
     },
     /// Send a transfer.
     ///
     /// This command will create a new transfer and encrypt it for the recipient.
     /// This encrypted transfer can then be shared with the recipient, who can then
     /// use the 'receive' command to claim the funds.
+    ///
+    /// With '--inbox', the transfer is delivered to the inbox of the recipient instead. With
+    /// '--notify', a notice of the transfer is also published for the recipient to learn about
+    /// it if listening.
     Send {
         /// The number of SafeNetworkTokens to send.
         #[clap(name = "amount")]
         amount: String,
-        /// Hex-encoded public address of the recipient.
+        /// Hex-encoded public address of the recipient, or its label in the address book of the
+        /// wallet.
         #[clap(name = "to")]
         to: String,
+        /// Deliver the transfer to the inbox the recipient created, instead of printing it.
+        #[clap(long)]
+        inbox: bool,
+        /// Publish a notice of the transfer, for the recipient to receive it right away if
+        /// listening with 'listen'.
+        #[clap(long)]
+        notify: bool,
+    },
+    /// Send the whole balance of the wallet to another address, e.g. to move it to cold storage.
+    ///
+    /// By default this is done offline: the transfer is printed to be delivered to the recipient
+    /// out-of-band, and the spends are broadcasted the next time the wallet sends tokens.
+    /// Use '--online' to broadcast them right away.
+    Sweep {
+        /// Hex-encoded public address of the recipient.
+        #[clap(long)]
+        to: String,
+        /// The number of SafeNetworkTokens to keep in the wallet.
+        #[clap(long)]
+        keep: Option<String>,
+        /// Broadcast the spends to the network straight away.
+        #[clap(long)]
+        online: bool,
     },
     /// Signs a transaction to be then broadcasted to the network.
     Sign {
@@ -97,6 +171,32 @@ pub enum WalletCmds {
         #[clap(long, name = "force", default_value = "false")]
         force: bool,
     },
+    /// Sign a message with the wallet's key, to prove ownership of the wallet.
+    ///
+    /// The signature is printed hex-encoded, along with the wallet address it can be verified with.
+    SignMessage {
+        /// The message to sign.
+        #[clap(name = "message", required_unless_present = "file")]
+        message: Option<String>,
+        /// Sign the contents of a file instead.
+        #[clap(long, value_name = "PATH", conflicts_with = "message")]
+        file: Option<PathBuf>,
+    },
+    /// Verify that a message was signed with the key of a wallet, using 'sign-message'.
+    VerifyMessage {
+        /// The hex-encoded signature.
+        #[clap(name = "signature")]
+        signature: String,
+        /// The hex-encoded address of the wallet that signed the message.
+        #[clap(name = "address")]
+        address: String,
+        /// The message that was signed.
+        #[clap(name = "message", required_unless_present = "file")]
+        message: Option<String>,
+        /// Verify the signature of the contents of a file instead.
+        #[clap(long, value_name = "PATH", conflicts_with = "message")]
+        file: Option<PathBuf>,
+    },
     /// Receive a transfer created by the 'send' or 'broadcast' command.
     Receive {
         /// Read the encrypted transfer from a file.
@@ -106,6 +206,33 @@ pub enum WalletCmds {
         #[clap(name = "transfer")]
         transfer: String,
     },
+    /// Listen to the notices of the transfers to the wallet, depositing them as they come in.
+    ///
+    /// The senders publish a notice after sending tokens with 'send --notify'. Only the transfers
+    /// sent while listening are received this way.
+    Listen,
+    /// Create the inbox of the wallet, for others to deliver transfers to with 'send --inbox'.
+    CreateInbox,
+    /// Receive the transfers delivered to the inbox of the wallet.
+    ///
+    /// Once its register is full, the inbox is rotated to a new one, paid for from the wallet.
+    Inbox {
+        /// Keep receiving the transfers as they are delivered, until interrupted.
+        #[clap(long)]
+        watch: bool,
+    },
+    /// Decode a transfer without depositing it.
+    ///
+    /// Prints the number of redemptions it holds, whether it is addressed to the local wallet, the
+    /// amounts it claims and whether the spends it refers to exist on the Network.
+    InspectTransfer {
+        /// Read the encrypted transfer from a file.
+        #[clap(long, default_value = "false")]
+        file: bool,
+        /// Encrypted transfer.
+        #[clap(name = "transfer")]
+        transfer: String,
+    },
     /// Verify a spend on the Network.
     Verify {
         /// The Network address or hex encoded UniquePubkey of the Spend to verify
@@ -131,14 +258,173 @@ pub enum WalletCmds {
         /// EXPERIMENTAL redeem all royalties
         #[clap(long, default_value = "false")]
         royalties: bool,
+        /// Display the royalties paid since the last audit, and in total per royalty key.
+        /// The statistics are kept in the wallet dir, so that each audit only counts the new spends.
+        #[clap(long, default_value = "false")]
+        royalty_stats: bool,
         /// Hex string of the Foundation SK.
         /// Providing this key allow displaying rewards statistics gathered from the DAG.
         #[clap(long, name = "sk_str")]
         sk_str: Option<String>,
+        /// EXPERIMENTAL Export the Audit DAG in the given format: dot, graphml or jsonl.
+        /// The spends are annotated with their amounts, reasons and faults.
+        #[clap(long, value_name = "FORMAT")]
+        export: Option<DagExportFormat>,
+        /// The file to export the DAG to, stdout by default.
+        #[clap(long, requires = "export")]
+        export_path: Option<PathBuf>,
+        /// Only export the transfers from or to this spend address.
+        /// Can be given multiple times.
+        #[clap(long = "filter-address", value_name = "ADDRESS", requires = "export")]
+        filter_addresses: Vec<String>,
+        /// Only export the transfers of at least this amount of tokens.
+        #[clap(long, requires = "export")]
+        min_amount: Option<String>,
+        /// Only export the transfers of at most this amount of tokens.
+        #[clap(long, requires = "export")]
+        max_amount: Option<String>,
+        /// Only export the transfers made by spends at least this many transfers away from Genesis.
+        /// Spends have no timestamp, so this orders them in time.
+        #[clap(long, requires = "export")]
+        min_generation: Option<usize>,
+        /// Only export the transfers made by spends at most this many transfers away from Genesis.
+        #[clap(long, requires = "export")]
+        max_generation: Option<usize>,
+    },
+    /// Print the history of the tokens received and sent by the wallet, oldest first.
+    ///
+    /// Only the activity since the history started being recorded is printed.
+    History {
+        /// Only print the entries recorded on or after this date, e.g. '2024-05-01'.
+        #[clap(long, value_name = "DATE", value_parser = parse_date)]
+        since: Option<u64>,
+        /// Only print the entries recorded before this date, e.g. '2024-06-01'.
+        #[clap(long, value_name = "DATE", value_parser = parse_date)]
+        until: Option<u64>,
+        /// Only print the tokens sent to this hex-encoded main public key.
+        #[clap(long, value_name = "PUBLIC_KEY")]
+        counterparty: Option<String>,
+        /// Skip this many of the matching entries.
+        #[clap(long, default_value_t = 0)]
+        offset: usize,
+        /// Print this many of the matching entries at most.
+        #[clap(long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Export the history of the tokens received and sent by the wallet, to be imported by
+    /// accounting software.
+    ///
+    /// Only the activity since the history started being recorded is exported.
+    ExportHistory {
+        /// The format to export to: csv, ofx, beancount or ledger.
+        #[clap(long, value_name = "FORMAT")]
+        format: HistoryFormat,
+        /// The file to export the history to, stdout by default.
+        #[clap(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Book the entries to another account than the default one, e.g.
+        /// 'storage=Expenses:Hosting'. Can be given multiple times.
+        ///
+        /// The keys are wallet, income, transfers, storage and fees, plus commodity to set the
+        /// currency code of the amounts.
+        #[clap(long = "account", value_name = "KEY=ACCOUNT")]
+        accounts: Vec<String>,
+    },
+    /// Print the wallet balance, its unconfirmed spends and available cash notes.
+    Status {
+        /// Also print the estimated value of the balance in a fiat currency, e.g. 'usd'.
+        #[clap(long, value_name = "CURRENCY")]
+        fiat: Option<String>,
     },
-    Status,
     /// Encrypt wallet with a password.
+    ///
+    /// The password is read from the SAFE_WALLET_PASSWORD env var or stdin (when piped), before
+    /// prompting for it. Unlocking an encrypted wallet reads it the same way; it can also be cached
+    /// in the OS keyring by setting 'password_cache_secs' with `safe config set`.
     Encrypt,
+    /// Change the password of an encrypted wallet.
+    ///
+    /// The current password is read the same way as for unlocking the wallet, then the new one as
+    /// for 'encrypt'.
+    ChangePassword,
+    /// Remove the password of an encrypted wallet, storing its secret key unencrypted again.
+    Decrypt,
+    /// Print the mnemonic the wallet was created from, to write it down as its recovery phrase.
+    ///
+    /// The wallet can be restored from it with 'wallet create --mnemonic', using the same
+    /// derivation passphrase, if any. Anyone knowing it can spend the funds of the wallet.
+    Mnemonic,
+    /// Print the serialization of the wallet file, or convert it to another one.
+    ///
+    /// The formats are 'legacy' (compact MsgPack, the default), 'msgpack' (MsgPack with the field
+    /// names) and 'json', to inspect the wallet with standard tools. The wallet keeps the format
+    /// it is converted to.
+    FileFormat {
+        /// The format to convert the wallet file to.
+        #[clap(value_parser = parse_wallet_file_format)]
+        format: Option<WalletFileFormat>,
+    },
+    /// Require an approval for the spends of the wallet above an amount, or print the setting.
+    ///
+    /// A spend sending more than the threshold out of the wallet, counting the spends made
+    /// without an approval over the last day, by the CLI or any application using the wallet, is
+    /// sent to the approver listening on the socket, e.g. 'wallet approve', and denied unless
+    /// approved before the timeout. The decisions are logged to 'spend_approvals.log' in the
+    /// wallet dir.
+    SpendApproval {
+        /// The amount above which a spend needs an approval, e.g. '1.5'.
+        #[clap(long)]
+        threshold: Option<String>,
+        /// The socket the approver listens on. Defaults to one in the wallet dir.
+        #[clap(long, requires = "threshold")]
+        socket: Option<PathBuf>,
+        /// The seconds the approver has to answer.
+        #[clap(long = "approval-timeout", default_value_t = DEFAULT_APPROVAL_TIMEOUT.as_secs(), requires = "threshold")]
+        timeout: u64,
+        /// Stop requiring approvals.
+        #[clap(long, conflicts_with = "threshold")]
+        disable: bool,
+    },
+    /// Label the recipients the wallet sends to, for 'wallet send' to be given their label.
+    #[clap(subcommand)]
+    AddressBook(AddressBookCmds),
+    /// Approve or deny the spends awaiting approval, prompting for each of them.
+    Approve {
+        /// The socket to listen on, instead of the one set with 'wallet spend-approval'.
+        #[clap(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Serve the wallet to GUIs and scripts over JSON-RPC on a local socket, until interrupted.
+    ///
+    /// Each line sent is a JSON-RPC 2.0 request, answered with a line. The methods are 'balance',
+    /// 'address', 'send', 'deposit', 'history', 'payment_request' and 'pay'.
+    #[cfg(feature = "wallet-rpc")]
+    Rpc {
+        /// The socket to listen on, instead of 'wallet_rpc.sock' in the wallet dir.
+        #[clap(long)]
+        socket: Option<PathBuf>,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub enum AddressBookCmds {
+    /// Add a recipient to the address book under a label.
+    Add {
+        /// The label of the recipient, which cannot itself be a hex-encoded address.
+        #[clap(name = "label")]
+        label: String,
+        /// Hex-encoded public address of the recipient.
+        #[clap(name = "address")]
+        address: String,
+    },
+    /// Remove a label from the address book.
+    Remove {
+        /// The label of the recipient.
+        #[clap(name = "label")]
+        label: String,
+    },
+    /// List the labelled recipients.
+    List,
 }
 
 pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Path) -> Result<()> {
@@ -151,10 +437,11 @@ pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Pat
             }
             Ok(())
         }
-        WalletCmds::Balance { peer_id } => {
+        WalletCmds::Balance { peer_id, fiat } => {
+            let rate = fiat_rate(fiat.as_deref()).await?;
             if peer_id.is_empty() {
                 let wallet = WalletApiHelper::load_from(root_dir)?;
-                println!("{}", wallet.balance());
+                println!("{}", annotate(wallet.balance(), rate.as_ref()));
             } else {
                 let default_node_dir_path = dirs_next::data_dir()
                     .ok_or_else(|| eyre!("Failed to obtain data directory path"))?
@@ -164,15 +451,20 @@ pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Pat
                 for id in peer_id {
                     let path = default_node_dir_path.join(id);
                     let rewards = WalletApiHelper::load_from(&path)?.balance();
-                    println!("Node's rewards wallet balance (PeerId: {id}): {rewards}");
+                    println!(
+                        "Node's rewards wallet balance (PeerId: {id}): {}",
+                        annotate(rewards, rate.as_ref())
+                    );
                 }
             }
             Ok(())
         }
         WalletCmds::Create {
+            name: _,
             no_replace,
             no_password,
             key,
+            mnemonic,
             derivation_passphrase,
             password,
         } => {
@@ -194,6 +486,12 @@ pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Pat
                     return Err(eyre!("Please provide a valid secret key in hex format. It must be 64 characters long."));
                 }
             }
+            // Likewise, parse the mnemonic before stashing an existing wallet
+            let mnemonic = mnemonic
+                .as_deref()
+                .map(parse_mnemonic)
+                .transpose()
+                .map_err(|err| eyre!("Please provide a valid mnemonic of 12 or 24 words: {err}"))?;
             // Check for existing wallet
             if HotWallet::is_encrypted(root_dir) {
                 wallet_already_exists = true;
@@ -225,7 +523,13 @@ pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Pat
                 MainSecretKey::new(sk)
             } else {
                 // If no key is specified, use the mnemonic
-                let mnemonic = load_or_create_mnemonic(root_dir)?;
+                let mnemonic = match mnemonic {
+                    Some(mnemonic) => {
+                        write_mnemonic_to_disk(root_dir, &mnemonic)?;
+                        mnemonic
+                    }
+                    None => load_or_create_mnemonic(root_dir)?,
+                };
                 secret_key_from_mnemonic(mnemonic, derivation_passphrase.to_owned())?
             };
             // Ask user if they want to encrypt the wallet with a password
@@ -245,13 +549,147 @@ pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Pat
             );
             Ok(())
         }
+        WalletCmds::Mnemonic => {
+            let mnemonic = wallet_mnemonic(root_dir).map_err(|err| {
+                eyre!(
+                    "No mnemonic found for the wallet, it may have been created from a key: {err}"
+                )
+            })?;
+            println!("Mnemonic of the wallet, keep it secret as it gives access to the funds:");
+            println!("{mnemonic}");
+            Ok(())
+        }
+        WalletCmds::FileFormat { format } => {
+            let mut wallet = WatchOnlyWallet::load_from_path(&root_dir.join(WALLET_DIR_NAME))?;
+            if let Some(format) = format {
+                wallet.set_file_format(*format)?;
+                println!("Wallet file converted to {format:?}");
+            } else {
+                println!("Wallet file format: {:?}", wallet.file_format()?);
+            }
+            Ok(())
+        }
+        WalletCmds::SpendApproval {
+            threshold,
+            socket,
+            timeout,
+            disable,
+        } => spend_approval(root_dir, threshold.as_deref(), socket, *timeout, *disable),
+        WalletCmds::AddressBook(cmds) => address_book(cmds, root_dir),
+        WalletCmds::Approve { socket } => approve_spends(root_dir, socket.as_deref()),
         WalletCmds::Sign { tx, force } => sign_transaction(tx, root_dir, *force),
-        WalletCmds::Status => {
+        WalletCmds::SignMessage { message, file } => {
+            let message = read_message(message.as_deref(), file.as_deref())?;
+            let wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
+            let signature = wallet.sign_message(&message);
+            println!("Message signed by wallet {}", wallet.address().to_hex());
+            println!("Signature: {}", hex::encode(signature.to_bytes()));
+            Ok(())
+        }
+        WalletCmds::VerifyMessage {
+            signature,
+            address,
+            message,
+            file,
+        } => {
+            let message = read_message(message.as_deref(), file.as_deref())?;
+            let address = MainPubkey::from_hex(address)
+                .map_err(|err| eyre!("Error while parsing the wallet address: {err:?}"))?;
+            let signature = parse_signature(signature)?;
+            if !verify_message(&address, &message, &signature) {
+                bail!("The signature is not valid for this message and wallet address");
+            }
+            println!("The signature is valid.");
+            Ok(())
+        }
+        WalletCmds::Sweep { to, keep, .. } => sweep_offline(to, keep.as_deref(), root_dir),
+        WalletCmds::Status { fiat } => {
+            let rate = fiat_rate(fiat.as_deref()).await?;
             let mut wallet = WalletApiHelper::load_from(root_dir)?;
-            println!("{}", wallet.balance());
+            println!("{}", annotate(wallet.balance(), rate.as_ref()));
             wallet.status()?;
             Ok(())
         }
+        WalletCmds::History {
+            since,
+            until,
+            counterparty,
+            offset,
+            limit,
+        } => {
+            let filter = HistoryFilter {
+                since: *since,
+                until: *until,
+                counterparty: counterparty
+                    .as_deref()
+                    .map(MainPubkey::from_hex)
+                    .transpose()?,
+                ..Default::default()
+            };
+            let wallet = WalletApiHelper::load_from(root_dir)?;
+            let history = match &wallet {
+                WalletApiHelper::WatchOnlyWallet(w) => w.history()?,
+                WalletApiHelper::HotWallet(w) => w.history()?,
+            };
+            let entries = history.query(&filter, *offset, *limit);
+            if entries.is_empty() {
+                println!("No history entries found.");
+            }
+            for entry in entries {
+                let time = chrono::DateTime::from_timestamp(entry.time as i64, 0)
+                    .unwrap_or_default()
+                    .format("%Y-%m-%d %H:%M:%S");
+                let counterparty = entry
+                    .counterparty
+                    .map(|pk| format!(" to {}", pk.to_hex()))
+                    .unwrap_or_default();
+                println!(
+                    "{time} {:?} {} (fee {}){counterparty}",
+                    entry.kind, entry.amount, entry.fee
+                );
+            }
+            Ok(())
+        }
+        WalletCmds::ExportHistory {
+            format,
+            output,
+            accounts,
+        } => {
+            let mapping = AccountMapping::with_overrides(accounts)?;
+            let wallet = WalletApiHelper::load_from(root_dir)?;
+            let (history, address) = match &wallet {
+                WalletApiHelper::WatchOnlyWallet(w) => (w.history()?, w.address()),
+                WalletApiHelper::HotWallet(w) => (w.history()?, w.address()),
+            };
+            let entries = history.entries();
+            match output {
+                Some(path) => {
+                    let mut file = std::fs::File::create(path)?;
+                    export_history(
+                        entries,
+                        address,
+                        wallet.balance(),
+                        *format,
+                        &mapping,
+                        &mut file,
+                    )?;
+                    println!(
+                        "Exported {} history entries as {format} to {}",
+                        entries.len(),
+                        path.display()
+                    );
+                }
+                None => export_history(
+                    entries,
+                    address,
+                    wallet.balance(),
+                    *format,
+                    &mapping,
+                    &mut std::io::stdout(),
+                )?,
+            }
+            Ok(())
+        }
         WalletCmds::Encrypt => {
             println!("Encrypt your wallet with a password. WARNING: If you forget your password, you will lose access to your wallet!");
             // Ask user for a new password to encrypt the wallet with
@@ -261,6 +699,25 @@ pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Pat
             println!("Wallet successfully encrypted.");
             Ok(())
         }
+        WalletCmds::ChangePassword => {
+            let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+            let password = password::get_wallet_password(&wallet_dir, "Enter current password: ");
+            println!("Enter the new password of your wallet. WARNING: If you forget your password, you will lose access to your wallet!");
+            if let Some(new_password) = request_password(true) {
+                WalletApiHelper::change_password(root_dir, &password, &new_password)?;
+                println!("Wallet password successfully changed.");
+            }
+            Ok(())
+        }
+        WalletCmds::Decrypt => {
+            let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+            let password = password::get_wallet_password(&wallet_dir, "Enter password: ");
+            WalletApiHelper::decrypt(root_dir, &password)?;
+            println!(
+                "Wallet successfully decrypted, its secret key is no longer password protected."
+            );
+            Ok(())
+        }
         cmd => Err(eyre!("{cmd:?} requires us to be connected to the Network")),
     }
 }
@@ -270,19 +727,56 @@ pub(crate) async fn wallet_cmds(
     client: &Client,
     root_dir: &Path,
     verify_store: bool,
+    output_format: OutputFormat,
 ) -> Result<()> {
     match cmds {
-        WalletCmds::Send { amount, to } => send(amount, to, client, root_dir, verify_store).await,
+        WalletCmds::Send {
+            amount,
+            to,
+            inbox,
+            notify,
+        } => send(amount, to, inbox, notify, client, root_dir, verify_store).await,
+        WalletCmds::Sweep { to, keep, .. } => {
+            sweep(&to, keep.as_deref(), client, root_dir, verify_store).await
+        }
         WalletCmds::Receive { file, transfer } => receive(transfer, file, client, root_dir).await,
+        WalletCmds::Listen => listen(client, root_dir).await,
+        WalletCmds::CreateInbox => create_inbox(client, root_dir, verify_store).await,
+        WalletCmds::Inbox { watch } => {
+            receive_from_inbox(watch, client, root_dir, verify_store).await
+        }
+        WalletCmds::InspectTransfer { file, transfer } => {
+            inspect_transfer(transfer, file, client, root_dir, output_format).await
+        }
         WalletCmds::GetFaucet {
             url,
             maid_address,
             signature,
-        } => get_faucet(root_dir, client, url.clone(), maid_address, signature).await,
+            retries,
+        } => {
+            get_faucet(
+                root_dir,
+                client,
+                url.clone(),
+                maid_address,
+                signature,
+                retries,
+                output_format,
+            )
+            .await
+        }
         WalletCmds::Audit {
             dot,
             royalties,
+            royalty_stats,
             sk_str,
+            export,
+            export_path,
+            filter_addresses,
+            min_amount,
+            max_amount,
+            min_generation,
+            max_generation,
         } => {
             let sk_key = if let Some(s) = sk_str {
                 match SecretKey::from_hex(&s) {
@@ -296,12 +790,43 @@ pub(crate) async fn wallet_cmds(
             } else {
                 None
             };
-            audit(client, dot, royalties, root_dir, sk_key).await
+            let export = match export {
+                Some(format) => {
+                    let filter = DagExportFilter {
+                        addresses: filter_addresses
+                            .iter()
+                            .map(|addr| SpendAddress::from_str(addr))
+                            .collect::<Result<_, _>>()?,
+                        min_amount: min_amount.map(|s| NanoTokens::from_str(&s)).transpose()?,
+                        max_amount: max_amount.map(|s| NanoTokens::from_str(&s)).transpose()?,
+                        min_generation,
+                        max_generation,
+                    };
+                    Some(DagExport {
+                        format,
+                        filter,
+                        path: export_path,
+                    })
+                }
+                None => None,
+            };
+            audit(
+                client,
+                dot,
+                royalties,
+                royalty_stats,
+                root_dir,
+                sk_key,
+                export,
+            )
+            .await
         }
         WalletCmds::Verify {
             spend_address,
             genesis,
         } => verify_spend_at(spend_address, genesis, client, root_dir).await,
+        #[cfg(feature = "wallet-rpc")]
+        WalletCmds::Rpc { socket } => serve_rpc(socket, client, root_dir, verify_store).await,
         cmd => Err(eyre!(
             "{cmd:?} has to be processed before connecting to the network"
         )),
@@ -311,6 +836,8 @@ pub(crate) async fn wallet_cmds(
 async fn send(
     amount: String,
     to: String,
+    inbox: bool,
+    notify: bool,
     client: &Client,
     root_dir: &Path,
     verify_store: bool,
@@ -324,10 +851,13 @@ async fn send(
             return Err(err.into());
         }
     };
-    let to = match MainPubkey::from_hex(to) {
+    let to = match to
+        .parse::<Recipient>()
+        .and_then(|recipient| from.address_book()?.resolve(&recipient))
+    {
         Ok(to) => to,
         Err(err) => {
-            println!("Error while parsing the recipient's 'to' key: {err:?}");
+            println!("Error while parsing the recipient's 'to' key: {err}");
             return Err(err.into());
         }
     };
@@ -358,6 +888,135 @@ async fn send(
         }
     };
 
+    let transfer = Transfer::transfer_from_cash_note(&cash_note)?;
+    // the recipient learns about the funds right away if they are listening
+    if notify {
+        match client.notify_transfer(to, &transfer).await {
+            Ok(()) => println!("The recipient has been notified of the transfer."),
+            Err(err) => println!("Could not notify the recipient of the transfer: {err:?}"),
+        }
+    }
+    if inbox {
+        match deliver_to_inbox(client, to, &transfer, verify_store).await {
+            Ok(()) => {
+                println!(
+                    "The encrypted transfer has been delivered to the inbox of the recipient."
+                );
+                println!("The recipient can then use the 'inbox' command to claim the funds.");
+                return Ok(());
+            }
+            // the tokens are spent already, so the transfer must not be lost
+            Err(err) => println!("Could not deliver the transfer to the inbox due to {err:?}."),
+        }
+    }
+
+    let transfer = transfer.to_hex()?;
+    println!("The encrypted transfer has been successfully created.");
+    println!("Please share this to the recipient:\n\n{transfer}\n");
+    println!("The recipient can then use the 'receive' command to claim the funds.");
+
+    Ok(())
+}
+
+async fn listen(client: &Client, root_dir: &Path) -> Result<()> {
+    let mut wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
+    let mut notices = client.subscribe_to_transfer_notices(wallet.key()).await?;
+    println!("Listening to the transfers to this wallet. Press Ctrl+C to stop.");
+
+    while let Some(transfer) = notices.recv().await {
+        match client.receive(&transfer, &wallet).await {
+            Ok(cash_notes) => {
+                wallet.deposit_and_store_to_disk(&cash_notes)?;
+                let amount = cash_notes
+                    .iter()
+                    .try_fold(NanoTokens::zero(), |total, cash_note| {
+                        total.checked_add(cash_note.value())
+                    })
+                    .ok_or_else(|| eyre!("The received amount overflowed"))?;
+                println!(
+                    "Received {amount}. New wallet balance is {}.",
+                    wallet.balance()
+                );
+            }
+            Err(err) => println!("Could not receive a transfer: {err:?}"),
+        }
+    }
+    Ok(())
+}
+
+async fn create_inbox(client: &Client, root_dir: &Path, verify_store: bool) -> Result<()> {
+    let mut inbox = Inbox::new(client.clone(), root_dir.to_path_buf())?;
+    let cost = inbox.create(verify_store).await?;
+    println!(
+        "Created the inbox at {} for {cost}",
+        inbox.address()?.to_hex()
+    );
+    println!("Transfers can now be sent to it with 'send --inbox'.");
+    Ok(())
+}
+
+async fn receive_from_inbox(
+    watch: bool,
+    client: &Client,
+    root_dir: &Path,
+    verify_store: bool,
+) -> Result<()> {
+    let mut inbox = Inbox::new(client.clone(), root_dir.to_path_buf())?;
+    inbox.set_verify_store(verify_store);
+    let mut events = inbox.get_event_receiver();
+    let printer = tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            match event {
+                InboxEvent::Received { amount, .. } => println!("Received {amount}"),
+                InboxEvent::Rejected { entry, error } => {
+                    println!("Rejected the transfer {}: {error}", hex::encode(entry.0))
+                }
+            }
+        }
+    });
+
+    if watch {
+        println!("Receiving the transfers as they are delivered. Press Ctrl+C to stop.");
+        inbox.run(DEFAULT_INBOX_POLL_INTERVAL).await?;
+    } else {
+        let cash_notes = inbox.receive().await?;
+        drop(inbox);
+        printer.await?;
+        let wallet = HotWallet::load_from(root_dir)?;
+        println!(
+            "Received {} cash notes. New wallet balance is {}.",
+            cash_notes.len(),
+            wallet.balance()
+        );
+    }
+    Ok(())
+}
+
+async fn sweep(
+    to: &str,
+    keep: Option<&str>,
+    client: &Client,
+    root_dir: &Path,
+    verify_store: bool,
+) -> Result<()> {
+    let from = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
+    let (to, keep) = parse_sweep_args(to, keep)?;
+
+    let cash_note = match sn_client::sweep(from, keep, to, client, verify_store).await {
+        Ok(cash_note) => cash_note,
+        Err(ClientError::AmountIsZero) => {
+            println!("The balance does not exceed the amount to keep. Nothing sent.");
+            return Ok(());
+        }
+        Err(err) => {
+            println!("Failed to sweep the wallet to {to:?} due to {err:?}.");
+            return Err(err.into());
+        }
+    };
+    let wallet = HotWallet::load_from(root_dir)?;
+    println!("Swept {} to {to:?}", cash_note.value());
+    println!("New wallet balance is {}.", wallet.balance());
+
     let transfer = Transfer::transfer_from_cash_note(&cash_note)?.to_hex()?;
     println!("The encrypted transfer has been successfully created.");
     println!("Please share this to the recipient:\n\n{transfer}\n");
@@ -366,6 +1025,74 @@ async fn send(
     Ok(())
 }
 
+fn sweep_offline(to: &str, keep: Option<&str>, root_dir: &Path) -> Result<()> {
+    let mut wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
+    let (to, keep) = parse_sweep_args(to, keep)?;
+
+    let cash_notes = wallet.local_sweep(to, keep)?;
+    println!("New wallet balance is {}.", wallet.balance());
+    println!("Please share the following transfer(s) to the recipient:\n");
+    for cash_note in cash_notes.iter() {
+        println!(
+            "{}\n",
+            Transfer::transfer_from_cash_note(cash_note)?.to_hex()?
+        );
+    }
+    println!(
+        "The spends have not been broadcasted yet, so the recipient will only be able to 'receive' \
+        the funds once this wallet sends them to the network. Use '--online' to do it right away."
+    );
+
+    Ok(())
+}
+
+fn parse_sweep_args(to: &str, keep: Option<&str>) -> Result<(MainPubkey, NanoTokens)> {
+    let to = MainPubkey::from_hex(to)
+        .map_err(|err| eyre!("Error while parsing the recipient's 'to' key: {err:?}"))?;
+    let keep = match keep {
+        Some(keep) => NanoTokens::from_str(keep)
+            .map_err(|err| eyre!("The amount to keep cannot be parsed: {err}"))?,
+        None => NanoTokens::zero(),
+    };
+    Ok((to, keep))
+}
+
+/// The message to sign or verify, given as an argument or read from a file.
+fn read_message(message: Option<&str>, file: Option<&Path>) -> Result<Vec<u8>> {
+    match (message, file) {
+        (_, Some(file)) => std::fs::read(file)
+            .map_err(|err| eyre!("Failed to read the message from {file:?}: {err}")),
+        (Some(message), None) => Ok(message.as_bytes().to_vec()),
+        (None, None) => bail!("Either a message or a file to read it from is required"),
+    }
+}
+
+/// Parses a date, e.g. '2024-05-01', into the seconds since the Unix epoch at its start.
+fn parse_date(date: &str) -> Result<u64> {
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|err| eyre!("The date {date:?} is not a valid YYYY-MM-DD date: {err}"))?;
+    let time = date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp();
+    u64::try_from(time).map_err(|_| eyre!("The date is before the Unix epoch"))
+}
+
+fn parse_wallet_file_format(format: &str) -> Result<WalletFileFormat> {
+    match format.to_lowercase().as_str() {
+        "legacy" => Ok(WalletFileFormat::Legacy),
+        "msgpack" => Ok(WalletFileFormat::MsgPack),
+        "json" => Ok(WalletFileFormat::Json),
+        _ => bail!("Unknown wallet file format {format:?}: expected legacy, msgpack or json"),
+    }
+}
+
+fn parse_signature(signature: &str) -> Result<Signature> {
+    let bytes = hex::decode(signature.trim())
+        .map_err(|err| eyre!("The signature is not valid hex: {err}"))?;
+    let bytes: [u8; SIG_SIZE] = bytes
+        .try_into()
+        .map_err(|_| eyre!("The signature must be {SIG_SIZE} bytes long"))?;
+    Signature::from_bytes(bytes).map_err(|err| eyre!("The signature cannot be parsed: {err}"))
+}
+
 fn sign_transaction(tx: &str, root_dir: &Path, force: bool) -> Result<()> {
     let wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
 
@@ -410,6 +1137,10 @@ fn sign_transaction(tx: &str, root_dir: &Path, force: bool) -> Result<()> {
 }
 
 fn request_password(required: bool) -> Option<String> {
+    if let Some(password) = password::non_interactive_password() {
+        return Some(password).filter(|password| !password.is_empty());
+    }
+
     'outer: loop {
         let prompt = if required {
             "Enter password: "
@@ -450,3 +1181,155 @@ fn request_password(required: bool) -> Option<String> {
         break None;
     }
 }
+
+fn address_book(cmds: &AddressBookCmds, root_dir: &Path) -> Result<()> {
+    let wallet = WatchOnlyWallet::load_from_path(&root_dir.join(WALLET_DIR_NAME))?;
+    let mut address_book = wallet.address_book()?;
+    match cmds {
+        AddressBookCmds::Add { label, address } => {
+            let pubkey = MainPubkey::from_hex(address)
+                .map_err(|err| eyre!("Error while parsing the recipient's address: {err:?}"))?;
+            address_book.add(label, pubkey)?;
+            println!("Added {} as {:?}", pubkey.to_hex(), label.trim());
+        }
+        AddressBookCmds::Remove { label } => match address_book.remove(label)? {
+            Some(pubkey) => println!("Removed {:?}, which was {}", label.trim(), pubkey.to_hex()),
+            None => println!(
+                "No recipient labelled {:?} in the address book",
+                label.trim()
+            ),
+        },
+        AddressBookCmds::List => {
+            if address_book.is_empty() {
+                println!("The address book is empty.");
+            }
+            for (label, pubkey) in address_book.list() {
+                println!("{label}: {}", pubkey.to_hex());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn spend_approval(
+    root_dir: &Path,
+    threshold: Option<&str>,
+    socket: &Option<PathBuf>,
+    timeout: u64,
+    disable: bool,
+) -> Result<()> {
+    let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+    if disable {
+        SpendApprovalConfig::remove(&wallet_dir)?;
+        println!("Spends no longer need an approval.");
+        return Ok(());
+    }
+    let Some(threshold) = threshold else {
+        match SpendApprovalConfig::load(&wallet_dir)? {
+            Some(config) => println!(
+                "Spends above {} need an approval on {:?}, within {}s.",
+                config.threshold, config.socket_path, config.timeout_secs
+            ),
+            None => println!("Spends need no approval."),
+        }
+        return Ok(());
+    };
+
+    let threshold = NanoTokens::from_str(threshold)?;
+    let mut config = SpendApprovalConfig::new(&wallet_dir, threshold);
+    if let Some(socket) = socket {
+        config.socket_path = socket.clone();
+    }
+    config.timeout_secs = timeout;
+    config.store(&wallet_dir)?;
+    println!(
+        "Spends above {threshold} now need an approval on {:?}. Run 'wallet approve' to answer them.",
+        config.socket_path
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+fn approve_spends(root_dir: &Path, socket: Option<&Path>) -> Result<()> {
+    use sn_client::transfers::ApprovalListener;
+
+    let socket_path = match socket {
+        Some(socket) => socket.to_path_buf(),
+        None => SpendApprovalConfig::load(&root_dir.join(WALLET_DIR_NAME))?
+            .map(|config| config.socket_path)
+            .ok_or_else(|| {
+                eyre!("Spends need no approval, set it up with 'wallet spend-approval' first")
+            })?,
+    };
+    let listener = ApprovalListener::bind(&socket_path)?;
+    println!("Awaiting the spends to approve on {socket_path:?}...");
+    loop {
+        let pending = match listener.next_request() {
+            Ok(pending) => pending,
+            Err(err) => {
+                println!("Ignoring an invalid request: {err}");
+                continue;
+            }
+        };
+        let request = &pending.request;
+        println!(
+            "\nSpend {} of {} by wallet {}",
+            request.id, request.amount, request.wallet
+        );
+        for (recipient, amount) in &request.recipients {
+            println!("\t{amount} to {recipient}");
+        }
+        if request.storage_payments > 0 {
+            println!(
+                "\tpaying for the storage of {} records",
+                request.storage_payments
+            );
+        }
+        let approved = Confirm::new()
+            .with_prompt("Approve this spend?")
+            .default(false)
+            .interact()?;
+        // the wallet may have given up waiting meanwhile
+        if let Err(err) = pending.respond(approved) {
+            println!("Could not answer the wallet: {err}");
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn approve_spends(_root_dir: &Path, _socket: Option<&Path>) -> Result<()> {
+    bail!("Spend approvals are not supported on this platform")
+}
+
+#[cfg(all(feature = "wallet-rpc", unix))]
+async fn serve_rpc(
+    socket: Option<PathBuf>,
+    client: &Client,
+    root_dir: &Path,
+    verify_store: bool,
+) -> Result<()> {
+    use sn_client::{WalletRpc, WALLET_RPC_SOCKET_FILE};
+
+    let wallet = load_account_wallet_or_create_with_mnemonic(root_dir, None)?;
+    let socket_path =
+        socket.unwrap_or_else(|| wallet.wo_wallet().wallet_dir().join(WALLET_RPC_SOCKET_FILE));
+    println!(
+        "Serving wallet {} on {socket_path:?}. Press Ctrl+C to stop.",
+        wallet.address().to_hex()
+    );
+    WalletRpc::new(client.clone(), wallet)
+        .with_verify_store(verify_store)
+        .serve(&socket_path)
+        .await?;
+    Ok(())
+}
+
+#[cfg(all(feature = "wallet-rpc", not(unix)))]
+async fn serve_rpc(
+    _socket: Option<PathBuf>,
+    _client: &Client,
+    _root_dir: &Path,
+    _verify_store: bool,
+) -> Result<()> {
+    bail!("The wallet RPC is not supported on this platform")
+}
@@ -0,0 +1,137 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::get_stdin_password_response;
+use keyring::Entry;
+use sn_cli::CliConfig;
+use std::{
+    io::{self, IsTerminal},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Env var that can hold the wallet password, for non-interactive use.
+pub(crate) const WALLET_PASSWORD_ENV_VAR: &str = "SAFE_WALLET_PASSWORD";
+
+/// Service name under which passwords are cached in the OS keyring.
+const KEYRING_SERVICE: &str = "safe-cli";
+
+/// Returns the password given through `SAFE_WALLET_PASSWORD` or piped through stdin, if any.
+pub(crate) fn non_interactive_password() -> Option<String> {
+    if let Ok(password) = std::env::var(WALLET_PASSWORD_ENV_VAR) {
+        debug!("Using the wallet password from {WALLET_PASSWORD_ENV_VAR}");
+        return Some(password.trim().to_owned());
+    }
+
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return None;
+    }
+    let mut buffer = String::new();
+    match stdin.read_line(&mut buffer) {
+        Ok(n) if n > 0 => {
+            debug!("Using the wallet password piped through stdin");
+            Some(buffer.trim().to_owned())
+        }
+        _ => None,
+    }
+}
+
+/// Gets the password of the encrypted wallet at `wallet_dir`.
+///
+/// It is looked up in `SAFE_WALLET_PASSWORD`, stdin (when piped) and the OS keyring, in that order,
+/// before prompting the user for it without echo.
+pub(crate) fn get_wallet_password(wallet_dir: &Path, prompt: &str) -> String {
+    if let Some(password) = non_interactive_password() {
+        return password;
+    }
+    if let Some(password) = cached_password(wallet_dir) {
+        debug!("Using the cached password for wallet at {wallet_dir:?}");
+        return password;
+    }
+    get_stdin_password_response(prompt)
+}
+
+/// Keeps the password of the wallet at `wallet_dir` in the OS keyring for `password_cache_secs`,
+/// if that is set in the config file. It should only be called once the password was verified.
+pub(crate) fn cache_password(wallet_dir: &Path, password: &str) {
+    let Some(cache_duration) = cache_duration() else {
+        return;
+    };
+    let Some(entry) = keyring_entry(wallet_dir) else {
+        return;
+    };
+    let expiry = (SystemTime::now() + cache_duration)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if let Err(err) = entry.set_password(&format!("{expiry}:{password}")) {
+        warn!("Failed to cache the wallet password in the OS keyring: {err}");
+    }
+}
+
+/// Removes any password cached for the wallet at `wallet_dir`.
+pub(crate) fn forget_password(wallet_dir: &Path) {
+    if let Some(entry) = keyring_entry(wallet_dir) {
+        match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(err) => warn!("Failed to remove the cached wallet password: {err}"),
+        }
+    }
+}
+
+fn cached_password(wallet_dir: &Path) -> Option<String> {
+    cache_duration()?;
+    let entry = keyring_entry(wallet_dir)?;
+    let secret = match entry.get_password() {
+        Ok(secret) => secret,
+        Err(keyring::Error::NoEntry) => return None,
+        Err(err) => {
+            warn!("Failed to read the cached wallet password: {err}");
+            return None;
+        }
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    match secret.split_once(':') {
+        Some((expiry, password)) if expiry.parse::<u64>().is_ok_and(|expiry| expiry > now) => {
+            Some(password.to_owned())
+        }
+        _ => {
+            debug!("Cached password for wallet at {wallet_dir:?} has expired");
+            forget_password(wallet_dir);
+            None
+        }
+    }
+}
+
+fn cache_duration() -> Option<Duration> {
+    let config = CliConfig::default_path()
+        .and_then(|path| CliConfig::load_from(&path))
+        .ok()?;
+    config
+        .password_cache_secs
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+fn keyring_entry(wallet_dir: &Path) -> Option<Entry> {
+    let wallet_dir = wallet_dir
+        .canonicalize()
+        .unwrap_or_else(|_| wallet_dir.to_path_buf());
+    match Entry::new(KEYRING_SERVICE, &wallet_dir.display().to_string()) {
+        Ok(entry) => Some(entry),
+        Err(err) => {
+            warn!("OS keyring is not available: {err}");
+            None
+        }
+    }
+}
@@ -0,0 +1,358 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    pricing::FixedRates,
+    profile::{NetworkProfile, ProfileSettings},
+};
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::debug;
+
+/// The name of the file holding the CLI configuration.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// The keys that can be read/written with `safe config get/set`.
+pub const CONFIG_KEYS: [&str; 10] = [
+    "network_profile",
+    "network_contacts",
+    "default_wallet",
+    "batch_size",
+    "connection_timeout",
+    "output_format",
+    "password_cache_secs",
+    "fiat_rates",
+    "fiat_rate_url",
+    "fiat_rate_pointer",
+];
+
+/// How the CLI should render its results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = color_eyre::eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(eyre!(
+                "Invalid output format {s:?}. Valid values are 'text' or 'json'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Settings read from `config.toml`.
+///
+/// Every field is optional; values given on the command line always take precedence over the ones
+/// found in the file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CliConfig {
+    /// The network to run against when `--network` is not given, the mainnet if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_profile: Option<NetworkProfile>,
+    /// Bootstrap peers of the mainnet, in the same formats accepted by the `--peer` argument.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_contacts: Vec<String>,
+    /// The wallet to use when none is specified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_wallet: Option<String>,
+    /// The number of chunks handled in parallel during uploads and downloads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<usize>,
+    /// The maximum number of seconds to wait for a connection to the network.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_timeout: Option<u64>,
+    /// How results are printed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    /// For how many seconds an encrypted wallet's password is kept in the OS keyring.
+    /// The password is not cached if this is unset or zero.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_cache_secs: Option<u64>,
+    /// Fixed exchange rates used by `--fiat`, as comma-separated `currency=rate` pairs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiat_rates: Option<String>,
+    /// The url of the API the exchange rates are fetched from, `{currency}` being replaced with
+    /// the requested currency. CoinGecko is used if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiat_rate_url: Option<String>,
+    /// The JSON pointer locating the rate in the responses of `fiat_rate_url`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiat_rate_pointer: Option<String>,
+    /// The settings of the network profiles, edited in their `[profiles.<name>]` tables.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<NetworkProfile, ProfileSettings>,
+}
+
+impl CliConfig {
+    /// The default location of the config file, e.g. `$HOME/.config/safe/config.toml` on Linux.
+    pub fn default_path() -> Result<PathBuf> {
+        let config_dir =
+            dirs_next::config_dir().ok_or_else(|| eyre!("Config directory is not obtainable"))?;
+        Ok(config_dir.join("safe").join(CONFIG_FILE_NAME))
+    }
+
+    /// Loads the config from the given path, returning the defaults if the file doesn't exist.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            debug!("No config file found at {path:?}, using defaults");
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let config = toml::from_str(&contents)
+            .map_err(|err| eyre!("Failed to parse config file {path:?}: {err}"))?;
+        debug!("Loaded config from {path:?}: {config:?}");
+        Ok(config)
+    }
+
+    /// Writes the config to the given path, creating the parent directories if required.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let contents = toml::to_string_pretty(self)
+            .map_err(|err| eyre!("Failed to serialize config: {err}"))?;
+        std::fs::write(path, contents)?;
+        debug!("Stored config to {path:?}");
+        Ok(())
+    }
+
+    /// Returns the value stored under `key`, or `None` if it is not set.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        let value = match key {
+            "network_profile" => self.network_profile.map(|v| v.to_string()),
+            "network_contacts" => {
+                if self.network_contacts.is_empty() {
+                    None
+                } else {
+                    Some(self.network_contacts.join(","))
+                }
+            }
+            "default_wallet" => self.default_wallet.clone(),
+            "batch_size" => self.batch_size.map(|v| v.to_string()),
+            "connection_timeout" => self.connection_timeout.map(|v| v.to_string()),
+            "output_format" => self.output_format.map(|v| v.to_string()),
+            "password_cache_secs" => self.password_cache_secs.map(|v| v.to_string()),
+            "fiat_rates" => self.fiat_rates.clone(),
+            "fiat_rate_url" => self.fiat_rate_url.clone(),
+            "fiat_rate_pointer" => self.fiat_rate_pointer.clone(),
+            _ => bail!(unknown_key(key)),
+        };
+        Ok(value)
+    }
+
+    /// Sets `key` to `value`. An empty value unsets the key.
+    ///
+    /// `network_contacts` takes a comma-separated list.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        match key {
+            "network_profile" => self.network_profile = parse_optional(value)?,
+            "network_contacts" => {
+                self.network_contacts = value
+                    .split(',')
+                    .map(|c| c.trim())
+                    .filter(|c| !c.is_empty())
+                    .map(|c| {
+                        sn_peers_acquisition::parse_peer_addr(c)
+                            .map(|_| c.to_string())
+                            .map_err(|err| eyre!("Invalid network contact {c:?}: {err}"))
+                    })
+                    .collect::<Result<_>>()?;
+            }
+            "default_wallet" => self.default_wallet = parse_optional(value)?,
+            "batch_size" => self.batch_size = parse_optional(value)?,
+            "connection_timeout" => self.connection_timeout = parse_optional(value)?,
+            "output_format" => self.output_format = parse_optional(value)?,
+            "password_cache_secs" => self.password_cache_secs = parse_optional(value)?,
+            "fiat_rates" => {
+                let _ = FixedRates::parse(value)?;
+                self.fiat_rates = parse_optional(value)?;
+            }
+            "fiat_rate_url" => {
+                if !value.is_empty() {
+                    let _ = url::Url::parse(value)
+                        .map_err(|err| eyre!("Invalid url {value:?}: {err}"))?;
+                }
+                self.fiat_rate_url = parse_optional(value)?;
+            }
+            "fiat_rate_pointer" => self.fiat_rate_pointer = parse_optional(value)?,
+            _ => bail!(unknown_key(key)),
+        }
+        Ok(())
+    }
+
+    /// The settings of the profile, its defaults if it has no table in the file.
+    pub fn profile_settings(&self, profile: NetworkProfile) -> ProfileSettings {
+        self.profiles.get(&profile).cloned().unwrap_or_default()
+    }
+
+    /// Parses the configured network contacts of the profile into multiaddrs. The mainnet falls
+    /// back to the `network_contacts` key, which predates the profiles.
+    pub fn network_contacts(&self, profile: NetworkProfile) -> Result<Vec<libp2p::Multiaddr>> {
+        let profile_contacts = self
+            .profiles
+            .get(&profile)
+            .map(|settings| settings.network_contacts.as_slice())
+            .unwrap_or_default();
+        let contacts = match profile {
+            NetworkProfile::Mainnet if profile_contacts.is_empty() => &self.network_contacts,
+            _ => profile_contacts,
+        };
+        contacts
+            .iter()
+            .map(|c| {
+                sn_peers_acquisition::parse_peer_addr(c)
+                    .map_err(|err| eyre!("Invalid network contact {c:?} in config: {err}"))
+            })
+            .collect()
+    }
+}
+
+fn parse_optional<T>(value: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|err| eyre!("Invalid value {value:?}: {err}"))
+}
+
+fn unknown_key(key: &str) -> String {
+    format!(
+        "Unknown config key {key:?}. Valid keys are: {}",
+        CONFIG_KEYS.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_should_be_persisted_and_loaded() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let path = tmp_dir.path().join("safe").join(CONFIG_FILE_NAME);
+
+        // a missing file gives the defaults
+        assert_eq!(CliConfig::load_from(&path)?, CliConfig::default());
+
+        let mut config = CliConfig::default();
+        config.set("network_contacts", "127.0.0.1:12000, 127.0.0.1:12001")?;
+        config.set("default_wallet", "savings")?;
+        config.set("batch_size", "32")?;
+        config.set("connection_timeout", "60")?;
+        config.set("output_format", "JSON")?;
+        config.set("password_cache_secs", "300")?;
+        config.set("fiat_rates", "usd=0.5,eur=0.45")?;
+        config.save_to(&path)?;
+
+        let loaded = CliConfig::load_from(&path)?;
+        assert_eq!(loaded, config);
+        assert_eq!(loaded.network_contacts(NetworkProfile::Mainnet)?.len(), 2);
+        // the contacts of the mainnet are not used with another network
+        assert!(loaded.network_contacts(NetworkProfile::Testnet)?.is_empty());
+        assert_eq!(loaded.get("batch_size")?, Some("32".to_string()));
+        assert_eq!(loaded.get("output_format")?, Some("json".to_string()));
+        assert_eq!(
+            loaded.get("network_contacts")?,
+            Some("127.0.0.1:12000,127.0.0.1:12001".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn profiles_should_be_read_from_their_tables() -> Result<()> {
+        let config: CliConfig = toml::from_str(
+            r#"
+            network_profile = "testnet"
+            network_contacts = ["127.0.0.1:12000"]
+
+            [profiles.testnet]
+            network_contacts = ["127.0.0.1:13000", "127.0.0.1:13001"]
+            network_version = "beta"
+            "#,
+        )?;
+        assert_eq!(config.network_profile, Some(NetworkProfile::Testnet));
+        assert_eq!(config.network_contacts(NetworkProfile::Testnet)?.len(), 2);
+        assert_eq!(config.network_contacts(NetworkProfile::Mainnet)?.len(), 1);
+        assert!(config.network_contacts(NetworkProfile::Local)?.is_empty());
+        assert_eq!(
+            config
+                .profile_settings(NetworkProfile::Testnet)
+                .network_version,
+            Some("beta".to_string())
+        );
+        assert_eq!(
+            config.profile_settings(NetworkProfile::Local),
+            ProfileSettings::default()
+        );
+
+        // the tables are kept when the config is written back
+        let written: CliConfig = toml::from_str(&toml::to_string_pretty(&config)?)?;
+        assert_eq!(written, config);
+        Ok(())
+    }
+
+    #[test]
+    fn empty_value_should_unset_the_key() -> Result<()> {
+        let mut config = CliConfig::default();
+        config.set("batch_size", "8")?;
+        assert_eq!(config.batch_size, Some(8));
+        config.set("batch_size", "")?;
+        assert_eq!(config.batch_size, None);
+        assert_eq!(config.get("batch_size")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_keys_and_values_should_be_rejected() {
+        let mut config = CliConfig::default();
+        assert!(config.set("not_a_key", "value").is_err());
+        assert!(config.get("not_a_key").is_err());
+        assert!(config.set("batch_size", "many").is_err());
+        assert!(config.set("output_format", "yaml").is_err());
+        assert!(config.set("network_profile", "devnet").is_err());
+        assert!(config.set("fiat_rates", "usd").is_err());
+        assert!(config.set("fiat_rate_url", "not a url").is_err());
+        assert!(config.set("network_contacts", "not-an-address").is_err());
+        assert_eq!(config, CliConfig::default());
+    }
+}
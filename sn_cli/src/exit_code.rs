@@ -0,0 +1,189 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use color_eyre::Report;
+use serde::Serialize;
+use sn_client::{
+    networking::NetworkError,
+    transfers::{TransferError, WalletError},
+    Error as ClientError,
+};
+use std::{error::Error, fmt};
+
+/// The kinds of failure the CLI tells apart, each exiting with its own code so that scripts can
+/// branch on them.
+///
+/// The codes are part of the CLI interface and must not be changed. Code 2 is left to the usage
+/// errors clap exits with on invalid arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Any failure not covered by the other kinds.
+    General,
+    /// No peers could be obtained or connected to.
+    NetworkUnreachable,
+    /// The wallet does not hold enough tokens for the operation.
+    InsufficientFunds,
+    /// Paying for the storage of the data failed.
+    PaymentFailed,
+    /// Data or a transfer could not be verified.
+    VerificationFailed,
+    /// Some of the files could not be uploaded, the upload can be resumed.
+    PartialUpload,
+}
+
+impl ErrorKind {
+    /// Classifies an error by looking for a known cause in its chain, the outermost first.
+    pub fn of(err: &Report) -> Self {
+        err.chain()
+            .find_map(Self::of_cause)
+            .unwrap_or(Self::General)
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::General => 1,
+            Self::NetworkUnreachable => 3,
+            Self::InsufficientFunds => 4,
+            Self::PaymentFailed => 5,
+            Self::VerificationFailed => 6,
+            Self::PartialUpload => 7,
+        }
+    }
+
+    fn of_cause(cause: &(dyn Error + 'static)) -> Option<Self> {
+        if cause.is::<IncompleteUpload>() {
+            return Some(Self::PartialUpload);
+        }
+        if cause.is::<VerificationFailed>() {
+            return Some(Self::VerificationFailed);
+        }
+        if let Some(err) = cause.downcast_ref::<ClientError>() {
+            return match err {
+                ClientError::ConnectionTimeout(_) | ClientError::IncompatibleProtocol { .. } => {
+                    Some(Self::NetworkUnreachable)
+                }
+                ClientError::SequentialUploadPaymentError
+                | ClientError::MaximumRepaymentsReached(_)
+                | ClientError::UploadFailedWithMaximumRepaymentsReached { .. } => {
+                    Some(Self::PaymentFailed)
+                }
+                ClientError::CouldNotVerifyTransfer(_) => Some(Self::VerificationFailed),
+                _ => None,
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<NetworkError>() {
+            return match err {
+                NetworkError::DialError(_)
+                | NetworkError::TransportError(_)
+                | NetworkError::NotEnoughPeers { .. } => Some(Self::NetworkUnreachable),
+                NetworkError::FailedToVerifyChunkProof(_) => Some(Self::VerificationFailed),
+                _ => None,
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<WalletError>() {
+            return match err {
+                WalletError::CouldNotVerifyTransfer(_) => Some(Self::VerificationFailed),
+                _ => None,
+            };
+        }
+        if let Some(TransferError::NotEnoughBalance(..)) = cause.downcast_ref::<TransferError>() {
+            return Some(Self::InsufficientFunds);
+        }
+        if let Some(err) = cause.downcast_ref::<sn_peers_acquisition::error::Error>() {
+            return match err {
+                sn_peers_acquisition::error::Error::FailedToObtainPeersFromUrl(..)
+                | sn_peers_acquisition::error::Error::NoMultiAddrObtainedFromNetworkContacts(_)
+                | sn_peers_acquisition::error::Error::PeersNotObtained => {
+                    Some(Self::NetworkUnreachable)
+                }
+                _ => None,
+            };
+        }
+        None
+    }
+}
+
+/// The error object printed in `--json` mode when a command fails.
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    pub exit_code: u8,
+    pub message: String,
+    /// The underlying causes, the outermost first.
+    pub causes: Vec<String>,
+}
+
+impl From<&Report> for ErrorReport {
+    fn from(err: &Report) -> Self {
+        let kind = ErrorKind::of(err);
+        Self {
+            kind,
+            exit_code: kind.exit_code(),
+            message: err.to_string(),
+            causes: err.chain().skip(1).map(|cause| cause.to_string()).collect(),
+        }
+    }
+}
+
+/// Returned when an upload completed but some of its files could not be uploaded.
+#[derive(Debug)]
+pub struct IncompleteUpload {
+    pub incomplete_files: usize,
+}
+
+impl fmt::Display for IncompleteUpload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} file(s) could not be uploaded", self.incomplete_files)
+    }
+}
+
+impl Error for IncompleteUpload {}
+
+/// Returned when downloaded data does not match what it was expected to be.
+#[derive(Debug)]
+pub struct VerificationFailed(pub String);
+
+impl fmt::Display for VerificationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Verification failed: {}", self.0)
+    }
+}
+
+impl Error for VerificationFailed {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+    use sn_client::transfers::NanoTokens;
+
+    #[test]
+    fn errors_should_be_classified_by_their_causes() {
+        assert_eq!(
+            ErrorKind::of(&eyre!("something went wrong")),
+            ErrorKind::General
+        );
+
+        let low_balance = ClientError::Wallet(WalletError::Transfer(
+            TransferError::NotEnoughBalance(NanoTokens::from(1), NanoTokens::from(2)),
+        ));
+        let err = Report::new(low_balance).wrap_err("Failed to upload chunk batch");
+        assert_eq!(ErrorKind::of(&err), ErrorKind::InsufficientFunds);
+
+        let err = Report::new(IncompleteUpload {
+            incomplete_files: 2,
+        });
+        let report = ErrorReport::from(&err);
+        assert_eq!(report.kind, ErrorKind::PartialUpload);
+        assert_eq!(report.exit_code, 7);
+
+        let err = Report::new(sn_peers_acquisition::error::Error::PeersNotObtained);
+        assert_eq!(ErrorKind::of(&err), ErrorKind::NetworkUnreachable);
+    }
+}
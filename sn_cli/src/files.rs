@@ -11,12 +11,16 @@ mod download;
 mod estimate;
 mod files_uploader;
 mod upload;
+mod upload_session;
+mod watch;
 
 pub use chunk_manager::ChunkManager;
-pub use download::{download_file, download_files};
+pub use download::{download_file, download_files, download_folder, DownloadCfg, OverwritePolicy};
 pub use estimate::Estimator;
 pub use files_uploader::{FilesUploadStatusNotifier, FilesUploadSummary, FilesUploader};
 pub use upload::{UploadedFile, UPLOADED_FILES};
+pub use upload_session::{UploadSession, UPLOAD_SESSIONS};
+pub use watch::{FileState, FolderWatcher, LedgerEntry, WatchLedger, WatchOutcome, WATCH_LEDGER};
 
 use color_eyre::Result;
 use indicatif::{ProgressBar, ProgressStyle};
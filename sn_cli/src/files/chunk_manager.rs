@@ -16,7 +16,7 @@ use color_eyre::{
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use sn_client::{
     protocol::storage::{Chunk, ChunkAddress},
-    FilesApi,
+    ErasureCoding, FilesApi,
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -72,6 +72,12 @@ pub struct ChunkManager {
     completed_files: Vec<(PathBuf, OsString, ChunkAddress)>,
     resumed_chunk_count: usize,
     resumed_files_count: usize,
+    /// The erasure coding of the chunks of the files chunked from now on
+    erasure_coding: Option<ErasureCoding>,
+    /// Whether the files chunked from now on are cut into segments by their content
+    content_defined_chunking: bool,
+    /// The files chunked into fixed size chunks even with content defined chunking
+    fixed_size_files: BTreeSet<PathBuf>,
 }
 
 impl ChunkManager {
@@ -86,9 +92,30 @@ impl ChunkManager {
             completed_files: Default::default(),
             resumed_files_count: 0,
             resumed_chunk_count: 0,
+            erasure_coding: None,
+            content_defined_chunking: false,
+            fixed_size_files: Default::default(),
         }
     }
 
+    /// Erasure code the chunks of the files, adding parity chunks out of which lost chunks can be
+    /// recovered. Files resumed from the artifacts dir keep the chunks they were chunked into.
+    pub fn set_erasure_coding(&mut self, erasure_coding: Option<ErasureCoding>) {
+        self.erasure_coding = erasure_coding;
+    }
+
+    /// Cut the files into segments by their content before self-encrypting them, so an edit of a
+    /// file only changes the chunks of the segments it reaches. Not erasure coded.
+    pub fn set_content_defined_chunking(&mut self, content_defined_chunking: bool) {
+        self.content_defined_chunking = content_defined_chunking;
+    }
+
+    /// The files to keep chunking into fixed size chunks with content defined chunking, as they
+    /// were before it, for their chunks to be reused as long as they are not edited.
+    pub fn set_fixed_size_files(&mut self, fixed_size_files: BTreeSet<PathBuf>) {
+        self.fixed_size_files = fixed_size_files;
+    }
+
     /// Chunk all the files in the provided `files_path`
     /// These are stored to the CHUNK_ARTIFACTS_DIR
     /// if read_cache is true, will take cache from previous runs into account
@@ -110,6 +137,17 @@ impl ChunkManager {
         )
     }
 
+    /// Remove the chunks cached for the file at `file_path`, so that a file which has changed since
+    /// it was chunked gets chunked again.
+    pub fn remove_cached_chunks(&self, file_path: &Path) -> Result<()> {
+        let file_chunks_dir = self.artifacts_dir.join(PathXorName::new(file_path).0);
+        if file_chunks_dir.exists() {
+            std::fs::remove_dir_all(&file_chunks_dir)?;
+            debug!("Removed the cached chunks of {file_path:?}");
+        }
+        Ok(())
+    }
+
     /// Return the filename and the file's Xor address if all their chunks has been marked as
     /// verified
     pub(crate) fn already_put_chunks(
@@ -210,6 +248,9 @@ impl ChunkManager {
         progress_bar.println(format!("Chunking {total_files} files..."));
 
         let artifacts_dir = &self.artifacts_dir.clone();
+        let erasure_coding = self.erasure_coding;
+        let content_defined_chunking = self.content_defined_chunking;
+        let fixed_size_files = &self.fixed_size_files;
         let chunked_files = self.files_to_chunk
             .par_iter()
             .map(|(original_file_name, path_xor, path)| {
@@ -222,7 +263,17 @@ impl ChunkManager {
                     file_chunks_dir
                 };
 
-                match FilesApi::chunk_file(path, &file_chunks_dir, include_data_maps) {
+                let chunked = if content_defined_chunking && !fixed_size_files.contains(path) {
+                    FilesApi::chunk_file_content_defined(path, &file_chunks_dir, include_data_maps)
+                } else {
+                    FilesApi::chunk_file_with_erasure_coding(
+                        path,
+                        &file_chunks_dir,
+                        include_data_maps,
+                        erasure_coding,
+                    )
+                };
+                match chunked {
                     Ok((head_chunk_address, data_map, size, chunks)) => {
                         progress_bar.clone().inc(1);
                         debug!("Chunked {original_file_name:?} with {path_xor:?} into file's XorName: {head_chunk_address:?} of size {size}, and chunks len: {}", chunks.len());
@@ -13,35 +13,77 @@ use super::{
 
 use std::collections::BTreeSet;
 use std::ffi::OsString;
-use std::path::Path;
+use std::path::{Component, Path};
 
-use color_eyre::Result;
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
 use indicatif::ProgressBar;
 use walkdir::WalkDir;
 use xor_name::XorName;
 
-use crate::utils::duration_to_minute_seconds_miliseconds_string;
+use crate::{utils::duration_to_minute_seconds_miliseconds_string, VerificationFailed};
 use sn_client::{
-    protocol::storage::{Chunk, ChunkAddress, RetryStrategy},
-    FilesApi, FilesDownload, FilesDownloadEvent,
+    protocol::storage::{Chunk, ChunkAddress, RegisterAddress, RetryStrategy},
+    Client, FilesApi, FilesDownload, FilesDownloadEvent, FolderEntry, FoldersApi, BATCH_SIZE,
 };
 use tracing::{debug, error, info};
 
 /// The default folder to download files to.
 const DOWNLOAD_FOLDER: &str = "safe_files";
 
+/// What to do when a file being downloaded already exists locally.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Fail the download of the file.
+    #[default]
+    Fail,
+    /// Replace the existing file.
+    Overwrite,
+    /// Keep the existing file and skip its download.
+    Skip,
+}
+
+/// The set of options to pass into the download functions
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadCfg {
+    pub batch_size: usize,
+    pub show_holders: bool,
+    pub retry_strategy: RetryStrategy,
+    pub overwrite_policy: OverwritePolicy,
+    /// Re-chunk the downloaded files and check they match the address they were downloaded from.
+    pub verify: bool,
+}
+
+impl Default for DownloadCfg {
+    fn default() -> Self {
+        Self {
+            batch_size: BATCH_SIZE,
+            show_holders: false,
+            retry_strategy: RetryStrategy::Quick,
+            overwrite_policy: OverwritePolicy::default(),
+            verify: false,
+        }
+    }
+}
+
+/// Download all the files uploaded by the current user to `download_path`, or to the default
+/// download folder if it's not provided.
 pub async fn download_files(
     files_api: &FilesApi,
     root_dir: &Path,
-    show_holders: bool,
-    batch_size: usize,
-    retry_strategy: RetryStrategy,
+    download_path: Option<&Path>,
+    cfg: DownloadCfg,
 ) -> Result<()> {
-    info!("Downloading with batch size of {}", batch_size);
+    info!("Downloading with batch size of {}", cfg.batch_size);
     let uploaded_files_path = root_dir.join(UPLOADED_FILES);
-    let download_path = dirs_next::download_dir()
-        .unwrap_or(root_dir.to_path_buf())
-        .join(DOWNLOAD_FOLDER);
+    let download_path = match download_path {
+        Some(path) => path.to_path_buf(),
+        None => dirs_next::download_dir()
+            .unwrap_or(root_dir.to_path_buf())
+            .join(DOWNLOAD_FOLDER),
+    };
     std::fs::create_dir_all(download_path.as_path())?;
 
     let mut uploaded_files = BTreeSet::new();
@@ -72,41 +114,108 @@ pub async fn download_files(
     }
 
     for (xorname, file_data) in uploaded_files.into_iter() {
-        download_file(
-            files_api.clone(),
-            xorname,
-            file_data,
-            &download_path,
-            show_holders,
-            batch_size,
-            retry_strategy,
-        )
-        .await;
+        if let Err(err) =
+            download_file(files_api.clone(), xorname, file_data, &download_path, cfg).await
+        {
+            println!("{err}");
+        }
     }
 
     Ok(())
 }
 
+/// Download a Folder, along with all its files and subfolders, from the network address of its
+/// register. Only Folders whose metadata is not encrypted can be downloaded this way.
+pub async fn download_folder(
+    client: &Client,
+    root_dir: &Path,
+    folder_addr: RegisterAddress,
+    download_path: &Path,
+    cfg: DownloadCfg,
+) -> Result<()> {
+    let files_api = FilesApi::new(client.clone(), root_dir.to_path_buf());
+    let mut folders_to_download = vec![(folder_addr, download_path.to_path_buf())];
+    let mut failed_files = 0;
+
+    while let Some((addr, target_path)) = folders_to_download.pop() {
+        println!("Downloading Folder {} onto {target_path:?}", addr.to_hex());
+        std::fs::create_dir_all(&target_path)?;
+        let mut folders_api = FoldersApi::retrieve(client.clone(), root_dir, addr).await?;
+
+        for (_, (_, metadata)) in folders_api.entries().await? {
+            if !is_plain_file_name(&metadata.name) {
+                println!("Skipping entry with invalid name {:?}", metadata.name);
+                continue;
+            }
+            match metadata.content {
+                FolderEntry::File(data_map_chunk) => {
+                    let file_data = (metadata.name.into(), Some(data_map_chunk.clone()));
+                    if let Err(err) = download_file(
+                        files_api.clone(),
+                        *data_map_chunk.name(),
+                        file_data,
+                        &target_path,
+                        cfg,
+                    )
+                    .await
+                    {
+                        println!("{err}");
+                        failed_files += 1;
+                    }
+                }
+                FolderEntry::Folder(subfolder_addr) => {
+                    folders_to_download.push((subfolder_addr, target_path.join(metadata.name)));
+                }
+            }
+        }
+    }
+
+    if failed_files > 0 {
+        bail!("Failed to download {failed_files} file(s) of the Folder");
+    }
+    Ok(())
+}
+
+/// Download a file to `download_path`, applying the overwrite policy and verification set in `cfg`.
 pub async fn download_file(
     files_api: FilesApi,
     xor_name: XorName,
     // original file name and optional datamap chunk
     (file_name, datamap): (OsString, Option<Chunk>),
     download_path: &Path,
-    show_holders: bool,
-    batch_size: usize,
-    retry_strategy: RetryStrategy,
-) {
+    cfg: DownloadCfg,
+) -> Result<()> {
     let start_time = std::time::Instant::now();
+    let downloaded_file_path = download_path.join(&file_name);
+
+    if downloaded_file_path.exists() {
+        match cfg.overwrite_policy {
+            OverwritePolicy::Overwrite => {
+                debug!("Overwriting existing file at {downloaded_file_path:?}");
+            }
+            OverwritePolicy::Skip => {
+                println!("Skipping {file_name:?} as it already exists at {downloaded_file_path:?}");
+                return Ok(());
+            }
+            OverwritePolicy::Fail => {
+                return Err(eyre!(
+                    "Error downloading {file_name:?}: {downloaded_file_path:?} already exists. \
+                    Use --force to overwrite it, or --no-clobber to skip it"
+                ));
+            }
+        }
+    }
 
     let mut files_download = FilesDownload::new(files_api.clone())
-        .set_batch_size(batch_size)
-        .set_show_holders(show_holders)
-        .set_retry_strategy(retry_strategy);
+        .set_batch_size(cfg.batch_size)
+        .set_show_holders(cfg.show_holders)
+        .set_retry_strategy(cfg.retry_strategy);
 
-    println!("Downloading {file_name:?} from {xor_name:64x} with batch-size {batch_size}");
+    println!(
+        "Downloading {file_name:?} from {xor_name:64x} with batch-size {}",
+        cfg.batch_size
+    );
     debug!("Downloading {file_name:?} from {:64x}", xor_name);
-    let downloaded_file_path = download_path.join(&file_name);
 
     let mut download_events_rx = files_download.get_events();
 
@@ -166,22 +275,66 @@ pub async fn download_file(
 
     // await on the progress handler first as we want to clear the progress bar before printing things.
     let _ = progress_handler.await;
-    match download_result {
-        Ok(_) => {
-            debug!(
-                "Saved {file_name:?} at {}",
-                downloaded_file_path.to_string_lossy()
-            );
-            println!(
-                "Saved {file_name:?} at {}",
-                downloaded_file_path.to_string_lossy()
-            );
-            let elapsed_time = duration_to_minute_seconds_miliseconds_string(duration);
-            println!("File downloaded in {elapsed_time}");
-        }
-        Err(error) => {
-            error!("Error downloading {file_name:?}: {error}");
-            println!("Error downloading {file_name:?}: {error}")
-        }
+    if let Err(error) = download_result {
+        error!("Error downloading {file_name:?}: {error}");
+        return Err(eyre!("Error downloading {file_name:?}: {error}"));
+    }
+    debug!(
+        "Saved {file_name:?} at {}",
+        downloaded_file_path.to_string_lossy()
+    );
+    println!(
+        "Saved {file_name:?} at {}",
+        downloaded_file_path.to_string_lossy()
+    );
+    let elapsed_time = duration_to_minute_seconds_miliseconds_string(duration);
+    println!("File downloaded in {elapsed_time}");
+
+    if cfg.verify {
+        verify_downloaded_file(&downloaded_file_path, xor_name)?;
+        println!("Verified {file_name:?} against its data map");
+    }
+
+    Ok(())
+}
+
+/// Re-chunk a downloaded file and check that its data map matches the address it was downloaded from.
+fn verify_downloaded_file(file_path: &Path, xor_name: XorName) -> Result<()> {
+    let chunk_dir = tempfile::tempdir()?;
+    let (head_address, _data_map, _file_size, _chunks) =
+        FilesApi::chunk_file(file_path, chunk_dir.path(), false)?;
+
+    if *head_address.xorname() != xor_name {
+        error!("Verification of {file_path:?} failed, its head address is {head_address:?}");
+        return Err(VerificationFailed(format!(
+            "the content of {file_path:?} does not match the data map at {xor_name:64x}"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+// Folder entries must not be able to write outside of the folder being downloaded.
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folder_entry_names_should_not_escape_the_download_path() {
+        assert!(is_plain_file_name("file.txt"));
+        assert!(is_plain_file_name("sub folder"));
+        assert!(!is_plain_file_name(""));
+        assert!(!is_plain_file_name(".."));
+        assert!(!is_plain_file_name("../file.txt"));
+        assert!(!is_plain_file_name("sub/file.txt"));
+        assert!(!is_plain_file_name("/etc/passwd"));
     }
 }
@@ -12,6 +12,8 @@ use std::path::{Path, PathBuf};
 
 use color_eyre::Result;
 
+use crate::pricing::{annotate, ExchangeRate};
+
 use sn_client::{
     protocol::{storage::ChunkAddress, NetworkAddress},
     transfers::NanoTokens,
@@ -31,12 +33,13 @@ impl Estimator {
         }
     }
 
-    /// Estimate the upload cost of a chosen file
+    /// Estimate the upload cost of a chosen file, along with its fiat equivalent if a rate is given
     pub async fn estimate_cost(
         mut self,
         path: PathBuf,
         make_data_public: bool,
         root_dir: &Path,
+        fiat_rate: Option<&ExchangeRate>,
     ) -> Result<()> {
         self.chunk_manager
             .chunk_path(&path, false, make_data_public)?;
@@ -70,11 +73,17 @@ impl Estimator {
         let total = balance.saturating_sub(estimate);
 
         println!("**************************************");
-        println!("Your current balance: {}", NanoTokens::from(balance));
-        println!("Transfer cost estimate: {}", NanoTokens::from(estimate));
+        println!(
+            "Your current balance: {}",
+            annotate(NanoTokens::from(balance), fiat_rate)
+        );
+        println!(
+            "Transfer cost estimate: {}",
+            annotate(NanoTokens::from(estimate), fiat_rate)
+        );
         println!(
             "Your balance estimate after transfer: {}",
-            NanoTokens::from(total)
+            annotate(NanoTokens::from(total), fiat_rate)
         );
         println!("**************************************");
 
@@ -10,16 +10,17 @@ use super::get_progress_bar;
 use crate::utils::duration_to_minute_seconds_string;
 use crate::ChunkManager;
 use bytes::Bytes;
-use color_eyre::{eyre::eyre, Report, Result};
+use color_eyre::{Report, Result};
 use futures::StreamExt;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 use sn_client::{
     transfers::{TransferError, WalletError},
-    Client, Error as ClientError, UploadCfg, UploadEvent, UploadSummary, Uploader,
+    Client, ErasureCoding, Error as ClientError, UploadCfg, UploadEvent, UploadSummary, Uploader,
 };
 use sn_protocol::storage::{Chunk, ChunkAddress};
 use std::{
+    collections::BTreeSet,
     ffi::OsString,
     path::{Path, PathBuf},
     time::{Duration, Instant},
@@ -76,10 +77,15 @@ pub struct FilesUploader {
     root_dir: PathBuf,
     /// entries to upload
     entries_to_upload: Vec<DirEntry>,
+    /// chunks known to be stored on the network already, which are not uploaded again
+    existing_chunks: BTreeSet<XorName>,
     /// The status notifier that can be overridden to perform custom actions instead of printing things to stdout.
     status_notifier: Option<Box<dyn FilesUploadStatusNotifier>>,
     /// config
     make_data_public: bool,
+    erasure_coding: Option<ErasureCoding>,
+    content_defined_chunking: bool,
+    fixed_size_files: BTreeSet<PathBuf>,
     upload_cfg: UploadCfg,
 }
 
@@ -92,8 +98,12 @@ impl FilesUploader {
             client,
             root_dir,
             entries_to_upload: Default::default(),
+            existing_chunks: Default::default(),
             status_notifier: Some(status_notifier),
             make_data_public: false,
+            erasure_coding: None,
+            content_defined_chunking: false,
+            fixed_size_files: Default::default(),
             upload_cfg: Default::default(),
         }
     }
@@ -108,6 +118,32 @@ impl FilesUploader {
         self
     }
 
+    /// Erasure code the chunks of the files, for them to be recovered even if some of their chunks
+    /// are lost. The parity chunks are paid for and uploaded along with the others.
+    pub fn set_erasure_coding(mut self, erasure_coding: Option<ErasureCoding>) -> Self {
+        self.erasure_coding = erasure_coding;
+        self
+    }
+
+    /// Cut the files into segments by their content, so an edit of a file only changes the chunks
+    /// of the segments it reaches. Other than `fixed_size_files`, the files are not erasure coded.
+    pub fn set_content_defined_chunking(
+        mut self,
+        content_defined_chunking: bool,
+        fixed_size_files: BTreeSet<PathBuf>,
+    ) -> Self {
+        self.content_defined_chunking = content_defined_chunking;
+        self.fixed_size_files = fixed_size_files;
+        self
+    }
+
+    /// Set the chunks known to be stored on the network already, e.g. those a changed file shares
+    /// with its previous version. They are neither paid for nor uploaded again.
+    pub fn set_existing_chunks(mut self, existing_chunks: BTreeSet<XorName>) -> Self {
+        self.existing_chunks = existing_chunks;
+        self
+    }
+
     /// Override the default status notifier. By default we print things to stdout.
     pub fn set_status_notifier(
         mut self,
@@ -133,6 +169,9 @@ impl FilesUploader {
 
     pub async fn start_upload(mut self) -> Result<FilesUploadSummary> {
         let mut chunk_manager = ChunkManager::new(&self.root_dir);
+        chunk_manager.set_erasure_coding(self.erasure_coding);
+        chunk_manager.set_content_defined_chunking(self.content_defined_chunking);
+        chunk_manager.set_fixed_size_files(std::mem::take(&mut self.fixed_size_files));
         let chunks_to_upload = self.get_chunks_to_upload(&mut chunk_manager).await?;
         let chunks_to_upload_len = chunks_to_upload.len();
 
@@ -160,16 +199,19 @@ impl FilesUploader {
 
         let upload_sum = match uploader.start_upload().await {
             Ok(summary) => summary,
-            Err(ClientError::Wallet(WalletError::Transfer(TransferError::NotEnoughBalance(
-                available,
-                required,
-            )))) => {
-                return Err(eyre!(
+            Err(
+                err @ ClientError::Wallet(WalletError::Transfer(TransferError::NotEnoughBalance(
+                    available,
+                    required,
+                ))),
+            ) => {
+                return Err(Report::new(err).wrap_err(format!(
                     "Not enough balance in wallet to pay for chunk. \
             We have {available:?} but need {required:?} to pay for the chunk"
-                ))
+                )))
             }
-            Err(err) => return Err(eyre!("Failed to upload chunk batch: {err}")),
+            // keep the error as the cause, so that the failure can be told apart by its kind
+            Err(err) => return Err(Report::new(err).wrap_err("Failed to upload chunk batch")),
         };
         let (chunk_manager, status_notifier) = events_handle.await??;
         self.status_notifier = status_notifier;
@@ -207,6 +249,19 @@ impl FilesUploader {
         )?;
         // We verify if there are no chunks left to upload.
         let mut chunks_to_upload = if !chunk_manager.is_chunks_empty() {
+            let existing_chunks = chunk_manager
+                .get_chunks()
+                .into_iter()
+                .filter(|(xorname, _)| self.existing_chunks.contains(xorname))
+                .map(|(xorname, _)| xorname)
+                .collect::<Vec<_>>();
+            if !existing_chunks.is_empty() {
+                debug!(
+                    "Skipping {} chunks stored on the network already",
+                    existing_chunks.len()
+                );
+                chunk_manager.mark_completed(existing_chunks.into_iter())?;
+            }
             chunk_manager.get_chunks()
         } else {
             // re chunk it again to get back all the chunks
@@ -0,0 +1,112 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use sn_client::{ClientStore, ErasureCoding};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tracing::debug;
+use xor_name::XorName;
+
+pub use sn_client::UPLOAD_SESSIONS;
+
+/// An upload that has been started but not completed yet.
+///
+/// The chunks of the files are kept by the `ChunkManager` and the payments made for them are kept by
+/// the wallet, so a session only needs to remember what was being uploaded in order to be resumed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSession {
+    /// The id used to resume the session.
+    pub id: String,
+    /// The file or directory being uploaded.
+    pub path: PathBuf,
+    pub make_data_public: bool,
+    pub started_at: SystemTime,
+    /// The erasure coding of the chunks, for a resumed session to chunk the files the same way.
+    #[serde(default)]
+    pub erasure_coding: Option<ErasureCoding>,
+}
+
+impl UploadSession {
+    /// Creates a new session for the given path. The id is derived from the path and the visibility
+    /// of the data, so uploading the same path again maps to the same session.
+    pub fn new(path: &Path, make_data_public: bool) -> Self {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut content = path.to_string_lossy().as_bytes().to_vec();
+        content.push(make_data_public as u8);
+        let id = hex::encode(&XorName::from_content(&content).0[..4]);
+
+        Self {
+            id,
+            path,
+            make_data_public,
+            started_at: SystemTime::now(),
+            erasure_coding: None,
+        }
+    }
+
+    /// Write the session to `root_dir`, unless a session with the same id is already there.
+    pub fn write(&self, root_dir: &Path) -> Result<()> {
+        if ClientStore::open(root_dir)?.write_upload_session(&self.id, self)? {
+            debug!("Wrote upload session {} for {:?}", self.id, self.path);
+        } else {
+            debug!("Upload session {} already exists", self.id);
+        }
+        Ok(())
+    }
+
+    /// Read the session with the given id from `root_dir`.
+    pub fn read(root_dir: &Path, id: &str) -> Result<Self> {
+        ClientStore::open(root_dir)?
+            .read_upload_session(id)?
+            .ok_or_else(|| eyre!("No pending upload session found with id {id}"))
+    }
+
+    /// Remove the session from `root_dir`, once the upload has completed.
+    pub fn remove(&self, root_dir: &Path) -> Result<()> {
+        ClientStore::open(root_dir)?.remove_upload_session(&self.id)?;
+        debug!("Removed upload session {}", self.id);
+        Ok(())
+    }
+
+    /// List all the pending sessions found in `root_dir`, oldest first.
+    pub fn list(root_dir: &Path) -> Result<Vec<Self>> {
+        let mut sessions: Vec<Self> = ClientStore::open(root_dir)?.upload_sessions()?;
+        sessions.sort_by_key(|session| session.started_at);
+        Ok(sessions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_should_be_written_listed_and_removed() -> Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        let upload_dir = tempfile::tempdir()?;
+
+        let session = UploadSession::new(upload_dir.path(), false);
+        // the same path maps to the same session, but not if the visibility differs
+        assert_eq!(UploadSession::new(upload_dir.path(), false).id, session.id);
+        assert_ne!(UploadSession::new(upload_dir.path(), true).id, session.id);
+
+        session.write(root_dir.path())?;
+        assert_eq!(UploadSession::read(root_dir.path(), &session.id)?, session);
+        assert_eq!(UploadSession::list(root_dir.path())?, vec![session.clone()]);
+
+        session.remove(root_dir.path())?;
+        assert!(UploadSession::read(root_dir.path(), &session.id).is_err());
+        assert!(UploadSession::list(root_dir.path())?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use color_eyre::{eyre::eyre, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+use tracing::{debug, warn};
+use walkdir::{DirEntry, WalkDir};
+
+/// The file in the client root dir the results of `files watch` are appended to, one JSON entry
+/// per line.
+pub const WATCH_LEDGER: &str = "watch_ledger.jsonl";
+
+/// The size and modification time of a file, used to tell whether it has changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileState {
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+impl FileState {
+    fn of(entry: &DirEntry) -> Option<Self> {
+        let metadata = entry.metadata().ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchOutcome {
+    Uploaded,
+    Failed,
+}
+
+/// The result of uploading one version of a watched file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub path: PathBuf,
+    pub state: FileState,
+    pub outcome: WatchOutcome,
+    /// The hex address of the head chunk, if the file was uploaded.
+    pub address: Option<String>,
+    pub recorded_at: SystemTime,
+}
+
+/// The append-only record of the files uploaded by `files watch`.
+///
+/// It is also what tells which files still have to be uploaded when the watch is restarted.
+#[derive(Debug)]
+pub struct WatchLedger {
+    path: PathBuf,
+    uploaded: BTreeMap<PathBuf, FileState>,
+}
+
+impl WatchLedger {
+    /// Loads the ledger from `root_dir`. A missing ledger is an empty one.
+    pub fn load(root_dir: &Path) -> Result<Self> {
+        let path = root_dir.join(WATCH_LEDGER);
+        let mut uploaded = BTreeMap::new();
+        if path.exists() {
+            let file = std::fs::File::open(&path)?;
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                match serde_json::from_str::<LedgerEntry>(&line) {
+                    Ok(entry) if entry.outcome == WatchOutcome::Uploaded => {
+                        uploaded.insert(entry.path, entry.state);
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("Skipping unreadable entry of the watch ledger: {err}"),
+                }
+            }
+        }
+        debug!("Loaded watch ledger with {} uploaded files", uploaded.len());
+        Ok(Self { path, uploaded })
+    }
+
+    /// Whether the file at `path` has been uploaded in the given state.
+    pub fn is_uploaded(&self, path: &Path, state: &FileState) -> bool {
+        self.uploaded.get(path) == Some(state)
+    }
+
+    pub fn append(&mut self, entry: &LedgerEntry) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        if entry.outcome == WatchOutcome::Uploaded {
+            self.uploaded.insert(entry.path.clone(), entry.state);
+        }
+        Ok(())
+    }
+}
+
+/// Detects the files of a directory that are new or have changed since they were last uploaded.
+#[derive(Debug)]
+pub struct FolderWatcher {
+    dir: PathBuf,
+    ignore: GlobSet,
+    debounce: Duration,
+    /// The last state seen for each file, and since when it has been in that state.
+    observed: BTreeMap<PathBuf, (FileState, Instant)>,
+}
+
+impl FolderWatcher {
+    /// `ignore_patterns` are globs matched against the paths relative to `dir`, e.g. `*.tmp` or
+    /// `.git/**`. A file is only reported once it has not changed for `debounce`.
+    pub fn new(dir: &Path, ignore_patterns: &[String], debounce: Duration) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in ignore_patterns {
+            let glob = Glob::new(pattern)
+                .map_err(|err| eyre!("Invalid ignore pattern {pattern:?}: {err}"))?;
+            builder.add(glob);
+        }
+        let ignore = builder
+            .build()
+            .map_err(|err| eyre!("Invalid ignore patterns: {err}"))?;
+
+        Ok(Self {
+            dir: dir.canonicalize()?,
+            ignore,
+            debounce,
+            observed: Default::default(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the files that are not in the ledger in their current state and that have settled.
+    pub fn scan(&mut self, ledger: &WatchLedger) -> Vec<(DirEntry, FileState)> {
+        let now = Instant::now();
+        let mut ready = vec![];
+        let mut still_present = BTreeMap::new();
+
+        for entry in WalkDir::new(&self.dir).into_iter().flatten() {
+            if !entry.file_type().is_file() || self.is_ignored(entry.path()) {
+                continue;
+            }
+            let Some(state) = FileState::of(&entry) else {
+                continue;
+            };
+            let since = match self.observed.get(entry.path()) {
+                Some((observed_state, since)) if *observed_state == state => *since,
+                _ => now,
+            };
+            still_present.insert(entry.path().to_path_buf(), (state, since));
+
+            if now.duration_since(since) >= self.debounce
+                && !ledger.is_uploaded(entry.path(), &state)
+            {
+                ready.push((entry, state));
+            }
+        }
+
+        // forget about the files that were removed
+        self.observed = still_present;
+        ready
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        let relative_path = path.strip_prefix(&self.dir).unwrap_or(path);
+        self.ignore.is_match(relative_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanned_paths(watcher: &mut FolderWatcher, ledger: &WatchLedger) -> Vec<PathBuf> {
+        watcher
+            .scan(ledger)
+            .into_iter()
+            .map(|(entry, _)| entry.into_path())
+            .collect()
+    }
+
+    #[test]
+    fn only_new_and_changed_files_should_be_reported() -> Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        let watched_dir = tempfile::tempdir()?;
+        std::fs::write(watched_dir.path().join("notes.txt"), "hello")?;
+        std::fs::write(watched_dir.path().join("draft.tmp"), "ignored")?;
+
+        let mut ledger = WatchLedger::load(root_dir.path())?;
+        let mut watcher =
+            FolderWatcher::new(watched_dir.path(), &["*.tmp".to_string()], Duration::ZERO)?;
+        let notes = watcher.dir().join("notes.txt");
+
+        let ready = watcher.scan(&ledger);
+        assert_eq!(ready.len(), 1);
+        let (entry, state) = &ready[0];
+        assert_eq!(entry.path(), notes);
+
+        ledger.append(&LedgerEntry {
+            path: notes.clone(),
+            state: *state,
+            outcome: WatchOutcome::Uploaded,
+            address: Some("00".to_string()),
+            recorded_at: SystemTime::now(),
+        })?;
+        assert!(scanned_paths(&mut watcher, &ledger).is_empty());
+
+        // the ledger is kept across restarts
+        let ledger = WatchLedger::load(root_dir.path())?;
+        assert!(scanned_paths(&mut watcher, &ledger).is_empty());
+
+        std::fs::write(&notes, "hello again")?;
+        assert_eq!(scanned_paths(&mut watcher, &ledger), vec![notes]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn files_should_only_be_reported_once_settled() -> Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        let watched_dir = tempfile::tempdir()?;
+        std::fs::write(watched_dir.path().join("notes.txt"), "hello")?;
+
+        let ledger = WatchLedger::load(root_dir.path())?;
+        let mut watcher = FolderWatcher::new(watched_dir.path(), &[], Duration::from_secs(60))?;
+        assert!(scanned_paths(&mut watcher, &ledger).is_empty());
+        assert!(scanned_paths(&mut watcher, &ledger).is_empty());
+
+        Ok(())
+    }
+}
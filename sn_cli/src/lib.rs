@@ -7,11 +7,21 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 mod acc_packet;
+pub mod accounting;
+mod config;
+mod exit_code;
 mod files;
+pub mod pricing;
+mod profile;
 pub mod utils;
 
 pub use acc_packet::AccountPacket;
+pub use config::{CliConfig, OutputFormat, CONFIG_FILE_NAME, CONFIG_KEYS};
+pub use exit_code::{ErrorKind, ErrorReport, IncompleteUpload, VerificationFailed};
 pub use files::{
-    download_file, download_files, ChunkManager, Estimator, FilesUploadStatusNotifier,
-    FilesUploadSummary, FilesUploader, UploadedFile, UPLOADED_FILES,
+    download_file, download_files, download_folder, ChunkManager, DownloadCfg, Estimator,
+    FileState, FilesUploadStatusNotifier, FilesUploadSummary, FilesUploader, FolderWatcher,
+    LedgerEntry, OverwritePolicy, UploadSession, UploadedFile, WatchLedger, WatchOutcome,
+    UPLOADED_FILES, UPLOAD_SESSIONS, WATCH_LEDGER,
 };
+pub use profile::{check_network_marker, NetworkProfile, ProfileSettings, NETWORK_MARKER_FILE};
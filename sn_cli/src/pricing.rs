@@ -0,0 +1,319 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::CliConfig;
+use async_trait::async_trait;
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde::Serialize;
+use sn_client::transfers::NanoTokens;
+use std::{collections::BTreeMap, fmt};
+use tracing::{debug, warn};
+
+/// The provider used when none is configured, pricing the tokens as MaidSafeCoin on CoinGecko.
+pub const DEFAULT_RATE_URL: &str =
+    "https://api.coingecko.com/api/v3/simple/price?ids=maidsafecoin&vs_currencies={currency}";
+/// Where the rate is found in the responses of the default provider.
+pub const DEFAULT_RATE_POINTER: &str = "/maidsafecoin/{currency}";
+
+const NANOS_PER_TOKEN: f64 = 1_000_000_000.0;
+
+/// A source of exchange rates between the SafeNetworkToken and fiat currencies.
+#[async_trait]
+pub trait RateProvider: Send + Sync {
+    /// A short name of the provider, reported along with the estimates.
+    fn name(&self) -> String;
+
+    /// The price of one whole token in `currency`, a lowercase ISO 4217 code such as "usd".
+    async fn fetch_rate(&self, currency: &str) -> Result<f64>;
+}
+
+/// Rates set by the user, e.g. the ones agreed with a customer.
+#[derive(Clone, Debug, Default)]
+pub struct FixedRates {
+    rates: BTreeMap<String, f64>,
+}
+
+impl FixedRates {
+    /// Parses a comma-separated list of `currency=rate` pairs, such as "usd=0.5,eur=0.45".
+    pub fn parse(rates: &str) -> Result<Self> {
+        let rates = rates
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (currency, rate) = pair
+                    .split_once('=')
+                    .ok_or_else(|| eyre!("Invalid rate {pair:?}, expected 'currency=rate'"))?;
+                let rate: f64 = rate
+                    .trim()
+                    .parse()
+                    .map_err(|err| eyre!("Invalid rate {pair:?}: {err}"))?;
+                if !rate.is_finite() || rate < 0.0 {
+                    bail!("Invalid rate {pair:?}: it must be a positive number");
+                }
+                Ok((currency.trim().to_lowercase(), rate))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rates })
+    }
+}
+
+#[async_trait]
+impl RateProvider for FixedRates {
+    fn name(&self) -> String {
+        "fixed rates".to_string()
+    }
+
+    async fn fetch_rate(&self, currency: &str) -> Result<f64> {
+        self.rates
+            .get(currency)
+            .copied()
+            .ok_or_else(|| eyre!("No fixed rate set for {currency:?}"))
+    }
+}
+
+/// Rates fetched from an HTTP API answering with JSON.
+///
+/// `{currency}` is replaced with the requested currency in both the url and the JSON pointer
+/// locating the rate in the response.
+#[derive(Clone, Debug)]
+pub struct HttpRateProvider {
+    url: String,
+    pointer: String,
+}
+
+impl HttpRateProvider {
+    pub fn new(url: String, pointer: String) -> Self {
+        Self { url, pointer }
+    }
+
+    /// Reads the rate out of a response of the API.
+    fn rate_from_response(&self, body: &str, currency: &str) -> Result<f64> {
+        let json: serde_json::Value = serde_json::from_str(body)
+            .map_err(|err| eyre!("Invalid response from the rate provider: {err}"))?;
+        let pointer = self.pointer.replace("{currency}", currency);
+        let value = json
+            .pointer(&pointer)
+            .ok_or_else(|| eyre!("No rate found at {pointer:?} in the response"))?;
+        // some APIs send the numbers as strings, to not lose their precision
+        match value {
+            serde_json::Value::Number(rate) => rate.as_f64(),
+            serde_json::Value::String(rate) => rate.parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| eyre!("The rate at {pointer:?} is not a number: {value}"))
+    }
+}
+
+impl Default for HttpRateProvider {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_RATE_URL.to_string(),
+            DEFAULT_RATE_POINTER.to_string(),
+        )
+    }
+}
+
+#[async_trait]
+impl RateProvider for HttpRateProvider {
+    fn name(&self) -> String {
+        match url::Url::parse(&self.url) {
+            Ok(url) => url.host_str().unwrap_or(&self.url).to_string(),
+            Err(_) => self.url.clone(),
+        }
+    }
+
+    async fn fetch_rate(&self, currency: &str) -> Result<f64> {
+        let url = self.url.replace("{currency}", currency);
+        debug!("Fetching the {currency} rate from {url}");
+        let response = reqwest::get(&url).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            bail!("The rate provider responded with {status}: {body:?}");
+        }
+        self.rate_from_response(&body, currency)
+    }
+}
+
+/// Converts token amounts to fiat currencies, using the first of its providers that has a rate.
+pub struct Pricing {
+    providers: Vec<Box<dyn RateProvider>>,
+}
+
+impl Pricing {
+    pub fn new(providers: Vec<Box<dyn RateProvider>>) -> Self {
+        Self { providers }
+    }
+
+    /// The providers set in the config: the fixed rates first, then the rate API, which defaults
+    /// to CoinGecko.
+    pub fn from_config(config: &CliConfig) -> Result<Self> {
+        let mut providers: Vec<Box<dyn RateProvider>> = vec![];
+        if let Some(rates) = &config.fiat_rates {
+            providers.push(Box::new(FixedRates::parse(rates)?));
+        }
+        let provider = match &config.fiat_rate_url {
+            Some(url) => HttpRateProvider::new(
+                url.clone(),
+                config
+                    .fiat_rate_pointer
+                    .clone()
+                    .unwrap_or_else(|| "/{currency}".to_string()),
+            ),
+            None => HttpRateProvider::default(),
+        };
+        providers.push(Box::new(provider));
+        Ok(Self::new(providers))
+    }
+
+    /// The current rate of `currency`, e.g. "usd" or "EUR".
+    pub async fn rate(&self, currency: &str) -> Result<ExchangeRate> {
+        let currency = currency.trim().to_lowercase();
+        if currency.is_empty() || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+            bail!("Invalid currency {currency:?}, expected a code such as 'usd'");
+        }
+        for provider in &self.providers {
+            match provider.fetch_rate(&currency).await {
+                Ok(per_token) => {
+                    return Ok(ExchangeRate {
+                        currency: currency.to_uppercase(),
+                        per_token,
+                        provider: provider.name(),
+                    })
+                }
+                Err(err) => warn!(
+                    "Could not get the {currency} rate from {}: {err:?}",
+                    provider.name()
+                ),
+            }
+        }
+        bail!("None of the rate providers has a rate for {currency:?}")
+    }
+}
+
+/// The price of one token in a fiat currency, as reported by a provider.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ExchangeRate {
+    /// Uppercase ISO 4217 code of the currency.
+    pub currency: String,
+    pub per_token: f64,
+    /// The provider the rate comes from.
+    pub provider: String,
+}
+
+impl ExchangeRate {
+    pub fn convert(&self, amount: NanoTokens) -> FiatEstimate {
+        FiatEstimate {
+            value: amount.as_nano() as f64 / NANOS_PER_TOKEN * self.per_token,
+            currency: self.currency.clone(),
+            provider: self.provider.clone(),
+        }
+    }
+
+    /// The amount followed by its fiat equivalent, e.g. "2.000000000 (~1.00 USD, estimated via
+    /// fixed rates)".
+    pub fn annotate(&self, amount: NanoTokens) -> String {
+        format!("{amount} ({})", self.convert(amount))
+    }
+}
+
+/// The fiat equivalent of an amount of tokens. It is only an estimate, as the rates are
+/// indicative and change over time.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FiatEstimate {
+    pub value: f64,
+    pub currency: String,
+    pub provider: String,
+}
+
+impl fmt::Display for FiatEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "~{:.2} {}, estimated via {}",
+            self.value, self.currency, self.provider
+        )
+    }
+}
+
+/// Annotates `amount` with its fiat equivalent if a rate is given.
+pub fn annotate(amount: NanoTokens, rate: Option<&ExchangeRate>) -> String {
+    match rate {
+        Some(rate) => rate.annotate(amount),
+        None => amount.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fixed_rates_should_be_used_before_the_other_providers() -> Result<()> {
+        let pricing = Pricing::new(vec![
+            Box::new(FixedRates::parse("usd=0.5, EUR=0.25")?),
+            Box::new(HttpRateProvider::new(
+                "http://127.0.0.1:0/{currency}".to_string(),
+                "/{currency}".to_string(),
+            )),
+        ]);
+
+        let rate = pricing.rate("EUR").await?;
+        assert_eq!(rate.currency, "EUR");
+        assert_eq!(rate.provider, "fixed rates");
+
+        let estimate = rate.convert(NanoTokens::from(4_000_000_000));
+        assert_eq!(estimate.value, 1.0);
+        assert_eq!(
+            rate.annotate(NanoTokens::from(4_000_000_000)),
+            "4.000000000 (~1.00 EUR, estimated via fixed rates)"
+        );
+
+        // no provider has a rate for it, the unreachable one included
+        assert!(pricing.rate("gbp").await.is_err());
+        assert!(pricing.rate("u$d").await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_fixed_rates_should_be_rejected() {
+        assert!(FixedRates::parse("usd").is_err());
+        assert!(FixedRates::parse("usd=cheap").is_err());
+        assert!(FixedRates::parse("usd=-1").is_err());
+    }
+
+    #[test]
+    fn rates_should_be_read_from_the_responses() -> Result<()> {
+        let provider = HttpRateProvider::default();
+        assert_eq!(provider.name(), "api.coingecko.com");
+        assert_eq!(
+            provider.rate_from_response(r#"{"maidsafecoin":{"usd":0.42}}"#, "usd")?,
+            0.42
+        );
+        assert!(provider
+            .rate_from_response(r#"{"maidsafecoin":{"usd":0.42}}"#, "eur")
+            .is_err());
+
+        let provider = HttpRateProvider::new(
+            "https://rates.example/{currency}".to_string(),
+            "/data/rate".to_string(),
+        );
+        assert_eq!(
+            provider.rate_from_response(r#"{"data":{"rate":"1.5"}}"#, "usd")?,
+            1.5
+        );
+        assert!(provider
+            .rate_from_response(r#"{"data":{"rate":null}}"#, "usd")
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use sn_client::transfers::{set_network_keys, MainPubkey, NetworkKeys};
+use sn_protocol::version::{set_network_version, ProtocolVersion};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::{info, warn};
+
+/// The file of the client data dir recording the network it is used with.
+pub const NETWORK_MARKER_FILE: &str = "network";
+
+/// The network the CLI is run against. It selects the network contacts, protocol version, keys
+/// and client data dir together, for the wallets of one network never to be used on another.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkProfile {
+    #[default]
+    Mainnet,
+    Testnet,
+    Local,
+}
+
+impl FromStr for NetworkProfile {
+    type Err = color_eyre::eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "mainnet" => Ok(Self::Mainnet),
+            "testnet" => Ok(Self::Testnet),
+            "local" => Ok(Self::Local),
+            _ => Err(eyre!(
+                "Invalid network profile {s:?}. Valid values are 'mainnet', 'testnet' or 'local'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NetworkProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mainnet => write!(f, "mainnet"),
+            Self::Testnet => write!(f, "testnet"),
+            Self::Local => write!(f, "local"),
+        }
+    }
+}
+
+impl NetworkProfile {
+    /// The client data dir of the profile within the default one. The mainnet keeps the default
+    /// dir, which is where its wallets were kept before there were profiles.
+    pub fn data_dir(&self, default_data_dir: &Path) -> PathBuf {
+        match self {
+            Self::Mainnet => default_data_dir.to_path_buf(),
+            profile => default_data_dir.join("networks").join(profile.to_string()),
+        }
+    }
+
+    /// Whether the network contacts can be fetched from a url, which a local network has none of.
+    pub fn fetches_network_contacts(&self) -> bool {
+        *self != Self::Local
+    }
+}
+
+/// The settings of a network profile, as found in its `[profiles.<name>]` table of the config
+/// file. Those unset are the ones the CLI was built with.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileSettings {
+    /// Bootstrap peers, in the same formats accepted by the `--peer` argument.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_contacts: Vec<String>,
+    /// The url the network contacts are fetched from when none are given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_contacts_url: Option<String>,
+    /// The network version prefixing the protocol versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genesis_pk: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foundation_pk: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_royalties_pk: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_forward_pk: Option<String>,
+}
+
+impl ProfileSettings {
+    /// Run with the network version and keys of the profile. They are read once, so this has to
+    /// be done before connecting to the network.
+    pub fn apply(&self) -> Result<()> {
+        let parse = |var: &str, key: &Option<String>| -> Result<Option<MainPubkey>> {
+            key.as_deref()
+                .map(|key| {
+                    MainPubkey::from_hex(key)
+                        .map_err(|err| eyre!("Invalid {var} {key:?} in the network profile: {err}"))
+                })
+                .transpose()
+        };
+        let keys = NetworkKeys {
+            genesis_pk: parse("genesis_pk", &self.genesis_pk)?,
+            foundation_pk: parse("foundation_pk", &self.foundation_pk)?,
+            network_royalties_pk: parse("network_royalties_pk", &self.network_royalties_pk)?,
+            payment_forward_pk: parse("payment_forward_pk", &self.payment_forward_pk)?,
+        };
+        if !set_network_keys(keys) {
+            bail!("The network keys were read before the network profile was applied");
+        }
+        if let Some(network_version) = &self.network_version {
+            if !set_network_version(network_version) {
+                bail!("The network version was read before the network profile was applied");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What the client data dir records of the network it is used with.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct NetworkMarker {
+    profile: NetworkProfile,
+    network_version: String,
+    /// Identifies the genesis and network keys
+    key_version: String,
+}
+
+/// Record the network the client data dir is used with, failing if it was used with another one,
+/// e.g. a mainnet wallet pointed at a testnet. A data dir used before there were profiles is
+/// taken to be of the selected network.
+///
+/// A network whose version or keys changed, e.g. on the upgrade of a testnet, is still the one of
+/// the profile: the change is warned about and recorded, for its data dir to remain usable.
+///
+/// The profile has to be applied beforehand.
+pub fn check_network_marker(data_dir: &Path, profile: NetworkProfile) -> Result<()> {
+    let ours = ProtocolVersion::ours();
+    let marker = NetworkMarker {
+        profile,
+        network_version: ours.network_version,
+        key_version: ours.key_version,
+    };
+    let path = data_dir.join(NETWORK_MARKER_FILE);
+    if !path.exists() {
+        info!("Recording the client data dir {data_dir:?} as used with the {profile} network");
+        fs::create_dir_all(data_dir)?;
+        fs::write(&path, serde_json::to_string_pretty(&marker)?)?;
+        return Ok(());
+    }
+
+    let recorded: NetworkMarker = serde_json::from_slice(&fs::read(&path)?)
+        .map_err(|err| eyre!("Invalid network marker {path:?}: {err}"))?;
+    if recorded.profile != profile {
+        bail!(
+            "The client data dir {data_dir:?} is used with the {} network, not with the {profile} \
+            network selected. Select its profile with --network, or remove {path:?} to use it anyway.",
+            recorded.profile,
+        );
+    }
+    if recorded != marker {
+        let message = format!(
+            "The {profile} network changed from keys {} and version {:?} to keys {} and version \
+            {:?} since the client data dir {data_dir:?} was last used: the cash notes of its \
+            wallets may not be spendable on it",
+            recorded.key_version,
+            recorded.network_version,
+            marker.key_version,
+            marker.network_version,
+        );
+        warn!("{message}");
+        eprintln!("Warning: {message}");
+        fs::write(&path, serde_json::to_string_pretty(&marker)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_should_have_their_own_data_dir() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let default_dir = tmp_dir.path();
+        let mainnet_dir = NetworkProfile::Mainnet.data_dir(default_dir);
+        let testnet_dir = NetworkProfile::Testnet.data_dir(default_dir);
+        assert_eq!(mainnet_dir, default_dir);
+        assert_ne!(testnet_dir, mainnet_dir);
+        assert_eq!(
+            "TestNet".parse::<NetworkProfile>()?,
+            NetworkProfile::Testnet
+        );
+        assert!("devnet".parse::<NetworkProfile>().is_err());
+
+        check_network_marker(&mainnet_dir, NetworkProfile::Mainnet)?;
+        check_network_marker(&mainnet_dir, NetworkProfile::Mainnet)?;
+        check_network_marker(&testnet_dir, NetworkProfile::Testnet)?;
+        // pointing the data dir of a network at another one is refused
+        assert!(check_network_marker(&mainnet_dir, NetworkProfile::Testnet).is_err());
+
+        // a new version of the network of the data dir is recorded
+        let path = testnet_dir.join(NETWORK_MARKER_FILE);
+        let mut marker: NetworkMarker = serde_json::from_slice(&fs::read(&path)?)?;
+        let ours = marker.network_version.clone();
+        marker.network_version = "an-older-version".to_string();
+        fs::write(&path, serde_json::to_string(&marker)?)?;
+        check_network_marker(&testnet_dir, NetworkProfile::Testnet)?;
+        let recorded: NetworkMarker = serde_json::from_slice(&fs::read(&path)?)?;
+        assert_eq!(recorded.network_version, ours);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_keys_should_be_rejected() {
+        let settings = ProfileSettings {
+            genesis_pk: Some("not-a-key".to_string()),
+            ..Default::default()
+        };
+        assert!(settings.apply().is_err());
+    }
+}
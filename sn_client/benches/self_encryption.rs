@@ -0,0 +1,46 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#![allow(clippy::unwrap_used)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rand::RngCore;
+use sn_client::FilesApi;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn generate_file(path: &Path, size_mb: usize) {
+    let mut content = vec![0u8; size_mb * 1024 * 1024];
+    rand::thread_rng().fill_bytes(&mut content);
+    std::fs::write(path, content).unwrap();
+}
+
+fn bench_chunk_file(c: &mut Criterion) {
+    let mut group = c.benchmark_group("self encryption");
+    let _ = group.sample_size(10);
+
+    for size_mb in [1, 10, 100] {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("file");
+        generate_file(&file_path, size_mb);
+        let chunk_dir = dir.path().join("chunks");
+        std::fs::create_dir_all(&chunk_dir).unwrap();
+
+        let _ = group.throughput(Throughput::Bytes((size_mb * 1024 * 1024) as u64));
+        let _ = group.bench_function(format!("chunk a {size_mb}MB file"), |b| {
+            b.iter(|| {
+                let _ = black_box(FilesApi::chunk_file(&file_path, &chunk_dir, true).unwrap());
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_chunk_file);
+criterion_main!(benches);
@@ -65,6 +65,29 @@ pub fn secret_key_from_mnemonic(
     user_secret::account_wallet_secret_key(mnemonic, &passphrase)
 }
 
+/// Create a wallet from the given mnemonic, e.g. to restore one from its recovery phrase.
+///
+/// The mnemonic is stored along with the wallet, replacing any existing one, so it can be
+/// exported again with [`wallet_mnemonic`].
+pub fn create_wallet_from_mnemonic(
+    root_dir: &Path,
+    mnemonic: Mnemonic,
+    derivation_passphrase: Option<&str>,
+    password: Option<String>,
+) -> Result<HotWallet> {
+    user_secret::write_mnemonic_to_disk(root_dir, &mnemonic)?;
+    let main_sk = secret_key_from_mnemonic(mnemonic, derivation_passphrase.map(|v| v.to_owned()))?;
+    Ok(HotWallet::create_from_key(root_dir, main_sk, password)?)
+}
+
+/// Export the mnemonic the wallet in `root_dir` was created from, to write it down as the
+/// recovery phrase of the wallet.
+///
+/// Wallets created from a hex-encoded key have no mnemonic, in which case this fails.
+pub fn wallet_mnemonic(root_dir: &Path) -> Result<Mnemonic> {
+    user_secret::read_mnemonic_from_disk(root_dir)
+}
+
 pub fn create_faucet_account_and_wallet() -> HotWallet {
     let root_dir = get_faucet_data_dir();
 
@@ -72,3 +95,59 @@ pub fn create_faucet_account_and_wallet() -> HotWallet {
     load_account_wallet_or_create_with_mnemonic(&root_dir, None)
         .expect("Faucet wallet shall be created successfully.")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn wallet_is_restored_from_its_mnemonic() -> Result<()> {
+        for word_count in user_secret::MNEMONIC_WORD_COUNTS {
+            let mnemonic = user_secret::random_mnemonic(word_count)?;
+            assert_eq!(mnemonic.word_count(), word_count);
+
+            let dir = tempfile::tempdir()?;
+            let wallet =
+                create_wallet_from_mnemonic(dir.path(), mnemonic.clone(), Some("pass"), None)?;
+            let exported = wallet_mnemonic(dir.path())?;
+            assert_eq!(exported, mnemonic);
+
+            let restored_dir = tempfile::tempdir()?;
+            let phrase = exported.to_string();
+            let restored = create_wallet_from_mnemonic(
+                restored_dir.path(),
+                user_secret::parse_mnemonic(&phrase)?,
+                Some("pass"),
+                None,
+            )?;
+            assert_eq!(restored.address(), wallet.address());
+
+            // another derivation passphrase gives another wallet
+            let other_dir = tempfile::tempdir()?;
+            let other = create_wallet_from_mnemonic(other_dir.path(), mnemonic, None, None)?;
+            assert_ne!(other.address(), wallet.address());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn mnemonics_of_other_word_counts_are_rejected() -> Result<()> {
+        assert!(matches!(
+            user_secret::random_mnemonic(15),
+            Err(Error::InvalidMnemonicWordCount(15))
+        ));
+
+        let mnemonic =
+            Mnemonic::from_entropy(&[7u8; 20]).map_err(|_| Error::FailedToParseEntropy)?;
+        assert!(matches!(
+            user_secret::parse_mnemonic(&mnemonic.to_string()),
+            Err(Error::InvalidMnemonicWordCount(15))
+        ));
+        assert!(matches!(
+            user_secret::parse_mnemonic("not a mnemonic"),
+            Err(Error::FailedToParseMnemonic)
+        ));
+        Ok(())
+    }
+}
@@ -22,8 +22,20 @@ const ACCOUNT_ROOT_XORNAME_DERIVATION: &str = "m/1/0";
 
 const ACCOUNT_WALLET_DERIVATION: &str = "m/2/0";
 
+/// The number of words of the mnemonics accepted for the accounts.
+pub const MNEMONIC_WORD_COUNTS: [usize; 2] = [12, 24];
+
 pub fn random_eip2333_mnemonic() -> Result<bip39::Mnemonic> {
-    let mut entropy = [1u8; 32];
+    random_mnemonic(24)
+}
+
+/// Generate a random mnemonic of 12 or 24 words, i.e. from 128 or 256 bits of entropy.
+pub fn random_mnemonic(word_count: usize) -> Result<bip39::Mnemonic> {
+    if !MNEMONIC_WORD_COUNTS.contains(&word_count) {
+        return Err(Error::InvalidMnemonicWordCount(word_count));
+    }
+    // each word encodes 11 bits, of which 32 out of 33 are entropy (the rest being the checksum)
+    let mut entropy = vec![1u8; word_count * 4 / 3];
     let rng = &mut rand::rngs::OsRng;
     rng.fill_bytes(&mut entropy);
     let mnemonic =
@@ -31,6 +43,17 @@ pub fn random_eip2333_mnemonic() -> Result<bip39::Mnemonic> {
     Ok(mnemonic)
 }
 
+/// Parse a mnemonic phrase of 12 or 24 words, as written down by the user.
+pub fn parse_mnemonic(phrase: &str) -> Result<bip39::Mnemonic> {
+    let mnemonic =
+        bip39::Mnemonic::parse_normalized(phrase).map_err(|_err| Error::FailedToParseMnemonic)?;
+    let word_count = mnemonic.word_count();
+    if !MNEMONIC_WORD_COUNTS.contains(&word_count) {
+        return Err(Error::InvalidMnemonicWordCount(word_count));
+    }
+    Ok(mnemonic)
+}
+
 /// Derive a wallet secret key from the mnemonic for the account.
 pub fn account_wallet_secret_key(
     mnemonic: bip39::Mnemonic,
@@ -65,7 +88,7 @@ pub fn write_mnemonic_to_disk(files_dir: &Path, mnemonic: &bip39::Mnemonic) -> R
     Ok(())
 }
 
-pub(super) fn read_mnemonic_from_disk(files_dir: &Path) -> Result<bip39::Mnemonic> {
+pub fn read_mnemonic_from_disk(files_dir: &Path) -> Result<bip39::Mnemonic> {
     let filename = files_dir.join(MNEMONIC_FILENAME);
     let content = std::fs::read_to_string(filename)?;
     let mnemonic =
@@ -8,10 +8,12 @@
 
 use super::{
     error::{Error, Result},
+    hedging::{HedgeCfg, Hedging},
     Client, ClientEvent, ClientEventsBroadcaster, ClientEventsReceiver, ClientRegister,
     WalletClient,
 };
 use bls::{PublicKey, SecretKey, Signature};
+use bytes::Bytes;
 use libp2p::{
     identity::Keypair,
     kad::{Quorum, Record},
@@ -21,11 +23,15 @@ use rand::{thread_rng, Rng};
 use sn_networking::{
     get_signed_spend_from_record, multiaddr_is_global,
     target_arch::{interval, spawn, timeout, Instant},
-    GetRecordCfg, NetworkBuilder, NetworkError, NetworkEvent, PutRecordCfg, VerificationKind,
+    CloseGroupConsistency, GetRecordCfg, NetworkBuilder, NetworkError, NetworkEvent, PutRecordCfg,
+    SwarmLocalState, VerificationKind,
 };
 use sn_protocol::{
     error::Error as ProtocolError,
-    messages::ChunkProof,
+    messages::{
+        ChunkProof, Query, QueryResponse, Request, Response, SignedPaymentReceipt,
+        MAX_PAYMENT_RECEIPTS_PER_QUERY,
+    },
     storage::{
         try_deserialize_record, try_serialize_record, Chunk, ChunkAddress, RecordHeader,
         RecordKind, RegisterAddress, RetryStrategy, SpendAddress,
@@ -40,7 +46,7 @@ use sn_transfers::{
 #[cfg(target_arch = "wasm32")]
 use std::path::PathBuf;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     num::NonZeroUsize,
     sync::Arc,
 };
@@ -123,6 +129,7 @@ impl Client {
             network: network.clone(),
             events_broadcaster,
             signer: Arc::new(signer),
+            hedging: None,
         };
 
         // subscribe to our events channel first, so we don't have intermittent
@@ -201,9 +208,9 @@ impl Client {
             tokio::select! {
             _ = connection_timeout_interval.tick() => {
                 if !is_connected {
-                    if let Some((our_protocol, their_protocols)) = unsupported_protocol_tracker {
+                    if let Some((ours, theirs)) = unsupported_protocol_tracker {
                         error!("Timeout: Client could not connect to the network as it does not support the protocol");
-                        break Err(Error::UnsupportedProtocol(our_protocol, their_protocols));
+                        break Err(Error::IncompatibleProtocol { ours, theirs });
                     }
                     error!("Timeout: Client failed to connect to the network within {connection_timeout:?}");
                     break Err(Error::ConnectionTimeout(connection_timeout));
@@ -273,6 +280,10 @@ impl Client {
                         their_protocol,
                     });
             }
+            NetworkEvent::GossipsubMsg { topic, msg } => {
+                self.events_broadcaster
+                    .broadcast(ClientEvent::GossipsubMsg { topic, msg });
+            }
             _other => {}
         }
 
@@ -305,6 +316,41 @@ impl Client {
         self.events_broadcaster.subscribe()
     }
 
+    /// Subscribe to a gossipsub topic, for the messages published on it to be broadcasted as
+    /// `ClientEvent::GossipsubMsg`.
+    pub async fn subscribe_to_topic(&self, topic: String) -> Result<()> {
+        info!("Subscribing to gossipsub topic {topic:?}");
+        Ok(self.network.subscribe_to_topic(topic).await?)
+    }
+
+    /// Unsubscribe from a gossipsub topic.
+    pub async fn unsubscribe_from_topic(&self, topic: String) -> Result<()> {
+        info!("Unsubscribing from gossipsub topic {topic:?}");
+        Ok(self.network.unsubscribe_from_topic(topic).await?)
+    }
+
+    /// Publish a message on a gossipsub topic.
+    pub async fn publish_on_topic(&self, topic: String, msg: Bytes) -> Result<()> {
+        info!(
+            "Publishing a message of {} bytes on topic {topic:?}",
+            msg.len()
+        );
+        Ok(self.network.publish_on_topic(topic, msg).await?)
+    }
+
+    /// Get the peers the client is currently connected to, along with the addresses it listens on.
+    pub async fn get_swarm_local_state(&self) -> Result<SwarmLocalState> {
+        Ok(self.network.get_swarm_local_state().await?)
+    }
+
+    /// Measure the round trip time of a lightweight query sent to the given peer.
+    pub async fn measure_peer_latency(&self, peer: PeerId) -> Result<Duration> {
+        let request = Request::Query(Query::CheckNodeInProblem(NetworkAddress::from_peer(peer)));
+        let start = Instant::now();
+        let _ = self.network.send_request(request, peer).await?;
+        Ok(start.elapsed())
+    }
+
     /// Sign the given data.
     ///
     /// # Arguments
@@ -411,6 +457,12 @@ impl Client {
         self.signer = Arc::new(sk);
     }
 
+    /// Hedge the chunk GETs slower than most of the recent ones with a request to another
+    /// holder, or stop hedging them with `None`. The clones of the client share the hedging.
+    pub fn set_chunk_hedging(&mut self, cfg: Option<HedgeCfg>) {
+        self.hedging = cfg.map(|cfg| Arc::new(Hedging::new(cfg)));
+    }
+
     /// Get a register from network
     ///
     /// # Arguments
@@ -586,15 +638,19 @@ impl Client {
     ///
     /// # Arguments
     /// * 'chunk' - [Chunk]
-    /// * 'payee' - [PeerId]
+    /// * 'payees' - [Vec]<[PeerId]> : The nodes paid for the chunk, to upload it to
     /// * 'payment' - [Payment]
     /// * 'verify_store' - Boolean
     /// * 'retry_strategy' - [Option]<[RetryStrategy]> : Uses Quick by default
     ///
+    #[tracing::instrument(
+        skip_all,
+        fields(record_key = %PrettyPrintRecordKey::from(&chunk.network_address().to_record_key()))
+    )]
     pub(super) async fn store_chunk(
         &self,
         chunk: Chunk,
-        payee: PeerId,
+        payees: Vec<PeerId>,
         payment: Payment,
         verify_store: bool,
         retry_strategy: Option<RetryStrategy>,
@@ -639,7 +695,7 @@ impl Client {
         let put_cfg = PutRecordCfg {
             put_quorum: Quorum::One,
             retry_strategy,
-            use_put_record_to: Some(vec![payee]),
+            use_put_record_to: Some(payees),
             verification,
         };
         Ok(self.network.put_record(record, &put_cfg).await?)
@@ -675,6 +731,12 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
+    #[tracing::instrument(
+        skip_all,
+        fields(record_key = %PrettyPrintRecordKey::from(
+            &NetworkAddress::from_chunk_address(address).to_record_key()
+        ))
+    )]
     pub async fn get_chunk(
         &self,
         address: ChunkAddress,
@@ -703,7 +765,13 @@ impl Client {
             target_record: None,
             expected_holders,
         };
-        let record = self.network.get_record_from_network(key, &get_cfg).await?;
+        let record = match &self.hedging {
+            Some(hedging) => {
+                self.get_chunk_record_hedged(hedging, address, key, &get_cfg)
+                    .await?
+            }
+            None => self.network.get_record_from_network(key, &get_cfg).await?,
+        };
         let header = RecordHeader::from_record(&record)?;
         if let RecordKind::Chunk = header.kind {
             let chunk: Chunk = try_deserialize_record(&record)?;
@@ -740,6 +808,80 @@ impl Client {
         Ok(())
     }
 
+    /// Get the number of close nodes holding a `Chunk`, each of them having to prove it holds the
+    /// chunk.
+    pub async fn count_chunk_replicas(&self, chunk: &Chunk) -> Result<usize> {
+        let address = chunk.network_address();
+        let random_nonce = thread_rng().gen::<u64>();
+        let record_value = try_serialize_record(&chunk, RecordKind::Chunk)?;
+        let expected_proof = ChunkProof::new(record_value.as_ref(), random_nonce);
+
+        Ok(self
+            .network
+            .count_chunk_replicas(address, random_nonce, expected_proof)
+            .await?)
+    }
+
+    /// Ask each node of the close group of the address whether it holds the record and the
+    /// digest of its copy, to find out where the group diverges, e.g. when fetching the record
+    /// only succeeds now and then.
+    pub async fn check_close_group_consistency(
+        &self,
+        address: &NetworkAddress,
+    ) -> Result<CloseGroupConsistency> {
+        Ok(self
+            .network
+            .check_close_group_consistency(address, false)
+            .await?)
+    }
+
+    /// Get the receipts a paid node signed when it accepted the PUTs of records, as a proof of
+    /// the payments it was made for the quotes, each given by the address of the record and the id
+    /// of the quote.
+    ///
+    /// The receipts are asked for in as few queries as possible. The ones the node does not have,
+    /// or that do not match the payment, are left out.
+    pub async fn get_payment_receipts(
+        &self,
+        payee: PeerId,
+        receipts: Vec<(NetworkAddress, XorName)>,
+    ) -> Result<BTreeMap<NetworkAddress, SignedPaymentReceipt>> {
+        let mut found = BTreeMap::new();
+        for asked in receipts.chunks(MAX_PAYMENT_RECEIPTS_PER_QUERY) {
+            let request = Request::Query(Query::GetPaymentReceipts(asked.to_vec()));
+            let results = match self.network.send_request(request, payee).await? {
+                Response::Query(QueryResponse::GetPaymentReceipts(results)) => results,
+                other => {
+                    warn!("Unexpected response to the payment receipts query: {other:?}");
+                    return Err(ProtocolError::ReceiptInvalid.into());
+                }
+            };
+
+            for ((address, quote_id), result) in asked.iter().zip(results) {
+                let signed = match result {
+                    Ok(signed) => signed,
+                    Err(err) => {
+                        warn!("No payment receipt of {payee:?} for {address:?}: {err:?}");
+                        continue;
+                    }
+                };
+                match signed.verify() {
+                    Ok((signer, receipt))
+                        if signer == payee
+                            && &receipt.record == address
+                            && &receipt.quote_id == quote_id =>
+                    {
+                        let _ = found.insert(address.clone(), signed);
+                    }
+                    _ => error!(
+                        "The payment receipt of {payee:?} for {address:?} does not match the payment"
+                    ),
+                }
+            }
+        }
+        Ok(found)
+    }
+
     /// Verify if a `Register` is stored by expected nodes on the network.
     ///
     /// # Arguments
@@ -926,11 +1068,47 @@ impl Client {
 
     /// This is a similar funcation to `get_spend_from_network` to get a spend from network.
     /// Just using different `RetryStrategy` to improve the performance during crawling.
+    ///
+    /// The spends crawled recently are served from the `SpendCache`, which is for the audits of
+    /// the spend DAG only: a double spend showing up since is not noticed until the spend
+    /// expires from the cache.
     pub async fn crawl_spend_from_network(&self, address: SpendAddress) -> Result<SignedSpend> {
+        if let Some(spend) = self.network.spend_cache().get(&address) {
+            return Ok(spend);
+        }
+        let result = self
+            .try_fetch_spend_from_network(
+                address,
+                GetRecordCfg {
+                    get_quorum: Quorum::Majority,
+                    retry_strategy: None,
+                    target_record: None,
+                    expected_holders: Default::default(),
+                },
+            )
+            .await;
+        match &result {
+            Ok(spend) => {
+                let _ = self.network.spend_cache().insert(spend);
+            }
+            Err(Error::Network(NetworkError::DoubleSpendAttempt(_))) => {
+                self.network.spend_cache().remove(&address);
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Get a spend from the network once, with the given quorum of the close group holding it.
+    pub(crate) async fn get_spend_with_quorum(
+        &self,
+        address: SpendAddress,
+        quorum: Quorum,
+    ) -> Result<SignedSpend> {
         self.try_fetch_spend_from_network(
             address,
             GetRecordCfg {
-                get_quorum: Quorum::Majority,
+                get_quorum: quorum,
                 retry_strategy: None,
                 target_record: None,
                 expected_holders: Default::default(),
@@ -982,7 +1160,7 @@ impl Client {
         match signed_spend.verify() {
             Ok(()) => {
                 trace!("Verified signed spend got from network for {address:?}");
-                Ok(signed_spend.clone())
+                Ok(signed_spend)
             }
             Err(err) => {
                 warn!("Invalid signed spend got from network for {address:?}: {err:?}.");
@@ -8,10 +8,14 @@
 
 mod dag_crawling;
 mod dag_error;
+mod dag_export;
+mod royalties;
 mod spend_dag;
 
 #[cfg(test)]
 mod tests;
 
 pub use dag_error::{DagError, SpendFault};
+pub use dag_export::{DagExportFilter, DagExportFormat};
+pub use royalties::{RoyaltyPayment, RoyaltyPeriod, RoyaltyReport};
 pub use spend_dag::{SpendDag, SpendDagGet};
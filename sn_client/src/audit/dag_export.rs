@@ -0,0 +1,384 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::spend_dag::{DagEntry, DagIndex, SpendDag};
+use petgraph::{graph::NodeIndex, visit::EdgeRef};
+use serde::Serialize;
+use sn_transfers::{NanoTokens, SignedSpend, SpendAddress, SpendReason};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// The formats the spend DAG can be exported to, to be analysed with standard graph tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DagExportFormat {
+    /// Graphviz DOT, the amounts and reasons in the labels.
+    Dot,
+    /// GraphML, the amounts and reasons as attributes of the nodes and edges.
+    GraphMl,
+    /// One JSON object per line, the spends first then the transfers between them.
+    JsonLines,
+}
+
+impl FromStr for DagExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dot" => Ok(Self::Dot),
+            "graphml" => Ok(Self::GraphMl),
+            "jsonl" | "json-lines" => Ok(Self::JsonLines),
+            _ => Err(format!(
+                "Unknown DAG export format {s}, expected one of: dot, graphml, jsonl"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DagExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dot => write!(f, "dot"),
+            Self::GraphMl => write!(f, "graphml"),
+            Self::JsonLines => write!(f, "jsonl"),
+        }
+    }
+}
+
+/// Selects the transfers of the DAG to export, all of them by default.
+///
+/// Spends carry no timestamp, so the generation of a spend, its distance in transfers from the
+/// source of the DAG, stands for the time it happened at. The spends exported are the ends of the
+/// transfers selected.
+#[derive(Debug, Clone, Default)]
+pub struct DagExportFilter {
+    /// Only the transfers from or to one of these addresses, any if empty.
+    pub addresses: BTreeSet<SpendAddress>,
+    /// Only the transfers of at least this amount.
+    pub min_amount: Option<NanoTokens>,
+    /// Only the transfers of at most this amount.
+    pub max_amount: Option<NanoTokens>,
+    /// Only the transfers made by spends of at least this generation, the source being 0.
+    pub min_generation: Option<usize>,
+    /// Only the transfers made by spends of at most this generation.
+    pub max_generation: Option<usize>,
+}
+
+impl DagExportFilter {
+    fn matches(&self, transfer: &ExportTransfer, generations: &BTreeMap<DagIndex, usize>) -> bool {
+        if !self.addresses.is_empty()
+            && !self.addresses.contains(&transfer.from_address)
+            && !self.addresses.contains(&transfer.to_address)
+        {
+            return false;
+        }
+        if self.min_amount.is_some_and(|min| transfer.amount < min)
+            || self.max_amount.is_some_and(|max| transfer.amount > max)
+        {
+            return false;
+        }
+        if self.min_generation.is_none() && self.max_generation.is_none() {
+            return true;
+        }
+        // the spends not reachable from the source are of no known generation
+        generations.get(&transfer.from).is_some_and(|generation| {
+            self.min_generation.is_none_or(|min| *generation >= min)
+                && self.max_generation.is_none_or(|max| *generation <= max)
+        })
+    }
+}
+
+/// A spend of the DAG, or a UTXO, as exported.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename = "spend")]
+struct ExportSpend {
+    id: DagIndex,
+    address: String,
+    status: &'static str,
+    /// The total amount given to the descendants, none for UTXOs
+    amount: Option<u64>,
+    reason: String,
+    generation: Option<usize>,
+    faults: Vec<String>,
+}
+
+/// A transfer between two spends of the DAG.
+#[derive(Debug)]
+struct ExportTransfer {
+    from: DagIndex,
+    to: DagIndex,
+    from_address: SpendAddress,
+    to_address: SpendAddress,
+    amount: NanoTokens,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename = "transfer")]
+struct JsonTransfer {
+    from: DagIndex,
+    to: DagIndex,
+    from_address: String,
+    to_address: String,
+    amount: u64,
+}
+
+impl SpendDag {
+    /// Write the transfers of the DAG selected by the filter, and the spends at their ends, in the
+    /// given format.
+    pub fn export<W: Write>(
+        &self,
+        format: DagExportFormat,
+        filter: &DagExportFilter,
+        mut writer: W,
+    ) -> io::Result<()> {
+        let generations = self.generations();
+        let transfers: Vec<ExportTransfer> = self
+            .dag
+            .edge_references()
+            .map(|edge| ExportTransfer {
+                from: edge.source().index(),
+                to: edge.target().index(),
+                from_address: self.dag[edge.source()],
+                to_address: self.dag[edge.target()],
+                amount: *edge.weight(),
+            })
+            .filter(|transfer| filter.matches(transfer, &generations))
+            .collect();
+        let ends: BTreeSet<DagIndex> = transfers
+            .iter()
+            .flat_map(|transfer| [transfer.from, transfer.to])
+            .collect();
+        let spends: Vec<ExportSpend> = self
+            .export_spends(&generations)
+            .into_iter()
+            .filter(|spend| ends.contains(&spend.id))
+            .collect();
+
+        match format {
+            DagExportFormat::Dot => write_dot(&mut writer, &spends, &transfers),
+            DagExportFormat::GraphMl => write_graphml(&mut writer, &spends, &transfers),
+            DagExportFormat::JsonLines => write_json_lines(&mut writer, &spends, &transfers),
+        }
+    }
+
+    /// The generation of every spend reachable from the source, by graph index.
+    fn generations(&self) -> BTreeMap<DagIndex, usize> {
+        let mut generations = BTreeMap::new();
+        let mut to_visit = VecDeque::new();
+        if let Some(entry) = self.spends.get(&self.source()) {
+            to_visit.extend(entry.indexes().into_iter().map(|idx| (idx, 0)));
+        }
+        while let Some((idx, generation)) = to_visit.pop_front() {
+            if generations.contains_key(&idx) {
+                continue;
+            }
+            let _ = generations.insert(idx, generation);
+            to_visit.extend(
+                self.dag
+                    .neighbors_directed(NodeIndex::new(idx), petgraph::Direction::Outgoing)
+                    .map(|child| (child.index(), generation + 1)),
+            );
+        }
+        generations
+    }
+
+    fn export_spends(&self, generations: &BTreeMap<DagIndex, usize>) -> Vec<ExportSpend> {
+        let mut spends = vec![];
+        for (address, entry) in &self.spends {
+            let faults: Vec<String> = self
+                .get_spend_faults(address)
+                .iter()
+                .map(|fault| fault.to_string())
+                .collect();
+            let mut push = |id: DagIndex, status, spend: Option<&SignedSpend>| {
+                spends.push(ExportSpend {
+                    id,
+                    address: address.to_hex(),
+                    status,
+                    amount: spend.map(|spend| spend.amount().as_nano()),
+                    reason: spend
+                        .map(|spend| reason_label(spend.reason()))
+                        .unwrap_or_default(),
+                    generation: generations.get(&id).copied(),
+                    faults: faults.clone(),
+                })
+            };
+            match entry {
+                DagEntry::NotGatheredYet(idx) => push(*idx, "utxo", None),
+                DagEntry::Spend(spend, idx) => push(*idx, "spend", Some(spend)),
+                DagEntry::DoubleSpend(double_spends) => {
+                    for (spend, idx) in double_spends {
+                        push(*idx, "double_spend", Some(spend));
+                    }
+                }
+            }
+        }
+        spends.sort_by_key(|spend| spend.id);
+        spends
+    }
+}
+
+/// The reason of a spend, readable and without the encrypted contents.
+fn reason_label(reason: &SpendReason) -> String {
+    match reason {
+        SpendReason::None => String::new(),
+        SpendReason::NetworkData(xorname) => format!("network_data:{}", hex::encode(xorname)),
+        SpendReason::Custom(bytes) => format!("custom:{}", hex::encode(bytes)),
+        SpendReason::BetaRewardTracking(_) => "beta_reward_tracking".to_string(),
+    }
+}
+
+fn write_dot<W: Write>(
+    writer: &mut W,
+    spends: &[ExportSpend],
+    transfers: &[ExportTransfer],
+) -> io::Result<()> {
+    writeln!(writer, "digraph spend_dag {{")?;
+    for spend in spends {
+        let mut label = spend.address[..8].to_string();
+        if let Some(amount) = spend.amount {
+            label.push_str(&format!("\\n{amount} nanos"));
+        }
+        if !spend.reason.is_empty() {
+            label.push_str(&format!("\\n{}", spend.reason));
+        }
+        let style = match spend.status {
+            "utxo" => ", style=dashed",
+            "double_spend" => ", color=red",
+            _ if !spend.faults.is_empty() => ", color=orange",
+            _ => "",
+        };
+        writeln!(
+            writer,
+            "    {} [label=\"{label}\", tooltip=\"{}\"{style}];",
+            spend.id, spend.address
+        )?;
+    }
+    for transfer in transfers {
+        writeln!(
+            writer,
+            "    {} -> {} [label=\"{}\"];",
+            transfer.from,
+            transfer.to,
+            transfer.amount.as_nano()
+        )?;
+    }
+    writeln!(writer, "}}")
+}
+
+fn write_graphml<W: Write>(
+    writer: &mut W,
+    spends: &[ExportSpend],
+    transfers: &[ExportTransfer],
+) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    for (id, name, attr_type) in [
+        ("address", "address", "string"),
+        ("status", "status", "string"),
+        ("spent", "amount", "long"),
+        ("reason", "reason", "string"),
+        ("generation", "generation", "int"),
+        ("faults", "faults", "string"),
+    ] {
+        writeln!(
+            writer,
+            r#"  <key id="{id}" for="node" attr.name="{name}" attr.type="{attr_type}"/>"#
+        )?;
+    }
+    writeln!(
+        writer,
+        r#"  <key id="amount" for="edge" attr.name="amount" attr.type="long"/>"#
+    )?;
+    writeln!(writer, r#"  <graph id="spend_dag" edgedefault="directed">"#)?;
+    for spend in spends {
+        writeln!(writer, r#"    <node id="n{}">"#, spend.id)?;
+        writeln!(
+            writer,
+            r#"      <data key="address">{}</data>"#,
+            spend.address
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="status">{}</data>"#,
+            spend.status
+        )?;
+        if let Some(amount) = spend.amount {
+            writeln!(writer, r#"      <data key="spent">{amount}</data>"#)?;
+        }
+        if !spend.reason.is_empty() {
+            writeln!(
+                writer,
+                r#"      <data key="reason">{}</data>"#,
+                xml_escape(&spend.reason)
+            )?;
+        }
+        if let Some(generation) = spend.generation {
+            writeln!(
+                writer,
+                r#"      <data key="generation">{generation}</data>"#
+            )?;
+        }
+        if !spend.faults.is_empty() {
+            writeln!(
+                writer,
+                r#"      <data key="faults">{}</data>"#,
+                xml_escape(&spend.faults.join("; "))
+            )?;
+        }
+        writeln!(writer, "    </node>")?;
+    }
+    for transfer in transfers {
+        writeln!(
+            writer,
+            r#"    <edge source="n{}" target="n{}"><data key="amount">{}</data></edge>"#,
+            transfer.from,
+            transfer.to,
+            transfer.amount.as_nano()
+        )?;
+    }
+    writeln!(writer, "  </graph>")?;
+    writeln!(writer, "</graphml>")
+}
+
+fn write_json_lines<W: Write>(
+    writer: &mut W,
+    spends: &[ExportSpend],
+    transfers: &[ExportTransfer],
+) -> io::Result<()> {
+    for spend in spends {
+        serde_json::to_writer(&mut *writer, spend)?;
+        writeln!(writer)?;
+    }
+    for transfer in transfers {
+        let transfer = JsonTransfer {
+            from: transfer.from,
+            to: transfer.to,
+            from_address: transfer.from_address.to_hex(),
+            to_address: transfer.to_address.to_hex(),
+            amount: transfer.amount.as_nano(),
+        };
+        serde_json::to_writer(&mut *writer, &transfer)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
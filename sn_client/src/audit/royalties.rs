@@ -0,0 +1,184 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Client, SpendDag};
+use serde::{Deserialize, Serialize};
+use sn_transfers::{Hash, MainPubkey, NanoTokens, SignedSpend, SpendAddress, UniquePubkey};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A royalty output of a spend, paid to one of the royalty keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoyaltyPayment {
+    /// The spend paying the royalty
+    pub payer: SpendAddress,
+    /// The royalty key the output is derived from
+    pub payee: MainPubkey,
+    /// The output holding the royalty
+    pub unique_pubkey: UniquePubkey,
+    pub amount: NanoTokens,
+}
+
+/// The royalties of the spends gathered during a period, between two updates of a report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoyaltyPeriod {
+    /// When the period ended, in seconds since the Unix epoch
+    pub ended_at: u64,
+    /// The number of royalty outputs
+    pub payments: u64,
+    pub total: NanoTokens,
+    pub per_payee: BTreeMap<MainPubkey, NanoTokens>,
+}
+
+/// Royalty statistics of the spend DAG, updated incrementally as the DAG grows.
+///
+/// Spends carry no timestamp, so the periods are those between the updates of the report, each
+/// counting the royalties of the spends gathered since the previous one. Saved to disk, the report
+/// is the checkpoint the next update resumes from: the spends already counted are never counted
+/// twice. Anyone holding the same DAG recomputes the same totals.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoyaltyReport {
+    /// The hashes of the spends counted already
+    counted: BTreeSet<Hash>,
+    /// The periods counted so far, oldest first
+    pub periods: Vec<RoyaltyPeriod>,
+}
+
+impl RoyaltyReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let report: RoyaltyReport = rmp_serde::from_slice(&bytes)?;
+        Ok(report)
+    }
+
+    pub fn dump_to_file<P: AsRef<Path>>(&self, path: P) -> crate::Result<()> {
+        let bytes = rmp_serde::to_vec(&self)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// The royalties of all the periods
+    pub fn total(&self) -> NanoTokens {
+        self.periods
+            .iter()
+            .fold(NanoTokens::zero(), |total, period| {
+                add_tokens(total, period.total)
+            })
+    }
+
+    /// The royalties of all the periods, per royalty key
+    pub fn per_payee(&self) -> BTreeMap<MainPubkey, NanoTokens> {
+        let mut per_payee = BTreeMap::new();
+        for period in &self.periods {
+            for (payee, amount) in &period.per_payee {
+                let total = per_payee.entry(*payee).or_insert(NanoTokens::zero());
+                *total = add_tokens(*total, *amount);
+            }
+        }
+        per_payee
+    }
+
+    /// Count the royalties paid to the `royalty_keys` by the spends of the DAG not counted yet,
+    /// as a new period ending at `ended_at` seconds since the Unix epoch
+    pub fn update(
+        &mut self,
+        dag: &SpendDag,
+        royalty_keys: &[MainPubkey],
+        ended_at: u64,
+    ) -> &RoyaltyPeriod {
+        let mut period = RoyaltyPeriod {
+            ended_at,
+            payments: 0,
+            total: NanoTokens::zero(),
+            per_payee: BTreeMap::new(),
+        };
+        for spend in dag.all_spends() {
+            if !dag.get_spend_faults(&spend.address()).is_empty()
+                || !self.counted.insert(spend.spend.hash())
+            {
+                continue;
+            }
+            for payment in royalty_payments(spend, royalty_keys) {
+                period.payments += 1;
+                period.total = add_tokens(period.total, payment.amount);
+                let payee_total = period
+                    .per_payee
+                    .entry(payment.payee)
+                    .or_insert(NanoTokens::zero());
+                *payee_total = add_tokens(*payee_total, payment.amount);
+            }
+        }
+        debug!(
+            "Counted {} royalties for a total of {} in the period ending at {ended_at}",
+            period.payments, period.total
+        );
+        self.periods.push(period);
+        &self.periods[self.periods.len() - 1]
+    }
+}
+
+impl SpendDag {
+    /// All the royalties paid to the `royalty_keys` by the spends of the DAG, the faulty spends
+    /// left aside
+    pub fn royalty_payments(&self, royalty_keys: &[MainPubkey]) -> Vec<RoyaltyPayment> {
+        self.all_spends()
+            .into_iter()
+            .filter(|spend| self.get_spend_faults(&spend.address()).is_empty())
+            .flat_map(|spend| royalty_payments(spend, royalty_keys))
+            .collect()
+    }
+}
+
+impl Client {
+    /// Extends the DAG from its UTXOs, then counts the royalties of the spends it gathered as a new
+    /// period of the report, ending now
+    pub async fn update_royalty_report(
+        &self,
+        dag: &mut SpendDag,
+        report: &mut RoyaltyReport,
+        royalty_keys: &[MainPubkey],
+    ) -> RoyaltyPeriod {
+        self.spend_dag_continue_from_utxos(dag, None, true).await;
+        let ended_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        report.update(dag, royalty_keys, ended_at).clone()
+    }
+}
+
+/// The outputs of the spend derived from one of the royalty keys by its royalty derivation indexes
+fn royalty_payments(spend: &SignedSpend, royalty_keys: &[MainPubkey]) -> Vec<RoyaltyPayment> {
+    let mut payments = vec![];
+    for derivation_index in &spend.spend.royalties {
+        for payee in royalty_keys {
+            let unique_pubkey = payee.new_unique_pubkey(derivation_index);
+            if let Some(amount) = spend.spend.get_output_amount(&unique_pubkey) {
+                payments.push(RoyaltyPayment {
+                    payer: spend.address(),
+                    payee: *payee,
+                    unique_pubkey,
+                    amount,
+                });
+            }
+        }
+    }
+    payments
+}
+
+/// The total supply fits in a u64, so sums of royalties cannot overflow
+fn add_tokens(a: NanoTokens, b: NanoTokens) -> NanoTokens {
+    NanoTokens::from(a.as_nano().saturating_add(b.as_nano()))
+}
@@ -39,27 +39,27 @@ use super::dag_error::{DagError, SpendFault};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpendDag {
     /// A directed graph of spend addresses
-    dag: DiGraph<SpendAddress, NanoTokens>,
+    pub(super) dag: DiGraph<SpendAddress, NanoTokens>,
     /// All the spends refered to in the dag indexed by their SpendAddress
-    spends: BTreeMap<SpendAddress, DagEntry>,
+    pub(super) spends: BTreeMap<SpendAddress, DagEntry>,
     /// The source of the DAG (aka Genesis)
     source: SpendAddress,
     /// Recorded faults in the DAG
-    faults: BTreeMap<SpendAddress, BTreeSet<SpendFault>>,
+    pub(super) faults: BTreeMap<SpendAddress, BTreeSet<SpendFault>>,
 }
 
-type DagIndex = usize;
+pub(super) type DagIndex = usize;
 
 /// Internal Dag entry type
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
-enum DagEntry {
+pub(super) enum DagEntry {
     NotGatheredYet(DagIndex),
     DoubleSpend(Vec<(SignedSpend, DagIndex)>),
     Spend(Box<SignedSpend>, DagIndex),
 }
 
 impl DagEntry {
-    fn indexes(&self) -> Vec<DagIndex> {
+    pub(super) fn indexes(&self) -> Vec<DagIndex> {
         match self {
             DagEntry::NotGatheredYet(idx) => vec![*idx],
             DagEntry::DoubleSpend(spends) => spends.iter().map(|(_, idx)| *idx).collect(),
@@ -13,9 +13,9 @@ use std::collections::BTreeSet;
 use setup::MockNetwork;
 
 use eyre::Result;
-use sn_transfers::SpendAddress;
+use sn_transfers::{MainSecretKey, NanoTokens, SpendAddress};
 
-use crate::{SpendDag, SpendFault};
+use crate::{DagExportFilter, DagExportFormat, RoyaltyReport, SpendDag, SpendFault};
 
 #[test]
 fn test_spend_dag_verify_valid_simple() -> Result<()> {
@@ -476,3 +476,116 @@ fn test_spend_dag_orphans() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn test_spend_dag_export_filters_transfers() -> Result<()> {
+    let mut net = MockNetwork::genesis()?;
+    let genesis = net.genesis_spend;
+
+    let owner1 = net.new_pk_with_balance(100)?;
+    let owner2 = net.new_pk_with_balance(0)?;
+    let owner3 = net.new_pk_with_balance(0)?;
+    net.send(&owner1, &owner2, 100)?;
+    let last_spend = net.send(&owner2, &owner3, 100)?;
+
+    let mut dag = SpendDag::new(genesis);
+    for spend in net.spends {
+        dag.insert(spend.address(), spend.clone());
+    }
+
+    let export_lines = |filter: &DagExportFilter| -> Result<Vec<serde_json::Value>> {
+        let mut bytes = vec![];
+        dag.export(DagExportFormat::JsonLines, filter, &mut bytes)?;
+        Ok(String::from_utf8(bytes)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?)
+    };
+    let transfers = |lines: &[serde_json::Value]| {
+        lines
+            .iter()
+            .filter(|line| line["kind"] == "transfer")
+            .count()
+    };
+
+    // genesis to itself, then to owner1 and its change, owner1 to owner2, owner2 to owner3
+    let all = export_lines(&DagExportFilter::default())?;
+    assert_eq!(transfers(&all), 5);
+    assert_eq!(all.len(), 5 + 6);
+
+    let small = export_lines(&DagExportFilter {
+        max_amount: Some(NanoTokens::from(100)),
+        ..Default::default()
+    })?;
+    assert_eq!(transfers(&small), 3);
+
+    let late = export_lines(&DagExportFilter {
+        min_generation: Some(3),
+        ..Default::default()
+    })?;
+    assert_eq!(transfers(&late), 1);
+    let spend = late
+        .iter()
+        .find(|line| line["status"] == "spend")
+        .ok_or_else(|| eyre::eyre!("the spend of owner2 to be exported"))?;
+    assert_eq!(spend["address"], last_spend[0].to_hex());
+    assert_eq!(spend["amount"], 100);
+    assert_eq!(spend["generation"], 3);
+
+    let by_address = export_lines(&DagExportFilter {
+        addresses: BTreeSet::from_iter(last_spend.clone()),
+        ..Default::default()
+    })?;
+    assert_eq!(transfers(&by_address), 2);
+
+    let mut dot = vec![];
+    dag.export(DagExportFormat::Dot, &DagExportFilter::default(), &mut dot)?;
+    assert!(String::from_utf8(dot)?.starts_with("digraph spend_dag {"));
+    let mut graphml = vec![];
+    dag.export(
+        DagExportFormat::GraphMl,
+        &DagExportFilter::default(),
+        &mut graphml,
+    )?;
+    assert_eq!(String::from_utf8(graphml)?.matches("<edge ").count(), 5);
+    Ok(())
+}
+
+#[test]
+fn test_royalty_report_counts_new_spends_once() -> Result<()> {
+    let mut net = MockNetwork::genesis()?;
+    let genesis = net.genesis_spend;
+    let royalty_pk1 = MainSecretKey::random().main_pubkey();
+    let royalty_pk2 = MainSecretKey::random().main_pubkey();
+
+    let owner1 = net.new_pk_with_balance(1000)?;
+    let owner2 = net.new_pk_with_balance(0)?;
+    net.send_with_royalty(&owner1, &owner2, 500, Some((royalty_pk1, 5)))?;
+
+    let mut dag = SpendDag::new(genesis);
+    for spend in &net.spends {
+        dag.insert(spend.address(), spend.clone());
+    }
+    let mut report = RoyaltyReport::new();
+    let period = report.update(&dag, &[royalty_pk1, royalty_pk2], 1).clone();
+    assert_eq!(period.payments, 1);
+    assert_eq!(period.total, NanoTokens::from(5));
+    assert_eq!(dag.royalty_payments(&[royalty_pk1]).len(), 1);
+
+    // only the spends gathered since the checkpoint are counted in the next period
+    net.send_with_royalty(&owner2, &owner1, 100, Some((royalty_pk2, 7)))?;
+    net.send_with_royalty(&owner1, &owner2, 100, Some((royalty_pk1, 3)))?;
+    for spend in &net.spends {
+        dag.insert(spend.address(), spend.clone());
+    }
+    let period = report.update(&dag, &[royalty_pk1, royalty_pk2], 2).clone();
+    assert_eq!(period.payments, 2);
+    assert_eq!(period.total, NanoTokens::from(10));
+
+    assert_eq!(report.periods.len(), 2);
+    assert_eq!(report.total(), NanoTokens::from(15));
+    let per_payee = report.per_payee();
+    assert_eq!(per_payee.get(&royalty_pk1), Some(&NanoTokens::from(8)));
+    assert_eq!(per_payee.get(&royalty_pk2), Some(&NanoTokens::from(7)));
+    Ok(())
+}
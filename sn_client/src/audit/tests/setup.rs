@@ -87,6 +87,17 @@ impl MockNetwork {
         from: &MainPubkey,
         to: &MainPubkey,
         amount: u64,
+    ) -> Result<Vec<SpendAddress>> {
+        self.send_with_royalty(from, to, amount, None)
+    }
+
+    /// Send, also paying a royalty of the given amount to the given key
+    pub fn send_with_royalty(
+        &mut self,
+        from: &MainPubkey,
+        to: &MainPubkey,
+        amount: u64,
+        royalty: Option<(MainPubkey, u64)>,
     ) -> Result<Vec<SpendAddress>> {
         let mut rng = rand::thread_rng();
         let from_wallet = self
@@ -100,12 +111,20 @@ impl MockNetwork {
 
         // perform offline transfer
         let derivation_index = DerivationIndex::random(&mut rng);
-        let recipient = vec![(
+        let mut recipient = vec![(
             NanoTokens::from(amount),
             to_wallet.sk.main_pubkey(),
             derivation_index,
             false,
         )];
+        if let Some((royalty_pk, royalty_amount)) = royalty {
+            recipient.push((
+                NanoTokens::from(royalty_amount),
+                royalty_pk,
+                DerivationIndex::random(&mut rng),
+                true,
+            ));
+        }
         let tx = SignedTransaction::new(
             from_wallet.cn.clone(),
             recipient,
@@ -135,9 +154,13 @@ impl MockNetwork {
         self.wallets
             .entry(*from)
             .and_modify(|w| w.cn = updated_from_wallet_cns);
-        self.wallets
-            .entry(*to)
-            .and_modify(|w| w.cn.extend(tx.output_cashnotes));
+        self.wallets.entry(*to).and_modify(|w| {
+            w.cn.extend(
+                tx.output_cashnotes
+                    .into_iter()
+                    .filter(|cn| cn.main_pubkey() == to),
+            )
+        });
 
         // update network spends
         let spent_addrs = spends.iter().map(|s| s.address()).collect();
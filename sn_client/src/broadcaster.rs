@@ -0,0 +1,197 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Client, Error, Result};
+use sn_transfers::{HotWallet, ResendPolicy, SignedSpend, SpendAddress, WalletError};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+/// The events emitted while broadcasting the unconfirmed spends.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BroadcastEvent {
+    /// The spend has been registered in the network, it is removed from the unconfirmed ones.
+    Confirmed(SpendAddress),
+    /// The cash note of the spend was already spent by another one, the spend is dropped.
+    DoubleSpent(SpendAddress),
+    /// The spend could not be registered. It is broadcast again once its backoff has elapsed.
+    Failed {
+        address: SpendAddress,
+        attempts: u32,
+        error: String,
+    },
+    /// The spend is older than the max age of the resend policy, it is given up.
+    Expired(SpendAddress),
+}
+
+/// The outcome of a round of broadcasts of the unconfirmed spends.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BroadcastSummary {
+    pub confirmed: usize,
+    pub double_spent: usize,
+    pub failed: usize,
+    pub expired: usize,
+}
+
+impl BroadcastSummary {
+    pub fn broadcast(&self) -> usize {
+        self.confirmed + self.double_spent + self.failed
+    }
+}
+
+/// Broadcasts the unconfirmed spends of a wallet until they are registered in the network,
+/// backing off on each spend failing to be as per the [`ResendPolicy`].
+///
+/// The attempts are recorded in the unconfirmed spend metadata of the wallet, for the backoff to
+/// carry over restarts and be shared with the other resends of its spends.
+///
+/// The broadcaster owns the wallet while it runs, for the unconfirmed spends not to be sent
+/// concurrently by someone else, and gives it back once they are all confirmed.
+pub struct TransactionBroadcaster {
+    client: Client,
+    wallet: HotWallet,
+    policy: ResendPolicy,
+    /// Whether to fetch the spends back from the network to confirm them.
+    verify_store: bool,
+    event_sender: Option<mpsc::Sender<BroadcastEvent>>,
+}
+
+impl TransactionBroadcaster {
+    pub fn new(
+        client: Client,
+        wallet: HotWallet,
+        policy: ResendPolicy,
+        verify_store: bool,
+    ) -> Self {
+        Self {
+            client,
+            wallet,
+            policy,
+            verify_store,
+            event_sender: None,
+        }
+    }
+
+    /// Get a receiver of the events of the broadcasts.
+    pub fn get_event_receiver(&mut self) -> mpsc::Receiver<BroadcastEvent> {
+        let (tx, rx) = mpsc::channel(100);
+        self.event_sender = Some(tx);
+        rx
+    }
+
+    /// Give the wallet back, whether its spends are all confirmed or not.
+    pub fn into_wallet(self) -> HotWallet {
+        self.wallet
+    }
+
+    /// Give up the expired unconfirmed spends, then broadcast the ones due as per the policy.
+    pub async fn broadcast_due(&mut self) -> Result<BroadcastSummary> {
+        let mut summary = BroadcastSummary::default();
+        for signed_spend in self.wallet.prune_expired_spend_requests(&self.policy)? {
+            let address = signed_spend.address();
+            warn!("Unconfirmed spend {address:?} has expired, giving it up");
+            summary.expired += 1;
+            self.send_event(BroadcastEvent::Expired(address)).await?;
+        }
+
+        for signed_spend in self.wallet.due_unconfirmed_spend_requests(&self.policy) {
+            // dropped along with an earlier double spend of the round
+            if !self
+                .wallet
+                .unconfirmed_spend_requests()
+                .contains(&signed_spend)
+            {
+                continue;
+            }
+            self.broadcast(signed_spend, &mut summary).await?;
+        }
+        Ok(summary)
+    }
+
+    /// Broadcast the unconfirmed spends as they get due, until they are all confirmed or an
+    /// error occurs. Returns the wallet once they are.
+    pub async fn run(mut self) -> Result<HotWallet> {
+        loop {
+            let summary = self.broadcast_due().await?;
+            if summary.broadcast() > 0 || summary.expired > 0 {
+                info!(
+                    "Broadcast {} unconfirmed spends: {summary:?}",
+                    summary.broadcast()
+                );
+            }
+            let next_resend_at = match self.wallet.next_resend_at(&self.policy) {
+                Some(next_resend_at) => next_resend_at,
+                None => {
+                    info!("All the unconfirmed spends of the wallet are confirmed");
+                    return Ok(self.wallet);
+                }
+            };
+            let next_due_in = next_resend_at
+                .duration_since(SystemTime::now())
+                .unwrap_or_default();
+            tokio::time::sleep(next_due_in.max(Duration::from_secs(1))).await;
+        }
+    }
+
+    async fn broadcast(
+        &mut self,
+        signed_spend: SignedSpend,
+        summary: &mut BroadcastSummary,
+    ) -> Result<()> {
+        let address = signed_spend.address();
+        let event = match self
+            .client
+            .send_spends([&signed_spend].into_iter(), self.verify_store)
+            .await
+        {
+            Ok(()) => {
+                info!("Unconfirmed spend {address:?} is now confirmed");
+                self.wallet.confirm_spend_request(&signed_spend)?;
+                summary.confirmed += 1;
+                BroadcastEvent::Confirmed(address)
+            }
+            Err(WalletError::DoubleSpendAttemptedForCashNotes(spent_cash_notes)) => {
+                warn!("Unconfirmed spend {address:?} is a double spend, dropping it");
+                for cash_note_key in spent_cash_notes.iter() {
+                    self.wallet.mark_notes_as_spent([cash_note_key]);
+                    self.wallet.clear_specific_spend_request(*cash_note_key);
+                }
+                summary.double_spent += 1;
+                BroadcastEvent::DoubleSpent(address)
+            }
+            Err(err) => {
+                let attempts = self
+                    .wallet
+                    .record_resend_attempt(address, Some(err.to_string()));
+                warn!("Could not broadcast unconfirmed spend {address:?} after {attempts} attempts: {err:?}");
+                summary.failed += 1;
+                BroadcastEvent::Failed {
+                    address,
+                    attempts,
+                    error: err.to_string(),
+                }
+            }
+        };
+
+        // persisted after every spend along with its attempts, for an interrupted round not to be
+        // started over, the confirmed ones having been by `confirm_spend_request`
+        if !matches!(event, BroadcastEvent::Confirmed(_)) {
+            self.wallet.store_unconfirmed_spend_requests()?;
+        }
+        self.send_event(event).await
+    }
+
+    async fn send_event(&self, event: BroadcastEvent) -> Result<()> {
+        if let Some(sender) = &self.event_sender {
+            sender.send(event).await.map_err(|err| {
+                error!("Could not send broadcast event due to {err:?}");
+                Error::CouldNotSendBroadcastEvent
+            })?;
+        }
+        Ok(())
+    }
+}
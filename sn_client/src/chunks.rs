@@ -6,8 +6,13 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod erasure;
 mod error;
 mod pac_man;
+mod segments;
 
+pub use self::erasure::ErasureCoding;
+pub(crate) use self::erasure::ParityGroup;
 pub(crate) use self::error::{Error, Result};
 pub(crate) use pac_man::{encrypt_large, DataMapLevel};
+pub(crate) use segments::{encrypt_segmented, segment_ranges};
@@ -0,0 +1,228 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{Error, Result};
+use bytes::Bytes;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use self_encryption::DataMap;
+use serde::{Deserialize, Serialize};
+use sn_protocol::storage::Chunk;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use xor_name::XorName;
+
+/// The erasure coding of the chunks of a file, for it to be recovered even if some of its chunks
+/// are lost: every group of `data_shards` consecutive chunks gets `parity_shards` parity chunks,
+/// and any `data_shards` chunks of a group, data or parity, are enough to rebuild the whole group.
+///
+/// The parity chunks are stored like any other chunk, so they are paid for as well: the storage
+/// cost of a file grows by `parity_shards / data_shards`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ErasureCoding {
+    data_shards: usize,
+    parity_shards: usize,
+}
+
+impl ErasureCoding {
+    /// At most 256 chunks, data and parity, can be coded together.
+    pub fn new(data_shards: usize, parity_shards: usize) -> Result<Self> {
+        if data_shards == 0 || parity_shards == 0 || data_shards + parity_shards > 256 {
+            return Err(Error::InvalidErasureCoding {
+                data_shards,
+                parity_shards,
+            });
+        }
+        Ok(Self {
+            data_shards,
+            parity_shards,
+        })
+    }
+
+    pub fn data_shards(&self) -> usize {
+        self.data_shards
+    }
+
+    pub fn parity_shards(&self) -> usize {
+        self.parity_shards
+    }
+}
+
+/// The parity chunks of a group of consecutive chunks of a file, as recorded in its data map.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ParityGroup {
+    /// The index in the data map of the first chunk of the group
+    first_index: usize,
+    /// The address and size of every chunk of the group. The chunks are padded to the size of the
+    /// largest one to be coded, and truncated back once recovered.
+    data: Vec<(XorName, usize)>,
+    parity: Vec<XorName>,
+}
+
+impl ParityGroup {
+    /// Whether the chunk of the data map at `index` belongs to the group.
+    pub(crate) fn contains(&self, index: usize) -> bool {
+        index >= self.first_index && index < self.first_index + self.data.len()
+    }
+
+    /// The addresses of the chunks of the group, its data chunks first, then its parity chunks.
+    pub(crate) fn addresses(&self) -> impl Iterator<Item = XorName> + '_ {
+        self.data
+            .iter()
+            .map(|(address, _)| *address)
+            .chain(self.parity.iter().copied())
+    }
+
+    /// The addresses of the parity chunks of the group.
+    pub(crate) fn parity_addresses(&self) -> &[XorName] {
+        &self.parity
+    }
+
+    /// Rebuilds the chunk of the data map at `index` out of the chunks of the group retrieved, in
+    /// the order of `addresses`, `None` standing for those missing.
+    pub(crate) fn recover(&self, index: usize, mut shards: Vec<Option<Vec<u8>>>) -> Result<Bytes> {
+        let position = index
+            .checked_sub(self.first_index)
+            .filter(|position| *position < self.data.len())
+            .ok_or(Error::NotInParityGroup(index))?;
+        let (address, size) = self.data[position];
+
+        let shard_size = self.shard_size();
+        for shard in shards.iter_mut().flatten() {
+            shard.resize(shard_size, 0);
+        }
+        ReedSolomon::new(self.data.len(), self.parity.len())?.reconstruct_data(&mut shards)?;
+
+        let mut recovered = shards
+            .swap_remove(position)
+            .ok_or(Error::ChunkMissing(address))?;
+        recovered.truncate(size);
+        if XorName::from_content(&recovered) != address {
+            error!(
+                "The chunk {address:?} recovered from its parity group does not match its address"
+            );
+            return Err(Error::ChunkMissing(address));
+        }
+        Ok(Bytes::from(recovered))
+    }
+
+    fn shard_size(&self) -> usize {
+        self.data
+            .iter()
+            .map(|(_, size)| *size)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// The parity groups of the chunks of a data map, along with the parity chunks written to disk.
+type EncodedParity = (Vec<ParityGroup>, Vec<(XorName, PathBuf)>);
+
+/// Computes the parity chunks of the chunks of the data map, which are read from `chunk_dir`,
+/// writing them there as well. Returns the parity groups to be recorded in the data map, along
+/// with the parity chunks to be uploaded.
+pub(crate) fn encode_parity(
+    erasure_coding: ErasureCoding,
+    data_map: &DataMap,
+    chunk_dir: &Path,
+) -> Result<EncodedParity> {
+    let mut groups = vec![];
+    let mut parity_chunks = vec![];
+    for infos in data_map.infos().chunks(erasure_coding.data_shards) {
+        let mut shards = infos
+            .iter()
+            .map(|info| fs::read(chunk_dir.join(hex::encode(info.dst_hash))))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let mut group = ParityGroup {
+            first_index: infos[0].index,
+            data: infos
+                .iter()
+                .zip(&shards)
+                .map(|(info, shard)| (info.dst_hash, shard.len()))
+                .collect(),
+            parity: vec![],
+        };
+
+        let shard_size = group.shard_size();
+        for shard in shards.iter_mut() {
+            shard.resize(shard_size, 0);
+        }
+        shards.resize(
+            infos.len() + erasure_coding.parity_shards,
+            vec![0; shard_size],
+        );
+        ReedSolomon::new(infos.len(), erasure_coding.parity_shards)?.encode(&mut shards)?;
+
+        for shard in shards.into_iter().skip(infos.len()) {
+            let chunk = Chunk::new(Bytes::from(shard));
+            let chunk_path = chunk_dir.join(hex::encode(chunk.name()));
+            fs::write(&chunk_path, chunk.value())?;
+            group.parity.push(*chunk.name());
+            parity_chunks.push((*chunk.name(), chunk_path));
+        }
+        groups.push(group);
+    }
+    debug!(
+        "Computed {} parity chunks for the {} chunks of the data map",
+        parity_chunks.len(),
+        data_map.infos().len()
+    );
+
+    Ok((groups, parity_chunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunks::{encrypt_large, DataMapLevel};
+    use rand::{thread_rng, Rng};
+
+    #[test]
+    fn lost_chunks_should_be_recovered_from_their_parity_group() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let file_path = temp_dir.path().join("file");
+        let mut content = vec![0u8; 5 * 1024 * 1024];
+        thread_rng().fill(&mut content[..]);
+        fs::write(&file_path, &content)?;
+        let chunk_dir = temp_dir.path().join("chunks");
+        fs::create_dir_all(&chunk_dir)?;
+
+        let (data_map_chunk, chunks) =
+            encrypt_large(&file_path, &chunk_dir, Some(ErasureCoding::new(3, 2)?))?;
+
+        // the data map is recorded along with the parity groups of its chunks
+        let DataMapLevel::ErasureCoded(data_map, groups) =
+            rmp_serde::from_slice(data_map_chunk.value())?
+        else {
+            panic!("The data map should record the erasure coding of its chunks");
+        };
+        let parity_count: usize = groups.iter().map(|group| group.parity.len()).sum();
+        assert_eq!(chunks.len(), data_map.infos().len() + parity_count);
+        let read = |address: XorName| fs::read(chunk_dir.join(hex::encode(address)));
+
+        for group in &groups {
+            let addresses: Vec<_> = group.addresses().collect();
+            let index = group.first_index;
+            // two chunks of the group are lost, the first of them being the one to recover
+            let mut shards = addresses
+                .iter()
+                .map(|address| read(*address).ok())
+                .collect::<Vec<_>>();
+            shards[0] = None;
+            shards[addresses.len() - 1] = None;
+            let recovered = group.recover(index, shards.clone())?;
+            assert_eq!(recovered.to_vec(), read(addresses[0])?);
+
+            // but not three of them
+            shards[1] = None;
+            assert!(group.recover(index, shards).is_err());
+        }
+        Ok(())
+    }
+}
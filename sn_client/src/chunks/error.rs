@@ -65,6 +65,21 @@ pub enum Error {
     #[error("Chunk could not be retrieved from the network: {0:?}")]
     ChunkMissing(XorName),
 
+    #[error(
+        "Invalid erasure coding of {data_shards} data chunks with {parity_shards} parity chunks: \
+        both must be positive and add up to at most 256"
+    )]
+    InvalidErasureCoding {
+        data_shards: usize,
+        parity_shards: usize,
+    },
+
+    #[error(transparent)]
+    ErasureCoding(#[from] reed_solomon_erasure::Error),
+
+    #[error("The chunk of index {0} is not part of the parity group")]
+    NotInParityGroup(usize),
+
     #[error("Not all data was chunked, expected {expected}, but we have {chunked}.)")]
     NotAllDataWasChunked {
         /// Number of Chunks expected to be generated
@@ -0,0 +1,268 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Content-defined chunking of files.
+//!
+//! A file is cut into segments where a rolling hash of its last 64 bytes matches a pattern, so the
+//! boundaries depend on the content around them rather than on their offset: inserting or
+//! removing bytes only moves the boundaries next to the edit, the rest of the file being cut as
+//! before. Each segment is self-encrypted on its own, so the chunks of the segments an edit does
+//! not reach are those of the previous version of the file.
+
+use super::{
+    pac_man::{pack_data_map, DataMapLevel},
+    Result,
+};
+use bytes::Bytes;
+use self_encryption::{DataMap, MIN_ENCRYPTABLE_BYTES};
+use sn_protocol::storage::Chunk;
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+use xor_name::XorName;
+
+/// The minimum size of a segment, but for the last one of a file.
+const MIN_SEGMENT_SIZE: usize = 512 * 1024;
+/// The maximum size of a segment, at which it is cut whatever its content.
+const MAX_SEGMENT_SIZE: usize = 8 * 1024 * 1024;
+/// A segment ends where the top 21 bits of the rolling hash are unset, 2 MiB past the minimum
+/// size on average.
+const BOUNDARY_MASK: u64 = ((1 << 21) - 1) << 43;
+
+/// The random values of the bytes in the rolling hash. Changing them changes where files are cut,
+/// and so loses the reuse of the chunks of the files uploaded before.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    // splitmix64, from a fixed seed
+    let mut table = [0; 256];
+    let mut state: u64 = 0x5afe_5afe_5afe_5afe;
+    let mut index = 0;
+    while index < table.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+}
+
+/// Finds the boundaries of the segments of a stream of bytes.
+#[derive(Default)]
+struct Segmenter {
+    len: usize,
+    hash: u64,
+}
+
+impl Segmenter {
+    /// Feeds the next byte, returning the length of the segment it ends, if it does.
+    fn push(&mut self, byte: u8) -> Option<usize> {
+        self.len += 1;
+        self.hash = (self.hash << 1).wrapping_add(GEAR[byte as usize]);
+        let at_boundary = self.len >= MAX_SEGMENT_SIZE
+            || (self.len >= MIN_SEGMENT_SIZE && self.hash & BOUNDARY_MASK == 0);
+        if !at_boundary {
+            return None;
+        }
+        let len = self.len;
+        *self = Self::default();
+        Some(len)
+    }
+}
+
+/// The lengths of the segments the content is cut into. A last segment too small to be
+/// self-encrypted is merged into the previous one.
+pub(crate) fn segment_lengths(mut reader: impl Read) -> io::Result<Vec<usize>> {
+    let mut lengths = vec![];
+    let mut segmenter = Segmenter::default();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        lengths.extend(
+            buffer[..read]
+                .iter()
+                .filter_map(|byte| segmenter.push(*byte)),
+        );
+    }
+
+    match lengths.last_mut() {
+        Some(last) if segmenter.len < MIN_ENCRYPTABLE_BYTES => *last += segmenter.len,
+        _ if segmenter.len > 0 => lengths.push(segmenter.len),
+        _ => {}
+    }
+    Ok(lengths)
+}
+
+/// Encrypts the file segment by segment, writing the encrypted chunks to `output_dir`. Returns the
+/// data map chunk of the file, listing the data maps of its segments, and the chunks.
+pub(crate) fn encrypt_segmented(
+    file_path: &Path,
+    output_dir: &Path,
+) -> Result<(Chunk, Vec<(XorName, PathBuf)>)> {
+    let lengths = segment_lengths(BufReader::new(File::open(file_path)?))?;
+    let mut file = BufReader::new(File::open(file_path)?);
+
+    let mut data_maps: Vec<DataMap> = Vec::with_capacity(lengths.len());
+    let mut encrypted_chunks = vec![];
+    // identical segments have identical chunks
+    let mut written = BTreeSet::new();
+    for length in lengths {
+        let mut segment = vec![0; length];
+        file.read_exact(&mut segment)?;
+        let (data_map, chunks) = self_encryption::encrypt(Bytes::from(segment))?;
+        for chunk in chunks {
+            let name = XorName::from_content(&chunk.content);
+            if !written.insert(name) {
+                continue;
+            }
+            let chunk_path = output_dir.join(hex::encode(name));
+            File::create(&chunk_path)?.write_all(&chunk.content)?;
+            encrypted_chunks.push((name, chunk_path));
+        }
+        data_maps.push(data_map);
+    }
+
+    let (data_map_chunk, additional_chunks) = pack_data_map(DataMapLevel::Segmented(data_maps))?;
+    for chunk in additional_chunks.iter() {
+        let chunk_path = output_dir.join(hex::encode(chunk.name()));
+        File::create(&chunk_path)?.write_all(&chunk.value)?;
+        encrypted_chunks.push((*chunk.name(), chunk_path));
+    }
+
+    Ok((data_map_chunk, encrypted_chunks))
+}
+
+/// The parts of the segments a read of `len` bytes from `position` of the file spans, as the
+/// index of the segment, and the position and length of the part in it.
+pub(crate) fn segment_ranges(
+    segments: &[DataMap],
+    position: usize,
+    len: usize,
+) -> Vec<(usize, usize, usize)> {
+    let end = position.saturating_add(len);
+    let mut ranges = vec![];
+    let mut segment_start = 0;
+    for (index, segment) in segments.iter().enumerate() {
+        let segment_end = segment_start + segment.file_size();
+        if segment_end > position && segment_start < end {
+            let start = position.max(segment_start);
+            let range_end = end.min(segment_end);
+            ranges.push((index, start - segment_start, range_end - start));
+        }
+        if segment_end >= end {
+            break;
+        }
+        segment_start = segment_end;
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    fn random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut bytes = vec![0; len];
+        StdRng::seed_from_u64(seed).fill_bytes(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn segments_should_be_cut_within_their_size_bounds() -> Result<()> {
+        let content = random_bytes(24 * 1024 * 1024, 0);
+        let lengths = segment_lengths(&content[..])?;
+
+        assert_eq!(lengths.iter().sum::<usize>(), content.len());
+        assert!(lengths.len() > 1);
+        let (last, others) = lengths.split_last().ok_or(eyre::eyre!("no segments"))?;
+        assert!(others
+            .iter()
+            .all(|len| (MIN_SEGMENT_SIZE..=MAX_SEGMENT_SIZE).contains(len)));
+        assert!(*last <= MAX_SEGMENT_SIZE);
+
+        // a uniform content has no boundary but the maximum size
+        let lengths = segment_lengths(&vec![0; 20 * 1024 * 1024][..])?;
+        assert_eq!(
+            lengths,
+            vec![MAX_SEGMENT_SIZE, MAX_SEGMENT_SIZE, 4 * 1024 * 1024]
+        );
+
+        // a last segment too small to be self-encrypted is merged into the previous one
+        let lengths = segment_lengths(&vec![0; MAX_SEGMENT_SIZE + 1][..])?;
+        assert_eq!(lengths, vec![MAX_SEGMENT_SIZE + 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn inserted_bytes_should_only_change_the_segment_they_are_inserted_in() -> Result<()> {
+        let content = random_bytes(24 * 1024 * 1024, 1);
+        let lengths = segment_lengths(&content[..])?;
+
+        let mut edited = content.clone();
+        let insert_at = 100;
+        edited.splice(insert_at..insert_at, random_bytes(1000, 2));
+        let edited_lengths = segment_lengths(&edited[..])?;
+
+        assert_eq!(edited_lengths[0], lengths[0] + 1000);
+        assert_eq!(edited_lengths[1..], lengths[1..]);
+        Ok(())
+    }
+
+    #[test]
+    fn edited_files_should_share_the_chunks_of_the_segments_not_edited() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let content = random_bytes(24 * 1024 * 1024, 3);
+        let file_path = dir.path().join("file");
+        std::fs::write(&file_path, &content)?;
+        let chunk_dir = dir.path().join("chunks");
+        std::fs::create_dir(&chunk_dir)?;
+        let (_, chunks) = encrypt_segmented(&file_path, &chunk_dir)?;
+
+        let mut edited = content.clone();
+        edited.splice(1000..2000, random_bytes(10, 4));
+        std::fs::write(&file_path, &edited)?;
+        let (_, edited_chunks) = encrypt_segmented(&file_path, &chunk_dir)?;
+
+        let names: BTreeSet<_> = chunks.iter().map(|(name, _)| *name).collect();
+        let edited_names: BTreeSet<_> = edited_chunks.iter().map(|(name, _)| *name).collect();
+        let shared = names.intersection(&edited_names).count();
+        // only the chunks of the first segment changed
+        assert!(shared > 0 && shared < edited_names.len());
+        assert!(edited_names.len() - shared <= MAX_SEGMENT_SIZE / self_encryption::MAX_CHUNK_SIZE);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_should_span_the_segments_they_cover() -> Result<()> {
+        let segments = [100, 50, 200]
+            .into_iter()
+            .map(|len| Ok(self_encryption::encrypt(Bytes::from(random_bytes(len, 5)))?.0))
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(segment_ranges(&segments, 0, 10), vec![(0, 0, 10)]);
+        assert_eq!(
+            segment_ranges(&segments, 90, 100),
+            vec![(0, 90, 10), (1, 0, 50), (2, 0, 40)]
+        );
+        assert_eq!(segment_ranges(&segments, 150, 1000), vec![(2, 0, 200)]);
+        assert_eq!(segment_ranges(&segments, 350, 10), vec![]);
+        Ok(())
+    }
+}
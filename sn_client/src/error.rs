@@ -11,7 +11,8 @@ pub(crate) type Result<T> = std::result::Result<T, Error>;
 use crate::UploadSummary;
 
 use super::ClientEvent;
-use sn_protocol::NetworkAddress;
+use sn_networking::{GetRecordError, NetworkError};
+use sn_protocol::{storage::ScratchpadAddress, NetworkAddress};
 use sn_registers::{Entry, EntryHash};
 use std::collections::BTreeSet;
 use thiserror::Error;
@@ -95,6 +96,51 @@ pub enum Error {
     #[error("Could not send files event")]
     CouldNotSendFilesEvent,
 
+    #[error("Could not send pin event")]
+    CouldNotSendPinEvent,
+
+    #[error("Could not send broadcast event")]
+    CouldNotSendBroadcastEvent,
+
+    #[error("Could not send republish event")]
+    CouldNotSendRepublishEvent,
+
+    #[error("Invalid name {0:?}: names are made of letters, digits and inner hyphens")]
+    InvalidName(String),
+
+    #[error("Invalid registrant {0:?} of a name: a hex encoded public key is expected")]
+    InvalidNameRegistrant(String),
+
+    #[error("The name {0:?} is already registered")]
+    NameTaken(String),
+
+    #[error("The name {0:?} is not registered")]
+    NameNotFound(String),
+
+    #[error("The name {0:?} was registered by another client")]
+    NotNameRegistrant(String),
+
+    #[error("Only encrypted transfers can be notified to their recipient")]
+    UnencryptedTransferNotice,
+
+    #[error("Could not send inbox event")]
+    CouldNotSendInboxEvent,
+
+    #[error("No inbox was created for {0:?}")]
+    InboxNotFound(sn_transfers::MainPubkey),
+
+    #[error("The inbox of {0:?} is full until its recipient receives from it")]
+    InboxFull(sn_transfers::MainPubkey),
+
+    #[error(
+        "The client data dir {path:?} is of version {found:?}, only versions up to {supported} are supported"
+    )]
+    UnsupportedClientStoreVersion {
+        path: std::path::PathBuf,
+        found: String,
+        supported: u32,
+    },
+
     #[error("Incorrect Download Option")]
     IncorrectDownloadOption,
 
@@ -110,8 +156,17 @@ pub enum Error {
     #[error("Could not find register after batch sync: {0:?}")]
     RegisterNotFoundAfterUpload(XorName),
 
-    #[error("Could not connect due to incompatible network protocols. Our protocol: {0} Network protocol: {1}")]
-    UnsupportedProtocol(String, String),
+    #[error("No valid copy of the Scratchpad {0:?} was found")]
+    ScratchpadNotFound(ScratchpadAddress),
+
+    #[error("The Scratchpad {0:?} already exists, it can only be updated")]
+    ScratchpadAlreadyExists(ScratchpadAddress),
+
+    #[error("Scratchpad data of {0} bytes exceeds the maximum size")]
+    ScratchpadTooBig(usize),
+
+    #[error("Could not connect due to incompatible network protocols. Our protocol: {ours} Network protocol: {theirs}")]
+    IncompatibleProtocol { ours: String, theirs: String },
 
     // ------ Upload Errors --------
     #[error("Overflow occurred while adding values")]
@@ -156,6 +211,61 @@ pub enum Error {
     #[error("Invalid mnemonic seed phrase")]
     InvalidMnemonicSeedPhrase,
 
+    #[error("Mnemonic phrases of {0} words are not supported, use 12 or 24 words")]
+    InvalidMnemonicWordCount(usize),
+
     #[error("SecretKey could not be created from the provided bytes")]
     InvalidKeyBytes,
+
+    #[error("Invalid invoice id {0:?}: only letters, digits, '-', '_' and '.' are allowed")]
+    InvalidInvoiceId(String),
+
+    #[error("Invalid payment URI {0:?}")]
+    InvalidPaymentUri(String),
+
+    #[error("No invoice {0:?} was issued")]
+    InvoiceNotFound(String),
+
+    #[error("The invoice {0:?} has already been issued")]
+    InvoiceAlreadyExists(String),
+
+    #[error("The invoice {0:?} is to be paid to another payee")]
+    InvoiceOfAnotherPayee(String),
+
+    #[error("The payment URI of invoice {0:?} does not match the invoice issued")]
+    InvoiceMismatch(String),
+
+    #[error("A payment is claimed with either its spend address or its transfer")]
+    InvalidPaymentClaim,
+
+    #[cfg(any(feature = "gateway", feature = "receipt-service"))]
+    #[error("HTTP server error: {0}")]
+    Gateway(#[from] hyper::Error),
+}
+
+impl Error {
+    /// The protocol error behind this error, usually one returned by a node in its response.
+    pub fn protocol_error(&self) -> Option<&sn_protocol::error::Error> {
+        match self {
+            Error::Protocol(err) | Error::Network(NetworkError::ProtocolError(err)) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Whether the operation might succeed if it is retried.
+    pub fn is_retryable(&self) -> bool {
+        if let Some(err) = self.protocol_error() {
+            return err.is_retryable();
+        }
+        matches!(
+            self,
+            Error::ConnectionTimeout(_)
+                | Error::Network(
+                    NetworkError::GetRecordError(GetRecordError::QueryTimeout)
+                        | NetworkError::NoStoreCostResponses
+                        | NetworkError::NotEnoughPeers { .. }
+                        | NetworkError::RecordNotStoredByNodes(_)
+                )
+        )
+    }
 }
@@ -6,8 +6,12 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use bytes::Bytes;
 use serde::Serialize;
-use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
 
 // Channel where events will be broadcasted by the client.
 #[derive(Clone, Debug)]
@@ -53,6 +57,12 @@ pub enum ClientEvent {
     /// No network activity has been received for a given duration
     /// we should error out
     InactiveClient(tokio::time::Duration),
+    /// A message was published on a gossipsub topic the client is subscribed to
+    GossipsubMsg {
+        topic: String,
+        #[debug(skip)]
+        msg: Bytes,
+    },
 }
 
 /// Receiver Channel where users of the public API can listen to events broadcasted by the client.
@@ -64,4 +74,9 @@ impl ClientEventsReceiver {
     pub async fn recv(&mut self) -> std::result::Result<ClientEvent, RecvError> {
         self.0.recv().await
     }
+
+    /// Receive an event that has already been broadcasted, without waiting for a new one.
+    pub fn try_recv(&mut self) -> std::result::Result<ClientEvent, TryRecvError> {
+        self.0.try_recv()
+    }
 }
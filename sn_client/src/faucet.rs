@@ -7,7 +7,10 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{wallet::send, Client, Error, Result};
-use sn_transfers::{load_genesis_wallet, HotWallet, NanoTokens, Transfer, FOUNDATION_PK};
+use sn_transfers::{
+    load_genesis_wallet, load_genesis_wallet_from, HotWallet, NanoTokens, Transfer, FOUNDATION_PK,
+};
+use std::path::Path;
 
 const INITIAL_FAUCET_BALANCE: NanoTokens = NanoTokens::from(900000000000000000);
 
@@ -17,6 +20,28 @@ pub async fn fund_faucet_from_genesis_wallet(
     client: &Client,
     faucet_wallet: &mut HotWallet,
 ) -> Result<()> {
+    fund_faucet(client, faucet_wallet, None).await
+}
+
+/// As [`fund_faucet_from_genesis_wallet`], with the genesis wallet kept in `genesis_dir` instead of
+/// the default test genesis directory.
+pub async fn fund_faucet_from_genesis_wallet_in(
+    client: &Client,
+    faucet_wallet: &mut HotWallet,
+    genesis_dir: &Path,
+) -> Result<()> {
+    fund_faucet(client, faucet_wallet, Some(genesis_dir)).await
+}
+
+async fn fund_faucet(
+    client: &Client,
+    faucet_wallet: &mut HotWallet,
+    genesis_dir: Option<&Path>,
+) -> Result<()> {
+    let load_genesis_wallet = || match genesis_dir {
+        Some(genesis_dir) => load_genesis_wallet_from(genesis_dir),
+        None => load_genesis_wallet(),
+    };
     faucet_wallet.try_load_cash_notes()?;
     let faucet_balance = faucet_wallet.balance();
     if !faucet_balance.is_zero() {
@@ -9,8 +9,11 @@
 pub(crate) mod download;
 
 use crate::{
-    acc_packet::load_account_wallet_or_create_with_mnemonic, chunks::Error as ChunksError,
-    error::Result, wallet::StoragePaymentResult, Client, Error, WalletClient,
+    acc_packet::load_account_wallet_or_create_with_mnemonic,
+    chunks::{DataMapLevel, ErasureCoding, Error as ChunksError},
+    error::Result,
+    wallet::StoragePaymentResult,
+    Client, Error, WalletClient,
 };
 use bytes::Bytes;
 use self_encryption::{self, MIN_ENCRYPTABLE_BYTES};
@@ -77,6 +80,57 @@ impl FilesApi {
         file_path: &Path,
         chunk_dir: &Path,
         include_data_map_in_chunks: bool,
+    ) -> ChunkFileResult {
+        Self::chunk_file_with_erasure_coding(file_path, chunk_dir, include_data_map_in_chunks, None)
+    }
+
+    /// Like `chunk_file`, but with the encrypted chunks erasure coded if `erasure_coding` is set:
+    /// their parity chunks are written to disk and returned along with them, and the data map
+    /// records them for the file to be recovered on download even if some of its chunks are lost.
+    pub fn chunk_file_with_erasure_coding(
+        file_path: &Path,
+        chunk_dir: &Path,
+        include_data_map_in_chunks: bool,
+        erasure_coding: Option<ErasureCoding>,
+    ) -> ChunkFileResult {
+        Self::chunk_file_by(
+            file_path,
+            chunk_dir,
+            include_data_map_in_chunks,
+            |output_dir| encrypt_large(file_path, output_dir, erasure_coding),
+        )
+    }
+
+    /// Like `chunk_file`, but with the file cut into segments by its content, each of them self
+    /// encrypted on its own: an edit of the file then only changes the chunks of the segments it
+    /// reaches, the others being those of its previous version.
+    ///
+    /// The data map chunk lists the data maps of the segments, which clients predating this
+    /// format cannot read.
+    pub fn chunk_file_content_defined(
+        file_path: &Path,
+        chunk_dir: &Path,
+        include_data_map_in_chunks: bool,
+    ) -> ChunkFileResult {
+        Self::chunk_file_by(
+            file_path,
+            chunk_dir,
+            include_data_map_in_chunks,
+            |output_dir| Ok(crate::chunks::encrypt_segmented(file_path, output_dir)?),
+        )
+    }
+
+    /// Whether the data map chunk is that of a file chunked by `chunk_file_content_defined`.
+    pub fn is_content_defined(data_map_chunk: &Chunk) -> bool {
+        rmp_serde::from_slice::<DataMapLevel>(data_map_chunk.value())
+            .is_ok_and(|data_map| data_map.is_segmented())
+    }
+
+    fn chunk_file_by(
+        file_path: &Path,
+        chunk_dir: &Path,
+        include_data_map_in_chunks: bool,
+        encrypt: impl FnOnce(&Path) -> Result<(Chunk, Vec<(XorName, PathBuf)>)>,
     ) -> ChunkFileResult {
         let file = File::open(file_path)?;
         let metadata = file.metadata()?;
@@ -86,7 +140,7 @@ impl FilesApi {
             if file_size < MIN_ENCRYPTABLE_BYTES as u64 {
                 Err(ChunksError::FileTooSmall)?
             } else {
-                let (data_map_chunk, chunks) = encrypt_large(file_path, chunk_dir)?;
+                let (data_map_chunk, chunks) = encrypt(chunk_dir)?;
                 (*data_map_chunk.name(), data_map_chunk, chunks)
             };
 
@@ -130,7 +184,7 @@ impl FilesApi {
         debug!("Payments for chunk: {chunk_addr:?} to {payee:?}:  {payment:?}");
 
         self.client
-            .store_chunk(chunk, payee, payment, verify_store, retry_strategy)
+            .store_chunk(chunk, vec![payee], payment, verify_store, retry_strategy)
             .await?;
 
         wallet_client.remove_payment_for_addr(&chunk_addr)?;
@@ -190,6 +244,14 @@ impl FilesApi {
 /// Does not store anything to the network.
 ///
 /// Returns data map as a chunk, and the resulting chunks
-fn encrypt_large(file_path: &Path, output_dir: &Path) -> Result<(Chunk, Vec<(XorName, PathBuf)>)> {
-    Ok(crate::chunks::encrypt_large(file_path, output_dir)?)
+fn encrypt_large(
+    file_path: &Path,
+    output_dir: &Path,
+    erasure_coding: Option<ErasureCoding>,
+) -> Result<(Chunk, Vec<(XorName, PathBuf)>)> {
+    Ok(crate::chunks::encrypt_large(
+        file_path,
+        output_dir,
+        erasure_coding,
+    )?)
 }
@@ -7,18 +7,18 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
-    chunks::{DataMapLevel, Error as ChunksError},
+    chunks::{segment_ranges, DataMapLevel, Error as ChunksError, ParityGroup},
     error::{Error as ClientError, Result},
     Client, FilesApi, BATCH_SIZE,
 };
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::StreamExt;
 use itertools::Itertools;
 use self_encryption::{decrypt_full_set, DataMap, EncryptedChunk, StreamSelfDecryptor};
 use sn_networking::target_arch::Instant;
 use sn_protocol::storage::{Chunk, ChunkAddress, RetryStrategy};
 
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{collections::HashMap, fs, io::Write, path::PathBuf, sync::Arc};
 use tokio::sync::mpsc::{self};
 use xor_name::XorName;
 
@@ -37,6 +37,14 @@ pub enum FilesDownloadEvent {
     Error,
 }
 
+/// The data maps of a file, out of its data map chunk.
+pub(crate) enum FileMap {
+    /// The data map of the file, with the parity groups its chunks were erasure coded in, if any.
+    Single(DataMap, Vec<ParityGroup>),
+    /// The data maps of the segments the file was cut into by its content.
+    Segmented(Vec<DataMap>),
+}
+
 // Internally used to differentiate between the various ways that the downloaded chunks are returned.
 enum DownloadReturnType {
     EncryptedChunks(Vec<EncryptedChunk>),
@@ -150,36 +158,37 @@ impl FilesDownload {
             .client
             .get_chunk(address, false, Some(self.retry_strategy))
             .await?;
+        self.download_range(chunk, position, length).await
+    }
 
+    /// Download a range of the bytes of a file out of its data map chunk, fetching only the
+    /// chunks the range spans: `length` bytes from `position`, or fewer past the end of the file.
+    pub async fn download_range(
+        &mut self,
+        chunk: Chunk,
+        position: usize,
+        length: usize,
+    ) -> Result<Bytes> {
         // First try to deserialize a LargeFile, if it works, we go and seek it.
         // If an error occurs, we consider it to be a SmallFile.
-        if let Ok(data_map) = self.unpack_chunk(chunk.clone()).await {
-            let info = self_encryption::seek_info(data_map.file_size(), position, length);
-            let range = &info.index_range;
-            let all_infos = data_map.infos();
-
-            let to_download = (range.start..range.end + 1)
-                .clone()
-                .map(|i| all_infos[i].clone())
-                .collect_vec();
-            let to_download = DataMap::new(to_download);
-
-            // not written to file and return the encrypted chunks
-            if let DownloadReturnType::EncryptedChunks(encrypted_chunks) =
-                self.read(to_download, None, true, false).await?
-            {
-                let bytes = self_encryption::decrypt_range(
-                    &data_map,
-                    &encrypted_chunks,
-                    info.relative_pos,
-                    length,
-                )
-                .map_err(ChunksError::SelfEncryption)?;
-                return Ok(bytes);
-            } else {
-                error!("IncorrectDownloadOption: expected to get the encrypted chunks back");
-                return Err(ClientError::IncorrectDownloadOption);
+        match self.unpack_head_chunk(chunk.clone()).await {
+            Ok(FileMap::Single(data_map, parity_groups)) => {
+                return self
+                    .read_range(&data_map, parity_groups, position, length)
+                    .await;
+            }
+            Ok(FileMap::Segmented(segments)) => {
+                let mut bytes = BytesMut::new();
+                for (index, position, length) in segment_ranges(&segments, position, length) {
+                    bytes.extend_from_slice(
+                        &self
+                            .read_range(&segments[index], vec![], position, length)
+                            .await?,
+                    );
+                }
+                return Ok(bytes.freeze());
             }
+            Err(_) => {}
         }
 
         // The error above is ignored to avoid leaking the storage format detail of SmallFiles and LargeFiles.
@@ -187,12 +196,84 @@ impl FilesDownload {
         // The cost of it is that some errors will not be seen without a refactor.
         let mut bytes = chunk.value().clone();
 
-        let _ = bytes.split_to(position);
+        let _ = bytes.split_to(position.min(bytes.len()));
         bytes.truncate(length);
 
         Ok(bytes)
     }
 
+    /// Download `length` bytes from `position` out of the chunks of the data map.
+    async fn read_range(
+        &mut self,
+        data_map: &DataMap,
+        parity_groups: Vec<ParityGroup>,
+        position: usize,
+        length: usize,
+    ) -> Result<Bytes> {
+        let info = self_encryption::seek_info(data_map.file_size(), position, length);
+        let range = &info.index_range;
+        let all_infos = data_map.infos();
+
+        let to_download = (range.start..range.end + 1)
+            .clone()
+            .map(|i| all_infos[i].clone())
+            .collect_vec();
+        let to_download = DataMap::new(to_download);
+
+        // not written to file and return the encrypted chunks
+        if let DownloadReturnType::EncryptedChunks(encrypted_chunks) = self
+            .read(
+                to_download,
+                parity_groups,
+                None,
+                true,
+                Some(FilesDownloadEvent::DatamapCount),
+            )
+            .await?
+        {
+            let bytes = self_encryption::decrypt_range(
+                data_map,
+                &encrypted_chunks,
+                info.relative_pos,
+                length,
+            )
+            .map_err(ChunksError::SelfEncryption)?;
+            Ok(bytes)
+        } else {
+            error!("IncorrectDownloadOption: expected to get the encrypted chunks back");
+            Err(ClientError::IncorrectDownloadOption)
+        }
+    }
+
+    /// Download the segments of a file one after the other, to the file at the path if any, else
+    /// returning the bytes.
+    async fn read_segments(
+        &mut self,
+        segments: Vec<DataMap>,
+        downloaded_file_path: Option<PathBuf>,
+    ) -> Result<Option<Bytes>> {
+        // a single count for the whole file, rather than one per segment
+        let count = segments.iter().map(|segment| segment.infos().len()).sum();
+        self.send_event(FilesDownloadEvent::DatamapCount(count))
+            .await?;
+
+        let mut file = downloaded_file_path.map(fs::File::create).transpose()?;
+        let mut bytes = BytesMut::new();
+        for segment in segments {
+            let DownloadReturnType::DecryptedBytes(segment_bytes) =
+                self.read(segment, vec![], None, false, None).await?
+            else {
+                error!("IncorrectDownloadOption: we should be getting the decrypted bytes back.");
+                return Err(ClientError::IncorrectDownloadOption);
+            };
+            match &mut file {
+                Some(file) => file.write_all(&segment_bytes)?,
+                None => bytes.extend_from_slice(&segment_bytes),
+            }
+        }
+        Ok(file.is_none().then(|| bytes.freeze()))
+    }
+
     /// Download a file from the network and get the decrypted bytes.
     /// If the data_map_chunk is not provided, the DataMap is fetched from the network using the provided address.
     pub async fn download_file(
@@ -285,11 +366,20 @@ impl FilesDownload {
         };
 
         // first try to deserialize a LargeFile, if it works, we go and seek it
-        match self.unpack_chunk(head_chunk.clone()).await {
-            Ok(data_map) => {
+        match self.unpack_head_chunk(head_chunk.clone()).await {
+            Ok(FileMap::Segmented(segments)) => {
+                self.read_segments(segments, downloaded_file_path).await
+            }
+            Ok(FileMap::Single(data_map, parity_groups)) => {
                 // read_all emits
                 match self
-                    .read(data_map, downloaded_file_path, false, false)
+                    .read(
+                        data_map,
+                        parity_groups,
+                        downloaded_file_path,
+                        false,
+                        Some(FilesDownloadEvent::DatamapCount),
+                    )
                     .await?
                 {
                     DownloadReturnType::EncryptedChunks(_) => {
@@ -335,13 +425,16 @@ impl FilesDownload {
     /// If return_encrypted_chunks is true, we return DownloadReturnType::EncryptedChunks
     /// Else we return DownloadReturnType::DecryptedBytes
     ///
-    /// Set we_are_downloading_a_datamap if we want to emit the DatamapCount else we emit ChunksCount
+    /// The number of chunks about to be downloaded is emitted with `count_event`, if set.
+    ///
+    /// The chunks which cannot be retrieved are recovered from their parity group, if any.
     async fn read(
         &mut self,
         data_map: DataMap,
+        parity_groups: Vec<ParityGroup>,
         decrypted_file_path: Option<PathBuf>,
         return_encrypted_chunks: bool,
-        we_are_downloading_a_datamap: bool,
+        count_event: Option<fn(usize) -> FilesDownloadEvent>,
     ) -> Result<DownloadReturnType> {
         // used internally
         enum DownloadKind {
@@ -362,13 +455,8 @@ impl FilesDownload {
         let chunk_infos = data_map.infos();
         let expected_count = chunk_infos.len();
 
-        if we_are_downloading_a_datamap {
-            self.send_event(FilesDownloadEvent::ChunksCount(expected_count))
-                .await?;
-        } else {
-            // we're downloading the chunks related to a huge datamap
-            self.send_event(FilesDownloadEvent::DatamapCount(expected_count))
-                .await?;
+        if let Some(count_event) = count_event {
+            self.send_event(count_event(expected_count)).await?;
         }
 
         let now = Instant::now();
@@ -376,6 +464,7 @@ impl FilesDownload {
         let client_clone = self.api.client.clone();
         let show_holders = self.show_holders;
         let retry_strategy = self.retry_strategy;
+        let parity_groups = Arc::new(parity_groups);
         // the initial index is not always 0 as we might seek a range of bytes. So fetch the first index
         let mut current_index = chunk_infos
             .first()
@@ -387,6 +476,7 @@ impl FilesDownload {
                     client_clone.clone(),
                     chunk_info.dst_hash,
                     chunk_info.index,
+                    Arc::clone(&parity_groups),
                     show_holders,
                     retry_strategy,
                 )
@@ -474,15 +564,108 @@ impl FilesDownload {
     /// Extracts a file DataMapLevel from a chunk.
     /// If the DataMapLevel is not the first level mapping directly to the user's contents,
     /// the process repeats itself until it obtains the first level DataMapLevel.
-    pub async fn unpack_chunk(&mut self, mut chunk: Chunk) -> Result<DataMap> {
+    ///
+    /// The file must not have been cut into segments by its content, as it has no single data
+    /// map then: see [`FilesApi::chunk_file_content_defined`].
+    pub async fn unpack_chunk(&mut self, chunk: Chunk) -> Result<DataMap> {
+        match self.unpack_head_chunk(chunk).await? {
+            FileMap::Single(data_map, _) => Ok(data_map),
+            FileMap::Segmented(_) => {
+                error!("IncorrectDownloadOption: the file has a data map per segment");
+                Err(ClientError::IncorrectDownloadOption)
+            }
+        }
+    }
+
+    /// The size of a file, out of its data map chunk.
+    pub async fn file_size(&mut self, chunk: Chunk) -> Result<usize> {
+        match self.unpack_head_chunk(chunk.clone()).await {
+            Ok(FileMap::Single(data_map, _)) => Ok(data_map.file_size()),
+            Ok(FileMap::Segmented(segments)) => {
+                Ok(segments.iter().map(|segment| segment.file_size()).sum())
+            }
+            // As when downloading it, a head chunk which is no data map holds a small file itself.
+            Err(ClientError::Chunks(ChunksError::Deserialisation(_))) => Ok(chunk.value().len()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Get the addresses of the chunks a file is made of, out of its head chunk: those of its
+    /// data maps, its content and its parity chunks, if it was erasure coded. The address of the
+    /// head chunk itself is not included.
+    pub async fn chunk_addresses(&mut self, mut chunk: Chunk) -> Result<Vec<XorName>> {
+        let mut addresses = vec![];
+        loop {
+            match rmp_serde::from_slice(chunk.value()).map_err(ChunksError::Deserialisation)? {
+                DataMapLevel::First(data_map) => {
+                    addresses.extend(data_map.infos().iter().map(|info| info.dst_hash));
+                    return Ok(addresses);
+                }
+                DataMapLevel::ErasureCoded(data_map, parity_groups) => {
+                    addresses.extend(data_map.infos().iter().map(|info| info.dst_hash));
+                    for group in &parity_groups {
+                        addresses.extend(group.parity_addresses());
+                    }
+                    return Ok(addresses);
+                }
+                DataMapLevel::Segmented(segments) => {
+                    addresses.extend(
+                        segments
+                            .iter()
+                            .flat_map(|segment| segment.infos())
+                            .map(|info| info.dst_hash),
+                    );
+                    return Ok(addresses);
+                }
+                DataMapLevel::Additional(data_map)
+                | DataMapLevel::AdditionalSegmented(data_map) => {
+                    addresses.extend(data_map.infos().iter().map(|info| info.dst_hash));
+                    if let DownloadReturnType::DecryptedBytes(serialized_chunk) = self
+                        .read(
+                            data_map,
+                            vec![],
+                            None,
+                            false,
+                            Some(FilesDownloadEvent::ChunksCount),
+                        )
+                        .await?
+                    {
+                        chunk = rmp_serde::from_slice(&serialized_chunk)
+                            .map_err(ChunksError::Deserialisation)?;
+                    } else {
+                        error!("IncorrectDownloadOption: we should be getting the decrypted bytes back.");
+                        return Err(ClientError::IncorrectDownloadOption);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like `unpack_chunk`, also returning the parity groups the chunks of the file were erasure
+    /// coded in, if they were, or the data maps of its segments, if it was cut into segments.
+    pub(crate) async fn unpack_head_chunk(&mut self, mut chunk: Chunk) -> Result<FileMap> {
         loop {
             match rmp_serde::from_slice(chunk.value()).map_err(ChunksError::Deserialisation)? {
                 DataMapLevel::First(data_map) => {
-                    return Ok(data_map);
+                    return Ok(FileMap::Single(data_map, vec![]));
+                }
+                DataMapLevel::ErasureCoded(data_map, parity_groups) => {
+                    return Ok(FileMap::Single(data_map, parity_groups));
+                }
+                DataMapLevel::Segmented(segments) => {
+                    return Ok(FileMap::Segmented(segments));
                 }
-                DataMapLevel::Additional(data_map) => {
-                    if let DownloadReturnType::DecryptedBytes(serialized_chunk) =
-                        self.read(data_map, None, false, true).await?
+                DataMapLevel::Additional(data_map)
+                | DataMapLevel::AdditionalSegmented(data_map) => {
+                    if let DownloadReturnType::DecryptedBytes(serialized_chunk) = self
+                        .read(
+                            data_map,
+                            vec![],
+                            None,
+                            false,
+                            Some(FilesDownloadEvent::ChunksCount),
+                        )
+                        .await?
                     {
                         chunk = rmp_serde::from_slice(&serialized_chunk)
                             .map_err(ChunksError::Deserialisation)?;
@@ -512,24 +695,59 @@ impl FilesDownload {
         client: Client,
         address: XorName,
         index: usize,
+        parity_groups: Arc<Vec<ParityGroup>>,
         show_holders: bool,
         retry_strategy: RetryStrategy,
     ) -> std::result::Result<(ChunkAddress, usize, EncryptedChunk), ChunksError> {
-        let chunk = client
+        let content = match client
             .get_chunk(
                 ChunkAddress::new(address),
                 show_holders,
                 Some(retry_strategy),
             )
             .await
-            .map_err(|err| {
+        {
+            Ok(chunk) => chunk.value,
+            Err(err) => {
                 error!("Chunk missing {address:?} with {err:?}",);
-                ChunksError::ChunkMissing(address)
-            })?;
-        let encrypted_chunk = EncryptedChunk {
-            index,
-            content: chunk.value,
+                let Some(parity_group) = parity_groups.iter().find(|group| group.contains(index))
+                else {
+                    return Err(ChunksError::ChunkMissing(address));
+                };
+                Self::recover_chunk(&client, parity_group, address, index, retry_strategy).await?
+            }
         };
-        Ok((chunk.address, index, encrypted_chunk))
+        let encrypted_chunk = EncryptedChunk { index, content };
+        Ok((ChunkAddress::new(address), index, encrypted_chunk))
+    }
+
+    // Rebuilds the chunk out of the other chunks of its parity group.
+    async fn recover_chunk(
+        client: &Client,
+        parity_group: &ParityGroup,
+        address: XorName,
+        index: usize,
+        retry_strategy: RetryStrategy,
+    ) -> std::result::Result<Bytes, ChunksError> {
+        info!("Recovering chunk {address:?} from its parity group");
+        let shards = futures::future::join_all(parity_group.addresses().map(|shard_address| {
+            let client = client.clone();
+            async move {
+                if shard_address == address {
+                    return None;
+                }
+                client
+                    .get_chunk(
+                        ChunkAddress::new(shard_address),
+                        false,
+                        Some(retry_strategy),
+                    )
+                    .await
+                    .map(|chunk| chunk.value.to_vec())
+                    .ok()
+            }
+        }))
+        .await;
+        parity_group.recover(index, shards)
     }
 }
@@ -0,0 +1,691 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A read-only HTTP gateway to the public files of the Network, available with the `gateway`
+//! feature.
+//!
+//! - `GET /<address>` serves the file whose data map is at the hex encoded chunk address, as
+//!   printed by `safe files upload`.
+//! - `GET /<folder address>/<path>` serves a file of an uploaded folder, with the MIME type guessed
+//!   from the name held by its metadata. The folder address alone lists the folder's entries.
+//! - `GET /<name>@<registrant>/<path>` does the same for the file or folder a registered name
+//!   points to, as `safe://<name>@<registrant>/<path>` would.
+//!
+//! A single byte range is honoured, letting browsers seek through media. The chunks read are kept
+//! in a cache of bounded size, shared by all the requests.
+//!
+//! What is served and to whom can be restricted with a `GatewayAccess`, e.g. to limit the
+//! requests and the bytes of each client.
+
+mod access;
+
+pub use access::{BandwidthQuota, GatewayAccess, RateLimit};
+
+use self::access::{AccessControl, Admission, Denied};
+use crate::{
+    error::Result, Client, Error, FilesApi, FilesDownload, FolderEntry, FoldersApi, NameTarget,
+    NamesApi,
+};
+use bytes::{Bytes, BytesMut};
+use futures::{future::try_join_all, stream, StreamExt};
+use hyper::{
+    header,
+    server::conn::AddrStream,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use lru::LruCache;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use self_encryption::{DataMap, EncryptedChunk};
+use sn_networking::{GetRecordError, NetworkError};
+use sn_protocol::storage::{Chunk, ChunkAddress, RegisterAddress};
+use std::{convert::Infallible, net::SocketAddr, path::PathBuf, sync::Arc, time::Instant};
+use tokio::sync::Mutex;
+use xor_name::XorName;
+
+use crate::{
+    chunks::{segment_ranges, Error as ChunksError},
+    files::download::FileMap,
+};
+
+/// The default size of the chunk cache, in bytes.
+pub const DEFAULT_CHUNK_CACHE_SIZE: usize = 256 * 1024 * 1024;
+
+/// The number of bytes decrypted at once when streaming a file.
+const STREAM_WINDOW: usize = 4 * 1024 * 1024;
+
+/// Serves the public files of the Network over HTTP.
+#[derive(Clone)]
+pub struct Gateway {
+    client: Client,
+    root_dir: PathBuf,
+    cache: Arc<Mutex<ChunkCache>>,
+    access: Arc<AccessControl>,
+}
+
+impl Gateway {
+    /// A gateway reading through `client`, keeping up to `cache_size` bytes of chunks.
+    ///
+    /// No payment is ever made: `root_dir` is only required by the folder APIs.
+    pub fn new(client: Client, root_dir: PathBuf, cache_size: usize) -> Self {
+        Self {
+            client,
+            root_dir,
+            cache: Arc::new(Mutex::new(ChunkCache::new(cache_size))),
+            access: Arc::new(AccessControl::new(GatewayAccess::default())),
+        }
+    }
+
+    /// Restrict what is served and to whom, everything being served to anyone otherwise.
+    pub fn with_access(mut self, access: GatewayAccess) -> Self {
+        self.access = Arc::new(AccessControl::new(access));
+        self
+    }
+
+    /// Serve the requests received on `addr`, until the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        let make_service = make_service_fn(move |conn: &AddrStream| {
+            let gateway = self.clone();
+            let remote_addr = conn.remote_addr();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let gateway = gateway.clone();
+                    async move { Ok::<_, Infallible>(gateway.handle(request, remote_addr).await) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)?.serve(make_service);
+        info!("Gateway listening on {}", server.local_addr());
+        server.await?;
+        Ok(())
+    }
+
+    async fn handle(&self, request: Request<Body>, remote_addr: SocketAddr) -> Response<Body> {
+        let head_only = match *request.method() {
+            Method::GET => false,
+            Method::HEAD => true,
+            _ => return status_response(StatusCode::METHOD_NOT_ALLOWED),
+        };
+        let client = match self.access.admit(
+            request.uri(),
+            request.headers(),
+            remote_addr,
+            Instant::now(),
+        ) {
+            Ok(client) => client,
+            Err(denied) => return denied_response(denied),
+        };
+        let path = request.uri().path().trim_matches('/').to_string();
+        let range = request
+            .headers()
+            .get(header::RANGE)
+            .and_then(|range| range.to_str().ok())
+            .map(str::to_string);
+        debug!("Gateway request for {path:?}, range {range:?}");
+
+        let response = match self.resolve(&path).await {
+            Ok(Resolved::File {
+                head,
+                name,
+                immutable,
+            }) => {
+                self.file_response(
+                    &client,
+                    head,
+                    name.as_deref(),
+                    immutable,
+                    range.as_deref(),
+                    head_only,
+                )
+                .await
+            }
+            Ok(Resolved::Folder(entries)) => {
+                let token = self.access.query_token(request.uri());
+                Ok(listing_response(&path, &entries, token, head_only))
+            }
+            Err(status) => Err(status),
+        };
+        response.unwrap_or_else(status_response)
+    }
+
+    /// Find the head chunk of the file at `path`, or the entries of the folder at `path`.
+    async fn resolve(&self, path: &str) -> std::result::Result<Resolved, StatusCode> {
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+        let mut segments = segments.into_iter().peekable();
+        let address = segments.next().ok_or(StatusCode::NOT_FOUND)?;
+
+        if let Some(xorname) = parse_xor_name(&address) {
+            if segments.next().is_some() {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            self.check_servable(&address)?;
+            let value = self.get_chunk(xorname).await.map_err(error_status)?;
+            return Ok(Resolved::File {
+                head: Chunk::new(value),
+                name: None,
+                immutable: true,
+            });
+        }
+
+        let mut folder = match RegisterAddress::from_hex(&address) {
+            Ok(folder) => {
+                self.check_servable(&address)?;
+                folder
+            }
+            // anything else is taken for a registered name
+            Err(_) => match NamesApi::new(self.client.clone(), self.root_dir.clone())
+                .resolve(&address)
+                .await
+                .map_err(error_status)?
+            {
+                NameTarget::Folder(folder) => {
+                    self.check_servable(&folder.to_hex())?;
+                    folder
+                }
+                NameTarget::File(head) if segments.peek().is_none() => {
+                    self.check_servable(&head.to_hex())?;
+                    let value = self
+                        .get_chunk(*head.xorname())
+                        .await
+                        .map_err(error_status)?;
+                    return Ok(Resolved::File {
+                        head: Chunk::new(value),
+                        name: None,
+                        immutable: false,
+                    });
+                }
+                _ => return Err(StatusCode::NOT_FOUND),
+            },
+        };
+        loop {
+            let mut folder_api = FoldersApi::retrieve(self.client.clone(), &self.root_dir, folder)
+                .await
+                .map_err(error_status)?;
+            let entries = folder_api.entries().await.map_err(error_status)?;
+            let entries: Vec<(String, FolderEntry)> = entries
+                .into_values()
+                .map(|(_, metadata)| (metadata.name, metadata.content))
+                .collect();
+
+            let Some(segment) = segments.next() else {
+                return Ok(Resolved::Folder(entries));
+            };
+            let name = percent_decode_str(&segment)
+                .decode_utf8()
+                .map_err(|_| StatusCode::BAD_REQUEST)?;
+            match entries
+                .into_iter()
+                .find(|(entry_name, _)| *entry_name == name)
+            {
+                Some((_, FolderEntry::Folder(address))) => folder = address,
+                Some((name, FolderEntry::File(head))) if segments.peek().is_none() => {
+                    return Ok(Resolved::File {
+                        head,
+                        name: Some(name),
+                        immutable: false,
+                    });
+                }
+                _ => return Err(StatusCode::NOT_FOUND),
+            }
+        }
+    }
+
+    /// Refuse the content at the hex encoded address, unless it has an allowed prefix.
+    fn check_servable(&self, address: &str) -> std::result::Result<(), StatusCode> {
+        if self.access.is_servable(address) {
+            Ok(())
+        } else {
+            debug!("Gateway refused {address}, it has no allowed prefix");
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+
+    async fn file_response(
+        &self,
+        client: &Admission,
+        head: Chunk,
+        name: Option<&str>,
+        immutable: bool,
+        range: Option<&str>,
+        head_only: bool,
+    ) -> std::result::Result<Response<Body>, StatusCode> {
+        let content = self.content(head).await.map_err(error_status)?;
+        let size = content.size();
+        let content_type = name.map_or(mime_guess::mime::APPLICATION_OCTET_STREAM, |name| {
+            mime_guess::from_path(name).first_or_octet_stream()
+        });
+
+        let mut response = Response::builder()
+            .header(header::CONTENT_TYPE, content_type.as_ref())
+            .header(header::ACCEPT_RANGES, "bytes");
+        if immutable {
+            // The content of a chunk address never changes, unlike the entries of a folder.
+            response =
+                response.header(header::CACHE_CONTROL, "public, max-age=31536000, immutable");
+        }
+        let (start, len) = match byte_range(range, size) {
+            ByteRange::Full => {
+                response = response.status(StatusCode::OK);
+                (0, size)
+            }
+            ByteRange::Partial { start, end } => {
+                response = response
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_RANGE, format!("bytes {start}-{end}/{size}"));
+                (start, end - start + 1)
+            }
+            ByteRange::Unsatisfiable => {
+                return Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{size}"))
+                    .body(Body::empty())
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        };
+        response = response.header(header::CONTENT_LENGTH, len);
+
+        let body = if head_only || len == 0 {
+            Body::empty()
+        } else {
+            if let Err(denied) = self.access.charge(client, len as u64, Instant::now()) {
+                return Ok(denied_response(denied));
+            }
+            // The file is decrypted one window at a time, as the body is sent.
+            let gateway = self.clone();
+            let windows = (start..start + len)
+                .step_by(STREAM_WINDOW)
+                .map(move |position| (position, STREAM_WINDOW.min(start + len - position)));
+            Body::wrap_stream(stream::iter(windows).then(move |(position, len)| {
+                let gateway = gateway.clone();
+                let content = content.clone();
+                async move { gateway.read(&content, position, len).await }
+            }))
+        };
+        response
+            .body(body)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// The content of a file, from its head chunk.
+    async fn content(&self, head: Chunk) -> Result<Content> {
+        let mut files_download =
+            FilesDownload::new(FilesApi::new(self.client.clone(), self.root_dir.clone()));
+        match files_download.unpack_head_chunk(head.clone()).await {
+            Ok(FileMap::Single(data_map, _)) => Ok(Content::Large(Arc::new(data_map))),
+            Ok(FileMap::Segmented(segments)) => Ok(Content::Segmented(Arc::new(segments))),
+            // As when downloading, a head chunk which is not a data map holds a small file.
+            Err(Error::Chunks(ChunksError::Deserialisation(_))) => Ok(Content::Small(head.value)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Read `len` bytes of the content, from `position`.
+    async fn read(&self, content: &Content, position: usize, len: usize) -> Result<Bytes> {
+        match content {
+            Content::Small(bytes) => Ok(bytes.slice(position..position + len)),
+            Content::Large(data_map) => self.read_data_map(data_map, position, len).await,
+            Content::Segmented(segments) => {
+                let mut bytes = BytesMut::new();
+                for (index, position, len) in segment_ranges(segments, position, len) {
+                    bytes.extend_from_slice(
+                        &self.read_data_map(&segments[index], position, len).await?,
+                    );
+                }
+                Ok(bytes.freeze())
+            }
+        }
+    }
+
+    /// Read `len` bytes of the content mapped by the data map, from `position`.
+    async fn read_data_map(
+        &self,
+        data_map: &DataMap,
+        position: usize,
+        len: usize,
+    ) -> Result<Bytes> {
+        let info = self_encryption::seek_info(data_map.file_size(), position, len);
+        let infos = data_map.infos();
+        let encrypted_chunks = try_join_all(
+            infos[info.index_range.start..=info.index_range.end]
+                .iter()
+                .map(|chunk_info| async move {
+                    let content = self.get_chunk(chunk_info.dst_hash).await?;
+                    Ok::<_, Error>(EncryptedChunk {
+                        index: chunk_info.index,
+                        content,
+                    })
+                }),
+        )
+        .await?;
+        let bytes =
+            self_encryption::decrypt_range(data_map, &encrypted_chunks, info.relative_pos, len)
+                .map_err(ChunksError::SelfEncryption)?;
+        Ok(bytes)
+    }
+
+    async fn get_chunk(&self, xorname: XorName) -> Result<Bytes> {
+        if let Some(value) = self.cache.lock().await.get(&xorname) {
+            return Ok(value);
+        }
+        let chunk = self
+            .client
+            .get_chunk(ChunkAddress::new(xorname), false, None)
+            .await?;
+        self.cache.lock().await.insert(xorname, chunk.value.clone());
+        Ok(chunk.value)
+    }
+}
+
+enum Resolved {
+    File {
+        head: Chunk,
+        /// The name the file was uploaded with, within a folder
+        name: Option<String>,
+        immutable: bool,
+    },
+    Folder(Vec<(String, FolderEntry)>),
+}
+
+#[derive(Clone)]
+enum Content {
+    Small(Bytes),
+    Large(Arc<DataMap>),
+    /// The data maps of the segments the file was cut into by its content
+    Segmented(Arc<Vec<DataMap>>),
+}
+
+impl Content {
+    fn size(&self) -> usize {
+        match self {
+            Content::Small(bytes) => bytes.len(),
+            Content::Large(data_map) => data_map.file_size(),
+            Content::Segmented(segments) => segments.iter().map(DataMap::file_size).sum(),
+        }
+    }
+}
+
+/// The chunks read recently, the least recently used evicted past the size of the cache.
+struct ChunkCache {
+    chunks: LruCache<XorName, Bytes>,
+    size: usize,
+    max_size: usize,
+}
+
+impl ChunkCache {
+    fn new(max_size: usize) -> Self {
+        Self {
+            chunks: LruCache::unbounded(),
+            size: 0,
+            max_size,
+        }
+    }
+
+    fn get(&mut self, xorname: &XorName) -> Option<Bytes> {
+        self.chunks.get(xorname).cloned()
+    }
+
+    fn insert(&mut self, xorname: XorName, value: Bytes) {
+        if value.len() > self.max_size {
+            return;
+        }
+        self.size += value.len();
+        if let Some(previous) = self.chunks.put(xorname, value) {
+            self.size -= previous.len();
+        }
+        while self.size > self.max_size {
+            match self.chunks.pop_lru() {
+                Some((_, evicted)) => self.size -= evicted.len(),
+                None => break,
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    /// The bytes from `start` to `end`, both included
+    Partial {
+        start: usize,
+        end: usize,
+    },
+    Unsatisfiable,
+}
+
+/// The range requested by the `Range` header, for a content of `size` bytes.
+///
+/// Only a single range is supported: any other range is ignored and the full content sent, as
+/// allowed by RFC 9110.
+fn byte_range(range: Option<&str>, size: usize) -> ByteRange {
+    let Some(spec) = range.and_then(|range| range.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // A suffix range, of the last bytes
+        return match end.parse::<usize>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial {
+                start: size.saturating_sub(suffix),
+                end: size - 1,
+            },
+            Err(_) => ByteRange::Full,
+        };
+    }
+    let Ok(start) = start.parse::<usize>() else {
+        return ByteRange::Full;
+    };
+    let end = if end.is_empty() {
+        usize::MAX
+    } else {
+        match end.parse::<usize>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial {
+        start,
+        end: end.min(size - 1),
+    }
+}
+
+fn parse_xor_name(hex: &str) -> Option<XorName> {
+    let bytes: [u8; xor_name::XOR_NAME_LEN] = hex::decode(hex).ok()?.try_into().ok()?;
+    Some(XorName(bytes))
+}
+
+fn error_status(err: Error) -> StatusCode {
+    match err {
+        Error::Network(NetworkError::GetRecordError(GetRecordError::RecordNotFound))
+        | Error::InvalidName(_)
+        | Error::InvalidNameRegistrant(_)
+        | Error::NameNotFound(_) => StatusCode::NOT_FOUND,
+        err => {
+            warn!("Gateway failed to read from the Network: {err}");
+            StatusCode::BAD_GATEWAY
+        }
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::from(status.to_string()));
+    *response.status_mut() = status;
+    response
+}
+
+fn denied_response(denied: Denied) -> Response<Body> {
+    match denied {
+        Denied::Unauthorized => {
+            let mut response = status_response(StatusCode::UNAUTHORIZED);
+            let _ = response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+            response
+        }
+        Denied::TooManyRequests { retry_after } => {
+            let mut response = status_response(StatusCode::TOO_MANY_REQUESTS);
+            // rounded up, not to be retried before the client is allowed to
+            let retry_after = retry_after
+                .as_secs()
+                .saturating_add(u64::from(retry_after.subsec_nanos() > 0));
+            let _ = response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, retry_after.into());
+            response
+        }
+    }
+}
+
+/// The listing of a folder, its links carrying the access token the folder was requested with,
+/// for a bearer of a token given in the query to browse it.
+fn listing_response(
+    path: &str,
+    entries: &[(String, FolderEntry)],
+    token: Option<&str>,
+    head_only: bool,
+) -> Response<Body> {
+    // the token is kept as given in the query, which it is compared to
+    let query = token
+        .map(|token| format!("?{}={token}", access::TOKEN_QUERY_PARAM))
+        .unwrap_or_default();
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>/{0}</title></head>\
+        <body><h1>/{0}</h1><ul>\n",
+        escape_html(path)
+    );
+    for (name, content) in entries {
+        let suffix = match content {
+            FolderEntry::Folder(_) => "/",
+            FolderEntry::File(_) => "",
+        };
+        html.push_str(&format!(
+            "<li><a href=\"/{path}/{}{}\">{}{suffix}</a></li>\n",
+            utf8_percent_encode(name, NON_ALPHANUMERIC),
+            escape_html(&query),
+            escape_html(name)
+        ));
+    }
+    html.push_str("</ul></body></html>\n");
+
+    let mut response = Response::new(if head_only {
+        Body::empty()
+    } else {
+        Body::from(html)
+    });
+    let _ = response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+    response
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_range_should_follow_the_range_header() {
+        assert_eq!(byte_range(None, 100), ByteRange::Full);
+        assert_eq!(
+            byte_range(Some("bytes=0-9"), 100),
+            ByteRange::Partial { start: 0, end: 9 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=90-"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=90-200"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=-10"), 100),
+            ByteRange::Partial { start: 90, end: 99 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=-200"), 100),
+            ByteRange::Partial { start: 0, end: 99 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(byte_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+
+        // Malformed or multiple ranges are ignored
+        assert_eq!(byte_range(Some("bytes=9-0"), 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("items=0-1"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn chunk_cache_should_evict_the_least_recently_used_chunks() {
+        let mut cache = ChunkCache::new(10);
+        let names: Vec<XorName> = (0..3u8).map(|i| XorName([i; 32])).collect();
+
+        cache.insert(names[0], Bytes::from(vec![0; 4]));
+        cache.insert(names[1], Bytes::from(vec![1; 4]));
+        assert!(cache.get(&names[0]).is_some());
+        cache.insert(names[2], Bytes::from(vec![2; 4]));
+
+        assert!(cache.get(&names[0]).is_some());
+        assert!(cache.get(&names[1]).is_none());
+        assert!(cache.get(&names[2]).is_some());
+        assert_eq!(cache.size, 8);
+
+        // A chunk larger than the cache is not kept
+        cache.insert(names[1], Bytes::from(vec![1; 11]));
+        assert!(cache.get(&names[1]).is_none());
+        assert_eq!(cache.size, 8);
+    }
+
+    #[tokio::test]
+    async fn listing_links_should_carry_the_token() -> eyre::Result<()> {
+        let entries = vec![(
+            "a b".to_string(),
+            FolderEntry::Folder(sn_registers::RegisterAddress::new(
+                XorName([1; 32]),
+                bls::SecretKey::random().public_key(),
+            )),
+        )];
+
+        let listing = listing_response("folder", &entries, Some("s3cret"), false);
+        let html = hyper::body::to_bytes(listing.into_body()).await?;
+        assert!(String::from_utf8(html.to_vec())?
+            .contains("<a href=\"/folder/a%20b?token=s3cret\">a b/</a>"));
+
+        let listing = listing_response("folder", &entries, None, false);
+        let html = hyper::body::to_bytes(listing.into_body()).await?;
+        assert!(String::from_utf8(html.to_vec())?.contains("<a href=\"/folder/a%20b\">"));
+        Ok(())
+    }
+}
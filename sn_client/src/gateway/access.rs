@@ -0,0 +1,557 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The control of who a gateway serves and how much, for operators to expose the public content
+//! of the Network without their gateway being used as a free and unlimited CDN.
+//!
+//! Clients are told apart by their IP, or the /64 network of an IPv6 one as it is commonly
+//! assigned to a single host, each one being limited in the number of requests it makes and in the
+//! bytes of the files it is sent. The bearer of an access token is exempt from both.
+
+use hyper::{header, HeaderMap, Uri};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The query parameter an access token can be given in, for links to carry it.
+pub(super) const TOKEN_QUERY_PARAM: &str = "token";
+
+/// The max number of clients whose usage is tracked. New clients are refused while that many are
+/// tracked, rather than having the usage of others forgotten.
+const MAX_TRACKED_CLIENTS: usize = 100_000;
+
+/// The number of requests a client can make, over a period.
+///
+/// Up to `requests` can be made at once, then one more each `per / requests`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+/// The bytes of files a client can be sent, over each period starting with its first request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BandwidthQuota {
+    pub bytes: u64,
+    pub per: Duration,
+}
+
+/// What a gateway serves and to whom, the default serving everything to anyone without limits.
+#[derive(Debug, Clone, Default)]
+pub struct GatewayAccess {
+    /// The requests of each client, unlimited when none.
+    pub rate_limit: Option<RateLimit>,
+    /// The bytes sent to each client, unlimited when none.
+    ///
+    /// A response is refused as a whole when its body would exceed what is left of the quota, a
+    /// client can still ask for a part of the file then, with a byte range.
+    pub bandwidth_quota: Option<BandwidthQuota>,
+    /// The hex encoded prefixes of the addresses served, any address being served when empty.
+    ///
+    /// The address checked is the one of the chunk, or of the folder, the path starts with, or
+    /// the one a registered name points to. Everything within an allowed folder is served.
+    pub allowed_prefixes: Vec<String>,
+    /// The tokens whose bearer is exempt from the rate limit and the bandwidth quota.
+    ///
+    /// A token is given as `Authorization: Bearer <token>`, or in the `token` query parameter.
+    pub access_tokens: HashSet<String>,
+    /// Whether only the bearers of an access token are served.
+    pub require_token: bool,
+    /// Whether the client IP is taken from the last entry of the `X-Forwarded-For` header, as
+    /// appended by a reverse proxy in front of the gateway, rather than from the connection.
+    pub trust_forwarded_for: bool,
+}
+
+/// The reason a request is refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Denied {
+    /// No valid access token was given, while one is required.
+    Unauthorized,
+    /// The client made too many requests, or was sent too many bytes, and is to retry after
+    /// the given time.
+    TooManyRequests { retry_after: Duration },
+}
+
+/// The client a request is made by, once admitted.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Admission {
+    /// The IP of the client, the /64 network for an IPv6 one
+    ip: IpAddr,
+    /// Whether the client bears an access token, being exempt from the limits
+    exempt: bool,
+}
+
+/// What a client has used of its limits.
+#[derive(Debug, Clone, Copy)]
+struct ClientUsage {
+    /// The requests the client can still make at once
+    requests_left: f64,
+    refilled_at: Instant,
+    bytes_sent: u64,
+    quota_started_at: Instant,
+}
+
+/// Applies the `GatewayAccess` of a gateway, tracking the usage of its clients.
+#[derive(Debug)]
+pub(super) struct AccessControl {
+    access: GatewayAccess,
+    allowed_prefixes: Vec<String>,
+    clients: Mutex<HashMap<IpAddr, ClientUsage>>,
+    pruned_at: Mutex<Instant>,
+}
+
+impl AccessControl {
+    pub(super) fn new(access: GatewayAccess) -> Self {
+        let allowed_prefixes = access
+            .allowed_prefixes
+            .iter()
+            .map(|prefix| prefix.to_lowercase())
+            .collect();
+        Self {
+            access,
+            allowed_prefixes,
+            clients: Mutex::new(HashMap::new()),
+            pruned_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Admit a request received from `remote_addr`, counting it against the rate limit of its
+    /// client unless it bears an access token.
+    pub(super) fn admit(
+        &self,
+        uri: &Uri,
+        headers: &HeaderMap,
+        remote_addr: SocketAddr,
+        now: Instant,
+    ) -> Result<Admission, Denied> {
+        let ip = self.client_ip(headers, remote_addr);
+        let exempt = self.bears_token(uri, headers);
+        if self.access.require_token && !exempt {
+            return Err(Denied::Unauthorized);
+        }
+        let admission = Admission { ip, exempt };
+        let Some(rate_limit) = self.access.rate_limit.filter(|_| !exempt) else {
+            return Ok(admission);
+        };
+
+        self.prune(now);
+        let mut clients = self.clients();
+        let usage = self.usage(&mut clients, ip, now)?;
+        let rate = f64::from(rate_limit.requests) / rate_limit.per.as_secs_f64().max(f64::EPSILON);
+        usage.requests_left = (usage.requests_left
+            + now
+                .saturating_duration_since(usage.refilled_at)
+                .as_secs_f64()
+                * rate)
+            .min(f64::from(rate_limit.requests));
+        usage.refilled_at = now;
+        if usage.requests_left < 1.0 {
+            debug!("Gateway client {ip} is over its rate limit");
+            return Err(Denied::TooManyRequests {
+                retry_after: Duration::from_secs_f64((1.0 - usage.requests_left) / rate),
+            });
+        }
+        usage.requests_left -= 1.0;
+        Ok(admission)
+    }
+
+    /// Whether the content at the hex encoded address is served.
+    pub(super) fn is_servable(&self, address: &str) -> bool {
+        let address = address.to_lowercase();
+        self.allowed_prefixes.is_empty()
+            || self
+                .allowed_prefixes
+                .iter()
+                .any(|prefix| address.starts_with(prefix))
+    }
+
+    /// Count `bytes` about to be sent to the client against its bandwidth quota, refusing to
+    /// send them when they exceed what is left of it.
+    pub(super) fn charge(
+        &self,
+        admission: &Admission,
+        bytes: u64,
+        now: Instant,
+    ) -> Result<(), Denied> {
+        let Some(quota) = self.access.bandwidth_quota.filter(|_| !admission.exempt) else {
+            return Ok(());
+        };
+        self.prune(now);
+        let mut clients = self.clients();
+        let usage = self.usage(&mut clients, admission.ip, now)?;
+        if now.saturating_duration_since(usage.quota_started_at) >= quota.per {
+            usage.bytes_sent = 0;
+            usage.quota_started_at = now;
+        }
+        if usage.bytes_sent.saturating_add(bytes) > quota.bytes {
+            debug!(
+                "Gateway client {} is over its bandwidth quota",
+                admission.ip
+            );
+            return Err(Denied::TooManyRequests {
+                retry_after: (usage.quota_started_at + quota.per).saturating_duration_since(now),
+            });
+        }
+        usage.bytes_sent += bytes;
+        Ok(())
+    }
+
+    /// The access token given in the query of `uri`, if it is a valid one, for the links of the
+    /// response to carry it.
+    pub(super) fn query_token<'a>(&self, uri: &'a Uri) -> Option<&'a str> {
+        query_tokens(uri).find(|token| self.access.access_tokens.contains(*token))
+    }
+
+    fn client_ip(&self, headers: &HeaderMap, remote_addr: SocketAddr) -> IpAddr {
+        let ip = if self.access.trust_forwarded_for {
+            headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .last()
+                .and_then(|ip| ip.trim().parse().ok())
+                .unwrap_or_else(|| remote_addr.ip())
+        } else {
+            remote_addr.ip()
+        };
+        match ip {
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => IpAddr::V4(ip),
+                None => IpAddr::V6(Ipv6Addr::from(u128::from(ip) & !u128::from(u64::MAX))),
+            },
+            ip => ip,
+        }
+    }
+
+    fn bears_token(&self, uri: &Uri, headers: &HeaderMap) -> bool {
+        if self.access.access_tokens.is_empty() {
+            return false;
+        }
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        bearer
+            .into_iter()
+            .chain(query_tokens(uri))
+            .any(|token| self.access.access_tokens.contains(token))
+    }
+
+    /// The usage of the client, tracking it if it is new and there is room for it.
+    fn usage<'a>(
+        &self,
+        clients: &'a mut HashMap<IpAddr, ClientUsage>,
+        ip: IpAddr,
+        now: Instant,
+    ) -> Result<&'a mut ClientUsage, Denied> {
+        if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(&ip) {
+            warn!("Gateway is tracking {MAX_TRACKED_CLIENTS} clients, refusing new client {ip}");
+            return Err(Denied::TooManyRequests {
+                retry_after: self.idle_period(),
+            });
+        }
+        Ok(clients.entry(ip).or_insert_with(|| self.new_usage(now)))
+    }
+
+    fn new_usage(&self, now: Instant) -> ClientUsage {
+        ClientUsage {
+            requests_left: self
+                .access
+                .rate_limit
+                .map_or(0.0, |rate_limit| f64::from(rate_limit.requests)),
+            refilled_at: now,
+            bytes_sent: 0,
+            quota_started_at: now,
+        }
+    }
+
+    /// Forget the clients idle for longer than the periods of the limits, which would be treated
+    /// as new clients anyway, for their number not to grow unbounded.
+    fn prune(&self, now: Instant) {
+        let idle = self.idle_period();
+        {
+            let mut pruned_at = self
+                .pruned_at
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if now.saturating_duration_since(*pruned_at) < idle {
+                return;
+            }
+            *pruned_at = now;
+        }
+        self.clients().retain(|_, usage| {
+            now.saturating_duration_since(usage.refilled_at) < idle
+                || now.saturating_duration_since(usage.quota_started_at) < idle
+        });
+    }
+
+    /// How long a client is idle for before being treated as a new one.
+    fn idle_period(&self) -> Duration {
+        self.access
+            .rate_limit
+            .map(|rate_limit| rate_limit.per)
+            .into_iter()
+            .chain(self.access.bandwidth_quota.map(|quota| quota.per))
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn clients(&self) -> std::sync::MutexGuard<'_, HashMap<IpAddr, ClientUsage>> {
+        // the usage is left consistent by each access, so a panic of another one can be ignored
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The tokens given in the query of `uri`.
+fn query_tokens(uri: &Uri) -> impl Iterator<Item = &str> {
+    uri.query().into_iter().flat_map(|query| {
+        query.split('&').filter_map(|param| {
+            param
+                .strip_prefix(TOKEN_QUERY_PARAM)
+                .and_then(|value| value.strip_prefix('='))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    fn remote_addr(last_byte: u8) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, last_byte], 4242))
+    }
+
+    #[test]
+    fn requests_should_be_limited_per_client() {
+        let access = AccessControl::new(GatewayAccess {
+            rate_limit: Some(RateLimit {
+                requests: 2,
+                per: Duration::from_secs(10),
+            }),
+            ..Default::default()
+        });
+        let uri = Uri::from_static("/");
+        let headers = HeaderMap::new();
+        let now = Instant::now();
+
+        assert!(access.admit(&uri, &headers, remote_addr(1), now).is_ok());
+        assert!(access.admit(&uri, &headers, remote_addr(1), now).is_ok());
+        assert_eq!(
+            access.admit(&uri, &headers, remote_addr(1), now).err(),
+            Some(Denied::TooManyRequests {
+                retry_after: Duration::from_secs(5)
+            })
+        );
+        // other clients have their own limit
+        assert!(access.admit(&uri, &headers, remote_addr(2), now).is_ok());
+        // one more request is allowed every 5s
+        let later = now + Duration::from_secs(5);
+        assert!(access.admit(&uri, &headers, remote_addr(1), later).is_ok());
+        assert!(access.admit(&uri, &headers, remote_addr(1), later).is_err());
+    }
+
+    #[test]
+    fn bytes_sent_should_be_limited_per_client_and_period() {
+        let access = AccessControl::new(GatewayAccess {
+            bandwidth_quota: Some(BandwidthQuota {
+                bytes: 100,
+                per: Duration::from_secs(60),
+            }),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let client = access
+            .admit(
+                &Uri::from_static("/"),
+                &HeaderMap::new(),
+                remote_addr(1),
+                now,
+            )
+            .expect("the client to be admitted");
+
+        assert!(access.charge(&client, 60, now).is_ok());
+        assert_eq!(
+            access.charge(&client, 60, now + Duration::from_secs(20)),
+            Err(Denied::TooManyRequests {
+                retry_after: Duration::from_secs(40)
+            })
+        );
+        assert!(access.charge(&client, 40, now).is_ok());
+        assert!(access
+            .charge(&client, 60, now + Duration::from_secs(60))
+            .is_ok());
+    }
+
+    #[test]
+    fn tokens_should_exempt_from_the_limits_and_can_be_required() {
+        let access = AccessControl::new(GatewayAccess {
+            rate_limit: Some(RateLimit {
+                requests: 1,
+                per: Duration::from_secs(60),
+            }),
+            bandwidth_quota: Some(BandwidthQuota {
+                bytes: 1,
+                per: Duration::from_secs(60),
+            }),
+            access_tokens: HashSet::from(["secret".to_string()]),
+            require_token: true,
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let mut headers = HeaderMap::new();
+        let plain = Uri::from_static("/abc");
+
+        assert_eq!(
+            access.admit(&plain, &headers, remote_addr(1), now).err(),
+            Some(Denied::Unauthorized)
+        );
+        let with_token = Uri::from_static("/abc?download=1&token=secret");
+        for _ in 0..3 {
+            let client = access
+                .admit(&with_token, &headers, remote_addr(1), now)
+                .expect("the bearer of a token to be admitted");
+            assert!(access.charge(&client, 1000, now).is_ok());
+        }
+
+        let _ = headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        assert!(access.admit(&plain, &headers, remote_addr(1), now).is_ok());
+        let _ = headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer guess"),
+        );
+        assert_eq!(
+            access.admit(&plain, &headers, remote_addr(1), now).err(),
+            Some(Denied::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn only_addresses_with_an_allowed_prefix_should_be_servable() {
+        let open = AccessControl::new(GatewayAccess::default());
+        assert!(open.is_servable("ab12"));
+
+        let restricted = AccessControl::new(GatewayAccess {
+            allowed_prefixes: vec!["AB".to_string(), "0f3".to_string()],
+            ..Default::default()
+        });
+        assert!(restricted.is_servable("ab12"));
+        assert!(restricted.is_servable("0F3e"));
+        assert!(!restricted.is_servable("0f4e"));
+        assert!(!restricted.is_servable("ba"));
+    }
+
+    #[test]
+    fn the_client_ip_should_come_from_the_proxy_only_when_trusted() {
+        let mut headers = HeaderMap::new();
+        let _ = headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("1.2.3.4, 5.6.7.8"),
+        );
+
+        let direct = AccessControl::new(GatewayAccess::default());
+        assert_eq!(
+            direct.client_ip(&headers, remote_addr(1)),
+            remote_addr(1).ip()
+        );
+
+        let proxied = AccessControl::new(GatewayAccess {
+            trust_forwarded_for: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            proxied.client_ip(&headers, remote_addr(1)),
+            IpAddr::from([5, 6, 7, 8])
+        );
+        assert_eq!(
+            proxied.client_ip(&HeaderMap::new(), remote_addr(1)),
+            remote_addr(1).ip()
+        );
+    }
+
+    #[test]
+    fn ipv6_clients_should_be_told_apart_by_their_network() {
+        let access = AccessControl::new(GatewayAccess::default());
+        let ip = |ip: &str| {
+            access.client_ip(
+                &HeaderMap::new(),
+                SocketAddr::new(ip.parse().expect("a valid ip"), 4242),
+            )
+        };
+
+        assert_eq!(ip("2001:db8:1:2:aaaa::1"), ip("2001:db8:1:2:bbbb::2"));
+        assert_ne!(ip("2001:db8:1:2::1"), ip("2001:db8:1:3::1"));
+        assert_eq!(ip("::ffff:10.0.0.1"), remote_addr(1).ip());
+    }
+
+    #[test]
+    fn new_clients_should_be_refused_once_too_many_are_tracked() {
+        let access = AccessControl::new(GatewayAccess {
+            rate_limit: Some(RateLimit {
+                requests: 1,
+                per: Duration::from_secs(60),
+            }),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        access
+            .clients()
+            .extend((0..MAX_TRACKED_CLIENTS as u32).map(|i| {
+                (
+                    IpAddr::from(std::net::Ipv4Addr::from(0x0b00_0000 + i)),
+                    access.new_usage(now),
+                )
+            }));
+        let uri = Uri::from_static("/");
+
+        assert_eq!(
+            access
+                .admit(&uri, &HeaderMap::new(), remote_addr(1), now)
+                .err(),
+            Some(Denied::TooManyRequests {
+                retry_after: Duration::from_secs(60)
+            })
+        );
+        // the tracked clients are still served
+        assert!(access
+            .admit(
+                &uri,
+                &HeaderMap::new(),
+                "11.0.0.1:4242".parse().expect("a valid addr"),
+                now
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn only_valid_tokens_should_be_carried_by_links() {
+        let access = AccessControl::new(GatewayAccess {
+            access_tokens: HashSet::from(["secret".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(
+            access.query_token(&Uri::from_static("/abc?token=guess&token=secret")),
+            Some("secret")
+        );
+        assert_eq!(
+            access.query_token(&Uri::from_static("/abc?token=guess")),
+            None
+        );
+        assert_eq!(access.query_token(&Uri::from_static("/abc")), None);
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The hedging of the chunk GETs, cutting their tail latency.
+//!
+//! A GET not answered within a percentile of the latencies of the recent ones is raced by a
+//! request sent straight to another holder of the chunk, the first chunk received being kept.
+//! The holder is one of the expected holders of the GET, or else of the closest peers to the
+//! chunk, other than the closest one, which the GET is likely waiting on.
+//! The hedges are paid from a budget earned by the GETs made, capping the extra load they put on
+//! the Network at a fraction of the GETs.
+
+use super::Client;
+use futures::future::{select, Either};
+use libp2p::{
+    kad::{Record, RecordKey},
+    PeerId,
+};
+use rand::seq::SliceRandom;
+use sn_networking::{
+    target_arch::{sleep, Instant},
+    GetRecordCfg, NetworkError,
+};
+use sn_protocol::{
+    messages::{Query, QueryResponse, Request, Response},
+    storage::{try_deserialize_record, Chunk, ChunkAddress},
+    NetworkAddress, PrettyPrintRecordKey, CLOSE_GROUP_SIZE,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    pin::pin,
+    sync::Mutex,
+    time::Duration,
+};
+use xor_name::XorName;
+
+/// The number of recent GETs whose latency the hedging delay is derived from.
+const LATENCY_SAMPLES: usize = 512;
+/// The number of GETs to make before their latencies replace the initial delay.
+const MIN_LATENCY_SAMPLES: usize = 20;
+
+/// The hedging of the chunk GETs, see [`Client::set_chunk_hedging`].
+#[derive(Clone, Copy, Debug)]
+pub struct HedgeCfg {
+    /// The percentile of the latencies of the recent GETs after which a GET is hedged, e.g. 0.95
+    pub percentile: f64,
+    /// The delay before hedging, until enough GETs were made to know their latencies
+    pub initial_delay: Duration,
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+    /// The hedges earned by each GET, e.g. 0.1 for one hedge every ten GETs at most
+    pub budget_ratio: f64,
+    /// The hedges which can be made in a row from the budget saved, which starts full
+    pub max_burst: u32,
+}
+
+impl Default for HedgeCfg {
+    fn default() -> Self {
+        Self {
+            percentile: 0.95,
+            initial_delay: Duration::from_secs(2),
+            min_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(10),
+            budget_ratio: 0.1,
+            max_burst: 10,
+        }
+    }
+}
+
+/// The latencies of the recent GETs and the budget left, shared by the clones of the client.
+#[derive(Debug)]
+pub(crate) struct Hedging {
+    cfg: HedgeCfg,
+    state: Mutex<HedgingState>,
+}
+
+#[derive(Debug)]
+struct HedgingState {
+    latencies: VecDeque<Duration>,
+    budget: f64,
+}
+
+impl Hedging {
+    pub(crate) fn new(cfg: HedgeCfg) -> Self {
+        Self {
+            cfg,
+            state: Mutex::new(HedgingState {
+                latencies: VecDeque::with_capacity(LATENCY_SAMPLES),
+                budget: f64::from(cfg.max_burst),
+            }),
+        }
+    }
+
+    /// Start a GET, earning its share of the budget, returning the delay after which it is hedged.
+    fn start(&self) -> Duration {
+        let mut state = self.lock_state();
+        state.budget = (state.budget + self.cfg.budget_ratio).min(f64::from(self.cfg.max_burst));
+        if state.latencies.len() < MIN_LATENCY_SAMPLES {
+            return self.cfg.initial_delay;
+        }
+        let mut latencies: Vec<_> = state.latencies.iter().copied().collect();
+        latencies.sort_unstable();
+        let rank = (self.cfg.percentile.clamp(0.0, 1.0) * latencies.len() as f64).ceil() as usize;
+        let delay = latencies[rank.clamp(1, latencies.len()) - 1];
+        delay.clamp(self.cfg.min_delay, self.cfg.max_delay)
+    }
+
+    /// Whether the budget allows another hedge, which is then paid for.
+    fn try_hedge(&self) -> bool {
+        let mut state = self.lock_state();
+        if state.budget < 1.0 {
+            return false;
+        }
+        state.budget -= 1.0;
+        true
+    }
+
+    /// Record the latency of a GET, hedged or not.
+    fn record(&self, latency: Duration) {
+        let mut state = self.lock_state();
+        if state.latencies.len() == LATENCY_SAMPLES {
+            let _ = state.latencies.pop_front();
+        }
+        state.latencies.push_back(latency);
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, HedgingState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Client {
+    /// Get the record of the chunk, hedging the GET once it is slower than most of the recent
+    /// ones, if the budget allows.
+    pub(crate) async fn get_chunk_record_hedged(
+        &self,
+        hedging: &Hedging,
+        address: ChunkAddress,
+        key: RecordKey,
+        cfg: &GetRecordCfg,
+    ) -> Result<Record, NetworkError> {
+        let start = Instant::now();
+        let delay = hedging.start();
+        let mut primary = pin!(self.network.get_record_from_network(key.clone(), cfg));
+
+        let result = match select(primary.as_mut(), pin!(sleep(delay))).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) if !hedging.try_hedge() => primary.await,
+            Either::Right(_) => {
+                debug!(
+                    "Hedging the GET of chunk {:?} after {delay:?}",
+                    PrettyPrintRecordKey::from(&key)
+                );
+                let hedge =
+                    pin!(self.get_chunk_record_from_a_holder(address, key, &cfg.expected_holders));
+                // the first chunk received is kept, a failed request leaving it to the other
+                match select(primary, hedge).await {
+                    Either::Left((Ok(record), _)) | Either::Right((Some(record), _)) => Ok(record),
+                    Either::Left((Err(err), hedge)) => hedge.await.ok_or(err),
+                    Either::Right((None, primary)) => primary.await,
+                }
+            }
+        };
+        if result.is_ok() {
+            hedging.record(start.elapsed());
+        }
+        result
+    }
+
+    /// Get the record of the chunk from one of its holders, none if it could not be, or was not
+    /// the chunk at the address.
+    async fn get_chunk_record_from_a_holder(
+        &self,
+        address: ChunkAddress,
+        key: RecordKey,
+        expected_holders: &HashSet<PeerId>,
+    ) -> Option<Record> {
+        let network_address = NetworkAddress::from_chunk_address(address);
+        let mut holders: Vec<PeerId> = if expected_holders.is_empty() {
+            self.network
+                .client_get_closest_peers(&network_address)
+                .await
+                .ok()?
+        } else {
+            expected_holders.iter().copied().collect()
+        };
+        let our_id = self.network.peer_id();
+        holders.retain(|peer| *peer != our_id);
+        holders.sort_by_key(|peer| network_address.distance(&NetworkAddress::from_peer(*peer)));
+        holders.truncate(CLOSE_GROUP_SIZE);
+        let holder = *holders.get(1..)?.choose(&mut rand::thread_rng())?;
+
+        let request = Request::Query(Query::GetReplicatedRecord {
+            requester: NetworkAddress::from_peer(self.network.peer_id()),
+            key: network_address,
+        });
+        let value = match self.network.send_request(request, holder).await {
+            Ok(Response::Query(QueryResponse::GetReplicatedRecord(Ok((_, value))))) => value,
+            other => {
+                debug!("The hedged GET of chunk {address:?} from {holder:?} failed: {other:?}");
+                return None;
+            }
+        };
+        let record = Record::new(key, value.to_vec());
+        // a single holder is trusted no further than the chunk it returns matches its address
+        match try_deserialize_record::<Chunk>(&record) {
+            Ok(chunk) if XorName::from_content(chunk.value()) == *address.xorname() => Some(record),
+            _ => {
+                warn!("The hedged GET of chunk {address:?} from {holder:?} returned another chunk");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn hedging_delay_should_follow_the_percentile_of_the_latencies() {
+        let hedging = Hedging::new(HedgeCfg {
+            percentile: 0.9,
+            initial_delay: ms(500),
+            min_delay: ms(20),
+            max_delay: ms(5_000),
+            ..Default::default()
+        });
+        assert_eq!(hedging.start(), ms(500));
+
+        for millis in 1..=100 {
+            hedging.record(ms(millis * 10));
+        }
+        assert_eq!(hedging.start(), ms(900));
+
+        // the oldest latencies are forgotten
+        for _ in 0..LATENCY_SAMPLES {
+            hedging.record(ms(1));
+        }
+        assert_eq!(hedging.start(), ms(20));
+    }
+
+    #[test]
+    fn hedges_should_be_capped_by_the_budget() {
+        let hedging = Hedging::new(HedgeCfg {
+            budget_ratio: 0.25,
+            max_burst: 2,
+            ..Default::default()
+        });
+        // the budget starts full
+        assert!(hedging.try_hedge());
+        assert!(hedging.try_hedge());
+        assert!(!hedging.try_hedge());
+
+        // then a hedge is earned every four GETs
+        for _ in 0..3 {
+            let _ = hedging.start();
+        }
+        assert!(!hedging.try_hedge());
+        let _ = hedging.start();
+        assert!(hedging.try_hedge());
+
+        // and no more than the burst can be saved
+        for _ in 0..100 {
+            let _ = hedging.start();
+        }
+        assert!(hedging.try_hedge());
+        assert!(hedging.try_hedge());
+        assert!(!hedging.try_hedge());
+    }
+}
@@ -0,0 +1,371 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{error::Result, Client, ClientRegister, WalletClient};
+use crate::{acc_packet::load_account_wallet_or_create_with_mnemonic, Error};
+use sn_networking::{GetRecordError, NetworkError};
+use sn_protocol::{error::Error as ProtocolError, storage::RegisterAddress};
+use sn_registers::{EntryHash, Permissions};
+use sn_transfers::{CashNote, MainPubkey, NanoTokens, Transfer, WalletError};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+use xor_name::XorName;
+
+/// The file of the wallet dir recording the entries of its inbox already received.
+pub const INBOX_RECEIVED_FILE: &str = "inbox_received";
+
+/// The file of the wallet dir recording the index of the first inbox register not fully received.
+pub const INBOX_INDEX_FILE: &str = "inbox_index";
+
+/// The entries an inbox register holds at most, once reached the next register of the inbox is
+/// delivered to.
+pub const INBOX_CAPACITY: u64 = 1024;
+
+/// The time between two polls of an inbox.
+pub const DEFAULT_INBOX_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The meta of the inbox registers, which are told apart by their owner and their index.
+const INBOX_META: &[u8] = b"inbox";
+
+/// The address of the register at `index` of the inbox of the wallet with the `recipient` main
+/// key. The first register of an inbox is at index 0.
+pub fn inbox_address(recipient: MainPubkey, index: u64) -> RegisterAddress {
+    RegisterAddress::new(inbox_meta(index), recipient.public_key())
+}
+
+fn inbox_meta(index: u64) -> XorName {
+    if index == 0 {
+        XorName::from_content(INBOX_META)
+    } else {
+        XorName::from_content(&[INBOX_META, &index.to_be_bytes()].concat())
+    }
+}
+
+/// Deliver a transfer to the inbox of its recipient, who has to have created it.
+///
+/// The transfer is encrypted to the recipient, so it can be written where anyone can read it. It
+/// is written to the first register of the inbox which is not full, the recipient creating the
+/// next one when receiving from a full register.
+pub async fn deliver_to_inbox(
+    client: &Client,
+    recipient: MainPubkey,
+    transfer: &Transfer,
+    verify_store: bool,
+) -> Result<()> {
+    let mut index = 0;
+    let mut register = loop {
+        let address = inbox_address(recipient, index);
+        match retrieve(client, address).await? {
+            Some(register) if register.size() < INBOX_CAPACITY => break register,
+            Some(_) => index += 1,
+            None if index == 0 => return Err(Error::InboxNotFound(recipient)),
+            None => return Err(Error::InboxFull(recipient)),
+        }
+    };
+    register
+        .write_merging_branches_online(&transfer.to_bytes()?, verify_store)
+        .await?;
+    info!(
+        "Delivered a transfer to the inbox at {:?}",
+        register.address()
+    );
+    Ok(())
+}
+
+/// The events emitted while receiving the transfers of an inbox.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InboxEvent {
+    /// A transfer was received and its cash notes deposited to the wallet.
+    Received {
+        entry: EntryHash,
+        amount: NanoTokens,
+    },
+    /// An entry was not a valid transfer to the wallet, or its cash notes were spent already.
+    Rejected { entry: EntryHash, error: String },
+}
+
+/// The inbox of a wallet: registers derived from its main key, which anyone can write transfers
+/// to, and which the wallet polls to deposit them, without them being delivered out-of-band.
+///
+/// A register holds up to `INBOX_CAPACITY` entries. Once one is full, the wallet creates the next
+/// one the next time it receives, paying for it, so that filling the inbox does not keep senders
+/// from delivering to it.
+pub struct Inbox {
+    client: Client,
+    wallet_dir: PathBuf,
+    /// The entries already received or rejected
+    received: BTreeSet<EntryHash>,
+    /// The index of the first register not fully received
+    index: u64,
+    verify_store: bool,
+    event_sender: Option<mpsc::Sender<InboxEvent>>,
+}
+
+impl Inbox {
+    /// The inbox of the wallet in `wallet_dir`.
+    pub fn new(client: Client, wallet_dir: PathBuf) -> Result<Self> {
+        let received = load_received(&wallet_dir)?;
+        let index = load_index(&wallet_dir)?;
+        Ok(Self {
+            client,
+            wallet_dir,
+            received,
+            index,
+            verify_store: true,
+            event_sender: None,
+        })
+    }
+
+    /// Set whether the registers the inbox is rotated to are verified once stored, which they are
+    /// by default.
+    pub fn set_verify_store(&mut self, verify_store: bool) {
+        self.verify_store = verify_store;
+    }
+
+    /// Get a receiver of the events of the inbox.
+    pub fn get_event_receiver(&mut self) -> mpsc::Receiver<InboxEvent> {
+        let (tx, rx) = mpsc::channel(100);
+        self.event_sender = Some(tx);
+        rx
+    }
+
+    /// The address of the first register of the inbox, for the senders to deliver transfers to.
+    pub fn address(&self) -> Result<RegisterAddress> {
+        let wallet = load_account_wallet_or_create_with_mnemonic(&self.wallet_dir, None)?;
+        Ok(inbox_address(wallet.address(), 0))
+    }
+
+    /// Create the inbox on the network, paying for it from the wallet. Returns the cost of the
+    /// inbox, royalties included.
+    pub async fn create(&mut self, verify_store: bool) -> Result<NanoTokens> {
+        self.verify_store = verify_store;
+        self.create_register(0).await
+    }
+
+    /// Create the register at `index` of the inbox, paying for it from the wallet.
+    async fn create_register(&self, index: u64) -> Result<NanoTokens> {
+        let wallet = load_account_wallet_or_create_with_mnemonic(&self.wallet_dir, None)?;
+        // the inbox is owned by the main key of the wallet, for the senders to find it
+        let mut owner_client = self.client.clone();
+        owner_client.set_signer_key(wallet.key().secret_key().clone());
+        let mut wallet_client = WalletClient::new(self.client.clone(), wallet);
+
+        let (register, storage_cost, royalties_fees) = ClientRegister::create_online(
+            owner_client,
+            inbox_meta(index),
+            &mut wallet_client,
+            self.verify_store,
+            Permissions::new_anyone_can_write(),
+        )
+        .await?;
+        info!("Created the inbox register at {:?}", register.address());
+
+        storage_cost
+            .checked_add(royalties_fees)
+            .ok_or(Error::NumericOverflow)
+    }
+
+    /// Receive the transfers delivered to the inbox since the last time, depositing their cash
+    /// notes to the wallet. Returns the cash notes deposited.
+    ///
+    /// When the last register of the inbox is full, the next one is created.
+    pub async fn receive(&mut self) -> Result<Vec<CashNote>> {
+        let recipient =
+            load_account_wallet_or_create_with_mnemonic(&self.wallet_dir, None)?.address();
+        let mut deposited = vec![];
+        let mut index = self.index;
+        loop {
+            let address = inbox_address(recipient, index);
+            let Some(register) = retrieve(&self.client, address).await? else {
+                if index == 0 {
+                    return Err(Error::InboxNotFound(recipient));
+                }
+                // the previous register is full, senders are waiting for this one
+                let cost = self.create_register(index).await?;
+                info!("Rotated the inbox to {address:?} for {cost}");
+                break;
+            };
+
+            let all_received = self.receive_register(&register, &mut deposited).await?;
+            if register.size() < INBOX_CAPACITY {
+                break;
+            }
+            if all_received && index == self.index {
+                self.index += 1;
+                store_index(&self.wallet_dir, self.index)?;
+            }
+            index += 1;
+        }
+
+        Ok(deposited)
+    }
+
+    /// Receive the new entries of an inbox register, returning whether all its entries have been
+    /// received or rejected.
+    async fn receive_register(
+        &mut self,
+        register: &ClientRegister,
+        deposited: &mut Vec<CashNote>,
+    ) -> Result<bool> {
+        let mut wallet = load_account_wallet_or_create_with_mnemonic(&self.wallet_dir, None)?;
+        let address = register.address();
+        let entries: Vec<_> = register
+            .merkle_reg()
+            .all_nodes()
+            .map(|node| (EntryHash(node.hash()), node.value.clone()))
+            .filter(|(entry, _)| !self.received.contains(entry))
+            .collect();
+        debug!(
+            "Found {} new entries in the inbox at {address:?}",
+            entries.len()
+        );
+
+        let mut all_received = true;
+        for (entry, value) in entries {
+            let received = match Transfer::try_from_bytes(&value) {
+                Ok(transfer) => self.client.receive(&transfer, &wallet).await,
+                Err(err) => Err(WalletError::from(err)),
+            };
+            match received {
+                Ok(cash_notes) => {
+                    wallet.deposit_and_store_to_disk(&cash_notes)?;
+                    let amount = cash_notes
+                        .iter()
+                        .map(|cash_note| cash_note.value())
+                        .try_fold(NanoTokens::zero(), |total, value| total.checked_add(value))
+                        .ok_or(Error::NumericOverflow)?;
+                    info!("Received {amount} from the inbox entry {entry:?}");
+                    deposited.extend(cash_notes);
+                    self.send_event(InboxEvent::Received { entry, amount })
+                        .await?;
+                }
+                // the spends of the transfer could not be fetched, it is tried again next time
+                Err(WalletError::FailedToGetSpend(err)) => {
+                    warn!("Could not verify the inbox entry {entry:?}: {err}");
+                    all_received = false;
+                    continue;
+                }
+                Err(err) => {
+                    warn!("Rejected the inbox entry {entry:?}: {err}");
+                    self.send_event(InboxEvent::Rejected {
+                        entry,
+                        error: err.to_string(),
+                    })
+                    .await?;
+                }
+            }
+            let _ = self.received.insert(entry);
+            store_received(&self.wallet_dir, &self.received)?;
+        }
+
+        Ok(all_received)
+    }
+
+    /// Receive the transfers delivered to the inbox every `interval`, until an error occurs.
+    pub async fn run(mut self, interval: Duration) -> Result<()> {
+        loop {
+            let _ = self.receive().await?;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn send_event(&self, event: InboxEvent) -> Result<()> {
+        if let Some(sender) = &self.event_sender {
+            sender.send(event).await.map_err(|err| {
+                error!("Could not send inbox event due to {err:?}");
+                Error::CouldNotSendInboxEvent
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn load_received(wallet_dir: &Path) -> Result<BTreeSet<EntryHash>> {
+    let path = wallet_dir.join(INBOX_RECEIVED_FILE);
+    if !path.exists() {
+        return Ok(BTreeSet::new());
+    }
+    Ok(rmp_serde::from_slice(&fs::read(path)?)?)
+}
+
+fn store_received(wallet_dir: &Path, received: &BTreeSet<EntryHash>) -> Result<()> {
+    fs::write(
+        wallet_dir.join(INBOX_RECEIVED_FILE),
+        rmp_serde::to_vec(received)?,
+    )?;
+    Ok(())
+}
+
+fn load_index(wallet_dir: &Path) -> Result<u64> {
+    let path = wallet_dir.join(INBOX_INDEX_FILE);
+    if !path.exists() {
+        return Ok(0);
+    }
+    Ok(rmp_serde::from_slice(&fs::read(path)?)?)
+}
+
+fn store_index(wallet_dir: &Path, index: u64) -> Result<()> {
+    fs::write(
+        wallet_dir.join(INBOX_INDEX_FILE),
+        rmp_serde::to_vec(&index)?,
+    )?;
+    Ok(())
+}
+
+/// Retrieve the register of an inbox, unless it was not created.
+async fn retrieve(client: &Client, address: RegisterAddress) -> Result<Option<ClientRegister>> {
+    match ClientRegister::retrieve(client.clone(), address).await {
+        Ok(register) => Ok(Some(register)),
+        Err(Error::Protocol(ProtocolError::RegisterNotFound(_)))
+        | Err(Error::Network(NetworkError::GetRecordError(GetRecordError::RecordNotFound))) => {
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::MainSecretKey;
+
+    #[test]
+    fn inboxes_should_be_told_apart_by_their_recipient() {
+        let recipient = MainSecretKey::random().main_pubkey();
+        let other = MainSecretKey::random().main_pubkey();
+
+        assert_eq!(inbox_address(recipient, 0), inbox_address(recipient, 0));
+        assert_ne!(inbox_address(recipient, 0), inbox_address(other, 0));
+        assert_eq!(inbox_address(recipient, 0).owner(), recipient.public_key());
+
+        // the registers an inbox is rotated to are told apart by their index
+        assert_ne!(inbox_address(recipient, 0), inbox_address(recipient, 1));
+        assert_ne!(inbox_address(recipient, 1), inbox_address(recipient, 2));
+        assert_eq!(inbox_address(recipient, 1).owner(), recipient.public_key());
+    }
+
+    #[test]
+    fn received_entries_should_be_persisted() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(load_received(dir.path())?.is_empty());
+
+        let entries: BTreeSet<_> = [EntryHash([1; 32]), EntryHash([2; 32])].into();
+        store_received(dir.path(), &entries)?;
+        assert_eq!(load_received(dir.path())?, entries);
+
+        assert_eq!(load_index(dir.path())?, 0);
+        store_index(dir.path(), 2)?;
+        assert_eq!(load_index(dir.path())?, 2);
+        Ok(())
+    }
+}
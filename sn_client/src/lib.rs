@@ -42,15 +42,30 @@ extern crate tracing;
 pub mod acc_packet;
 pub mod api;
 mod audit;
+mod broadcaster;
 mod chunks;
 mod error;
 mod event;
 mod faucet;
 mod files;
 mod folders;
+#[cfg(feature = "gateway")]
+mod gateway;
+mod hedging;
+mod inbox;
+mod names;
+mod network;
+mod pin;
+mod receipts;
 mod register;
+mod republish;
+mod scratchpad;
+mod store;
+mod transfer_notifs;
 mod uploader;
 mod wallet;
+#[cfg(feature = "wallet-rpc")]
+mod wallet_rpc;
 
 /// Test utils
 #[cfg(feature = "test-utils")]
@@ -67,21 +82,64 @@ pub use sn_transfers as transfers;
 const MAX_CONCURRENT_TASKS: usize = 4096;
 
 pub use self::{
-    audit::{DagError, SpendDag, SpendDagGet, SpendFault},
+    audit::{
+        DagError, DagExportFilter, DagExportFormat, RoyaltyPayment, RoyaltyPeriod, RoyaltyReport,
+        SpendDag, SpendDagGet, SpendFault,
+    },
+    broadcaster::{BroadcastEvent, BroadcastSummary, TransactionBroadcaster},
+    chunks::ErasureCoding,
     error::Error,
     event::{ClientEvent, ClientEventsBroadcaster, ClientEventsReceiver},
-    faucet::fund_faucet_from_genesis_wallet,
+    faucet::{fund_faucet_from_genesis_wallet, fund_faucet_from_genesis_wallet_in},
     files::{
         download::{FilesDownload, FilesDownloadEvent},
         FilesApi, BATCH_SIZE,
     },
     folders::{FolderEntry, FoldersApi, Metadata},
+    hedging::HedgeCfg,
+    inbox::{
+        deliver_to_inbox, inbox_address, Inbox, InboxEvent, DEFAULT_INBOX_POLL_INTERVAL,
+        INBOX_RECEIVED_FILE,
+    },
+    names::{NameTarget, NamesApi, MAX_NAME_LEN, NAME_REGISTRANT_SEPARATOR, NAME_SCHEME},
+    network::ClientNetwork,
+    pin::{
+        Pin, PinCfg, PinCheckSummary, PinEvent, PinScheduler, PinSet, DEFAULT_CHECK_INTERVAL,
+        DEFAULT_MIN_REPLICAS, PIN_SET_FILE,
+    },
+    receipts::{
+        Invoice, PaymentClaim, PaymentStatus, ReceiptVerifier, SignedVerification,
+        VerificationResult, INVOICES_FILE, PAYMENT_URI_SCHEME,
+    },
     register::ClientRegister,
-    uploader::{UploadCfg, UploadEvent, UploadSummary, Uploader},
-    wallet::{send, StoragePaymentResult, WalletClient},
+    republish::{
+        Republish, RepublishCfg, RepublishEvent, RepublishSchedule, RepublishSummary,
+        RepublishTarget, Republisher, DEFAULT_REPUBLISH_INTERVAL, DEFAULT_REPUBLISH_JITTER,
+        REPUBLISH_SCHEDULE_FILE,
+    },
+    store::{
+        ClientStore, CLIENT_STORE_VERSION, CLIENT_STORE_VERSION_FILE, MAX_CACHED_PEERS,
+        PEER_CACHE_FILE, QUOTE_CACHE_DIR, UPLOAD_SESSIONS,
+    },
+    transfer_notifs::{TransferNotice, TransferNotices},
+    uploader::{ChunkDedup, UploadCfg, UploadEvent, UploadSummary, Uploader},
+    wallet::{
+        send, sweep, verify_cashnote_on, ConfirmationCfg, SpendConfirmation, SpendFinality,
+        StoragePaymentResult, WalletClient, DEFAULT_CONFIRMATION_POLL_INTERVAL,
+        DEFAULT_CONFIRMATION_TIMEOUT,
+    },
 };
 pub(crate) use error::Result;
 
+#[cfg(feature = "gateway")]
+pub use self::gateway::{
+    BandwidthQuota, Gateway, GatewayAccess, RateLimit, DEFAULT_CHUNK_CACHE_SIZE,
+};
+#[cfg(feature = "wallet-rpc")]
+pub use self::wallet_rpc::{WalletRpc, WALLET_RPC_SOCKET_FILE};
+#[cfg(feature = "test-utils")]
+pub use self::network::MockNetwork;
+
 use sn_networking::Network;
 use std::sync::Arc;
 
@@ -155,4 +213,5 @@ pub struct Client {
     network: Network,
     events_broadcaster: ClientEventsBroadcaster,
     signer: Arc<bls::SecretKey>,
+    hedging: Option<Arc<hedging::Hedging>>,
 }
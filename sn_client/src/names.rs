@@ -0,0 +1,342 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{error::Result, Client, ClientRegister, WalletClient};
+use crate::{acc_packet::load_account_wallet_or_create_with_mnemonic, Error};
+use bls::{PublicKey, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use sn_networking::{GetRecordError, NetworkError};
+use sn_protocol::{
+    error::Error as ProtocolError,
+    storage::{ChunkAddress, RegisterAddress},
+};
+use sn_registers::Permissions;
+use sn_transfers::NanoTokens;
+use std::path::PathBuf;
+use xor_name::XorName;
+
+/// The scheme names are given with in URLs, as in `safe://mysite`.
+pub const NAME_SCHEME: &str = "safe://";
+
+/// The maximum length of a name, that of a DNS label.
+pub const MAX_NAME_LEN: usize = 63;
+
+/// What a name points to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameTarget {
+    /// A file, by the address of its data map chunk.
+    File(ChunkAddress),
+    Folder(RegisterAddress),
+    PublicKey(PublicKey),
+}
+
+/// The separator of a name and the hex encoded public key of its registrant, as in
+/// `safe://mysite@<registrant>`.
+pub const NAME_REGISTRANT_SEPARATOR: char = '@';
+
+/// A version of the target of a name, signed by the registrant of the name.
+///
+/// The register of a name is owned by a key derived from the one of the registrant and the name,
+/// which only the registrant can sign with. The records are signed by the registrant as well, and
+/// only those are trusted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct NameRecord {
+    target: NameTarget,
+    version: u64,
+    signature: Signature,
+}
+
+impl NameRecord {
+    fn new(name: &str, target: NameTarget, version: u64, registrant: &SecretKey) -> Result<Self> {
+        let signature = registrant.sign(Self::bytes_to_sign(name, &target, version)?);
+        Ok(Self {
+            target,
+            version,
+            signature,
+        })
+    }
+
+    fn bytes_to_sign(name: &str, target: &NameTarget, version: u64) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec(&(name, target, version))?)
+    }
+
+    fn verify(&self, name: &str, registrant: &PublicKey) -> bool {
+        Self::bytes_to_sign(name, &self.target, self.version)
+            .map(|bytes| registrant.verify(&self.signature, bytes))
+            .unwrap_or(false)
+    }
+}
+
+/// Register human-readable names pointing to files, folders or keys, and resolve them.
+///
+/// A name maps to the address of a register, which holds the successive targets of the name. Names
+/// are scoped to their registrant, whose public key is given along with the name to resolve it,
+/// as in `safe://mysite@<registrant>`: the same name registered by two clients points to two
+/// registers, and only the registrant of a name can update it.
+#[derive(Clone)]
+pub struct NamesApi {
+    client: Client,
+    wallet_dir: PathBuf,
+}
+
+impl NamesApi {
+    /// The registrations are paid from the wallet found in `wallet_dir`.
+    pub fn new(client: Client, wallet_dir: PathBuf) -> Self {
+        Self { client, wallet_dir }
+    }
+
+    /// Create a new WalletClient from the directory set.
+    pub fn wallet(&self) -> Result<WalletClient> {
+        let wallet = load_account_wallet_or_create_with_mnemonic(&self.wallet_dir, None)?;
+        Ok(WalletClient::new(self.client.clone(), wallet))
+    }
+
+    /// Normalise a name, which may be given with the `safe://` scheme, into the name registered.
+    ///
+    /// Names are case insensitive, and made of ASCII letters, digits and inner hyphens.
+    pub fn parse_name(name: &str) -> Result<String> {
+        let name = name.strip_prefix(NAME_SCHEME).unwrap_or(name);
+        let name = name.trim_end_matches('/').to_ascii_lowercase();
+        let valid = !name.is_empty()
+            && name.len() <= MAX_NAME_LEN
+            && !name.starts_with('-')
+            && !name.ends_with('-')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid {
+            return Err(Error::InvalidName(name));
+        }
+        Ok(name)
+    }
+
+    /// Split a name given along with its registrant, as in `mysite@<registrant>`, into the name
+    /// registered and the public key of the registrant, none if not given.
+    pub fn parse_qualified_name(name: &str) -> Result<(String, Option<PublicKey>)> {
+        let name = name.strip_prefix(NAME_SCHEME).unwrap_or(name);
+        match name.split_once(NAME_REGISTRANT_SEPARATOR) {
+            Some((name, registrant)) => {
+                let registrant = registrant.trim_end_matches('/');
+                let registrant = PublicKey::from_hex(registrant)
+                    .map_err(|_| Error::InvalidNameRegistrant(registrant.to_string()))?;
+                Ok((Self::parse_name(name)?, Some(registrant)))
+            }
+            None => Ok((Self::parse_name(name)?, None)),
+        }
+    }
+
+    /// The address of the register of a name of the registrant, whether it is registered or not.
+    pub fn address(name: &str, registrant: &PublicKey) -> Result<RegisterAddress> {
+        let name = Self::parse_name(name)?;
+        Ok(RegisterAddress::new(
+            name_meta(&name),
+            registrant.derive_child(&name_meta(&name)),
+        ))
+    }
+
+    /// Register a name pointing to `target`, returning the address of its register and the cost
+    /// of the registration.
+    pub async fn register(
+        &self,
+        name: &str,
+        target: NameTarget,
+        verify_store: bool,
+    ) -> Result<(RegisterAddress, NanoTokens)> {
+        let name = Self::parse_name(name)?;
+        let address = Self::address(&name, &self.client.signer_pk())?;
+        if self.retrieve(address).await?.is_some() {
+            return Err(Error::NameTaken(name));
+        }
+
+        let mut owner_client = self.client.clone();
+        owner_client.set_signer_key(name_owner(self.client.signer(), &name));
+        let mut wallet_client = self.wallet()?;
+        let (mut register, storage_cost, royalties_fees) = ClientRegister::create_online(
+            owner_client,
+            name_meta(&name),
+            &mut wallet_client,
+            verify_store,
+            Permissions::new_with([self.client.signer_pk()]),
+        )
+        .await?;
+
+        let record = NameRecord::new(&name, target, 0, self.client.signer())?;
+        register
+            .write_merging_branches_online(&rmp_serde::to_vec(&record)?, verify_store)
+            .await?;
+        info!("Registered name {name:?} at {address:?}");
+
+        let cost = storage_cost
+            .checked_add(royalties_fees)
+            .ok_or(Error::NumericOverflow)?;
+        Ok((address, cost))
+    }
+
+    /// Point a name registered by this client to `target`, returning the version of the target.
+    pub async fn update(&self, name: &str, target: NameTarget, verify_store: bool) -> Result<u64> {
+        let name = Self::parse_name(name)?;
+        let address = Self::address(&name, &self.client.signer_pk())?;
+        let Some(register) = self.retrieve(address).await? else {
+            return Err(Error::NameNotFound(name));
+        };
+        if registrant(&register) != Some(self.client.signer_pk()) {
+            return Err(Error::NotNameRegistrant(name));
+        }
+
+        let version = latest_record(&register, &name, &self.client.signer_pk())
+            .map_or(0, |record| record.version + 1);
+        let record = NameRecord::new(&name, target, version, self.client.signer())?;
+
+        let mut owner_client = self.client.clone();
+        owner_client.set_signer_key(name_owner(self.client.signer(), &name));
+        let mut register = ClientRegister::retrieve(owner_client, address).await?;
+        register
+            .write_merging_branches_online(&rmp_serde::to_vec(&record)?, verify_store)
+            .await?;
+        info!("Updated name {name:?} to version {version}");
+        Ok(version)
+    }
+
+    /// Get the target a name points to. The name is given along with its registrant, as in
+    /// `mysite@<registrant>`, else it is a name registered by this client.
+    pub async fn resolve(&self, name: &str) -> Result<NameTarget> {
+        let (name, registrant) = Self::parse_qualified_name(name)?;
+        let registrant = registrant.unwrap_or_else(|| self.client.signer_pk());
+        let address = Self::address(&name, &registrant)?;
+        let register = self
+            .retrieve(address)
+            .await?
+            .ok_or_else(|| Error::NameNotFound(name.clone()))?;
+        if self::registrant(&register) != Some(registrant) {
+            return Err(Error::NameNotFound(name));
+        }
+
+        let record = latest_record(&register, &name, &registrant)
+            .ok_or_else(|| Error::NameNotFound(name.clone()))?;
+        debug!(
+            "Resolved name {name:?} to version {} of its target: {:?}",
+            record.version, record.target
+        );
+        Ok(record.target)
+    }
+
+    /// Retrieve the register of a name, unless it is not registered.
+    async fn retrieve(&self, address: RegisterAddress) -> Result<Option<ClientRegister>> {
+        match ClientRegister::retrieve(self.client.clone(), address).await {
+            Ok(register) => Ok(Some(register)),
+            Err(Error::Protocol(ProtocolError::RegisterNotFound(_)))
+            | Err(Error::Network(NetworkError::GetRecordError(GetRecordError::RecordNotFound))) => {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// The meta of the register of a name.
+fn name_meta(name: &str) -> XorName {
+    XorName::from_content(format!("{NAME_SCHEME}{name}").as_bytes())
+}
+
+/// The key owning the register of a name of the registrant, which only the registrant can derive,
+/// while anyone can derive its public key from the one of the registrant.
+fn name_owner(registrant: &SecretKey, name: &str) -> SecretKey {
+    registrant.derive_child(&name_meta(name))
+}
+
+/// The client which registered the name, i.e. the writer of its register other than its owner.
+fn registrant(register: &ClientRegister) -> Option<PublicKey> {
+    match register.permissions() {
+        Permissions::Writers(writers) => {
+            let mut writers = writers.iter().filter(|writer| **writer != register.owner());
+            match (writers.next(), writers.next()) {
+                (Some(registrant), None) => Some(*registrant),
+                _ => None,
+            }
+        }
+        Permissions::AnyoneCanWrite => None,
+    }
+}
+
+/// The record of the highest version signed by the registrant, out of all the entries ever
+/// written to the register.
+fn latest_record(
+    register: &ClientRegister,
+    name: &str,
+    registrant: &PublicKey,
+) -> Option<NameRecord> {
+    register
+        .merkle_reg()
+        .all_nodes()
+        .filter_map(|node| rmp_serde::from_slice::<NameRecord>(&node.value).ok())
+        .filter(|record| record.verify(name, registrant))
+        .max_by_key(|record| record.version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_should_be_normalised() -> Result<()> {
+        assert_eq!(NamesApi::parse_name("safe://MySite/")?, "mysite");
+        assert_eq!(NamesApi::parse_name("my-site-2")?, "my-site-2");
+        for invalid in [
+            "", "safe://", "-site", "site-", "my site", "my.site", "sité",
+        ] {
+            assert!(NamesApi::parse_name(invalid).is_err(), "{invalid:?}");
+        }
+        assert!(NamesApi::parse_name(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+
+        // the address of a name does not depend on how it is written, but on its registrant
+        let registrant = SecretKey::random().public_key();
+        assert_eq!(
+            NamesApi::address("safe://MySite", &registrant)?,
+            NamesApi::address("mysite", &registrant)?
+        );
+        assert_ne!(
+            NamesApi::address("mysite", &registrant)?,
+            NamesApi::address("mysite2", &registrant)?
+        );
+        assert_ne!(
+            NamesApi::address("mysite", &registrant)?,
+            NamesApi::address("mysite", &SecretKey::random().public_key())?
+        );
+
+        let qualified = format!("safe://MySite@{}/", registrant.to_hex());
+        assert_eq!(
+            NamesApi::parse_qualified_name(&qualified)?,
+            ("mysite".to_string(), Some(registrant))
+        );
+        assert_eq!(
+            NamesApi::parse_qualified_name("mysite")?,
+            ("mysite".to_string(), None)
+        );
+        assert!(NamesApi::parse_qualified_name("mysite@not-a-key").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_records_signed_by_the_registrant_should_be_trusted() -> Result<()> {
+        let registrant = SecretKey::random();
+        let target = NameTarget::PublicKey(SecretKey::random().public_key());
+        let record = NameRecord::new("mysite", target.clone(), 1, &registrant)?;
+        assert!(record.verify("mysite", &registrant.public_key()));
+        assert!(!record.verify("othersite", &registrant.public_key()));
+
+        // the owner of the register is derived from the registrant, which no one else can sign as
+        let owner = name_owner(&registrant, "mysite");
+        assert_eq!(
+            NamesApi::address("mysite", &registrant.public_key())?,
+            RegisterAddress::new(name_meta("mysite"), owner.public_key())
+        );
+        let forged = NameRecord::new("mysite", target, 2, &SecretKey::random())?;
+        assert!(!forged.verify("mysite", &registrant.public_key()));
+        Ok(())
+    }
+}
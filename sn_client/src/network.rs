@@ -0,0 +1,284 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{error::Result, Client};
+use async_trait::async_trait;
+use libp2p::kad::{Record, RecordKey};
+use sn_networking::{GetRecordCfg, PutRecordCfg};
+use sn_protocol::storage::SpendAddress;
+use sn_transfers::SignedSpend;
+
+/// The interactions of the client with the network.
+///
+/// It is implemented by the [`Client`], and by an in memory `MockNetwork` with the `test-utils`
+/// feature, so the logic built on top of it can be unit tested without a running network, as is
+/// the verification of cash notes, [`verify_cashnote_on`](crate::verify_cashnote_on).
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait ClientNetwork: Send + Sync {
+    /// Get the record stored at `key`.
+    async fn get_record(&self, key: RecordKey, cfg: &GetRecordCfg) -> Result<Record>;
+
+    /// Store the `record`.
+    async fn put_record(&self, record: Record, cfg: &PutRecordCfg) -> Result<()>;
+
+    /// Get the verified spend at `address`.
+    async fn get_spend(&self, address: SpendAddress) -> Result<SignedSpend>;
+
+    /// Send the spends of a payment, optionally verifying they are stored.
+    async fn send_payment(&self, spends: &[SignedSpend], verify_store: bool) -> Result<()>;
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl ClientNetwork for Client {
+    async fn get_record(&self, key: RecordKey, cfg: &GetRecordCfg) -> Result<Record> {
+        Ok(self.network.get_record_from_network(key, cfg).await?)
+    }
+
+    async fn put_record(&self, record: Record, cfg: &PutRecordCfg) -> Result<()> {
+        Ok(self.network.put_record(record, cfg).await?)
+    }
+
+    async fn get_spend(&self, address: SpendAddress) -> Result<SignedSpend> {
+        self.get_spend_from_network(address).await
+    }
+
+    async fn send_payment(&self, spends: &[SignedSpend], verify_store: bool) -> Result<()> {
+        Ok(self.send_spends(spends.iter(), verify_store).await?)
+    }
+}
+
+#[cfg(feature = "test-utils")]
+pub use mock::MockNetwork;
+
+#[cfg(feature = "test-utils")]
+mod mock {
+    use super::*;
+    use crate::Error;
+    use sn_networking::{get_raw_signed_spends_from_record, GetRecordError, NetworkError};
+    use sn_protocol::{
+        storage::{try_serialize_record, RecordKind},
+        NetworkAddress,
+    };
+    use sn_transfers::WalletError;
+    use std::{
+        collections::{BTreeSet, HashMap},
+        sync::{Arc, RwLock},
+    };
+
+    /// An in memory network, storing every record it is given.
+    ///
+    /// Spends are stored like the nodes do: a second spend of the same key is kept along with the
+    /// first one, and the spend is then reported as double spent.
+    #[derive(Clone, Debug, Default)]
+    pub struct MockNetwork {
+        records: Arc<RwLock<HashMap<RecordKey, Record>>>,
+    }
+
+    impl MockNetwork {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// The number of records stored.
+        pub fn record_count(&self) -> usize {
+            self.records
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .len()
+        }
+
+        fn record(&self, key: &RecordKey) -> Option<Record> {
+            self.records
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .get(key)
+                .cloned()
+        }
+
+        fn insert(&self, record: Record) {
+            let _ = self
+                .records
+                .write()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .insert(record.key.clone(), record);
+        }
+
+        /// Store the spend, returning whether it is now double spent.
+        fn store_spend(&self, spend: &SignedSpend) -> Result<bool> {
+            let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+            let key = NetworkAddress::from_spend_address(address).to_record_key();
+
+            let mut spends: BTreeSet<SignedSpend> = match self.record(&key) {
+                Some(record) => get_raw_signed_spends_from_record(&record)?
+                    .into_iter()
+                    .collect(),
+                None => BTreeSet::new(),
+            };
+            let _ = spends.insert(spend.clone());
+
+            let double_spent = spends.len() > 1;
+            let spends: Vec<_> = spends.into_iter().collect();
+            self.insert(Record {
+                key,
+                value: try_serialize_record(&spends, RecordKind::Spend)?.to_vec(),
+                publisher: None,
+                expires: None,
+            });
+            Ok(double_spent)
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+    impl ClientNetwork for MockNetwork {
+        async fn get_record(&self, key: RecordKey, _cfg: &GetRecordCfg) -> Result<Record> {
+            self.record(&key).ok_or_else(|| {
+                Error::Network(NetworkError::GetRecordError(GetRecordError::RecordNotFound))
+            })
+        }
+
+        async fn put_record(&self, record: Record, _cfg: &PutRecordCfg) -> Result<()> {
+            self.insert(record);
+            Ok(())
+        }
+
+        async fn get_spend(&self, address: SpendAddress) -> Result<SignedSpend> {
+            let key = NetworkAddress::from_spend_address(address).to_record_key();
+            let record = self.record(&key).ok_or_else(|| {
+                Error::Network(NetworkError::GetRecordError(GetRecordError::RecordNotFound))
+            })?;
+            let spend = sn_networking::get_signed_spend_from_record(&address, &record)?;
+            spend.verify()?;
+            Ok(spend)
+        }
+
+        async fn send_payment(&self, spends: &[SignedSpend], _verify_store: bool) -> Result<()> {
+            let mut double_spent_keys = BTreeSet::new();
+            for spend in spends {
+                spend.verify()?;
+                if self.store_spend(spend)? {
+                    let _ = double_spent_keys.insert(*spend.unique_pubkey());
+                }
+            }
+
+            if !double_spent_keys.is_empty() {
+                return Err(
+                    WalletError::DoubleSpendAttemptedForCashNotes(double_spent_keys).into(),
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use eyre::Result;
+    use sn_networking::{GetRecordError, NetworkError};
+    use sn_transfers::{
+        test_utils::fake_cash_note, DerivationIndex, HotWallet, MainSecretKey, NanoTokens,
+        WalletError,
+    };
+
+    #[tokio::test]
+    async fn mock_network_should_store_the_spends_of_a_payment() -> Result<()> {
+        let network = MockNetwork::new();
+        let secret_key = bls::SecretKey::random();
+        let dir = tempfile::tempdir()?;
+        let mut wallet =
+            HotWallet::create_from_key(dir.path(), MainSecretKey::new(secret_key.clone()), None)?;
+        let cash_note = fake_cash_note(wallet.address(), NanoTokens::from(1_000));
+        wallet.deposit_and_store_to_disk(&vec![cash_note.clone()])?;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _ = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        let spends: Vec<_> = wallet
+            .unconfirmed_spend_requests()
+            .iter()
+            .cloned()
+            .collect();
+        network.send_payment(&spends, true).await?;
+
+        let address = SpendAddress::from_unique_pubkey(&cash_note.unique_pubkey());
+        assert_eq!(network.get_spend(address).await?, spends[0]);
+
+        // spending the same cash note again is a double spend
+        let dir = tempfile::tempdir()?;
+        let mut other_wallet =
+            HotWallet::create_from_key(dir.path(), MainSecretKey::new(secret_key), None)?;
+        other_wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+        let _ = other_wallet.local_send(vec![(NanoTokens::from(200), recipient)], None)?;
+        let double_spends: Vec<_> = other_wallet
+            .unconfirmed_spend_requests()
+            .iter()
+            .cloned()
+            .collect();
+        assert!(matches!(
+            network.send_payment(&double_spends, true).await,
+            Err(Error::Wallet(
+                WalletError::DoubleSpendAttemptedForCashNotes(_)
+            ))
+        ));
+        assert!(network.get_spend(address).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cash_notes_should_be_verified_against_the_network() -> Result<()> {
+        let network = MockNetwork::new();
+        let dir = tempfile::tempdir()?;
+        let mut wallet = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note = fake_cash_note(wallet.address(), NanoTokens::from(1_000));
+        wallet.deposit_and_store_to_disk(&vec![cash_note.clone()])?;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let created = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        let sent = created
+            .iter()
+            .find(|cash_note| cash_note.main_pubkey() == &recipient)
+            .ok_or_else(|| eyre::eyre!("no cash note created for the recipient"))?;
+
+        // the parent spends are not stored yet
+        assert!(matches!(
+            crate::verify_cashnote_on(&network, sent).await,
+            Err(WalletError::CouldNotVerifyTransfer(_))
+        ));
+
+        let spends: Vec<_> = wallet
+            .unconfirmed_spend_requests()
+            .iter()
+            .cloned()
+            .collect();
+        network.send_payment(&spends, true).await?;
+        crate::verify_cashnote_on(&network, sent).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mock_network_should_not_find_missing_records() {
+        let network = MockNetwork::new();
+        let address = SpendAddress::from_unique_pubkey(
+            &MainSecretKey::random()
+                .main_pubkey()
+                .new_unique_pubkey(&DerivationIndex::random(&mut rand::thread_rng())),
+        );
+        assert!(matches!(
+            network.get_spend(address).await,
+            Err(Error::Network(NetworkError::GetRecordError(
+                GetRecordError::RecordNotFound
+            )))
+        ));
+        assert_eq!(network.record_count(), 0);
+    }
+}
@@ -0,0 +1,475 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, FilesApi, FilesDownload, Result};
+use serde::{Deserialize, Serialize};
+use sn_protocol::storage::{ChunkAddress, RetryStrategy};
+use sn_transfers::{NanoTokens, WalletApi};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::mpsc;
+use xor_name::XorName;
+
+/// The file of the client data dir the pin set is kept in.
+pub const PIN_SET_FILE: &str = "pins";
+
+/// The number of close nodes a pinned chunk is expected to be held by, out of the
+/// `CLOSE_GROUP_SIZE` nodes it is stored to.
+pub const DEFAULT_MIN_REPLICAS: usize = 3;
+
+/// The time between two checks of a pinned chunk.
+pub const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The state of a pinned chunk, as of its last check.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pin {
+    /// The head chunk of the file the chunk was pinned along with, if any.
+    pub file: Option<XorName>,
+    pub pinned_at: SystemTime,
+    pub last_checked: Option<SystemTime>,
+    /// The number of close nodes found holding the chunk at the last check.
+    pub replicas: Option<usize>,
+    /// The number of times the chunk was paid for and uploaded again.
+    pub repairs: u32,
+}
+
+/// The chunks the client keeps stored on the network, persisted to the client data dir.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PinSet {
+    path: PathBuf,
+    pins: BTreeMap<XorName, Pin>,
+}
+
+impl PinSet {
+    /// Load the pin set of the client data dir, which is empty if nothing was pinned yet.
+    pub fn load(root_dir: &Path) -> Result<Self> {
+        let path = root_dir.join(PIN_SET_FILE);
+        let pins = if path.exists() {
+            rmp_serde::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, pins })
+    }
+
+    /// Write the pin set to the client data dir.
+    pub fn store(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, rmp_serde::to_vec(&self.pins)?)?;
+        Ok(())
+    }
+
+    /// Pin the chunk, returning whether it was not pinned already.
+    pub fn pin(&mut self, address: XorName, file: Option<XorName>) -> bool {
+        if self.pins.contains_key(&address) {
+            return false;
+        }
+        let _ = self.pins.insert(
+            address,
+            Pin {
+                file,
+                pinned_at: SystemTime::now(),
+                last_checked: None,
+                replicas: None,
+                repairs: 0,
+            },
+        );
+        true
+    }
+
+    /// Unpin the chunk, along with the chunks pinned with it if it is the head chunk of a file.
+    /// Returns the number of chunks unpinned.
+    pub fn unpin(&mut self, address: &XorName) -> usize {
+        let count = self.pins.len();
+        self.pins
+            .retain(|pinned, pin| pinned != address && pin.file.as_ref() != Some(address));
+        count - self.pins.len()
+    }
+
+    pub fn get(&self, address: &XorName) -> Option<&Pin> {
+        self.pins.get(address)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&XorName, &Pin)> {
+        self.pins.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pins.is_empty()
+    }
+
+    /// The chunks which have not been checked for `interval`, the least recently checked first.
+    pub fn due(&self, now: SystemTime, interval: Duration) -> Vec<XorName> {
+        let mut due: Vec<_> = self
+            .pins
+            .iter()
+            .filter(|(_, pin)| match pin.last_checked {
+                Some(last_checked) => now
+                    .duration_since(last_checked)
+                    .is_ok_and(|elapsed| elapsed >= interval),
+                None => true,
+            })
+            .map(|(address, pin)| (pin.last_checked, *address))
+            .collect();
+        due.sort();
+        due.into_iter().map(|(_, address)| address).collect()
+    }
+
+    /// The time until the next chunk is due to be checked.
+    pub fn next_due_in(&self, now: SystemTime, interval: Duration) -> Duration {
+        self.pins
+            .values()
+            .map(|pin| match pin.last_checked {
+                Some(last_checked) => {
+                    let elapsed = now.duration_since(last_checked).unwrap_or_default();
+                    interval.saturating_sub(elapsed)
+                }
+                None => Duration::ZERO,
+            })
+            .min()
+            .unwrap_or(interval)
+    }
+
+    fn record_check(&mut self, address: &XorName, replicas: usize) {
+        if let Some(pin) = self.pins.get_mut(address) {
+            pin.last_checked = Some(SystemTime::now());
+            pin.replicas = Some(replicas);
+        }
+    }
+
+    fn record_repair(&mut self, address: &XorName) {
+        if let Some(pin) = self.pins.get_mut(address) {
+            pin.repairs += 1;
+        }
+    }
+}
+
+/// The configuration of the checks of the pinned chunks.
+#[derive(Clone, Copy, Debug)]
+pub struct PinCfg {
+    /// A chunk held by fewer close nodes is paid for and uploaded again.
+    pub min_replicas: usize,
+    pub check_interval: Duration,
+}
+
+impl Default for PinCfg {
+    fn default() -> Self {
+        Self {
+            min_replicas: DEFAULT_MIN_REPLICAS,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+        }
+    }
+}
+
+/// The events emitted while checking the pinned chunks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PinEvent {
+    /// The chunk is held by enough close nodes.
+    Healthy { address: XorName, replicas: usize },
+    /// The chunk is held by too few close nodes, it is being paid for and uploaded again.
+    UnderReplicated { address: XorName, replicas: usize },
+    /// The chunk has been paid for and uploaded again.
+    Repaired { address: XorName, cost: NanoTokens },
+    /// The chunk could not be repaired. It is tried again at its next check.
+    RepairFailed { address: XorName, error: String },
+    /// The chunk could not be checked. It is tried again at its next check.
+    CheckFailed { address: XorName, error: String },
+    /// No copy of the chunk could be retrieved, even retrying, it cannot be repaired.
+    Lost(XorName),
+}
+
+/// The outcome of a round of checks of the pinned chunks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PinCheckSummary {
+    pub healthy: usize,
+    pub repaired: usize,
+    /// The chunks which could not be checked or repaired.
+    pub failed: usize,
+    pub lost: usize,
+    /// The cost of the repairs, royalties included.
+    pub cost: NanoTokens,
+}
+
+impl Default for PinCheckSummary {
+    fn default() -> Self {
+        Self {
+            healthy: 0,
+            repaired: 0,
+            failed: 0,
+            lost: 0,
+            cost: NanoTokens::zero(),
+        }
+    }
+}
+
+impl PinCheckSummary {
+    pub fn checked(&self) -> usize {
+        self.healthy + self.repaired + self.failed + self.lost
+    }
+}
+
+/// Periodically checks how many close nodes hold the pinned chunks, paying for and uploading
+/// again those held by too few of them.
+///
+/// The payment made for a chunk is once and for all, while the nodes holding it may leave the
+/// network: pinning the chunk keeps it replicated as long as the scheduler runs.
+pub struct PinScheduler {
+    files_api: FilesApi,
+    pin_set: PinSet,
+    cfg: PinCfg,
+    event_sender: Option<mpsc::Sender<PinEvent>>,
+}
+
+impl PinScheduler {
+    /// The repairs are paid from the wallet of the `files_api`.
+    pub fn new(files_api: FilesApi, pin_set: PinSet, cfg: PinCfg) -> Self {
+        Self {
+            files_api,
+            pin_set,
+            cfg,
+            event_sender: None,
+        }
+    }
+
+    /// Get a receiver of the events of the checks.
+    pub fn get_event_receiver(&mut self) -> mpsc::Receiver<PinEvent> {
+        let (tx, rx) = mpsc::channel(100);
+        self.event_sender = Some(tx);
+        rx
+    }
+
+    pub fn pin_set(&self) -> &PinSet {
+        &self.pin_set
+    }
+
+    /// Pin a file, i.e. its head chunk and all the chunks it is made of. Returns the number of
+    /// chunks newly pinned.
+    pub async fn pin_file(&mut self, head_address: ChunkAddress) -> Result<usize> {
+        let head_chunk = self
+            .files_api
+            .client()
+            .get_chunk(head_address, false, None)
+            .await?;
+        let addresses = FilesDownload::new(self.files_api.clone())
+            .chunk_addresses(head_chunk)
+            .await?;
+
+        let head = *head_address.xorname();
+        let mut count = usize::from(self.pin_set.pin(head, None));
+        for address in addresses {
+            count += usize::from(self.pin_set.pin(address, Some(head)));
+        }
+        self.pin_set.store()?;
+        info!("Pinned {count} chunks of file {head_address:?}");
+        Ok(count)
+    }
+
+    /// Check the pinned chunks which are due to be.
+    pub async fn check_due(&mut self) -> Result<PinCheckSummary> {
+        let due = self.pin_set.due(SystemTime::now(), self.cfg.check_interval);
+        self.check(due).await
+    }
+
+    /// Check all the pinned chunks, whether they are due to be or not.
+    pub async fn check_all(&mut self) -> Result<PinCheckSummary> {
+        let all = self.pin_set.iter().map(|(address, _)| *address).collect();
+        self.check(all).await
+    }
+
+    /// Check the pinned chunks as they get due, until the pin set can no longer be stored.
+    pub async fn run(mut self) -> Result<()> {
+        loop {
+            let summary = self.check_due().await?;
+            if summary.checked() > 0 {
+                info!("Checked {} pinned chunks: {summary:?}", summary.checked());
+            }
+            let next_due_in = self
+                .pin_set
+                .next_due_in(SystemTime::now(), self.cfg.check_interval);
+            tokio::time::sleep(next_due_in.max(Duration::from_secs(1))).await;
+        }
+    }
+
+    async fn check(&mut self, addresses: Vec<XorName>) -> Result<PinCheckSummary> {
+        let mut summary = PinCheckSummary::default();
+        for address in addresses {
+            // a chunk failing to be checked does not stop the round
+            if let Err(err) = self.check_chunk(address, &mut summary).await {
+                error!("Could not check pinned chunk {address:?}: {err:?}");
+                summary.failed += 1;
+                let _ = self
+                    .send_event(PinEvent::CheckFailed {
+                        address,
+                        error: err.to_string(),
+                    })
+                    .await;
+            }
+            // persisted after every chunk, for an interrupted round not to be started over
+            self.pin_set.store()?;
+        }
+        Ok(summary)
+    }
+
+    async fn check_chunk(&mut self, address: XorName, summary: &mut PinCheckSummary) -> Result<()> {
+        let client = self.files_api.client().clone();
+        // retried, for a chunk not to be declared lost on a single failed GET
+        let chunk = match client
+            .get_chunk(
+                ChunkAddress::new(address),
+                false,
+                Some(RetryStrategy::Balanced),
+            )
+            .await
+        {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                warn!("Could not retrieve pinned chunk {address:?}: {err:?}");
+                self.pin_set.record_check(&address, 0);
+                summary.lost += 1;
+                return self.send_event(PinEvent::Lost(address)).await;
+            }
+        };
+
+        let replicas = client.count_chunk_replicas(&chunk).await?;
+        self.pin_set.record_check(&address, replicas);
+        if replicas >= self.cfg.min_replicas {
+            summary.healthy += 1;
+            return self
+                .send_event(PinEvent::Healthy { address, replicas })
+                .await;
+        }
+
+        warn!("Pinned chunk {address:?} is only held by {replicas} close nodes, repairing it");
+        self.send_event(PinEvent::UnderReplicated { address, replicas })
+            .await?;
+        let repair = async {
+            // the payment of a repair that failed to upload is still valid, it is not made again
+            let paid = WalletApi::new_from_root_dir(&self.files_api.wallet_dir)
+                .get_recent_payment(&address)
+                .is_ok_and(|payment| !payment.quote.has_expired());
+            let cost = if paid {
+                debug!("Reusing the existing payment of pinned chunk {address:?}");
+                NanoTokens::zero()
+            } else {
+                let payment = self.files_api.pay_for_chunks(vec![address]).await?;
+                payment
+                    .storage_cost
+                    .checked_add(payment.royalty_fees)
+                    .ok_or(Error::NumericOverflow)?
+            };
+            self.files_api
+                .get_local_payment_and_upload_chunk(chunk, true, None)
+                .await?;
+            Ok::<_, Error>(cost)
+        };
+        match repair.await {
+            Ok(cost) => {
+                self.pin_set.record_repair(&address);
+                summary.repaired += 1;
+                summary.cost = summary
+                    .cost
+                    .checked_add(cost)
+                    .ok_or(Error::NumericOverflow)?;
+                self.send_event(PinEvent::Repaired { address, cost }).await
+            }
+            Err(err) => {
+                error!("Could not repair pinned chunk {address:?}: {err:?}");
+                summary.failed += 1;
+                self.send_event(PinEvent::RepairFailed {
+                    address,
+                    error: err.to_string(),
+                })
+                .await
+            }
+        }
+    }
+
+    async fn send_event(&self, event: PinEvent) -> Result<()> {
+        if let Some(sender) = &self.event_sender {
+            sender.send(event).await.map_err(|err| {
+                error!("Could not send pin event due to {err:?}");
+                Error::CouldNotSendPinEvent
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    #[test]
+    fn pin_set_should_be_persisted() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut rng = rand::thread_rng();
+        let (head, chunk, other) = (
+            XorName::random(&mut rng),
+            XorName::random(&mut rng),
+            XorName::random(&mut rng),
+        );
+
+        let mut pin_set = PinSet::load(dir.path())?;
+        assert!(pin_set.is_empty());
+        assert!(pin_set.pin(head, None));
+        assert!(pin_set.pin(chunk, Some(head)));
+        assert!(pin_set.pin(other, None));
+        assert!(!pin_set.pin(other, None));
+        pin_set.record_check(&chunk, 2);
+        pin_set.record_repair(&chunk);
+        pin_set.store()?;
+
+        let mut loaded = PinSet::load(dir.path())?;
+        assert_eq!(loaded, pin_set);
+        let pin = loaded.get(&chunk).expect("the chunk is pinned");
+        assert_eq!((pin.replicas, pin.repairs), (Some(2), 1));
+
+        // unpinning a file unpins its chunks
+        assert_eq!(loaded.unpin(&head), 2);
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded.get(&other).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn pins_should_be_due_once_their_interval_has_elapsed() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut rng = rand::thread_rng();
+        let (checked, unchecked) = (XorName::random(&mut rng), XorName::random(&mut rng));
+        let interval = Duration::from_secs(60);
+
+        let mut pin_set = PinSet::load(dir.path())?;
+        let _ = pin_set.pin(checked, None);
+        let _ = pin_set.pin(unchecked, None);
+        pin_set.record_check(&checked, DEFAULT_MIN_REPLICAS);
+
+        let now = SystemTime::now();
+        assert_eq!(pin_set.due(now, interval), vec![unchecked]);
+        assert_eq!(pin_set.next_due_in(now, interval), Duration::ZERO);
+
+        // the least recently checked chunks come first
+        let later = now + interval * 2;
+        assert_eq!(pin_set.due(later, interval), vec![unchecked, checked]);
+
+        let _ = pin_set.unpin(&unchecked);
+        assert!(pin_set.due(now, interval).is_empty());
+        assert!(pin_set.next_due_in(now, interval) <= interval);
+        Ok(())
+    }
+}
@@ -0,0 +1,560 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The verification of the payments made to the invoices of a merchant.
+//!
+//! An invoice is paid to the key derived from the merchant's wallet at a derivation index handed
+//! out for it alone, so a payment can be told apart from any other. The payer claims to have paid
+//! with the transfer it sent, or the address of the spend paying the invoice. The claim is checked
+//! against the spends on the Network, and the result signed with the key of the merchant, for the
+//! shop front to trust it without holding the key.
+//!
+//! With the `receipt-service` feature, the verifications can be requested over HTTP:
+//! `POST /verify` with a JSON body of the `invoice` id or payment URI, and either the
+//! `spend_address` or the `transfer` of the payment, hex encoded. The requests bear the access
+//! token of the service as `Authorization: Bearer <token>`.
+
+use crate::{Client, Error, Result};
+use serde::{Deserialize, Serialize};
+use sn_networking::{GetRecordError, NetworkError};
+use sn_transfers::{
+    verify_message, DerivationIndex, DerivationPurpose, HotWallet, MainPubkey, NanoTokens,
+    PaymentRequest, SignedSpend, SpendAddress, Transfer, UniquePubkey, WalletError,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The scheme of the payment URIs of the invoices, shared with the plain payment requests.
+pub use sn_transfers::PAYMENT_URI_SCHEME;
+
+/// The file of the wallet dir the invoices issued by the merchant are kept in.
+pub const INVOICES_FILE: &str = "invoices";
+
+/// The max size of the body of a verification request, a transfer of a few cash notes being
+/// well under it.
+#[cfg(feature = "receipt-service")]
+const MAX_VERIFY_REQUEST_SIZE: usize = 256 * 1024;
+
+/// A request for the payment of an amount to the merchant.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invoice {
+    /// Identifies the invoice to the merchant, e.g. its order number
+    pub id: String,
+    /// The main pubkey of the merchant
+    pub payee: MainPubkey,
+    /// The index the key paid is derived at, handed out for this invoice alone
+    pub derivation_index: DerivationIndex,
+    pub amount: NanoTokens,
+}
+
+impl Invoice {
+    /// An invoice of the id, which is made of letters, digits, '-', '_' and '.' for it to be
+    /// written as is in a payment URI.
+    pub fn new(
+        id: &str,
+        payee: MainPubkey,
+        derivation_index: DerivationIndex,
+        amount: NanoTokens,
+    ) -> Result<Self> {
+        let valid_id = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid_id {
+            return Err(Error::InvalidInvoiceId(id.to_string()));
+        }
+        Ok(Self {
+            id: id.to_string(),
+            payee,
+            derivation_index,
+            amount,
+        })
+    }
+
+    /// The key the invoice is to be paid to.
+    pub fn unique_pubkey(&self) -> UniquePubkey {
+        self.payee.new_unique_pubkey(&self.derivation_index)
+    }
+
+    /// The payment request of the invoice, for the payer to pay it to the key of the invoice.
+    pub fn payment_request(&self) -> PaymentRequest {
+        let request = PaymentRequest::new(self.payee)
+            .with_derivation_index(self.derivation_index)
+            .with_amount(self.amount);
+        // the id was validated on creation, under the same rules
+        PaymentRequest {
+            id: Some(self.id.clone()),
+            ..request
+        }
+    }
+
+    /// The payment URI of the invoice, e.g. to be shown as a QR code to the payer:
+    /// `safe-pay:<payee>?index=<derivation index>&amount=<nanos>&id=<id>`.
+    pub fn to_uri(&self) -> String {
+        self.payment_request().to_string()
+    }
+
+    /// Parse a payment URI written by `to_uri`, which is a payment request giving the index, the
+    /// amount and the id. The reason and expiry it may give play no part in the verification.
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let invalid = || Error::InvalidPaymentUri(uri.to_string());
+        let request: PaymentRequest = uri.parse().map_err(|_| invalid())?;
+        match (request.derivation_index, request.amount, request.id) {
+            (Some(derivation_index), Some(amount), Some(id)) => {
+                Self::new(&id, request.to, derivation_index, amount)
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// What the payer gives as proof of the payment of an invoice.
+#[derive(Clone, Debug)]
+pub enum PaymentClaim {
+    /// The transfer sent to the merchant, whose cash note of the invoice is looked for
+    Transfer(Transfer),
+    /// The address of a spend with an output to the key of the invoice
+    Spend(SpendAddress),
+}
+
+/// How much of an invoice the claimed payment covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaymentStatus {
+    /// The whole amount was paid, or more
+    Paid,
+    /// Part of the amount was paid
+    Underpaid,
+    /// Nothing was found paying the invoice
+    NotPaid,
+}
+
+/// The result of the verification of a payment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationResult {
+    pub invoice_id: String,
+    pub unique_pubkey: UniquePubkey,
+    pub amount_due: NanoTokens,
+    pub amount_paid: NanoTokens,
+    pub status: PaymentStatus,
+    /// The spends found paying the invoice
+    pub spends: Vec<SpendAddress>,
+    /// When the verification was made, in seconds since the Unix epoch
+    pub verified_at: u64,
+}
+
+impl VerificationResult {
+    /// The result of the payment of the invoice by the spends, only those with an output to its
+    /// key counting.
+    pub fn new(invoice: &Invoice, spends: &[SignedSpend], verified_at: u64) -> Self {
+        let unique_pubkey = invoice.unique_pubkey();
+        let mut amount_paid: u64 = 0;
+        let mut paying_spends = vec![];
+        for spend in spends {
+            // a spend given twice pays once
+            if paying_spends.contains(&spend.address()) {
+                continue;
+            }
+            if let Some(amount) = spend.spend.get_output_amount(&unique_pubkey) {
+                amount_paid = amount_paid.saturating_add(amount.as_nano());
+                paying_spends.push(spend.address());
+            }
+        }
+        let status = if paying_spends.is_empty() {
+            PaymentStatus::NotPaid
+        } else if amount_paid < invoice.amount.as_nano() {
+            PaymentStatus::Underpaid
+        } else {
+            PaymentStatus::Paid
+        };
+        Self {
+            invoice_id: invoice.id.clone(),
+            unique_pubkey,
+            amount_due: invoice.amount,
+            amount_paid: NanoTokens::from(amount_paid),
+            status,
+            spends: paying_spends,
+            verified_at,
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec(self)?)
+    }
+}
+
+/// A verification result, signed by the merchant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedVerification {
+    pub result: VerificationResult,
+    /// The signature of the result by the main key of the merchant, as made by
+    /// `HotWallet::sign_message`
+    pub signature: bls::Signature,
+}
+
+impl SignedVerification {
+    /// Whether the result was signed by the merchant.
+    pub fn verify(&self, merchant: &MainPubkey) -> bool {
+        self.result
+            .to_bytes()
+            .is_ok_and(|bytes| verify_message(merchant, &bytes, &self.signature))
+    }
+}
+
+/// Issues the invoices of a merchant and verifies their payments.
+pub struct ReceiptVerifier {
+    client: Client,
+    wallet: HotWallet,
+}
+
+impl ReceiptVerifier {
+    /// A verifier of the payments to the wallet, which signs the results.
+    pub fn new(client: Client, wallet: HotWallet) -> Self {
+        Self { client, wallet }
+    }
+
+    /// Issue an invoice of the amount, paid to a key derived at an index handed out for it alone.
+    pub fn issue_invoice(&self, id: &str, amount: NanoTokens) -> Result<Invoice> {
+        // the invoices issued concurrently by another instance of the wallet are not overwritten
+        let _exclusive_access = self.wallet.lock()?;
+        let mut invoices = load_invoices(self.wallet_dir()?)?;
+        if invoices.contains_key(id) {
+            return Err(Error::InvoiceAlreadyExists(id.to_string()));
+        }
+        let derivation_index = self
+            .wallet
+            .next_derivation_index(DerivationPurpose::Invoice)?;
+        let invoice = Invoice::new(id, self.wallet.address(), derivation_index, amount)?;
+        let _ = invoices.insert(invoice.id.clone(), invoice.clone());
+        store_invoices(self.wallet_dir()?, &invoices)?;
+        Ok(invoice)
+    }
+
+    /// The dir the invoices are kept in, that of the wallet, which has to be stored on disk.
+    fn wallet_dir(&self) -> Result<&Path> {
+        self.wallet
+            .wo_wallet()
+            .dir_on_disk()
+            .ok_or(WalletError::NotStoredOnDisk("invoices").into())
+    }
+
+    /// The invoice of the id, as issued by the merchant.
+    pub fn invoice(&self, id: &str) -> Result<Invoice> {
+        load_invoices(self.wallet_dir()?)?
+            .remove(id)
+            .ok_or_else(|| Error::InvoiceNotFound(id.to_string()))
+    }
+
+    /// Check the claimed payment of the invoice, given by its id or its payment URI, against the
+    /// spends on the Network, and sign the result.
+    ///
+    /// The invoice is always the one issued by the merchant: a payment URI only gives its id, and
+    /// is rejected if it does not match it.
+    pub async fn verify(&self, invoice: &str, claim: PaymentClaim) -> Result<SignedVerification> {
+        let invoice = if invoice.starts_with(PAYMENT_URI_SCHEME) {
+            let claimed = Invoice::from_uri(invoice)?;
+            if claimed.payee != self.wallet.address() {
+                return Err(Error::InvoiceOfAnotherPayee(claimed.id));
+            }
+            let issued = self.invoice(&claimed.id)?;
+            if issued != claimed {
+                return Err(Error::InvoiceMismatch(claimed.id));
+            }
+            issued
+        } else {
+            self.invoice(invoice)?
+        };
+
+        let spend_addresses: BTreeSet<SpendAddress> = match claim {
+            PaymentClaim::Spend(address) => BTreeSet::from([address]),
+            PaymentClaim::Transfer(transfer) => transfer
+                .cashnote_redemptions(self.wallet.key())?
+                .into_iter()
+                .filter(|redemption| redemption.derivation_index == invoice.derivation_index)
+                .flat_map(|redemption| redemption.parent_spends)
+                .collect(),
+        };
+
+        let mut spends = vec![];
+        for address in spend_addresses {
+            match self.client.get_spend_from_network(address).await {
+                Ok(spend) => spends.push(spend),
+                Err(Error::Network(NetworkError::GetRecordError(
+                    GetRecordError::RecordNotFound,
+                ))) => {
+                    debug!(
+                        "The spend {address:?} claimed to pay {} is not found",
+                        invoice.id
+                    );
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let verified_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        let result = VerificationResult::new(&invoice, &spends, verified_at);
+        info!(
+            "Payment of invoice {} verified: {:?}, {} of {} paid",
+            invoice.id, result.status, result.amount_paid, result.amount_due
+        );
+        let signature = self.wallet.sign_message(&result.to_bytes()?);
+        Ok(SignedVerification { result, signature })
+    }
+
+    /// Serve the verifications requested on `addr` by the bearers of the access token, until
+    /// the server fails.
+    #[cfg(feature = "receipt-service")]
+    pub async fn serve(self, addr: std::net::SocketAddr, access_token: String) -> Result<()> {
+        use hyper::service::{make_service_fn, service_fn};
+        use std::{convert::Infallible, sync::Arc};
+
+        let verifier = Arc::new(self);
+        let access_token = Arc::new(access_token);
+        let make_service = make_service_fn(move |_conn| {
+            let verifier = Arc::clone(&verifier);
+            let access_token = Arc::clone(&access_token);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let verifier = Arc::clone(&verifier);
+                    let access_token = Arc::clone(&access_token);
+                    async move { Ok::<_, Infallible>(verifier.handle(request, &access_token).await) }
+                }))
+            }
+        });
+        let server = hyper::Server::try_bind(&addr)?.serve(make_service);
+        info!("Receipt verification listening on {}", server.local_addr());
+        server.await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "receipt-service")]
+    async fn handle(
+        &self,
+        request: hyper::Request<hyper::Body>,
+        access_token: &str,
+    ) -> hyper::Response<hyper::Body> {
+        use hyper::{body::HttpBody, Method, StatusCode};
+
+        if !bears_token(request.headers(), access_token) {
+            return json_response(StatusCode::UNAUTHORIZED, serde_json::json!({}));
+        }
+        if request.uri().path().trim_matches('/') != "verify" {
+            return json_response(StatusCode::NOT_FOUND, serde_json::json!({}));
+        }
+        if request.method() != Method::POST {
+            return json_response(StatusCode::METHOD_NOT_ALLOWED, serde_json::json!({}));
+        }
+        let mut body = vec![];
+        let mut chunks = request.into_body();
+        while let Some(chunk) = chunks.data().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(err) => return error_response(StatusCode::BAD_REQUEST, err),
+            };
+            if body.len() + chunk.len() > MAX_VERIFY_REQUEST_SIZE {
+                return json_response(StatusCode::PAYLOAD_TOO_LARGE, serde_json::json!({}));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let request: VerifyRequest = match serde_json::from_slice(&body) {
+            Ok(request) => request,
+            Err(err) => return error_response(StatusCode::BAD_REQUEST, err),
+        };
+        let claim = match (request.spend_address, request.transfer) {
+            (Some(address), None) => SpendAddress::from_hex(&address)
+                .map(PaymentClaim::Spend)
+                .map_err(Error::from),
+            (None, Some(transfer)) => Transfer::from_hex(&transfer)
+                .map(PaymentClaim::Transfer)
+                .map_err(Error::from),
+            _ => Err(Error::InvalidPaymentClaim),
+        };
+        let claim = match claim {
+            Ok(claim) => claim,
+            Err(err) => return error_response(StatusCode::BAD_REQUEST, err),
+        };
+
+        match self.verify(&request.invoice, claim).await {
+            Ok(verification) => json_response(
+                StatusCode::OK,
+                serde_json::json!({
+                    "invoice_id": verification.result.invoice_id,
+                    "unique_pubkey": verification.result.unique_pubkey.to_hex(),
+                    "amount_due": verification.result.amount_due.as_nano(),
+                    "amount_paid": verification.result.amount_paid.as_nano(),
+                    "status": verification.result.status,
+                    "spends": verification
+                        .result
+                        .spends
+                        .iter()
+                        .map(SpendAddress::to_hex)
+                        .collect::<Vec<_>>(),
+                    "verified_at": verification.result.verified_at,
+                    "signature": hex::encode(verification.signature.to_bytes()),
+                }),
+            ),
+            Err(err @ (Error::InvoiceNotFound(_) | Error::InvoiceOfAnotherPayee(_))) => {
+                error_response(StatusCode::NOT_FOUND, err)
+            }
+            Err(
+                err @ (Error::InvalidPaymentUri(_)
+                | Error::InvalidInvoiceId(_)
+                | Error::InvoiceMismatch(_)),
+            ) => error_response(StatusCode::BAD_REQUEST, err),
+            Err(err) => {
+                warn!(
+                    "Could not verify the payment of {:?}: {err}",
+                    request.invoice
+                );
+                error_response(StatusCode::BAD_GATEWAY, err)
+            }
+        }
+    }
+}
+
+/// The body of a `POST /verify` request.
+#[cfg(feature = "receipt-service")]
+#[derive(Deserialize)]
+struct VerifyRequest {
+    invoice: String,
+    spend_address: Option<String>,
+    transfer: Option<String>,
+}
+
+/// Whether the request bears the access token of the service.
+#[cfg(feature = "receipt-service")]
+fn bears_token(headers: &hyper::HeaderMap, access_token: &str) -> bool {
+    headers
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| !access_token.is_empty() && token == access_token)
+}
+
+#[cfg(feature = "receipt-service")]
+fn json_response(
+    status: hyper::StatusCode,
+    body: serde_json::Value,
+) -> hyper::Response<hyper::Body> {
+    let mut response = hyper::Response::new(hyper::Body::from(body.to_string()));
+    *response.status_mut() = status;
+    let _ = response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+#[cfg(feature = "receipt-service")]
+fn error_response(
+    status: hyper::StatusCode,
+    err: impl std::fmt::Display,
+) -> hyper::Response<hyper::Body> {
+    json_response(status, serde_json::json!({ "error": err.to_string() }))
+}
+
+fn load_invoices(wallet_dir: &Path) -> Result<BTreeMap<String, Invoice>> {
+    let path = invoices_path(wallet_dir);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(rmp_serde::from_slice(&fs::read(&path)?)?)
+}
+
+/// Write the invoices to a temporary file renamed over the previous one, for an interrupted
+/// write not to lose them.
+fn store_invoices(wallet_dir: &Path, invoices: &BTreeMap<String, Invoice>) -> Result<()> {
+    let path = invoices_path(wallet_dir);
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, rmp_serde::to_vec(invoices)?)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+fn invoices_path(wallet_dir: &Path) -> PathBuf {
+    wallet_dir.join(INVOICES_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+    use sn_transfers::{create_first_cash_note_from_key, MainSecretKey};
+
+    #[test]
+    fn payment_uri_should_round_trip() -> Result<()> {
+        let payee = MainSecretKey::random().main_pubkey();
+        let index = DerivationIndex::random(&mut rand::thread_rng());
+        let invoice = Invoice::new("order-42", payee, index, NanoTokens::from(1_500))?;
+        assert_eq!(Invoice::from_uri(&invoice.to_uri())?, invoice);
+
+        assert!(Invoice::new("order 42", payee, index, NanoTokens::from(1)).is_err());
+        assert!(Invoice::from_uri("safe-pay:not-a-key?index=00&amount=1&id=a").is_err());
+        assert!(Invoice::from_uri(&invoice.to_uri().replace("&amount=1500", "")).is_err());
+
+        // it is a payment request, to be paid by any wallet to the key of the invoice
+        let request: PaymentRequest = invoice.to_uri().parse()?;
+        assert_eq!(request.unique_pubkey(), Some(invoice.unique_pubkey()));
+        assert!(Invoice::from_uri(&PaymentRequest::new(payee).to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_outputs_to_the_invoice_should_pay_it() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut payer = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note = create_first_cash_note_from_key(payer.key())?;
+        payer.deposit_and_store_to_disk(&vec![cash_note])?;
+
+        let merchant = MainSecretKey::random().main_pubkey();
+        let paid = payer.local_send(vec![(NanoTokens::from(1_000), merchant)], None)?;
+        let spends: Vec<_> = paid[0].parent_spends.iter().cloned().collect();
+
+        let invoice = |amount| Invoice::new("order", merchant, paid[0].derivation_index, amount);
+        let result = VerificationResult::new(&invoice(NanoTokens::from(1_000))?, &spends, 0);
+        assert_eq!(result.status, PaymentStatus::Paid);
+        assert_eq!(result.amount_paid, NanoTokens::from(1_000));
+        assert_eq!(result.spends.len(), 1);
+
+        let result = VerificationResult::new(&invoice(NanoTokens::from(2_000))?, &spends, 0);
+        assert_eq!(result.status, PaymentStatus::Underpaid);
+
+        // a spend given twice does not pay twice
+        let twice = [spends.clone(), spends.clone()].concat();
+        let result = VerificationResult::new(&invoice(NanoTokens::from(2_000))?, &twice, 0);
+        assert_eq!(result.status, PaymentStatus::Underpaid);
+        assert_eq!(result.amount_paid, NanoTokens::from(1_000));
+
+        // the same spends do not pay an invoice of another derivation index
+        let other_index = DerivationIndex::random(&mut rand::thread_rng());
+        let other = Invoice::new("other", merchant, other_index, NanoTokens::from(1))?;
+        let result = VerificationResult::new(&other, &spends, 0);
+        assert_eq!(result.status, PaymentStatus::NotPaid);
+        assert!(result.spends.is_empty());
+
+        // the signature of the merchant is checked against its key
+        let merchant_wallet = HotWallet::create_from_key(
+            &dir.path().join("merchant"),
+            MainSecretKey::random(),
+            None,
+        )?;
+        let verification = SignedVerification {
+            signature: merchant_wallet.sign_message(&result.to_bytes()?),
+            result,
+        };
+        assert!(verification.verify(&merchant_wallet.address()));
+        assert!(!verification.verify(&merchant));
+        Ok(())
+    }
+}
@@ -0,0 +1,451 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Client, Error, Result};
+use libp2p::kad::{Quorum, Record};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sn_networking::PutRecordCfg;
+use sn_protocol::{
+    storage::{try_serialize_record, RecordKind, RetryStrategy, ScratchpadAddress},
+    NetworkAddress,
+};
+use sn_registers::RegisterAddress;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::mpsc;
+
+/// The file of the client data dir the republish schedule is kept in.
+pub const REPUBLISH_SCHEDULE_FILE: &str = "republish_schedule";
+
+/// The time between two republishes of a record: the Kademlia republication interval, which
+/// comes well ahead of the 36h TTL after which peers following its semantics expire a record.
+pub const DEFAULT_REPUBLISH_INTERVAL: Duration = Duration::from_secs(22 * 60 * 60);
+
+/// Up to how much earlier than the interval a record is republished, for the records of a client
+/// not to be all republished at once.
+pub const DEFAULT_REPUBLISH_JITTER: Duration = Duration::from_secs(60 * 60);
+
+/// The delay before a failed republish is tried again, doubled on each consecutive failure.
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// A mutable record the client keeps republishing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RepublishTarget {
+    Register(RegisterAddress),
+    Scratchpad(ScratchpadAddress),
+}
+
+/// The state of a record in the republish schedule.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Republish {
+    pub added_at: SystemTime,
+    pub last_republished: Option<SystemTime>,
+    pub next_due: SystemTime,
+    /// The number of republishes failed in a row.
+    pub failures: u32,
+}
+
+/// The records the client republishes and when they are next due, persisted to the client data
+/// dir for the schedule to survive restarts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepublishSchedule {
+    path: PathBuf,
+    entries: BTreeMap<RepublishTarget, Republish>,
+}
+
+impl RepublishSchedule {
+    /// Load the schedule of the client data dir, which is empty if nothing was scheduled yet.
+    pub fn load(root_dir: &Path) -> Result<Self> {
+        let path = root_dir.join(REPUBLISH_SCHEDULE_FILE);
+        let entries = if path.exists() {
+            rmp_serde::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    /// Write the schedule to the client data dir.
+    pub fn store(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, rmp_serde::to_vec(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Schedule the record, due right away, returning whether it was not scheduled already.
+    pub fn add(&mut self, target: RepublishTarget) -> bool {
+        if self.entries.contains_key(&target) {
+            return false;
+        }
+        let now = SystemTime::now();
+        let _ = self.entries.insert(
+            target,
+            Republish {
+                added_at: now,
+                last_republished: None,
+                next_due: now,
+                failures: 0,
+            },
+        );
+        true
+    }
+
+    /// Stop republishing the record, returning whether it was scheduled.
+    pub fn remove(&mut self, target: &RepublishTarget) -> bool {
+        self.entries.remove(target).is_some()
+    }
+
+    pub fn get(&self, target: &RepublishTarget) -> Option<&Republish> {
+        self.entries.get(target)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&RepublishTarget, &Republish)> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The records due to be republished, the most overdue first.
+    pub fn due(&self, now: SystemTime) -> Vec<RepublishTarget> {
+        let mut due: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, republish)| republish.next_due <= now)
+            .map(|(target, republish)| (republish.next_due, *target))
+            .collect();
+        due.sort();
+        due.into_iter().map(|(_, target)| target).collect()
+    }
+
+    /// The time until the next record is due to be republished, `None` if nothing is scheduled.
+    pub fn next_due_in(&self, now: SystemTime) -> Option<Duration> {
+        self.entries
+            .values()
+            .map(|republish| {
+                republish
+                    .next_due
+                    .duration_since(now)
+                    .unwrap_or(Duration::ZERO)
+            })
+            .min()
+    }
+
+    /// Record the record as republished at `now`, scheduling it again after the interval, less a
+    /// random part of the jitter.
+    fn record_republish<R: Rng>(
+        &mut self,
+        target: &RepublishTarget,
+        now: SystemTime,
+        cfg: &RepublishCfg,
+        rng: &mut R,
+    ) {
+        if let Some(republish) = self.entries.get_mut(target) {
+            let jitter = cfg.jitter.min(cfg.interval);
+            let earlier = jitter.mul_f64(rng.gen_range(0.0..=1.0));
+            republish.last_republished = Some(now);
+            republish.next_due = now + (cfg.interval - earlier);
+            republish.failures = 0;
+        }
+    }
+
+    /// Record a failed republish at `now`, to be tried again sooner than the interval.
+    fn record_failure(&mut self, target: &RepublishTarget, now: SystemTime, cfg: &RepublishCfg) {
+        if let Some(republish) = self.entries.get_mut(target) {
+            let retry_in = RETRY_DELAY
+                .saturating_mul(2u32.saturating_pow(republish.failures))
+                .min(cfg.interval);
+            republish.next_due = now + retry_in;
+            republish.failures = republish.failures.saturating_add(1);
+        }
+    }
+}
+
+/// The configuration of the republishes.
+#[derive(Clone, Copy, Debug)]
+pub struct RepublishCfg {
+    pub interval: Duration,
+    pub jitter: Duration,
+}
+
+impl Default for RepublishCfg {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_REPUBLISH_INTERVAL,
+            jitter: DEFAULT_REPUBLISH_JITTER,
+        }
+    }
+}
+
+/// The events emitted while republishing the scheduled records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RepublishEvent {
+    /// The latest version of the record has been put to its close nodes again.
+    Republished(RepublishTarget),
+    /// The record could not be republished. It is tried again shortly.
+    Failed {
+        target: RepublishTarget,
+        error: String,
+    },
+}
+
+/// The outcome of a round of republishes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RepublishSummary {
+    pub republished: usize,
+    pub failed: usize,
+}
+
+/// Periodically puts the latest version of the registers and scratchpads the client cares about
+/// to their close nodes again, for long-lived mutable data not to silently age out of the network
+/// as the nodes holding it leave, or to be left outdated on some of them.
+pub struct Republisher {
+    client: Client,
+    schedule: RepublishSchedule,
+    cfg: RepublishCfg,
+    event_sender: Option<mpsc::Sender<RepublishEvent>>,
+}
+
+impl Republisher {
+    pub fn new(client: Client, schedule: RepublishSchedule, cfg: RepublishCfg) -> Self {
+        Self {
+            client,
+            schedule,
+            cfg,
+            event_sender: None,
+        }
+    }
+
+    /// Get a receiver of the events of the republishes.
+    pub fn get_event_receiver(&mut self) -> mpsc::Receiver<RepublishEvent> {
+        let (tx, rx) = mpsc::channel(100);
+        self.event_sender = Some(tx);
+        rx
+    }
+
+    pub fn schedule(&self) -> &RepublishSchedule {
+        &self.schedule
+    }
+
+    /// Keep republishing the register. Returns whether it was not scheduled already.
+    pub fn add_register(&mut self, address: RegisterAddress) -> Result<bool> {
+        let added = self.schedule.add(RepublishTarget::Register(address));
+        self.schedule.store()?;
+        Ok(added)
+    }
+
+    /// Keep republishing the scratchpad. Returns whether it was not scheduled already.
+    pub fn add_scratchpad(&mut self, address: ScratchpadAddress) -> Result<bool> {
+        let added = self.schedule.add(RepublishTarget::Scratchpad(address));
+        self.schedule.store()?;
+        Ok(added)
+    }
+
+    /// Stop republishing the record. Returns whether it was scheduled.
+    pub fn remove(&mut self, target: &RepublishTarget) -> Result<bool> {
+        let removed = self.schedule.remove(target);
+        self.schedule.store()?;
+        Ok(removed)
+    }
+
+    /// Republish the records which are due to be.
+    pub async fn republish_due(&mut self) -> Result<RepublishSummary> {
+        let mut summary = RepublishSummary::default();
+        for target in self.schedule.due(SystemTime::now()) {
+            let result = match target {
+                RepublishTarget::Register(address) => self.client.republish_register(address).await,
+                RepublishTarget::Scratchpad(address) => {
+                    self.client.republish_scratchpad(address).await
+                }
+            };
+
+            let now = SystemTime::now();
+            let event = match result {
+                Ok(()) => {
+                    self.schedule.record_republish(
+                        &target,
+                        now,
+                        &self.cfg,
+                        &mut rand::thread_rng(),
+                    );
+                    summary.republished += 1;
+                    RepublishEvent::Republished(target)
+                }
+                Err(err) => {
+                    warn!("Could not republish {target:?}: {err:?}");
+                    self.schedule.record_failure(&target, now, &self.cfg);
+                    summary.failed += 1;
+                    RepublishEvent::Failed {
+                        target,
+                        error: err.to_string(),
+                    }
+                }
+            };
+            // persisted after every record, for an interrupted round not to be started over
+            self.schedule.store()?;
+            self.send_event(event).await?;
+        }
+        Ok(summary)
+    }
+
+    /// Republish the records as they get due, until an error occurs.
+    pub async fn run(mut self) -> Result<()> {
+        loop {
+            let summary = self.republish_due().await?;
+            if summary.republished + summary.failed > 0 {
+                info!("Republished scheduled records: {summary:?}");
+            }
+            let next_due_in = self
+                .schedule
+                .next_due_in(SystemTime::now())
+                .unwrap_or(self.cfg.interval);
+            tokio::time::sleep(next_due_in.max(Duration::from_secs(1))).await;
+        }
+    }
+
+    async fn send_event(&self, event: RepublishEvent) -> Result<()> {
+        if let Some(sender) = &self.event_sender {
+            sender.send(event).await.map_err(|err| {
+                error!("Could not send republish event due to {err:?}");
+                Error::CouldNotSendRepublishEvent
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Put the Register, merged from all the copies found, to its close nodes again. The nodes
+    /// holding it merge it with their own copy, catching up with the edits they missed.
+    pub async fn republish_register(&self, address: RegisterAddress) -> Result<()> {
+        let register = self.get_signed_register_from_network(address).await?;
+        info!("Republishing register at {address:?}");
+        let record = Record {
+            key: NetworkAddress::from_register_address(address).to_record_key(),
+            value: try_serialize_record(&register, RecordKind::Register)?.to_vec(),
+            publisher: None,
+            expires: None,
+        };
+        let put_cfg = PutRecordCfg {
+            put_quorum: Quorum::Majority,
+            retry_strategy: Some(RetryStrategy::Balanced),
+            use_put_record_to: None,
+            verification: None,
+        };
+        Ok(self.network.put_record(record, &put_cfg).await?)
+    }
+
+    /// Put the latest version of the scratchpad found on the network again, as signed by its
+    /// owner. The nodes holding an outdated copy, or none, take it, the others already hold it.
+    pub async fn republish_scratchpad(&self, address: ScratchpadAddress) -> Result<()> {
+        let scratchpad = self.get_scratchpad(address).await?;
+        info!(
+            "Republishing scratchpad at {address:?} with counter {}",
+            scratchpad.counter
+        );
+        let record = Record {
+            key: NetworkAddress::ScratchpadAddress(address).to_record_key(),
+            value: try_serialize_record(&scratchpad, RecordKind::Scratchpad)?.to_vec(),
+            publisher: None,
+            expires: None,
+        };
+        let put_cfg = PutRecordCfg {
+            put_quorum: Quorum::Majority,
+            retry_strategy: Some(RetryStrategy::Balanced),
+            use_put_record_to: None,
+            verification: None,
+        };
+        Ok(self.network.put_record(record, &put_cfg).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls::SecretKey;
+    use eyre::Result;
+    use xor_name::XorName;
+
+    fn register_target() -> RepublishTarget {
+        let owner = SecretKey::random().public_key();
+        RepublishTarget::Register(RegisterAddress::new(
+            XorName::random(&mut rand::thread_rng()),
+            owner,
+        ))
+    }
+
+    #[test]
+    fn schedule_should_be_persisted() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let register = register_target();
+        let scratchpad =
+            RepublishTarget::Scratchpad(ScratchpadAddress::new(SecretKey::random().public_key()));
+
+        let mut schedule = RepublishSchedule::load(dir.path())?;
+        assert!(schedule.is_empty());
+        assert!(schedule.add(register));
+        assert!(schedule.add(scratchpad));
+        assert!(!schedule.add(register));
+        schedule.record_failure(&scratchpad, SystemTime::now(), &RepublishCfg::default());
+        schedule.store()?;
+
+        let mut loaded = RepublishSchedule::load(dir.path())?;
+        assert_eq!(loaded, schedule);
+        assert_eq!(loaded.get(&scratchpad).map(|r| r.failures), Some(1));
+        assert!(loaded.remove(&register));
+        assert_eq!(loaded.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn republishes_should_be_due_within_the_jitter_of_the_interval() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cfg = RepublishCfg {
+            interval: Duration::from_secs(1_000),
+            jitter: Duration::from_secs(100),
+        };
+        let (republished, failed) = (register_target(), register_target());
+
+        let mut schedule = RepublishSchedule::load(dir.path())?;
+        let _ = schedule.add(republished);
+        let _ = schedule.add(failed);
+        let now = SystemTime::now();
+        assert_eq!(schedule.due(now).len(), 2);
+
+        let mut rng = rand::thread_rng();
+        schedule.record_republish(&republished, now, &cfg, &mut rng);
+        schedule.record_failure(&failed, now, &cfg);
+        schedule.record_failure(&failed, now, &cfg);
+        assert_eq!(schedule.due(now), Vec::<RepublishTarget>::new());
+
+        // a failed republish is retried well before the interval, backing off
+        assert_eq!(schedule.next_due_in(now), Some(RETRY_DELAY * 2));
+        assert_eq!(schedule.due(now + RETRY_DELAY * 2), vec![failed]);
+
+        let next_due = schedule
+            .get(&republished)
+            .map(|republish| republish.next_due.duration_since(now))
+            .transpose()?
+            .unwrap_or_default();
+        assert!(next_due >= cfg.interval - cfg.jitter && next_due <= cfg.interval);
+        assert_eq!(schedule.due(now + cfg.interval), vec![failed, republished]);
+        Ok(())
+    }
+}
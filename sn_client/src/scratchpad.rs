@@ -0,0 +1,219 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Client, Error, Result, WalletClient};
+use bls::SecretKey;
+use libp2p::{
+    kad::{Quorum, Record},
+    PeerId,
+};
+use sn_networking::{GetRecordCfg, GetRecordError, NetworkError, PutRecordCfg, VerificationKind};
+use sn_protocol::storage::{
+    try_deserialize_record, try_serialize_record, RecordHeader, RecordKind, RetryStrategy,
+    Scratchpad, ScratchpadAddress, MAX_SCRATCHPAD_SIZE,
+};
+use sn_transfers::{NanoTokens, Payment};
+
+impl Client {
+    /// Retrieve the latest version of a Scratchpad from the network.
+    ///
+    /// Nodes may still be holding older versions of it, the valid copy with the highest counter
+    /// is returned.
+    ///
+    /// # Arguments
+    /// * 'address' - [ScratchpadAddress]
+    ///
+    /// # Example
+    /// ```no_run
+    /// use sn_client::{Client, Error};
+    /// use bls::SecretKey;
+    /// use sn_protocol::storage::ScratchpadAddress;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(),Error>{
+    /// let client = Client::new(SecretKey::random(), None, None, None).await?;
+    /// let owner = SecretKey::random();
+    /// let scratchpad = client
+    ///     .get_scratchpad(ScratchpadAddress::new(owner.public_key()))
+    ///     .await?;
+    /// let data = scratchpad.decrypt_data(&owner)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_scratchpad(&self, address: ScratchpadAddress) -> Result<Scratchpad> {
+        info!("Getting scratchpad: {address:?}");
+        let key = sn_protocol::NetworkAddress::ScratchpadAddress(address).to_record_key();
+        let get_cfg = GetRecordCfg {
+            get_quorum: Quorum::Majority,
+            retry_strategy: Some(RetryStrategy::Quick),
+            target_record: None,
+            expected_holders: Default::default(),
+        };
+
+        let records = match self.network.get_record_from_network(key, &get_cfg).await {
+            Ok(record) => vec![record],
+            Err(NetworkError::GetRecordError(GetRecordError::NotEnoughCopies {
+                record, ..
+            })) => vec![record],
+            Err(NetworkError::GetRecordError(GetRecordError::SplitRecord { result_map })) => {
+                debug!(
+                    "Found {} versions of the scratchpad {address:?}",
+                    result_map.len()
+                );
+                result_map.into_values().map(|(record, _)| record).collect()
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        records
+            .iter()
+            .filter(|record| {
+                RecordHeader::from_record(record)
+                    .is_ok_and(|header| header.kind == RecordKind::Scratchpad)
+            })
+            .filter_map(|record| try_deserialize_record::<Scratchpad>(record).ok())
+            .filter(|scratchpad| scratchpad.address() == &address && scratchpad.is_valid())
+            .max_by_key(|scratchpad| scratchpad.counter)
+            .ok_or(Error::ScratchpadNotFound(address))
+    }
+
+    /// Create a new Scratchpad owned by `owner`, holding `data` encrypted to the owner.
+    ///
+    /// There is a single Scratchpad per owner key. It is paid for once, later updates are free.
+    ///
+    /// # Arguments
+    /// * 'owner' - [SecretKey]
+    /// * 'data' - The data to store, up to [MAX_SCRATCHPAD_SIZE] once encrypted
+    /// * 'wallet_client' - [WalletClient]
+    /// * 'verify_store' - Boolean
+    ///
+    /// Return Type:
+    ///
+    /// Result<([Scratchpad], [NanoTokens], [NanoTokens])>, with the storage cost and royalties paid
+    pub async fn create_scratchpad(
+        &self,
+        owner: &SecretKey,
+        data: &[u8],
+        wallet_client: &mut WalletClient,
+        verify_store: bool,
+    ) -> Result<(Scratchpad, NanoTokens, NanoTokens)> {
+        let scratchpad = Scratchpad::new_signed(owner, data, 0);
+        if scratchpad.payload_size() > MAX_SCRATCHPAD_SIZE {
+            return Err(Error::ScratchpadTooBig(scratchpad.payload_size()));
+        }
+        let address = *scratchpad.address();
+        if self.get_scratchpad(address).await.is_ok() {
+            return Err(Error::ScratchpadAlreadyExists(address));
+        }
+
+        info!("Paying for a new scratchpad at {address:?}");
+        let net_addr = scratchpad.network_address();
+        let payment_result = wallet_client
+            .pay_for_storage(std::iter::once(net_addr.clone()))
+            .await?;
+        if let Err(err) = wallet_client.store_local_wallet() {
+            warn!("Failed to store wallet with cached payment proofs: {err:?}");
+        }
+        let payment = wallet_client.get_recent_payment_for_addr(&net_addr)?;
+
+        self.put_scratchpad(&scratchpad, Some(payment), verify_store)
+            .await?;
+        Ok((
+            scratchpad,
+            payment_result.storage_cost,
+            payment_result.royalty_fees,
+        ))
+    }
+
+    /// Replace the data of the Scratchpad owned by `owner` with `data`.
+    ///
+    /// The counter of the Scratchpad is increased, nodes reject any version that is not newer than
+    /// the one they hold.
+    ///
+    /// # Arguments
+    /// * 'owner' - [SecretKey]
+    /// * 'data' - The data to store, up to [MAX_SCRATCHPAD_SIZE] once encrypted
+    /// * 'verify_store' - Boolean
+    pub async fn update_scratchpad(
+        &self,
+        owner: &SecretKey,
+        data: &[u8],
+        verify_store: bool,
+    ) -> Result<Scratchpad> {
+        let current = self
+            .get_scratchpad(ScratchpadAddress::new(owner.public_key()))
+            .await?;
+        let counter = current
+            .counter
+            .checked_add(1)
+            .ok_or(Error::NumericOverflow)?;
+
+        let scratchpad = Scratchpad::new_signed(owner, data, counter);
+        if scratchpad.payload_size() > MAX_SCRATCHPAD_SIZE {
+            return Err(Error::ScratchpadTooBig(scratchpad.payload_size()));
+        }
+        info!(
+            "Updating scratchpad at {:?} to counter {counter}",
+            scratchpad.address()
+        );
+        self.put_scratchpad(&scratchpad, None, verify_store).await?;
+        Ok(scratchpad)
+    }
+
+    /// Put a Scratchpad to the network, along with its payment if it is a new one.
+    async fn put_scratchpad(
+        &self,
+        scratchpad: &Scratchpad,
+        payment: Option<(Payment, PeerId)>,
+        verify_store: bool,
+    ) -> Result<()> {
+        let key = scratchpad.network_address().to_record_key();
+        let (value, payee) = match payment {
+            Some((payment, payee)) => (
+                try_serialize_record(&(payment, scratchpad), RecordKind::ScratchpadWithPayment)?,
+                Some(vec![payee]),
+            ),
+            None => (
+                try_serialize_record(scratchpad, RecordKind::Scratchpad)?,
+                None,
+            ),
+        };
+        let record = Record {
+            key: key.clone(),
+            value: value.to_vec(),
+            publisher: None,
+            expires: None,
+        };
+
+        let verification = if verify_store {
+            // nodes only keep the scratchpad itself, without the payment
+            let target_record = Record {
+                key,
+                value: try_serialize_record(scratchpad, RecordKind::Scratchpad)?.to_vec(),
+                publisher: None,
+                expires: None,
+            };
+            let verification_cfg = GetRecordCfg {
+                get_quorum: Quorum::Majority,
+                retry_strategy: Some(RetryStrategy::Balanced),
+                target_record: Some(target_record),
+                expected_holders: Default::default(),
+            };
+            Some((VerificationKind::Network, verification_cfg))
+        } else {
+            None
+        };
+
+        let put_cfg = PutRecordCfg {
+            put_quorum: Quorum::All,
+            retry_strategy: Some(RetryStrategy::Balanced),
+            use_put_record_to: payee,
+            verification,
+        };
+        Ok(self.network.put_record(record, &put_cfg).await?)
+    }
+}
@@ -0,0 +1,407 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The runtime state of a client kept in its data dir across restarts: the peers it last knew,
+//! the quotes it has not paid yet, its pending upload sessions and its pinned chunks.
+
+use crate::{Client, Error, PinSet, Result};
+use libp2p::{Multiaddr, PeerId};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sn_networking::PayeeQuote;
+use sn_protocol::storage::PaymentStrategy;
+use sn_transfers::{MainPubkey, PaymentQuote};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use xor_name::XorName;
+
+/// The version of the layout of the client data dir, bumped on any incompatible change to it.
+pub const CLIENT_STORE_VERSION: u32 = 1;
+
+/// The file of the client data dir its layout version is kept in.
+pub const CLIENT_STORE_VERSION_FILE: &str = "client_store_version";
+
+/// The file of the client data dir the peers of the last session are kept in.
+pub const PEER_CACHE_FILE: &str = "peers";
+
+/// The subdir of the client data dir the quotes not paid yet are kept in.
+pub const QUOTE_CACHE_DIR: &str = "quotes";
+
+/// The subdir of the client data dir the upload sessions not completed yet are kept in.
+pub const UPLOAD_SESSIONS: &str = "upload_sessions";
+
+/// The max number of peers kept to bootstrap from.
+pub const MAX_CACHED_PEERS: usize = 50;
+
+/// The quotes got for a record, along with the strategy they were selected with.
+#[derive(Serialize, Deserialize)]
+struct CachedQuotes {
+    strategy: PaymentStrategy,
+    /// The `PeerId`s are kept as bytes, as they are not serializable.
+    quotes: Vec<(Vec<u8>, MainPubkey, PaymentQuote)>,
+}
+
+/// The client data dir, all the state a client persists across restarts goes through it.
+///
+/// The layout of the dir is versioned: a store written by a newer client is refused rather than
+/// being misread, and a store from before the versioning is taken as the current version.
+#[derive(Clone, Debug)]
+pub struct ClientStore {
+    root_dir: PathBuf,
+}
+
+impl ClientStore {
+    /// Open the store of the client data dir, creating it if needed.
+    pub fn open(root_dir: &Path) -> Result<Self> {
+        let version_path = root_dir.join(CLIENT_STORE_VERSION_FILE);
+        let mut version_found = None;
+        if version_path.exists() {
+            let found = fs::read_to_string(&version_path)?;
+            match found.trim().parse::<u32>() {
+                Ok(version) if version <= CLIENT_STORE_VERSION => version_found = Some(version),
+                _ => {
+                    return Err(Error::UnsupportedClientStoreVersion {
+                        path: root_dir.to_path_buf(),
+                        found: found.trim().to_string(),
+                        supported: CLIENT_STORE_VERSION,
+                    })
+                }
+            }
+        } else {
+            fs::create_dir_all(root_dir)?;
+        }
+        // there is no older layout to migrate from yet, the version is only written when it
+        // changes, for a store in a read-only dir to open
+        if version_found != Some(CLIENT_STORE_VERSION) {
+            fs::write(&version_path, CLIENT_STORE_VERSION.to_string())?;
+        }
+
+        Ok(Self {
+            root_dir: root_dir.to_path_buf(),
+        })
+    }
+
+    /// The client data dir of the store.
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    /// The peers of the last session, to bootstrap from along with the network contacts.
+    pub fn peers(&self) -> Result<Vec<Multiaddr>> {
+        let path = self.root_dir.join(PEER_CACHE_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let peers: Vec<String> = rmp_serde::from_slice(&fs::read(path)?)?;
+        Ok(peers
+            .iter()
+            .filter_map(|peer| match peer.parse() {
+                Ok(addr) => Some(addr),
+                Err(err) => {
+                    warn!("Skipping the unparsable cached peer {peer:?}: {err:?}");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Keep the peers for the next session, topped up with the ones already kept, up to
+    /// [`MAX_CACHED_PEERS`].
+    pub fn store_peers(&self, peers: &[Multiaddr]) -> Result<()> {
+        let mut kept: Vec<Multiaddr> = vec![];
+        for peer in peers.iter().cloned().chain(self.peers()?) {
+            if kept.len() >= MAX_CACHED_PEERS {
+                break;
+            }
+            if !kept.contains(&peer) {
+                kept.push(peer);
+            }
+        }
+        let kept: Vec<String> = kept.iter().map(|peer| peer.to_string()).collect();
+        fs::write(
+            self.root_dir.join(PEER_CACHE_FILE),
+            rmp_serde::to_vec(&kept)?,
+        )?;
+        debug!("Cached {} peers", kept.len());
+        Ok(())
+    }
+
+    /// Keep the peers of the routing table of the client for the next session.
+    pub async fn store_peers_of(&self, client: &Client) -> Result<()> {
+        let peers = client.network.get_routing_table_addrs().await?;
+        self.store_peers(&peers)
+    }
+
+    /// Keep the quotes got for a record until they are paid.
+    pub fn cache_quotes(
+        &self,
+        xorname: &XorName,
+        strategy: PaymentStrategy,
+        quotes: &[PayeeQuote],
+    ) -> Result<()> {
+        let cached = CachedQuotes {
+            strategy,
+            quotes: quotes
+                .iter()
+                .map(|(peer_id, main_pubkey, quote)| {
+                    (peer_id.to_bytes(), *main_pubkey, quote.clone())
+                })
+                .collect(),
+        };
+        let path = self.quotes_path(xorname);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, rmp_serde::to_vec(&cached)?)?;
+        Ok(())
+    }
+
+    /// The quotes kept for a record, if they were selected with the same strategy and none of
+    /// them has expired.
+    pub fn cached_quotes(
+        &self,
+        xorname: &XorName,
+        strategy: PaymentStrategy,
+    ) -> Result<Option<Vec<PayeeQuote>>> {
+        let path = self.quotes_path(xorname);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let cached: CachedQuotes = rmp_serde::from_slice(&fs::read(&path)?)?;
+        if cached
+            .quotes
+            .iter()
+            .any(|(_, _, quote)| quote.has_expired())
+        {
+            fs::remove_file(path)?;
+            return Ok(None);
+        }
+        if cached.strategy != strategy {
+            return Ok(None);
+        }
+
+        let mut quotes = vec![];
+        for (peer_id, main_pubkey, quote) in cached.quotes {
+            let Ok(peer_id) = PeerId::from_bytes(&peer_id) else {
+                return Ok(None);
+            };
+            quotes.push((peer_id, main_pubkey, quote));
+        }
+        Ok(Some(quotes))
+    }
+
+    /// Drop the quotes kept for a record, once they are paid.
+    pub fn remove_cached_quotes(&self, xorname: &XorName) -> Result<()> {
+        let path = self.quotes_path(xorname);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Drop all the quotes kept that have expired, returning how many records they were for.
+    pub fn prune_expired_quotes(&self) -> Result<usize> {
+        let dir = self.root_dir.join(QUOTE_CACHE_DIR);
+        if !dir.exists() {
+            return Ok(0);
+        }
+        let mut pruned = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let expired = match fs::read(&path)
+                .map_err(Error::from)
+                .and_then(|bytes| Ok(rmp_serde::from_slice::<CachedQuotes>(&bytes)?))
+            {
+                Ok(cached) => cached
+                    .quotes
+                    .iter()
+                    .any(|(_, _, quote)| quote.has_expired()),
+                Err(err) => {
+                    warn!("Dropping the unreadable cached quotes {path:?}: {err:?}");
+                    true
+                }
+            };
+            if expired {
+                fs::remove_file(path)?;
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Keep an upload session, returning false if one with the same id is already kept.
+    pub fn write_upload_session<T: Serialize>(&self, id: &str, session: &T) -> Result<bool> {
+        let path = self.upload_session_path(id);
+        if path.exists() {
+            return Ok(false);
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, rmp_serde::to_vec(session)?)?;
+        Ok(true)
+    }
+
+    /// The upload session kept with the id, if any.
+    pub fn read_upload_session<T: DeserializeOwned>(&self, id: &str) -> Result<Option<T>> {
+        let path = self.upload_session_path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(rmp_serde::from_slice(&fs::read(path)?)?))
+    }
+
+    /// Drop the upload session kept with the id, once the upload has completed.
+    pub fn remove_upload_session(&self, id: &str) -> Result<()> {
+        let path = self.upload_session_path(id);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// All the upload sessions kept, skipping the unreadable ones.
+    pub fn upload_sessions<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        let dir = self.root_dir.join(UPLOAD_SESSIONS);
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut sessions = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            match fs::read(&path)
+                .map_err(Error::from)
+                .and_then(|bytes| Ok(rmp_serde::from_slice(&bytes)?))
+            {
+                Ok(session) => sessions.push(session),
+                Err(err) => error!("Skipping unreadable upload session {path:?}: {err:?}"),
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// The pinned chunks of the client.
+    pub fn pins(&self) -> Result<PinSet> {
+        PinSet::load(&self.root_dir)
+    }
+
+    fn quotes_path(&self, xorname: &XorName) -> PathBuf {
+        self.root_dir
+            .join(QUOTE_CACHE_DIR)
+            .join(hex::encode(xorname))
+    }
+
+    fn upload_session_path(&self, id: &str) -> PathBuf {
+        self.root_dir.join(UPLOAD_SESSIONS).join(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+    use sn_transfers::{MainSecretKey, NanoTokens};
+    use std::time::{Duration, SystemTime};
+
+    fn payee_quote(xorname: XorName) -> PayeeQuote {
+        (
+            PeerId::random(),
+            MainSecretKey::random().main_pubkey(),
+            PaymentQuote::test_dummy(xorname, NanoTokens::from(10)),
+        )
+    }
+
+    #[test]
+    fn store_written_by_a_newer_client_is_refused() -> Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        let _store = ClientStore::open(root_dir.path())?;
+        assert_eq!(
+            fs::read_to_string(root_dir.path().join(CLIENT_STORE_VERSION_FILE))?,
+            CLIENT_STORE_VERSION.to_string()
+        );
+        // reopening the same version is fine, and leaves the file alone
+        let modified = fs::metadata(root_dir.path().join(CLIENT_STORE_VERSION_FILE))?.modified()?;
+        std::thread::sleep(Duration::from_millis(10));
+        let _store = ClientStore::open(root_dir.path())?;
+        assert_eq!(
+            fs::metadata(root_dir.path().join(CLIENT_STORE_VERSION_FILE))?.modified()?,
+            modified
+        );
+
+        fs::write(
+            root_dir.path().join(CLIENT_STORE_VERSION_FILE),
+            (CLIENT_STORE_VERSION + 1).to_string(),
+        )?;
+        assert!(matches!(
+            ClientStore::open(root_dir.path()),
+            Err(Error::UnsupportedClientStoreVersion { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn peers_are_kept_across_sessions_up_to_the_max() -> Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        let store = ClientStore::open(root_dir.path())?;
+        assert!(store.peers()?.is_empty());
+
+        let peer = |port: usize| -> Multiaddr {
+            format!("/ip4/10.0.0.1/udp/{port}/quic-v1/p2p/{}", PeerId::random())
+                .parse()
+                .expect("valid multiaddr")
+        };
+        let old: Vec<_> = (0..MAX_CACHED_PEERS).map(peer).collect();
+        store.store_peers(&old)?;
+        assert_eq!(ClientStore::open(root_dir.path())?.peers()?, old);
+
+        // the new peers come first, topped up with the old ones
+        let new = vec![peer(MAX_CACHED_PEERS), old[1].clone()];
+        store.store_peers(&new)?;
+        let peers = store.peers()?;
+        assert_eq!(peers.len(), MAX_CACHED_PEERS);
+        assert_eq!(peers[..2], new[..]);
+        assert_eq!(peers[2], old[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn only_unexpired_quotes_of_the_same_strategy_are_reused() -> Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        let store = ClientStore::open(root_dir.path())?;
+
+        let xorname = XorName::random(&mut rand::thread_rng());
+        let quotes = vec![payee_quote(xorname), payee_quote(xorname)];
+        store.cache_quotes(&xorname, PaymentStrategy::All, &quotes)?;
+        assert_eq!(
+            store.cached_quotes(&xorname, PaymentStrategy::All)?,
+            Some(quotes)
+        );
+        assert_eq!(
+            store.cached_quotes(&xorname, PaymentStrategy::CheapestMajority)?,
+            None
+        );
+        store.remove_cached_quotes(&xorname)?;
+        assert_eq!(store.cached_quotes(&xorname, PaymentStrategy::All)?, None);
+
+        let expired_xorname = XorName::random(&mut rand::thread_rng());
+        let mut expired = payee_quote(expired_xorname);
+        expired.2.expiry = Some(SystemTime::now() - Duration::from_secs(1));
+        store.cache_quotes(&expired_xorname, PaymentStrategy::All, &[expired])?;
+        store.cache_quotes(&xorname, PaymentStrategy::All, &[payee_quote(xorname)])?;
+        assert_eq!(store.prune_expired_quotes()?, 1);
+        assert_eq!(
+            store.cached_quotes(&expired_xorname, PaymentStrategy::All)?,
+            None
+        );
+        assert!(store
+            .cached_quotes(&xorname, PaymentStrategy::All)?
+            .is_some());
+        Ok(())
+    }
+}
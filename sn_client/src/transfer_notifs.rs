@@ -0,0 +1,147 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    error::{Error, Result},
+    Client, ClientEvent, ClientEventsReceiver,
+};
+use bytes::Bytes;
+use sn_networking::transfer_notifs_topic;
+use sn_transfers::{MainPubkey, MainSecretKey, Transfer};
+use tokio::sync::broadcast::error::RecvError;
+
+/// A notice of a transfer, published after paying for the recipient to learn about it.
+///
+/// A notice is the transfer alone, encrypted to the recipient: it does not name the recipient,
+/// who recognises its notices by decrypting them. The topic it is published on is shared with the
+/// recipients whose key falls in the same bucket, see `transfer_notifs_topic`.
+#[derive(Clone, Debug)]
+pub struct TransferNotice {
+    pub transfer: Transfer,
+}
+
+impl TransferNotice {
+    pub fn to_bytes(&self) -> Result<Bytes> {
+        Ok(Bytes::from(self.transfer.to_bytes()?))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(Self {
+            transfer: Transfer::try_from_bytes(bytes)?,
+        })
+    }
+
+    /// Whether the notice is to the owner of `key`, which is the case if it can decrypt it.
+    pub fn is_to(&self, key: &MainSecretKey) -> bool {
+        matches!(self.transfer, Transfer::Encrypted(_))
+            && self.transfer.cashnote_redemptions(key).is_ok()
+    }
+}
+
+/// The transfers to a recipient, as their notices are received.
+pub struct TransferNotices {
+    events: ClientEventsReceiver,
+    key: MainSecretKey,
+    topic: String,
+}
+
+impl TransferNotices {
+    /// The next transfer to the recipient. Returns `None` once the client is dropped.
+    ///
+    /// The transfer still has to be verified before its cash notes are deposited, see
+    /// `Client::receive`.
+    pub async fn recv(&mut self) -> Option<Transfer> {
+        loop {
+            match self.events.recv().await {
+                Ok(ClientEvent::GossipsubMsg { topic, msg }) if topic == self.topic => {
+                    match TransferNotice::from_bytes(&msg) {
+                        // the topic is shared with other recipients
+                        Ok(notice) if notice.is_to(&self.key) => return Some(notice.transfer),
+                        Ok(_) => {}
+                        Err(err) => warn!("Invalid transfer notice on topic {topic:?}: {err:?}"),
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Missed {count} client events while waiting for transfer notices");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Publish the notice of a transfer, for its recipient to learn about it if listening.
+    ///
+    /// Only encrypted transfers can be notified, the others would be readable by anyone.
+    pub async fn notify_transfer(&self, recipient: MainPubkey, transfer: &Transfer) -> Result<()> {
+        if !matches!(transfer, Transfer::Encrypted(_)) {
+            return Err(Error::UnencryptedTransferNotice);
+        }
+        let notice = TransferNotice {
+            transfer: transfer.clone(),
+        };
+        self.publish_on_topic(transfer_notifs_topic(&recipient), notice.to_bytes()?)
+            .await
+    }
+
+    /// Listen to the notices of the transfers to the owner of `key`.
+    pub async fn subscribe_to_transfer_notices(
+        &self,
+        key: &MainSecretKey,
+    ) -> Result<TransferNotices> {
+        let topic = transfer_notifs_topic(&key.main_pubkey());
+        // the receiver is created first, so that no notice is missed
+        let events = self.events_channel();
+        self.subscribe_to_topic(topic.clone()).await?;
+        Ok(TransferNotices {
+            events,
+            key: MainSecretKey::new(key.secret_key().clone()),
+            topic,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_networking::{transfer_notifs_topics, MAX_GOSSIP_MSG_SIZE};
+    use sn_transfers::{CashNoteRedemption, DerivationIndex, MainSecretKey, SpendAddress};
+
+    #[test]
+    fn transfer_notices_should_be_published_on_a_known_topic() -> eyre::Result<()> {
+        let recipient = MainSecretKey::random();
+        let topic = transfer_notifs_topic(&recipient.main_pubkey());
+        assert!(transfer_notifs_topics().any(|known| known == topic));
+        assert_eq!(topic, transfer_notifs_topic(&recipient.main_pubkey()));
+
+        let redemption = CashNoteRedemption::new(
+            DerivationIndex([1; 32]),
+            [SpendAddress::new(xor_name::XorName::random(
+                &mut rand::thread_rng(),
+            ))]
+            .into(),
+        );
+        let notice = TransferNotice {
+            transfer: Transfer::create(vec![redemption.clone()], recipient.main_pubkey())?,
+        };
+        let bytes = notice.to_bytes()?;
+        assert!(bytes.len() < MAX_GOSSIP_MSG_SIZE);
+
+        // only the recipient recognises the notice, which does not name it
+        let notice = TransferNotice::from_bytes(&bytes)?;
+        assert!(notice.is_to(&recipient));
+        assert!(!notice.is_to(&MainSecretKey::random()));
+        let unencrypted = TransferNotice {
+            transfer: Transfer::Unencrypted(vec![redemption]),
+        };
+        assert!(!unencrypted.is_to(&recipient));
+        Ok(())
+    }
+}
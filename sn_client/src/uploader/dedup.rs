@@ -0,0 +1,145 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::watch;
+use xor_name::XorName;
+
+/// The chunks in flight across the uploads of a process sharing it, for each unique chunk to be
+/// quoted, paid and uploaded by one upload alone while the others await its result.
+///
+/// Set the same instance on each `Uploader` with `Uploader::set_chunk_dedup`. If the upload
+/// storing a chunk fails, one of the uploads awaiting it takes it over.
+#[derive(Clone, Default)]
+pub struct ChunkDedup {
+    in_flight: Arc<Mutex<HashMap<XorName, watch::Receiver<Option<bool>>>>>,
+}
+
+impl ChunkDedup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim the chunk for the calling upload, unless another upload is storing it already.
+    pub(super) fn claim(&self, xorname: XorName) -> ChunkClaim {
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .expect("The chunk dedup lock is not poisoned");
+        if let Some(outcome) = in_flight.get(&xorname) {
+            // the entry of a claim dropped while this was waiting on the lock is stale
+            if outcome.borrow().is_none() {
+                return ChunkClaim::Shared(outcome.clone());
+            }
+        }
+        let (sender, receiver) = watch::channel(None);
+        let _ = in_flight.insert(xorname, receiver);
+        ChunkClaim::Owned(OwnedChunk {
+            xorname,
+            sender,
+            in_flight: Arc::clone(&self.in_flight),
+        })
+    }
+
+    /// The number of chunks being stored.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+            .lock()
+            .expect("The chunk dedup lock is not poisoned")
+            .len()
+    }
+}
+
+pub(super) enum ChunkClaim {
+    /// The chunk is to be stored by the calling upload
+    Owned(OwnedChunk),
+    /// The chunk is being stored by another upload, which tells whether it succeeded
+    Shared(watch::Receiver<Option<bool>>),
+}
+
+/// A chunk the upload holding it is storing for all the others. Dropping it before it is stored
+/// hands it over to the uploads awaiting it.
+pub(super) struct OwnedChunk {
+    xorname: XorName,
+    sender: watch::Sender<Option<bool>>,
+    in_flight: Arc<Mutex<HashMap<XorName, watch::Receiver<Option<bool>>>>>,
+}
+
+impl OwnedChunk {
+    /// The chunk is in the network, be it uploaded or found there.
+    pub(super) fn stored(self) {
+        let _ = self.sender.send(Some(true));
+    }
+
+    /// Whether another upload is awaiting the chunk, besides the entry of the chunk dedup.
+    #[cfg(test)]
+    pub(super) fn is_awaited(&self) -> bool {
+        self.sender.receiver_count() > 1
+    }
+}
+
+impl Drop for OwnedChunk {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            let _ = in_flight.remove(&self.xorname);
+        }
+        // a no-op if the chunk has been stored
+        let _ = self.sender.send_if_modified(|outcome| {
+            let failed = outcome.is_none();
+            if failed {
+                *outcome = Some(false);
+            }
+            failed
+        });
+    }
+}
+
+/// Wait for the upload storing the chunk to be done with it, returning whether it was stored.
+pub(super) async fn await_shared_chunk(mut outcome: watch::Receiver<Option<bool>>) -> bool {
+    match outcome.wait_for(Option::is_some).await {
+        Ok(outcome) => *outcome == Some(true),
+        // the claim is dropped after its outcome is sent, so this is not expected
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_chunk_should_be_stored_by_a_single_upload() {
+        let dedup = ChunkDedup::new();
+        let xorname = XorName::random(&mut rand::thread_rng());
+
+        let ChunkClaim::Owned(owned) = dedup.claim(xorname) else {
+            panic!("The first claim should own the chunk");
+        };
+        let ChunkClaim::Shared(shared) = dedup.claim(xorname) else {
+            panic!("A claim of a chunk in flight should share it");
+        };
+        assert_eq!(dedup.in_flight(), 1);
+        owned.stored();
+        assert!(await_shared_chunk(shared).await);
+        assert_eq!(dedup.in_flight(), 0);
+
+        // a failed upload hands the chunk over to the next claim
+        let ChunkClaim::Owned(owned) = dedup.claim(xorname) else {
+            panic!("A stored chunk should be claimable again");
+        };
+        let ChunkClaim::Shared(shared) = dedup.claim(xorname) else {
+            panic!("A claim of a chunk in flight should share it");
+        };
+        drop(owned);
+        assert!(!await_shared_chunk(shared).await);
+        assert!(matches!(dedup.claim(xorname), ChunkClaim::Owned(_)));
+    }
+}
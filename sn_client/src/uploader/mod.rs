@@ -6,16 +6,21 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod dedup;
 #[cfg(test)]
 mod tests;
 mod upload;
 
+pub use self::dedup::ChunkDedup;
+
 use self::upload::{start_upload, InnerUploader, MAX_REPAYMENTS_PER_FAILED_ITEM};
 use crate::{Client, ClientRegister, Error, Result, BATCH_SIZE};
 use itertools::Either;
+use libp2p::PeerId;
 use sn_networking::PayeeQuote;
 use sn_protocol::{
-    storage::{Chunk, ChunkAddress, RetryStrategy},
+    messages::SignedPaymentReceipt,
+    storage::{Chunk, ChunkAddress, PaymentStrategy, RetryStrategy},
     NetworkAddress,
 };
 use sn_registers::{Register, RegisterAddress};
@@ -26,6 +31,7 @@ use std::{
     path::PathBuf,
 };
 use tokio::sync::mpsc;
+use tracing::Instrument;
 use xor_name::XorName;
 
 /// The set of options to pass into the `Uploader`
@@ -37,6 +43,9 @@ pub struct UploadCfg {
     pub retry_strategy: RetryStrategy,
     pub max_repayments_for_failed_data: usize, // we want people to specify an explicit limit here.
     pub collect_registers: bool,
+    pub reuse_existing_payments: bool,
+    pub collect_payment_receipts: bool,
+    pub payment_strategy: PaymentStrategy,
 }
 
 impl Default for UploadCfg {
@@ -48,6 +57,9 @@ impl Default for UploadCfg {
             retry_strategy: RetryStrategy::Balanced,
             max_repayments_for_failed_data: MAX_REPAYMENTS_PER_FAILED_ITEM,
             collect_registers: false,
+            reuse_existing_payments: false,
+            collect_payment_receipts: false,
+            payment_strategy: PaymentStrategy::CheapestMajority,
         }
     }
 }
@@ -60,6 +72,8 @@ pub struct UploadSummary {
     pub final_balance: NanoTokens,
     pub uploaded_addresses: BTreeSet<NetworkAddress>,
     pub uploaded_registers: BTreeMap<RegisterAddress, ClientRegister>,
+    /// The receipts signed by the paid nodes, if collected.
+    pub payment_receipts: BTreeMap<NetworkAddress, SignedPaymentReceipt>,
     pub uploaded_count: usize,
     pub skipped_count: usize,
 }
@@ -69,6 +83,7 @@ impl UploadSummary {
     pub fn merge(mut self, other: Self) -> Result<Self> {
         self.uploaded_addresses.extend(other.uploaded_addresses);
         self.uploaded_registers.extend(other.uploaded_registers);
+        self.payment_receipts.extend(other.payment_receipts);
 
         let summary = Self {
             storage_cost: self
@@ -85,6 +100,7 @@ impl UploadSummary {
                 .ok_or(Error::NumericOverflow)?,
             uploaded_addresses: self.uploaded_addresses,
             uploaded_registers: self.uploaded_registers,
+            payment_receipts: self.payment_receipts,
             uploaded_count: self.uploaded_count + other.uploaded_count,
             skipped_count: self.skipped_count + other.skipped_count,
         };
@@ -133,7 +149,9 @@ impl Uploader {
             .expect("Uploader::new makes sure inner is present")
             .event_sender
             .clone();
-        match start_upload(Box::new(self)).await {
+        // Tags every log line of the upload, including those of its spawned tasks.
+        let span = info_span!("upload", upload_id = %format!("{:016x}", rand::random::<u64>()));
+        match start_upload(Box::new(self)).instrument(span).await {
             Err(err) => {
                 if let Some(event_sender) = event_sender {
                     if let Err(err) = event_sender.send(UploadEvent::Error).await {
@@ -232,6 +250,52 @@ impl Uploader {
             .set_collect_registers(collect_registers);
     }
 
+    /// Enables the uploader to skip the payment of the chunks that still have a non-expired payment in the wallet.
+    /// This allows resuming an interrupted upload without paying again for the chunks that were already paid for.
+    ///
+    /// By default, this option is set to False
+    pub fn set_reuse_existing_payments(&mut self, reuse_existing_payments: bool) {
+        self.inner
+            .as_mut()
+            .expect("Uploader::new makes sure inner is present")
+            .set_reuse_existing_payments(reuse_existing_payments);
+    }
+
+    /// Enables the uploader to fetch the receipts signed by the paid nodes once all the records are
+    /// uploaded, as a proof of which node was paid for which record. The receipts are fetched in a
+    /// single query per paid node and returned through the UploadSummary.
+    ///
+    /// By default, this option is set to False
+    pub fn set_collect_payment_receipts(&mut self, collect_payment_receipts: bool) {
+        self.inner
+            .as_mut()
+            .expect("Uploader::new makes sure inner is present")
+            .set_collect_payment_receipts(collect_payment_receipts);
+    }
+
+    /// Sets the strategy used to choose which of the close group nodes are paid for each chunk, when they quote
+    /// different store costs. Registers are always paid to the cheapest node.
+    ///
+    /// By default, this option is set to PaymentStrategy::CheapestMajority
+    pub fn set_payment_strategy(&mut self, payment_strategy: PaymentStrategy) {
+        self.inner
+            .as_mut()
+            .expect("Uploader::new makes sure inner is present")
+            .set_payment_strategy(payment_strategy);
+    }
+
+    /// Shares the chunks in flight with the other uploads of the process set with the same `ChunkDedup`, for
+    /// the chunks they have in common to be quoted, paid and uploaded once. A chunk stored by another upload
+    /// is counted as skipped, as if it was found in the network.
+    ///
+    /// By default, the chunks are not shared.
+    pub fn set_chunk_dedup(&mut self, chunk_dedup: ChunkDedup) {
+        self.inner
+            .as_mut()
+            .expect("Uploader::new makes sure inner is present")
+            .set_chunk_dedup(chunk_dedup);
+    }
+
     /// Returns a receiver for UploadEvent.
     /// This method is optional and the upload process can be performed without it.
     pub fn get_event_receiver(&mut self) -> mpsc::Receiver<UploadEvent> {
@@ -287,22 +351,18 @@ trait UploaderInterface: Send + Sync {
         task_result_sender: mpsc::Sender<TaskResult>,
     );
 
-    #[expect(clippy::too_many_arguments)]
     fn submit_get_store_cost_task(
         &mut self,
         client: Client,
         wallet_api: WalletApi,
-        xorname: XorName,
-        address: NetworkAddress,
-        get_store_cost_strategy: GetStoreCostStrategy,
-        max_repayments_for_failed_data: usize,
+        task: GetStoreCostTask,
         task_result_sender: mpsc::Sender<TaskResult>,
     );
 
     fn submit_make_payment_task(
         &mut self,
-        to_send: Option<(UploadItem, Box<PayeeQuote>)>,
-        make_payment_sender: mpsc::Sender<Option<(UploadItem, Box<PayeeQuote>)>>,
+        to_send: Option<(UploadItem, Vec<PayeeQuote>)>,
+        make_payment_sender: mpsc::Sender<Option<(UploadItem, Vec<PayeeQuote>)>>,
     );
 
     fn submit_upload_item_task(
@@ -314,6 +374,14 @@ trait UploaderInterface: Send + Sync {
         retry_strategy: RetryStrategy,
         task_result_sender: mpsc::Sender<TaskResult>,
     );
+
+    fn submit_get_payment_receipts_task(
+        &mut self,
+        client: Client,
+        payee: PeerId,
+        receipts: Vec<(NetworkAddress, XorName)>,
+        task_result_sender: mpsc::Sender<TaskResult>,
+    );
 }
 
 // Configuration functions are used in tests. So these are defined here and re-used inside `Uploader`
@@ -346,6 +414,22 @@ impl InnerUploader {
         self.cfg.collect_registers = collect_registers;
     }
 
+    pub(super) fn set_reuse_existing_payments(&mut self, reuse_existing_payments: bool) {
+        self.cfg.reuse_existing_payments = reuse_existing_payments;
+    }
+
+    pub(super) fn set_collect_payment_receipts(&mut self, collect_payment_receipts: bool) {
+        self.cfg.collect_payment_receipts = collect_payment_receipts;
+    }
+
+    pub(super) fn set_payment_strategy(&mut self, payment_strategy: PaymentStrategy) {
+        self.cfg.payment_strategy = payment_strategy;
+    }
+
+    pub(super) fn set_chunk_dedup(&mut self, chunk_dedup: ChunkDedup) {
+        self.chunk_dedup = Some(chunk_dedup);
+    }
+
     pub(super) fn get_event_receiver(&mut self) -> mpsc::Receiver<UploadEvent> {
         let (tx, rx) = mpsc::channel(100);
         self.event_sender = Some(tx);
@@ -429,7 +513,8 @@ enum TaskResult {
     PushRegisterErr(XorName),
     GetStoreCostOk {
         xorname: XorName,
-        quote: Box<PayeeQuote>,
+        // the first quote is the one of the node the item is uploaded to
+        quotes: Vec<PayeeQuote>,
     },
     GetStoreCostErr {
         xorname: XorName,
@@ -443,19 +528,46 @@ enum TaskResult {
         new_balance: NanoTokens,
     },
     MakePaymentsErr {
-        failed_xornames: Vec<(XorName, Box<PayeeQuote>)>,
+        failed_xornames: Vec<(XorName, Vec<PayeeQuote>)>,
         insufficient_balance: Option<(NanoTokens, NanoTokens)>,
+        /// Whether the quotes of the items could not be paid, being expired or mismatched.
+        stale_quotes: bool,
+    },
+    UploadOk {
+        xorname: XorName,
+        /// The node paid for the item, as per the quote of the payment.
+        payee: PeerId,
+        quote_id: XorName,
     },
-    UploadOk(XorName),
     UploadErr {
         xorname: XorName,
     },
+    /// Another upload is done with the chunk, having stored it or not.
+    SharedChunkDone {
+        xorname: XorName,
+        stored: bool,
+    },
+    /// The receipts fetched from a paid node, the ones it did not have being left out.
+    GetPaymentReceipts {
+        payee: PeerId,
+        receipts: BTreeMap<NetworkAddress, SignedPaymentReceipt>,
+    },
+}
+
+/// The item to get the store cost of, and how to select its payees.
+#[derive(Debug, Clone)]
+struct GetStoreCostTask {
+    xorname: XorName,
+    address: NetworkAddress,
+    get_store_cost_strategy: GetStoreCostStrategy,
+    payment_strategy: PaymentStrategy,
+    max_repayments_for_failed_data: usize,
 }
 
 #[derive(Debug, Clone)]
 enum GetStoreCostStrategy {
-    /// Selects the PeerId with the lowest quote
+    /// Selects the PeerIds as per the PaymentStrategy
     Cheapest,
-    /// Selects the cheapest PeerId that we have not made payment to.
+    /// Selects the PeerIds as per the PaymentStrategy, among the ones that we have not made payment to.
     SelectDifferentPayee,
 }
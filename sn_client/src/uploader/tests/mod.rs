@@ -9,15 +9,21 @@
 mod setup;
 
 use crate::{
-    uploader::tests::setup::{
-        get_dummy_chunk_paths, get_dummy_registers, get_inner_uploader, start_uploading_with_steps,
-        TestSteps,
+    uploader::{
+        dedup::ChunkClaim,
+        tests::setup::{
+            get_dummy_chunk_paths, get_dummy_registers, get_inner_uploader,
+            start_uploading_with_steps, TestSteps,
+        },
     },
-    Error as ClientError, UploadEvent,
+    ChunkDedup, ClientStore, Error as ClientError, UploadEvent,
 };
 use assert_matches::assert_matches;
 use eyre::Result;
+use libp2p::PeerId;
 use sn_logging::LogBuilder;
+use sn_protocol::storage::PaymentStrategy;
+use sn_transfers::{MainSecretKey, NanoTokens, PaymentDetails, PaymentQuote, Transfer};
 use std::collections::VecDeque;
 use tempfile::tempdir;
 
@@ -84,6 +90,42 @@ async fn chunk_should_be_paid_for_and_uploaded_if_cost_is_not_zero() -> Result<(
     Ok(())
 }
 
+/// 2a. Chunk: the receipts of the paid nodes are collected into the summary, if requested.
+#[tokio::test]
+async fn chunk_payment_receipts_should_be_collected_if_requested() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    inner_uploader.set_batch_size(1);
+    inner_uploader.set_collect_payment_receipts(true);
+    inner_uploader.insert_chunk_paths(get_dummy_chunk_paths(1, temp_dir.path().to_path_buf()));
+
+    // the path to test
+    let steps = vec![
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentOk,
+        TestSteps::UploadItemOk,
+    ];
+
+    let (upload_handle, _events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+
+    let summary = upload_handle.await??;
+    assert_eq!(summary.payment_receipts.len(), 1);
+    let (address, receipt) = summary
+        .payment_receipts
+        .iter()
+        .next()
+        .expect("a receipt to be collected");
+    assert_eq!(&receipt.verify()?.1.record, address);
+    Ok(())
+}
+
 /// 3. Register: if GET register = ok, then merge and push the register.
 #[tokio::test]
 async fn register_should_be_merged_and_pushed_if_it_already_exists_in_the_network() -> Result<()> {
@@ -144,8 +186,233 @@ async fn register_should_be_paid_and_uploaded_if_it_does_not_exists() -> Result<
     Ok(())
 }
 
+/// 5. Chunk: with the payment strategy set to All, every quoting node is paid.
+#[tokio::test]
+async fn chunks_should_be_paid_to_all_quoting_nodes_if_requested() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    inner_uploader.set_batch_size(1);
+    inner_uploader.set_payment_strategy(PaymentStrategy::All);
+    inner_uploader.insert_chunk_paths(get_dummy_chunk_paths(1, temp_dir.path().to_path_buf()));
+
+    // the path to test
+    let steps = vec![
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentOk,
+        TestSteps::UploadItemOk,
+    ];
+
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+
+    let stats = upload_handle.await??;
+    let _events = events_handle.await?;
+
+    // 3 nodes paid 10 each
+    assert_eq!(stats.storage_cost, NanoTokens::from(3 * 10));
+    Ok(())
+}
+
+/// 6. Register: a register is paid to a single node, whatever the payment strategy.
+#[tokio::test]
+async fn register_should_be_paid_to_a_single_node_even_if_all_are_requested() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    inner_uploader.set_batch_size(1);
+    inner_uploader.set_payment_strategy(PaymentStrategy::All);
+    inner_uploader.insert_register(get_dummy_registers(1, inner_uploader.client.clone()));
+
+    // the path to test
+    let steps = vec![
+        TestSteps::GetRegisterErr,
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentOk,
+        TestSteps::UploadItemOk,
+    ];
+
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+
+    let stats = upload_handle.await??;
+    let _events = events_handle.await?;
+
+    assert_eq!(stats.storage_cost, NanoTokens::from(10));
+    Ok(())
+}
+
+/// 7. Chunk: if reuse_existing_payments is set and a valid payment exists, then skip straight to the upload.
+#[tokio::test]
+async fn chunk_with_an_existing_payment_should_be_uploaded_without_paying_again() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    inner_uploader.set_batch_size(1);
+    inner_uploader.set_reuse_existing_payments(true);
+    let chunk_paths = get_dummy_chunk_paths(1, temp_dir.path().to_path_buf());
+    for (xorname, _) in chunk_paths.iter() {
+        let transfer = Transfer::NetworkRoyalties(vec![]);
+        let payment = PaymentDetails {
+            recipient: MainSecretKey::random().main_pubkey(),
+            peer_id_bytes: PeerId::random().to_bytes(),
+            transfer: (transfer.clone(), NanoTokens::zero()),
+            royalties: (transfer, NanoTokens::zero()),
+            quote: PaymentQuote::test_dummy(*xorname, NanoTokens::from(10)),
+            other_payees: vec![],
+        };
+        inner_uploader
+            .wallet_api
+            .insert_payment_transaction(*xorname, payment)?;
+    }
+    inner_uploader.insert_chunk_paths(chunk_paths);
+
+    // the path to test
+    let steps = vec![TestSteps::UploadItemOk];
+
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+
+    let _stats = upload_handle.await??;
+    let events = events_handle.await?;
+
+    assert_eq!(events.len(), 1);
+    assert_matches!(events[0], UploadEvent::ChunkUploaded(..));
+    Ok(())
+}
+
+/// 8. Chunk: if the quotes of an interrupted upload are cached and still valid, then skip straight to the payment.
+#[tokio::test]
+async fn chunk_with_cached_quotes_should_be_paid_without_asking_for_quotes_again() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    inner_uploader.set_batch_size(1);
+    let chunk_paths = get_dummy_chunk_paths(1, temp_dir.path().to_path_buf());
+    let store = ClientStore::open(temp_dir.path())?;
+    for (xorname, _) in chunk_paths.iter() {
+        let quote = (
+            PeerId::random(),
+            MainSecretKey::random().main_pubkey(),
+            PaymentQuote::test_dummy(*xorname, NanoTokens::from(10)),
+        );
+        store.cache_quotes(xorname, PaymentStrategy::CheapestMajority, &[quote])?;
+    }
+    inner_uploader.insert_chunk_paths(chunk_paths.clone());
+
+    // the path to test
+    let steps = vec![TestSteps::MakePaymentOk, TestSteps::UploadItemOk];
+
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+
+    let _stats = upload_handle.await??;
+    let events = events_handle.await?;
+
+    assert_eq!(events.len(), 2);
+    assert_matches!(events[0], UploadEvent::PaymentMade { .. });
+    assert_matches!(events[1], UploadEvent::ChunkUploaded(..));
+    // the quotes are dropped once paid
+    for (xorname, _) in chunk_paths.iter() {
+        assert!(store
+            .cached_quotes(xorname, PaymentStrategy::CheapestMajority)?
+            .is_none());
+    }
+    Ok(())
+}
+
 // ===== REPAYMENTS ======
 
+/// 9. Chunk: if the quotes cannot be paid anymore, then new ones are asked for before paying.
+#[tokio::test]
+async fn chunk_with_stale_quotes_should_be_quoted_again_before_being_paid() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    inner_uploader.set_batch_size(1);
+    inner_uploader.insert_chunk_paths(get_dummy_chunk_paths(1, temp_dir.path().to_path_buf()));
+
+    // the path to test
+    let steps = vec![
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentErr { stale_quotes: true },
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentOk,
+        TestSteps::UploadItemOk,
+    ];
+
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+
+    let _stats = upload_handle.await??;
+    let events = events_handle.await?;
+
+    assert_eq!(events.len(), 2);
+    assert_matches!(events[0], UploadEvent::PaymentMade { .. });
+    assert_matches!(events[1], UploadEvent::ChunkUploaded(..));
+    Ok(())
+}
+
+/// 10. Chunk: quotes found stale again and again count as repayments, until the maximum is reached.
+#[tokio::test]
+async fn chunk_with_quotes_stale_again_and_again_should_reach_the_maximum_repayments() -> Result<()>
+{
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    inner_uploader.set_batch_size(1);
+    inner_uploader.insert_chunk_paths(get_dummy_chunk_paths(1, temp_dir.path().to_path_buf()));
+
+    // the path to test
+    let steps = vec![
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentErr { stale_quotes: true },
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentErr { stale_quotes: true },
+    ];
+
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+
+    assert_matches!(
+        upload_handle.await?,
+        Err(ClientError::UploadFailedWithMaximumRepaymentsReached { .. })
+    );
+    let events = events_handle.await?;
+    assert!(events.is_empty());
+    Ok(())
+}
+
 /// 1. Chunks: if upload task fails > threshold, then get store cost should be triggered with SelectDifferentStrategy
 /// and then uploaded.
 #[tokio::test]
@@ -353,8 +620,12 @@ async fn chunk_should_error_out_if_there_are_multiple_errors_during_make_payment
             trigger_zero_cost: false,
             assert_select_different_payee: false,
         },
-        TestSteps::MakePaymentErr,
-        TestSteps::MakePaymentErr,
+        TestSteps::MakePaymentErr {
+            stale_quotes: false,
+        },
+        TestSteps::MakePaymentErr {
+            stale_quotes: false,
+        },
     ];
 
     let (upload_handle, events_handle) =
@@ -390,8 +661,12 @@ async fn register_should_error_out_if_there_are_multiple_errors_during_make_paym
             trigger_zero_cost: false,
             assert_select_different_payee: false,
         },
-        TestSteps::MakePaymentErr,
-        TestSteps::MakePaymentErr,
+        TestSteps::MakePaymentErr {
+            stale_quotes: false,
+        },
+        TestSteps::MakePaymentErr {
+            stale_quotes: false,
+        },
     ];
 
     let (upload_handle, events_handle) =
@@ -457,3 +732,86 @@ async fn maximum_repayment_error_should_be_triggered_during_get_store_cost() ->
     assert_matches!(events[1], UploadEvent::PaymentMade { .. });
     Ok(())
 }
+
+// ===== CHUNK DEDUP =====
+
+/// 1. Chunk: a chunk stored by another upload should be skipped once it is stored.
+#[tokio::test]
+async fn chunk_stored_by_another_upload_should_be_skipped() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    let dedup = ChunkDedup::new();
+    let chunks = get_dummy_chunk_paths(1, temp_dir.path().to_path_buf());
+    let ChunkClaim::Owned(other_upload) = dedup.claim(chunks[0].0) else {
+        panic!("The chunk should not be in flight yet");
+    };
+    inner_uploader.set_batch_size(1);
+    inner_uploader.set_chunk_dedup(dedup.clone());
+    inner_uploader.insert_chunk_paths(chunks);
+
+    // no step, the chunk is neither quoted, paid nor uploaded
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::new(), task_result_rx);
+    while !other_upload.is_awaited() {
+        tokio::task::yield_now().await;
+    }
+    other_upload.stored();
+
+    let summary = upload_handle.await??;
+    let events = events_handle.await?;
+
+    assert_eq!(summary.skipped_count, 1);
+    assert_eq!(summary.uploaded_count, 0);
+    assert_eq!(events.len(), 1);
+    assert_matches!(events[0], UploadEvent::ChunkAlreadyExistsInNetwork(_));
+    assert_eq!(dedup.in_flight(), 0);
+    Ok(())
+}
+
+/// 2. Chunk: a chunk another upload failed to store should be taken over.
+#[tokio::test]
+async fn chunk_failed_by_another_upload_should_be_taken_over() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("uploader", true);
+    let temp_dir = tempdir()?;
+    let (mut inner_uploader, task_result_rx) = get_inner_uploader(temp_dir.path().to_path_buf())?;
+
+    // cfg
+    let dedup = ChunkDedup::new();
+    let chunks = get_dummy_chunk_paths(1, temp_dir.path().to_path_buf());
+    let ChunkClaim::Owned(other_upload) = dedup.claim(chunks[0].0) else {
+        panic!("The chunk should not be in flight yet");
+    };
+    inner_uploader.set_batch_size(1);
+    inner_uploader.set_chunk_dedup(dedup.clone());
+    inner_uploader.insert_chunk_paths(chunks);
+
+    // the path to test
+    let steps = vec![
+        TestSteps::GetStoreCostOk {
+            trigger_zero_cost: false,
+            assert_select_different_payee: false,
+        },
+        TestSteps::MakePaymentOk,
+        TestSteps::UploadItemOk,
+    ];
+
+    let (upload_handle, events_handle) =
+        start_uploading_with_steps(inner_uploader, VecDeque::from(steps), task_result_rx);
+    while !other_upload.is_awaited() {
+        tokio::task::yield_now().await;
+    }
+    drop(other_upload);
+
+    let summary = upload_handle.await??;
+    let events = events_handle.await?;
+
+    assert_eq!(summary.uploaded_count, 1);
+    assert_eq!(events.len(), 2);
+    assert_matches!(events[0], UploadEvent::PaymentMade { .. });
+    assert_matches!(events[1], UploadEvent::ChunkUploaded(..));
+    assert_eq!(dedup.in_flight(), 0);
+    Ok(())
+}
@@ -9,7 +9,7 @@
 use crate::{
     uploader::{
         upload::{start_upload, InnerUploader},
-        GetStoreCostStrategy, TaskResult, UploadItem, UploaderInterface,
+        GetStoreCostStrategy, GetStoreCostTask, TaskResult, UploadItem, UploaderInterface,
     },
     ClientRegister, UploadEvent,
 };
@@ -21,7 +21,11 @@ use libp2p::PeerId;
 use libp2p_identity::Keypair;
 use rand::thread_rng;
 use sn_networking::{NetworkBuilder, PayeeQuote};
-use sn_protocol::{storage::RetryStrategy, NetworkAddress};
+use sn_protocol::{
+    messages::{PaymentReceipt, SignedPaymentReceipt},
+    storage::{PaymentStrategy, RetryStrategy},
+    NetworkAddress,
+};
 use sn_registers::{Register, RegisterAddress};
 use sn_transfers::{MainSecretKey, NanoTokens, PaymentQuote, WalletApi};
 use std::{
@@ -38,7 +42,7 @@ struct TestUploader {
     task_result_sender: mpsc::Sender<TaskResult>,
 
     // test states
-    make_payment_collector: Vec<(XorName, Box<PayeeQuote>)>,
+    make_payment_collector: Vec<(XorName, Vec<PayeeQuote>)>,
     payments_made_per_xorname: BTreeMap<XorName, usize>,
     batch_size: usize,
 }
@@ -137,12 +141,16 @@ impl UploaderInterface for TestUploader {
         &mut self,
         _client: Client,
         _wallet_api: WalletApi,
-        xorname: XorName,
-        _address: NetworkAddress,
-        get_store_cost_strategy: GetStoreCostStrategy,
-        max_repayments_for_failed_data: usize,
+        task: GetStoreCostTask,
         _task_result_sender: mpsc::Sender<TaskResult>,
     ) {
+        let GetStoreCostTask {
+            xorname,
+            address,
+            get_store_cost_strategy,
+            payment_strategy,
+            max_repayments_for_failed_data,
+        } = task;
         let step = self
             .test_steps
             .pop_front()
@@ -153,6 +161,11 @@ impl UploaderInterface for TestUploader {
         println!("spawn_get_store_cost called for: {xorname:?}. Step to execute: {step:?}");
         info!("TEST: spawn_get_store_cost called for: {xorname:?}. Step to execute: {step:?}");
 
+        // a register is always paid to a single node
+        if let NetworkAddress::RegisterAddress(_) = address {
+            assert_eq!(payment_strategy, PaymentStrategy::CheapestMajority);
+        }
+
         let has_max_payments_reached_closure =
             |get_store_cost_strategy: &GetStoreCostStrategy| -> bool {
                 match get_store_cost_strategy {
@@ -193,16 +206,23 @@ impl UploaderInterface for TestUploader {
                 if !trigger_zero_cost {
                     quote.cost = NanoTokens::from(10);
                 }
+                // the majority of the close group is paid when paying all, unless the record exists
+                let n_payees = match payment_strategy {
+                    PaymentStrategy::All if !trigger_zero_cost => 3,
+                    _ => 1,
+                };
+                let quotes = (0..n_payees)
+                    .map(|_| {
+                        (
+                            PeerId::random(),
+                            MainSecretKey::random().main_pubkey(),
+                            quote.clone(),
+                        )
+                    })
+                    .collect();
                 handle.spawn(async move {
                     task_result_sender
-                        .send(TaskResult::GetStoreCostOk {
-                            xorname,
-                            quote: Box::new((
-                                PeerId::random(),
-                                MainSecretKey::random().main_pubkey(),
-                                quote,
-                            )),
-                        })
+                        .send(TaskResult::GetStoreCostOk { xorname, quotes })
                         .await
                         .expect("Failed to send task result");
                 });
@@ -237,8 +257,8 @@ impl UploaderInterface for TestUploader {
 
     fn submit_make_payment_task(
         &mut self,
-        to_send: Option<(UploadItem, Box<PayeeQuote>)>,
-        _make_payment_sender: mpsc::Sender<Option<(UploadItem, Box<PayeeQuote>)>>,
+        to_send: Option<(UploadItem, Vec<PayeeQuote>)>,
+        _make_payment_sender: mpsc::Sender<Option<(UploadItem, Vec<PayeeQuote>)>>,
     ) {
         let step = self
             .test_steps
@@ -247,7 +267,7 @@ impl UploaderInterface for TestUploader {
         let handle = Handle::current();
         let task_result_sender = self.task_result_sender.clone();
         match &to_send {
-            Some((upload_item, quote)) => {
+            Some((upload_item, quotes)) => {
                 let xorname = upload_item.xorname();
                 println!("spawn_make_payment called for: {xorname:?}. Step to execute: {step:?}");
                 info!(
@@ -255,7 +275,7 @@ impl UploaderInterface for TestUploader {
                 );
 
                 self.make_payment_collector
-                    .push((upload_item.xorname(), quote.clone()));
+                    .push((upload_item.xorname(), quotes.clone()));
             }
             None => {
                 println!(
@@ -276,7 +296,14 @@ impl UploaderInterface for TestUploader {
             //     assert!(!make_payment);
             // }
             TestSteps::MakePaymentOk => {
-                let paid_xornames = std::mem::take(&mut self.make_payment_collector)
+                let paid = std::mem::take(&mut self.make_payment_collector);
+                // every one of the quotes of an item is paid
+                let storage_cost = paid
+                    .iter()
+                    .flat_map(|(_, quotes)| quotes.iter())
+                    .map(|(_, _, quote)| quote.cost.as_nano())
+                    .sum::<u64>();
+                let paid_xornames = paid
                     .into_iter()
                     .map(|(xorname, _)| xorname)
                     .collect::<Vec<_>>();
@@ -291,7 +318,7 @@ impl UploaderInterface for TestUploader {
                     task_result_sender
                         .send(TaskResult::MakePaymentsOk {
                             paid_xornames,
-                            storage_cost: NanoTokens::from(batch_size as u64 * 10),
+                            storage_cost: NanoTokens::from(storage_cost),
                             royalty_fees: NanoTokens::from(batch_size as u64 * 3),
                             new_balance: NanoTokens::from(batch_size as u64 * 1000),
                         })
@@ -299,7 +326,7 @@ impl UploaderInterface for TestUploader {
                         .expect("Failed to send task result");
                 });
             }
-            TestSteps::MakePaymentErr => {
+            TestSteps::MakePaymentErr { stale_quotes } => {
                 let failed_xornames = std::mem::take(&mut self.make_payment_collector);
 
                 handle.spawn(async move {
@@ -307,6 +334,7 @@ impl UploaderInterface for TestUploader {
                         .send(TaskResult::MakePaymentsErr {
                             failed_xornames,
                             insufficient_balance: None,
+                            stale_quotes,
                         })
                         .await
                         .expect("Failed to send task result");
@@ -339,7 +367,11 @@ impl UploaderInterface for TestUploader {
             TestSteps::UploadItemOk => {
                 handle.spawn(async move {
                     task_result_sender
-                        .send(TaskResult::UploadOk(xorname))
+                        .send(TaskResult::UploadOk {
+                            xorname,
+                            payee: PeerId::random(),
+                            quote_id: xorname,
+                        })
                         .await
                         .expect("Failed to send task result");
                 });
@@ -355,6 +387,41 @@ impl UploaderInterface for TestUploader {
             con => panic!("Test failed: Expected UploadItem step. Got: {con:?}"),
         }
     }
+
+    fn submit_get_payment_receipts_task(
+        &mut self,
+        _client: Client,
+        payee: PeerId,
+        receipts: Vec<(NetworkAddress, XorName)>,
+        _task_result_sender: mpsc::Sender<TaskResult>,
+    ) {
+        let handle = Handle::current();
+        let task_result_sender = self.task_result_sender.clone();
+
+        println!("spawn_get_payment_receipts called for: {payee:?}");
+        info!("TEST: spawn_get_payment_receipts called for: {payee:?}");
+        // as signed by the paid node
+        let keypair = Keypair::generate_ed25519();
+        let receipts = receipts
+            .into_iter()
+            .map(|(record, quote_id)| {
+                let receipt = PaymentReceipt {
+                    record: record.clone(),
+                    amount: NanoTokens::from(1),
+                    quote_id,
+                };
+                let signed =
+                    SignedPaymentReceipt::sign(&keypair, &receipt).expect("receipt to be signed");
+                (record, signed)
+            })
+            .collect();
+        handle.spawn(async move {
+            task_result_sender
+                .send(TaskResult::GetPaymentReceipts { payee, receipts })
+                .await
+                .expect("Failed to send task result");
+        });
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -372,7 +439,9 @@ pub enum TestSteps {
     },
     // MakePaymentJustCollectItem,
     MakePaymentOk,
-    MakePaymentErr,
+    MakePaymentErr {
+        stale_quotes: bool,
+    },
     UploadItemOk,
     UploadItemErr,
 }
@@ -431,6 +500,7 @@ pub fn build_unconnected_client(root_dir: PathBuf) -> Result<Client> {
         network,
         events_broadcaster: Default::default(),
         signer: Arc::new(SecretKey::random()),
+        hedging: None,
     };
     Ok(client)
 }
@@ -7,22 +7,23 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    GetStoreCostStrategy, TaskResult, UploadCfg, UploadEvent, UploadItem, UploadSummary,
-    UploaderInterface,
+    dedup::{await_shared_chunk, ChunkClaim, ChunkDedup, OwnedChunk},
+    GetStoreCostStrategy, GetStoreCostTask, TaskResult, UploadCfg, UploadEvent, UploadItem,
+    UploadSummary, UploaderInterface,
 };
 use crate::{
     acc_packet::load_account_wallet_or_create_with_mnemonic,
     transfers::{TransferError, WalletError},
-    Client, ClientRegister, Error as ClientError, Result, Uploader, WalletClient,
+    Client, ClientRegister, ClientStore, Error as ClientError, Result, Uploader, WalletClient,
 };
 use bytes::Bytes;
 use itertools::Either;
 use libp2p::PeerId;
 use sn_networking::PayeeQuote;
 use sn_protocol::{
-    messages::RegisterCmd,
-    storage::{Chunk, RetryStrategy},
-    NetworkAddress,
+    messages::{RegisterCmd, SignedPaymentReceipt},
+    storage::{Chunk, PaymentStrategy, RetryStrategy},
+    NetworkAddress, PrettyPrintRecordKey,
 };
 use sn_registers::{Register, RegisterAddress};
 use sn_transfers::{NanoTokens, WalletApi};
@@ -32,6 +33,7 @@ use std::{
 };
 use tiny_keccak::{Hasher, Sha3};
 use tokio::sync::mpsc;
+use tracing::Instrument;
 use xor_name::XorName;
 
 /// The number of repayments to attempt for a failed item before returning an error.
@@ -98,6 +100,61 @@ pub(super) async fn start_upload(
         })
         .collect();
 
+    // chunks stored by another upload of the process are awaited instead
+    if uploader.chunk_dedup.is_some() {
+        let pending = std::mem::take(&mut uploader.pending_to_get_store_cost);
+        for (xorname, strategy) in pending {
+            if uploader.claim_chunk(xorname, &task_result_sender) {
+                uploader.pending_to_get_store_cost.push((xorname, strategy));
+            }
+        }
+        debug!(
+            "Awaiting {} chunks stored by other uploads",
+            uploader.awaiting_other_uploads.len()
+        );
+    }
+
+    // chunks that have already been paid for (e.g. by an interrupted upload) can be uploaded straight away
+    if uploader.cfg.reuse_existing_payments {
+        let wallet_api = uploader.wallet_api.clone();
+        let (paid, unpaid): (Vec<_>, Vec<_>) = uploader
+            .pending_to_get_store_cost
+            .drain(..)
+            .partition(|(xorname, _)| {
+                wallet_api
+                    .get_recent_payment(xorname)
+                    .is_ok_and(|payment| !payment.quote.has_expired())
+            });
+        debug!(
+            "Reusing existing payments for {} chunks out of {}",
+            paid.len(),
+            paid.len() + unpaid.len()
+        );
+        uploader.pending_to_get_store_cost = unpaid;
+        uploader
+            .pending_to_upload
+            .extend(paid.into_iter().map(|(xorname, _)| xorname));
+    }
+
+    // chunks quoted for by an interrupted upload can be paid for without being quoted for again
+    if let Some(store) = &uploader.client_store {
+        if let Err(err) = store.prune_expired_quotes() {
+            warn!("Could not prune the expired cached quotes: {err:?}");
+        }
+        let mut unquoted = vec![];
+        for (xorname, strategy) in uploader.pending_to_get_store_cost.drain(..) {
+            match store.cached_quotes(&xorname, uploader.cfg.payment_strategy) {
+                Ok(Some(quotes)) => uploader.pending_to_pay.push((xorname, quotes)),
+                _ => unquoted.push((xorname, strategy)),
+            }
+        }
+        debug!(
+            "Reusing cached quotes for {} chunks",
+            uploader.pending_to_pay.len()
+        );
+        uploader.pending_to_get_store_cost = unquoted;
+    }
+
     // registers have to be verified + merged with remote replica, so we have to fetch it first.
     uploader.pending_to_get_register = uploader
         .all_upload_items
@@ -115,6 +172,23 @@ pub(super) async fn start_upload(
         // Break if we have uploaded all the items.
         // The loop also breaks if we fail to get_store_cost / make payment / upload for n consecutive times.
         if uploader.all_upload_items.is_empty() {
+            // the receipts are fetched once everything is uploaded, in a single query per paid node
+            for (payee, receipts) in std::mem::take(&mut uploader.pending_to_get_payment_receipts) {
+                trace!(
+                    "Fetching {} payment receipts from {payee:?}",
+                    receipts.len()
+                );
+                let _ = uploader.on_going_get_payment_receipts.insert(payee);
+                interface.submit_get_payment_receipts_task(
+                    uploader.client.clone(),
+                    payee,
+                    receipts,
+                    task_result_sender.clone(),
+                );
+            }
+        }
+        if uploader.all_upload_items.is_empty() && uploader.on_going_get_payment_receipts.is_empty()
+        {
             debug!("Upload items are empty, exiting main upload loop.");
             // To avoid empty final_balance when all items are skipped.
             uploader.upload_final_balance =
@@ -130,6 +204,7 @@ pub(super) async fn start_upload(
                 uploaded_count: uploader.uploaded_count,
                 skipped_count: uploader.skipped_count,
                 uploaded_registers: uploader.uploaded_registers,
+                payment_receipts: uploader.payment_receipts,
             };
 
             if !uploader.max_repayments_reached.is_empty() {
@@ -192,14 +267,23 @@ pub(super) async fn start_upload(
                 uploader.pop_item_for_get_store_cost()?;
             trace!("Conditions met for get store cost. {xorname:?} {get_store_cost_strategy:?}",);
 
+            // only the chunks are paid as per the payment strategy, a register is paid to a single node
+            let payment_strategy = match address {
+                NetworkAddress::RegisterAddress(_) => PaymentStrategy::CheapestMajority,
+                _ => uploader.cfg.payment_strategy,
+            };
+
             let _ = uploader.on_going_get_cost.insert(xorname);
             interface.submit_get_store_cost_task(
                 uploader.client.clone(),
                 uploader.wallet_api.clone(),
-                xorname,
-                address,
-                get_store_cost_strategy,
-                uploader.cfg.max_repayments_for_failed_data,
+                GetStoreCostTask {
+                    xorname,
+                    address,
+                    get_store_cost_strategy,
+                    payment_strategy,
+                    max_repayments_for_failed_data: uploader.cfg.max_repayments_for_failed_data,
+                },
                 task_result_sender.clone(),
             );
         }
@@ -209,15 +293,15 @@ pub(super) async fn start_upload(
             && uploader.on_going_payments.len() < uploader.cfg.batch_size
             && uploader.pending_to_upload.len() < uploader.cfg.batch_size
         {
-            let (upload_item, quote) = uploader.pop_item_for_make_payment()?;
+            let (upload_item, quotes) = uploader.pop_item_for_make_payment()?;
             trace!(
-                "Conditions met for making payments. {:?} {quote:?}",
+                "Conditions met for making payments. {:?} {quotes:?}",
                 upload_item.xorname()
             );
             let _ = uploader.on_going_payments.insert(upload_item.xorname());
 
             interface
-                .submit_make_payment_task(Some((upload_item, quote)), make_payment_sender.clone());
+                .submit_make_payment_task(Some((upload_item, quotes)), make_payment_sender.clone());
         }
 
         // try to upload if we have enough buffer to upload.
@@ -322,14 +406,30 @@ pub(super) async fn start_upload(
                     return Err(ClientError::SequentialNetworkErrors);
                 }
             }
-            TaskResult::GetStoreCostOk { xorname, quote } => {
+            TaskResult::GetStoreCostOk { xorname, quotes } => {
                 let _ = uploader.on_going_get_cost.remove(&xorname);
                 uploader.get_store_cost_errors = 0; // reset error if Ok. We only throw error after 'n' sequential errors
 
-                trace!("GetStoreCostOk for {xorname:?}'s store_cost {:?}", quote.2);
+                trace!(
+                    "GetStoreCostOk for {xorname:?}'s store_costs {:?}",
+                    quotes.iter().map(|(_, _, quote)| quote).collect::<Vec<_>>()
+                );
 
-                if quote.2.cost != NanoTokens::zero() {
-                    uploader.pending_to_pay.push((xorname, quote));
+                if quotes
+                    .iter()
+                    .any(|(_, _, quote)| quote.cost != NanoTokens::zero())
+                {
+                    if let (Some(store), Some(UploadItem::Chunk { .. })) = (
+                        &uploader.client_store,
+                        uploader.all_upload_items.get(&xorname),
+                    ) {
+                        if let Err(err) =
+                            store.cache_quotes(&xorname, uploader.cfg.payment_strategy, &quotes)
+                        {
+                            warn!("Could not cache the quotes for {xorname:?}: {err:?}");
+                        }
+                    }
+                    uploader.pending_to_pay.push((xorname, quotes));
                 }
                 // if cost is 0, then it already in the network.
                 else {
@@ -341,6 +441,9 @@ pub(super) async fn start_upload(
                     let _ = uploader.uploaded_addresses.insert(removed_item.address());
                     trace!("{xorname:?} has store cost of 0 and it already exists on the network");
                     uploader.skipped_count += 1;
+                    if let Some(owned) = uploader.owned_chunks.remove(&xorname) {
+                        owned.stored();
+                    }
 
                     // if during the first try we skip the item, then it is already present in the network.
                     match removed_item {
@@ -374,6 +477,8 @@ pub(super) async fn start_upload(
                     error!("Max repayments reached for {xorname:?}. Skipping upload for it");
                     uploader.max_repayments_reached.insert(xorname);
                     uploader.all_upload_items.remove(&xorname);
+                    // hands the chunk over to the uploads awaiting it
+                    let _ = uploader.owned_chunks.remove(&xorname);
                 } else {
                     // use the same strategy. The repay different payee is set only if upload fails.
                     uploader
@@ -396,6 +501,11 @@ pub(super) async fn start_upload(
                 paid_xornames.len(), InnerUploader::hash_of_xornames(paid_xornames.iter()));
                 for xorname in paid_xornames.iter() {
                     let _ = uploader.on_going_payments.remove(xorname);
+                    if let Some(store) = &uploader.client_store {
+                        if let Err(err) = store.remove_cached_quotes(xorname) {
+                            warn!("Could not drop the cached quotes for {xorname:?}: {err:?}");
+                        }
+                    }
                 }
                 uploader.pending_to_upload.extend(paid_xornames);
                 uploader.make_payments_errors = 0;
@@ -420,6 +530,7 @@ pub(super) async fn start_upload(
             TaskResult::MakePaymentsErr {
                 failed_xornames,
                 insufficient_balance,
+                stale_quotes,
             } => {
                 trace!(
                     "MakePaymentsErr for {:?} items: hash({:?})",
@@ -433,9 +544,45 @@ pub(super) async fn start_upload(
                     )));
                 }
 
-                for (xorname, quote) in failed_xornames {
+                if stale_quotes {
+                    // the quotes cannot be paid anymore, ask for new ones
+                    warn!("The quotes of the payment batch are stale, getting new ones");
+                    for (xorname, _) in failed_xornames {
+                        let _ = uploader.on_going_payments.remove(&xorname);
+                        if let Some(store) = &uploader.client_store {
+                            if let Err(err) = store.remove_cached_quotes(&xorname) {
+                                warn!("Could not drop the cached quotes for {xorname:?}: {err:?}");
+                            }
+                        }
+
+                        // each new quote counts as a repayment of the item, not to ask forever
+                        let payments_made = uploader
+                            .wallet_api
+                            .get_all_payments(&xorname)
+                            .map(|payments| payments.len())
+                            .unwrap_or_default();
+                        let requotes = uploader.n_requotes.entry(xorname).or_insert(0);
+                        *requotes += 1;
+                        if InnerUploader::have_we_reached_max_repayments(
+                            payments_made + *requotes,
+                            uploader.cfg.max_repayments_for_failed_data,
+                        ) {
+                            error!("Max repayments reached for {xorname:?} while its quotes kept being stale. Skipping upload for it");
+                            uploader.max_repayments_reached.insert(xorname);
+                            uploader.all_upload_items.remove(&xorname);
+                            let _ = uploader.owned_chunks.remove(&xorname);
+                            continue;
+                        }
+                        uploader
+                            .pending_to_get_store_cost
+                            .push((xorname, GetStoreCostStrategy::Cheapest));
+                    }
+                    continue;
+                }
+
+                for (xorname, quotes) in failed_xornames {
                     let _ = uploader.on_going_payments.remove(&xorname);
-                    uploader.pending_to_pay.push((xorname, quote));
+                    uploader.pending_to_pay.push((xorname, quotes));
                 }
                 uploader.make_payments_errors += 1;
 
@@ -447,7 +594,11 @@ pub(super) async fn start_upload(
                     return Err(ClientError::SequentialUploadPaymentError);
                 }
             }
-            TaskResult::UploadOk(xorname) => {
+            TaskResult::UploadOk {
+                xorname,
+                payee,
+                quote_id,
+            } => {
                 let _ = uploader.on_going_uploads.remove(&xorname);
                 uploader.uploaded_count += 1;
                 trace!("UploadOk for {xorname:?}");
@@ -457,6 +608,16 @@ pub(super) async fn start_upload(
                     .remove(&xorname)
                     .ok_or(ClientError::UploadableItemNotFound(xorname))?;
                 let _ = uploader.uploaded_addresses.insert(removed_item.address());
+                if let Some(owned) = uploader.owned_chunks.remove(&xorname) {
+                    owned.stored();
+                }
+                if uploader.cfg.collect_payment_receipts {
+                    uploader
+                        .pending_to_get_payment_receipts
+                        .entry(payee)
+                        .or_default()
+                        .push((removed_item.address(), quote_id));
+                }
 
                 match removed_item {
                     UploadItem::Chunk { address, .. } => {
@@ -494,6 +655,32 @@ pub(super) async fn start_upload(
                     uploader.pending_to_upload.push(xorname);
                 }
             }
+            TaskResult::GetPaymentReceipts { payee, receipts } => {
+                let _ = uploader.on_going_get_payment_receipts.remove(&payee);
+                trace!("Got {} payment receipts from {payee:?}", receipts.len());
+                uploader.payment_receipts.extend(receipts);
+            }
+            TaskResult::SharedChunkDone { xorname, stored } => {
+                let _ = uploader.awaiting_other_uploads.remove(&xorname);
+                if stored {
+                    trace!("{xorname:?} has been stored by another upload");
+                    let removed_item = uploader
+                        .all_upload_items
+                        .remove(&xorname)
+                        .ok_or(ClientError::UploadableItemNotFound(xorname))?;
+                    let _ = uploader.uploaded_addresses.insert(removed_item.address());
+                    uploader.skipped_count += 1;
+                    if let UploadItem::Chunk { address, .. } = removed_item {
+                        uploader
+                            .emit_upload_event(UploadEvent::ChunkAlreadyExistsInNetwork(address));
+                    }
+                } else if uploader.claim_chunk(xorname, &task_result_sender) {
+                    debug!("The upload storing {xorname:?} failed, taking it over");
+                    uploader
+                        .pending_to_get_store_cost
+                        .push((xorname, GetStoreCostStrategy::Cheapest));
+                }
+            }
         }
     }
 }
@@ -509,47 +696,55 @@ impl UploaderInterface for Uploader {
         &mut self,
         client: Client,
         wallet_api: WalletApi,
-        xorname: XorName,
-        address: NetworkAddress,
-        get_store_cost_strategy: GetStoreCostStrategy,
-        max_repayments_for_failed_data: usize,
+        task: GetStoreCostTask,
         task_result_sender: mpsc::Sender<TaskResult>,
     ) {
+        let GetStoreCostTask {
+            xorname,
+            address,
+            get_store_cost_strategy,
+            payment_strategy,
+            max_repayments_for_failed_data,
+        } = task;
         trace!("Spawning get_store_cost for {xorname:?}");
-        let _handle = tokio::spawn(async move {
-            let task_result = match InnerUploader::get_store_cost(
-                client,
-                wallet_api,
-                xorname,
-                address,
-                get_store_cost_strategy.clone(),
-                max_repayments_for_failed_data,
-            )
-            .await
-            {
-                Ok(quote) => {
-                    debug!("StoreCosts retrieved for {xorname:?} quote: {quote:?}");
-                    TaskResult::GetStoreCostOk {
+        let _handle =
+            tokio::spawn(
+                async move {
+                    let task_result = match InnerUploader::get_store_cost(
+                        client,
+                        wallet_api,
                         xorname,
-                        quote: Box::new(quote),
-                    }
-                }
-                Err(err) => {
-                    error!("Encountered error {err:?} when getting store_cost for {xorname:?}",);
+                        address,
+                        get_store_cost_strategy.clone(),
+                        payment_strategy,
+                        max_repayments_for_failed_data,
+                    )
+                    .await
+                    {
+                        Ok(quotes) => {
+                            debug!("StoreCosts retrieved for {xorname:?} quotes: {quotes:?}");
+                            TaskResult::GetStoreCostOk { xorname, quotes }
+                        }
+                        Err(err) => {
+                            error!(
+                                "Encountered error {err:?} when getting store_cost for {xorname:?}",
+                            );
 
-                    let max_repayments_reached =
-                        matches!(&err, ClientError::MaximumRepaymentsReached(_));
+                            let max_repayments_reached =
+                                matches!(&err, ClientError::MaximumRepaymentsReached(_));
 
-                    TaskResult::GetStoreCostErr {
-                        xorname,
-                        get_store_cost_strategy,
-                        max_repayments_reached,
-                    }
-                }
-            };
+                            TaskResult::GetStoreCostErr {
+                                xorname,
+                                get_store_cost_strategy,
+                                max_repayments_reached,
+                            }
+                        }
+                    };
 
-            let _ = task_result_sender.send(task_result).await;
-        });
+                    let _ = task_result_sender.send(task_result).await;
+                }
+                .in_current_span(),
+            );
     }
 
     fn submit_get_register_task(
@@ -606,8 +801,8 @@ impl UploaderInterface for Uploader {
 
     fn submit_make_payment_task(
         &mut self,
-        to_send: Option<(UploadItem, Box<PayeeQuote>)>,
-        make_payment_sender: mpsc::Sender<Option<(UploadItem, Box<PayeeQuote>)>>,
+        to_send: Option<(UploadItem, Vec<PayeeQuote>)>,
+        make_payment_sender: mpsc::Sender<Option<(UploadItem, Vec<PayeeQuote>)>>,
     ) {
         let _handle = tokio::spawn(async move {
             let _ = make_payment_sender.send(to_send).await;
@@ -625,29 +820,65 @@ impl UploaderInterface for Uploader {
     ) {
         trace!("Spawning upload item task for {:?}", upload_item.xorname());
 
-        let _handle = tokio::spawn(async move {
-            let xorname = upload_item.xorname();
-            let result = InnerUploader::upload_item(
-                client,
-                wallet_api,
-                upload_item,
-                verify_store,
-                retry_strategy,
-            )
-            .await;
-
-            trace!("Upload item {xorname:?} uploaded with result {result:?}");
-            match result {
-                Ok(_) => {
-                    let _ = task_result_sender.send(TaskResult::UploadOk(xorname)).await;
-                }
-                Err(_) => {
-                    let _ = task_result_sender
-                        .send(TaskResult::UploadErr { xorname })
-                        .await;
-                }
-            };
-        });
+        let _handle = tokio::spawn(
+            async move {
+                let xorname = upload_item.xorname();
+                let result = InnerUploader::upload_item(
+                    client,
+                    wallet_api,
+                    upload_item,
+                    verify_store,
+                    retry_strategy,
+                )
+                .await;
+
+                trace!("Upload item {xorname:?} uploaded with result {result:?}");
+                match result {
+                    Ok((payee, quote_id)) => {
+                        let _ = task_result_sender
+                            .send(TaskResult::UploadOk {
+                                xorname,
+                                payee,
+                                quote_id,
+                            })
+                            .await;
+                    }
+                    Err(_) => {
+                        let _ = task_result_sender
+                            .send(TaskResult::UploadErr { xorname })
+                            .await;
+                    }
+                };
+            }
+            .in_current_span(),
+        );
+    }
+
+    fn submit_get_payment_receipts_task(
+        &mut self,
+        client: Client,
+        payee: PeerId,
+        receipts: Vec<(NetworkAddress, XorName)>,
+        task_result_sender: mpsc::Sender<TaskResult>,
+    ) {
+        trace!("Spawning get payment receipts task for {payee:?}");
+
+        let _handle = tokio::spawn(
+            async move {
+                // the records are stored, missing receipts do not fail the upload
+                let receipts = client
+                    .get_payment_receipts(payee, receipts)
+                    .await
+                    .unwrap_or_else(|err| {
+                        warn!("Could not get the payment receipts from {payee:?}: {err:?}");
+                        Default::default()
+                    });
+                let _ = task_result_sender
+                    .send(TaskResult::GetPaymentReceipts { payee, receipts })
+                    .await;
+            }
+            .in_current_span(),
+        );
     }
 }
 
@@ -662,14 +893,21 @@ pub(super) struct InnerUploader {
     #[debug(skip)]
     pub(super) wallet_api: WalletApi,
     pub(super) root_dir: PathBuf,
+    /// Keeps the quotes not paid yet, for an interrupted upload to pay them on its resumption.
+    #[debug(skip)]
+    pub(super) client_store: Option<ClientStore>,
+    #[debug(skip)]
+    pub(super) chunk_dedup: Option<ChunkDedup>,
 
     // states
     pub(super) all_upload_items: HashMap<XorName, UploadItem>,
     pub(super) pending_to_get_register: Vec<RegisterAddress>,
     pub(super) pending_to_push_register: Vec<XorName>,
     pub(super) pending_to_get_store_cost: Vec<(XorName, GetStoreCostStrategy)>,
-    pub(super) pending_to_pay: Vec<(XorName, Box<PayeeQuote>)>,
+    pub(super) pending_to_pay: Vec<(XorName, Vec<PayeeQuote>)>,
     pub(super) pending_to_upload: Vec<XorName>,
+    /// The receipts to fetch from each paid node once all the items are uploaded.
+    pub(super) pending_to_get_payment_receipts: BTreeMap<PeerId, Vec<(NetworkAddress, XorName)>>,
 
     // trackers
     pub(super) on_going_get_register: BTreeSet<XorName>,
@@ -677,9 +915,17 @@ pub(super) struct InnerUploader {
     pub(super) on_going_get_cost: BTreeSet<XorName>,
     pub(super) on_going_payments: BTreeSet<XorName>,
     pub(super) on_going_uploads: BTreeSet<XorName>,
+    pub(super) on_going_get_payment_receipts: BTreeSet<PeerId>,
+    /// The chunks stored by other uploads of the process sharing the chunk dedup.
+    pub(super) awaiting_other_uploads: BTreeSet<XorName>,
+    /// The chunks this upload stores for the other uploads sharing the chunk dedup.
+    #[debug(skip)]
+    pub(super) owned_chunks: HashMap<XorName, OwnedChunk>,
 
     // error trackers
     pub(super) n_errors_during_uploads: BTreeMap<XorName, usize>,
+    /// The times the quotes of an item were found stale and asked for again.
+    pub(super) n_requotes: BTreeMap<XorName, usize>,
     pub(super) push_register_errors: usize,
     pub(super) get_store_cost_errors: usize,
     pub(super) make_payments_errors: usize,
@@ -691,6 +937,7 @@ pub(super) struct InnerUploader {
     pub(super) max_repayments_reached: BTreeSet<XorName>,
     pub(super) uploaded_addresses: BTreeSet<NetworkAddress>,
     pub(super) uploaded_registers: BTreeMap<RegisterAddress, ClientRegister>,
+    pub(super) payment_receipts: BTreeMap<NetworkAddress, SignedPaymentReceipt>,
     pub(super) uploaded_count: usize,
     pub(super) skipped_count: usize,
 
@@ -711,6 +958,10 @@ impl InnerUploader {
             cfg: Default::default(),
             client,
             wallet_api: WalletApi::new_from_root_dir(&root_dir),
+            client_store: ClientStore::open(&root_dir)
+                .inspect_err(|err| warn!("Not caching the quotes of the upload: {err:?}"))
+                .ok(),
+            chunk_dedup: None,
             root_dir,
 
             all_upload_items: Default::default(),
@@ -719,14 +970,19 @@ impl InnerUploader {
             pending_to_get_store_cost: Default::default(),
             pending_to_pay: Default::default(),
             pending_to_upload: Default::default(),
+            pending_to_get_payment_receipts: Default::default(),
 
             on_going_get_register: Default::default(),
             on_going_push_register: Default::default(),
             on_going_get_cost: Default::default(),
             on_going_payments: Default::default(),
             on_going_uploads: Default::default(),
+            on_going_get_payment_receipts: Default::default(),
+            awaiting_other_uploads: Default::default(),
+            owned_chunks: Default::default(),
 
             n_errors_during_uploads: Default::default(),
+            n_requotes: Default::default(),
             push_register_errors: Default::default(),
             get_store_cost_errors: Default::default(),
             max_repayments_reached: Default::default(),
@@ -737,6 +993,7 @@ impl InnerUploader {
             upload_final_balance: NanoTokens::zero(),
             uploaded_addresses: Default::default(),
             uploaded_registers: Default::default(),
+            payment_receipts: Default::default(),
             uploaded_count: Default::default(),
             skipped_count: Default::default(),
 
@@ -746,6 +1003,38 @@ impl InnerUploader {
         }
     }
 
+    /// Claim the chunk in the chunk dedup, returning whether this upload is to store it. Else the
+    /// upload storing it is awaited, its outcome being sent as a task result.
+    fn claim_chunk(
+        &mut self,
+        xorname: XorName,
+        task_result_sender: &mpsc::Sender<TaskResult>,
+    ) -> bool {
+        let Some(dedup) = &self.chunk_dedup else {
+            return true;
+        };
+        match dedup.claim(xorname) {
+            ChunkClaim::Owned(owned) => {
+                let _ = self.owned_chunks.insert(xorname, owned);
+                true
+            }
+            ChunkClaim::Shared(outcome) => {
+                let _ = self.awaiting_other_uploads.insert(xorname);
+                let task_result_sender = task_result_sender.clone();
+                let _handle = tokio::spawn(
+                    async move {
+                        let stored = await_shared_chunk(outcome).await;
+                        let _ = task_result_sender
+                            .send(TaskResult::SharedChunkDone { xorname, stored })
+                            .await;
+                    }
+                    .in_current_span(),
+                );
+                false
+            }
+        }
+    }
+
     // ====== Pop items ======
 
     fn pop_item_for_push_register(&mut self) -> Result<UploadItem> {
@@ -777,14 +1066,14 @@ impl InnerUploader {
         Ok((xorname, address, strategy))
     }
 
-    fn pop_item_for_make_payment(&mut self) -> Result<(UploadItem, Box<PayeeQuote>)> {
-        if let Some((name, quote)) = self.pending_to_pay.pop() {
+    fn pop_item_for_make_payment(&mut self) -> Result<(UploadItem, Vec<PayeeQuote>)> {
+        if let Some((name, quotes)) = self.pending_to_pay.pop() {
             let upload_item = self
                 .all_upload_items
                 .get(&name)
                 .cloned()
                 .ok_or(ClientError::UploadableItemNotFound(name))?;
-            Ok((upload_item, quote))
+            Ok((upload_item, quotes))
         } else {
             // the caller will be making sure this does not happen.
             Err(ClientError::UploadStateTrackerIsEmpty)
@@ -811,7 +1100,7 @@ impl InnerUploader {
     // each time we have to make a payment.
     fn start_make_payment_processing_loop(
         &self,
-        mut make_payment_receiver: mpsc::Receiver<Option<(UploadItem, Box<PayeeQuote>)>>,
+        mut make_payment_receiver: mpsc::Receiver<Option<(UploadItem, Vec<PayeeQuote>)>>,
         task_result_sender: mpsc::Sender<TaskResult>,
         batch_size: usize,
     ) -> Result<()> {
@@ -826,12 +1115,16 @@ impl InnerUploader {
 
             let mut got_a_previous_force_payment = false;
             while let Some(payment) = make_payment_receiver.recv().await {
-                let make_payments = if let Some((item, quote)) = payment {
+                let make_payments = if let Some((item, quotes)) = payment {
                     let xorname = item.xorname();
                     trace!("Inserted {xorname:?} into cost_map");
 
-                    current_batch.push((xorname, quote.clone()));
-                    let _ = cost_map.insert(xorname, (quote.1, quote.2, quote.0.to_bytes()));
+                    current_batch.push((xorname, quotes.clone()));
+                    let payees = quotes
+                        .into_iter()
+                        .map(|(peer_id, main_pubkey, quote)| (main_pubkey, quote, peer_id.to_bytes()))
+                        .collect::<Vec<_>>();
+                    let _ = cost_map.insert(xorname, payees);
                     cost_map.len() >= batch_size || got_a_previous_force_payment
                 } else {
                     // using None to indicate as all paid.
@@ -866,7 +1159,9 @@ impl InnerUploader {
 
                     let mut terminate_process = false;
 
-                    let result = match wallet_client.pay_for_records(&cost_map, verify_store).await
+                    let result = match wallet_client
+                        .pay_for_records_to_payees(&cost_map, verify_store)
+                        .await
                     {
                         Ok((storage_cost, royalty_fees)) => {
                             let paid_xornames = std::mem::take(&mut current_batch);
@@ -904,11 +1199,22 @@ impl InnerUploader {
                                     TaskResult::MakePaymentsErr {
                                         failed_xornames,
                                         insufficient_balance: Some((available, required)),
+                                        stale_quotes: false,
+                                    }
+                                }
+                                WalletError::QuoteExpired(_)
+                                | WalletError::QuoteContentMismatch { .. }
+                                | WalletError::QuotePayeeMismatch(_) => {
+                                    TaskResult::MakePaymentsErr {
+                                        failed_xornames,
+                                        insufficient_balance: None,
+                                        stale_quotes: true,
                                     }
                                 }
                                 _ => TaskResult::MakePaymentsErr {
                                     failed_xornames,
                                     insufficient_balance: None,
+                                    stale_quotes: false,
                                 },
                             }
                         }
@@ -932,7 +1238,7 @@ impl InnerUploader {
                 }
             }
             debug!("Make payment processing loop terminated.");
-        });
+        }.in_current_span());
         Ok(())
     }
 
@@ -960,17 +1266,19 @@ impl InnerUploader {
         xorname: XorName,
         address: NetworkAddress,
         get_store_cost_strategy: GetStoreCostStrategy,
+        payment_strategy: PaymentStrategy,
         max_repayments_for_failed_data: usize,
-    ) -> Result<PayeeQuote> {
+    ) -> Result<Vec<PayeeQuote>> {
         let filter_list = match get_store_cost_strategy {
             GetStoreCostStrategy::Cheapest => vec![],
             GetStoreCostStrategy::SelectDifferentPayee => {
                 // Check if we have already made payment for the provided xorname. If so filter out those payee
-                let filter_list = wallet_api
-                    .get_all_payments(&xorname)?
-                    .into_iter()
-                    .map(|details| {
-                        PeerId::from_bytes(&details.peer_id_bytes).map_err(|_| {
+                let payments = wallet_api.get_all_payments(&xorname)?;
+                let filter_list = payments
+                    .iter()
+                    .flat_map(|details| details.all_peer_id_bytes())
+                    .map(|peer_id_bytes| {
+                        PeerId::from_bytes(peer_id_bytes).map_err(|_| {
                             ClientError::Wallet(WalletError::NoPaymentForAddress(xorname))
                         })
                     })
@@ -978,7 +1286,7 @@ impl InnerUploader {
 
                 // if we have already made initial + max_repayments, then we should error out.
                 if Self::have_we_reached_max_repayments(
-                    filter_list.len(),
+                    payments.len(),
                     max_repayments_for_failed_data,
                 ) {
                     // error is used by the caller.
@@ -989,28 +1297,39 @@ impl InnerUploader {
                 filter_list
             }
         };
-        let quote = client
+        let quotes = client
             .network
-            .get_store_costs_from_network(address, filter_list)
+            .get_store_quotes_from_network(address, filter_list, payment_strategy)
             .await?;
-        Ok(quote)
+        Ok(quotes)
     }
 
+    #[tracing::instrument(
+        skip_all,
+        fields(record_key = %PrettyPrintRecordKey::from(&upload_item.address().to_record_key()))
+    )]
     async fn upload_item(
         client: Client,
         wallet_api: WalletApi,
         upload_item: UploadItem,
         verify_store: bool,
         retry_strategy: RetryStrategy,
-    ) -> Result<()> {
+    ) -> Result<(PeerId, XorName)> {
         let xorname = upload_item.xorname();
 
         let payment_details = wallet_api.get_recent_payment(&xorname)?;
         let payment = payment_details.to_payment();
-        let payee = PeerId::from_bytes(&payment_details.peer_id_bytes)
-            .map_err(|_| ClientError::Wallet(WalletError::NoPaymentForAddress(xorname)))?;
-
-        debug!("Payments for upload item: {xorname:?} to {payee:?}:  {payment:?}");
+        let payees = payment_details
+            .all_peer_id_bytes()
+            .map(|peer_id_bytes| {
+                PeerId::from_bytes(peer_id_bytes)
+                    .map_err(|_| ClientError::Wallet(WalletError::NoPaymentForAddress(xorname)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // the first payee is the one paid as per the quote of the payment
+        let payee = payees[0];
+
+        debug!("Payments for upload item: {xorname:?} to {payees:?}:  {payment:?}");
 
         match upload_item {
             UploadItem::Chunk { address: _, chunk } => {
@@ -1024,7 +1343,7 @@ impl InnerUploader {
 
                 trace!("Client upload started for chunk: {xorname:?}");
                 client
-                    .store_chunk(chunk, payee, payment, verify_store, Some(retry_strategy))
+                    .store_chunk(chunk, payees, payment, verify_store, Some(retry_strategy))
                     .await?;
                 trace!("Client upload completed for chunk: {xorname:?}");
             }
@@ -1048,7 +1367,7 @@ impl InnerUploader {
         // remove the payment if the upload is successful.
         wallet_api.remove_payment_transaction(&xorname);
 
-        Ok(())
+        Ok((payee, payment_details.quote.id()))
     }
 
     // ====== Misc ======
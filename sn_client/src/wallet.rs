@@ -8,20 +8,21 @@
 
 use crate::Error;
 
-use super::{error::Result, Client};
+use super::{error::Result, Client, ClientNetwork};
 use backoff::{backoff::Backoff, ExponentialBackoff};
 use futures::{future::join_all, TryFutureExt};
-use libp2p::PeerId;
+use libp2p::{kad::Quorum, PeerId};
 use sn_networking::target_arch::Instant;
 use sn_networking::{GetRecordError, PayeeQuote};
 use sn_protocol::NetworkAddress;
 use sn_transfers::{
-    CashNote, HotWallet, MainPubkey, NanoTokens, Payment, PaymentQuote, SignedSpend, SpendAddress,
-    Transfer, WalletError, WalletResult,
+    CashNote, HotWallet, MainPubkey, NanoTokens, Payment, PaymentQuote, PaymentRequest,
+    QuotesByAddress, ResendPolicy, SignedSpend, SpendAddress, Transfer, WalletError, WalletResult,
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
     iter::Iterator,
+    time::SystemTime,
 };
 use tokio::{
     task::JoinSet,
@@ -29,7 +30,21 @@ use tokio::{
 };
 use xor_name::XorName;
 
-const MAX_RESEND_PENDING_TX_ATTEMPTS: usize = 10;
+pub(crate) const MAX_RESEND_PENDING_TX_ATTEMPTS: usize = 10;
+/// The time between two polls of the spends being confirmed.
+pub const DEFAULT_CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The time after which the spends not confirmed yet are reported as pending.
+pub const DEFAULT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The resends of the pending transactions of a wallet client, bounded for the few resends
+/// made before giving up not to take more than a few seconds each.
+pub(crate) fn pending_tx_resend_policy() -> ResendPolicy {
+    ResendPolicy {
+        initial_backoff: Duration::from_secs(1),
+        max_backoff: Duration::from_secs(2),
+        ..Default::default()
+    }
+}
 
 /// A wallet client can be used to send and receive tokens to and from other wallets.
 pub struct WalletClient {
@@ -37,6 +52,47 @@ pub struct WalletClient {
     wallet: HotWallet,
 }
 
+/// The configuration of the confirmation of spends, see [`Client::confirm_spends`].
+#[derive(Clone, Copy, Debug)]
+pub struct ConfirmationCfg {
+    /// The number of nodes of the close group a spend must be fetched from to be confirmed.
+    pub quorum: Quorum,
+    pub poll_interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for ConfirmationCfg {
+    fn default() -> Self {
+        Self {
+            quorum: Quorum::Majority,
+            poll_interval: DEFAULT_CONFIRMATION_POLL_INTERVAL,
+            timeout: DEFAULT_CONFIRMATION_TIMEOUT,
+        }
+    }
+}
+
+/// Whether a spend is final in the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpendFinality {
+    /// The spend is held by the quorum of its close group: it cannot be reverted.
+    Confirmed,
+    /// Another spend of the same cash note is held by the network: the spend will never be.
+    DoubleSpent,
+    /// The spend was not held by the quorum of its close group before the timeout.
+    Pending,
+}
+
+/// The finality of a spend, as found by [`Client::confirm_spends`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpendConfirmation {
+    pub address: SpendAddress,
+    pub finality: SpendFinality,
+    /// The number of times the spend was polled.
+    pub polls: u32,
+    /// The time it took to be confirmed or found double spent, or the timeout when pending.
+    pub elapsed: Duration,
+}
+
 /// The result of the payment made for a set of Content Addresses
 pub struct StoragePaymentResult {
     pub storage_cost: NanoTokens,
@@ -306,8 +362,41 @@ impl WalletClient {
         to: MainPubkey,
         verify_store: bool,
     ) -> WalletResult<CashNote> {
+        // approved ahead, for the wallet not to block the runtime while the approver answers
+        self.wallet
+            .approve_spend_async(vec![(amount, to)], 0)
+            .await?;
         let created_cash_notes = self.wallet.local_send(vec![(amount, to)], None)?;
+        self.send_created_cash_note(created_cash_notes, verify_store)
+            .await
+    }
 
+    /// Pay the payment request, of the amount it requests, else of the given one, like
+    /// `send_cash_note`. The hash of the request, if any, is the reason of the spends.
+    pub async fn send_payment_request(
+        &mut self,
+        request: &PaymentRequest,
+        amount: Option<NanoTokens>,
+        verify_store: bool,
+    ) -> WalletResult<CashNote> {
+        if !request.is_expired() {
+            let to_pay = request.amount_to_pay(amount)?;
+            self.wallet
+                .approve_spend_async(vec![(to_pay, request.to)], 0)
+                .await?;
+        }
+        let created_cash_notes = self.wallet.local_send_payment_request(request, amount)?;
+        self.send_created_cash_note(created_cash_notes, verify_store)
+            .await
+    }
+
+    /// Send the spends of a transfer to a single recipient to the network, returning the cash
+    /// note created for it.
+    async fn send_created_cash_note(
+        &mut self,
+        created_cash_notes: Vec<CashNote>,
+        verify_store: bool,
+    ) -> WalletResult<CashNote> {
         // send to network
         if let Err(error) = self
             .client
@@ -540,16 +629,91 @@ impl WalletClient {
     /// wallet_client.pay_for_records(&cost_map,true).await?;
     /// # Ok(())
     /// # }
+    #[tracing::instrument(skip_all, fields(records = cost_map.len()))]
     pub async fn pay_for_records(
         &mut self,
         cost_map: &BTreeMap<XorName, (MainPubkey, PaymentQuote, Vec<u8>)>,
         verify_store: bool,
+    ) -> WalletResult<(NanoTokens, NanoTokens)> {
+        let cost_map = cost_map
+            .iter()
+            .map(|(xorname, payee)| (*xorname, vec![payee.clone()]))
+            .collect();
+        self.pay_for_records_to_payees(&cost_map, verify_store)
+            .await
+    }
+
+    /// Send tokens to every one of the nodes quoted for each record, as chosen by a
+    /// [`PaymentStrategy`]. The first payee of a record is the one it is to be uploaded to.
+    ///
+    /// Returns the amount paid for storage, including the network royalties fee paid.
+    ///
+    /// [`PaymentStrategy`]: sn_protocol::storage::PaymentStrategy
+    #[tracing::instrument(skip_all, fields(records = cost_map.len()))]
+    pub async fn pay_for_records_to_payees(
+        &mut self,
+        cost_map: &QuotesByAddress,
+        verify_store: bool,
     ) -> WalletResult<(NanoTokens, NanoTokens)> {
         // Before wallet progress, there shall be no `unconfirmed_spend_requests`
         self.resend_pending_transaction_until_success(verify_store)
             .await?;
+
+        // Each batch is paid by its own transfer, so that failing to register one in the network
+        // leaves the payments of the previous batches valid, only the failed ones to be repaired.
+        let mut storage_cost = NanoTokens::zero();
+        let mut royalties_fees = NanoTokens::zero();
+        let batches = self.wallet.storage_payment_batches(cost_map)?;
+        let batch_count = batches.len();
+        for (i, batch) in batches.into_iter().enumerate() {
+            // the next batch spends the change of this one, which it waits to be registered for
+            let verify_batch = verify_store || i + 1 < batch_count;
+            let (batch_storage_cost, batch_royalties_fees) =
+                self.pay_for_batch(&batch, verify_batch).await?;
+            storage_cost = storage_cost
+                .checked_add(batch_storage_cost)
+                .ok_or(WalletError::TotalPriceTooHigh)?;
+            royalties_fees = royalties_fees
+                .checked_add(batch_royalties_fees)
+                .ok_or(WalletError::TotalPriceTooHigh)?;
+        }
+
+        Ok((storage_cost, royalties_fees))
+    }
+
+    /// Pay again for a record whose payment was not registered in the network, e.g. as the
+    /// transfer of its batch failed, sending tokens to the given payees. Nothing is paid if the
+    /// cached payment turns out to be confirmed once the pending transactions are resent.
+    ///
+    /// Returns the amount paid for storage, including the network royalties fee paid.
+    pub async fn repair_payment(
+        &mut self,
+        xorname: XorName,
+        payees: Vec<(MainPubkey, PaymentQuote, Vec<u8>)>,
+        verify_store: bool,
+    ) -> WalletResult<(NanoTokens, NanoTokens)> {
+        self.resend_pending_transaction_until_success(verify_store)
+            .await?;
+        if self.wallet.is_payment_confirmed(&xorname) {
+            info!("The payment for {xorname:?} is confirmed, there is nothing to repair");
+            return Ok((NanoTokens::zero(), NanoTokens::zero()));
+        }
+
+        info!("Repairing the payment for {xorname:?}");
+        self.wallet.remove_payment_for_xorname(&xorname);
+        self.pay_for_records_to_payees(&BTreeMap::from([(xorname, payees)]), verify_store)
+            .await
+    }
+
+    /// Pay for a batch of records with a single transfer, and send it to the network.
+    async fn pay_for_batch(
+        &mut self,
+        cost_map: &QuotesByAddress,
+        verify_store: bool,
+    ) -> WalletResult<(NanoTokens, NanoTokens)> {
         let start = Instant::now();
-        let total_cost = self.wallet.local_send_storage_payment(cost_map)?;
+        self.wallet.approve_storage_payment_async(cost_map).await?;
+        let total_cost = self.wallet.local_send_storage_payment_to_payees(cost_map)?;
 
         trace!(
             "local_send_storage_payment of {} chunks completed in {:?}",
@@ -574,8 +738,7 @@ impl WalletClient {
             start.elapsed()
         );
 
-        // Here is bit risky that for the whole bunch of spends to the chunks' store_costs and royalty_fee
-        // they will get re-paid again for ALL, if any one of the payment failed to be put.
+        // If any of the spends fails to be put, the payments of the whole batch are unconfirmed.
         let start = Instant::now();
         if let Err(error) = spend_attempt_result {
             warn!("The storage payment transfer was not successfully registered in the network: {error:?}. It will be retried later.");
@@ -607,19 +770,33 @@ impl WalletClient {
         Ok(total_cost)
     }
 
-    /// Resend failed transactions. This can optionally verify the store has been successful.
+    /// Resend the failed transactions whose spends are due as per the
+    /// [`pending_tx_resend_policy`], recording the attempt of each. This can optionally verify the
+    /// store has been successful.
     /// This will attempt to GET the cash_note from the network.
     async fn resend_pending_transactions(&mut self, verify_store: bool) {
-        if self
-            .client
-            .send_spends(
-                self.wallet.unconfirmed_spend_requests().iter(),
-                verify_store,
-            )
-            .await
-            .is_ok()
-        {
-            self.wallet.clear_confirmed_spend_requests();
+        let due = self
+            .wallet
+            .due_unconfirmed_spend_requests(&pending_tx_resend_policy());
+        if due.is_empty() {
+            return;
+        }
+
+        let stored = match self.client.send_spends(due.iter(), verify_store).await {
+            Ok(()) => due
+                .iter()
+                .try_for_each(|spend| self.wallet.confirm_spend_request(spend)),
+            Err(err) => {
+                for spend in due.iter() {
+                    let _ = self
+                        .wallet
+                        .record_resend_attempt(spend.address(), Some(err.to_string()));
+                }
+                self.wallet.store_unconfirmed_spend_requests()
+            }
+        };
+        if let Err(err) = stored {
+            warn!("Could not store the resend attempts of the unconfirmed spends: {err:?}");
         }
     }
 
@@ -750,7 +927,7 @@ impl WalletClient {
     }
 
     /// Try resending failed transactions multiple times until it succeeds or until we reach max attempts.
-    async fn resend_pending_transaction_until_success(
+    pub(crate) async fn resend_pending_transaction_until_success(
         &mut self,
         verify_store: bool,
     ) -> WalletResult<()> {
@@ -758,8 +935,15 @@ impl WalletClient {
         // Wallet shall be all clear to progress forward.
         let mut attempts = 0;
         while self.wallet.unconfirmed_spend_requests_exist() {
-            info!("Pre-Unconfirmed transactions exist, sending again after 1 second...");
-            sleep(Duration::from_secs(1)).await;
+            // waiting for the next spend to be due, at least a second
+            let wait = self
+                .wallet
+                .next_resend_at(&pending_tx_resend_policy())
+                .and_then(|at| at.duration_since(SystemTime::now()).ok())
+                .unwrap_or_default()
+                .max(Duration::from_secs(1));
+            info!("Pre-Unconfirmed transactions exist, sending again after {wait:?}...");
+            sleep(wait).await;
             self.resend_pending_transactions(verify_store).await;
 
             if attempts > MAX_RESEND_PENDING_TX_ATTEMPTS {
@@ -779,6 +963,36 @@ impl WalletClient {
         }
     }
 
+    /// Confirm the unconfirmed spends of the wallet, see [`Client::confirm_spends`], removing
+    /// the ones which are final from the wallet: the confirmed ones, and the double spent ones
+    /// along with their cash notes.
+    pub async fn confirm_unconfirmed_spends(
+        &mut self,
+        cfg: &ConfirmationCfg,
+    ) -> WalletResult<Vec<SpendConfirmation>> {
+        let spends: Vec<SignedSpend> = self
+            .wallet
+            .unconfirmed_spend_requests()
+            .iter()
+            .cloned()
+            .collect();
+        let confirmations = self.client.confirm_spends(&spends, cfg).await;
+
+        for (spend, confirmation) in spends.iter().zip(confirmations.iter()) {
+            let unique_pubkey = spend.spend.unique_pubkey;
+            match confirmation.finality {
+                SpendFinality::Confirmed => self.wallet.confirm_spend_request(spend)?,
+                SpendFinality::DoubleSpent => {
+                    warn!("Removing double spent CashNote from wallet: {unique_pubkey:?}");
+                    self.wallet.drop_double_spent_request(unique_pubkey)?;
+                }
+                SpendFinality::Pending => {}
+            }
+        }
+
+        Ok(confirmations)
+    }
+
     /// Returns the wallet:
     ///
     /// Return type: [HotWallet]
@@ -854,6 +1068,7 @@ impl Client {
     /// # Ok(())
     /// # }
     /// ```
+    #[tracing::instrument(skip_all)]
     pub async fn send_spends(
         &self,
         spend_requests: impl Iterator<Item = &SignedSpend>,
@@ -925,6 +1140,67 @@ impl Client {
         Ok(())
     }
 
+    /// Poll the network until each spend is held by the quorum of its close group, or is found
+    /// double spent, reporting the finality of each spend and the time it took.
+    ///
+    /// The spends not final by the timeout of the configuration are reported as pending: they can
+    /// be sent again, see [`Client::send_spends`].
+    pub async fn confirm_spends(
+        &self,
+        spends: &[SignedSpend],
+        cfg: &ConfirmationCfg,
+    ) -> Vec<SpendConfirmation> {
+        let start = Instant::now();
+        let mut confirmations: Vec<SpendConfirmation> = spends
+            .iter()
+            .map(|spend| SpendConfirmation {
+                address: spend.address(),
+                finality: SpendFinality::Pending,
+                polls: 0,
+                elapsed: Duration::ZERO,
+            })
+            .collect();
+
+        loop {
+            let polls = spends
+                .iter()
+                .zip(confirmations.iter())
+                .filter(|(_, confirmation)| confirmation.finality == SpendFinality::Pending)
+                .map(|(spend, _)| async move {
+                    let fetched = self
+                        .get_spend_with_quorum(spend.address(), cfg.quorum)
+                        .await;
+                    (spend.address(), spend_finality(spend, fetched))
+                });
+            for (address, finality) in join_all(polls).await {
+                if let Some(confirmation) = confirmations
+                    .iter_mut()
+                    .find(|confirmation| confirmation.address == address)
+                {
+                    confirmation.polls += 1;
+                    confirmation.finality = finality;
+                    confirmation.elapsed = start.elapsed();
+                }
+            }
+
+            let pending = confirmations
+                .iter()
+                .filter(|confirmation| confirmation.finality == SpendFinality::Pending)
+                .count();
+            if pending == 0 || start.elapsed() >= cfg.timeout {
+                info!(
+                    "Confirmed {} out of {} spends in {:?}, {pending} are pending",
+                    confirmations.len() - pending,
+                    confirmations.len(),
+                    start.elapsed()
+                );
+                return confirmations;
+            }
+            debug!("{pending} spends are pending confirmation, polling them again");
+            sleep(cfg.poll_interval).await;
+        }
+    }
+
     /// Receive a Transfer, verify and redeem CashNotes from the Network.
     ///
     /// # Arguments
@@ -975,6 +1251,22 @@ impl Client {
         Ok(valuable_cashnotes)
     }
 
+    /// Like `receive`, for the redemptions of a transfer unwrapped by the wallet beforehand, for
+    /// the wallet not to be held while the Network is queried.
+    #[cfg(feature = "wallet-rpc")]
+    pub(crate) async fn receive_redemptions(
+        &self,
+        main_pubkey: MainPubkey,
+        cashnote_redemptions: &[sn_transfers::CashNoteRedemption],
+    ) -> WalletResult<Vec<CashNote>> {
+        let cashnotes = self
+            .network
+            .verify_cash_notes_redemptions(main_pubkey, cashnote_redemptions)
+            .map_err(|e| WalletError::CouldNotReceiveMoney(format!("{e:?}")))
+            .await?;
+        self.filter_out_already_spend_cash_notes(cashnotes).await
+    }
+
     /// Check that the redeemed CashNotes are not already spent
     async fn filter_out_already_spend_cash_notes(
         &self,
@@ -1049,34 +1341,59 @@ impl Client {
     /// # }
     /// ```
     pub async fn verify_cashnote(&self, cash_note: &CashNote) -> WalletResult<()> {
-        // We need to get all the spends in the cash_note from the network,
-        // and compare them to the spends in the cash_note, to know if the
-        // transfer is considered valid in the network.
-        let mut tasks = Vec::new();
-        for spend in &cash_note.parent_spends {
-            let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
-            debug!(
-                "Getting spend for pubkey {:?} from network at {address:?}",
-                spend.unique_pubkey()
-            );
-            tasks.push(self.get_spend_from_network(address));
-        }
+        verify_cashnote_on(self, cash_note).await
+    }
+}
 
-        let mut received_spends = std::collections::BTreeSet::new();
-        for result in join_all(tasks).await {
-            let network_valid_spend =
-                result.map_err(|err| WalletError::CouldNotVerifyTransfer(err.to_string()))?;
-            let _ = received_spends.insert(network_valid_spend);
-        }
+/// Verify that the spends referred to in the CashNote exist on the network, like
+/// [`Client::verify_cashnote`], over any [`ClientNetwork`], e.g. a `MockNetwork` in tests.
+pub async fn verify_cashnote_on(
+    network: &impl ClientNetwork,
+    cash_note: &CashNote,
+) -> WalletResult<()> {
+    // We need to get all the spends in the cash_note from the network,
+    // and compare them to the spends in the cash_note, to know if the
+    // transfer is considered valid in the network.
+    let mut tasks = Vec::new();
+    for spend in &cash_note.parent_spends {
+        let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+        debug!(
+            "Getting spend for pubkey {:?} from network at {address:?}",
+            spend.unique_pubkey()
+        );
+        tasks.push(network.get_spend(address));
+    }
 
-        // If all the spends in the cash_note are the same as the ones in the network,
-        // we have successfully verified that the cash_note is globally recognised and therefor valid.
-        if received_spends == cash_note.parent_spends {
-            return Ok(());
+    let mut received_spends = std::collections::BTreeSet::new();
+    for result in join_all(tasks).await {
+        let network_valid_spend =
+            result.map_err(|err| WalletError::CouldNotVerifyTransfer(err.to_string()))?;
+        let _ = received_spends.insert(network_valid_spend);
+    }
+
+    // If all the spends in the cash_note are the same as the ones in the network,
+    // we have successfully verified that the cash_note is globally recognised and therefor valid.
+    if received_spends == cash_note.parent_spends {
+        return Ok(());
+    }
+    Err(WalletError::CouldNotVerifyTransfer(
+        "The spends in network were not the same as the ones in the CashNote. The parents of this CashNote are probably double spends.".into(),
+    ))
+}
+
+/// The finality of the spend, given what was fetched from its address.
+fn spend_finality(spend: &SignedSpend, fetched: Result<SignedSpend>) -> SpendFinality {
+    match fetched {
+        Ok(fetched) if &fetched == spend => SpendFinality::Confirmed,
+        Ok(_) | Err(Error::Network(sn_networking::NetworkError::DoubleSpendAttempt(_))) => {
+            warn!("Spend {:?} is double spent", spend.address());
+            SpendFinality::DoubleSpent
+        }
+        // the holders not agreeing yet, e.g. the spend still being replicated, is polled again
+        Err(err) => {
+            trace!("Spend {:?} is not confirmed yet: {err}", spend.address());
+            SpendFinality::Pending
         }
-        Err(WalletError::CouldNotVerifyTransfer(
-            "The spends in network were not the same as the ones in the CashNote. The parents of this CashNote are probably double spends.".into(),
-        ))
     }
 }
 
@@ -1155,3 +1472,78 @@ pub async fn send(
 
     Ok(new_cash_note)
 }
+
+/// Send the whole balance of the wallet, except for `keep`, to `to`.
+/// Returns the new CashNote for the recipient.
+pub async fn sweep(
+    from: HotWallet,
+    keep: NanoTokens,
+    to: MainPubkey,
+    client: &Client,
+    verify_store: bool,
+) -> Result<CashNote> {
+    let balance = from.balance();
+    let amount = match balance.checked_sub(keep) {
+        Some(amount) if !amount.is_zero() => amount,
+        _ => return Err(Error::AmountIsZero),
+    };
+    info!("Sweeping {amount} out of {balance} to {to:?}");
+
+    send(from, amount, to, client, verify_store).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_networking::NetworkError;
+    use sn_transfers::GENESIS_CASHNOTE;
+    use std::collections::HashMap;
+
+    fn genesis_spend() -> SignedSpend {
+        GENESIS_CASHNOTE
+            .parent_spends
+            .first()
+            .cloned()
+            .expect("the genesis CashNote to have a parent spend")
+    }
+
+    #[test]
+    fn split_records_should_be_polled_again() {
+        let spend = genesis_spend();
+        let mut other = spend.clone();
+        other.derived_key_sig = bls::SecretKey::random().sign(b"other");
+
+        assert_eq!(
+            spend_finality(&spend, Ok(spend.clone())),
+            SpendFinality::Confirmed
+        );
+        assert_eq!(
+            spend_finality(&spend, Ok(other.clone())),
+            SpendFinality::DoubleSpent
+        );
+        assert_eq!(
+            spend_finality(
+                &spend,
+                Err(NetworkError::DoubleSpendAttempt(vec![spend.clone(), other]).into())
+            ),
+            SpendFinality::DoubleSpent
+        );
+        assert_eq!(
+            spend_finality(
+                &spend,
+                Err(NetworkError::GetRecordError(GetRecordError::SplitRecord {
+                    result_map: HashMap::new(),
+                })
+                .into())
+            ),
+            SpendFinality::Pending
+        );
+        assert_eq!(
+            spend_finality(
+                &spend,
+                Err(NetworkError::GetRecordError(GetRecordError::RecordNotFound).into())
+            ),
+            SpendFinality::Pending
+        );
+    }
+}
@@ -0,0 +1,671 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A wallet daemon, for GUIs and scripts to share one wallet process rather than each opening the
+//! wallet dir.
+//!
+//! The daemon holds the hot wallet and answers JSON-RPC 2.0 requests on a local socket, one
+//! request per line, each answered with a line. The methods, with their params, are:
+//!
+//! - `balance`: the balance in nanos, and whether unconfirmed spends remain
+//! - `address`: the hex-encoded main pubkey of the wallet
+//! - `send`, `{ "to", "amount" }`: send the nanos to the hex-encoded pubkey, or the label of the
+//!   address book, returning the hex-encoded transfer for the recipient
+//! - `deposit`, `{ "transfer" }`: receive the hex-encoded transfer, once verified with the Network
+//! - `history`, `{ "since", "until", "kind", "counterparty", "offset", "limit" }`: the entries of
+//!   the history of the wallet, all params being optional
+//! - `payment_request`, `{ "amount", "reason", "expires_in" }`: the payment URI of a request to
+//!   pay the wallet, all params being optional, `expires_in` in seconds
+//! - `pay`, `{ "request", "amount" }`: pay the payment URI, of the amount it requests, else of the
+//!   given one
+//!
+//! Amounts are in nanos. Anyone able to connect to the socket can spend from the wallet, so it is
+//! only accessible to the user running the daemon.
+
+use crate::{
+    wallet::{pending_tx_resend_policy, MAX_RESEND_PENDING_TX_ATTEMPTS},
+    Client, Error,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sn_transfers::{
+    Hash, HistoryEntry, HistoryFilter, HistoryKind, HotWallet, MainPubkey, NanoTokens,
+    PaymentRequest, Recipient, Transfer, WalletError,
+};
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use tokio::{sync::Mutex, time::sleep};
+
+/// The file of the wallet dir the daemon listens on by default.
+pub const WALLET_RPC_SOCKET_FILE: &str = "wallet_rpc.sock";
+
+/// The max length of a request line, a transfer of a few cash notes being well under it.
+const MAX_REQUEST_LINE_LEN: usize = 256 * 1024;
+
+/// The number of history entries returned when no limit is given.
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The code of the errors of the wallet and of the Network.
+const WALLET_ERROR: i64 = -32000;
+
+/// Answers the JSON-RPC requests made to the wallet.
+pub struct WalletRpc {
+    client: Client,
+    /// Held only while the wallet is read or updated, never while the Network is waited for
+    wallet: Mutex<HotWallet>,
+    /// Held through each spend, for the spends to be made one at a time
+    spending: Mutex<()>,
+    verify_store: bool,
+}
+
+impl WalletRpc {
+    /// A daemon of the wallet, connected to the Network through the client.
+    pub fn new(client: Client, wallet: HotWallet) -> Self {
+        Self {
+            client,
+            wallet: Mutex::new(wallet),
+            spending: Mutex::new(()),
+            verify_store: true,
+        }
+    }
+
+    /// Whether the spends sent are verified to be stored, which they are by default.
+    pub fn with_verify_store(mut self, verify_store: bool) -> Self {
+        self.verify_store = verify_store;
+        self
+    }
+
+    /// Serve the requests made on the socket until the listener fails. The socket left by a
+    /// previous daemon is replaced, but not a live one nor anything else found at the path.
+    #[cfg(unix)]
+    pub async fn serve(self, socket_path: &std::path::Path) -> crate::Result<()> {
+        use std::sync::Arc;
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let listener = bind_socket(socket_path)?;
+        info!("Wallet RPC listening on {socket_path:?}");
+
+        let rpc = Arc::new(self);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let rpc = Arc::clone(&rpc);
+            let _handle = tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut reader = BufReader::new(reader);
+                loop {
+                    let mut line = Vec::new();
+                    let limit = MAX_REQUEST_LINE_LEN as u64 + 1;
+                    match (&mut reader).take(limit).read_until(b'\n', &mut line).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) => {}
+                    }
+                    // the rest of a line too long cannot be told apart from the next request
+                    let too_long = line.len() > MAX_REQUEST_LINE_LEN;
+                    let response = if too_long {
+                        let error = RpcError::new(
+                            INVALID_REQUEST,
+                            format!("requests are limited to {MAX_REQUEST_LINE_LEN} bytes"),
+                        );
+                        Some(response(Value::Null, Err(error)))
+                    } else {
+                        rpc.handle_line(&String::from_utf8_lossy(&line)).await
+                    };
+                    if let Some(mut response) = response {
+                        response.push('\n');
+                        if writer.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                    if too_long {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    /// The response to the request on the line, none if it is a notification.
+    async fn handle_line(&self, line: &str) -> Option<String> {
+        let request = match parse_request(line) {
+            Ok(request) => request,
+            Err(error) => return Some(response(Value::Null, Err(error))),
+        };
+        let result = self.call(&request.method, request.params).await;
+        if let Err(error) = &result {
+            debug!("Wallet RPC {} failed: {}", request.method, error.message);
+        }
+        request.id.map(|id| response(id, result))
+    }
+
+    async fn call(&self, method: &str, params: Value) -> RpcResult {
+        if let Some(result) = call_offline(&mut *self.wallet.lock().await, method, params.clone()) {
+            return result;
+        }
+        match method {
+            "send" => {
+                let params: SendParams = parse_params(params)?;
+                let to = {
+                    let wallet = self.wallet.lock().await;
+                    params
+                        .to
+                        .parse::<Recipient>()
+                        .and_then(|recipient| wallet.address_book()?.resolve(&recipient))
+                        .map_err(Error::from)?
+                };
+                let amount = NanoTokens::from(params.amount);
+                self.spend(to, Spend::Send(amount)).await
+            }
+            "pay" => {
+                let params: PayParams = parse_params(params)?;
+                let request = PaymentRequest::from_str(&params.request).map_err(Error::from)?;
+                let amount = params.amount.map(NanoTokens::from);
+                let to = request.to;
+                self.spend(to, Spend::Pay(request, amount)).await
+            }
+            "deposit" => {
+                let params: DepositParams = parse_params(params)?;
+                let transfer = Transfer::from_hex(&params.transfer).map_err(Error::from)?;
+                let (address, redemptions) = {
+                    let wallet = self.wallet.lock().await;
+                    (wallet.address(), wallet.unwrap_transfer(&transfer)?)
+                };
+                let cash_notes = self
+                    .client
+                    .receive_redemptions(address, &redemptions)
+                    .await?;
+                let deposited = cash_notes
+                    .iter()
+                    .map(|cash_note| cash_note.value().as_nano())
+                    .fold(0, u64::saturating_add);
+                let mut wallet = self.wallet.lock().await;
+                wallet.deposit_and_store_to_disk(&cash_notes)?;
+                info!("Wallet RPC deposited {}", NanoTokens::from(deposited));
+                Ok(json!({
+                    "deposited": deposited,
+                    "balance": wallet.balance().as_nano(),
+                }))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("no method {method:?}"),
+            )),
+        }
+    }
+
+    /// Make the spend, once the ones left unconfirmed by a previous spend are, and notify the
+    /// recipient of the transfer. The wallet is held to build the transfer and to record its
+    /// spends as sent, but not while they are sent.
+    async fn spend(&self, to: MainPubkey, spend: Spend) -> RpcResult {
+        let _spending = self.spending.lock().await;
+        self.resend_unconfirmed_spends().await?;
+
+        let (created_cash_notes, spends) = {
+            let mut wallet = self.wallet.lock().await;
+            let created_cash_notes = match &spend {
+                Spend::Send(amount) => {
+                    if amount.is_zero() {
+                        return Err(Error::AmountIsZero.into());
+                    }
+                    wallet.approve_spend_async(vec![(*amount, to)], 0).await?;
+                    wallet.local_send(vec![(*amount, to)], None)?
+                }
+                Spend::Pay(request, amount) => {
+                    if !request.is_expired() {
+                        let to_pay = request.amount_to_pay(*amount)?;
+                        wallet.approve_spend_async(vec![(to_pay, to)], 0).await?;
+                    }
+                    wallet.local_send_payment_request(request, *amount)?
+                }
+            };
+            let spends: Vec<_> = wallet
+                .unconfirmed_spend_requests()
+                .iter()
+                .cloned()
+                .collect();
+            (created_cash_notes, spends)
+        };
+        let cash_note = match &created_cash_notes[..] {
+            [cash_note] => cash_note.clone(),
+            _ => {
+                return Err(Error::from(WalletError::CouldNotSendMoney(format!(
+                    "{} cash notes were created for a single recipient",
+                    created_cash_notes.len()
+                )))
+                .into())
+            }
+        };
+
+        // the spends stay unconfirmed in the wallet if they fail to be sent, to be sent again
+        if let Err(error) = self
+            .client
+            .send_spends(spends.iter(), self.verify_store)
+            .await
+        {
+            return Err(Error::from(WalletError::CouldNotSendMoney(format!(
+                "The transfer was not successfully registered in the network: {error:?}"
+            )))
+            .into());
+        }
+        let balance = {
+            let mut wallet = self.wallet.lock().await;
+            for spend in &spends {
+                wallet.confirm_spend_request(spend)?;
+            }
+            wallet.balance()
+        };
+        info!("Wallet RPC sent {} to {to:?}", cash_note.value());
+
+        let transfer = Transfer::transfer_from_cash_note(&cash_note).map_err(Error::from)?;
+        // the recipient learns about the funds right away if they are listening
+        if let Err(err) = self.client.notify_transfer(to, &transfer).await {
+            debug!("Could not notify the recipient of the transfer: {err:?}");
+        }
+        Ok(json!({
+            "transfer": transfer.to_hex().map_err(Error::from)?,
+            "amount": cash_note.value().as_nano(),
+            "balance": balance.as_nano(),
+        }))
+    }
+
+    /// Send again the spends left unconfirmed, as they become due, like
+    /// `WalletClient::resend_pending_transaction_until_success` but without holding the wallet
+    /// while waiting or sending.
+    async fn resend_unconfirmed_spends(&self) -> std::result::Result<(), RpcError> {
+        let policy = pending_tx_resend_policy();
+        for _ in 0..=MAX_RESEND_PENDING_TX_ATTEMPTS {
+            let wait = {
+                let mut wallet = self.wallet.lock().await;
+                if !wallet.unconfirmed_spend_requests_exist() {
+                    return Ok(());
+                }
+                wallet
+                    .next_resend_at(&policy)
+                    .and_then(|at| at.duration_since(SystemTime::now()).ok())
+                    .unwrap_or_default()
+                    .max(Duration::from_secs(1))
+            };
+            info!("Unconfirmed spends exist, sending them again after {wait:?}...");
+            sleep(wait).await;
+
+            let due = self
+                .wallet
+                .lock()
+                .await
+                .due_unconfirmed_spend_requests(&policy);
+            if due.is_empty() {
+                continue;
+            }
+            let sent = self.client.send_spends(due.iter(), self.verify_store).await;
+            let mut wallet = self.wallet.lock().await;
+            match sent {
+                Ok(()) => {
+                    for spend in &due {
+                        wallet.confirm_spend_request(spend)?;
+                    }
+                }
+                Err(err) => {
+                    for spend in &due {
+                        let _ =
+                            wallet.record_resend_attempt(spend.address(), Some(err.to_string()));
+                    }
+                    wallet.store_unconfirmed_spend_requests()?;
+                }
+            }
+        }
+
+        error!("Wallet has pre-unconfirmed transactions, can't progress further.");
+        Err(Error::from(WalletError::UnconfirmedTxAfterRetries).into())
+    }
+}
+
+/// Bind the socket at the path, replacing the socket of a previous daemon which is gone.
+///
+/// The socket is bound in a dir only the user can enter, then moved to the path, for no one else
+/// to connect to it before it is made private to the user.
+#[cfg(unix)]
+fn bind_socket(socket_path: &std::path::Path) -> std::io::Result<tokio::net::UnixListener> {
+    use std::{
+        fs, io,
+        os::unix::{
+            fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+            net::UnixStream,
+        },
+    };
+
+    if let Ok(metadata) = fs::symlink_metadata(socket_path) {
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{socket_path:?} is not a socket"),
+            ));
+        }
+        if UnixStream::connect(socket_path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("a wallet daemon already listens on {socket_path:?}"),
+            ));
+        }
+        fs::remove_file(socket_path)?;
+    }
+
+    let file_name = socket_path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no socket file name"))?;
+    let private_dir = socket_path.with_file_name(format!(
+        ".{}.{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    fs::DirBuilder::new().mode(0o700).create(&private_dir)?;
+    let private_path = private_dir.join(file_name);
+    let bound = tokio::net::UnixListener::bind(&private_path).and_then(|listener| {
+        fs::set_permissions(&private_path, fs::Permissions::from_mode(0o600))?;
+        fs::rename(&private_path, socket_path)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&private_path);
+    let _ = fs::remove_dir(&private_dir);
+    bound
+}
+
+/// The spends made through the daemon.
+enum Spend {
+    Send(NanoTokens),
+    Pay(PaymentRequest, Option<NanoTokens>),
+}
+
+/// Answer the methods needing no access to the Network, none being returned for the others.
+fn call_offline(wallet: &mut HotWallet, method: &str, params: Value) -> Option<RpcResult> {
+    let result = match method {
+        "balance" => Ok(json!({
+            "balance": wallet.balance().as_nano(),
+            "unconfirmed_spends": wallet.unconfirmed_spend_requests_exist(),
+        })),
+        "address" => Ok(json!({ "address": wallet.address().to_hex() })),
+        "history" => history(wallet, params),
+        "payment_request" => payment_request(wallet, params),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn history(wallet: &HotWallet, params: Value) -> RpcResult {
+    let params: HistoryParams = parse_params(params)?;
+    let counterparty = params
+        .counterparty
+        .as_deref()
+        .map(MainPubkey::from_hex)
+        .transpose()
+        .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+    let filter = HistoryFilter {
+        since: params.since,
+        until: params.until,
+        kind: params.kind,
+        counterparty,
+    };
+    let history = wallet.history()?;
+    let entries: Vec<_> = history
+        .query(
+            &filter,
+            params.offset,
+            params.limit.unwrap_or(DEFAULT_HISTORY_LIMIT),
+        )
+        .into_iter()
+        .map(history_entry_json)
+        .collect();
+    Ok(json!({ "entries": entries }))
+}
+
+fn history_entry_json(entry: &HistoryEntry) -> Value {
+    json!({
+        "time": entry.time,
+        "kind": entry.kind,
+        "amount": entry.amount.as_nano(),
+        "fee": entry.fee.as_nano(),
+        "counterparty": entry.counterparty.map(|pubkey| pubkey.to_hex()),
+        "cash_notes": entry
+            .cash_notes
+            .iter()
+            .map(|unique_pubkey| unique_pubkey.to_hex())
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn payment_request(wallet: &HotWallet, params: Value) -> RpcResult {
+    let params: PaymentRequestParams = parse_params(params)?;
+    let mut request = PaymentRequest::new(wallet.address());
+    if let Some(amount) = params.amount {
+        request = request.with_amount(NanoTokens::from(amount));
+    }
+    if let Some(reason) = params.reason {
+        let reason = Hash::from_hex(&reason)
+            .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+        request = request.with_reason(reason);
+    }
+    if let Some(expires_in) = params.expires_in {
+        request = request.expiring_in(Duration::from_secs(expires_in));
+    }
+    Ok(json!({ "uri": request.to_string() }))
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Missing for the notifications, which are not answered
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct SendParams {
+    to: String,
+    amount: u64,
+}
+
+#[derive(Deserialize)]
+struct PayParams {
+    request: String,
+    amount: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct DepositParams {
+    transfer: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct HistoryParams {
+    since: Option<u64>,
+    until: Option<u64>,
+    kind: Option<HistoryKind>,
+    counterparty: Option<String>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PaymentRequestParams {
+    amount: Option<u64>,
+    reason: Option<String>,
+    expires_in: Option<u64>,
+}
+
+type RpcResult = std::result::Result<Value, RpcError>;
+
+/// The error member of a response.
+#[derive(Debug, Serialize, thiserror::Error)]
+#[error("{message} (code {code})")]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(err: Error) -> Self {
+        Self::new(WALLET_ERROR, err.to_string())
+    }
+}
+
+impl From<WalletError> for RpcError {
+    fn from(err: WalletError) -> Self {
+        Error::from(err).into()
+    }
+}
+
+fn parse_request(line: &str) -> std::result::Result<Request, RpcError> {
+    let request: Value =
+        serde_json::from_str(line).map_err(|err| RpcError::new(PARSE_ERROR, err.to_string()))?;
+    let request: Request = serde_json::from_value(request)
+        .map_err(|err| RpcError::new(INVALID_REQUEST, err.to_string()))?;
+    if request.jsonrpc != "2.0" {
+        return Err(RpcError::new(
+            INVALID_REQUEST,
+            "only JSON-RPC 2.0 is served",
+        ));
+    }
+    Ok(request)
+}
+
+/// The params of a method, those of the methods taking no params being omitted.
+fn parse_params<P: for<'de> Deserialize<'de>>(params: Value) -> std::result::Result<P, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+fn response(id: Value, result: RpcResult) -> String {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+    response.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+    use sn_transfers::{create_first_cash_note_from_key, MainSecretKey};
+
+    fn call(wallet: &mut HotWallet, method: &str, params: Value) -> RpcResult {
+        call_offline(wallet, method, params).expect("an offline method")
+    }
+
+    #[test]
+    fn requests_should_be_answered_per_json_rpc() {
+        let request = parse_request(r#"{"jsonrpc":"2.0","id":7,"method":"balance"}"#)
+            .expect("a valid request");
+        assert_eq!(request.id, Some(json!(7)));
+        assert!(request.params.is_null());
+
+        let notification =
+            parse_request(r#"{"jsonrpc":"2.0","method":"balance"}"#).expect("a valid notification");
+        assert_eq!(notification.id, None);
+
+        let code = |line| parse_request(line).err().map(|error| error.code);
+        assert_eq!(code("{"), Some(PARSE_ERROR));
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1}"#), Some(INVALID_REQUEST));
+        assert_eq!(
+            code(r#"{"jsonrpc":"1.0","id":1,"method":"balance"}"#),
+            Some(INVALID_REQUEST)
+        );
+
+        let answer: Value = serde_json::from_str(&response(
+            json!(7),
+            Err(RpcError::new(METHOD_NOT_FOUND, "no")),
+        ))
+        .expect("a JSON response");
+        assert_eq!(
+            answer,
+            json!({ "jsonrpc": "2.0", "id": 7, "error": { "code": METHOD_NOT_FOUND, "message": "no" } })
+        );
+    }
+
+    #[test]
+    fn offline_methods_should_answer_from_the_wallet() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note = create_first_cash_note_from_key(wallet.key())?;
+        wallet.deposit_and_store_to_disk(&vec![cash_note.clone()])?;
+
+        let balance = call(&mut wallet, "balance", Value::Null)?;
+        assert_eq!(balance["balance"], json!(cash_note.value().as_nano()));
+        assert_eq!(balance["unconfirmed_spends"], json!(false));
+
+        let address = call(&mut wallet, "address", Value::Null)?;
+        assert_eq!(address["address"], json!(wallet.address().to_hex()));
+
+        let history = call(&mut wallet, "history", json!({ "kind": "Received" }))?;
+        assert_eq!(history["entries"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            history["entries"][0]["amount"],
+            json!(cash_note.value().as_nano())
+        );
+        let history = call(&mut wallet, "history", json!({ "kind": "Sent" }))?;
+        assert_eq!(history["entries"], json!([]));
+
+        let request = call(&mut wallet, "payment_request", json!({ "amount": 1_500 }))?;
+        let uri = request["uri"].as_str().unwrap_or_default();
+        let parsed = PaymentRequest::from_str(uri)?;
+        assert_eq!(parsed.to, wallet.address());
+        assert_eq!(parsed.amount, Some(NanoTokens::from(1_500)));
+
+        let invalid = call(
+            &mut wallet,
+            "history",
+            json!({ "counterparty": "not a key" }),
+        );
+        assert_eq!(invalid.err().map(|error| error.code), Some(INVALID_PARAMS));
+        assert!(call_offline(&mut wallet, "send", Value::Null).is_none());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn the_daemon_should_only_replace_a_stale_socket() -> Result<()> {
+        use std::{fs, io::ErrorKind, os::unix::fs::PermissionsExt};
+
+        let dir = tempfile::tempdir()?;
+        let socket_path = dir.path().join(WALLET_RPC_SOCKET_FILE);
+        fs::write(&socket_path, b"not a socket")?;
+        let refused = bind_socket(&socket_path).err().map(|err| err.kind());
+        assert_eq!(refused, Some(ErrorKind::AlreadyExists));
+        fs::remove_file(&socket_path)?;
+
+        let listener = bind_socket(&socket_path)?;
+        let mode = fs::metadata(&socket_path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        let refused = bind_socket(&socket_path).err().map(|err| err.kind());
+        assert_eq!(refused, Some(ErrorKind::AddrInUse));
+
+        // the socket left once the daemon is gone
+        drop(listener);
+        let _listener = bind_socket(&socket_path)?;
+        Ok(())
+    }
+}
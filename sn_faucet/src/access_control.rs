@@ -0,0 +1,637 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Limiting who can get tokens from the faucet, and how often, so that a public faucet cannot be
+//! drained by a script.
+//!
+//! Every gift is rate limited per IP address and per wallet. On top of that, the faucet can
+//! require either a valid access token, or the solution to a proof-of-work challenge it issued.
+//!
+//! The tokens are dripped: each gift is a small amount, up to a lifetime cap per wallet, and the
+//! wallets can be restricted by an allowlist or a denylist. The gifts are recorded in a ledger,
+//! which also restores the rate limits and the amounts gifted after a restart.
+
+use clap::Args;
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+use sn_transfers::{rand::Rng, Hash, MainPubkey, NanoTokens};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+use tracing::{debug, error, info, warn};
+
+/// The name of the file recording every gift, one JSON object per line.
+const LEDGER_FILE_NAME: &str = "dispensing_ledger.jsonl";
+
+/// How long an issued proof-of-work challenge can be solved for.
+const CHALLENGE_LIFETIME: Duration = Duration::from_secs(300);
+
+/// The maximum number of challenges waiting for a solution.
+const MAX_PENDING_CHALLENGES: usize = 10_000;
+
+/// The options limiting the gifts of the faucet.
+#[derive(Args, Debug, Clone)]
+pub struct AccessArgs {
+    /// The minimum number of seconds between two gifts to the same IP address, 0 to disable.
+    #[clap(long, default_value_t = 60)]
+    pub ip_interval: u64,
+    /// The minimum number of seconds between two gifts to the same wallet, 0 to disable.
+    #[clap(long, default_value_t = 3600)]
+    pub wallet_interval: u64,
+    /// The number of leading zero bits required from the proof-of-work solutions, 0 to disable.
+    ///
+    /// Requests with a valid access token do not need a solution.
+    #[clap(long, default_value_t = 0)]
+    pub pow_difficulty: u8,
+    /// A token granting access to the faucet, can be provided multiple times.
+    ///
+    /// When any is provided, requests without proof-of-work need one of them.
+    #[clap(long = "access-token")]
+    pub access_tokens: Vec<String>,
+    /// Take the IP address of the requester from the X-Forwarded-For header.
+    ///
+    /// Only use this when the faucet runs behind a reverse proxy setting that header.
+    #[clap(long)]
+    pub trust_forwarded_for: bool,
+    /// The amount of tokens dripped by each gift, at most once per wallet interval.
+    #[clap(long, value_parser = NanoTokens::from_str, default_value = "1")]
+    pub drip_amount: NanoTokens,
+    /// The total amount of tokens a wallet can ever be gifted.
+    #[clap(long, value_parser = NanoTokens::from_str)]
+    pub lifetime_cap: Option<NanoTokens>,
+    /// A file of hex-encoded wallet keys, one per line, to only gift to.
+    #[clap(long)]
+    pub allowlist: Option<PathBuf>,
+    /// A file of hex-encoded wallet keys, one per line, to never gift to.
+    #[clap(long)]
+    pub denylist: Option<PathBuf>,
+}
+
+/// The credentials a gift request comes with, from its query parameters.
+#[derive(Debug, Default, Clone)]
+pub struct Credentials {
+    pub token: Option<String>,
+    /// A hex-encoded challenge, along with the nonce solving it.
+    pub challenge: Option<String>,
+    pub nonce: Option<u64>,
+}
+
+impl Credentials {
+    pub fn from_query(query: &HashMap<String, String>) -> Self {
+        Self {
+            token: query.get("token").cloned(),
+            challenge: query.get("challenge").cloned(),
+            nonce: query.get("nonce").and_then(|nonce| nonce.parse().ok()),
+        }
+    }
+}
+
+/// A proof-of-work challenge issued to a requester.
+#[derive(Debug, Serialize)]
+pub struct Challenge {
+    /// Hex-encoded, to be hashed with the wallet key and the nonce.
+    pub challenge: String,
+    pub difficulty: u8,
+    pub expires_in_secs: u64,
+}
+
+/// Why a gift request has been denied.
+#[derive(Debug, PartialEq, Eq)]
+pub enum AccessDenied {
+    RateLimited { retry_after: Duration },
+    CredentialsRequired,
+    InvalidToken,
+    InvalidProofOfWork,
+    TooManyChallenges,
+    WalletNotAllowed,
+    LifetimeCapReached,
+}
+
+impl std::fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RateLimited { retry_after } => {
+                write!(
+                    f,
+                    "Rate limited, retry in {} seconds",
+                    retry_after.as_secs()
+                )
+            }
+            Self::CredentialsRequired => write!(
+                f,
+                "An access token or a proof-of-work solution is required, see /api/challenge"
+            ),
+            Self::InvalidToken => write!(f, "Invalid access token"),
+            Self::InvalidProofOfWork => write!(f, "Invalid or expired proof-of-work solution"),
+            Self::TooManyChallenges => write!(f, "Too many pending challenges, retry later"),
+            Self::WalletNotAllowed => write!(f, "This wallet cannot be gifted"),
+            Self::LifetimeCapReached => {
+                write!(f, "This wallet has already been gifted all it can be")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccessDenied {}
+
+/// An entry of the dispensing ledger.
+#[derive(Debug, Serialize, Deserialize)]
+struct LedgerEntry {
+    /// Seconds since the Unix epoch.
+    time: u64,
+    ip: Option<IpAddr>,
+    wallet: String,
+    amount: NanoTokens,
+}
+
+#[derive(Default)]
+struct AccessState {
+    last_gift_by_ip: HashMap<IpAddr, SystemTime>,
+    last_gift_by_wallet: HashMap<String, SystemTime>,
+    /// The total amount gifted to each wallet, including the gifts being sent.
+    gifted_by_wallet: HashMap<String, NanoTokens>,
+    /// The challenges waiting for a solution, with the time they expire at.
+    pending_challenges: HashMap<[u8; 32], SystemTime>,
+}
+
+/// Decides which gift requests are served, and records the gifts.
+pub struct AccessControl {
+    args: AccessArgs,
+    allowlist: Option<HashSet<String>>,
+    denylist: HashSet<String>,
+    ledger_path: PathBuf,
+    state: Mutex<AccessState>,
+}
+
+impl AccessControl {
+    /// Keep the ledger in `root_dir`, restoring the rate limits and the amounts gifted from the
+    /// gifts it holds.
+    pub fn new(args: AccessArgs, root_dir: &Path) -> Result<Self> {
+        let ledger_path = root_dir.join(LEDGER_FILE_NAME);
+        let mut state = AccessState::default();
+
+        if ledger_path.exists() {
+            let file = std::fs::File::open(&ledger_path)?;
+            let mut entries = 0;
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                let entry: LedgerEntry = match serde_json::from_str(&line) {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        warn!("Skipping the invalid ledger entry {line:?}: {err}");
+                        continue;
+                    }
+                };
+                let time = SystemTime::UNIX_EPOCH + Duration::from_secs(entry.time);
+                if let Some(ip) = entry.ip {
+                    let _ = state.last_gift_by_ip.insert(ip, time);
+                }
+                let gifted = state
+                    .gifted_by_wallet
+                    .entry(entry.wallet.clone())
+                    .or_insert(NanoTokens::zero());
+                *gifted = gifted
+                    .checked_add(entry.amount)
+                    .unwrap_or(NanoTokens::from(u64::MAX));
+                let _ = state.last_gift_by_wallet.insert(entry.wallet, time);
+                entries += 1;
+            }
+            info!("Restored the rate limits from {entries} entries of {ledger_path:?}");
+        }
+
+        let allowlist = args.allowlist.as_deref().map(read_wallets).transpose()?;
+        let denylist = args
+            .denylist
+            .as_deref()
+            .map(read_wallets)
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            args,
+            allowlist,
+            denylist,
+            ledger_path,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn trust_forwarded_for(&self) -> bool {
+        self.args.trust_forwarded_for
+    }
+
+    /// Issue a new proof-of-work challenge.
+    pub fn issue_challenge(&self) -> Result<Challenge, AccessDenied> {
+        let now = SystemTime::now();
+        let mut state = self.lock_state();
+        state.pending_challenges.retain(|_, expiry| *expiry > now);
+        if state.pending_challenges.len() >= MAX_PENDING_CHALLENGES {
+            warn!("Refusing to issue a challenge, too many are pending");
+            return Err(AccessDenied::TooManyChallenges);
+        }
+
+        let challenge: [u8; 32] = sn_transfers::rand::thread_rng().gen();
+        let _ = state
+            .pending_challenges
+            .insert(challenge, now + CHALLENGE_LIFETIME);
+        Ok(Challenge {
+            challenge: hex::encode(challenge),
+            difficulty: self.args.pow_difficulty,
+            expires_in_secs: CHALLENGE_LIFETIME.as_secs(),
+        })
+    }
+
+    /// Check that a gift to `wallet` can be sent to the requester, reserving it if so.
+    /// Returns the amount to gift, which is less than the drip amount when close to the cap.
+    ///
+    /// `wallet` is the hex encoding of a `MainPubkey`, as returned by `MainPubkey::to_hex`.
+    /// The reservation is either confirmed with `record_gift`, or cancelled with `cancel_gift`.
+    pub fn authorize_gift(
+        &self,
+        ip: IpAddr,
+        wallet: &str,
+        credentials: &Credentials,
+    ) -> Result<NanoTokens, AccessDenied> {
+        if self.denylist.contains(wallet)
+            || self
+                .allowlist
+                .as_ref()
+                .is_some_and(|allowlist| !allowlist.contains(wallet))
+        {
+            return Err(AccessDenied::WalletNotAllowed);
+        }
+
+        let now = SystemTime::now();
+        let mut state = self.lock_state();
+
+        let gifted = state
+            .gifted_by_wallet
+            .get(wallet)
+            .copied()
+            .unwrap_or(NanoTokens::zero());
+        let amount = match self.args.lifetime_cap {
+            Some(cap) => cap
+                .checked_sub(gifted)
+                .unwrap_or(NanoTokens::zero())
+                .min(self.args.drip_amount),
+            None => self.args.drip_amount,
+        };
+        if amount.is_zero() {
+            return Err(AccessDenied::LifetimeCapReached);
+        }
+
+        if let Some(retry_after) = self.retry_after(&state, ip, wallet, now) {
+            return Err(AccessDenied::RateLimited { retry_after });
+        }
+        self.check_credentials(&mut state, wallet, credentials, now)?;
+
+        let _ = state.last_gift_by_ip.insert(ip, now);
+        let _ = state.last_gift_by_wallet.insert(wallet.to_string(), now);
+        let _ = state.gifted_by_wallet.insert(
+            wallet.to_string(),
+            gifted
+                .checked_add(amount)
+                .unwrap_or(NanoTokens::from(u64::MAX)),
+        );
+        Ok(amount)
+    }
+
+    /// Release the reservation of a gift of `amount` that could not be sent.
+    pub fn cancel_gift(&self, ip: IpAddr, wallet: &str, amount: NanoTokens) {
+        let mut state = self.lock_state();
+        let _ = state.last_gift_by_ip.remove(&ip);
+        let _ = state.last_gift_by_wallet.remove(wallet);
+        if let Some(gifted) = state.gifted_by_wallet.get_mut(wallet) {
+            *gifted = gifted.checked_sub(amount).unwrap_or(NanoTokens::zero());
+        }
+    }
+
+    /// Record a gift that has been sent in the ledger.
+    pub fn record_gift(&self, ip: IpAddr, wallet: &str, amount: NanoTokens) {
+        let entry = LedgerEntry {
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            ip: Some(ip),
+            wallet: wallet.to_string(),
+            amount,
+        };
+        let result = serde_json::to_string(&entry)
+            .map_err(std::io::Error::from)
+            .and_then(|line| {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.ledger_path)?;
+                writeln!(file, "{line}")
+            });
+        if let Err(err) = result {
+            error!("Failed to record the gift {entry:?} in the ledger: {err}");
+        }
+    }
+
+    fn retry_after(
+        &self,
+        state: &AccessState,
+        ip: IpAddr,
+        wallet: &str,
+        now: SystemTime,
+    ) -> Option<Duration> {
+        let remaining = |last_gift: Option<&SystemTime>, interval: u64| {
+            let elapsed = now.duration_since(*last_gift?).unwrap_or_default();
+            Duration::from_secs(interval).checked_sub(elapsed)
+        };
+
+        let by_ip = remaining(state.last_gift_by_ip.get(&ip), self.args.ip_interval);
+        let by_wallet = remaining(
+            state.last_gift_by_wallet.get(wallet),
+            self.args.wallet_interval,
+        );
+        by_ip
+            .max(by_wallet)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    fn check_credentials(
+        &self,
+        state: &mut AccessState,
+        wallet: &str,
+        credentials: &Credentials,
+        now: SystemTime,
+    ) -> Result<(), AccessDenied> {
+        if let Some(token) = &credentials.token {
+            return if self.args.access_tokens.contains(token) {
+                Ok(())
+            } else {
+                Err(AccessDenied::InvalidToken)
+            };
+        }
+
+        if self.args.pow_difficulty > 0 {
+            let (Some(challenge), Some(nonce)) = (&credentials.challenge, credentials.nonce) else {
+                return Err(AccessDenied::CredentialsRequired);
+            };
+            let challenge: [u8; 32] = hex::decode(challenge)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or(AccessDenied::InvalidProofOfWork)?;
+            // a challenge can only be used once, whether the solution is valid or not
+            let is_pending = state
+                .pending_challenges
+                .remove(&challenge)
+                .is_some_and(|expiry| expiry > now);
+            if !is_pending || !solves(&challenge, wallet, nonce, self.args.pow_difficulty) {
+                debug!("Invalid proof-of-work for {wallet}");
+                return Err(AccessDenied::InvalidProofOfWork);
+            }
+            return Ok(());
+        }
+
+        if self.args.access_tokens.is_empty() {
+            Ok(())
+        } else {
+            Err(AccessDenied::CredentialsRequired)
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, AccessState> {
+        // the state is always left consistent, even if a holder panicked
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Read a list of hex-encoded wallet keys, one per line, normalising their encoding.
+fn read_wallets(path: &Path) -> Result<HashSet<String>> {
+    let mut wallets = HashSet::new();
+    for line in std::fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let _ = wallets.insert(MainPubkey::from_hex(line)?.to_hex());
+    }
+    info!("Read {} wallets from {path:?}", wallets.len());
+    Ok(wallets)
+}
+
+/// Whether the nonce solves the challenge for the wallet, i.e. whether the hash of the three of
+/// them starts with `difficulty` zero bits.
+fn solves(challenge: &[u8; 32], wallet: &str, nonce: u64, difficulty: u8) -> bool {
+    let mut bytes = challenge.to_vec();
+    bytes.extend_from_slice(wallet.as_bytes());
+    bytes.extend_from_slice(&nonce.to_le_bytes());
+    leading_zero_bits(Hash::hash(&bytes).slice()) >= difficulty as u32
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut zeros = 0;
+    for byte in bytes {
+        zeros += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    zeros
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use std::net::Ipv4Addr;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn args() -> AccessArgs {
+        AccessArgs {
+            ip_interval: 60,
+            wallet_interval: 3600,
+            pow_difficulty: 0,
+            access_tokens: vec![],
+            trust_forwarded_for: false,
+            drip_amount: NanoTokens::from(10),
+            lifetime_cap: None,
+            allowlist: None,
+            denylist: None,
+        }
+    }
+
+    #[test]
+    fn gifts_should_be_rate_limited_across_restarts() -> Result<()> {
+        let dir = TempDir::new()?;
+        let ip: IpAddr = [127, 0, 0, 1].into();
+        let other_ip: IpAddr = [127, 0, 0, 2].into();
+        let access = AccessControl::new(args(), dir.path())?;
+
+        let _ = access.authorize_gift(ip, "wallet", &Credentials::default())?;
+        access.record_gift(ip, "wallet", NanoTokens::from(1));
+        assert!(matches!(
+            access.authorize_gift(ip, "other wallet", &Credentials::default()),
+            Err(AccessDenied::RateLimited { .. })
+        ));
+        assert!(matches!(
+            access.authorize_gift(other_ip, "wallet", &Credentials::default()),
+            Err(AccessDenied::RateLimited { .. })
+        ));
+
+        // a cancelled gift does not count
+        let amount = access.authorize_gift(other_ip, "other wallet", &Credentials::default())?;
+        access.cancel_gift(other_ip, "other wallet", amount);
+        let _ = access.authorize_gift(other_ip, "other wallet", &Credentials::default())?;
+
+        let restarted = AccessControl::new(args(), dir.path())?;
+        assert!(matches!(
+            restarted.authorize_gift(other_ip, "wallet", &Credentials::default()),
+            Err(AccessDenied::RateLimited { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn gifts_should_require_credentials_when_configured() -> Result<()> {
+        let dir = TempDir::new()?;
+        let access = AccessControl::new(
+            AccessArgs {
+                ip_interval: 0,
+                wallet_interval: 0,
+                pow_difficulty: 8,
+                access_tokens: vec!["secret".to_string()],
+                ..args()
+            },
+            dir.path(),
+        )?;
+
+        assert_eq!(
+            access.authorize_gift(LOCALHOST, "wallet", &Credentials::default()),
+            Err(AccessDenied::CredentialsRequired)
+        );
+        let mut credentials = Credentials {
+            token: Some("guess".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            access.authorize_gift(LOCALHOST, "wallet", &credentials),
+            Err(AccessDenied::InvalidToken)
+        );
+        credentials.token = Some("secret".to_string());
+        let _ = access.authorize_gift(LOCALHOST, "wallet", &credentials)?;
+
+        let solve = |wallet: &str, other_wallet: &str| -> Result<Credentials> {
+            let challenge = access.issue_challenge()?;
+            let bytes: [u8; 32] = hex::decode(&challenge.challenge)?
+                .try_into()
+                .map_err(|_| color_eyre::eyre::eyre!("challenge to be 32 bytes"))?;
+            let nonce = (0..).find(|nonce| {
+                solves(&bytes, wallet, *nonce, challenge.difficulty)
+                    && !solves(&bytes, other_wallet, *nonce, challenge.difficulty)
+            });
+            Ok(Credentials {
+                token: None,
+                challenge: Some(challenge.challenge),
+                nonce,
+            })
+        };
+
+        let credentials = solve("wallet", "other wallet")?;
+        let _ = access.authorize_gift(LOCALHOST, "wallet", &credentials)?;
+        // a challenge can only be used once
+        assert_eq!(
+            access.authorize_gift(LOCALHOST, "wallet", &credentials),
+            Err(AccessDenied::InvalidProofOfWork)
+        );
+
+        // a solution is only valid for the wallet it has been found for
+        let credentials = solve("wallet", "other wallet")?;
+        assert_eq!(
+            access.authorize_gift(LOCALHOST, "other wallet", &credentials),
+            Err(AccessDenied::InvalidProofOfWork)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gifts_should_be_capped_per_wallet_across_restarts() -> Result<()> {
+        let dir = TempDir::new()?;
+        let args = AccessArgs {
+            ip_interval: 0,
+            wallet_interval: 0,
+            lifetime_cap: Some(NanoTokens::from(25)),
+            ..args()
+        };
+        let access = AccessControl::new(args.clone(), dir.path())?;
+
+        for expected in [10, 10] {
+            let amount = access.authorize_gift(LOCALHOST, "wallet", &Credentials::default())?;
+            assert_eq!(amount, NanoTokens::from(expected));
+            access.record_gift(LOCALHOST, "wallet", amount);
+        }
+
+        // the accounting is restored from the ledger
+        let restarted = AccessControl::new(args, dir.path())?;
+        let amount = restarted.authorize_gift(LOCALHOST, "wallet", &Credentials::default())?;
+        assert_eq!(amount, NanoTokens::from(5));
+        restarted.record_gift(LOCALHOST, "wallet", amount);
+        assert_eq!(
+            restarted.authorize_gift(LOCALHOST, "wallet", &Credentials::default()),
+            Err(AccessDenied::LifetimeCapReached)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gifts_should_follow_the_allowlist_and_denylist() -> Result<()> {
+        let dir = TempDir::new()?;
+        let wallet = |byte| {
+            MainPubkey::new(
+                bls::SecretKey::from_bytes([byte; bls::SK_SIZE])
+                    .expect("secret key to be valid")
+                    .public_key(),
+            )
+            .to_hex()
+        };
+        let (allowed, denied, other) = (wallet(1), wallet(2), wallet(3));
+        let allowlist = dir.path().join("allowlist");
+        std::fs::write(&allowlist, format!("# allowed\n{allowed}\n{denied}\n"))?;
+        let denylist = dir.path().join("denylist");
+        std::fs::write(&denylist, format!("{denied}\n"))?;
+
+        let access = AccessControl::new(
+            AccessArgs {
+                allowlist: Some(allowlist),
+                denylist: Some(denylist),
+                ..args()
+            },
+            dir.path(),
+        )?;
+        let _ = access.authorize_gift(LOCALHOST, &allowed, &Credentials::default())?;
+        for wallet in [denied, other] {
+            assert_eq!(
+                access.authorize_gift(LOCALHOST, &wallet, &Credentials::default()),
+                Err(AccessDenied::WalletNotAllowed)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn proof_of_work_should_count_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0, 0, 0xff]), 16);
+        assert_eq!(leading_zero_bits(&[0, 0x10, 0]), 11);
+        assert_eq!(leading_zero_bits(&[0x80]), 0);
+    }
+}
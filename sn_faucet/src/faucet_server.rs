@@ -6,7 +6,9 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::access_control::{AccessArgs, AccessControl, AccessDenied, Credentials};
 use crate::claim_genesis;
+use crate::maintenance::{run_balance_monitor, BalanceStatus, MaintenanceArgs};
 #[cfg(feature = "gifting")]
 use crate::send_tokens;
 #[cfg(feature = "distribution")]
@@ -18,14 +20,19 @@ use sn_client::{
     Client,
 };
 use sn_transfers::{
-    get_faucet_data_dir, wallet_lockfile_name, NanoTokens, Transfer, WALLET_DIR_NAME,
+    get_faucet_data_dir, wallet_lockfile_name, MainPubkey, NanoTokens, Transfer, WALLET_DIR_NAME,
 };
 use std::path::Path;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tokio::sync::Semaphore;
 use tracing::{debug, error, info, warn};
 use warp::{
-    http::{Response, StatusCode},
+    http::{HeaderMap, Response, StatusCode},
     Filter, Reply,
 };
 
@@ -48,6 +55,14 @@ use tokio::{fs, io::AsyncWriteExt};
 ///
 /// This will listen on port 8000 and send a transfer of tokens as response to any GET request.
 ///
+/// The same gifts are available with JSON responses under `/api`:
+/// - `GET /api/status` returns the state of the faucet balance
+/// - `GET /api/challenge` issues a proof-of-work challenge
+/// - `GET /api/gift/<key>?challenge=<challenge>&nonce=<nonce>` or `?token=<token>` sends a gift
+///
+/// A challenge is solved by a nonce for which the SHA3-256 hash of the challenge bytes, the key
+/// and the little-endian nonce starts with the requested number of zero bits.
+///
 /// # Example
 ///
 /// ```bash
@@ -62,7 +77,11 @@ use tokio::{fs, io::AsyncWriteExt};
 ///
 /// # balance should be updated
 /// ```
-pub async fn run_faucet_server(client: &Client) -> Result<()> {
+pub async fn run_faucet_server(
+    client: &Client,
+    access: AccessArgs,
+    maintenance: MaintenanceArgs,
+) -> Result<()> {
     let root_dir = get_faucet_data_dir();
     let wallet = load_account_wallet_or_create_with_mnemonic(&root_dir, None)?;
     claim_genesis(client, wallet).await.inspect_err(|_err| {
@@ -76,7 +95,7 @@ pub async fn run_faucet_server(client: &Client) -> Result<()> {
         let _ = upload_initial_data(client, &root_dir).await;
     }
 
-    startup_server(client.clone()).await
+    startup_server(client.clone(), access, maintenance).await
 }
 
 #[cfg(feature = "initial-data")]
@@ -240,7 +259,11 @@ async fn upload_downloaded_book(
     Ok(head_addresses)
 }
 
-pub async fn restart_faucet_server(client: &Client) -> Result<()> {
+pub async fn restart_faucet_server(
+    client: &Client,
+    access: AccessArgs,
+    maintenance: MaintenanceArgs,
+) -> Result<()> {
     let root_dir = get_faucet_data_dir();
     println!("Loading the previous wallet at {root_dir:?}");
     debug!("Loading the previous wallet at {root_dir:?}");
@@ -250,7 +273,7 @@ pub async fn restart_faucet_server(client: &Client) -> Result<()> {
     println!("Previous wallet loaded");
     debug!("Previous wallet loaded");
 
-    startup_server(client.clone()).await
+    startup_server(client.clone(), access, maintenance).await
 }
 
 #[cfg(feature = "distribution")]
@@ -394,35 +417,195 @@ async fn respond_to_donate_request(
     }
 }
 
+/// How the response to a gift request is formatted.
+#[derive(Debug, Clone, Copy)]
+enum ResponseFormat {
+    /// The transfer, or the error, as plain text.
+    Text,
+    /// A JSON object with a `status`, and either a `transfer` or an `error`.
+    Json,
+}
+
+/// A gift request that could not be served.
+struct GiftFailure {
+    status: StatusCode,
+    message: String,
+    retry_after: Option<Duration>,
+}
+
+impl GiftFailure {
+    fn new(status: StatusCode, message: String) -> Self {
+        Self {
+            status,
+            message,
+            retry_after: None,
+        }
+    }
+}
+
+impl From<AccessDenied> for GiftFailure {
+    fn from(denied: AccessDenied) -> Self {
+        let status = match denied {
+            AccessDenied::RateLimited { .. } | AccessDenied::TooManyChallenges => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            AccessDenied::CredentialsRequired
+            | AccessDenied::InvalidToken
+            | AccessDenied::InvalidProofOfWork
+            | AccessDenied::WalletNotAllowed
+            | AccessDenied::LifetimeCapReached => StatusCode::FORBIDDEN,
+        };
+        let retry_after = match denied {
+            AccessDenied::RateLimited { retry_after } => Some(retry_after),
+            _ => None,
+        };
+        Self {
+            status,
+            message: denied.to_string(),
+            retry_after,
+        }
+    }
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> warp::reply::Response {
+    warp::reply::with_status(warp::reply::json(&body), status).into_response()
+}
+
+fn challenge_response(access: &AccessControl) -> warp::reply::Response {
+    match access.issue_challenge() {
+        Ok(challenge) => json_response(
+            StatusCode::OK,
+            serde_json::json!({
+                "status": "ok",
+                "challenge": challenge.challenge,
+                "difficulty": challenge.difficulty,
+                "expires_in_secs": challenge.expires_in_secs,
+            }),
+        ),
+        Err(denied) => json_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            serde_json::json!({ "status": "error", "error": denied.to_string() }),
+        ),
+    }
+}
+
+/// The IP address of the requester: the last address of the X-Forwarded-For headers if they are
+/// trusted, which is the one appended by our proxy, or else that of the peer.
+///
+/// The requests whose address is unknown are all rate limited as coming from the unspecified
+/// address, rather than escaping the limit.
+fn requester_ip(access: &AccessControl, remote: Option<SocketAddr>, headers: &HeaderMap) -> IpAddr {
+    let remote_ip = remote.map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |addr| addr.ip());
+    if !access.trust_forwarded_for() {
+        return remote_ip;
+    }
+    // the addresses before the last one are set by the client, and cannot be trusted
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .last()
+        .and_then(|ip| ip.trim().parse().ok())
+        .unwrap_or(remote_ip)
+}
+
+async fn respond_to_gift_request(
+    client: Client,
+    key: String,
+    query: HashMap<String, String>,
+    ip: IpAddr,
+    access: Arc<AccessControl>,
+    semaphore: Arc<Semaphore>,
+    format: ResponseFormat,
+) -> std::result::Result<impl Reply, std::convert::Infallible> {
+    let result = gift(client, &key, &query, ip, &access, semaphore).await;
+
+    let response = match (format, result) {
+        (ResponseFormat::Text, Ok(transfer)) => Response::new(transfer).into_response(),
+        (ResponseFormat::Text, Err(failure)) => {
+            let mut response = Response::new(failure.message);
+            *response.status_mut() = failure.status;
+            response.into_response()
+        }
+        (ResponseFormat::Json, Ok(transfer)) => json_response(
+            StatusCode::OK,
+            serde_json::json!({ "status": "ok", "transfer": transfer }),
+        ),
+        (ResponseFormat::Json, Err(failure)) => json_response(
+            failure.status,
+            serde_json::json!({
+                "status": "error",
+                "error": failure.message,
+                "retry_after_secs": failure.retry_after.map(|retry_after| retry_after.as_secs()),
+            }),
+        ),
+    };
+    Ok(response)
+}
+
+/// Send a gift to the wallet `key`, if the access control allows it, and record it.
+async fn gift(
+    client: Client,
+    key: &str,
+    query: &HashMap<String, String>,
+    ip: IpAddr,
+    access: &AccessControl,
+    semaphore: Arc<Semaphore>,
+) -> std::result::Result<String, GiftFailure> {
+    // rate limit the wallets rather than their textual representations
+    let wallet = MainPubkey::from_hex(key)
+        .map_err(|err| {
+            GiftFailure::new(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid wallet key: {err}"),
+            )
+        })?
+        .to_hex();
+
+    let amount = access.authorize_gift(ip, &wallet, &Credentials::from_query(query))?;
+    match send_gift(client, &wallet, amount, semaphore).await {
+        Ok(transfer) => {
+            access.record_gift(ip, &wallet, amount);
+            Ok(transfer)
+        }
+        Err(failure) => {
+            access.cancel_gift(ip, &wallet, amount);
+            Err(failure)
+        }
+    }
+}
+
 #[cfg(not(feature = "gifting"))]
 #[expect(clippy::unused_async)]
-async fn respond_to_gift_request(
+async fn send_gift(
     _client: Client,
-    _key: String,
+    _key: &str,
+    _amount: NanoTokens,
     _semaphore: Arc<Semaphore>,
-) -> std::result::Result<impl Reply, std::convert::Infallible> {
-    let mut response = Response::new("Gifting not enabled".to_string());
-    *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
-
-    Ok(response)
+) -> std::result::Result<String, GiftFailure> {
+    Err(GiftFailure::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Gifting not enabled".to_string(),
+    ))
 }
 
 #[cfg(feature = "gifting")]
-async fn respond_to_gift_request(
+async fn send_gift(
     client: Client,
-    key: String,
+    key: &str,
+    amount: NanoTokens,
     semaphore: Arc<Semaphore>,
-) -> std::result::Result<impl Reply, std::convert::Infallible> {
+) -> std::result::Result<String, GiftFailure> {
     let faucet_root = get_faucet_data_dir();
 
     let from = match load_account_wallet_or_create_with_mnemonic(&faucet_root, None) {
         Ok(wallet) => wallet,
         Err(_error) => {
-            let mut response = Response::new("Could not load wallet".to_string());
-            *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
-
-            // Either opening the file or locking it failed, indicating rate limiting should occur
-            return Ok(response);
+            return Err(GiftFailure::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Could not load wallet".to_string(),
+            ));
         }
     };
 
@@ -431,31 +614,46 @@ async fn respond_to_gift_request(
     // some rate limiting
     if is_wallet_locked() || permit.is_err() {
         warn!("Rate limited request due");
-        let mut response = Response::new("Rate limited".to_string());
-        *response.status_mut() = StatusCode::TOO_MANY_REQUESTS;
-
         // Either opening the file or locking it failed, indicating rate limiting should occur
-        return Ok(response);
+        return Err(GiftFailure::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Rate limited".to_string(),
+        ));
     }
 
-    const GIFT_AMOUNT_SNT: &str = "1";
-    match send_tokens(&client, from, GIFT_AMOUNT_SNT, &key).await {
+    match send_tokens(&client, from, &amount.to_string(), key).await {
         Ok(transfer) => {
-            println!("Sent tokens to {key}");
-            debug!("Sent tokens to {key}");
-            Ok(Response::new(transfer.to_string()))
+            println!("Sent {amount} tokens to {key}");
+            debug!("Sent {amount} tokens to {key}");
+            Ok(transfer)
         }
         Err(err) => {
             eprintln!("Failed to send tokens to {key}: {err}");
             error!("Failed to send tokens to {key}: {err}");
-            Ok(Response::new(format!("Failed to send tokens: {err}")))
+            Err(GiftFailure::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to send tokens: {err}"),
+            ))
         }
     }
 }
 
-async fn startup_server(client: Client) -> Result<()> {
+async fn startup_server(
+    client: Client,
+    access: AccessArgs,
+    maintenance: MaintenanceArgs,
+) -> Result<()> {
     // Create a semaphore with a single permit
     let semaphore = Arc::new(Semaphore::new(1));
+    let access = Arc::new(AccessControl::new(access, &get_faucet_data_dir())?);
+
+    let balance_status = Arc::new(Mutex::new(BalanceStatus::default()));
+    let _handle = tokio::spawn(run_balance_monitor(
+        client.clone(),
+        maintenance,
+        Arc::clone(&semaphore),
+        Arc::clone(&balance_status),
+    ));
 
     #[expect(unused)]
     let mut balances = HashMap::<String, NanoTokens>::new();
@@ -474,6 +672,7 @@ async fn startup_server(client: Client) -> Result<()> {
     }
 
     let gift_client = client.clone();
+    let api_gift_client = client.clone();
     let donation_client = client.clone();
     let donation_addr_client = client.clone();
     let donation_semaphore = Arc::clone(&semaphore);
@@ -496,19 +695,84 @@ async fn startup_server(client: Client) -> Result<()> {
             respond_to_distribution_request(client, query, balances.clone(), semaphore)
         });
 
+    // The requester of a gift: the credentials in the query, and the IP address
+    let requester = {
+        let access = Arc::clone(&access);
+        warp::query::<HashMap<String, String>>()
+            .and(warp::addr::remote())
+            .and(warp::header::headers_cloned())
+            .map(move |query, remote, headers: HeaderMap| {
+                (query, requester_ip(&access, remote, &headers))
+            })
+    };
+
     // GET /key
+    let gift_semaphore = Arc::clone(&semaphore);
+    let gift_access = Arc::clone(&access);
     let gift_route = warp::get()
         .and(warp::path!(String))
         .map(|query| {
             debug!("Gift distribution request: {query}");
             query
         })
-        .and_then(move |key| {
+        .and(requester.clone())
+        .and_then(move |key, (query, ip)| {
             let client = gift_client.clone();
-            let semaphore = Arc::clone(&semaphore);
+            let semaphore = Arc::clone(&gift_semaphore);
+            let access = Arc::clone(&gift_access);
+
+            respond_to_gift_request(
+                client,
+                key,
+                query,
+                ip,
+                access,
+                semaphore,
+                ResponseFormat::Text,
+            )
+        });
 
-            respond_to_gift_request(client, key, semaphore)
+    // GET /api/challenge
+    let challenge_access = Arc::clone(&access);
+    let api_challenge_route = warp::get()
+        .and(warp::path!("api" / "challenge"))
+        .map(move || {
+            debug!("Proof-of-work challenge request");
+            challenge_response(&challenge_access)
+        });
+
+    // GET /api/gift/key
+    let api_gift_route = warp::get()
+        .and(warp::path!("api" / "gift" / String))
+        .map(|query| {
+            debug!("Gift distribution request from the API: {query}");
+            query
+        })
+        .and(requester)
+        .and_then(move |key, (query, ip)| {
+            let client = api_gift_client.clone();
+            let semaphore = Arc::clone(&semaphore);
+            let access = Arc::clone(&access);
+
+            respond_to_gift_request(
+                client,
+                key,
+                query,
+                ip,
+                access,
+                semaphore,
+                ResponseFormat::Json,
+            )
         });
+    // GET /api/status
+    let api_status_route = warp::get().and(warp::path!("api" / "status")).map(move || {
+        let status = balance_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        warp::reply::json(&status)
+    });
+    let api_routes = api_status_route.or(api_challenge_route).or(api_gift_route);
 
     // GET /donate
     let donation_addr = warp::get().and(warp::path("donate")).and_then(move || {
@@ -539,6 +803,7 @@ async fn startup_server(client: Client) -> Result<()> {
     #[cfg(feature = "distribution")]
     warp::serve(
         distribution_route
+            .or(api_routes)
             .or(donation_route)
             .or(donation_addr)
             .or(gift_route),
@@ -548,9 +813,14 @@ async fn startup_server(client: Client) -> Result<()> {
     .await;
 
     #[cfg(not(feature = "distribution"))]
-    warp::serve(donation_route.or(donation_addr).or(gift_route))
-        .run(([0, 0, 0, 0], 8000))
-        .await;
+    warp::serve(
+        api_routes
+            .or(donation_route)
+            .or(donation_addr)
+            .or(gift_route),
+    )
+    .run(([0, 0, 0, 0], 8000))
+    .await;
 
     debug!("Server closed");
     Ok(())
@@ -6,16 +6,20 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod access_control;
 mod faucet_server;
 #[cfg(feature = "initial-data")]
 pub(crate) mod gutenberger;
+mod maintenance;
 #[cfg(feature = "distribution")]
 mod token_distribution;
 
+use access_control::AccessArgs;
 use clap::{Parser, Subcommand};
 use color_eyre::eyre::{bail, eyre, Result};
 use faucet_server::{restart_faucet_server, run_faucet_server};
 use indicatif::ProgressBar;
+use maintenance::MaintenanceArgs;
 use sn_client::{
     acc_packet::load_account_wallet_or_create_with_mnemonic, fund_faucet_from_genesis_wallet, send,
     Client, ClientEvent, ClientEventsBroadcaster, ClientEventsReceiver,
@@ -186,7 +190,17 @@ enum SubCmd {
     },
     /// Starts an http server that will send tokens to anyone who requests them.
     /// curl http://localhost:8000/your-hex-encoded-wallet-public-address
-    Server,
+    ///
+    /// Gifts are rate limited per IP address and per wallet, and can require an access token or
+    /// a proof-of-work solution, see the /api routes.
+    ///
+    /// The balance of the faucet is monitored, and can be topped up from a treasury wallet.
+    Server {
+        #[command(flatten)]
+        access: AccessArgs,
+        #[command(flatten)]
+        maintenance: MaintenanceArgs,
+    },
     /// Restart the faucet_server from the last breaking point.
     ///
     /// Before firing this cmd, ensure:
@@ -195,7 +209,12 @@ enum SubCmd {
     ///   3, The old `wallet` and `wallet.lock` files shall also be removed.
     /// The command will create a new wallet with the same key,
     /// then deposit all valid cash_notes into wallet and startup the faucet_server.
-    RestartServer,
+    RestartServer {
+        #[command(flatten)]
+        access: AccessArgs,
+        #[command(flatten)]
+        maintenance: MaintenanceArgs,
+    },
 }
 
 async fn faucet_cmds(cmds: SubCmd, client: &Client, funded_wallet: HotWallet) -> Result<()> {
@@ -206,13 +225,19 @@ async fn faucet_cmds(cmds: SubCmd, client: &Client, funded_wallet: HotWallet) ->
         SubCmd::Send { amount, to } => {
             send_tokens(client, funded_wallet, &amount, &to).await?;
         }
-        SubCmd::Server => {
+        SubCmd::Server {
+            access,
+            maintenance,
+        } => {
             // shouldn't return except on error
-            run_faucet_server(client).await?;
+            run_faucet_server(client, access, maintenance).await?;
         }
-        SubCmd::RestartServer => {
+        SubCmd::RestartServer {
+            access,
+            maintenance,
+        } => {
             // shouldn't return except on error
-            restart_faucet_server(client).await?;
+            restart_faucet_server(client, access, maintenance).await?;
         }
     }
     Ok(())
@@ -0,0 +1,243 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Monitoring the balance of the faucet wallet, so that the faucet does not silently run dry.
+//!
+//! The balance is checked periodically, alerting when it is below a threshold. When a treasury
+//! wallet is configured, the faucet wallet is then topped up from it.
+
+use clap::Args;
+use color_eyre::eyre::{eyre, Result};
+use serde::Serialize;
+use sn_client::{acc_packet::load_account_wallet_or_create_with_mnemonic, send, Client};
+use sn_transfers::{get_faucet_data_dir, HotWallet, NanoTokens};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::sync::Semaphore;
+use tracing::{debug, error, info, warn};
+
+/// The options of the balance monitoring of the faucet.
+#[derive(Args, Debug, Clone)]
+pub struct MaintenanceArgs {
+    /// The number of seconds between two checks of the faucet balance, at least 1.
+    #[clap(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    pub balance_check_interval: u64,
+    /// The balance below which the faucet alerts, and tops up when a treasury is configured.
+    #[clap(long, value_parser = NanoTokens::from_str)]
+    pub low_balance_threshold: Option<NanoTokens>,
+    /// The root directory of the treasury wallet the faucet is topped up from.
+    #[clap(long)]
+    pub treasury_dir: Option<PathBuf>,
+    /// The amount of tokens sent from the treasury by each top up.
+    #[clap(long, value_parser = NanoTokens::from_str, default_value = "1000")]
+    pub top_up_amount: NanoTokens,
+}
+
+/// The state of the faucet balance, as served by the status route.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BalanceStatus {
+    /// The balance at the last check, in nanos.
+    pub balance: Option<u64>,
+    pub low_balance_threshold: Option<u64>,
+    pub is_low: bool,
+    /// Seconds since the Unix epoch.
+    pub last_check: Option<u64>,
+    pub last_top_up: Option<u64>,
+    pub top_ups: u64,
+    pub failed_top_ups: u64,
+}
+
+/// What to do about the balance of the faucet.
+#[derive(Debug, PartialEq, Eq)]
+enum BalanceCheck {
+    Healthy,
+    /// The balance is low, and there is no treasury to top up from.
+    Low,
+    TopUp(NanoTokens),
+}
+
+fn check_balance(balance: NanoTokens, args: &MaintenanceArgs) -> BalanceCheck {
+    match args.low_balance_threshold {
+        Some(threshold) if balance < threshold => {
+            if args.treasury_dir.is_some() && !args.top_up_amount.is_zero() {
+                BalanceCheck::TopUp(args.top_up_amount)
+            } else {
+                BalanceCheck::Low
+            }
+        }
+        _ => BalanceCheck::Healthy,
+    }
+}
+
+fn secs_since_epoch() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+/// Check the balance of the faucet periodically, topping it up when low, forever.
+///
+/// The wallet of the faucet is only modified while holding a permit of the `semaphore`, which is
+/// the one guarding the gifts.
+pub async fn run_balance_monitor(
+    client: Client,
+    args: MaintenanceArgs,
+    semaphore: Arc<Semaphore>,
+    status: Arc<Mutex<BalanceStatus>>,
+) {
+    info!("Monitoring the faucet balance with {args:?}");
+    // `interval` panics on a zero period
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(args.balance_check_interval.max(1)));
+    loop {
+        let _ = interval.tick().await;
+        if let Err(err) = maintain_balance(&client, &args, &semaphore, &status).await {
+            error!("Failed to maintain the faucet balance: {err}");
+            eprintln!("Failed to maintain the faucet balance: {err}");
+        }
+    }
+}
+
+async fn maintain_balance(
+    client: &Client,
+    args: &MaintenanceArgs,
+    semaphore: &Semaphore,
+    status: &Mutex<BalanceStatus>,
+) -> Result<()> {
+    let _permit = semaphore.acquire().await?;
+
+    let faucet_root = get_faucet_data_dir();
+    let mut faucet_wallet = load_account_wallet_or_create_with_mnemonic(&faucet_root, None)?;
+    faucet_wallet.try_load_cash_notes()?;
+    let balance = faucet_wallet.balance();
+    let check = check_balance(balance, args);
+    debug!("Faucet balance: {balance}, {check:?}");
+
+    update_status(status, |status| {
+        status.balance = Some(balance.as_nano());
+        status.low_balance_threshold = args.low_balance_threshold.map(NanoTokens::as_nano);
+        status.is_low = check != BalanceCheck::Healthy;
+        status.last_check = secs_since_epoch();
+    });
+
+    let amount = match check {
+        BalanceCheck::Healthy => return Ok(()),
+        BalanceCheck::Low => {
+            warn!("Faucet balance {balance} is low, and there is no treasury to top up from");
+            eprintln!("ALERT: faucet balance {balance} is low");
+            return Ok(());
+        }
+        BalanceCheck::TopUp(amount) => amount,
+    };
+
+    warn!("Faucet balance {balance} is low, topping up {amount} from the treasury");
+    println!("Faucet balance {balance} is low, topping up {amount} from the treasury");
+    match top_up(client, args, &mut faucet_wallet, amount).await {
+        Ok(()) => {
+            let new_balance = faucet_wallet.balance();
+            info!("Faucet topped up, from {balance} to {new_balance}");
+            println!("Faucet topped up, from {balance} to {new_balance}");
+            update_status(status, |status| {
+                status.balance = Some(new_balance.as_nano());
+                status.is_low = check_balance(new_balance, args) != BalanceCheck::Healthy;
+                status.last_top_up = secs_since_epoch();
+                status.top_ups += 1;
+            });
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("ALERT: failed to top up the faucet balance {balance}: {err}");
+            update_status(status, |status| status.failed_top_ups += 1);
+            Err(err)
+        }
+    }
+}
+
+async fn top_up(
+    client: &Client,
+    args: &MaintenanceArgs,
+    faucet_wallet: &mut HotWallet,
+    amount: NanoTokens,
+) -> Result<()> {
+    let treasury_dir = args
+        .treasury_dir
+        .as_ref()
+        .ok_or_else(|| eyre!("No treasury wallet configured"))?;
+    let mut treasury = HotWallet::try_load_from(treasury_dir)?;
+    treasury.try_load_cash_notes()?;
+    if treasury.balance() < amount {
+        return Err(eyre!(
+            "The treasury balance {} is below the top up amount {amount}",
+            treasury.balance()
+        ));
+    }
+
+    let cash_note = send(treasury, amount, faucet_wallet.address(), client, true).await?;
+    faucet_wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+    Ok(())
+}
+
+fn update_status(status: &Mutex<BalanceStatus>, update: impl FnOnce(&mut BalanceStatus)) {
+    let mut status = status
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    update(&mut status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_balance_should_be_topped_up_when_possible() {
+        let mut args = MaintenanceArgs {
+            balance_check_interval: 300,
+            low_balance_threshold: None,
+            treasury_dir: None,
+            top_up_amount: NanoTokens::from(100),
+        };
+        assert_eq!(
+            check_balance(NanoTokens::zero(), &args),
+            BalanceCheck::Healthy
+        );
+
+        args.low_balance_threshold = Some(NanoTokens::from(10));
+        assert_eq!(
+            check_balance(NanoTokens::from(10), &args),
+            BalanceCheck::Healthy
+        );
+        assert_eq!(check_balance(NanoTokens::from(9), &args), BalanceCheck::Low);
+
+        args.treasury_dir = Some(PathBuf::from("treasury"));
+        assert_eq!(
+            check_balance(NanoTokens::from(9), &args),
+            BalanceCheck::TopUp(NanoTokens::from(100))
+        );
+    }
+
+    #[test]
+    fn balance_check_interval_should_not_be_zero() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            maintenance: MaintenanceArgs,
+        }
+
+        assert!(Cli::try_parse_from(["faucet", "--balance-check-interval", "0"]).is_err());
+        let cli = Cli::try_parse_from(["faucet", "--balance-check-interval", "1"])
+            .expect("an interval of 1 to be valid");
+        assert_eq!(cli.maintenance.balance_check_interval, 1);
+    }
+}
@@ -0,0 +1,957 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The filesystem over the Folders API.
+//!
+//! Every folder is a register listing the metadata of its entries: a file entry holds the data map
+//! chunk of the file, a folder entry the address of the register of the subfolder. Folders are
+//! retrieved and listed as they are looked up, and the inodes given to their entries last as long
+//! as the mount. Reads fetch only the chunks the range read spans. A file written to is first
+//! downloaded to a spool file on disk, which the writes go to, and written back to the Network from
+//! there when the file is flushed, released or synced: no file is ever held in memory whole.
+
+use bytes::Bytes;
+use color_eyre::Result;
+use fuser::{
+    FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty,
+    ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow,
+};
+use libc::{c_int, EEXIST, EINVAL, EIO, EISDIR, ENOENT, ENOTDIR, ENOTEMPTY};
+use sn_client::{
+    acc_packet::load_account_wallet_or_create_with_mnemonic,
+    protocol::{
+        storage::{Chunk, RegisterAddress},
+        NetworkAddress,
+    },
+    registers::EntryHash,
+    Client, FilesApi, FilesDownload, FolderEntry, FoldersApi, UploadCfg, Uploader, WalletClient,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    fs::OpenOptions,
+    io,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tempfile::TempPath;
+use tokio::runtime::Handle;
+
+/// How long the kernel may cache the attributes and entries given to it.
+const TTL: Duration = Duration::from_secs(1);
+/// The inode of the mounted folder, as expected by the kernel.
+const ROOT_INODE: u64 = 1;
+const BLOCK_SIZE: u32 = 512;
+/// The directory of the spool files of the files being written, in the client data directory.
+const SPOOL_DIR: &str = "mount_spool";
+
+type FsResult<T> = std::result::Result<T, c_int>;
+
+enum NodeKind {
+    Folder {
+        address: RegisterAddress,
+        /// The folder, once retrieved from the Network
+        api: Option<Box<FoldersApi>>,
+        /// The inodes of the entries of the folder by name, once listed
+        children: Option<BTreeMap<String, u64>>,
+        /// Whether the register of the folder is yet to be paid for
+        is_new: bool,
+    },
+    File {
+        /// The data map chunk of the content last written to the Network
+        head: Option<Chunk>,
+        size: u64,
+        /// The spool file holding the content, while the file is being written
+        spool: Option<TempPath>,
+        /// Whether the content changed since it was last written back
+        dirty: bool,
+    },
+}
+
+struct Node {
+    parent: u64,
+    name: String,
+    /// The entry of the node in its parent folder, once written to it
+    entry: Option<EntryHash>,
+    kind: NodeKind,
+    mtime: SystemTime,
+}
+
+pub(crate) struct SafeFs {
+    client: Client,
+    root_dir: PathBuf,
+    spool_dir: PathBuf,
+    /// The runtime the filesystem calls block on, from the thread of the FUSE session
+    runtime: Handle,
+    nodes: HashMap<u64, Node>,
+    next_inode: u64,
+}
+
+impl SafeFs {
+    /// Retrieves the folder at `address` to be mounted. To be called from within the runtime the
+    /// filesystem calls then block on.
+    pub(crate) async fn new(
+        client: Client,
+        root_dir: PathBuf,
+        address: RegisterAddress,
+    ) -> Result<Self> {
+        let api = FoldersApi::retrieve(client.clone(), &root_dir, address).await?;
+        let spool_dir = root_dir.join(SPOOL_DIR);
+        std::fs::create_dir_all(&spool_dir)?;
+        let root = Node {
+            parent: ROOT_INODE,
+            name: String::new(),
+            entry: None,
+            kind: NodeKind::Folder {
+                address,
+                api: Some(Box::new(api)),
+                children: None,
+                is_new: false,
+            },
+            mtime: SystemTime::now(),
+        };
+        Ok(Self {
+            client,
+            root_dir,
+            spool_dir,
+            runtime: Handle::current(),
+            nodes: HashMap::from([(ROOT_INODE, root)]),
+            next_inode: ROOT_INODE + 1,
+        })
+    }
+
+    fn node(&self, ino: u64) -> FsResult<&Node> {
+        self.nodes.get(&ino).ok_or(ENOENT)
+    }
+
+    fn attr(&self, req: &Request<'_>, ino: u64) -> FsResult<FileAttr> {
+        let node = self.node(ino)?;
+        let (kind, size, perm, nlink) = match &node.kind {
+            NodeKind::Folder { .. } => (FileType::Directory, 0, 0o755, 2),
+            NodeKind::File { size, .. } => (FileType::RegularFile, *size, 0o644, 1),
+        };
+        Ok(FileAttr {
+            ino,
+            size,
+            blocks: size.div_ceil(u64::from(BLOCK_SIZE)),
+            atime: node.mtime,
+            mtime: node.mtime,
+            ctime: node.mtime,
+            crtime: node.mtime,
+            kind,
+            perm,
+            nlink,
+            uid: req.uid(),
+            gid: req.gid(),
+            rdev: 0,
+            blksize: BLOCK_SIZE,
+            flags: 0,
+        })
+    }
+
+    /// Adds a node to its parent folder, if listed already.
+    fn insert_node(
+        &mut self,
+        parent: u64,
+        name: String,
+        entry: Option<EntryHash>,
+        kind: NodeKind,
+    ) -> u64 {
+        let ino = self.next_inode;
+        self.next_inode += 1;
+        if let Some(Node {
+            kind:
+                NodeKind::Folder {
+                    children: Some(children),
+                    ..
+                },
+            ..
+        }) = self.nodes.get_mut(&parent)
+        {
+            let _ = children.insert(name.clone(), ino);
+        }
+        let _ = self.nodes.insert(
+            ino,
+            Node {
+                parent,
+                name,
+                entry,
+                kind,
+                mtime: SystemTime::now(),
+            },
+        );
+        ino
+    }
+
+    /// Removes a node from its parent folder.
+    fn detach_node(&mut self, ino: u64) -> Option<Node> {
+        let node = self.nodes.remove(&ino)?;
+        if let Some(Node {
+            kind:
+                NodeKind::Folder {
+                    children: Some(children),
+                    ..
+                },
+            ..
+        }) = self.nodes.get_mut(&node.parent)
+        {
+            let _ = children.remove(&node.name);
+        }
+        Some(node)
+    }
+
+    /// The folder, retrieved from the Network the first time.
+    fn folder_api(&mut self, ino: u64) -> FsResult<&mut FoldersApi> {
+        let node = self.nodes.get_mut(&ino).ok_or(ENOENT)?;
+        let NodeKind::Folder { address, api, .. } = &mut node.kind else {
+            return Err(ENOTDIR);
+        };
+        if api.is_none() {
+            let retrieved = self
+                .runtime
+                .block_on(FoldersApi::retrieve(
+                    self.client.clone(),
+                    &self.root_dir,
+                    *address,
+                ))
+                .map_err(io_error)?;
+            *api = Some(Box::new(retrieved));
+        }
+        api.as_deref_mut().ok_or(EIO)
+    }
+
+    /// The inodes of the entries of the folder by name, listed from the Network the first time.
+    fn children(&mut self, ino: u64) -> FsResult<BTreeMap<String, u64>> {
+        if let NodeKind::Folder {
+            children: Some(children),
+            ..
+        } = &self.node(ino)?.kind
+        {
+            return Ok(children.clone());
+        }
+
+        let runtime = self.runtime.clone();
+        let entries = runtime
+            .block_on(self.folder_api(ino)?.entries())
+            .map_err(io_error)?;
+        let mut files_download =
+            FilesDownload::new(FilesApi::new(self.client.clone(), self.root_dir.clone()));
+        let mut listed = BTreeMap::new();
+        for (entry, (_, metadata)) in entries {
+            let kind = match metadata.content {
+                FolderEntry::File(head) => NodeKind::File {
+                    size: runtime.block_on(file_size(&mut files_download, &head)),
+                    head: Some(head),
+                    spool: None,
+                    dirty: false,
+                },
+                FolderEntry::Folder(address) => NodeKind::Folder {
+                    address,
+                    api: None,
+                    children: None,
+                    is_new: false,
+                },
+            };
+            let child = self.insert_node(ino, metadata.name.clone(), Some(entry), kind);
+            let _ = listed.insert(metadata.name, child);
+        }
+
+        if let Some(Node {
+            kind: NodeKind::Folder { children, .. },
+            ..
+        }) = self.nodes.get_mut(&ino)
+        {
+            *children = Some(listed.clone());
+        }
+        Ok(listed)
+    }
+
+    fn child(&mut self, parent: u64, name: &OsStr) -> FsResult<u64> {
+        let name = name.to_str().ok_or(ENOENT)?;
+        self.children(parent)?.get(name).copied().ok_or(ENOENT)
+    }
+
+    /// Adds a node named `name` to the folder, failing if the name is taken.
+    fn create_node(&mut self, parent: u64, name: &OsStr, kind: NodeKind) -> FsResult<u64> {
+        let name = name.to_str().ok_or(EINVAL)?;
+        if self.children(parent)?.contains_key(name) {
+            return Err(EEXIST);
+        }
+        Ok(self.insert_node(parent, name.to_string(), None, kind))
+    }
+
+    /// An empty spool file.
+    fn new_spool(&self) -> FsResult<TempPath> {
+        tempfile::NamedTempFile::new_in(&self.spool_dir)
+            .map(tempfile::NamedTempFile::into_temp_path)
+            .map_err(io_error)
+    }
+
+    /// The spool file of the file, its content downloaded to it the first time.
+    fn spool(&mut self, ino: u64) -> FsResult<PathBuf> {
+        let head = match &self.node(ino)?.kind {
+            NodeKind::File {
+                spool: Some(spool), ..
+            } => return Ok(spool.to_path_buf()),
+            NodeKind::File { head, .. } => head.clone(),
+            NodeKind::Folder { .. } => return Err(EISDIR),
+        };
+        let spool = self.new_spool()?;
+        if let Some(head) = head {
+            let files_api = FilesApi::new(self.client.clone(), self.root_dir.clone());
+            self.runtime
+                .block_on(FilesDownload::new(files_api).download_file_to_path(
+                    *head.address(),
+                    Some(head),
+                    spool.to_path_buf(),
+                ))
+                .map_err(io_error)?;
+        }
+        let path = spool.to_path_buf();
+        if let Some(Node {
+            kind: NodeKind::File { spool: slot, .. },
+            ..
+        }) = self.nodes.get_mut(&ino)
+        {
+            *slot = Some(spool);
+        }
+        Ok(path)
+    }
+
+    /// Records a change of the content of the file, to be written back.
+    fn modified(&mut self, ino: u64, new_size: u64) {
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            node.mtime = SystemTime::now();
+            if let NodeKind::File { size, dirty, .. } = &mut node.kind {
+                *size = new_size;
+                *dirty = true;
+            }
+        }
+    }
+
+    fn truncate(&mut self, ino: u64, size: u64) -> FsResult<()> {
+        let spool = self.spool(ino)?;
+        set_spool_len(&spool, size).map_err(io_error)?;
+        self.modified(ino, size);
+        Ok(())
+    }
+
+    fn write_at(&mut self, ino: u64, offset: i64, data: &[u8]) -> FsResult<()> {
+        let offset = u64::try_from(offset).map_err(|_| EINVAL)?;
+        let end = offset.checked_add(data.len() as u64).ok_or(EINVAL)?;
+        let size = match &self.node(ino)?.kind {
+            NodeKind::File { size, .. } => *size,
+            NodeKind::Folder { .. } => return Err(EISDIR),
+        };
+        let spool = self.spool(ino)?;
+        write_spool(&spool, offset, data).map_err(io_error)?;
+        self.modified(ino, size.max(end));
+        Ok(())
+    }
+
+    /// Reads `size` bytes of the file from `offset`, from its spool file if written to, or else
+    /// from the chunks of the Network the range spans.
+    fn read_at(&mut self, ino: u64, offset: i64, size: u32) -> FsResult<Vec<u8>> {
+        let (head, spool, file_size) = match &self.node(ino)?.kind {
+            NodeKind::File {
+                head, spool, size, ..
+            } => (
+                head.clone(),
+                spool.as_ref().map(|spool| spool.to_path_buf()),
+                *size,
+            ),
+            NodeKind::Folder { .. } => return Err(EISDIR),
+        };
+        let Some((offset, length)) = range_to_read(offset, size, file_size) else {
+            return Ok(vec![]);
+        };
+        if let Some(spool) = spool {
+            return read_spool(&spool, offset, length).map_err(io_error);
+        }
+        let Some(head) = head else {
+            return Ok(vec![]);
+        };
+        let position = usize::try_from(offset).map_err(|_| EINVAL)?;
+        let files_api = FilesApi::new(self.client.clone(), self.root_dir.clone());
+        self.runtime
+            .block_on(FilesDownload::new(files_api).download_range(head, position, length))
+            .map(|bytes| bytes.to_vec())
+            .map_err(io_error)
+    }
+
+    /// Drops the spool file of the file once written back, its content then read from the
+    /// Network again.
+    fn drop_spool(&mut self, ino: u64) {
+        if let Some(Node {
+            kind:
+                NodeKind::File {
+                    spool,
+                    dirty: false,
+                    ..
+                },
+            ..
+        }) = self.nodes.get_mut(&ino)
+        {
+            *spool = None;
+        }
+    }
+
+    /// Writes the content of the file back to the Network if it changed, then its entry to its
+    /// folder.
+    fn write_back(&mut self, ino: u64) -> FsResult<()> {
+        let node = self.node(ino)?;
+        let (parent, name, entry, spool, size) = match &node.kind {
+            NodeKind::File {
+                spool: Some(spool),
+                size,
+                dirty: true,
+                ..
+            } => (
+                node.parent,
+                node.name.clone(),
+                node.entry,
+                spool.to_path_buf(),
+                *size,
+            ),
+            _ => return Ok(()),
+        };
+
+        let runtime = self.runtime.clone();
+        let head = runtime
+            .block_on(upload(&self.client, &self.root_dir, &spool, size))
+            .map_err(io_error)?;
+        let api = self.folder_api(parent)?;
+        let (new_entry, _, _) = match entry {
+            Some(entry) => api.replace_file(entry, name.into(), head.clone(), None),
+            None => api.add_file(name.into(), head.clone(), None),
+        }
+        .map_err(io_error)?;
+        self.sync_folder(parent)?;
+
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            node.entry = Some(new_entry);
+            if let NodeKind::File {
+                head: node_head,
+                dirty,
+                ..
+            } = &mut node.kind
+            {
+                *node_head = Some(head);
+                *dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Pays for the new entries of the folder, and for its register if new, then syncs it with the
+    /// Network.
+    fn sync_folder(&mut self, ino: u64) -> FsResult<()> {
+        let is_new = match &self.node(ino)?.kind {
+            NodeKind::Folder { is_new, .. } => *is_new,
+            NodeKind::File { .. } => return Err(ENOTDIR),
+        };
+        let runtime = self.runtime.clone();
+        let client = self.client.clone();
+        let root_dir = self.root_dir.clone();
+        let api = self.folder_api(ino)?;
+        runtime
+            .block_on(async {
+                let mut addresses: Vec<NetworkAddress> =
+                    api.meta_addrs_to_pay().into_iter().collect();
+                if is_new {
+                    addresses.push(api.as_net_addr());
+                }
+                if !addresses.is_empty() {
+                    let wallet = load_account_wallet_or_create_with_mnemonic(&root_dir, None)?;
+                    let _ = WalletClient::new(client, wallet)
+                        .pay_for_storage(addresses.into_iter())
+                        .await?;
+                }
+                api.sync(UploadCfg::default()).await?;
+                Ok::<_, color_eyre::Report>(())
+            })
+            .map_err(io_error)?;
+
+        if let Some(Node {
+            kind: NodeKind::Folder { is_new, .. },
+            ..
+        }) = self.nodes.get_mut(&ino)
+        {
+            *is_new = false;
+        }
+        Ok(())
+    }
+
+    fn make_folder(&mut self, parent: u64, name: &OsStr) -> FsResult<u64> {
+        let api = FoldersApi::new(self.client.clone(), &self.root_dir, None).map_err(io_error)?;
+        let address = *api.address();
+        let ino = self.create_node(
+            parent,
+            name,
+            NodeKind::Folder {
+                address,
+                api: Some(Box::new(api)),
+                children: Some(BTreeMap::new()),
+                is_new: true,
+            },
+        )?;
+        // The new register is created on the Network before it is added to its parent.
+        let added = self.sync_folder(ino).and_then(|()| {
+            let (entry, _, _) = self
+                .folder_api(parent)?
+                .add_folder(name.to_os_string(), address, None)
+                .map_err(io_error)?;
+            self.sync_folder(parent)?;
+            Ok(entry)
+        });
+        match added {
+            Ok(entry) => {
+                if let Some(node) = self.nodes.get_mut(&ino) {
+                    node.entry = Some(entry);
+                }
+                Ok(ino)
+            }
+            Err(errno) => {
+                let _ = self.detach_node(ino);
+                Err(errno)
+            }
+        }
+    }
+
+    fn remove_node(&mut self, parent: u64, name: &OsStr, is_folder: bool) -> FsResult<()> {
+        let ino = self.child(parent, name)?;
+        match (&self.node(ino)?.kind, is_folder) {
+            (NodeKind::Folder { .. }, false) => return Err(EISDIR),
+            (NodeKind::File { .. }, true) => return Err(ENOTDIR),
+            _ => {}
+        }
+        if is_folder && !self.children(ino)?.is_empty() {
+            return Err(ENOTEMPTY);
+        }
+        // A file created but not yet written back has no entry in its folder.
+        if let Some(entry) = self.node(ino)?.entry {
+            self.folder_api(parent)?
+                .remove_item(entry)
+                .map_err(io_error)?;
+            self.sync_folder(parent)?;
+        }
+        let _ = self.detach_node(ino);
+        Ok(())
+    }
+
+    fn rename_node(
+        &mut self,
+        parent: u64,
+        name: &OsStr,
+        new_parent: u64,
+        new_name: &OsStr,
+    ) -> FsResult<()> {
+        let ino = self.child(parent, name)?;
+        let new_name_str = new_name.to_str().ok_or(EINVAL)?.to_string();
+        // The file is moved with its latest content.
+        self.write_back(ino)?;
+
+        if let Ok(target) = self.child(new_parent, new_name) {
+            if target == ino {
+                return Ok(());
+            }
+            let is_folder = matches!(self.node(target)?.kind, NodeKind::Folder { .. });
+            self.remove_node(new_parent, new_name, is_folder)?;
+        }
+
+        let node = self.node(ino)?;
+        let old_entry = node.entry;
+        let content = match &node.kind {
+            NodeKind::File {
+                head: Some(head), ..
+            } => FolderEntry::File(head.clone()),
+            // Written back above, a file always has its content on the Network.
+            NodeKind::File { head: None, .. } => return Err(EIO),
+            NodeKind::Folder { address, .. } => FolderEntry::Folder(*address),
+        };
+
+        // The entry is added to its new folder before it is removed from its old one, so it is
+        // never lost in between.
+        let api = self.folder_api(new_parent)?;
+        let (new_entry, _, _) = match content {
+            FolderEntry::File(head) => api.add_file(new_name.to_os_string(), head, None),
+            FolderEntry::Folder(address) => api.add_folder(new_name.to_os_string(), address, None),
+        }
+        .map_err(io_error)?;
+        self.sync_folder(new_parent)?;
+        if let Some(entry) = old_entry {
+            self.folder_api(parent)?
+                .remove_item(entry)
+                .map_err(io_error)?;
+            self.sync_folder(parent)?;
+        }
+
+        if let Some(mut node) = self.detach_node(ino) {
+            node.parent = new_parent;
+            node.name = new_name_str.clone();
+            node.entry = Some(new_entry);
+            let _ = self.nodes.insert(ino, node);
+            if let Some(Node {
+                kind:
+                    NodeKind::Folder {
+                        children: Some(children),
+                        ..
+                    },
+                ..
+            }) = self.nodes.get_mut(&new_parent)
+            {
+                let _ = children.insert(new_name_str, ino);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Filesystem for SafeFs {
+    fn lookup(&mut self, req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        match self.child(parent, name).and_then(|ino| self.attr(req, ino)) {
+            Ok(attr) => reply.entry(&TTL, &attr, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn getattr(&mut self, req: &Request<'_>, ino: u64, reply: ReplyAttr) {
+        match self.attr(req, ino) {
+            Ok(attr) => reply.attr(&TTL, &attr),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn setattr(
+        &mut self,
+        req: &Request<'_>,
+        ino: u64,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        // Only the size can be changed: the metadata of an entry holds nothing else.
+        let result = match size {
+            Some(size) => self.truncate(ino, size),
+            None => Ok(()),
+        };
+        match result.and_then(|()| self.attr(req, ino)) {
+            Ok(attr) => reply.attr(&TTL, &attr),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn mkdir(
+        &mut self,
+        req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        match self
+            .make_folder(parent, name)
+            .and_then(|ino| self.attr(req, ino))
+        {
+            Ok(attr) => reply.entry(&TTL, &attr, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn unlink(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        match self.remove_node(parent, name, false) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn rmdir(&mut self, _req: &Request<'_>, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        match self.remove_node(parent, name, true) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn rename(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        _flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        match self.rename_node(parent, name, newparent, newname) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn open(&mut self, _req: &Request<'_>, ino: u64, _flags: i32, reply: ReplyOpen) {
+        match self.node(ino) {
+            Ok(_) => reply.opened(0, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        match self.read_at(ino, offset, size) {
+            Ok(data) => reply.data(&data),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        match self.write_at(ino, offset, data) {
+            Ok(()) => reply.written(data.len() as u32),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn flush(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _lock_owner: u64,
+        reply: ReplyEmpty,
+    ) {
+        match self.write_back(ino) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        match self.write_back(ino) {
+            Ok(()) => {
+                self.drop_spool(ino);
+                reply.ok()
+            }
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn fsync(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        _datasync: bool,
+        reply: ReplyEmpty,
+    ) {
+        match self.write_back(ino) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: ReplyDirectory,
+    ) {
+        let children = match self.children(ino) {
+            Ok(children) => children,
+            Err(errno) => return reply.error(errno),
+        };
+        let parent = self.nodes.get(&ino).map_or(ino, |node| node.parent);
+        let mut entries = vec![
+            (ino, FileType::Directory, ".".to_string()),
+            (parent, FileType::Directory, "..".to_string()),
+        ];
+        for (name, child) in children {
+            let kind = match self.nodes.get(&child).map(|node| &node.kind) {
+                Some(NodeKind::Folder { .. }) => FileType::Directory,
+                _ => FileType::RegularFile,
+            };
+            entries.push((child, kind, name));
+        }
+
+        let offset = usize::try_from(offset).unwrap_or_default();
+        for (index, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset) {
+            // The offset given with an entry is that of the next one.
+            if reply.add(ino, (index + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn create(
+        &mut self,
+        req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        // The new file is written to its folder when released, even if left empty.
+        match self
+            .new_spool()
+            .and_then(|spool| {
+                let kind = NodeKind::File {
+                    head: None,
+                    size: 0,
+                    spool: Some(spool),
+                    dirty: true,
+                };
+                self.create_node(parent, name, kind)
+            })
+            .and_then(|ino| self.attr(req, ino))
+        {
+            Ok(attr) => reply.created(&TTL, &attr, 0, 0, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+}
+
+/// The size of the file the data map chunk is the head of.
+async fn file_size(files_download: &mut FilesDownload, head: &Chunk) -> u64 {
+    match files_download.file_size(head.clone()).await {
+        Ok(size) => size as u64,
+        Err(_) => head.value().len() as u64,
+    }
+}
+
+/// Uploads the content of the spool file, returning the data map chunk of the file.
+async fn upload(client: &Client, root_dir: &Path, spool: &Path, size: u64) -> Result<Chunk> {
+    // Too small to be self-encrypted, the content is held by the head chunk itself.
+    if size < self_encryption::MIN_ENCRYPTABLE_BYTES as u64 {
+        return Ok(Chunk::new(Bytes::from(std::fs::read(spool)?)));
+    }
+
+    let chunk_dir = tempfile::tempdir_in(root_dir)?;
+    let (_, head, _, chunks) = FilesApi::chunk_file(spool, chunk_dir.path(), false)?;
+
+    let mut uploader = Uploader::new(client.clone(), root_dir.to_path_buf());
+    uploader.set_upload_cfg(UploadCfg::default());
+    uploader.insert_chunk_paths(chunks);
+    let _summary = uploader.start_upload().await?;
+    Ok(head)
+}
+
+/// The offset and length of the part of a file of `file_size` bytes a read of `size` bytes from
+/// `offset` covers, if any.
+fn range_to_read(offset: i64, size: u32, file_size: u64) -> Option<(u64, usize)> {
+    let offset = u64::try_from(offset).ok()?;
+    let length = file_size.checked_sub(offset)?.min(u64::from(size));
+    (length > 0).then_some((offset, length as usize))
+}
+
+fn read_spool(spool: &Path, offset: u64, length: usize) -> io::Result<Vec<u8>> {
+    let file = OpenOptions::new().read(true).open(spool)?;
+    let mut data = vec![0; length];
+    let mut read = 0;
+    while read < length {
+        match file.read_at(&mut data[read..], offset + read as u64)? {
+            0 => break,
+            count => read += count,
+        }
+    }
+    data.truncate(read);
+    Ok(data)
+}
+
+fn write_spool(spool: &Path, offset: u64, data: &[u8]) -> io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .open(spool)?
+        .write_all_at(data, offset)
+}
+
+fn set_spool_len(spool: &Path, size: u64) -> io::Result<()> {
+    OpenOptions::new().write(true).open(spool)?.set_len(size)
+}
+
+fn io_error(err: impl std::fmt::Display) -> c_int {
+    error!("Failed to access the Network: {err}");
+    EIO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    #[test]
+    fn range_to_read_should_be_clamped_to_the_file() {
+        assert_eq!(range_to_read(0, 4096, 10), Some((0, 10)));
+        assert_eq!(range_to_read(4, 3, 10), Some((4, 3)));
+        assert_eq!(range_to_read(8, 4096, 10), Some((8, 2)));
+        assert_eq!(range_to_read(10, 4096, 10), None);
+        assert_eq!(range_to_read(11, 4096, 10), None);
+        assert_eq!(range_to_read(-1, 4096, 10), None);
+        assert_eq!(range_to_read(0, 0, 10), None);
+    }
+
+    #[test]
+    fn spool_should_be_written_read_and_truncated_in_place() -> Result<()> {
+        let spool = tempfile::NamedTempFile::new()?.into_temp_path();
+
+        write_spool(&spool, 0, b"hello world")?;
+        // a write past the end leaves a hole of zeros
+        write_spool(&spool, 13, b"!")?;
+        assert_eq!(read_spool(&spool, 0, 4096)?, b"hello world\0\0!");
+        assert_eq!(read_spool(&spool, 6, 5)?, b"world");
+
+        write_spool(&spool, 0, b"HELLO")?;
+        set_spool_len(&spool, 8)?;
+        assert_eq!(read_spool(&spool, 0, 4096)?, b"HELLO wo");
+        assert_eq!(read_spool(&spool, 8, 4096)?, b"");
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[macro_use]
+extern crate tracing;
+
+#[cfg(target_os = "linux")]
+mod fs;
+
+use clap::Parser;
+use color_eyre::Result;
+use sn_peers_acquisition::PeersArgs;
+use std::path::PathBuf;
+
+/// The file holding the client key of `safe`, in its data directory.
+const CLIENT_KEY: &str = "clientkey";
+
+/// Mount a folder of the Safe Network as a filesystem, until it is unmounted.
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+struct Opt {
+    /// The hex encoded address of the folder, as printed by `safe folders upload`.
+    folder: String,
+    /// The directory the folder is mounted on.
+    mountpoint: PathBuf,
+    /// Mount the folder read-only: nothing is written to the Network.
+    #[clap(long)]
+    read_only: bool,
+    /// Unmount the folder when this process exits.
+    #[clap(long)]
+    auto_unmount: bool,
+    #[command(flatten)]
+    peers: PeersArgs,
+}
+
+#[cfg(target_os = "linux")]
+fn main() -> Result<()> {
+    use bls::SecretKey;
+    use sn_client::{protocol::storage::RegisterAddress, transfers::bls_secret_from_hex, Client};
+
+    color_eyre::install()?;
+    let opt = Opt::parse();
+    let address = RegisterAddress::from_hex(&opt.folder)
+        .map_err(|err| color_eyre::eyre::eyre!("Invalid folder address {}: {err}", opt.folder))?;
+
+    let mut root_dir = dirs_next::data_dir().ok_or_else(|| {
+        color_eyre::eyre::eyre!("The data directory of the platform could not be found")
+    })?;
+    root_dir.push("safe");
+    root_dir.push("client");
+    std::fs::create_dir_all(&root_dir)?;
+
+    let key_path = root_dir.join(CLIENT_KEY);
+    let secret_key = if key_path.is_file() {
+        bls_secret_from_hex(std::fs::read(key_path)?)?
+    } else {
+        let secret_key = SecretKey::random();
+        std::fs::write(key_path, hex::encode(secret_key.to_bytes()))?;
+        secret_key
+    };
+
+    // The filesystem calls block on the runtime from the thread of the FUSE session, which is
+    // thus kept outside of it.
+    let runtime = tokio::runtime::Runtime::new()?;
+    let fs = runtime.block_on(async {
+        let peers = opt.peers.get_peers().await?;
+        let peers = if peers.is_empty() { None } else { Some(peers) };
+        println!("Connecting to the network...");
+        let client = Client::new(secret_key, peers, None, None).await?;
+        fs::SafeFs::new(client, root_dir, address).await
+    })?;
+
+    let mut options = vec![
+        fuser::MountOption::FSName("safe".to_string()),
+        fuser::MountOption::Subtype("safe".to_string()),
+        fuser::MountOption::DefaultPermissions,
+    ];
+    if opt.read_only {
+        options.push(fuser::MountOption::RO);
+    }
+    if opt.auto_unmount {
+        options.push(fuser::MountOption::AutoUnmount);
+    }
+    println!(
+        "Mounting folder {} on {:?}",
+        opt.folder,
+        opt.mountpoint.as_path()
+    );
+    fuser::mount2(fs, &opt.mountpoint, &options)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let _opt = Opt::parse();
+    Err(color_eyre::eyre::eyre!(
+        "Mounting folders is only supported on Linux"
+    ))
+}
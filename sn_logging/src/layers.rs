@@ -11,7 +11,10 @@ use crate::{
     error::{Error, Result},
     LogFormat, LogOutputDest,
 };
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_core::{Event, Level, Subscriber};
 use tracing_subscriber::{
@@ -36,23 +39,73 @@ const ALL_SN_LOGS: &str = "all";
 // Trace at nodes, clients, debug at networking layer
 const VERBOSE_SN_LOGS: &str = "v";
 
+pub(crate) type FilterHandle = Handle<Box<dyn Filter<Registry> + Send + Sync>, Registry>;
+
 /// Handle that implements functions to change the log level on the fly.
-pub struct ReloadHandle(pub(crate) Handle<Box<dyn Filter<Registry> + Send + Sync>, Registry>);
+///
+/// It changes the levels of all the outputs of the logs, and keeps track of the levels in effect
+/// so a single target can be changed, e.g. to turn on debug for `sn_networking` only.
+pub struct ReloadHandle {
+    handles: Vec<FilterHandle>,
+    levels: Arc<Mutex<BTreeMap<String, Level>>>,
+}
 
 impl ReloadHandle {
+    pub(crate) fn new(handles: Vec<FilterHandle>, levels: Vec<(String, Level)>) -> Self {
+        Self {
+            handles,
+            levels: Arc::new(Mutex::new(levels.into_iter().collect())),
+        }
+    }
+
     /// Modify the log level to the provided CSV value
     /// Example input: `libp2p=DEBUG,tokio=INFO,all,sn_client=ERROR`
     ///
     /// Custom keywords will take less precedence if the same target has been manually specified in the CSV.
     /// `sn_client=ERROR` in the above example will be used instead of the TRACE level set by "all" keyword.
     pub fn modify_log_level(&self, logging_value: &str) -> Result<()> {
-        let targets: Vec<(String, Level)> = get_logging_targets(logging_value)?;
-        self.0.modify(|old_filter| {
-            let new_filter: Box<dyn Filter<Registry> + Send + Sync> =
-                Box::new(Targets::new().with_targets(targets));
-            *old_filter = new_filter;
-        })?;
+        let targets = get_logging_targets(logging_value)?;
+        self.apply(targets.into_iter().collect())
+    }
+
+    /// Change the levels of the targets of the provided CSV value, in the same format as for
+    /// `modify_log_level`, keeping the levels of the other targets.
+    pub fn merge_log_level(&self, logging_value: &str) -> Result<()> {
+        let targets = get_logging_targets(logging_value)?;
+        let mut levels = self.levels();
+        levels.extend(targets);
+        self.apply(levels)
+    }
+
+    /// The levels in effect, by target.
+    pub fn levels(&self) -> BTreeMap<String, Level> {
+        self.levels
+            .lock()
+            .map(|levels| levels.clone())
+            .unwrap_or_default()
+    }
+
+    /// The levels in effect, as a CSV value accepted by `modify_log_level`.
+    pub fn log_level(&self) -> String {
+        self.levels()
+            .iter()
+            .map(|(target, level)| format!("{target}={level}"))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
 
+    fn apply(&self, levels: BTreeMap<String, Level>) -> Result<()> {
+        for handle in &self.handles {
+            let targets = levels.clone();
+            handle.modify(|old_filter| {
+                let new_filter: Box<dyn Filter<Registry> + Send + Sync> =
+                    Box::new(Targets::new().with_targets(targets));
+                *old_filter = new_filter;
+            })?;
+        }
+        if let Ok(mut current) = self.levels.lock() {
+            *current = levels;
+        }
         Ok(())
     }
 }
@@ -98,17 +151,24 @@ pub(crate) struct TracingLayers {
 }
 
 impl TracingLayers {
+    /// Add an output of the logs of the targets, returning the handle of its filter.
     pub(crate) fn fmt_layer(
         &mut self,
-        default_logging_targets: Vec<(String, Level)>,
+        targets: Vec<(String, Level)>,
         output_dest: &LogOutputDest,
         format: LogFormat,
         max_uncompressed_log_files: Option<usize>,
         max_compressed_log_files: Option<usize>,
         print_updates_to_stdout: bool,
-    ) -> Result<ReloadHandle> {
-        let layer = match output_dest {
-            LogOutputDest::Stdout => {
+    ) -> Result<FilterHandle> {
+        let layer = match (output_dest, format) {
+            (LogOutputDest::Stdout, LogFormat::Json) => {
+                if print_updates_to_stdout {
+                    println!("Logging to stdout");
+                }
+                tracing_fmt::layer().json().flatten_event(true).boxed()
+            }
+            (LogOutputDest::Stdout, LogFormat::Default) => {
                 if print_updates_to_stdout {
                     println!("Logging to stdout");
                 }
@@ -118,13 +178,18 @@ impl TracingLayers {
                     .event_format(LogFormatter)
                     .boxed()
             }
-            LogOutputDest::Stderr => tracing_fmt::layer()
+            (LogOutputDest::Stderr, LogFormat::Json) => tracing_fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_writer(std::io::stderr)
+                .boxed(),
+            (LogOutputDest::Stderr, LogFormat::Default) => tracing_fmt::layer()
                 .with_ansi(false)
                 .with_target(false)
                 .event_format(LogFormatter)
                 .with_writer(std::io::stderr)
                 .boxed(),
-            LogOutputDest::Path(path) => {
+            (LogOutputDest::Path(path), format) => {
                 std::fs::create_dir_all(path)?;
                 if print_updates_to_stdout {
                     println!("Logging to directory: {path:?}");
@@ -162,16 +227,6 @@ impl TracingLayers {
                 }
             }
         };
-        let targets = match std::env::var("SN_LOG") {
-            Ok(sn_log_val) => {
-                if print_updates_to_stdout {
-                    println!("Using SN_LOG={sn_log_val}");
-                }
-                get_logging_targets(&sn_log_val)?
-            }
-            Err(_) => default_logging_targets,
-        };
-
         let target_filters: Box<dyn Filter<Registry> + Send + Sync> =
             Box::new(Targets::new().with_targets(targets));
 
@@ -180,13 +235,14 @@ impl TracingLayers {
         let layer = layer.with_filter(filter);
         self.layers.push(Box::new(layer));
 
-        Ok(ReloadHandle(reload_handle))
+        Ok(reload_handle)
     }
 
     #[cfg(feature = "otlp")]
     pub(crate) fn otlp_layer(
         &mut self,
         default_logging_targets: Vec<(String, Level)>,
+        config: &crate::OtlpConfig,
     ) -> Result<()> {
         use opentelemetry::{
             sdk::{trace, Resource},
@@ -196,19 +252,27 @@ impl TracingLayers {
         use opentelemetry_semantic_conventions::resource::{SERVICE_INSTANCE_ID, SERVICE_NAME};
         use rand::{distributions::Alphanumeric, thread_rng, Rng};
 
-        let service_name = std::env::var("OTLP_SERVICE_NAME").unwrap_or_else(|_| {
-            let random_node_name: String = thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(10)
-                .map(char::from)
-                .collect();
-            random_node_name
-        });
+        let service_name = config
+            .service_name
+            .clone()
+            .or_else(|| std::env::var("OTLP_SERVICE_NAME").ok())
+            .unwrap_or_else(|| {
+                let random_node_name: String = thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(10)
+                    .map(char::from)
+                    .collect();
+                random_node_name
+            });
         println!("The opentelemetry traces are logged under the name: {service_name}");
 
+        let mut exporter = opentelemetry_otlp::new_exporter().tonic().with_env();
+        if let Some(endpoint) = &config.endpoint {
+            exporter = exporter.with_endpoint(endpoint);
+        }
         let tracer = opentelemetry_otlp::new_pipeline()
             .tracing()
-            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_env())
+            .with_exporter(exporter)
             .with_trace_config(trace::config().with_resource(Resource::new(vec![
                 KeyValue::new(SERVICE_NAME, service_name),
                 KeyValue::new(SERVICE_INSTANCE_ID, std::process::id().to_string()),
@@ -234,11 +298,31 @@ impl TracingLayers {
     }
 }
 
+/// The levels logged at start: the ones of the `SN_LOG` env variable if it is set, else the
+/// defaults, with the levels of the targets given by `overrides` replaced.
+pub(crate) fn initial_logging_targets(
+    default_logging_targets: Vec<(String, Level)>,
+    overrides: Vec<(String, Level)>,
+    print_updates_to_stdout: bool,
+) -> Result<Vec<(String, Level)>> {
+    let mut targets: BTreeMap<String, Level> = match std::env::var("SN_LOG") {
+        Ok(sn_log_val) => {
+            if print_updates_to_stdout {
+                println!("Using SN_LOG={sn_log_val}");
+            }
+            get_logging_targets(&sn_log_val)?.into_iter().collect()
+        }
+        Err(_) => default_logging_targets.into_iter().collect(),
+    };
+    targets.extend(overrides);
+    Ok(targets.into_iter().collect())
+}
+
 /// Parses the logging targets from the env variable (SN_LOG). The crates should be given as a CSV, for e.g.,
 /// `export SN_LOG = libp2p=DEBUG, tokio=INFO, all, sn_client=ERROR`
 /// Custom keywords will take less precedence if the same target has been manually specified in the CSV.
 /// `sn_client=ERROR` in the above example will be used instead of the TRACE level set by "all" keyword.
-fn get_logging_targets(logging_env_value: &str) -> Result<Vec<(String, Level)>> {
+pub(crate) fn get_logging_targets(logging_env_value: &str) -> Result<Vec<(String, Level)>> {
     let mut targets = BTreeMap::new();
     let mut contains_keyword_all_sn_logs = false;
     let mut contains_keyword_verbose_sn_logs = false;
@@ -13,7 +13,7 @@ mod layers;
 pub mod metrics;
 
 use crate::error::Result;
-use layers::TracingLayers;
+use layers::{initial_logging_targets, TracingLayers};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tracing::info;
@@ -120,14 +120,31 @@ impl LogFormat {
     }
 }
 
+/// Where and under which name the traces are exported, with the `otlp` feature.
+///
+/// The unset values are read from the `OTEL_EXPORTER_OTLP_ENDPOINT` and `OTLP_SERVICE_NAME` env
+/// variables. The traces are only exported if an endpoint is given either way.
+#[derive(Debug, Clone, Default)]
+pub struct OtlpConfig {
+    /// The endpoint of the OTLP collector, e.g. `http://localhost:4317`.
+    pub endpoint: Option<String>,
+    /// The name of the service the traces are exported under, random by default.
+    pub service_name: Option<String>,
+}
+
 pub struct LogBuilder {
     default_logging_targets: Vec<(String, Level)>,
     output_dest: LogOutputDest,
     format: LogFormat,
     max_uncompressed_log_files: Option<usize>,
     max_compressed_log_files: Option<usize>,
+    /// The levels of the targets replacing the defaults and the ones of `SN_LOG`.
+    log_levels: Vec<(String, Level)>,
+    /// The outputs the logs are written to besides `output_dest`.
+    additional_outputs: Vec<(LogOutputDest, LogFormat)>,
     /// Setting this would print the sn_logging related updates to stdout.
     print_updates_to_stdout: bool,
+    otlp_config: OtlpConfig,
 }
 
 impl LogBuilder {
@@ -142,7 +159,10 @@ impl LogBuilder {
             format: LogFormat::Default,
             max_uncompressed_log_files: None,
             max_compressed_log_files: None,
+            log_levels: vec![],
+            additional_outputs: vec![],
             print_updates_to_stdout: true,
+            otlp_config: OtlpConfig::default(),
         }
     }
 
@@ -156,6 +176,18 @@ impl LogBuilder {
         self.format = format
     }
 
+    /// Set the levels of some targets, e.g. `sn_networking=DEBUG`, replacing their levels in the
+    /// defaults and in the `SN_LOG` env variable.
+    pub fn log_levels(&mut self, log_levels: Vec<(String, Level)>) {
+        self.log_levels = log_levels;
+    }
+
+    /// Also write the logs to another output, e.g. to stdout as well as to the log files. The
+    /// levels of all the outputs are the same, and changed together by the `ReloadHandle`.
+    pub fn add_output(&mut self, output_dest: LogOutputDest, format: LogFormat) {
+        self.additional_outputs.push((output_dest, format));
+    }
+
     /// The max number of uncompressed log files to store
     pub fn max_uncompressed_log_files(&mut self, files: usize) {
         self.max_uncompressed_log_files = Some(files);
@@ -166,6 +198,11 @@ impl LogBuilder {
         self.max_compressed_log_files = Some(files);
     }
 
+    /// Set where the traces are exported, ignored without the `otlp` feature.
+    pub fn otlp_config(&mut self, otlp_config: OtlpConfig) {
+        self.otlp_config = otlp_config;
+    }
+
     /// Setting this to false would prevent sn_logging from printing things to stdout.
     pub fn print_updates_to_stdout(&mut self, print: bool) {
         self.print_updates_to_stdout = print;
@@ -177,24 +214,38 @@ impl LogBuilder {
     /// Logging should be instantiated only once.
     pub fn initialize(self) -> Result<(ReloadHandle, Option<WorkerGuard>)> {
         let mut layers = TracingLayers::default();
-
-        let reload_handle = layers.fmt_layer(
+        let targets = initial_logging_targets(
             self.default_logging_targets.clone(),
-            &self.output_dest,
-            self.format,
-            self.max_uncompressed_log_files,
-            self.max_compressed_log_files,
+            self.log_levels.clone(),
             self.print_updates_to_stdout,
         )?;
 
+        let mut handles = vec![];
+        let outputs = std::iter::once((self.output_dest.clone(), self.format))
+            .chain(self.additional_outputs.iter().cloned());
+        for (output_dest, format) in outputs {
+            handles.push(layers.fmt_layer(
+                targets.clone(),
+                &output_dest,
+                format,
+                self.max_uncompressed_log_files,
+                self.max_compressed_log_files,
+                self.print_updates_to_stdout,
+            )?);
+        }
+        let reload_handle = ReloadHandle::new(handles, targets);
+
         #[cfg(feature = "otlp")]
         {
-            match std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") {
-                Ok(_) => layers.otlp_layer(self.default_logging_targets)?,
-                Err(_) => println!(
-                "The OTLP feature is enabled but the OTEL_EXPORTER_OTLP_ENDPOINT variable is not \
-                set, so traces will not be submitted."
-            ),
+            if self.otlp_config.endpoint.is_some()
+                || std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_ok()
+            {
+                layers.otlp_layer(self.default_logging_targets, &self.otlp_config)?
+            } else {
+                println!(
+                    "The OTLP feature is enabled but no endpoint is configured nor the \
+                    OTEL_EXPORTER_OTLP_ENDPOINT variable set, so traces will not be submitted."
+                )
             }
         }
 
@@ -278,13 +329,21 @@ impl LogBuilder {
 
         let mut layers = TracingLayers::default();
 
+        let targets =
+            initial_logging_targets(vec![], vec![], false).expect("Failed to parse SN_LOG");
         let _reload_handle = layers
-            .fmt_layer(vec![], &output_dest, LogFormat::Default, None, None, false)
+            .fmt_layer(targets, &output_dest, LogFormat::Default, None, None, false)
             .expect("Failed to get TracingLayers");
         layers
     }
 }
 
+/// Parse the levels of targets given as a CSV value, in the format of the `SN_LOG` env variable,
+/// e.g. `sn_networking=DEBUG,sn_node=TRACE`.
+pub fn parse_log_levels(value: &str) -> Result<Vec<(String, Level)>> {
+    layers::get_logging_targets(value)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{layers::LogFormatter, ReloadHandle};
@@ -322,7 +381,7 @@ mod tests {
 
         // add the reload layer
         let (filter, handle) = reload::Layer::new(target_filters);
-        let reload_handle = ReloadHandle(handle);
+        let reload_handle = ReloadHandle::new(vec![handle], vec![(test_target, Level::TRACE)]);
         let layer = layer.with_filter(filter);
         tracing_subscriber::registry().with(layer).try_init()?;
 
@@ -343,6 +402,7 @@ mod tests {
         }
 
         reload_handle.modify_log_level("sn_logging::tests=WARN")?;
+        assert_eq!(reload_handle.log_level(), "sn_logging::tests=WARN");
 
         // trace should not be logged now.
         trace!("Second trace event");
@@ -363,4 +423,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn merged_log_levels_should_keep_the_other_targets() -> Result<()> {
+        let target_filters: Box<dyn Filter<Registry> + Send + Sync> = Box::new(Targets::new());
+        let (_filter, handle) = reload::Layer::new(target_filters);
+        let reload_handle = ReloadHandle::new(
+            vec![handle],
+            vec![
+                ("sn_networking".to_string(), Level::INFO),
+                ("sn_node".to_string(), Level::DEBUG),
+            ],
+        );
+
+        reload_handle.merge_log_level("sn_networking=DEBUG")?;
+        assert_eq!(
+            reload_handle.log_level(),
+            "sn_networking=DEBUG,sn_node=DEBUG"
+        );
+        // modifying replaces all the levels
+        reload_handle.modify_log_level("sn_networking=WARN")?;
+        assert_eq!(reload_handle.log_level(), "sn_networking=WARN");
+        Ok(())
+    }
 }
@@ -0,0 +1,128 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#![allow(clippy::unwrap_used)]
+
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use libp2p::{
+    kad::{store::RecordStore, Record, RecordKey},
+    PeerId,
+};
+use rand::RngCore;
+use sn_networking::{NodeRecordStore, NodeRecordStoreConfig};
+use sn_protocol::storage::{try_serialize_record, Chunk, RecordKind, RecordType};
+use std::path::Path;
+use tempfile::TempDir;
+use tokio::{runtime::Runtime, sync::mpsc};
+
+const RECORD_COUNT: usize = 100;
+const RECORD_SIZE: usize = 512 * 1024;
+
+fn chunk_records() -> Vec<Record> {
+    let mut rng = rand::thread_rng();
+    (0..RECORD_COUNT)
+        .map(|_| {
+            let mut value = vec![0u8; RECORD_SIZE];
+            rng.fill_bytes(&mut value);
+            let chunk = Chunk::new(Bytes::from(value));
+            Record {
+                key: chunk.network_address().to_record_key(),
+                value: try_serialize_record(&chunk, RecordKind::Chunk)
+                    .unwrap()
+                    .to_vec(),
+                publisher: None,
+                expires: None,
+            }
+        })
+        .collect()
+}
+
+/// Write the records to a store in `dir`, returning once they are all on disk.
+async fn write_records(dir: &Path, records: Vec<Record>) -> NodeRecordStore {
+    let (network_event_sender, _network_event_receiver) = mpsc::channel(1);
+    let (swarm_cmd_sender, mut swarm_cmd_receiver) = mpsc::channel(RECORD_COUNT);
+    let config = NodeRecordStoreConfig {
+        storage_dir: dir.to_path_buf(),
+        historic_quote_dir: dir.to_path_buf(),
+        ..Default::default()
+    };
+    let mut store = NodeRecordStore::with_config(
+        PeerId::random(),
+        config,
+        network_event_sender,
+        swarm_cmd_sender,
+    );
+
+    let count = records.len();
+    for record in records {
+        store.put_verified(record, RecordType::Chunk).unwrap();
+    }
+    // each write is acknowledged by a cmd once on disk
+    for _ in 0..count {
+        let _ = swarm_cmd_receiver.recv().await;
+    }
+    store
+}
+
+/// A store reopened from `dir`, which then has to read the records from disk.
+fn reopen_store(dir: &Path) -> NodeRecordStore {
+    let (network_event_sender, _) = mpsc::channel(1);
+    let (swarm_cmd_sender, _) = mpsc::channel(1);
+    let config = NodeRecordStoreConfig {
+        storage_dir: dir.to_path_buf(),
+        historic_quote_dir: dir.to_path_buf(),
+        ..Default::default()
+    };
+    NodeRecordStore::with_config(
+        PeerId::random(),
+        config,
+        network_event_sender,
+        swarm_cmd_sender,
+    )
+}
+
+fn bench_record_store(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("record store");
+    let _ = group
+        .sample_size(10)
+        .throughput(Throughput::Bytes((RECORD_COUNT * RECORD_SIZE) as u64));
+
+    let _ = group.bench_function(format!("write {RECORD_COUNT} chunks"), |b| {
+        b.iter_batched(
+            || (TempDir::new().unwrap(), chunk_records()),
+            |(dir, records)| {
+                let _ = runtime.block_on(write_records(dir.path(), records));
+                dir
+            },
+            BatchSize::PerIteration,
+        );
+    });
+
+    let dir = TempDir::new().unwrap();
+    let records = chunk_records();
+    let keys: Vec<RecordKey> = records.iter().map(|record| record.key.clone()).collect();
+    let _ = runtime.block_on(write_records(dir.path(), records));
+    let store = {
+        let _guard = runtime.enter();
+        reopen_store(dir.path())
+    };
+    let _ = group.bench_function(format!("read {RECORD_COUNT} chunks"), |b| {
+        b.iter(|| {
+            for key in &keys {
+                let _ = black_box(store.get(key).unwrap());
+            }
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_record_store);
+criterion_main!(benches);
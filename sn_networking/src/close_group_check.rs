@@ -0,0 +1,142 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Network, Result};
+use libp2p::PeerId;
+use sn_protocol::{
+    messages::{ChunkProof, Nonce, Query, QueryResponse, Request, Response},
+    NetworkAddress, PrettyPrintRecordKey,
+};
+
+/// What each peer of the close group of an address holds of its record, for the copies held
+/// across the group to be compared.
+#[derive(Debug, Clone)]
+pub struct CloseGroupConsistency {
+    pub address: NetworkAddress,
+    /// The digests of the distinct copies of the record held in the group, each with the peers
+    /// holding it, the most held first
+    pub copies: Vec<(ChunkProof, Vec<PeerId>)>,
+    /// The peers answering that they do not hold the record
+    pub missing: Vec<PeerId>,
+    /// The peers which did not answer, with the reason
+    pub unreachable: Vec<(PeerId, String)>,
+}
+
+impl CloseGroupConsistency {
+    /// The number of peers of the group holding a copy of the record.
+    pub fn holders(&self) -> usize {
+        self.copies.iter().map(|(_, peers)| peers.len()).sum()
+    }
+
+    /// Whether the whole group answered and holds the same copy of the record.
+    pub fn is_consistent(&self) -> bool {
+        self.copies.len() == 1 && self.missing.is_empty() && self.unreachable.is_empty()
+    }
+
+    fn add_copy(&mut self, peer: PeerId, proof: ChunkProof) {
+        match self.copies.iter_mut().find(|(copy, _)| copy.verify(&proof)) {
+            Some((_, peers)) => peers.push(peer),
+            None => self.copies.push((proof, vec![peer])),
+        }
+    }
+}
+
+impl Network {
+    /// Ask each peer of the close group of the address whether it holds the record, along with
+    /// the digest of its copy, reporting where the group diverges.
+    ///
+    /// The digests are proofs of existence over a random nonce, so that a peer can not answer
+    /// one without holding the record. If `include_self` is set, our node counts in the group
+    /// and its own copy is checked locally.
+    pub async fn check_close_group_consistency(
+        &self,
+        address: &NetworkAddress,
+        include_self: bool,
+    ) -> Result<CloseGroupConsistency> {
+        let pretty_key = PrettyPrintRecordKey::from(&address.to_record_key()).into_owned();
+        let close_group = self.get_closest_peers(address, !include_self).await?;
+        let nonce: Nonce = rand::random();
+        let mut report = CloseGroupConsistency {
+            address: address.clone(),
+            copies: vec![],
+            missing: vec![],
+            unreachable: vec![],
+        };
+
+        let our_peer_id = self.peer_id();
+        let (ours, peers): (Vec<PeerId>, Vec<PeerId>) = close_group
+            .into_iter()
+            .partition(|peer| *peer == our_peer_id);
+        if !ours.is_empty() {
+            match self.get_local_record(&address.to_record_key()).await? {
+                Some(record) => report.add_copy(our_peer_id, ChunkProof::new(&record.value, nonce)),
+                None => report.missing.push(our_peer_id),
+            }
+        }
+
+        let request = Request::Query(Query::GetChunkExistenceProof {
+            key: address.clone(),
+            nonce,
+        });
+        for (peer, response) in self.send_and_get_responses(&peers, &request, true).await {
+            match response {
+                Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Ok(proof)))) => {
+                    report.add_copy(peer, proof)
+                }
+                Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Err(_)))) => {
+                    report.missing.push(peer)
+                }
+                Ok(other) => report
+                    .unreachable
+                    .push((peer, format!("unexpected response {other:?}"))),
+                Err(err) => report.unreachable.push((peer, err.to_string())),
+            }
+        }
+        report
+            .copies
+            .sort_by_key(|(_, peers)| std::cmp::Reverse(peers.len()));
+
+        if report.is_consistent() {
+            debug!("The close group of {pretty_key:?} holds a single copy of it");
+        } else {
+            warn!(
+                "The close group of {pretty_key:?} diverges: {} copies, missing from {:?}, unreachable {:?}",
+                report.copies.len(),
+                report.missing,
+                report.unreachable
+            );
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_copies_should_be_grouped_by_digest() {
+        let address = NetworkAddress::from_peer(PeerId::random());
+        let mut report = CloseGroupConsistency {
+            address,
+            copies: vec![],
+            missing: vec![],
+            unreachable: vec![],
+        };
+        let nonce = 7;
+        for _ in 0..3 {
+            report.add_copy(PeerId::random(), ChunkProof::new(b"record", nonce));
+        }
+        assert!(report.is_consistent());
+
+        report.add_copy(PeerId::random(), ChunkProof::new(b"stale record", nonce));
+        assert_eq!(report.copies.len(), 2);
+        assert_eq!(report.holders(), 4);
+        assert!(!report.is_consistent());
+    }
+}
@@ -14,15 +14,18 @@ use crate::{
     multiaddr_pop_p2p, GetRecordCfg, GetRecordError, MsgResponder, NetworkEvent, CLOSE_GROUP_SIZE,
     REPLICATION_PEERS_COUNT,
 };
+use bytes::Bytes;
 use libp2p::{
+    gossipsub::IdentTopic,
     kad::{
         store::{Error as StoreError, RecordStore},
         Quorum, Record, RecordKey,
     },
+    multiaddr::Protocol,
     Multiaddr, PeerId,
 };
 use sn_protocol::{
-    messages::{Cmd, Request, Response},
+    messages::{Cmd, Request, RequestAuth, Response},
     storage::{RecordHeader, RecordKind, RecordType},
     NetworkAddress, PrettyPrintRecordKey,
 };
@@ -63,6 +66,11 @@ pub enum LocalSwarmCmd {
     GetKBuckets {
         sender: oneshot::Sender<BTreeMap<u32, Vec<PeerId>>>,
     },
+    /// Get the addresses of all the peers of the local Routing Table, each ending with the
+    /// `/p2p/<peer_id>` of its peer.
+    GetRoutingTableAddrs {
+        sender: oneshot::Sender<Vec<Multiaddr>>,
+    },
     // Returns up to K_VALUE peers from all the k-buckets from the local Routing Table.
     // And our PeerId as well.
     GetClosestKLocalPeers {
@@ -74,6 +82,10 @@ pub enum LocalSwarmCmd {
         sender: oneshot::Sender<Vec<PeerId>>,
     },
     GetSwarmLocalState(oneshot::Sender<SwarmLocalState>),
+    /// Get the queue depths and in-flight task counts of the SwarmDriver
+    GetDriverStats {
+        sender: oneshot::Sender<SwarmDriverStats>,
+    },
     /// Check if the local RecordStore contains the provided key
     RecordStoreHasKey {
         key: RecordKey,
@@ -187,6 +199,23 @@ pub enum NetworkSwarmCmd {
         sender: oneshot::Sender<Result<()>>,
         quorum: Quorum,
     },
+
+    /// Subscribe to a gossipsub topic
+    GossipsubSubscribe {
+        topic: String,
+        sender: oneshot::Sender<Result<()>>,
+    },
+    /// Unsubscribe from a gossipsub topic
+    GossipsubUnsubscribe {
+        topic: String,
+        sender: oneshot::Sender<Result<()>>,
+    },
+    /// Publish a message on a gossipsub topic
+    GossipsubPublish {
+        topic: String,
+        msg: Bytes,
+        sender: oneshot::Sender<Result<()>>,
+    },
 }
 
 /// Debug impl for LocalSwarmCmd to avoid printing full Record, instead only RecodKey
@@ -244,9 +273,15 @@ impl Debug for LocalSwarmCmd {
             LocalSwarmCmd::GetKBuckets { .. } => {
                 write!(f, "LocalSwarmCmd::GetKBuckets")
             }
+            LocalSwarmCmd::GetRoutingTableAddrs { .. } => {
+                write!(f, "LocalSwarmCmd::GetRoutingTableAddrs")
+            }
             LocalSwarmCmd::GetSwarmLocalState { .. } => {
                 write!(f, "LocalSwarmCmd::GetSwarmLocalState")
             }
+            LocalSwarmCmd::GetDriverStats { .. } => {
+                write!(f, "LocalSwarmCmd::GetDriverStats")
+            }
             LocalSwarmCmd::RecordStoreHasKey { key, .. } => {
                 write!(
                     f,
@@ -331,6 +366,19 @@ impl Debug for NetworkSwarmCmd {
                     "NetworkSwarmCmd::SendRequest req: {req:?}, peer: {peer:?}"
                 )
             }
+            NetworkSwarmCmd::GossipsubSubscribe { topic, .. } => {
+                write!(f, "NetworkSwarmCmd::GossipsubSubscribe({topic:?})")
+            }
+            NetworkSwarmCmd::GossipsubUnsubscribe { topic, .. } => {
+                write!(f, "NetworkSwarmCmd::GossipsubUnsubscribe({topic:?})")
+            }
+            NetworkSwarmCmd::GossipsubPublish { topic, msg, .. } => {
+                write!(
+                    f,
+                    "NetworkSwarmCmd::GossipsubPublish({topic:?}, {} bytes)",
+                    msg.len()
+                )
+            }
         }
     }
 }
@@ -341,6 +389,30 @@ pub struct SwarmLocalState {
     pub connected_peers: Vec<PeerId>,
     /// List of addresses the node is currently listening on
     pub listeners: Vec<Multiaddr>,
+    /// The identify protocol version advertised by each connected peer
+    pub peer_protocol_versions: BTreeMap<PeerId, String>,
+}
+
+/// Snapshot of the load of the SwarmDriver, to spot its queues growing when the callers outpace
+/// the network
+#[derive(Debug, Clone, Default)]
+pub struct SwarmDriverStats {
+    /// The `LocalSwarmCmd`s queued in their channel
+    pub local_cmd_backlog: usize,
+    /// The `NetworkSwarmCmd`s queued in their channel
+    pub network_cmd_backlog: usize,
+    /// The `NetworkEvent`s queued for the node or client to handle
+    pub event_backlog: usize,
+    /// The tasks spawned by the `Network` handles to wait for room in a full cmd channel
+    pub pending_cmd_sends: usize,
+    /// The get closest peers queries in flight
+    pub pending_get_closest_peers: usize,
+    /// The requests awaiting a response from a peer
+    pub pending_requests: usize,
+    /// The get record queries in flight
+    pub pending_get_record: usize,
+    /// The connections currently open
+    pub live_connections: usize,
 }
 
 impl SwarmDriver {
@@ -448,6 +520,42 @@ impl SwarmDriver {
                 }
             }
 
+            NetworkSwarmCmd::GossipsubSubscribe { topic, sender } => {
+                cmd_string = "GossipsubSubscribe";
+                let topic = IdentTopic::new(topic);
+                let res = self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .subscribe(&topic)
+                    .map(|_| ())
+                    .map_err(NetworkError::from);
+                let _ = sender.send(res);
+            }
+            NetworkSwarmCmd::GossipsubUnsubscribe { topic, sender } => {
+                cmd_string = "GossipsubUnsubscribe";
+                let topic = IdentTopic::new(topic);
+                let res = self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .unsubscribe(&topic)
+                    .map(|_| ())
+                    .map_err(NetworkError::from);
+                let _ = sender.send(res);
+            }
+            NetworkSwarmCmd::GossipsubPublish { topic, msg, sender } => {
+                cmd_string = "GossipsubPublish";
+                let topic = IdentTopic::new(topic);
+                let res = self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .publish(topic, msg)
+                    .map(|_| ())
+                    .map_err(NetworkError::from);
+                let _ = sender.send(res);
+            }
             NetworkSwarmCmd::Dial { addr, sender } => {
                 cmd_string = "Dial";
 
@@ -490,6 +598,8 @@ impl SwarmDriver {
                     if let Request::Query(query) = req {
                         self.send_event(NetworkEvent::QueryRequestReceived {
                             query,
+                            peer,
+                            signer: None,
                             channel: MsgResponder::FromSelf(sender),
                         });
                     } else {
@@ -498,6 +608,21 @@ impl SwarmDriver {
                         trace!("Replicate cmd to self received, ignoring");
                     }
                 } else {
+                    // Sign the request, for the receiver to hold us to the identity it claims,
+                    // e.g. as the holder of replicated keys.
+                    let req = match &req {
+                        Request::Authenticated { .. } => req,
+                        _ => match RequestAuth::sign_with_ed25519(&self.keypair, &req) {
+                            Ok(auth) => Request::Authenticated {
+                                request: Box::new(req),
+                                auth,
+                            },
+                            Err(err) => {
+                                warn!("Sending request {req:?} unsigned, failed to sign it: {err}");
+                                req
+                            }
+                        },
+                    };
                     let request_id = self
                         .swarm
                         .behaviour_mut()
@@ -745,6 +870,23 @@ impl SwarmDriver {
                 }
                 let _ = sender.send(ilog2_kbuckets);
             }
+            LocalSwarmCmd::GetRoutingTableAddrs { sender } => {
+                cmd_string = "GetRoutingTableAddrs";
+                let mut addrs = vec![];
+                for kbucket in self.swarm.behaviour_mut().kademlia.kbuckets() {
+                    for peer_entry in kbucket.iter() {
+                        let peer_id = (*peer_entry.node.key).into_preimage();
+                        for addr in peer_entry.node.value.iter() {
+                            let mut addr = addr.clone();
+                            if !matches!(addr.iter().last(), Some(Protocol::P2p(_))) {
+                                addr.push(Protocol::P2p(peer_id));
+                            }
+                            addrs.push(addr);
+                        }
+                    }
+                }
+                let _ = sender.send(addrs);
+            }
             LocalSwarmCmd::GetCloseGroupLocalPeers { key, sender } => {
                 cmd_string = "GetCloseGroupLocalPeers";
                 let key = key.as_kbucket_key();
@@ -772,12 +914,17 @@ impl SwarmDriver {
                 let current_state = SwarmLocalState {
                     connected_peers: self.swarm.connected_peers().cloned().collect(),
                     listeners: self.swarm.listeners().cloned().collect(),
+                    peer_protocol_versions: self.peer_protocol_versions.clone(),
                 };
 
                 sender
                     .send(current_state)
                     .map_err(|_| NetworkError::InternalMsgChannelDropped)?;
             }
+            LocalSwarmCmd::GetDriverStats { sender } => {
+                cmd_string = "GetDriverStats";
+                let _ = sender.send(self.driver_stats());
+            }
             LocalSwarmCmd::AddPeerToBlockList { peer_id } => {
                 cmd_string = "AddPeerToBlockList";
                 self.swarm.behaviour_mut().blocklist.block_peer(peer_id);
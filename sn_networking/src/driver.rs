@@ -13,7 +13,7 @@ use crate::metrics_service::run_metrics_server;
 use crate::{
     bootstrap::{ContinuousBootstrap, BOOTSTRAP_INTERVAL},
     circular_vec::CircularVec,
-    cmd::{LocalSwarmCmd, NetworkSwarmCmd},
+    cmd::{LocalSwarmCmd, NetworkSwarmCmd, SwarmDriverStats},
     error::{NetworkError, Result},
     event::{NetworkEvent, NodeEvent},
     external_address::ExternalAddressManager,
@@ -24,7 +24,9 @@ use crate::{
     record_store_api::UnifiedRecordStore,
     relay_manager::RelayManager,
     replication_fetcher::ReplicationFetcher,
+    spend_cache::SpendCacheConfig,
     target_arch::{interval, spawn, Instant},
+    transfers::TransferNoticeValidator,
     GetRecordError, Network, CLOSE_GROUP_SIZE,
 };
 use crate::{transport, NodeIssue};
@@ -52,7 +54,7 @@ use sn_protocol::{
     storage::RetryStrategy,
     version::{
         IDENTIFY_CLIENT_VERSION_STR, IDENTIFY_NODE_VERSION_STR, IDENTIFY_PROTOCOL_STR,
-        REQ_RESPONSE_VERSION_STR,
+        REQ_RESPONSE_COMPATIBLE_VERSION_STRS,
     },
     NetworkAddress, PrettyPrintKBucketKey, PrettyPrintRecordKey,
 };
@@ -109,6 +111,10 @@ pub(crate) type BadNodes = BTreeMap<PeerId, (Vec<(NodeIssue, Instant)>, bool)>;
 // TODO: revisit once cashnote_redemption is in
 pub const MAX_PACKET_SIZE: usize = 1024 * 1024 * 5; // the chunk size is 1mb, so should be higher than that to prevent failures, 5mb here to allow for CashNote storage
 
+/// Gossip messages larger than this will be rejected, they are meant to be small notices of
+/// transfers.
+pub const MAX_GOSSIP_MSG_SIZE: usize = 16 * 1024;
+
 // Timeout for requests sent/received through the request_response behaviour.
 const REQUEST_TIMEOUT_DEFAULT_S: Duration = Duration::from_secs(30);
 // Sets the keep-alive timeout of idle connections.
@@ -213,6 +219,7 @@ pub(super) struct NodeBehaviour {
     pub(super) relay_server: libp2p::relay::Behaviour,
     pub(super) kademlia: kad::Behaviour<UnifiedRecordStore>,
     pub(super) request_response: request_response::cbor::Behaviour<Request, Response>,
+    pub(super) gossipsub: libp2p::gossipsub::Behaviour,
 }
 
 #[derive(Debug)]
@@ -225,6 +232,7 @@ pub struct NetworkBuilder {
     request_timeout: Option<Duration>,
     concurrency_limit: Option<usize>,
     initial_peers: Vec<Multiaddr>,
+    spend_cache_config: SpendCacheConfig,
     #[cfg(feature = "open-metrics")]
     metrics_metadata_registry: Option<Registry>,
     #[cfg(feature = "open-metrics")]
@@ -246,6 +254,7 @@ impl NetworkBuilder {
             request_timeout: None,
             concurrency_limit: None,
             initial_peers: Default::default(),
+            spend_cache_config: Default::default(),
             #[cfg(feature = "open-metrics")]
             metrics_metadata_registry: None,
             #[cfg(feature = "open-metrics")]
@@ -277,6 +286,11 @@ impl NetworkBuilder {
         self.initial_peers = initial_peers;
     }
 
+    /// Bound the cache of the spends fetched from the network.
+    pub fn spend_cache_config(&mut self, spend_cache_config: SpendCacheConfig) {
+        self.spend_cache_config = spend_cache_config;
+    }
+
     /// Set the Registry that will be served at the `/metadata` endpoint. This Registry should contain only the static
     /// info about the peer. Configure the `metrics_server_port` to enable the metrics server.
     #[cfg(feature = "open-metrics")]
@@ -515,15 +529,14 @@ impl NetworkBuilder {
 
             info!(
                 "Building request response with {:?}",
-                REQ_RESPONSE_VERSION_STR.as_str()
+                REQ_RESPONSE_COMPATIBLE_VERSION_STRS.as_slice()
             );
-            request_response::cbor::Behaviour::new(
-                [(
-                    StreamProtocol::new(&REQ_RESPONSE_VERSION_STR),
-                    req_res_protocol,
-                )],
-                cfg,
-            )
+            // The older compatible versions are supported as well, so that requests can still be
+            // exchanged with the peers that have not been upgraded yet.
+            let protocols = REQ_RESPONSE_COMPATIBLE_VERSION_STRS
+                .iter()
+                .map(|version| (StreamProtocol::new(version), req_res_protocol.clone()));
+            request_response::cbor::Behaviour::new(protocols, cfg)
         };
 
         let (network_event_sender, network_event_receiver) = mpsc::channel(NETWORKING_CHANNEL_SIZE);
@@ -547,7 +560,11 @@ impl NetworkBuilder {
                     #[cfg(feature = "open-metrics")]
                     if let Some(metrics) = &network_metrics {
                         node_record_store = node_record_store
-                            .set_record_count_metric(metrics.records_stored.clone());
+                            .set_record_count_metric(metrics.records_stored.clone())
+                            .set_record_duration_metrics(
+                                metrics.record_read_duration_secs.clone(),
+                                metrics.record_write_duration_secs.clone(),
+                            );
                     }
 
                     let store = UnifiedRecordStore::Node(node_record_store);
@@ -609,6 +626,26 @@ impl NetworkBuilder {
             libp2p::relay::Behaviour::new(peer_id, relay_server_cfg)
         };
 
+        let gossipsub = {
+            // messages are told apart by their content, so that the same one is only relayed once
+            let message_id_fn = |message: &libp2p::gossipsub::Message| {
+                libp2p::gossipsub::MessageId::from(XorName::from_content(&message.data).0)
+            };
+            let gossipsub_cfg = libp2p::gossipsub::ConfigBuilder::default()
+                .validation_mode(libp2p::gossipsub::ValidationMode::Strict)
+                // messages are only relayed once validated, see `TransferNoticeValidator`
+                .validate_messages()
+                .message_id_fn(message_id_fn)
+                .max_transmit_size(MAX_GOSSIP_MSG_SIZE)
+                .build()
+                .map_err(|err| NetworkError::GossipsubConfig(err.to_string()))?;
+            libp2p::gossipsub::Behaviour::new(
+                libp2p::gossipsub::MessageAuthenticity::Signed(self.keypair.clone()),
+                gossipsub_cfg,
+            )
+            .map_err(|err| NetworkError::GossipsubConfig(err.to_string()))?
+        };
+
         let behaviour = NodeBehaviour {
             blocklist: libp2p::allow_block_list::Behaviour::default(),
             relay_client: relay_behaviour,
@@ -620,6 +657,7 @@ impl NetworkBuilder {
             identify,
             #[cfg(feature = "local-discovery")]
             mdns,
+            gossipsub,
         };
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -642,6 +680,7 @@ impl NetworkBuilder {
         let swarm_driver = SwarmDriver {
             swarm,
             self_peer_id: peer_id,
+            keypair: self.keypair.clone(),
             local: self.local,
             is_client,
             is_behind_home_network: self.is_behind_home_network,
@@ -668,12 +707,14 @@ impl NetworkBuilder {
             network_discovery: NetworkDiscovery::new(&peer_id),
             bootstrap_peers: Default::default(),
             live_connected_peers: Default::default(),
+            peer_protocol_versions: Default::default(),
             handling_statistics: Default::default(),
             handled_times: 0,
             hard_disk_write_error: 0,
             bad_nodes: Default::default(),
             quotes_history: Default::default(),
             replication_targets: Default::default(),
+            transfer_notice_validator: Default::default(),
         };
 
         let network = Network::new(
@@ -682,6 +723,7 @@ impl NetworkBuilder {
             peer_id,
             self.root_dir,
             self.keypair,
+            self.spend_cache_config,
         );
 
         Ok((network, network_event_receiver, swarm_driver))
@@ -691,6 +733,8 @@ impl NetworkBuilder {
 pub struct SwarmDriver {
     pub(crate) swarm: Swarm<NodeBehaviour>,
     pub(crate) self_peer_id: PeerId,
+    /// Signs the requests sent to other peers.
+    pub(crate) keypair: Keypair,
     /// When true, we don't filter our local addresses
     pub(crate) local: bool,
     pub(crate) is_client: bool,
@@ -724,6 +768,8 @@ pub struct SwarmDriver {
     // Peers that having live connection to. Any peer got contacted during kad network query
     // will have live connection established. And they may not appear in the RT.
     pub(crate) live_connected_peers: BTreeMap<ConnectionId, (PeerId, Instant)>,
+    // The identify protocol version advertised by each connected peer.
+    pub(crate) peer_protocol_versions: BTreeMap<PeerId, String>,
     // Record the handling time of the recent 10 for each handling kind.
     handling_statistics: BTreeMap<String, Vec<Duration>>,
     handled_times: usize,
@@ -731,6 +777,7 @@ pub struct SwarmDriver {
     pub(crate) bad_nodes: BadNodes,
     pub(crate) quotes_history: BTreeMap<PeerId, PaymentQuote>,
     pub(crate) replication_targets: BTreeMap<PeerId, Instant>,
+    pub(crate) transfer_notice_validator: TransferNoticeValidator,
 }
 
 impl SwarmDriver {
@@ -842,6 +889,21 @@ impl SwarmDriver {
         farthest_distance
     }
 
+    /// The queue depths and in-flight task counts of the driver. The sends waiting for room in
+    /// the cmd channels are counted by the `Network` handles they are spawned by.
+    pub(crate) fn driver_stats(&self) -> SwarmDriverStats {
+        SwarmDriverStats {
+            local_cmd_backlog: channel_backlog(&self.local_cmd_sender),
+            network_cmd_backlog: channel_backlog(&self.network_cmd_sender),
+            event_backlog: channel_backlog(&self.event_sender),
+            pending_cmd_sends: 0,
+            pending_get_closest_peers: self.pending_get_closest_peers.len(),
+            pending_requests: self.pending_requests.len(),
+            pending_get_record: self.pending_get_record.len(),
+            live_connections: self.live_connected_peers.len(),
+        }
+    }
+
     /// Pushes NetworkSwarmCmd off thread so as to be non-blocking
     /// this is a wrapper around the `mpsc::Sender::send` call
     pub(crate) fn queue_network_swarm_cmd(&self, event: NetworkSwarmCmd) {
@@ -979,3 +1041,8 @@ impl SwarmDriver {
         Ok(())
     }
 }
+
+/// The number of messages queued in the channel of the sender.
+pub(crate) fn channel_backlog<T>(sender: &mpsc::Sender<T>) -> usize {
+    sender.max_capacity() - sender.capacity()
+}
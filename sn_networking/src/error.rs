@@ -19,6 +19,7 @@ use std::{
     fmt::Debug,
     io,
     path::PathBuf,
+    time::Duration,
 };
 use thiserror::Error;
 use tokio::sync::oneshot;
@@ -150,6 +151,9 @@ pub enum NetworkError {
     },
 
     // ---------- Internal Network Errors
+    #[error("The network is busy with the cmds queued so far, retry after {retry_after:?}")]
+    Busy { retry_after: Duration },
+
     #[error("Could not get enough peers ({required}) to satisfy the request, found {found}")]
     NotEnoughPeers { found: usize, required: usize },
 
@@ -167,6 +171,15 @@ pub enum NetworkError {
     #[error("A Kademlia event has been dropped: {query_id:?} {event}")]
     ReceivedKademliaEventDropped { query_id: QueryId, event: String },
 
+    #[error("Could not configure gossipsub: {0}")]
+    GossipsubConfig(String),
+
+    #[error("Gossipsub subscription error: {0}")]
+    GossipsubSubscription(#[from] libp2p::gossipsub::SubscriptionError),
+
+    #[error("Gossipsub publishing error: {0}")]
+    GossipsubPublishing(#[from] libp2p::gossipsub::PublishError),
+
     #[error("The oneshot::sender has been dropped")]
     SenderDropped(#[from] oneshot::error::RecvError),
 
@@ -11,6 +11,7 @@ mod request_response;
 mod swarm;
 
 use crate::{driver::SwarmDriver, error::Result};
+use bytes::Bytes;
 use core::fmt;
 use custom_debug::Debug as CustomDebug;
 #[cfg(feature = "local-discovery")]
@@ -22,7 +23,7 @@ use libp2p::{
 };
 
 use sn_protocol::{
-    messages::{Query, Request, Response},
+    messages::{Query, Request, RequestSigner, Response},
     NetworkAddress, PrettyPrintRecordKey,
 };
 use sn_transfers::PaymentQuote;
@@ -44,6 +45,7 @@ pub(super) enum NodeEvent {
     Identify(Box<libp2p::identify::Event>),
     RelayClient(Box<libp2p::relay::client::Event>),
     RelayServer(Box<libp2p::relay::Event>),
+    Gossipsub(Box<libp2p::gossipsub::Event>),
     Void(void::Void),
 }
 
@@ -89,6 +91,12 @@ impl From<libp2p::relay::Event> for NodeEvent {
     }
 }
 
+impl From<libp2p::gossipsub::Event> for NodeEvent {
+    fn from(event: libp2p::gossipsub::Event) -> Self {
+        NodeEvent::Gossipsub(Box::new(event))
+    }
+}
+
 impl From<void::Void> for NodeEvent {
     fn from(event: void::Void) -> Self {
         NodeEvent::Void(event)
@@ -110,6 +118,10 @@ pub enum NetworkEvent {
     QueryRequestReceived {
         /// Query
         query: Query,
+        /// The peer the query came from, as authenticated by the connection
+        peer: PeerId,
+        /// The key the query was signed with, if it was
+        signer: Option<RequestSigner>,
         /// The channel to send the `Response` through
         channel: MsgResponder,
     },
@@ -144,6 +156,13 @@ pub enum NetworkEvent {
         peer_id: PeerId,
         keys_to_verify: Vec<NetworkAddress>,
     },
+    /// A message published on a gossipsub topic we are subscribed to
+    GossipsubMsg {
+        /// Topic the message was published on
+        topic: String,
+        /// The raw bytes of the message
+        msg: Bytes,
+    },
 }
 
 /// Terminate node for the following reason
@@ -211,6 +230,13 @@ impl Debug for NetworkEvent {
                     "NetworkEvent::ChunkProofVerification({peer_id:?} {keys_to_verify:?})"
                 )
             }
+            NetworkEvent::GossipsubMsg { topic, msg } => {
+                write!(
+                    f,
+                    "NetworkEvent::GossipsubMsg({topic:?}, {} bytes)",
+                    msg.len()
+                )
+            }
         }
     }
 }
@@ -11,10 +11,12 @@ use crate::{
     NetworkEvent, SwarmDriver, CLOSE_GROUP_SIZE,
 };
 use itertools::Itertools;
+use libp2p::request_response::ResponseChannel;
 use libp2p::request_response::{self, Message};
 use rand::{rngs::OsRng, thread_rng, Rng};
 use sn_protocol::{
-    messages::{CmdResponse, Request, Response},
+    error::Error as ProtocolError,
+    messages::{CmdResponse, Request, RequestSigner, Response},
     storage::RecordType,
     NetworkAddress,
 };
@@ -34,11 +36,40 @@ impl SwarmDriver {
                     ..
                 } => {
                     debug!("Received request {request_id:?} from peer {peer:?}, req: {request:?}");
+                    let (request, signer) = match request {
+                        Request::Authenticated { request, auth } => {
+                            // A peer signing with its libp2p identity can only sign as itself.
+                            let verified =
+                                auth.verify(&request)
+                                    .and_then(|()| match auth.signer.peer_id() {
+                                        Some(signer) if signer != peer => {
+                                            Err(ProtocolError::RequestAuthInvalid)
+                                        }
+                                        _ => Ok(()),
+                                    });
+                            if let Err(err) = verified {
+                                warn!("Rejecting request {request_id:?} from peer {peer:?}: {err}");
+                                self.reject_request(err, channel);
+                                return Ok(());
+                            }
+                            debug!(
+                                "Request {request_id:?} from peer {peer:?} is signed by {:?}",
+                                auth.signer
+                            );
+                            (*request, Some(auth.signer))
+                        }
+                        request => (request, None),
+                    };
                     // If the request is replication or quote verification,
                     // we can handle it and send the OK response here.
                     // As the handle result is unimportant to the sender.
                     match request {
                         Request::Cmd(sn_protocol::messages::Cmd::Replicate { holder, keys }) => {
+                            if !is_claimed_by_signer(signer.as_ref(), &holder) {
+                                warn!("Rejecting replicate cmd {request_id:?} from peer {peer:?}, it is not signed by the holder {holder:?}");
+                                self.reject_request(ProtocolError::RequestAuthInvalid, channel);
+                                return Ok(());
+                            }
                             let response = Response::Cmd(
                                 sn_protocol::messages::CmdResponse::Replicate(Ok(())),
                             );
@@ -79,6 +110,11 @@ impl SwarmDriver {
                             bad_peer,
                             bad_behaviour,
                         }) => {
+                            if !is_claimed_by_signer(signer.as_ref(), &detected_by) {
+                                warn!("Rejecting bad peer notification {request_id:?} from peer {peer:?}, it is not signed by the detector {detected_by:?}");
+                                self.reject_request(ProtocolError::RequestAuthInvalid, channel);
+                                return Ok(());
+                            }
                             let response = Response::Cmd(
                                 sn_protocol::messages::CmdResponse::PeerConsideredAsBad(Ok(())),
                             );
@@ -110,9 +146,19 @@ impl SwarmDriver {
                         Request::Query(query) => {
                             self.send_event(NetworkEvent::QueryRequestReceived {
                                 query,
+                                peer,
+                                signer,
                                 channel: MsgResponder::FromPeer(channel),
                             })
                         }
+                        Request::Authenticated { .. } => {
+                            warn!("Rejecting request {request_id:?} from peer {peer:?}, it is signed more than once");
+                            self.reject_request(ProtocolError::RequestAuthInvalid, channel);
+                        }
+                        Request::Unknown(_)
+                        | Request::Cmd(sn_protocol::messages::Cmd::Unknown(_)) => {
+                            warn!("Dropping request {request_id:?} from peer {peer:?}, it is unknown to this version");
+                        }
                     }
                 }
                 Message::Response {
@@ -126,11 +172,21 @@ impl SwarmDriver {
                         // Else the Request was just sent to the peer and the Response was
                         // meant to be handled in another way and is not awaited.
                         match sender {
-                            Some(sender) => sender
-                                .send(Ok(response))
-                                .map_err(|_| NetworkError::InternalMsgChannelDropped)?,
+                            Some(sender) => {
+                                let result = match response {
+                                    Response::Rejected(err) => Err(err.into()),
+                                    response => Ok(response),
+                                };
+                                sender
+                                    .send(result)
+                                    .map_err(|_| NetworkError::InternalMsgChannelDropped)?
+                            }
                             None => {
-                                if let Response::Cmd(CmdResponse::Replicate(Ok(()))) = response {
+                                if let Response::Rejected(err) = response {
+                                    warn!("Request {request_id:?} was rejected by peer {peer:?}: {err}");
+                                } else if let Response::Cmd(CmdResponse::Replicate(Ok(()))) =
+                                    response
+                                {
                                     // Nothing to do, response was fine
                                     // This only exists to ensure we dont drop the handle and
                                     // exit early, potentially logging false connection woes
@@ -185,6 +241,14 @@ impl SwarmDriver {
         Ok(())
     }
 
+    /// Reply to a request refused before being handled, for the requester not to wait on it.
+    fn reject_request(&mut self, err: ProtocolError, channel: ResponseChannel<Response>) {
+        self.queue_network_swarm_cmd(NetworkSwarmCmd::SendResponse {
+            resp: Response::Rejected(err),
+            channel: MsgResponder::FromPeer(channel),
+        });
+    }
+
     fn add_keys_to_replication_fetcher(
         &mut self,
         sender: NetworkAddress,
@@ -315,3 +379,12 @@ impl SwarmDriver {
         }
     }
 }
+
+/// Whether the identity a cmd claims, e.g. the holder of replicated keys, is the one it is signed
+/// with. Unsigned cmds, sent by peers of earlier versions, can't be checked.
+fn is_claimed_by_signer(signer: Option<&RequestSigner>, claimed: &NetworkAddress) -> bool {
+    match signer {
+        Some(signer) => signer.peer_id().is_some() && signer.peer_id() == claimed.as_peer_id(),
+        None => true,
+    }
+}
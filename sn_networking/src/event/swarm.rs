@@ -10,6 +10,7 @@ use crate::{
     cmd::LocalSwarmCmd, event::NodeEvent, multiaddr_is_global, multiaddr_strip_p2p,
     relay_manager::is_a_relayed_peer, target_arch::Instant, NetworkEvent, Result, SwarmDriver,
 };
+use bytes::Bytes;
 #[cfg(feature = "local-discovery")]
 use libp2p::mdns;
 #[cfg(feature = "open-metrics")]
@@ -23,7 +24,9 @@ use libp2p::{
     },
     Multiaddr, PeerId, TransportError,
 };
-use sn_protocol::version::{IDENTIFY_NODE_VERSION_STR, IDENTIFY_PROTOCOL_STR};
+use sn_protocol::version::{
+    check_protocol_compatibility, IDENTIFY_NODE_VERSION_STR, IDENTIFY_PROTOCOL_STR,
+};
 use std::collections::HashSet;
 use tokio::time::Duration;
 
@@ -106,6 +109,42 @@ impl SwarmDriver {
                     _ => {}
                 }
             }
+            SwarmEvent::Behaviour(NodeEvent::Gossipsub(event)) => {
+                event_string = "gossipsub";
+
+                match *event {
+                    libp2p::gossipsub::Event::Message {
+                        propagation_source,
+                        message_id,
+                        message,
+                    } => {
+                        let acceptance = self.transfer_notice_validator.validate(&message);
+                        let accepted =
+                            matches!(acceptance, libp2p::gossipsub::MessageAcceptance::Accept);
+                        if !accepted {
+                            debug!("Not relaying the gossip message {message_id:?} from {propagation_source:?}: {acceptance:?}");
+                        }
+                        if let Err(err) = self
+                            .swarm
+                            .behaviour_mut()
+                            .gossipsub
+                            .report_message_validation_result(
+                                &message_id,
+                                &propagation_source,
+                                acceptance,
+                            )
+                        {
+                            warn!("Could not report the validation of the gossip message {message_id:?}: {err:?}");
+                        }
+                        if accepted {
+                            let topic = message.topic.into_string();
+                            let msg = Bytes::from(message.data);
+                            self.send_event(NetworkEvent::GossipsubMsg { topic, msg });
+                        }
+                    }
+                    other => trace!("Gossipsub event: {other:?}"),
+                }
+            }
             SwarmEvent::Behaviour(NodeEvent::Identify(iden)) => {
                 // Record the Identify event for metrics if the feature is enabled.
                 #[cfg(feature = "open-metrics")]
@@ -122,8 +161,8 @@ impl SwarmDriver {
                     } => {
                         debug!(conn_id=%connection_id, %peer_id, ?info, "identify: received info");
 
-                        if info.protocol_version != IDENTIFY_PROTOCOL_STR.to_string() {
-                            warn!(?info.protocol_version, "identify: {peer_id:?} does not have the same protocol. Our IDENTIFY_PROTOCOL_STR: {:?}", IDENTIFY_PROTOCOL_STR.as_str());
+                        if let Err(err) = check_protocol_compatibility(&info.protocol_version) {
+                            warn!(?info.protocol_version, "identify: {peer_id:?} does not have a compatible protocol: {err}");
 
                             self.send_event(NetworkEvent::PeerWithUnsupportedProtocol {
                                 our_protocol: IDENTIFY_PROTOCOL_STR.to_string(),
@@ -140,6 +179,9 @@ impl SwarmDriver {
 
                             return Ok(());
                         }
+                        let _ = self
+                            .peer_protocol_versions
+                            .insert(peer_id, info.protocol_version.clone());
 
                         // if client, return.
                         if info.agent_version != IDENTIFY_NODE_VERSION_STR.to_string() {
@@ -379,6 +421,9 @@ impl SwarmDriver {
                 event_string = "ConnectionClosed";
                 debug!(%peer_id, ?connection_id, ?cause, num_established, "ConnectionClosed: {}", endpoint_str(&endpoint));
                 let _ = self.live_connected_peers.remove(&connection_id);
+                if num_established == 0 {
+                    let _ = self.peer_protocol_versions.remove(&peer_id);
+                }
                 self.record_connection_metrics();
             }
             SwarmEvent::OutgoingConnectionError {
@@ -11,6 +11,7 @@ extern crate tracing;
 
 mod bootstrap;
 mod circular_vec;
+mod close_group_check;
 mod cmd;
 mod driver;
 mod error;
@@ -26,6 +27,7 @@ mod record_store;
 mod record_store_api;
 mod relay_manager;
 mod replication_fetcher;
+mod spend_cache;
 mod spends;
 pub mod target_arch;
 mod transfers;
@@ -38,18 +40,25 @@ use xor_name::XorName;
 pub use target_arch::{interval, sleep, spawn, Instant, Interval};
 
 pub use self::{
-    cmd::{NodeIssue, SwarmLocalState},
+    close_group_check::CloseGroupConsistency,
+    cmd::{NodeIssue, SwarmDriverStats, SwarmLocalState},
     driver::{
-        GetRecordCfg, NetworkBuilder, PutRecordCfg, SwarmDriver, VerificationKind, MAX_PACKET_SIZE,
+        GetRecordCfg, NetworkBuilder, PutRecordCfg, SwarmDriver, VerificationKind,
+        MAX_GOSSIP_MSG_SIZE, MAX_PACKET_SIZE,
     },
     error::{GetRecordError, NetworkError},
     event::{MsgResponder, NetworkEvent},
-    record_store::{calculate_cost_for_records, NodeRecordStore},
-    transfers::{get_raw_signed_spends_from_record, get_signed_spend_from_record},
+    record_store::{calculate_cost_for_records, NodeRecordStore, NodeRecordStoreConfig},
+    spend_cache::{SpendCache, SpendCacheConfig},
+    transfers::{
+        get_raw_signed_spends_from_record, get_signed_spend_from_record, transfer_notifs_topic,
+        transfer_notifs_topics, TRANSFER_NOTIFS_TOPIC_PREFIX,
+    },
 };
 
 use self::{cmd::NetworkSwarmCmd, error::Result};
 use backoff::{Error as BackoffError, ExponentialBackoff};
+use bytes::Bytes;
 use futures::future::select_all;
 use libp2p::{
     identity::Keypair,
@@ -58,11 +67,11 @@ use libp2p::{
     request_response::OutboundFailure,
     Multiaddr, PeerId,
 };
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng};
 use sn_protocol::{
     error::Error as ProtocolError,
     messages::{ChunkProof, Cmd, Nonce, Query, QueryResponse, Request, Response},
-    storage::{RecordType, RetryStrategy},
+    storage::{PaymentStrategy, RecordType, RetryStrategy},
     NetworkAddress, PrettyPrintKBucketKey, PrettyPrintRecordKey, CLOSE_GROUP_SIZE,
 };
 use sn_transfers::{MainPubkey, NanoTokens, PaymentQuote, QuotingMetrics};
@@ -70,7 +79,10 @@ use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
     net::IpAddr,
     path::PathBuf,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 use tokio::sync::{
     mpsc::{self, Sender},
@@ -93,6 +105,12 @@ pub const fn close_group_majority() -> usize {
     CLOSE_GROUP_SIZE / 2 + 1
 }
 
+/// The sends of cmds waiting for room in the full channels of the SwarmDriver past which the
+/// `Network` reports being busy, rather than spawning more of them.
+const MAX_PENDING_CMD_SENDS: usize = 1_000;
+/// How long a caller is asked to wait before retrying when the `Network` is busy.
+const BUSY_RETRY_AFTER: Duration = Duration::from_millis(500);
+
 /// Max duration to wait for verification.
 const MAX_WAIT_BEFORE_READING_A_PUT: Duration = Duration::from_millis(750);
 /// Min duration to wait for verification
@@ -163,6 +181,10 @@ struct NetworkInner {
     peer_id: PeerId,
     root_dir_path: PathBuf,
     keypair: Keypair,
+    /// The spawned sends of cmds waiting for room in a full channel of the SwarmDriver
+    pending_cmd_sends: Arc<AtomicUsize>,
+    /// The spends fetched and verified recently
+    spend_cache: SpendCache,
 }
 
 impl Network {
@@ -172,6 +194,7 @@ impl Network {
         peer_id: PeerId,
         root_dir_path: PathBuf,
         keypair: Keypair,
+        spend_cache_config: SpendCacheConfig,
     ) -> Self {
         Self {
             inner: Arc::new(NetworkInner {
@@ -180,6 +203,8 @@ impl Network {
                 peer_id,
                 root_dir_path,
                 keypair,
+                pending_cmd_sends: Default::default(),
+                spend_cache: SpendCache::new(spend_cache_config),
             }),
         }
     }
@@ -199,6 +224,11 @@ impl Network {
         &self.inner.root_dir_path
     }
 
+    /// Returns the cache of the spends fetched from the network and verified.
+    pub fn spend_cache(&self) -> &SpendCache {
+        &self.inner.spend_cache
+    }
+
     /// Get the sender to send a `NetworkSwarmCmd` to the underlying `Swarm`.
     pub(crate) fn network_swarm_cmd_sender(&self) -> &mpsc::Sender<NetworkSwarmCmd> {
         &self.inner.network_swarm_cmd_sender
@@ -208,6 +238,32 @@ impl Network {
         &self.inner.local_swarm_cmd_sender
     }
 
+    /// Returns the queue depths and in-flight task counts of the SwarmDriver, along with the
+    /// sends of cmds spawned by this `Network` still waiting for room in a full channel.
+    pub async fn get_driver_stats(&self) -> Result<SwarmDriverStats> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_local_swarm_cmd(LocalSwarmCmd::GetDriverStats { sender });
+        let mut stats = receiver
+            .await
+            .map_err(|_e| NetworkError::InternalMsgChannelDropped)?;
+        stats.pending_cmd_sends = self.inner.pending_cmd_sends.load(Ordering::Relaxed);
+        Ok(stats)
+    }
+
+    /// Fails with `NetworkError::Busy` when the SwarmDriver lags behind the cmds sent to it, for
+    /// callers to back off instead of queueing ever more of them.
+    pub fn check_backpressure(&self) -> Result<()> {
+        let is_full = self.network_swarm_cmd_sender().capacity() == 0
+            || self.inner.pending_cmd_sends.load(Ordering::Relaxed) >= MAX_PENDING_CMD_SENDS;
+        if is_full {
+            warn!("The SwarmDriver is lagging behind its cmds, reporting the network as busy");
+            return Err(NetworkError::Busy {
+                retry_after: BUSY_RETRY_AFTER,
+            });
+        }
+        Ok(())
+    }
+
     /// Signs the given data with the node's keypair.
     pub fn sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
         self.keypair().sign(msg).map_err(NetworkError::from)
@@ -231,6 +287,29 @@ impl Network {
         receiver.await?
     }
 
+    /// Subscribe to a gossipsub topic, for the messages published on it to be emitted as
+    /// `NetworkEvent::GossipsubMsg`, and relayed to the other subscribers.
+    pub async fn subscribe_to_topic(&self, topic: String) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_network_swarm_cmd(NetworkSwarmCmd::GossipsubSubscribe { topic, sender });
+        receiver.await?
+    }
+
+    /// Unsubscribe from a gossipsub topic.
+    pub async fn unsubscribe_from_topic(&self, topic: String) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_network_swarm_cmd(NetworkSwarmCmd::GossipsubUnsubscribe { topic, sender });
+        receiver.await?
+    }
+
+    /// Publish a message on a gossipsub topic. It fails if no peer we know of is subscribed to
+    /// the topic.
+    pub async fn publish_on_topic(&self, topic: String, msg: Bytes) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_network_swarm_cmd(NetworkSwarmCmd::GossipsubPublish { topic, msg, sender });
+        receiver.await?
+    }
+
     /// Returns the closest peers to the given `XorName`, sorted by their distance to the xor_name.
     /// Excludes the client's `PeerId` while calculating the closest peers.
     pub async fn client_get_closest_peers(&self, key: &NetworkAddress) -> Result<Vec<PeerId>> {
@@ -255,6 +334,16 @@ impl Network {
             .map_err(|_e| NetworkError::InternalMsgChannelDropped)
     }
 
+    /// Returns the addresses of all the peers of our local Routing Table, to bootstrap from them
+    /// later on. Does not include self.
+    pub async fn get_routing_table_addrs(&self) -> Result<Vec<Multiaddr>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_local_swarm_cmd(LocalSwarmCmd::GetRoutingTableAddrs { sender });
+        receiver
+            .await
+            .map_err(|_e| NetworkError::InternalMsgChannelDropped)
+    }
+
     /// Returns all the PeerId from all the KBuckets from our local Routing Table
     /// Also contains our own PeerId.
     pub async fn get_closest_k_value_local_peers(&self) -> Result<Vec<PeerId>> {
@@ -298,32 +387,9 @@ impl Network {
                 "Getting ChunkProof for {pretty_key:?}. Attempts: {retry_attempts:?}/{total_attempts:?}",
             );
 
-            let request = Request::Query(Query::GetChunkExistenceProof {
-                key: chunk_address.clone(),
-                nonce,
-            });
-            let responses = self
-                .send_and_get_responses(&close_nodes, &request, true)
+            let n_verified = self
+                .count_chunk_existence_proofs(&chunk_address, &close_nodes, nonce, &expected_proof)
                 .await;
-            let n_verified = responses
-                .into_iter()
-                .filter_map(|(peer, resp)| {
-                    if let Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Ok(proof)))) =
-                        resp
-                    {
-                        if expected_proof.verify(&proof) {
-                            debug!("Got a valid ChunkProof from {peer:?}");
-                            Some(())
-                        } else {
-                            warn!("Failed to verify the ChunkProof from {peer:?}. The chunk might have been tampered?");
-                            None
-                        }
-                    } else {
-                        debug!("Did not get a valid response for the ChunkProof from {peer:?}");
-                        None
-                    }
-                })
-                .count();
             debug!("Got {n_verified} verified chunk existence proofs for chunk_address {chunk_address:?}");
 
             if n_verified >= expected_n_verified {
@@ -344,6 +410,59 @@ impl Network {
         ))
     }
 
+    /// Get the number of close nodes to the chunk address holding the chunk, by challenging each of
+    /// them to prove they hold it.
+    pub async fn count_chunk_replicas(
+        &self,
+        chunk_address: NetworkAddress,
+        nonce: Nonce,
+        expected_proof: ChunkProof,
+    ) -> Result<usize> {
+        let close_nodes = self.get_closest_peers(&chunk_address, true).await?;
+        let n_verified = self
+            .count_chunk_existence_proofs(&chunk_address, &close_nodes, nonce, &expected_proof)
+            .await;
+        debug!(
+            "{n_verified} of the {} close nodes hold chunk_address {chunk_address:?}",
+            close_nodes.len()
+        );
+        Ok(n_verified)
+    }
+
+    async fn count_chunk_existence_proofs(
+        &self,
+        chunk_address: &NetworkAddress,
+        close_nodes: &[PeerId],
+        nonce: Nonce,
+        expected_proof: &ChunkProof,
+    ) -> usize {
+        let request = Request::Query(Query::GetChunkExistenceProof {
+            key: chunk_address.clone(),
+            nonce,
+        });
+        let responses = self
+            .send_and_get_responses(close_nodes, &request, true)
+            .await;
+        responses
+            .into_iter()
+            .filter_map(|(peer, resp)| {
+                if let Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Ok(proof)))) = resp
+                {
+                    if expected_proof.verify(&proof) {
+                        debug!("Got a valid ChunkProof from {peer:?}");
+                        Some(())
+                    } else {
+                        warn!("Failed to verify the ChunkProof from {peer:?}. The chunk might have been tampered?");
+                        None
+                    }
+                } else {
+                    debug!("Did not get a valid response for the ChunkProof from {peer:?}");
+                    None
+                }
+            })
+            .count()
+    }
+
     /// Get the store costs from the majority of the closest peers to the provided RecordKey.
     /// Record already exists will have a cost of zero to be returned.
     ///
@@ -354,6 +473,29 @@ impl Network {
         record_address: NetworkAddress,
         ignore_peers: Vec<PeerId>,
     ) -> Result<PayeeQuote> {
+        self.get_store_quotes_from_network(
+            record_address,
+            ignore_peers,
+            PaymentStrategy::CheapestMajority,
+        )
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(NetworkError::NoStoreCostResponses)
+    }
+
+    /// Get the quotes of the payees chosen by `strategy` among the majority of the closest peers
+    /// to the provided RecordKey. The first one is the peer the record is to be uploaded to.
+    /// Record already exists will have a single quote of zero to be returned.
+    ///
+    /// Ignore the quote from any peers from `ignore_peers`. This is useful if we want to repay a different PeerId
+    /// on failure.
+    pub async fn get_store_quotes_from_network(
+        &self,
+        record_address: NetworkAddress,
+        ignore_peers: Vec<PeerId>,
+        strategy: PaymentStrategy,
+    ) -> Result<Vec<PayeeQuote>> {
         // The requirement of having at least CLOSE_GROUP_SIZE
         // close nodes will be checked internally automatically.
         let close_nodes = self.get_closest_peers(&record_address, true).await?;
@@ -366,6 +508,7 @@ impl Network {
         // loop over responses, generating an average fee and storing all responses along side
         let mut all_costs = vec![];
         let mut all_quotes = vec![];
+        let mut node_errors = vec![];
         for response in responses.into_values().flatten() {
             debug!(
                 "StoreCostReq for {record_address:?} received response: {:?}",
@@ -377,6 +520,14 @@ impl Network {
                     payment_address,
                     peer_address,
                 }) => {
+                    // only keep the quotes that the node will accept the payment of
+                    let is_valid = peer_address.as_peer_id().is_some_and(|peer_id| {
+                        quote.is_valid_for(peer_id, record_address.as_xorname().unwrap_or_default())
+                    });
+                    if !is_valid {
+                        warn!("StoreCostReq for {record_address:?} got an invalid quote from {peer_address:?}: {quote:?}");
+                        continue;
+                    }
                     all_costs.push((peer_address.clone(), payment_address, quote.clone()));
                     all_quotes.push((peer_address, quote));
                 }
@@ -387,12 +538,30 @@ impl Network {
                 }) => {
                     all_costs.push((peer_address, payment_address, PaymentQuote::zero()));
                 }
+                Response::Query(QueryResponse::GetStoreCost {
+                    quote: Err(err),
+                    peer_address,
+                    ..
+                }) => {
+                    warn!(
+                        "StoreCostReq for {record_address:?} failed at {peer_address:?} with code {}: {err}",
+                        err.code()
+                    );
+                    node_errors.push(err);
+                }
                 _ => {
                     error!("Non store cost response received,  was {:?}", response);
                 }
             }
         }
 
+        // surface the error of the nodes, so that the caller can tell whether to retry
+        if all_costs.is_empty() {
+            if let Some(err) = node_errors.into_iter().next() {
+                return Err(NetworkError::ProtocolError(err));
+            }
+        }
+
         for peer_id in close_nodes.iter() {
             let request = Request::Cmd(Cmd::QuoteVerification {
                 target: NetworkAddress::from_peer(*peer_id),
@@ -424,7 +593,7 @@ impl Network {
             .take(close_group_majority())
             .collect();
 
-        get_fees_from_store_cost_responses(all_costs)
+        get_fees_from_store_cost_responses(all_costs, strategy)
     }
 
     /// Get register from network.
@@ -642,7 +811,10 @@ impl Network {
             {
                 warn!("Failed to PUT record with key: {pretty_key:?} to network (retry via backoff) with error: {err:?}");
 
-                if cfg.retry_strategy.is_some() {
+                if let (NetworkError::Busy { retry_after }, Some(_)) = (&err, cfg.retry_strategy) {
+                    let retry_after = Some(*retry_after);
+                    BackoffError::Transient { err, retry_after }
+                } else if cfg.retry_strategy.is_some() {
                     BackoffError::Transient { err, retry_after: None }
                 } else {
                     BackoffError::Permanent(err)
@@ -660,6 +832,7 @@ impl Network {
             pretty_key,
             record.value.len()
         );
+        self.check_backpressure()?;
 
         // Waiting for a response to avoid flushing to network too quick that causing choke
         let (sender, receiver) = oneshot::channel();
@@ -858,11 +1031,21 @@ impl Network {
 
     /// Helper to send NetworkSwarmCmd
     fn send_network_swarm_cmd(&self, cmd: NetworkSwarmCmd) {
-        send_network_swarm_cmd(self.network_swarm_cmd_sender().clone(), cmd);
+        let pending_sends = Arc::clone(&self.inner.pending_cmd_sends);
+        send_swarm_cmd(
+            self.network_swarm_cmd_sender().clone(),
+            cmd,
+            Some(pending_sends),
+        );
     }
     /// Helper to send LocalSwarmCmd
     fn send_local_swarm_cmd(&self, cmd: LocalSwarmCmd) {
-        send_local_swarm_cmd(self.local_swarm_cmd_sender().clone(), cmd);
+        let pending_sends = Arc::clone(&self.inner.pending_cmd_sends);
+        send_swarm_cmd(
+            self.local_swarm_cmd_sender().clone(),
+            cmd,
+            Some(pending_sends),
+        );
     }
 
     /// Returns the closest peers to the given `XorName`, sorted by their distance to the xor_name.
@@ -950,40 +1133,49 @@ impl Network {
     }
 }
 
-/// Given `all_costs` it will return the closest / lowest cost
+/// Given `all_costs` it will return the quotes to pay according to `strategy`
 /// Closest requiring it to be within CLOSE_GROUP nodes
 fn get_fees_from_store_cost_responses(
-    all_costs: Vec<(NetworkAddress, MainPubkey, PaymentQuote)>,
-) -> Result<PayeeQuote> {
-    // Find the minimum cost using a linear scan with random tie break
+    mut all_costs: Vec<(NetworkAddress, MainPubkey, PaymentQuote)>,
+    strategy: PaymentStrategy,
+) -> Result<Vec<PayeeQuote>> {
+    if all_costs.is_empty() {
+        return Err(NetworkError::NoStoreCostResponses);
+    }
+
+    // Sort by cost, with random tie break
     let mut rng = rand::thread_rng();
-    let payee = all_costs
-        .into_iter()
-        .min_by(
-            |(_address_a, _main_key_a, cost_a), (_address_b, _main_key_b, cost_b)| {
-                let cmp = cost_a.cost.cmp(&cost_b.cost);
-                if cmp == std::cmp::Ordering::Equal {
-                    if rng.gen() {
-                        std::cmp::Ordering::Less
-                    } else {
-                        std::cmp::Ordering::Greater
-                    }
-                } else {
-                    cmp
-                }
-            },
-        )
-        .ok_or(NetworkError::NoStoreCostResponses)?;
+    all_costs.shuffle(&mut rng);
+    all_costs.sort_by_key(|(_address, _main_key, quote)| quote.cost);
 
-    info!("Final fees calculated as: {payee:?}");
-    // we dont need to have the address outside of here for now
-    let payee_id = if let Some(peer_id) = payee.0.as_peer_id() {
-        peer_id
+    // a record already existing at any of the peers does not need to be paid for
+    let record_exists = all_costs[0].2.cost.is_zero();
+    let payees = if record_exists {
+        all_costs.into_iter().take(1).collect()
     } else {
-        error!("Can't get PeerId from payee {:?}", payee.0);
-        return Err(NetworkError::NoStoreCostResponses);
+        match strategy {
+            PaymentStrategy::CheapestMajority => all_costs.into_iter().take(1).collect(),
+            PaymentStrategy::Median => {
+                let median = (all_costs.len() - 1) / 2;
+                all_costs.into_iter().skip(median).take(1).collect()
+            }
+            PaymentStrategy::All => all_costs,
+        }
     };
-    Ok((payee_id, payee.1, payee.2))
+
+    info!("Final fees calculated with {strategy:?} as: {payees:?}");
+    // we dont need to have the address outside of here for now
+    payees
+        .into_iter()
+        .map(|(address, main_key, quote)| {
+            if let Some(peer_id) = address.as_peer_id() {
+                Ok((peer_id, main_key, quote))
+            } else {
+                error!("Can't get PeerId from payee {address:?}");
+                Err(NetworkError::NoStoreCostResponses)
+            }
+        })
+        .collect()
 }
 
 /// Get the value of the provided Quorum
@@ -1063,26 +1255,15 @@ pub(crate) fn multiaddr_get_ip(addr: &Multiaddr) -> Option<IpAddr> {
 }
 
 pub(crate) fn send_local_swarm_cmd(swarm_cmd_sender: Sender<LocalSwarmCmd>, cmd: LocalSwarmCmd) {
-    let capacity = swarm_cmd_sender.capacity();
-
-    if capacity == 0 {
-        error!(
-            "SwarmCmd channel is full. Await capacity to send: {:?}",
-            cmd
-        );
-    }
-
-    // Spawn a task to send the SwarmCmd and keep this fn sync
-    let _handle = spawn(async move {
-        if let Err(error) = swarm_cmd_sender.send(cmd).await {
-            error!("Failed to send SwarmCmd: {}", error);
-        }
-    });
+    send_swarm_cmd(swarm_cmd_sender, cmd, None);
 }
 
-pub(crate) fn send_network_swarm_cmd(
-    swarm_cmd_sender: Sender<NetworkSwarmCmd>,
-    cmd: NetworkSwarmCmd,
+/// Sends the cmd from a spawned task to keep the caller sync, counting the task among the
+/// `pending_sends` while it waits for room in the channel, if given.
+fn send_swarm_cmd<T: std::fmt::Debug + Send + 'static>(
+    swarm_cmd_sender: Sender<T>,
+    cmd: T,
+    pending_sends: Option<Arc<AtomicUsize>>,
 ) {
     let capacity = swarm_cmd_sender.capacity();
 
@@ -1093,11 +1274,17 @@ pub(crate) fn send_network_swarm_cmd(
         );
     }
 
+    if let Some(pending_sends) = &pending_sends {
+        let _ = pending_sends.fetch_add(1, Ordering::Relaxed);
+    }
     // Spawn a task to send the SwarmCmd and keep this fn sync
     let _handle = spawn(async move {
         if let Err(error) = swarm_cmd_sender.send(cmd).await {
             error!("Failed to send SwarmCmd: {}", error);
         }
+        if let Some(pending_sends) = pending_sends {
+            let _ = pending_sends.fetch_sub(1, Ordering::Relaxed);
+        }
     });
 }
 
@@ -1108,6 +1295,78 @@ mod tests {
     use super::*;
     use sn_transfers::PaymentQuote;
 
+    #[tokio::test]
+    async fn put_record_should_report_busy_when_the_cmd_channel_is_full() -> eyre::Result<()> {
+        let (network_swarm_cmd_sender, _network_swarm_cmd_receiver) = mpsc::channel(1);
+        let (local_swarm_cmd_sender, _local_swarm_cmd_receiver) = mpsc::channel(1);
+        let keypair = Keypair::generate_ed25519();
+        let network = Network::new(
+            network_swarm_cmd_sender.clone(),
+            local_swarm_cmd_sender,
+            keypair.public().to_peer_id(),
+            PathBuf::new(),
+            keypair,
+            Default::default(),
+        );
+        assert!(network.check_backpressure().is_ok());
+
+        // the driver does not keep up: its channel fills up
+        let (sender, _receiver) = oneshot::channel();
+        network_swarm_cmd_sender.try_send(NetworkSwarmCmd::Dial {
+            addr: Multiaddr::empty(),
+            sender,
+        })?;
+        let cfg = PutRecordCfg {
+            put_quorum: Quorum::One,
+            retry_strategy: None,
+            use_put_record_to: None,
+            verification: None,
+        };
+        let record = Record::new(RecordKey::new(&[1]), vec![]);
+        match network.put_record(record, &cfg).await {
+            Err(NetworkError::Busy { retry_after }) => assert_eq!(retry_after, BUSY_RETRY_AFTER),
+            other => bail!("Expected the network to be busy, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn driver_stats_should_report_the_pending_cmd_sends() -> eyre::Result<()> {
+        let (network_swarm_cmd_sender, _network_swarm_cmd_receiver) = mpsc::channel(1);
+        let (local_swarm_cmd_sender, mut local_swarm_cmd_receiver) = mpsc::channel(1);
+        let keypair = Keypair::generate_ed25519();
+        let network = Network::new(
+            network_swarm_cmd_sender,
+            local_swarm_cmd_sender,
+            keypair.public().to_peer_id(),
+            PathBuf::new(),
+            keypair,
+            Default::default(),
+        );
+        // the driver answers the stats, but never takes the network cmds
+        let _driver = tokio::spawn(async move {
+            while let Some(cmd) = local_swarm_cmd_receiver.recv().await {
+                if let LocalSwarmCmd::GetDriverStats { sender } = cmd {
+                    let _ = sender.send(SwarmDriverStats::default());
+                }
+            }
+        });
+        assert_eq!(network.get_driver_stats().await?.pending_cmd_sends, 0);
+
+        // the first dial fills the channel, the second one waits for room in it
+        for _ in 0..2 {
+            let (sender, _receiver) = oneshot::channel();
+            network.send_network_swarm_cmd(NetworkSwarmCmd::Dial {
+                addr: Multiaddr::empty(),
+                sender,
+            });
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(network.get_driver_stats().await?.pending_cmd_sends, 1);
+        assert!(network.check_backpressure().is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_get_fee_from_store_cost_responses() -> Result<()> {
         // for a vec of different costs of CLOSE_GROUP size
@@ -1122,7 +1381,8 @@ mod tests {
             ));
         }
         let expected_price = costs[0].2.cost.as_nano();
-        let (_peer_id, _key, price) = get_fees_from_store_cost_responses(costs)?;
+        let (_peer_id, _key, price) =
+            get_fees_from_store_cost_responses(costs, PaymentStrategy::CheapestMajority)?.remove(0);
 
         assert_eq!(
             price.cost.as_nano(),
@@ -1153,10 +1413,11 @@ mod tests {
         // this should be the lowest price
         let expected_price = costs[0].2.cost.as_nano();
 
-        let (_peer_id, _key, price) = match get_fees_from_store_cost_responses(costs) {
-            Err(_) => bail!("Should not have errored as we have enough responses"),
-            Ok(cost) => cost,
-        };
+        let (_peer_id, _key, price) =
+            match get_fees_from_store_cost_responses(costs, PaymentStrategy::CheapestMajority) {
+                Err(_) => bail!("Should not have errored as we have enough responses"),
+                Ok(mut costs) => costs.remove(0),
+            };
 
         assert_eq!(
             price.cost.as_nano(),
@@ -1167,6 +1428,67 @@ mod tests {
         Ok(())
     }
 
+    fn costs_of(prices: &[u64]) -> Vec<(NetworkAddress, MainPubkey, PaymentQuote)> {
+        prices
+            .iter()
+            .map(|price| {
+                (
+                    NetworkAddress::from_peer(PeerId::random()),
+                    MainPubkey::new(bls::SecretKey::random().public_key()),
+                    PaymentQuote::test_dummy(Default::default(), NanoTokens::from(*price)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_get_median_fee_from_store_cost_responses() -> Result<()> {
+        let payees =
+            get_fees_from_store_cost_responses(costs_of(&[30, 10, 20]), PaymentStrategy::Median)?;
+        assert_eq!(payees.len(), 1);
+        assert_eq!(payees[0].2.cost.as_nano(), 20);
+
+        // the lower of the two medians is paid for an even count of responses
+        let payees = get_fees_from_store_cost_responses(
+            costs_of(&[40, 10, 30, 20]),
+            PaymentStrategy::Median,
+        )?;
+        assert_eq!(payees[0].2.cost.as_nano(), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_all_fees_from_store_cost_responses() -> Result<()> {
+        let payees =
+            get_fees_from_store_cost_responses(costs_of(&[30, 10, 20]), PaymentStrategy::All)?;
+        let prices = payees
+            .iter()
+            .map(|(_, _, quote)| quote.cost.as_nano())
+            .collect::<Vec<_>>();
+        assert_eq!(prices, vec![10, 20, 30]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_record_is_not_paid_whatever_the_strategy() -> Result<()> {
+        for strategy in [
+            PaymentStrategy::CheapestMajority,
+            PaymentStrategy::Median,
+            PaymentStrategy::All,
+        ] {
+            let mut costs = costs_of(&[30, 20]);
+            costs.push((
+                NetworkAddress::from_peer(PeerId::random()),
+                MainPubkey::new(bls::SecretKey::random().public_key()),
+                PaymentQuote::zero(),
+            ));
+            let payees = get_fees_from_store_cost_responses(costs, strategy)?;
+            assert_eq!(payees.len(), 1, "{strategy:?}");
+            assert!(payees[0].2.cost.is_zero(), "{strategy:?}");
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_network_sign_verify() -> eyre::Result<()> {
         let (network, _, _) =
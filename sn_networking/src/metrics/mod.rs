@@ -11,7 +11,11 @@ use libp2p::metrics::{Metrics as Libp2pMetrics, Recorder};
 #[cfg(feature = "upnp")]
 use prometheus_client::metrics::family::Family;
 use prometheus_client::{
-    metrics::{counter::Counter, gauge::Gauge},
+    metrics::{
+        counter::Counter,
+        gauge::Gauge,
+        histogram::{exponential_buckets, Histogram},
+    },
     registry::Registry,
 };
 use sysinfo::{Pid, ProcessRefreshKind, System};
@@ -39,6 +43,8 @@ pub(crate) struct NetworkMetricsRecorder {
     pub(crate) open_connections: Gauge,
     pub(crate) peers_in_routing_table: Gauge,
     pub(crate) records_stored: Gauge,
+    pub(crate) record_read_duration_secs: Histogram,
+    pub(crate) record_write_duration_secs: Histogram,
 
     // store cost
     store_cost: Gauge,
@@ -68,6 +74,20 @@ impl NetworkMetricsRecorder {
             records_stored.clone(),
         );
 
+        // From 100µs to about 3s
+        let record_read_duration_secs = Histogram::new(exponential_buckets(0.0001, 2.0, 16));
+        sub_registry.register(
+            "record_read_duration_secs",
+            "The time taken to read a record from disk, in seconds",
+            record_read_duration_secs.clone(),
+        );
+        let record_write_duration_secs = Histogram::new(exponential_buckets(0.0001, 2.0, 16));
+        sub_registry.register(
+            "record_write_duration_secs",
+            "The time taken to encrypt (when enabled) and write a record to disk, in seconds",
+            record_write_duration_secs.clone(),
+        );
+
         let connected_peers = Gauge::default();
         sub_registry.register(
             "connected_peers",
@@ -169,6 +189,8 @@ impl NetworkMetricsRecorder {
             upnp_events,
 
             records_stored,
+            record_read_duration_secs,
+            record_write_duration_secs,
             estimated_network_size,
             connected_peers,
             open_connections,
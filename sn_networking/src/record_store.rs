@@ -7,6 +7,9 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 #![allow(clippy::mutable_key_type)] // for the Bytes in NetworkAddress
 
+mod record_file;
+
+use self::record_file::RecordFile;
 use crate::cmd::LocalSwarmCmd;
 use crate::driver::MAX_PACKET_SIZE;
 use crate::send_local_swarm_cmd;
@@ -26,7 +29,7 @@ use libp2p::{
     },
 };
 #[cfg(feature = "open-metrics")]
-use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::{gauge::Gauge, histogram::Histogram};
 use rand::RngCore;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
@@ -94,6 +97,9 @@ pub struct NodeRecordStore {
     #[cfg(feature = "open-metrics")]
     /// Used to report the number of records held by the store to the metrics server.
     record_count_metric: Option<Gauge>,
+    #[cfg(feature = "open-metrics")]
+    /// Used to report the time taken to read and write records on disk to the metrics server.
+    record_duration_metrics: Option<(Histogram, Histogram)>,
     /// Counting how many times got paid
     received_payment_count: usize,
     /// Encyption cipher for the records, randomly generated at node startup
@@ -158,6 +164,12 @@ impl NodeRecordStore {
     ) -> HashMap<Key, (NetworkAddress, RecordType)> {
         let process_entry = |entry: &DirEntry| -> _ {
             let path = entry.path();
+            if path.is_file() && record_file::is_temp_file(path) {
+                // the leftover of an interrupted write, the record having never been stored
+                info!("Removing the leftover of an interrupted write: {path:?}");
+                let _ = fs::remove_file(path);
+                return None;
+            }
             if path.is_file() {
                 debug!("Existing record found: {path:?}");
                 // if we've got a file, lets try and read it
@@ -180,21 +192,27 @@ impl NodeRecordStore {
                 };
                 // get the record key from the filename
                 let key = Self::get_data_from_filename(filename)?;
-                let record = match fs::read(path) {
-                    Ok(bytes) => {
-                        // and the stored record
-                        Self::get_record_from_bytes(bytes, &key, encryption_details)?
-                    }
+                let file = match RecordFile::read(path) {
+                    Ok(file) => file,
                     Err(err) => {
                         error!("Error while reading file. filename: {filename}, error: {err:?}");
                         return None;
                     }
                 };
+                // only the type of the record is needed, which is read straight out of the file
+                // when it is stored in the clear
+                let decrypted;
+                let value: &[u8] = if cfg!(feature = "encrypt-records") {
+                    decrypted = Self::get_record_from_bytes(file, &key, encryption_details)?;
+                    &decrypted.value
+                } else {
+                    &file
+                };
 
-                let record_type = match RecordHeader::is_record_of_type_chunk(&record) {
-                    Ok(true) => RecordType::Chunk,
-                    Ok(false) => {
-                        let xorname_hash = XorName::from_content(&record.value);
+                let record_type = match RecordHeader::try_deserialize(value) {
+                    Ok(header) if header.kind == RecordKind::Chunk => RecordType::Chunk,
+                    Ok(_) => {
+                        let xorname_hash = XorName::from_content(value);
                         RecordType::NonChunk(xorname_hash)
                     }
                     Err(error) => {
@@ -295,6 +313,8 @@ impl NodeRecordStore {
             responsible_distance_range: None,
             #[cfg(feature = "open-metrics")]
             record_count_metric: None,
+            #[cfg(feature = "open-metrics")]
+            record_duration_metrics: None,
             received_payment_count,
             encryption_details,
             timestamp,
@@ -315,6 +335,14 @@ impl NodeRecordStore {
         self
     }
 
+    /// Set the histograms to report the time taken to read and write records on disk to the
+    /// metrics server
+    #[cfg(feature = "open-metrics")]
+    pub fn set_record_duration_metrics(mut self, read: Histogram, write: Histogram) -> Self {
+        self.record_duration_metrics = Some((read, write));
+        self
+    }
+
     /// Returns the current distance ilog2 (aka bucket) range of CLOSE_GROUP nodes.
     pub fn get_responsible_distance_range(&self) -> Option<u32> {
         self.responsible_distance_range
@@ -337,31 +365,29 @@ impl NodeRecordStore {
     }
 
     /// Upon read perform any data transformations required to return a `Record`.
+    /// When encrypting, the record is decrypted straight out of the file, mapped or not.
     fn get_record_from_bytes<'a>(
-        bytes: Vec<u8>,
+        bytes: RecordFile,
         key: &Key,
         encryption_details: &(Aes256GcmSiv, [u8; 4]),
     ) -> Option<Cow<'a, Record>> {
-        let mut record = Record {
+        let record = |value| Record {
             key: key.clone(),
-            value: bytes,
+            value,
             publisher: None,
             expires: None,
         };
 
         // if we're not encrypting, lets just return the record
         if !cfg!(feature = "encrypt-records") {
-            return Some(Cow::Owned(record));
+            return Some(Cow::Owned(record(bytes.into_vec())));
         }
 
         let (cipher, nonce_starter) = encryption_details;
         let nonce = generate_nonce_for_record(nonce_starter, key);
 
-        match cipher.decrypt(&nonce, record.value.as_ref()) {
-            Ok(value) => {
-                record.value = value;
-                return Some(Cow::Owned(record));
-            }
+        match cipher.decrypt(&nonce, &*bytes) {
+            Ok(value) => Some(Cow::Owned(record(value))),
             Err(error) => {
                 error!("Error while decrypting record. key: {key:?}: {error:?}");
                 None
@@ -380,7 +406,7 @@ impl NodeRecordStore {
         let file_path = storage_dir.join(&filename);
 
         // we should only be reading if we know the record is written to disk properly
-        match fs::read(file_path) {
+        match RecordFile::read(&file_path) {
             Ok(bytes) => {
                 // vdash metric (if modified please notify at https://github.com/happybeing/vdash/issues):
                 info!(
@@ -567,7 +593,7 @@ impl NodeRecordStore {
     ///
     /// The record is marked as written to disk once `mark_as_stored` is called,
     /// this avoids us returning half-written data or registering it as stored before it is.
-    pub(crate) fn put_verified(&mut self, r: Record, record_type: RecordType) -> Result<()> {
+    pub fn put_verified(&mut self, r: Record, record_type: RecordType) -> Result<()> {
         let key = &r.key;
         let record_key = PrettyPrintRecordKey::from(&r.key).into_owned();
         debug!("PUTting a verified Record: {record_key:?}");
@@ -614,15 +640,26 @@ impl NodeRecordStore {
 
         let encryption_details = self.encryption_details.clone();
         let cloned_cmd_sender = self.local_swarm_cmd_sender.clone();
+        #[cfg(feature = "open-metrics")]
+        let write_metric = self
+            .record_duration_metrics
+            .as_ref()
+            .map(|(_, write_metric)| write_metric.clone());
 
         let record_key2 = record_key.clone();
         spawn(async move {
+            #[cfg(feature = "open-metrics")]
+            let start = Instant::now();
             let key = r.key.clone();
             if let Some(bytes) = Self::prepare_record_bytes(r, encryption_details) {
-                let cmd = match fs::write(&file_path, bytes) {
+                let cmd = match record_file::write(&file_path, &bytes) {
                     Ok(_) => {
                         // vdash metric (if modified please notify at https://github.com/happybeing/vdash/issues):
                         info!("Wrote record {record_key2:?} to disk! filename: {filename}");
+                        #[cfg(feature = "open-metrics")]
+                        if let Some(write_metric) = write_metric {
+                            write_metric.observe(start.elapsed().as_secs_f64());
+                        }
 
                         LocalSwarmCmd::AddLocalRecordAsStored { key, record_type }
                     }
@@ -747,7 +784,14 @@ impl RecordStore for NodeRecordStore {
 
         debug!("GET request for Record key: {key}");
 
-        Self::read_from_disk(&self.encryption_details, k, &self.config.storage_dir)
+        #[cfg(feature = "open-metrics")]
+        let start = Instant::now();
+        let record = Self::read_from_disk(&self.encryption_details, k, &self.config.storage_dir);
+        #[cfg(feature = "open-metrics")]
+        if let Some((read_metric, _)) = &self.record_duration_metrics {
+            read_metric.observe(start.elapsed().as_secs_f64());
+        }
+        record
     }
 
     fn put(&mut self, record: Record) -> Result<()> {
@@ -955,7 +999,7 @@ mod tests {
 
     use super::*;
     use bls::SecretKey;
-    use sn_protocol::storage::{Scratchpad, ScratchpadAddress};
+    use sn_protocol::storage::{PaymentStrategy, Scratchpad, ScratchpadAddress};
     use xor_name::XorName;
 
     use bytes::Bytes;
@@ -1776,6 +1820,8 @@ mod tests {
                 },
                 pub_key: peer.pk.to_bytes().to_vec(),
                 signature: vec![], // unimportant for cost calc
+                expiry: None,      // unimportant for cost calc
+                reward_address: None,
             };
 
             costs_vec.push((peer.address.clone(), peer.pk, quote));
@@ -1784,7 +1830,11 @@ mod tests {
         // sort by address first
         costs_vec.sort_by(|(a_addr, _, _), (b_addr, _, _)| a_addr.cmp(b_addr));
 
-        let Ok((recip_id, _pk, q)) = get_fees_from_store_cost_responses(costs_vec) else {
+        let Some((recip_id, _pk, q)) =
+            get_fees_from_store_cost_responses(costs_vec, PaymentStrategy::CheapestMajority)
+                .ok()
+                .and_then(|mut payees| payees.pop())
+        else {
             bail!("Failed to get fees from store cost responses")
         };
 
@@ -0,0 +1,154 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Reading and writing of the files holding the records.
+//!
+//! With the `mmap-records` feature, large record files are memory-mapped on read rather than
+//! read into a buffer. Encrypted records are decrypted straight out of the page cache, saving the
+//! buffer of the ciphertext, and the records found on start up are indexed from the mapping
+//! without being copied at all. A record served in the clear is copied once out of the mapping,
+//! kad records owning their value, which then moves into the outbound response without further
+//! copies. Without the feature, or when a file cannot be mapped, the file is read into a buffer.
+//!
+//! A mapped file must not be truncated while mapped, so record files are never rewritten in
+//! place: they are written to a temporary file which is then renamed over the record file, any
+//! mapping of the previous one being left untouched. Removing a mapped file is fine as well.
+
+use std::{
+    fs, io,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// The extension of the temporary files the records are written to before being renamed.
+pub(super) const TEMP_FILE_EXTENSION: &str = "tmp";
+
+/// The size from which a record file is mapped rather than read, below which the cost of setting
+/// up the mapping outweighs that of the copy.
+#[cfg(all(feature = "mmap-records", not(target_arch = "wasm32")))]
+const MMAP_MIN_FILE_SIZE: u64 = 64 * 1024;
+
+/// The content of a record file.
+pub(super) enum RecordFile {
+    #[cfg(all(feature = "mmap-records", not(target_arch = "wasm32")))]
+    Mapped(memmap2::Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl RecordFile {
+    /// Read the record file at the given path, mapping it if large enough, falling back to a
+    /// buffered read if it cannot be.
+    pub(super) fn read(path: &Path) -> io::Result<Self> {
+        #[cfg(all(feature = "mmap-records", not(target_arch = "wasm32")))]
+        {
+            let file = fs::File::open(path)?;
+            if file.metadata()?.len() >= MMAP_MIN_FILE_SIZE {
+                // SAFETY: record files are only ever replaced through a rename or removed, never
+                // modified in place, so the mapped content cannot change while mapped.
+                #[allow(unsafe_code)]
+                match unsafe { memmap2::Mmap::map(&file) } {
+                    Ok(map) => return Ok(Self::Mapped(map)),
+                    Err(err) => {
+                        warn!("Failed to map the record file {path:?}, reading it instead: {err:?}")
+                    }
+                }
+            }
+        }
+
+        fs::read(path).map(Self::Buffered)
+    }
+
+    /// The content of the file as an owned buffer, copied out of the mapping if mapped.
+    pub(super) fn into_vec(self) -> Vec<u8> {
+        match self {
+            #[cfg(all(feature = "mmap-records", not(target_arch = "wasm32")))]
+            Self::Mapped(map) => map.to_vec(),
+            Self::Buffered(bytes) => bytes,
+        }
+    }
+
+    /// Whether the file was mapped rather than read.
+    #[cfg(test)]
+    fn is_mapped(&self) -> bool {
+        !matches!(self, Self::Buffered(_))
+    }
+}
+
+impl Deref for RecordFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(all(feature = "mmap-records", not(target_arch = "wasm32")))]
+            Self::Mapped(map) => map,
+            Self::Buffered(bytes) => bytes,
+        }
+    }
+}
+
+/// Write the record file at the given path, replacing any previous one at once.
+pub(super) fn write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp_path = temp_file_path(path);
+    if let Err(err) = fs::write(&temp_path, bytes) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(err);
+    }
+    fs::rename(&temp_path, path)
+}
+
+/// Whether the given path is that of a temporary file left over by an interrupted write.
+pub(super) fn is_temp_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == TEMP_FILE_EXTENSION)
+}
+
+fn temp_file_path(path: &Path) -> PathBuf {
+    path.with_extension(TEMP_FILE_EXTENSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    #[test]
+    fn record_files_read_back_whatever_their_size() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for size in [0, 1024, 256 * 1024] {
+            let content: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let path = dir.path().join(format!("{size:x}"));
+            write(&path, &content)?;
+            assert!(!temp_file_path(&path).exists());
+
+            let file = RecordFile::read(&path)?;
+            let mapped = cfg!(all(feature = "mmap-records", not(target_arch = "wasm32")))
+                && size >= 64 * 1024;
+            assert_eq!(file.is_mapped(), mapped);
+            assert_eq!(&*file, &content[..]);
+            assert_eq!(file.into_vec(), content);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rewriting_a_record_file_leaves_its_mapping_intact() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("record");
+        let old = vec![1u8; 128 * 1024];
+        write(&path, &old)?;
+        let file = RecordFile::read(&path)?;
+
+        write(&path, &[2u8; 16])?;
+        assert_eq!(&*file, &old[..]);
+        assert_eq!(RecordFile::read(&path)?.into_vec(), vec![2u8; 16]);
+
+        fs::remove_file(&path)?;
+        assert_eq!(&*file, &old[..]);
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::target_arch::Instant;
+use lru::LruCache;
+use sn_protocol::storage::SpendAddress;
+use sn_transfers::SignedSpend;
+use std::{sync::Mutex, time::Duration};
+
+/// The default number of spends kept in the cache.
+const DEFAULT_MAX_SPENDS: usize = 10_000;
+/// The default time a spend is served from the cache.
+const DEFAULT_SPEND_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The bounds of the `SpendCache`.
+#[derive(Debug, Clone, Copy)]
+pub struct SpendCacheConfig {
+    /// The number of spends kept, the least recently used evicted past it.
+    /// Zero disables the cache.
+    pub max_spends: usize,
+    /// How long a spend is served from the cache once fetched.
+    ///
+    /// A valid spend never changes, but a double spend of its key can still show up at its
+    /// address afterwards, so it is fetched again past this, for that to be noticed.
+    pub ttl: Duration,
+}
+
+impl Default for SpendCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_spends: DEFAULT_MAX_SPENDS,
+            ttl: DEFAULT_SPEND_TTL,
+        }
+    }
+}
+
+/// The spends fetched from the network and verified, for the popular ones, e.g. the descendants
+/// of genesis, not to be fetched again and again during an audit.
+///
+/// It is only served to the crawls of the spend DAG. The fetches with a quorum, those looking for
+/// double spends and those of the nodes always go to the network, as a spend cached before its
+/// double spend showed up would hide it.
+///
+/// Only a spend whose signature checks out and which lives at the address it is cached under
+/// gets in, so that a hit can be used as if it was just fetched.
+#[derive(Debug)]
+pub struct SpendCache {
+    config: SpendCacheConfig,
+    spends: Mutex<LruCache<SpendAddress, (SignedSpend, Instant)>>,
+}
+
+impl SpendCache {
+    pub fn new(config: SpendCacheConfig) -> Self {
+        Self {
+            config,
+            spends: Mutex::new(LruCache::unbounded()),
+        }
+    }
+
+    /// The spend at the address, if it was cached no longer than the TTL ago.
+    pub fn get(&self, address: &SpendAddress) -> Option<SignedSpend> {
+        let mut spends = self.spends.lock().ok()?;
+        match spends.get(address) {
+            Some((spend, cached_at)) if cached_at.elapsed() < self.config.ttl => {
+                trace!("Spend at {address:?} served from the cache");
+                Some(spend.clone())
+            }
+            Some(_) => {
+                let _ = spends.pop(address);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache the spend, once checked to be signed by the key of its address.
+    /// Returns whether it was cached.
+    pub fn insert(&self, spend: &SignedSpend) -> bool {
+        if self.config.max_spends == 0 {
+            return false;
+        }
+        if let Err(err) = spend.verify() {
+            warn!(
+                "Not caching the spend of {:?}, it is invalid: {err:?}",
+                spend.unique_pubkey()
+            );
+            return false;
+        }
+        let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+        let Ok(mut spends) = self.spends.lock() else {
+            return false;
+        };
+        let _ = spends.put(address, (spend.clone(), Instant::now()));
+        while spends.len() > self.config.max_spends {
+            let _ = spends.pop_lru();
+        }
+        true
+    }
+
+    /// Drop the spend at the address, e.g. once a double spend of it was found.
+    pub fn remove(&self, address: &SpendAddress) {
+        if let Ok(mut spends) = self.spends.lock() {
+            let _ = spends.pop(address);
+        }
+    }
+
+    /// The number of spends cached.
+    pub fn len(&self) -> usize {
+        self.spends.lock().map(|spends| spends.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::GENESIS_CASHNOTE;
+
+    fn genesis_spend() -> SignedSpend {
+        GENESIS_CASHNOTE
+            .parent_spends
+            .first()
+            .cloned()
+            .expect("the genesis CashNote to have a parent spend")
+    }
+
+    #[test]
+    fn only_verified_spends_are_served_until_expired() {
+        let spend = genesis_spend();
+        let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+
+        let cache = SpendCache::new(SpendCacheConfig::default());
+        assert!(cache.insert(&spend));
+        assert_eq!(cache.get(&address), Some(spend.clone()));
+
+        let mut forged = spend.clone();
+        forged.derived_key_sig = bls::SecretKey::random().sign(b"forged");
+        cache.remove(&address);
+        assert!(!cache.insert(&forged));
+        assert!(cache.get(&address).is_none());
+
+        let expiring = SpendCache::new(SpendCacheConfig {
+            max_spends: 1,
+            ttl: Duration::ZERO,
+        });
+        assert!(expiring.insert(&spend));
+        assert!(expiring.get(&address).is_none());
+        assert!(expiring.is_empty());
+
+        let disabled = SpendCache::new(SpendCacheConfig {
+            max_spends: 0,
+            ttl: DEFAULT_SPEND_TTL,
+        });
+        assert!(!disabled.insert(&spend));
+    }
+}
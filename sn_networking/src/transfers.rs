@@ -7,16 +7,105 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
-    close_group_majority, driver::GetRecordCfg, GetRecordError, Network, NetworkError, Result,
+    close_group_majority, driver::GetRecordCfg, target_arch::Instant, GetRecordError, Network,
+    NetworkError, Result,
+};
+use libp2p::{
+    gossipsub::{Message, MessageAcceptance},
+    kad::{Quorum, Record},
+    PeerId,
 };
-use libp2p::kad::{Quorum, Record};
 use sn_protocol::{
     storage::{try_deserialize_record, RecordHeader, RecordKind, RetryStrategy, SpendAddress},
     NetworkAddress, PrettyPrintRecordKey,
 };
 use sn_transfers::{CashNote, CashNoteRedemption, HotWallet, MainPubkey, SignedSpend, Transfer};
-use std::collections::BTreeSet;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
 use tokio::task::JoinSet;
+use xor_name::XorName;
+
+/// The prefix of the gossipsub topics the notices of transfers are published on.
+pub const TRANSFER_NOTIFS_TOPIC_PREFIX: &str = "TRANSFER_NOTIFS_";
+
+/// The number of topics the notices of transfers are spread over.
+///
+/// The nodes subscribe to all of them to relay the notices, while a wallet only subscribes to the
+/// one derived from its key, so that it is not swamped with the notices to the others. A topic
+/// only tells which of these buckets the recipient's key falls in, the notices do not name it.
+const TRANSFER_NOTIFS_TOPICS_COUNT: u8 = 16;
+
+/// The gossipsub topic the notices of the transfers to `recipient` are published on.
+pub fn transfer_notifs_topic(recipient: &MainPubkey) -> String {
+    let bucket = XorName::from_content(&recipient.to_bytes()).0[0] % TRANSFER_NOTIFS_TOPICS_COUNT;
+    format!("{TRANSFER_NOTIFS_TOPIC_PREFIX}{bucket:x}")
+}
+
+/// All the gossipsub topics the notices of transfers may be published on.
+pub fn transfer_notifs_topics() -> impl Iterator<Item = String> {
+    (0..TRANSFER_NOTIFS_TOPICS_COUNT)
+        .map(|bucket| format!("{TRANSFER_NOTIFS_TOPIC_PREFIX}{bucket:x}"))
+}
+
+/// The window over which the notices published by a peer are counted.
+const TRANSFER_NOTICES_WINDOW: Duration = Duration::from_secs(60);
+
+/// The notices a peer may publish per window, the others are not relayed.
+const MAX_TRANSFER_NOTICES_PER_WINDOW: usize = 10;
+
+/// The publishers whose notices are counted at most, the notices of the others are not relayed
+/// until the current windows end.
+const MAX_TRANSFER_NOTICE_PUBLISHERS: usize = 10_000;
+
+/// The validation of the gossip messages before they are relayed: only encrypted transfers on the
+/// topics of the transfer notices are, and only so many per publisher.
+#[derive(Default)]
+pub(crate) struct TransferNoticeValidator {
+    /// The start of the current window of each publisher, and its notices within it
+    publishers: BTreeMap<PeerId, (Instant, usize)>,
+}
+
+impl TransferNoticeValidator {
+    pub(crate) fn validate(&mut self, message: &Message) -> MessageAcceptance {
+        let topic = message.topic.as_str();
+        if !transfer_notifs_topics().any(|known| known == topic)
+            || !matches!(
+                Transfer::try_from_bytes(&message.data),
+                Ok(Transfer::Encrypted(_))
+            )
+        {
+            return MessageAcceptance::Reject;
+        }
+        let Some(publisher) = message.source else {
+            return MessageAcceptance::Reject;
+        };
+
+        let now = Instant::now();
+        if self.publishers.len() >= MAX_TRANSFER_NOTICE_PUBLISHERS {
+            self.publishers
+                .retain(|_, (start, _)| now.duration_since(*start) < TRANSFER_NOTICES_WINDOW);
+        }
+        if self.publishers.len() >= MAX_TRANSFER_NOTICE_PUBLISHERS
+            && !self.publishers.contains_key(&publisher)
+        {
+            return MessageAcceptance::Ignore;
+        }
+
+        let (start, count) = self.publishers.entry(publisher).or_insert((now, 0));
+        if now.duration_since(*start) >= TRANSFER_NOTICES_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        if *count > MAX_TRANSFER_NOTICES_PER_WINDOW {
+            MessageAcceptance::Ignore
+        } else {
+            MessageAcceptance::Accept
+        }
+    }
+}
 
 impl Network {
     /// Gets raw spends from the Network.
@@ -41,13 +130,20 @@ impl Network {
             "Got record from the network, {:?}",
             PrettyPrintRecordKey::from(&record.key)
         );
-        get_raw_signed_spends_from_record(&record)
+        let spends = get_raw_signed_spends_from_record(&record)?;
+        if spends.len() > 1 {
+            // a spend cached before its double spend showed up is not to be served anymore
+            self.spend_cache().remove(&address);
+        }
+        Ok(spends)
     }
 
     /// Gets a spend from the Network.
     /// We know it must be there, and has to be fetched from Quorum::All
     ///
     /// If we get a quorum error, we increase the RetryStrategy
+    ///
+    /// The `SpendCache` is not used, for a double spend of the parents to always be noticed.
     pub async fn get_spend(&self, address: SpendAddress) -> Result<SignedSpend> {
         let key = NetworkAddress::from_spend_address(address).to_record_key();
         let mut get_cfg = GetRecordCfg {
@@ -219,3 +315,65 @@ pub fn get_signed_spend_from_record(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::gossipsub::TopicHash;
+    use sn_transfers::{DerivationIndex, MainSecretKey};
+
+    fn message(source: PeerId, topic: String, data: Vec<u8>) -> Message {
+        Message {
+            source: Some(source),
+            data,
+            sequence_number: None,
+            topic: TopicHash::from_raw(topic),
+        }
+    }
+
+    #[test]
+    fn only_so_many_valid_transfer_notices_should_be_relayed() -> eyre::Result<()> {
+        let recipient = MainSecretKey::random().main_pubkey();
+        let redemption = CashNoteRedemption::new(
+            DerivationIndex([1; 32]),
+            [SpendAddress::new(XorName([2; 32]))].into(),
+        );
+        let transfer = Transfer::create(vec![redemption.clone()], recipient)?.to_bytes()?;
+        let topic = transfer_notifs_topic(&recipient);
+        let publisher = PeerId::random();
+        let mut validator = TransferNoticeValidator::default();
+
+        // anything but an encrypted transfer on a notices topic is rejected
+        let unencrypted = Transfer::Unencrypted(vec![redemption]).to_bytes()?;
+        for invalid in [
+            message(publisher, "other".to_string(), transfer.clone()),
+            message(publisher, topic.clone(), vec![0; 32]),
+            message(publisher, topic.clone(), unencrypted),
+        ] {
+            assert!(matches!(
+                validator.validate(&invalid),
+                MessageAcceptance::Reject
+            ));
+        }
+
+        let notice = message(publisher, topic.clone(), transfer.clone());
+        for _ in 0..MAX_TRANSFER_NOTICES_PER_WINDOW {
+            assert!(matches!(
+                validator.validate(&notice),
+                MessageAcceptance::Accept
+            ));
+        }
+        assert!(matches!(
+            validator.validate(&notice),
+            MessageAcceptance::Ignore
+        ));
+
+        // the other publishers are counted apart
+        let other = message(PeerId::random(), topic, transfer);
+        assert!(matches!(
+            validator.validate(&other),
+            MessageAcceptance::Accept
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The cash notes of the payments accepted by a node paid to an external reward address, kept in
+//! its root dir for a cash note not to pay for more than one record.
+//!
+//! Such cash notes are not deposited in the wallet of the node, which then cannot tell them apart
+//! from the ones of a new payment. As they are bound to the quote they pay, which cannot be paid
+//! once expired, they are only kept track of for as long as their quote could be valid.
+
+use crate::{
+    error::{Error, Result},
+    node::Node,
+};
+use sn_transfers::{CashNote, QUOTE_EXPIRATION_SECS};
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+const ACCEPTED_CASH_NOTES_DIR_NAME: &str = "accepted_cash_notes";
+
+/// How long an accepted cash note is kept track of, well past the expiry of the quote it pays.
+const ACCEPTED_CASH_NOTES_RETENTION: Duration = Duration::from_secs(2 * QUOTE_EXPIRATION_SECS);
+
+impl Node {
+    /// Accept the cash notes of a payment, returning the ones not accepted before, or
+    /// `ReusedPayment` if there are none.
+    pub(crate) fn accept_cash_notes(&self, cash_notes: Vec<CashNote>) -> Result<Vec<CashNote>> {
+        let dir = self
+            .network()
+            .root_dir_path()
+            .join(ACCEPTED_CASH_NOTES_DIR_NAME);
+        accept_cash_notes(&dir, cash_notes)
+    }
+
+    /// Stop keeping track of the cash notes accepted long enough ago for their quote to have
+    /// expired.
+    pub(crate) fn prune_accepted_cash_notes(&self) {
+        let dir = self
+            .network()
+            .root_dir_path()
+            .join(ACCEPTED_CASH_NOTES_DIR_NAME);
+        match prune_accepted_cash_notes(&dir, SystemTime::now()) {
+            Ok(0) => {}
+            Ok(pruned) => info!("Pruned {pruned} accepted cash notes whose quote has expired"),
+            Err(err) => warn!("Could not prune the accepted cash notes: {err:?}"),
+        }
+    }
+}
+
+fn accept_cash_notes(dir: &Path, cash_notes: Vec<CashNote>) -> Result<Vec<CashNote>> {
+    fs::create_dir_all(dir)?;
+
+    let mut accepted = vec![];
+    for cash_note in cash_notes {
+        let unique_pubkey = cash_note.unique_pubkey();
+        // the file is created once, even by concurrent PUTs paid with the same cash note
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(unique_pubkey.to_hex()))
+        {
+            Ok(_) => accepted.push(cash_note),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                debug!("The cash note {unique_pubkey:?} was already accepted");
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    if accepted.is_empty() {
+        return Err(Error::ReusedPayment);
+    }
+    Ok(accepted)
+}
+
+fn prune_accepted_cash_notes(dir: &Path, now: SystemTime) -> Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+    let mut pruned = 0;
+    for entry in entries {
+        let entry = entry?;
+        let accepted_at = entry.metadata()?.modified()?;
+        if now.duration_since(accepted_at).unwrap_or_default() >= ACCEPTED_CASH_NOTES_RETENTION {
+            fs::remove_file(entry.path())?;
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{
+        create_first_cash_note_from_key, DerivationIndex, MainSecretKey, NanoTokens,
+        SignedTransaction, SpendReason,
+    };
+
+    #[test]
+    fn cash_notes_should_pay_for_a_single_record() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let key = MainSecretKey::random();
+        let genesis = create_first_cash_note_from_key(&key)?;
+        let reward_address = MainSecretKey::random().main_pubkey();
+        let mut rng = rand::thread_rng();
+        let tx = SignedTransaction::new(
+            vec![genesis],
+            vec![
+                (
+                    NanoTokens::from(100),
+                    reward_address,
+                    DerivationIndex::random(&mut rng),
+                    false,
+                ),
+                (
+                    NanoTokens::from(100),
+                    reward_address,
+                    DerivationIndex::random(&mut rng),
+                    false,
+                ),
+            ],
+            key.main_pubkey(),
+            SpendReason::default(),
+            &key,
+        )?;
+        let payment = tx.output_cashnotes;
+
+        // the payment of a first record is accepted
+        let accepted = accept_cash_notes(dir.path(), payment.clone())?;
+        assert_eq!(accepted, payment);
+
+        // while the same transfer replayed for another record is not
+        assert!(matches!(
+            accept_cash_notes(dir.path(), payment.clone()),
+            Err(Error::ReusedPayment)
+        ));
+
+        // until its quote has long expired
+        assert_eq!(prune_accepted_cash_notes(dir.path(), SystemTime::now())?, 0);
+        let later = SystemTime::now() + ACCEPTED_CASH_NOTES_RETENTION;
+        assert_eq!(prune_accepted_cash_notes(dir.path(), later)?, payment.len());
+        assert_eq!(accept_cash_notes(dir.path(), payment.clone())?, payment);
+        Ok(())
+    }
+}
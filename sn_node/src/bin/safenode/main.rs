@@ -17,9 +17,13 @@ use libp2p::{identity::Keypair, PeerId};
 #[cfg(feature = "metrics")]
 use sn_logging::metrics::init_metrics;
 use sn_logging::{Level, LogFormat, LogOutputDest, ReloadHandle};
-use sn_node::{Marker, NodeBuilder, NodeEvent, NodeEventsReceiver};
+use sn_node::{
+    archived_peer_id, export_node_data, import_node_data, Marker, NodeBuilder, NodeEvent,
+    NodeEventsReceiver, SnapshotSource,
+};
 use sn_peers_acquisition::PeersArgs;
 use sn_protocol::{node::get_safenode_root_dir, node_rpc::NodeCtrl};
+use sn_transfers::MainPubkey;
 use std::{
     env,
     io::Write,
@@ -35,6 +39,12 @@ use tokio::{
 };
 use tracing_appender::non_blocking::WorkerGuard;
 
+/// The env variable setting the reward address when `--reward-address` is not used.
+const REWARD_ADDRESS_ENV: &str = "SAFENODE_REWARD_ADDRESS";
+
+/// The env variable holding the password node data archives are encrypted with.
+const DATA_PASSWORD_ENV: &str = "SAFENODE_DATA_PASSWORD";
+
 #[derive(Debug, Clone)]
 pub enum LogOutputDestArg {
     Stdout,
@@ -52,6 +62,10 @@ impl std::fmt::Display for LogOutputDestArg {
     }
 }
 
+fn parse_reward_address(val: &str) -> Result<MainPubkey> {
+    MainPubkey::from_hex(val.trim()).map_err(|err| eyre!("Invalid reward address {val:?}: {err}"))
+}
+
 pub fn parse_log_output(val: &str) -> Result<LogOutputDestArg> {
     match val {
         "stdout" => Ok(LogOutputDestArg::Stdout),
@@ -101,6 +115,14 @@ struct Opt {
     #[clap(long, value_parser = LogFormat::parse_from_str, verbatim_doc_comment)]
     log_format: Option<LogFormat>,
 
+    /// Set the log levels of some modules, over the defaults and the `SN_LOG` env variable.
+    ///
+    /// They can be changed at runtime through the `UpdateLogLevel` RPC.
+    ///
+    /// Example: --log-level sn_networking=DEBUG,sn_node=TRACE
+    #[clap(long, verbatim_doc_comment)]
+    log_level: Option<String>,
+
     /// Specify the maximum number of uncompressed log files to store.
     ///
     /// This argument is ignored if `log_output_dest` is set to "stdout"
@@ -118,6 +140,20 @@ struct Opt {
     #[clap(long = "max_archived_log_files", verbatim_doc_comment)]
     max_compressed_log_files: Option<usize>,
 
+    /// The endpoint of the OpenTelemetry collector the traces are exported to.
+    ///
+    /// Defaults to the `OTEL_EXPORTER_OTLP_ENDPOINT` env variable.
+    #[cfg(feature = "otlp")]
+    #[clap(long, verbatim_doc_comment)]
+    otlp_endpoint: Option<String>,
+
+    /// The name of the service the traces are exported under.
+    ///
+    /// Defaults to the `OTLP_SERVICE_NAME` env variable, or a random name.
+    #[cfg(feature = "otlp")]
+    #[clap(long, verbatim_doc_comment)]
+    otlp_service_name: Option<String>,
+
     /// Specify the node's data directory.
     ///
     /// If not provided, the default location is platform specific:
@@ -143,6 +179,25 @@ struct Opt {
     #[command(flatten)]
     peers: PeersArgs,
 
+    /// Export the identity key, records and wallet of the node in `--root-dir` to this archive, then exit.
+    ///
+    /// The node must be stopped. The archive is imported on the new host with `--import-data`.
+    ///
+    /// The archive holds the identity and wallet keys of the node: it is encrypted with the
+    /// password set in the `SAFENODE_DATA_PASSWORD` env variable, which must be kept secret.
+    #[clap(long, requires = "root_dir", verbatim_doc_comment)]
+    export_data: Option<PathBuf>,
+
+    /// Import the node data exported with `--export-data` before starting, so that the node resumes
+    /// with the same PeerId and records.
+    ///
+    /// The data is imported into `--root-dir`, or the default dir of the node's PeerId. Nothing is
+    /// imported if the node is already there, e.g. when it restarts.
+    ///
+    /// The archive is decrypted with the password set in the `SAFENODE_DATA_PASSWORD` env variable.
+    #[clap(long, conflicts_with = "export_data", verbatim_doc_comment)]
+    import_data: Option<PathBuf>,
+
     /// Enable the admin/control RPC service by providing an IP and port for it to listen on.
     ///
     /// The RPC service can be used for querying information about the running node.
@@ -159,6 +214,28 @@ struct Opt {
     #[clap(long)]
     owner: Option<String>,
 
+    /// Have the node's earnings paid directly to this MainPubkey, in hex, rather than to its own wallet.
+    ///
+    /// Defaults to the `SAFENODE_REWARD_ADDRESS` env variable, which can be set for a whole fleet, e.g.
+    /// with the `--env` argument of the node manager.
+    #[clap(long, value_parser = parse_reward_address, verbatim_doc_comment)]
+    reward_address: Option<MainPubkey>,
+
+    /// Import a snapshot of the records of the node's address range from the closest peer, once
+    /// connected, instead of fetching them one by one through replication.
+    ///
+    /// If `--snapshot-archive` is also set, the snapshot is written to it.
+    #[clap(long, verbatim_doc_comment)]
+    snapshot_bootstrap: bool,
+
+    /// Import the snapshot of records from this archive once connected, each record being
+    /// validated before it is stored.
+    ///
+    /// With `--snapshot-bootstrap`, the snapshot fetched from the closest peer is written to it
+    /// instead.
+    #[clap(long, verbatim_doc_comment)]
+    snapshot_archive: Option<PathBuf>,
+
     #[cfg(feature = "open-metrics")]
     /// Specify the port for the OpenMetrics server.
     ///
@@ -177,14 +254,67 @@ struct Opt {
         required_if_eq("metrics_server_port", "0")
     )]
     enable_metrics_server: bool,
+
+    #[cfg(feature = "chaos")]
+    #[command(flatten)]
+    chaos: ChaosArgs,
+}
+
+/// The faults injected by the node, to test the resilience of a network.
+///
+/// They can be changed while the node is running with the `Chaos` RPC.
+#[cfg(feature = "chaos")]
+#[derive(clap::Args, Debug)]
+struct ChaosArgs {
+    /// The percentage of queries to leave without a response.
+    #[clap(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=100))]
+    chaos_drop_request_percent: u8,
+
+    /// The delay in milliseconds to add before sending each response.
+    #[clap(long, default_value_t = 0)]
+    chaos_response_delay_millis: u64,
+
+    /// The percentage of records read for another node to corrupt.
+    #[clap(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=100))]
+    chaos_corrupt_record_percent: u8,
+
+    /// Restart the node, retaining its peer id, after this many seconds.
+    ///
+    /// As the node restarts with the same arguments, it then keeps restarting at this interval.
+    #[clap(long)]
+    chaos_restart_interval_secs: Option<u64>,
+}
+
+#[cfg(feature = "chaos")]
+impl ChaosArgs {
+    fn config(&self) -> sn_node::ChaosConfig {
+        sn_node::ChaosConfig {
+            drop_request_percent: self.chaos_drop_request_percent,
+            response_delay: Duration::from_millis(self.chaos_response_delay_millis),
+            corrupt_record_percent: self.chaos_corrupt_record_percent,
+        }
+    }
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
     let opt = Opt::parse();
 
+    if let (Some(archive), Some(root_dir)) = (&opt.export_data, &opt.root_dir) {
+        let summary = export_node_data(root_dir, archive, &data_password()?)?;
+        println!(
+            "Exported {} files ({} bytes) of node {} to {archive:?}",
+            summary.files, summary.bytes, summary.peer_id
+        );
+        return Ok(());
+    }
+    let root_dir = match &opt.import_data {
+        Some(archive) => Some(import_data_once(archive, &opt.root_dir)?),
+        None => opt.root_dir.clone(),
+    };
+
     let node_socket_addr = SocketAddr::new(opt.ip, opt.port);
-    let (root_dir, keypair) = get_root_dir_and_keypair(&opt.root_dir)?;
+    let (root_dir, keypair) = get_root_dir_and_keypair(&root_dir)?;
 
     let (log_output_dest, log_reload_handle, _log_appender_guard) =
         init_logging(&opt, keypair.public().to_peer_id())?;
@@ -222,6 +352,21 @@ fn main() -> Result<()> {
             opt.upnp,
         );
         node_builder.is_behind_home_network = opt.home_network;
+        let reward_address = match opt.reward_address {
+            Some(reward_address) => Some(reward_address),
+            None => env::var(REWARD_ADDRESS_ENV)
+                .ok()
+                .map(|val| parse_reward_address(&val))
+                .transpose()?,
+        };
+        if let Some(reward_address) = reward_address {
+            node_builder.external_reward_address(reward_address);
+        }
+        if opt.snapshot_bootstrap {
+            node_builder.snapshot_bootstrap(SnapshotSource::ClosestPeer, opt.snapshot_archive);
+        } else if let Some(archive) = opt.snapshot_archive {
+            node_builder.snapshot_bootstrap(SnapshotSource::Archive(archive), None);
+        }
         #[cfg(feature = "open-metrics")]
         let mut node_builder = node_builder;
         // if enable flag is provided or only if the port is specified then enable the server by setting Some()
@@ -233,8 +378,21 @@ fn main() -> Result<()> {
         };
         #[cfg(feature = "open-metrics")]
         node_builder.metrics_server_port(metrics_server_port);
-        let restart_options =
-            run_node(node_builder, opt.rpc, &log_output_dest, log_reload_handle).await?;
+        #[cfg(feature = "chaos")]
+        let mut node_builder = node_builder;
+        #[cfg(feature = "chaos")]
+        node_builder.chaos(opt.chaos.config());
+        let restart_options = run_node(
+            node_builder,
+            opt.rpc,
+            &log_output_dest,
+            log_reload_handle,
+            #[cfg(feature = "chaos")]
+            opt.chaos
+                .chaos_restart_interval_secs
+                .map(Duration::from_secs),
+        )
+        .await?;
 
         Ok::<_, eyre::Report>(restart_options)
     })?;
@@ -259,6 +417,7 @@ async fn run_node(
     rpc: Option<SocketAddr>,
     log_output_dest: &str,
     log_reload_handle: ReloadHandle,
+    #[cfg(feature = "chaos")] chaos_restart_interval: Option<Duration>,
 ) -> Result<Option<(PathBuf, u16)>> {
     let started_instant = std::time::Instant::now();
 
@@ -307,6 +466,24 @@ You can check your reward balance by running:
         }
     });
 
+    #[cfg(feature = "chaos")]
+    if let Some(interval) = chaos_restart_interval {
+        let ctrl_tx_clone = ctrl_tx.clone();
+        let _handle = tokio::spawn(async move {
+            sleep(interval).await;
+            warn!("Chaos: restarting the node after {interval:?}");
+            if let Err(err) = ctrl_tx_clone
+                .send(NodeCtrl::Restart {
+                    delay: Duration::ZERO,
+                    retain_peer_id: true,
+                })
+                .await
+            {
+                error!("Failed to send node control msg to safenode bin main thread: {err}");
+            }
+        });
+    }
+
     // Start up gRPC interface if enabled by user
     if let Some(addr) = rpc {
         rpc_service::start_rpc_service(
@@ -419,6 +596,13 @@ fn init_logging(opt: &Opt, peer_id: PeerId) -> Result<(String, ReloadHandle, Opt
         ("sn_transfers".to_string(), Level::DEBUG),
     ];
 
+    let log_levels = opt
+        .log_level
+        .as_deref()
+        .map(sn_logging::parse_log_levels)
+        .transpose()?
+        .unwrap_or_default();
+
     let output_dest = match &opt.log_output_dest {
         LogOutputDestArg::Stdout => LogOutputDest::Stdout,
         LogOutputDestArg::DataDir => {
@@ -433,6 +617,7 @@ fn init_logging(opt: &Opt, peer_id: PeerId) -> Result<(String, ReloadHandle, Opt
         let mut log_builder = sn_logging::LogBuilder::new(logging_targets);
         log_builder.output_dest(output_dest.clone());
         log_builder.format(opt.log_format.unwrap_or(LogFormat::Default));
+        log_builder.log_levels(log_levels);
         if let Some(files) = opt.max_uncompressed_log_files {
             log_builder.max_uncompressed_log_files(files);
         }
@@ -451,12 +636,17 @@ fn init_logging(opt: &Opt, peer_id: PeerId) -> Result<(String, ReloadHandle, Opt
             let mut log_builder = sn_logging::LogBuilder::new(logging_targets);
             log_builder.output_dest(output_dest.clone());
             log_builder.format(opt.log_format.unwrap_or(LogFormat::Default));
+            log_builder.log_levels(log_levels);
             if let Some(files) = opt.max_uncompressed_log_files {
                 log_builder.max_uncompressed_log_files(files);
             }
             if let Some(files) = opt.max_compressed_log_files {
                 log_builder.max_compressed_log_files(files);
             }
+            log_builder.otlp_config(sn_logging::OtlpConfig {
+                endpoint: opt.otlp_endpoint.clone(),
+                service_name: opt.otlp_service_name.clone(),
+            });
             log_builder.initialize()
         })?;
         (rt, reload_handle, log_appender_guard)
@@ -510,6 +700,44 @@ fn keypair_from_path(path: impl AsRef<Path>) -> Result<Keypair> {
     Ok(keypair)
 }
 
+/// Import the node data of the archive into `root_dir`, or the default dir of its PeerId, unless
+/// the node is already there. Returns the root dir of the node.
+fn import_data_once(archive: &Path, root_dir: &Option<PathBuf>) -> Result<PathBuf> {
+    let peer_id = archived_peer_id(archive)?;
+    let dir = match root_dir {
+        Some(dir) => dir.clone(),
+        None => get_safenode_root_dir(peer_id)?,
+    };
+
+    let secret_key_path = dir.join("secret-key");
+    if secret_key_path.exists() {
+        let existing = keypair_from_path(&secret_key_path)?.public().to_peer_id();
+        if existing != peer_id {
+            return Err(eyre!(
+                "{dir:?} already holds node {existing}, not the node {peer_id} of the archive"
+            ));
+        }
+        println!("Node {peer_id} was already imported into {dir:?}");
+    } else {
+        let summary = import_node_data(archive, &dir, &data_password()?)?;
+        println!(
+            "Imported {} files ({} bytes) of node {peer_id} into {dir:?}",
+            summary.files, summary.bytes
+        );
+    }
+    Ok(dir)
+}
+
+/// The password node data archives are encrypted with.
+fn data_password() -> Result<String> {
+    match env::var(DATA_PASSWORD_ENV) {
+        Ok(password) if !password.is_empty() => Ok(password),
+        _ => Err(eyre!(
+            "the {DATA_PASSWORD_ENV} env variable must hold the password of the node data archive"
+        )),
+    }
+}
+
 /// The keypair is located inside the root directory. At the same time, when no dir is specified,
 /// the dir name is derived from the keypair used in the application: the peer ID is used as the directory name.
 fn get_root_dir_and_keypair(root_dir: &Option<PathBuf>) -> Result<(PathBuf, Keypair)> {
@@ -7,17 +7,20 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use eyre::{ErrReport, Result};
+use libp2p::kad::RecordKey;
 use sn_logging::ReloadHandle;
 use sn_node::RunningNode;
-use sn_protocol::node_rpc::NodeCtrl;
 use sn_protocol::safenode_proto::{
-    k_buckets_response,
+    close_group_consistency_response, k_buckets_response,
     safe_node_server::{SafeNode, SafeNodeServer},
-    KBucketsRequest, KBucketsResponse, NetworkInfoRequest, NetworkInfoResponse, NodeEvent,
-    NodeEventsRequest, NodeInfoRequest, NodeInfoResponse, RecordAddressesRequest,
-    RecordAddressesResponse, RestartRequest, RestartResponse, StopRequest, StopResponse,
-    UpdateLogLevelRequest, UpdateLogLevelResponse, UpdateRequest, UpdateResponse,
+    ChaosRequest, ChaosResponse, CloseGroupConsistencyRequest, CloseGroupConsistencyResponse,
+    DriverStatsRequest, DriverStatsResponse, KBucketsRequest, KBucketsResponse, NetworkInfoRequest,
+    NetworkInfoResponse, NodeEvent, NodeEventsRequest, NodeInfoRequest, NodeInfoResponse,
+    PeerProtocolVersion, RecordAddressesRequest, RecordAddressesResponse, RestartRequest,
+    RestartResponse, StopRequest, StopResponse, UpdateLogLevelRequest, UpdateLogLevelResponse,
+    UpdateRequest, UpdateResponse,
 };
+use sn_protocol::{node_rpc::NodeCtrl, NetworkAddress};
 use std::{
     collections::HashMap,
     env,
@@ -93,15 +96,54 @@ impl SafeNode for SafeNodeRpcService {
             .expect("failed to get local swarm state");
         let connected_peers = state.connected_peers.iter().map(|p| p.to_bytes()).collect();
         let listeners = state.listeners.iter().map(|m| m.to_string()).collect();
+        let peer_protocol_versions = state
+            .peer_protocol_versions
+            .into_iter()
+            .map(|(peer, protocol_version)| PeerProtocolVersion {
+                peer: peer.to_bytes(),
+                protocol_version,
+            })
+            .collect();
 
         let resp = Response::new(NetworkInfoResponse {
             connected_peers,
             listeners,
+            peer_protocol_versions,
         });
 
         Ok(resp)
     }
 
+    async fn driver_stats(
+        &self,
+        request: Request<DriverStatsRequest>,
+    ) -> Result<Response<DriverStatsResponse>, Status> {
+        debug!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        let stats = self.running_node.get_driver_stats().await.map_err(|err| {
+            Status::new(
+                Code::Internal,
+                format!("Failed to get the stats of the SwarmDriver: {err}"),
+            )
+        })?;
+
+        Ok(Response::new(DriverStatsResponse {
+            local_cmd_backlog: stats.local_cmd_backlog as u64,
+            network_cmd_backlog: stats.network_cmd_backlog as u64,
+            event_backlog: stats.event_backlog as u64,
+            pending_cmd_sends: stats.pending_cmd_sends as u64,
+            pending_get_closest_peers: stats.pending_get_closest_peers as u64,
+            pending_requests: stats.pending_requests as u64,
+            pending_get_record: stats.pending_get_record as u64,
+            live_connections: stats.live_connections as u64,
+            busy: self.running_node.is_network_busy(),
+        }))
+    }
+
     async fn node_events(
         &self,
         request: Request<NodeEventsRequest>,
@@ -272,17 +314,114 @@ impl SafeNode for SafeNodeRpcService {
             request.get_ref()
         );
 
-        match self
-            .log_reload_handle
-            .modify_log_level(&request.get_ref().log_level)
-        {
-            Ok(()) => Ok(Response::new(UpdateLogLevelResponse {})),
+        let request = request.get_ref();
+        let result = if request.merge {
+            self.log_reload_handle.merge_log_level(&request.log_level)
+        } else {
+            self.log_reload_handle.modify_log_level(&request.log_level)
+        };
+        match result {
+            Ok(()) => Ok(Response::new(UpdateLogLevelResponse {
+                log_level: self.log_reload_handle.log_level(),
+            })),
             Err(err) => Err(Status::new(
                 Code::Internal,
                 format!("Failed to update node's log level: {err:?}"),
             )),
         }
     }
+
+    async fn chaos(
+        &self,
+        request: Request<ChaosRequest>,
+    ) -> Result<Response<ChaosResponse>, Status> {
+        debug!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        #[cfg(feature = "chaos")]
+        {
+            let request = request.get_ref();
+            let percent = |value: u32| {
+                u8::try_from(value)
+                    .ok()
+                    .filter(|percent| *percent <= 100)
+                    .ok_or_else(|| {
+                        Status::new(
+                            Code::InvalidArgument,
+                            format!("{value} is not a percentage"),
+                        )
+                    })
+            };
+            self.running_node.set_chaos_config(sn_node::ChaosConfig {
+                drop_request_percent: percent(request.drop_request_percent)?,
+                response_delay: Duration::from_millis(request.response_delay_millis),
+                corrupt_record_percent: percent(request.corrupt_record_percent)?,
+            });
+            Ok(Response::new(ChaosResponse {}))
+        }
+
+        #[cfg(not(feature = "chaos"))]
+        Err(Status::new(
+            Code::Unimplemented,
+            "The node was not built with the chaos feature",
+        ))
+    }
+
+    async fn close_group_consistency(
+        &self,
+        request: Request<CloseGroupConsistencyRequest>,
+    ) -> Result<Response<CloseGroupConsistencyResponse>, Status> {
+        debug!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        let key = RecordKey::new(&request.get_ref().key);
+        let report = self
+            .running_node
+            .check_close_group_consistency(&NetworkAddress::from_record_key(&key))
+            .await
+            .map_err(|err| {
+                Status::new(
+                    Code::Internal,
+                    format!("Failed to check the close group of the record: {err}"),
+                )
+            })?;
+
+        let copies = report
+            .copies
+            .into_iter()
+            .map(|(proof, peers)| close_group_consistency_response::Copy {
+                digest: proof.to_hex(),
+                peers: peers.into_iter().map(|peer| peer.to_bytes()).collect(),
+            })
+            .collect();
+        let missing = report
+            .missing
+            .into_iter()
+            .map(|peer| peer.to_bytes())
+            .collect();
+        let unreachable = report
+            .unreachable
+            .into_iter()
+            .map(
+                |(peer, reason)| close_group_consistency_response::Unreachable {
+                    peer: peer.to_bytes(),
+                    reason,
+                },
+            )
+            .collect();
+
+        Ok(Response::new(CloseGroupConsistencyResponse {
+            copies,
+            missing,
+            unreachable,
+        }))
+    }
 }
 
 pub(crate) fn start_rpc_service(
@@ -0,0 +1,147 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Faults injected by the node into its handling of queries, to test the resilience of clients
+//! and of the replication.
+//!
+//! This is only meant for test networks, and is only available with the `chaos` feature.
+
+use bytes::Bytes;
+use rand::Rng;
+use sn_protocol::messages::{QueryResponse, Response};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// The faults injected by a node. The default config injects none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChaosConfig {
+    /// The percentage of queries left without a response.
+    pub drop_request_percent: u8,
+    /// The delay added before sending each response.
+    pub response_delay: Duration,
+    /// The percentage of records read for another node which are corrupted.
+    pub corrupt_record_percent: u8,
+}
+
+impl ChaosConfig {
+    /// Whether any fault is injected.
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Whether the next query shall be left without a response.
+    pub(crate) fn should_drop_request(&self) -> bool {
+        chance(self.drop_request_percent)
+    }
+
+    /// Delay the response, and corrupt the record it holds if it is a replicated or a register
+    /// record.
+    pub(crate) async fn tamper_with_response(&self, response: Response) -> Response {
+        if !self.response_delay.is_zero() {
+            tokio::time::sleep(self.response_delay).await;
+        }
+
+        if !chance(self.corrupt_record_percent) {
+            return response;
+        }
+        match response {
+            Response::Query(QueryResponse::GetReplicatedRecord(Ok((holder, value)))) => {
+                warn!("Chaos: corrupting the replicated record sent by {holder:?}");
+                Response::Query(QueryResponse::GetReplicatedRecord(Ok((
+                    holder,
+                    corrupt(value),
+                ))))
+            }
+            Response::Query(QueryResponse::GetRegisterRecord(Ok((holder, value)))) => {
+                warn!("Chaos: corrupting the register record sent by {holder:?}");
+                Response::Query(QueryResponse::GetRegisterRecord(Ok((
+                    holder,
+                    corrupt(value),
+                ))))
+            }
+            other => other,
+        }
+    }
+}
+
+/// The chaos config of a node, which can be changed while it is running.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Chaos(Arc<RwLock<ChaosConfig>>);
+
+impl Chaos {
+    pub(crate) fn new(config: ChaosConfig) -> Self {
+        Self(Arc::new(RwLock::new(config)))
+    }
+
+    pub(crate) fn config(&self) -> ChaosConfig {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn set_config(&self, config: ChaosConfig) {
+        info!("Chaos: injecting {config:?}");
+        *self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = config;
+    }
+}
+
+fn chance(percent: u8) -> bool {
+    percent > 0 && rand::thread_rng().gen_range(0..100) < percent
+}
+
+/// Flip the bits of the first byte of the value, enough to break its deserialisation or its hash.
+fn corrupt(value: Bytes) -> Bytes {
+    let mut value = value.to_vec();
+    if let Some(byte) = value.first_mut() {
+        *byte = !*byte;
+    }
+    Bytes::from(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_protocol::NetworkAddress;
+
+    fn replicated_record() -> Response {
+        Response::Query(QueryResponse::GetReplicatedRecord(Ok((
+            NetworkAddress::from_peer(libp2p::PeerId::random()),
+            Bytes::from_static(b"record"),
+        ))))
+    }
+
+    fn record_value(response: Response) -> Bytes {
+        match response {
+            Response::Query(QueryResponse::GetReplicatedRecord(Ok((_, value)))) => value,
+            other => panic!("Unexpected response {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_should_only_be_corrupted_when_configured() {
+        let mut config = ChaosConfig::default();
+        assert!(!config.is_enabled());
+        assert!(!config.should_drop_request());
+        let response = config.tamper_with_response(replicated_record()).await;
+        assert_eq!(record_value(response), Bytes::from_static(b"record"));
+
+        config.corrupt_record_percent = 100;
+        config.drop_request_percent = 100;
+        assert!(config.is_enabled());
+        assert!(config.should_drop_request());
+        let value = record_value(config.tamper_with_response(replicated_record()).await);
+        assert_ne!(value, Bytes::from_static(b"record"));
+        assert_eq!(value.len(), b"record".len());
+    }
+}
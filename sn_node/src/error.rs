@@ -18,6 +18,9 @@ pub enum Error {
     #[error("Network error {0}")]
     Network(#[from] sn_networking::NetworkError),
 
+    #[error("I/O error {0}")]
+    Io(#[from] std::io::Error),
+
     #[error("Protocol error {0}")]
     Protocol(#[from] sn_protocol::Error),
 
@@ -55,6 +58,9 @@ pub enum Error {
     // Scratchpad is invalid
     #[error("Scratchpad signature is invalid over the counter + content hash")]
     InvalidScratchpadSignature,
+    // Scratchpad holds more data than allowed
+    #[error("Scratchpad data of {0} bytes exceeds the maximum size")]
+    ScratchpadTooBig(usize),
 
     // ---------- Payment Errors
     #[error("The content of the payment quote is invalid")]
@@ -63,6 +69,9 @@ pub enum Error {
     InvalidQuoteSignature,
     #[error("The payment quote expired for {0:?}")]
     QuoteExpired(NetworkAddress),
+    /// None of the quotes paid were given by our node, the payment was meant for other nodes
+    #[error("None of the quotes paid for record {0:?} were given by our node")]
+    NoQuoteFromOurNode(PrettyPrintRecordKey<'static>),
     /// Payment proof received has no inputs
     #[error(
         "Payment proof received with record:{0:?}. No payment for our node in its transaction"
@@ -77,13 +86,23 @@ pub enum Error {
         paid: NanoTokens,
         expected: NanoTokens,
     },
-    #[error("A payment we received contains cash notes already confirmed to be spent")]
+    #[error("A payment we received contains cash notes already confirmed to be spent or accepted")]
     ReusedPayment,
 
     // ---------- Initialize Errors
     #[error("Failed to generate a reward key")]
     FailedToGenerateRewardKey,
 
+    // ---------- Snapshot Errors
+    #[error("None of the nearby peers could provide a record snapshot")]
+    SnapshotUnavailable,
+    #[error("Could not read or write the record snapshot archive: {0}")]
+    SnapshotArchive(String),
+
+    // ---------- Migration Errors
+    #[error("Invalid node data archive: {0}")]
+    InvalidNodeArchive(String),
+
     // ---------- Miscellaneous Errors
     #[error("Failed to obtain node's current port")]
     FailedToGetNodePort,
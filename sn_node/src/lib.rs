@@ -27,26 +27,37 @@
 #[macro_use]
 extern crate tracing;
 
+mod accepted_payments;
+#[cfg(feature = "chaos")]
+mod chaos;
 mod error;
 mod event;
 mod log_markers;
 #[cfg(feature = "open-metrics")]
 mod metrics;
+mod migration;
 mod node;
 mod put_validation;
 mod quote;
+mod receipts;
 mod replication;
+mod snapshot;
 
 pub use self::{
     event::{NodeEvent, NodeEventsChannel, NodeEventsReceiver},
     log_markers::Marker,
+    migration::{archived_peer_id, export_node_data, import_node_data, NodeArchiveSummary},
     node::{NodeBuilder, PERIODIC_REPLICATION_INTERVAL_MAX_S},
+    snapshot::SnapshotSource,
 };
 
+#[cfg(feature = "chaos")]
+pub use self::chaos::ChaosConfig;
+
 use crate::error::{Error, Result};
 
 use libp2p::PeerId;
-use sn_networking::{Network, SwarmLocalState};
+use sn_networking::{CloseGroupConsistency, Network, SwarmDriverStats, SwarmLocalState};
 use sn_protocol::{get_port_from_multiaddr, NetworkAddress};
 use sn_transfers::{HotWallet, NanoTokens};
 use std::{
@@ -60,6 +71,8 @@ use std::{
 pub struct RunningNode {
     network: Network,
     node_events_channel: NodeEventsChannel,
+    #[cfg(feature = "chaos")]
+    chaos: chaos::Chaos,
 }
 
 impl RunningNode {
@@ -103,6 +116,18 @@ impl RunningNode {
         Err(Error::FailedToGetNodePort)
     }
 
+    /// Returns the queue depths and in-flight task counts of the SwarmDriver of this node.
+    pub async fn get_driver_stats(&self) -> Result<SwarmDriverStats> {
+        let stats = self.network.get_driver_stats().await?;
+        Ok(stats)
+    }
+
+    /// Whether the SwarmDriver of this node lags behind the cmds sent to it, the network then
+    /// being reported as busy.
+    pub fn is_network_busy(&self) -> bool {
+        self.network.check_backpressure().is_err()
+    }
+
     /// Returns the node events channel where to subscribe to receive `NodeEvent`s
     pub fn node_events_channel(&self) -> &NodeEventsChannel {
         &self.node_events_channel
@@ -126,4 +151,29 @@ impl RunningNode {
         let kbuckets = self.network.get_kbuckets().await?;
         Ok(kbuckets)
     }
+
+    /// Asks each peer of the close group of the address, this node included, whether it holds
+    /// the record and the digest of its copy, reporting where the group diverges.
+    pub async fn check_close_group_consistency(
+        &self,
+        address: &NetworkAddress,
+    ) -> Result<CloseGroupConsistency> {
+        let report = self
+            .network
+            .check_close_group_consistency(address, true)
+            .await?;
+        Ok(report)
+    }
+
+    #[cfg(feature = "chaos")]
+    /// Returns the faults currently injected by the node
+    pub fn chaos_config(&self) -> ChaosConfig {
+        self.chaos.config()
+    }
+
+    #[cfg(feature = "chaos")]
+    /// Replaces the faults injected by the node
+    pub fn set_chaos_config(&self, config: ChaosConfig) {
+        self.chaos.set_config(config)
+    }
 }
@@ -0,0 +1,542 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Moving a node to another machine or disk: its identity key, record store and wallet are
+//! exported to an archive, which is verified when imported on the new host, so that the node
+//! resumes with the same PeerId and the records it is responsible for.
+//!
+//! The identity key and the wallet keys are among the exported files, so the archive is
+//! encrypted with a password: each file is sealed with ChaCha20-Poly1305, under a key derived from
+//! the password with PBKDF2, the same way as the wallet keys. Only the PeerId of the node is
+//! readable without it.
+
+use crate::error::{Error, Result};
+use libp2p::{identity::Keypair, PeerId};
+use rand::Rng;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    num::NonZeroU32,
+    path::{Component, Path, PathBuf},
+};
+use walkdir::WalkDir;
+use xor_name::XorName;
+
+/// The version of the archive format written by [`export_node_data`].
+const ARCHIVE_VERSION: u8 = 2;
+
+/// Number of iterations of PBKDF2 deriving the key of an archive from its password.
+const KEY_ITERATIONS: NonZeroU32 = match NonZeroU32::new(100_000) {
+    Some(v) => v,
+    None => panic!("`100_000` is not be zero"),
+};
+
+/// The file holding the identity key of a node, in its root dir.
+const SECRET_KEY_FILE_NAME: &str = "secret-key";
+
+/// The dirs of the root dir that are not moved along with the node.
+const SKIPPED_DIRS: [&str; 1] = ["logs"];
+
+/// The frames an archive is made of, written one after the other so that the records don't have
+/// to be held in memory all at once.
+#[derive(Serialize, Deserialize)]
+enum ArchiveFrame {
+    Header {
+        version: u8,
+        peer_id: String,
+        /// The salt the key of the archive is derived from the password with.
+        salt: [u8; 16],
+    },
+    File {
+        /// The path of the file, relative to the root dir, with `/` separators.
+        path: String,
+        nonce: [u8; 12],
+        /// The content of the file, sealed with the key of the archive and its path.
+        content: Vec<u8>,
+        /// The hash of the content before it was sealed.
+        hash: XorName,
+    },
+    End {
+        files: u64,
+        /// The hashes of the paths and contents of all the files, chained.
+        digest: XorName,
+    },
+}
+
+/// What an archive holds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeArchiveSummary {
+    /// The identity of the node
+    pub peer_id: PeerId,
+    /// The number of files of its root dir
+    pub files: u64,
+    /// The total size of the files
+    pub bytes: u64,
+}
+
+/// Export the data of the node in `root_dir` to an archive at `archive_path`, encrypted with
+/// `password`.
+///
+/// The node must be stopped, for its record store not to change while being exported.
+pub fn export_node_data(
+    root_dir: &Path,
+    archive_path: &Path,
+    password: &str,
+) -> Result<NodeArchiveSummary> {
+    let peer_id = read_peer_id(&root_dir.join(SECRET_KEY_FILE_NAME))?;
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill(&mut salt);
+    let key = ArchiveKey::derive(password, &salt)?;
+
+    let mut opt = private_file_options();
+    let _ = opt.create(true).truncate(true);
+    let mut writer = BufWriter::new(opt.open(archive_path)?);
+    write_frame(
+        &mut writer,
+        &ArchiveFrame::Header {
+            version: ARCHIVE_VERSION,
+            peer_id: peer_id.to_string(),
+            salt,
+        },
+    )?;
+
+    let mut digest = Digest::default();
+    let mut bytes = 0;
+    let entries = WalkDir::new(root_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() != 1
+                || !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+        });
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::InvalidNodeArchive(err.to_string()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = archive_path_of(root_dir, entry.path())?;
+        let content = fs::read(entry.path())?;
+        let hash = XorName::from_content(&content);
+        digest.add(&path, &hash);
+        bytes += content.len() as u64;
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill(&mut nonce);
+        let content = key.seal(&path, nonce, content)?;
+        write_frame(
+            &mut writer,
+            &ArchiveFrame::File {
+                path,
+                nonce,
+                content,
+                hash,
+            },
+        )?;
+    }
+
+    let files = digest.files;
+    write_frame(
+        &mut writer,
+        &ArchiveFrame::End {
+            files,
+            digest: digest.finish(),
+        },
+    )?;
+    writer.flush()?;
+    info!("Exported {files} files of node {peer_id:?} to {archive_path:?}");
+
+    Ok(NodeArchiveSummary {
+        peer_id,
+        files,
+        bytes,
+    })
+}
+
+/// Read the PeerId of the node an archive holds, e.g. to find its default root dir.
+pub fn archived_peer_id(archive_path: &Path) -> Result<PeerId> {
+    let mut reader = BufReader::new(File::open(archive_path)?);
+    read_header(&mut reader).map(|(peer_id, _)| peer_id)
+}
+
+/// Import the node data of the archive at `archive_path`, decrypted with `password`, into
+/// `root_dir`, which must not hold a node already.
+///
+/// The archive is entirely verified before `root_dir` is created: a truncated or altered archive,
+/// or a wrong password, leaves nothing behind.
+pub fn import_node_data(
+    archive_path: &Path,
+    root_dir: &Path,
+    password: &str,
+) -> Result<NodeArchiveSummary> {
+    if root_dir.join(SECRET_KEY_FILE_NAME).exists() {
+        return Err(Error::InvalidNodeArchive(format!(
+            "{root_dir:?} already holds a node"
+        )));
+    }
+    let mut staging_dir = root_dir.as_os_str().to_owned();
+    staging_dir.push(".importing");
+    let staging_dir = PathBuf::from(staging_dir);
+    if staging_dir.exists() {
+        fs::remove_dir_all(&staging_dir)?;
+    }
+
+    let result = unpack_archive(archive_path, &staging_dir, password);
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(err) => {
+            let _ = fs::remove_dir_all(&staging_dir);
+            return Err(err);
+        }
+    };
+
+    if root_dir.exists() {
+        // an empty dir, e.g. created by the operator, is replaced
+        fs::remove_dir(root_dir)?;
+    }
+    fs::rename(&staging_dir, root_dir)?;
+    info!(
+        "Imported {} files of node {:?} into {root_dir:?}",
+        summary.files, summary.peer_id
+    );
+    Ok(summary)
+}
+
+fn unpack_archive(archive_path: &Path, dir: &Path, password: &str) -> Result<NodeArchiveSummary> {
+    let mut reader = BufReader::new(File::open(archive_path)?);
+    let (peer_id, salt) = read_header(&mut reader)?;
+    let key = ArchiveKey::derive(password, &salt)?;
+
+    let mut digest = Digest::default();
+    let mut bytes = 0;
+    loop {
+        match read_frame(&mut reader)? {
+            ArchiveFrame::File {
+                path,
+                nonce,
+                content,
+                hash,
+            } => {
+                let content = key.open(&path, nonce, content)?;
+                if XorName::from_content(&content) != hash {
+                    return Err(Error::InvalidNodeArchive(format!(
+                        "the content of {path:?} is corrupted"
+                    )));
+                }
+                digest.add(&path, &hash);
+                bytes += content.len() as u64;
+                write_private_file(&dir.join(local_path_of(&path)?), &content)?;
+            }
+            ArchiveFrame::End { files, digest: end } => {
+                if files != digest.files || end != digest.finish() {
+                    return Err(Error::InvalidNodeArchive(
+                        "the files do not match the ones exported".to_string(),
+                    ));
+                }
+                break;
+            }
+            ArchiveFrame::Header { .. } => {
+                return Err(Error::InvalidNodeArchive(
+                    "unexpected header in the middle of the archive".to_string(),
+                ))
+            }
+        }
+    }
+
+    // the node must resume with the identity it had
+    if read_peer_id(&dir.join(SECRET_KEY_FILE_NAME))? != peer_id {
+        return Err(Error::InvalidNodeArchive(
+            "the identity key does not match the PeerId of the archive".to_string(),
+        ));
+    }
+
+    Ok(NodeArchiveSummary {
+        peer_id,
+        files: digest.files,
+        bytes,
+    })
+}
+
+fn read_header(reader: &mut BufReader<File>) -> Result<(PeerId, [u8; 16])> {
+    match read_frame(reader)? {
+        ArchiveFrame::Header {
+            version,
+            peer_id,
+            salt,
+        } if version == ARCHIVE_VERSION => peer_id
+            .parse()
+            .map(|peer_id| (peer_id, salt))
+            .map_err(|_| Error::InvalidNodeArchive(format!("invalid PeerId {peer_id:?}"))),
+        ArchiveFrame::Header { version, .. } => Err(Error::InvalidNodeArchive(format!(
+            "unsupported archive version {version}"
+        ))),
+        _ => Err(Error::InvalidNodeArchive("missing header".to_string())),
+    }
+}
+
+fn read_peer_id(secret_key_path: &Path) -> Result<PeerId> {
+    let key = fs::read(secret_key_path).map_err(|err| {
+        Error::InvalidNodeArchive(format!("could not read {secret_key_path:?}: {err}"))
+    })?;
+    let keypair = Keypair::ed25519_from_bytes(key)
+        .map_err(|err| Error::InvalidNodeArchive(format!("invalid identity key: {err}")))?;
+    Ok(keypair.public().to_peer_id())
+}
+
+fn write_frame(writer: &mut impl Write, frame: &ArchiveFrame) -> Result<()> {
+    rmp_serde::encode::write(writer, frame)
+        .map_err(|err| Error::InvalidNodeArchive(err.to_string()))
+}
+
+fn read_frame(reader: &mut BufReader<File>) -> Result<ArchiveFrame> {
+    rmp_serde::decode::from_read(reader).map_err(|err| match err {
+        rmp_serde::decode::Error::InvalidMarkerRead(err)
+        | rmp_serde::decode::Error::InvalidDataRead(err)
+            if err.kind() == ErrorKind::UnexpectedEof =>
+        {
+            Error::InvalidNodeArchive("the archive is truncated".to_string())
+        }
+        err => Error::InvalidNodeArchive(err.to_string()),
+    })
+}
+
+/// The path of a file of the root dir in the archive.
+fn archive_path_of(root_dir: &Path, path: &Path) -> Result<String> {
+    let relative = path
+        .strip_prefix(root_dir)
+        .map_err(|err| Error::InvalidNodeArchive(err.to_string()))?;
+    let components = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(name) => name
+                .to_str()
+                .map(str::to_string)
+                .ok_or_else(|| Error::InvalidNodeArchive(format!("invalid file name {name:?}"))),
+            _ => Err(Error::InvalidNodeArchive(format!(
+                "invalid path {relative:?}"
+            ))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(components.join("/"))
+}
+
+/// The path of a file of the archive, relative to the root dir. Paths leaving the root dir are
+/// rejected.
+fn local_path_of(path: &str) -> Result<PathBuf> {
+    let mut local_path = PathBuf::new();
+    for name in path.split('/') {
+        let is_normal = matches!(
+            Path::new(name).components().collect::<Vec<_>>().as_slice(),
+            [Component::Normal(_)]
+        );
+        if !is_normal {
+            return Err(Error::InvalidNodeArchive(format!("invalid path {path:?}")));
+        }
+        local_path.push(name);
+    }
+    Ok(local_path)
+}
+
+fn write_private_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut opt = private_file_options();
+    let _ = opt.create_new(true);
+    opt.open(path)?.write_all(content)?;
+    Ok(())
+}
+
+/// Files are only readable by the current user, as the identity and wallet keys are among them.
+fn private_file_options() -> OpenOptions {
+    let mut opt = OpenOptions::new();
+    let _ = opt.write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        let _ = opt.mode(0o600);
+    }
+    opt
+}
+
+/// The key the files of an archive are sealed with.
+struct ArchiveKey(LessSafeKey);
+
+impl ArchiveKey {
+    fn derive(password: &str, salt: &[u8; 16]) -> Result<Self> {
+        let mut key = [0; 32];
+        ring::pbkdf2::derive(
+            ring::pbkdf2::PBKDF2_HMAC_SHA512,
+            KEY_ITERATIONS,
+            salt,
+            password.as_bytes(),
+            &mut key,
+        );
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &key).map_err(|_| {
+            Error::InvalidNodeArchive("could not create the key of the archive".to_string())
+        })?;
+        Ok(Self(LessSafeKey::new(key)))
+    }
+
+    /// The path is authenticated along with the content, for a file not to be moved to another.
+    fn seal(&self, path: &str, nonce: [u8; 12], mut content: Vec<u8>) -> Result<Vec<u8>> {
+        self.0
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(path.as_bytes()),
+                &mut content,
+            )
+            .map_err(|_| Error::InvalidNodeArchive(format!("could not seal {path:?}")))?;
+        Ok(content)
+    }
+
+    fn open(&self, path: &str, nonce: [u8; 12], mut content: Vec<u8>) -> Result<Vec<u8>> {
+        let len = self
+            .0
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(path.as_bytes()),
+                &mut content,
+            )
+            .map_err(|_| {
+                Error::InvalidNodeArchive(format!(
+                    "wrong password, or the content of {path:?} is corrupted"
+                ))
+            })?
+            .len();
+        content.truncate(len);
+        Ok(content)
+    }
+}
+
+/// The digest of the files of an archive, chained so that it doesn't grow with them.
+#[derive(Default)]
+struct Digest {
+    files: u64,
+    digest: XorName,
+}
+
+impl Digest {
+    fn add(&mut self, path: &str, hash: &XorName) {
+        self.files += 1;
+        self.digest = XorName::from_content_parts(&[&self.digest.0, path.as_bytes(), &hash.0]);
+    }
+
+    fn finish(&self) -> XorName {
+        self.digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    const PASSWORD: &str = "password";
+
+    fn node_dir(dir: &Path) -> Result<PeerId> {
+        let keypair = libp2p::identity::ed25519::Keypair::generate();
+        fs::create_dir_all(dir.join("record_store"))?;
+        fs::create_dir_all(dir.join("wallet"))?;
+        fs::create_dir_all(dir.join("logs"))?;
+        fs::write(dir.join(SECRET_KEY_FILE_NAME), keypair.secret().as_ref())?;
+        fs::write(dir.join("record_store").join("record"), b"chunk")?;
+        fs::write(dir.join("wallet").join("main_secret_key"), b"wallet key")?;
+        fs::write(dir.join("logs").join("safenode.log"), b"log")?;
+        Ok(Keypair::from(keypair).public().to_peer_id())
+    }
+
+    #[test]
+    fn node_data_should_be_moved_with_its_identity() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let peer_id = node_dir(&tmp.path().join("old"))?;
+        let archive = tmp.path().join("node.archive");
+
+        let exported = export_node_data(&tmp.path().join("old"), &archive, PASSWORD)?;
+        assert_eq!(exported.peer_id, peer_id);
+        assert_eq!(exported.files, 3);
+        assert_eq!(archived_peer_id(&archive)?, peer_id);
+
+        let new_dir = tmp.path().join("new");
+        let imported = import_node_data(&archive, &new_dir, PASSWORD)?;
+        assert_eq!(imported, exported);
+        assert_eq!(
+            fs::read(new_dir.join("record_store").join("record"))?,
+            b"chunk"
+        );
+        assert!(!new_dir.join("logs").exists());
+
+        // a node is never overwritten
+        assert!(import_node_data(&archive, &new_dir, PASSWORD).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn altered_archives_should_not_be_imported() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let _ = node_dir(&tmp.path().join("old"))?;
+        let archive = tmp.path().join("node.archive");
+        let _ = export_node_data(&tmp.path().join("old"), &archive, PASSWORD)?;
+        let bytes = fs::read(&archive)?;
+
+        let truncated = tmp.path().join("truncated.archive");
+        fs::write(&truncated, &bytes[..bytes.len() - 10])?;
+        assert!(import_node_data(&truncated, &tmp.path().join("new"), PASSWORD).is_err());
+        assert!(!tmp.path().join("new").exists());
+
+        let mut reader = BufReader::new(File::open(&archive)?);
+        let mut altered = vec![];
+        loop {
+            let mut frame = read_frame(&mut reader)?;
+            if let ArchiveFrame::File { path, content, .. } = &mut frame {
+                if path == "record_store/record" {
+                    content[0] ^= 1;
+                }
+            }
+            let end = matches!(frame, ArchiveFrame::End { .. });
+            write_frame(&mut altered, &frame)?;
+            if end {
+                break;
+            }
+        }
+        let altered_path = tmp.path().join("altered.archive");
+        fs::write(&altered_path, altered)?;
+        assert!(import_node_data(&altered_path, &tmp.path().join("new"), PASSWORD).is_err());
+        assert!(!tmp.path().join("new").exists());
+
+        assert!(local_path_of("../secret-key").is_err());
+        assert!(local_path_of("wallet//key").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn archived_keys_should_only_be_read_with_the_password() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let peer_id = node_dir(&tmp.path().join("old"))?;
+        let archive = tmp.path().join("node.archive");
+        let _ = export_node_data(&tmp.path().join("old"), &archive, PASSWORD)?;
+
+        let secret_key = fs::read(tmp.path().join("old").join(SECRET_KEY_FILE_NAME))?;
+        let bytes = fs::read(&archive)?;
+        assert!(!bytes.windows(10).any(|window| window == b"wallet key"));
+        assert!(!bytes
+            .windows(secret_key.len())
+            .any(|window| window == secret_key));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&archive)?.permissions().mode() & 0o777, 0o600);
+        }
+
+        // the PeerId is readable without the password, to find the root dir of the node
+        assert_eq!(archived_peer_id(&archive)?, peer_id);
+        assert!(import_node_data(&archive, &tmp.path().join("new"), "wrong").is_err());
+        assert!(!tmp.path().join("new").exists());
+        Ok(())
+    }
+}
@@ -10,8 +10,11 @@ use super::{
     error::{Error, Result},
     event::NodeEventsChannel,
     quote::quotes_verification,
+    snapshot::SnapshotSource,
     Marker, NodeEvent,
 };
+#[cfg(feature = "chaos")]
+use crate::chaos::{Chaos, ChaosConfig};
 #[cfg(feature = "open-metrics")]
 use crate::metrics::NodeMetricsRecorder;
 use crate::RunningNode;
@@ -23,8 +26,8 @@ use prometheus_client::metrics::{gauge::Gauge, info::Info};
 use prometheus_client::registry::Registry;
 use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
 use sn_networking::{
-    close_group_majority, Instant, Network, NetworkBuilder, NetworkError, NetworkEvent, NodeIssue,
-    SwarmDriver,
+    close_group_majority, transfer_notifs_topics, Instant, Network, NetworkBuilder, NetworkError,
+    NetworkEvent, NodeIssue, SwarmDriver,
 };
 use sn_protocol::{
     error::Error as ProtocolError,
@@ -97,6 +100,10 @@ pub struct NodeBuilder {
     owner: Option<String>,
     #[cfg(feature = "upnp")]
     upnp: bool,
+    #[cfg(feature = "chaos")]
+    chaos: ChaosConfig,
+    snapshot: Option<(SnapshotSource, Option<PathBuf>)>,
+    external_reward_address: Option<MainPubkey>,
 }
 
 impl NodeBuilder {
@@ -122,6 +129,10 @@ impl NodeBuilder {
             owner,
             #[cfg(feature = "upnp")]
             upnp,
+            #[cfg(feature = "chaos")]
+            chaos: ChaosConfig::default(),
+            snapshot: None,
+            external_reward_address: None,
         }
     }
 
@@ -131,6 +142,23 @@ impl NodeBuilder {
         self.metrics_server_port = port;
     }
 
+    #[cfg(feature = "chaos")]
+    /// Set the faults to inject from the start. None are injected if not set
+    pub fn chaos(&mut self, config: ChaosConfig) {
+        self.chaos = config;
+    }
+
+    /// Import a snapshot of the records of the node's address range once it is connected to the
+    /// network. The snapshot fetched from a peer is also written to `archive` if set.
+    pub fn snapshot_bootstrap(&mut self, source: SnapshotSource, archive: Option<PathBuf>) {
+        self.snapshot = Some((source, archive));
+    }
+
+    /// Have the node's earnings paid directly to an external key, rather than to its own wallet.
+    pub fn external_reward_address(&mut self, reward_address: MainPubkey) {
+        self.external_reward_address = Some(reward_address);
+    }
+
     /// Asynchronously runs a new node instance, setting up the swarm driver,
     /// creating a data storage, and handling network events. Returns the
     /// created `RunningNode` which contains a `NodeEventsChannel` for listening
@@ -152,7 +180,13 @@ impl NodeBuilder {
         let mut rng = sn_transfers::rng::from_vec(&sig_vec);
 
         let reward_key = MainSecretKey::random_from_rng(&mut rng);
-        let reward_address = reward_key.main_pubkey();
+        let reward_address = match self.external_reward_address {
+            Some(external_reward_address) => {
+                info!("The earnings of the node are paid to {external_reward_address:?}");
+                external_reward_address
+            }
+            None => reward_key.main_pubkey(),
+        };
 
         let mut wallet = HotWallet::load_from_main_key(&self.root_dir, reward_key)?;
         // store in case it's a fresh wallet created if none was found
@@ -196,15 +230,21 @@ impl NodeBuilder {
 
         let (network, network_event_receiver, swarm_driver) = network_builder.build_node()?;
         let node_events_channel = NodeEventsChannel::default();
+        #[cfg(feature = "chaos")]
+        let chaos = Chaos::new(self.chaos);
 
         let node = NodeInner {
             network: network.clone(),
             events_channel: node_events_channel.clone(),
             initial_peers: self.initial_peers,
             reward_address,
+            is_reward_address_external: self.external_reward_address.is_some(),
             #[cfg(feature = "open-metrics")]
             node_metrics,
             owner: self.owner,
+            #[cfg(feature = "chaos")]
+            chaos: chaos.clone(),
+            snapshot: self.snapshot,
         };
         let node = Node {
             inner: Arc::new(node),
@@ -212,6 +252,8 @@ impl NodeBuilder {
         let running_node = RunningNode {
             network,
             node_events_channel,
+            #[cfg(feature = "chaos")]
+            chaos,
         };
 
         // Run the node
@@ -242,6 +284,12 @@ struct NodeInner {
     /// If not set, there will be no payment forward to be undertaken
     owner: Option<String>,
     reward_address: MainPubkey,
+    /// Whether the earnings are paid to an external key rather than to the node's wallet
+    is_reward_address_external: bool,
+    #[cfg(feature = "chaos")]
+    chaos: Chaos,
+    /// The snapshot to import once connected to the network, and where to archive it
+    snapshot: Option<(SnapshotSource, Option<PathBuf>)>,
 }
 
 impl Node {
@@ -276,6 +324,14 @@ impl Node {
         &self.inner.reward_address
     }
 
+    /// Returns the reward address of the node if it is an external key, which the node cannot
+    /// decrypt the payments to
+    pub(crate) fn external_reward_address(&self) -> Option<MainPubkey> {
+        self.inner
+            .is_reward_address_external
+            .then_some(self.inner.reward_address)
+    }
+
     /// Runs the provided `SwarmDriver` and spawns a task to process for `NetworkEvents`
     fn run(self, swarm_driver: SwarmDriver, mut network_event_receiver: Receiver<NetworkEvent>) {
         let mut rng = StdRng::from_entropy();
@@ -298,6 +354,18 @@ impl Node {
         });
 
         let _handle = spawn(swarm_driver.run());
+
+        // relay the notices of transfers, which clients publish for the recipients to learn
+        // about their incoming funds
+        let network = self.network().clone();
+        let _handle = spawn(async move {
+            for topic in transfer_notifs_topics() {
+                if let Err(err) = network.subscribe_to_topic(topic.clone()).await {
+                    warn!("Could not subscribe to the gossipsub topic {topic:?}: {err:?}");
+                }
+            }
+        });
+
         let _handle = spawn(async move {
             // use a random inactivity timeout to ensure that the nodes do not sync when messages
             // are being transmitted.
@@ -437,6 +505,11 @@ impl Node {
                         let _handle = spawn(async move {
                             Self::trigger_unrelevant_record_cleanup(network);
                         });
+
+                        if self.external_reward_address().is_some() {
+                            self.prune_accepted_cash_notes();
+                        }
+                        self.prune_payment_receipts();
                     }
                 }
             }
@@ -471,6 +544,15 @@ impl Node {
                 if peers_connected.load(Ordering::SeqCst) == CLOSE_GROUP_SIZE {
                     self.events_channel()
                         .broadcast(NodeEvent::ConnectedToNetwork);
+
+                    if let Some((source, archive)) = self.inner.snapshot.clone() {
+                        let node = self.clone();
+                        let _handle = spawn(async move {
+                            if let Err(err) = node.bootstrap_from_snapshot(source, archive).await {
+                                error!("Could not bootstrap from the record snapshot: {err:?}");
+                            }
+                        });
+                    }
                 }
 
                 self.record_metrics(Marker::PeersInRoutingTable(connected_peers));
@@ -527,13 +609,39 @@ impl Node {
                     error!("Error while trying to fetch replicated data {err:?}");
                 }
             }
-            NetworkEvent::QueryRequestReceived { query, channel } => {
+            NetworkEvent::QueryRequestReceived {
+                query,
+                peer,
+                signer,
+                channel,
+            } => {
                 event_header = "QueryRequestReceived";
+                if let Some(signer) = signer {
+                    debug!("Query {query:?} is signed by {signer:?}");
+                }
                 let network = self.network().clone();
                 let payment_address = *self.reward_address();
+                let external_reward_address = self.external_reward_address();
+                #[cfg(feature = "chaos")]
+                let chaos = self.inner.chaos.config();
 
                 let _handle = spawn(async move {
-                    let res = Self::handle_query(&network, query, payment_address).await;
+                    #[cfg(feature = "chaos")]
+                    if chaos.should_drop_request() {
+                        warn!("Chaos: dropping query {query:?}");
+                        return;
+                    }
+
+                    let res = Self::handle_query(
+                        &network,
+                        query,
+                        peer,
+                        payment_address,
+                        external_reward_address,
+                    )
+                    .await;
+                    #[cfg(feature = "chaos")]
+                    let res = chaos.tamper_with_response(res).await;
                     debug!("Sending response {res:?}");
 
                     network.send_response(res, channel);
@@ -613,6 +721,11 @@ impl Node {
                     network.record_node_issues(peer_id, NodeIssue::FailedChunkProofCheck);
                 });
             }
+            NetworkEvent::GossipsubMsg { .. } => {
+                // nodes only relay the messages, which gossipsub does by itself once the
+                // networking validated them
+                event_header = "GossipsubMsg";
+            }
         }
 
         trace!(
@@ -691,10 +804,18 @@ impl Node {
         Ok(())
     }
 
+    #[tracing::instrument(
+        skip_all,
+        fields(record_key = query
+            .dst()
+            .map(|dst| PrettyPrintRecordKey::from(&dst.to_record_key()).to_string()))
+    )]
     async fn handle_query(
         network: &Network,
         query: Query,
+        peer: PeerId,
         payment_address: MainPubkey,
+        external_reward_address: Option<MainPubkey>,
     ) -> Response {
         let resp: QueryResponse = match query {
             Query::GetStoreCost(address) => {
@@ -721,6 +842,7 @@ impl Node {
                                     cost,
                                     &address,
                                     &quoting_metrics,
+                                    external_reward_address,
                                 ),
                                 payment_address,
                                 peer_address: NetworkAddress::from_peer(self_id),
@@ -803,6 +925,40 @@ impl Node {
                     is_in_trouble,
                 }
             }
+            Query::GetRecordSnapshot {
+                requester,
+                start,
+                after,
+            } => {
+                debug!("Got GetRecordSnapshot from {peer:?} for {requester:?}, starting at {start} after {after:?}");
+
+                // the snapshot holds every record of the range of the requester, only hand it
+                // to the requester itself
+                let result = if requester.as_peer_id() == Some(peer) {
+                    Self::take_record_snapshot(network, requester, start, after).await
+                } else {
+                    warn!("Peer {peer:?} queried the record snapshot of {requester:?}");
+                    Err(ProtocolError::SnapshotRequesterMismatch {
+                        requester: Box::new(requester),
+                    })
+                };
+                QueryResponse::GetRecordSnapshot(result)
+            }
+            Query::GetPaymentReceipt { key, quote_id } => {
+                debug!("Got GetPaymentReceipt for {key:?} and quote {quote_id:?}");
+
+                QueryResponse::GetPaymentReceipt(Self::get_payment_receipt(network, key, quote_id))
+            }
+            Query::GetPaymentReceipts(receipts) => {
+                debug!("Got GetPaymentReceipts for {} records", receipts.len());
+
+                QueryResponse::GetPaymentReceipts(Self::get_payment_receipts(network, receipts))
+            }
+            Query::Unknown(unknown) => {
+                // answer in kind, rather than leaving the requester to time out
+                debug!("Got a query unknown to this version");
+                QueryResponse::Unknown(unknown)
+            }
         };
         Response::Query(resp)
     }
@@ -10,9 +10,10 @@ use crate::{node::Node, quote::verify_quote_for_storecost, Error, Marker, Result
 use libp2p::kad::{Record, RecordKey};
 use sn_networking::{get_raw_signed_spends_from_record, GetRecordError, NetworkError};
 use sn_protocol::{
+    messages::PaymentReceipt,
     storage::{
         try_deserialize_record, try_serialize_record, Chunk, RecordHeader, RecordKind, RecordType,
-        Scratchpad, SpendAddress,
+        Scratchpad, SpendAddress, MAX_SCRATCHPAD_SIZE,
     },
     NetworkAddress, PrettyPrintRecordKey,
 };
@@ -27,8 +28,11 @@ use xor_name::XorName;
 
 impl Node {
     /// Validate a record and it's payment, and store the record to the RecordStore
+    #[tracing::instrument(skip_all, fields(record_key = %PrettyPrintRecordKey::from(&record.key)))]
     pub(crate) async fn validate_and_store_record(&self, record: Record) -> Result<()> {
         let record_header = RecordHeader::from_record(&record)?;
+        // refuse the records written in a format this version cannot read, rather than guessing
+        record_header.ensure_supported()?;
 
         match record_header.kind {
             RecordKind::ChunkWithPayment => {
@@ -47,6 +51,10 @@ impl Node {
                 // Now that we've taken any money passed to us, regardless of the payment's validity,
                 // if we already have the data we can return early
                 if already_exists {
+                    if let Ok(receipt) = payment_res {
+                        self.keep_payment_receipt(receipt);
+                    }
+
                     // if we're receiving this chunk PUT again, and we have been paid,
                     // we eagery retry replicaiton as it seems like other nodes are having trouble
                     // did not manage to get this chunk as yet
@@ -66,7 +74,7 @@ impl Node {
                 }
 
                 // Finally before we store, lets bail for any payment issues
-                payment_res?;
+                let receipt = payment_res?;
 
                 // Writing chunk to disk takes time, hence try to execute it first.
                 // So that when the replicate target asking for the copy,
@@ -76,6 +84,7 @@ impl Node {
                 if store_chunk_result.is_ok() {
                     Marker::ValidPaidChunkPutFromClient(&PrettyPrintRecordKey::from(&record.key))
                         .log();
+                    self.keep_payment_receipt(receipt);
                     self.replicate_valid_fresh_record(record_key, RecordType::Chunk);
 
                     // Notify replication_fetcher to mark the attempt as completed.
@@ -98,7 +107,7 @@ impl Node {
                 let record_key = record.key.clone();
                 let (payment, scratchpad) =
                     try_deserialize_record::<(Payment, Scratchpad)>(&record)?;
-                let _already_exists = self
+                let already_exists = self
                     .validate_key_and_existence(&scratchpad.network_address(), &record_key)
                     .await?;
 
@@ -111,14 +120,25 @@ impl Node {
                     )
                     .await;
 
-                // Finally before we store, lets bail for any payment issues
-                payment_res?;
+                // Finally before we store, lets bail for any payment issues.
+                // As with registers, an existing scratchpad may be getting an update only.
+                let receipt = match payment_res {
+                    Ok(receipt) => Some(receipt),
+                    Err(err) if already_exists => {
+                        debug!(
+                            "Payment of the incoming existing scratchpad {:?} having error {err:?}",
+                            scratchpad.address()
+                        );
+                        None
+                    }
+                    Err(err) => return Err(err),
+                };
 
                 // Writing chunk to disk takes time, hence try to execute it first.
                 // So that when the replicate target asking for the copy,
                 // the node can have a higher chance to respond.
                 let store_scratchpad_result = self
-                    .validate_and_store_scratchpad_record(record, true)
+                    .validate_and_store_scratchpad_record(scratchpad, record_key.clone(), true)
                     .await;
 
                 if store_scratchpad_result.is_ok() {
@@ -126,6 +146,9 @@ impl Node {
                         &record_key,
                     ))
                     .log();
+                    if let Some(receipt) = receipt {
+                        self.keep_payment_receipt(receipt);
+                    }
                     self.replicate_valid_fresh_record(record_key.clone(), RecordType::Scratchpad);
 
                     // Notify replication_fetcher to mark the attempt as completed.
@@ -138,10 +161,31 @@ impl Node {
                 store_scratchpad_result
             }
             RecordKind::Scratchpad => {
-                error!("Scratchpad should not be validated at this point");
-                Err(Error::InvalidPutWithoutPayment(
-                    PrettyPrintRecordKey::from(&record.key).into_owned(),
-                ))
+                // An update of a scratchpad we already hold does not need to be paid for again
+                let record_key = record.key.clone();
+                let scratchpad = try_deserialize_record::<Scratchpad>(&record)?;
+                let net_addr = scratchpad.network_address();
+                let pretty_key = PrettyPrintRecordKey::from(&record_key);
+                debug!("Got record to store without payment for scratchpad at {pretty_key:?}");
+                if !self
+                    .validate_key_and_existence(&net_addr, &record_key)
+                    .await?
+                {
+                    debug!("Ignore store without payment for scratchpad at {pretty_key:?}");
+                    return Err(Error::InvalidPutWithoutPayment(
+                        PrettyPrintRecordKey::from(&record.key).into_owned(),
+                    ));
+                }
+
+                let result = self
+                    .validate_and_store_scratchpad_record(scratchpad, record_key.clone(), true)
+                    .await;
+                if result.is_ok() {
+                    Marker::ValidScratchpadRecordPutFromClient(&pretty_key).log();
+                    self.network()
+                        .notify_fetch_completed(record_key, RecordType::Scratchpad);
+                }
+                result
             }
             RecordKind::Spend => {
                 let record_key = record.key.clone();
@@ -228,20 +272,26 @@ impl Node {
                 // The payment shall get deposit to self even the register already presents.
                 // However, if the register already presents, the incoming one maybe for edit only.
                 // Hence the corresponding payment error shall not be thrown out.
-                if let Err(err) = self
+                let receipt = match self
                     .payment_for_us_exists_and_is_still_valid(&net_addr, payment)
                     .await
                 {
-                    if already_exists {
+                    Ok(receipt) => Some(receipt),
+                    Err(err) if already_exists => {
                         debug!("Payment of the incoming exists register {pretty_key:?} having error {err:?}");
-                    } else {
+                        None
+                    }
+                    Err(err) => {
                         error!("Payment of the incoming non-exist register {pretty_key:?} having error {err:?}");
                         return Err(err);
                     }
-                }
+                };
 
                 let res = self.validate_and_store_register(register, true).await;
                 if res.is_ok() {
+                    if let Some(receipt) = receipt {
+                        self.keep_payment_receipt(receipt);
+                    }
                     let content_hash = XorName::from_content(&record.value);
 
                     // Notify replication_fetcher to mark the attempt as completed.
@@ -258,6 +308,7 @@ impl Node {
     }
 
     /// Store a pre-validated, and already paid record to the RecordStore
+    #[tracing::instrument(skip_all, fields(record_key = %PrettyPrintRecordKey::from(&record.key)))]
     pub(crate) async fn store_replicated_in_record(&self, record: Record) -> Result<()> {
         debug!("Storing record which was replicated to us {:?}", record.key);
         let record_header = RecordHeader::from_record(&record)?;
@@ -289,7 +340,8 @@ impl Node {
                 self.store_chunk(&chunk)
             }
             RecordKind::Scratchpad => {
-                self.validate_and_store_scratchpad_record(record, false)
+                let scratchpad = try_deserialize_record::<Scratchpad>(&record)?;
+                self.validate_and_store_scratchpad_record(scratchpad, record.key, false)
                     .await
             }
             RecordKind::Spend => {
@@ -389,13 +441,10 @@ impl Node {
 
     pub(crate) async fn validate_and_store_scratchpad_record(
         &self,
-        record: Record,
+        scratchpad: Scratchpad,
+        record_key: RecordKey,
         is_client_put: bool,
     ) -> Result<()> {
-        let record_key = record.key.clone();
-
-        let scratchpad = try_deserialize_record::<Scratchpad>(&record)?;
-
         // owner PK is defined herein, so as long as record key and this match, we're good
         let addr = scratchpad.address();
         debug!("Validating and storing scratchpad {addr:?}");
@@ -410,6 +459,14 @@ impl Node {
         // check if the Scratchpad is present locally that we don't have a newer version
         if let Some(local_pad) = self.network().get_local_record(&scratchpad_key).await? {
             let local_pad = try_deserialize_record::<Scratchpad>(&local_pad)?;
+            // the very same scratchpad, as republished by a client, is already held
+            if local_pad == scratchpad {
+                debug!(
+                    "Already holding scratchpad {addr:?} with counter {}",
+                    local_pad.counter
+                );
+                return Ok(());
+            }
             if local_pad.counter >= scratchpad.counter {
                 warn!("Rejecting Scratchpad PUT with counter less than or equal to the current counter");
                 return Err(Error::IgnoringOutdatedScratchpadPut);
@@ -422,10 +479,25 @@ impl Node {
             return Err(Error::InvalidScratchpadSignature);
         }
 
+        if scratchpad.payload_size() > MAX_SCRATCHPAD_SIZE {
+            warn!(
+                "Rejecting Scratchpad PUT of {} bytes",
+                scratchpad.payload_size()
+            );
+            return Err(Error::ScratchpadTooBig(scratchpad.payload_size()));
+        }
+
         info!(
             "Storing sratchpad {addr:?} with content of {:?} as Record locally",
             scratchpad.encrypted_data_hash()
         );
+        // A client may have sent it along with a payment, only the scratchpad itself is kept
+        let record = Record {
+            key: record_key.clone(),
+            value: try_serialize_record(&scratchpad, RecordKind::Scratchpad)?.to_vec(),
+            publisher: None,
+            expires: None,
+        };
         self.network().put_local_record(record);
 
         let pretty_key = PrettyPrintRecordKey::from(&record_key);
@@ -493,6 +565,7 @@ impl Node {
 
     /// Validate and store `Vec<SignedSpend>` to the RecordStore
     /// If we already have a spend at this address, the Vec is extended and stored.
+    #[tracing::instrument(skip_all, fields(record_key = %PrettyPrintRecordKey::from(record_key)))]
     pub(crate) async fn validate_merge_and_store_spends(
         &self,
         signed_spends: Vec<SignedSpend>,
@@ -594,12 +667,29 @@ impl Node {
                     .await
                 {
                     // transfer not for us
-                    Err(NetworkError::Wallet(WalletError::FailedToDecypherTransfer)) => continue,
+                    Err(NetworkError::Wallet(WalletError::FailedToDecypherTransfer(_))) => continue,
                     // transfer invalid
                     Err(e) => return Err(e.into()),
                     // transfer ok, add to cash_notes and continue as more transfers might be ours
                     Ok(cns) => cash_notes.extend(cns),
                 },
+                // only sent to the nodes paying their rewards to an external key, which they cannot
+                // decrypt the transfers to
+                Transfer::Unencrypted(cashnote_redemptions) => {
+                    let Some(reward_address) = self.external_reward_address() else {
+                        continue;
+                    };
+                    match self
+                        .network()
+                        .verify_cash_notes_redemptions(reward_address, &cashnote_redemptions)
+                        .await
+                    {
+                        // transfer not for us, but to another of the holders paid
+                        Err(NetworkError::InvalidTransfer(_)) => continue,
+                        Err(e) => return Err(e.into()),
+                        Ok(cns) => cash_notes.extend(cns),
+                    }
+                }
                 Transfer::NetworkRoyalties(cashnote_redemptions) => {
                     match self
                         .network()
@@ -644,59 +734,94 @@ impl Node {
     }
 
     /// Perform validations on the provided `Record`.
+    ///
+    /// Returns the receipt of the payment once accepted, to be kept when the record is stored.
     async fn payment_for_us_exists_and_is_still_valid(
         &self,
         address: &NetworkAddress,
         payment: Payment,
-    ) -> Result<()> {
+    ) -> Result<PaymentReceipt> {
         let key = address.to_record_key();
         let pretty_key = PrettyPrintRecordKey::from(&key).into_owned();
         debug!("Validating record payment for {pretty_key}");
 
         // load wallet
-        let mut wallet = HotWallet::load_from(self.network().root_dir_path())?;
+        let mut wallet = HotWallet::load_from_async(self.network().root_dir_path()).await?;
         let old_balance = wallet.balance().as_nano();
 
         // unpack transfer
         debug!("Unpacking incoming Transfers for record {pretty_key}");
-        let (received_fee, mut cash_notes, royalties_cash_notes_r) = self
-            .cash_notes_from_transfers(payment.transfers, &wallet, pretty_key.clone())
+        let (mut received_fee, mut cash_notes, royalties_cash_notes_r) = self
+            .cash_notes_from_transfers(payment.transfers.clone(), &wallet, pretty_key.clone())
             .await?;
 
-        // check for cash notes that we have already spent
-        // this can happen in cases where the client retries a failed PUT after we have already used the cash note
-        cash_notes.retain(|cash_note| {
-            let already_present = wallet.cash_note_presents(&cash_note.unique_pubkey());
-            if already_present {
-                return !already_present;
+        // when several holders were paid, ours is the quote signed by us
+        let self_peer_id = self.network().peer_id();
+        let quote = payment
+            .quotes()
+            .find(|quote| quote.check_is_signed_by_claimed_peer(self_peer_id))
+            .ok_or_else(|| Error::NoQuoteFromOurNode(pretty_key.clone()))?
+            .clone();
+
+        if let Some(reward_address) = self.external_reward_address() {
+            // the cash notes are held by the owner of the external key, not by our wallet, so
+            // only the ones bound to our quote count, and we keep track of the ones already
+            // accepted ourselves
+            let paid_to_reward_address = total_cash_notes_amount(&cash_notes)?;
+            let quote_index = quote.derivation_index();
+            cash_notes.retain(|cash_note| cash_note.derivation_index() == quote_index);
+            if cash_notes.is_empty() {
+                return Err(Error::NoPaymentToOurNode(pretty_key));
             }
+            let cash_notes = self.accept_cash_notes(cash_notes)?;
+            let accepted_fee = total_cash_notes_amount(&cash_notes)?;
+            received_fee = received_fee
+                .checked_sub(paid_to_reward_address)
+                .and_then(|royalties_fee| royalties_fee.checked_add(accepted_fee))
+                .ok_or(Error::NumericOverflow)?;
+            debug!(
+                "Received payment of {received_fee:?} in {} new cash note/s for {pretty_key}, paid to {reward_address:?}",
+                cash_notes.len()
+            );
 
-            let spend_addr = SpendAddress::from_unique_pubkey(&cash_note.unique_pubkey());
-            !wallet.has_confirmed_spend(spend_addr)
-        });
-        if cash_notes.is_empty() {
-            info!("All incoming cash notes were already received, no need to further process");
-            return Err(Error::ReusedPayment);
-        }
+            // Notify `record_store` that the node received a payment.
+            self.network().notify_payment_received();
+        } else {
+            // check for cash notes that we have already spent
+            // this can happen in cases where the client retries a failed PUT after we have already used the cash note
+            cash_notes.retain(|cash_note| {
+                let already_present = wallet.cash_note_presents(&cash_note.unique_pubkey());
+                if already_present {
+                    return !already_present;
+                }
+
+                let spend_addr = SpendAddress::from_unique_pubkey(&cash_note.unique_pubkey());
+                !wallet.has_confirmed_spend(spend_addr)
+            });
+            if cash_notes.is_empty() {
+                info!("All incoming cash notes were already received, no need to further process");
+                return Err(Error::ReusedPayment);
+            }
 
-        debug!("Received payment of {received_fee:?} for {pretty_key}");
+            debug!("Received payment of {received_fee:?} for {pretty_key}");
 
-        // Notify `record_store` that the node received a payment.
-        self.network().notify_payment_received();
+            // Notify `record_store` that the node received a payment.
+            self.network().notify_payment_received();
 
-        // deposit the CashNotes in our wallet
-        wallet.deposit_and_store_to_disk(&cash_notes)?;
-        let new_balance = wallet.balance().as_nano();
-        info!(
-            "The new wallet balance is {new_balance}, after earning {}",
-            new_balance - old_balance
-        );
+            // deposit the CashNotes in our wallet
+            wallet.deposit_and_store_to_disk_async(cash_notes).await?;
+            let new_balance = wallet.balance().as_nano();
+            info!(
+                "The new wallet balance is {new_balance}, after earning {}",
+                new_balance - old_balance
+            );
 
-        #[cfg(feature = "open-metrics")]
-        if let Some(node_metrics) = self.node_metrics() {
-            let _ = node_metrics
-                .current_reward_wallet_balance
-                .set(new_balance as i64);
+            #[cfg(feature = "open-metrics")]
+            if let Some(node_metrics) = self.node_metrics() {
+                let _ = node_metrics
+                    .current_reward_wallet_balance
+                    .set(new_balance as i64);
+            }
         }
 
         if royalties_cash_notes_r.is_empty() {
@@ -705,12 +830,13 @@ impl Node {
         }
 
         // check if the quote is valid
-        let storecost = payment.quote.cost;
-        verify_quote_for_storecost(self.network(), payment.quote, address)?;
+        let storecost = quote.cost;
+        let quote_id = quote.id();
+        verify_quote_for_storecost(self.network(), quote, address)?;
         debug!("Payment quote valid for record {pretty_key}");
 
         // Let's check payment is sufficient both for our store cost and for network royalties
-        // Since the storage payment is made to a single node, we can calculate the royalties fee based on that single payment.
+        // Since each holder paid is paid for its own quote, we can calculate the royalties fee based on our single payment.
         let expected_royalties_fee = calculate_royalties_fee(storecost);
         let expected_fee = storecost
             .checked_add(expected_royalties_fee)
@@ -728,7 +854,11 @@ impl Node {
         // vdash metric (if modified please notify at https://github.com/happybeing/vdash/issues):
         info!("Total payment of {received_fee:?} nanos accepted for record {pretty_key}");
 
-        Ok(())
+        Ok(PaymentReceipt {
+            record: address.clone(),
+            amount: received_fee,
+            quote_id,
+        })
     }
 
     async fn register_validation(
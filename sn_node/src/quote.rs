@@ -10,7 +10,7 @@ use crate::{node::Node, Error, Result};
 use libp2p::PeerId;
 use sn_networking::{calculate_cost_for_records, Network, NodeIssue};
 use sn_protocol::{error::Error as ProtocolError, storage::ChunkAddress, NetworkAddress};
-use sn_transfers::{NanoTokens, PaymentQuote, QuotingMetrics};
+use sn_transfers::{MainPubkey, NanoTokens, PaymentQuote, QuotingMetrics, QUOTE_EXPIRATION_SECS};
 use std::time::Duration;
 
 impl Node {
@@ -19,10 +19,19 @@ impl Node {
         cost: NanoTokens,
         address: &NetworkAddress,
         quoting_metrics: &QuotingMetrics,
+        reward_address: Option<MainPubkey>,
     ) -> Result<PaymentQuote, ProtocolError> {
         let content = address.as_xorname().unwrap_or_default();
         let timestamp = std::time::SystemTime::now();
-        let bytes = PaymentQuote::bytes_for_signing(content, cost, timestamp, quoting_metrics);
+        let expiry = Some(timestamp + Duration::from_secs(QUOTE_EXPIRATION_SECS));
+        let bytes = PaymentQuote::bytes_for_signing(
+            content,
+            cost,
+            timestamp,
+            quoting_metrics,
+            expiry,
+            reward_address,
+        );
 
         let Ok(signature) = network.sign(&bytes) else {
             return Err(ProtocolError::QuoteGenerationFailed);
@@ -35,6 +44,8 @@ impl Node {
             quoting_metrics: quoting_metrics.clone(),
             pub_key: network.get_pub_key(),
             signature,
+            expiry,
+            reward_address,
         };
 
         debug!("Created payment quote for {address:?}: {quote:?}");
@@ -65,6 +76,8 @@ pub(crate) fn verify_quote_for_storecost(
         quote.cost,
         quote.timestamp,
         &quote.quoting_metrics,
+        quote.expiry,
+        quote.reward_address,
     );
     let signature = quote.signature;
     if !network.verify(&bytes, &signature) {
@@ -0,0 +1,169 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The receipts of the paid PUTs a node accepted, kept in its root dir for the payers to fetch
+//! them as a proof of which node was paid for which record.
+//!
+//! A payer fetches its receipts once its upload is done, they are hence only kept for a day.
+
+use crate::{error::Result, node::Node};
+use sn_networking::Network;
+use sn_protocol::{
+    error::Error as ProtocolError,
+    messages::{PaymentReceipt, SignedPaymentReceipt, MAX_PAYMENT_RECEIPTS_PER_QUERY},
+    NetworkAddress,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use xor_name::XorName;
+
+const RECEIPTS_DIR_NAME: &str = "payment_receipts";
+
+/// How long a receipt is kept for its payer to fetch it.
+const RECEIPTS_RETENTION: Duration = Duration::from_secs(24 * 3600);
+
+impl Node {
+    /// Sign and keep the receipt of a paid PUT we accepted.
+    ///
+    /// Failing to do so does not fail the PUT, the payer only misses the receipt.
+    pub(crate) fn keep_payment_receipt(&self, receipt: PaymentReceipt) {
+        let quote_id = receipt.quote_id;
+        if let Err(err) = write_receipt(self.network(), &receipt) {
+            warn!("Could not keep the receipt of quote {quote_id:?}: {err:?}");
+        }
+    }
+
+    /// Get the receipt we signed for the payment of the quote `quote_id`, for the record `key`.
+    pub(crate) fn get_payment_receipt(
+        network: &Network,
+        key: NetworkAddress,
+        quote_id: XorName,
+    ) -> Result<SignedPaymentReceipt, ProtocolError> {
+        read_receipt(&receipts_dir(network), key, quote_id)
+    }
+
+    /// Get the receipts we signed for the payments of several quotes, in the same order, up to
+    /// `MAX_PAYMENT_RECEIPTS_PER_QUERY` of them.
+    pub(crate) fn get_payment_receipts(
+        network: &Network,
+        receipts: Vec<(NetworkAddress, XorName)>,
+    ) -> Vec<Result<SignedPaymentReceipt, ProtocolError>> {
+        let dir = receipts_dir(network);
+        receipts
+            .into_iter()
+            .take(MAX_PAYMENT_RECEIPTS_PER_QUERY)
+            .map(|(key, quote_id)| read_receipt(&dir, key, quote_id))
+            .collect()
+    }
+
+    /// Stop keeping the receipts signed long enough ago for their payer to have fetched them.
+    pub(crate) fn prune_payment_receipts(&self) {
+        match prune_receipts(&receipts_dir(self.network()), SystemTime::now()) {
+            Ok(0) => {}
+            Ok(pruned) => info!("Pruned {pruned} payment receipts"),
+            Err(err) => warn!("Could not prune the payment receipts: {err:?}"),
+        }
+    }
+}
+
+fn receipts_dir(network: &Network) -> PathBuf {
+    network.root_dir_path().join(RECEIPTS_DIR_NAME)
+}
+
+fn read_receipt(
+    dir: &Path,
+    key: NetworkAddress,
+    quote_id: XorName,
+) -> Result<SignedPaymentReceipt, ProtocolError> {
+    let not_found = || ProtocolError::ReceiptNotFound {
+        key: Box::new(key.clone()),
+        quote_id,
+    };
+    let bytes = fs::read(dir.join(hex::encode(quote_id))).map_err(|_| not_found())?;
+    let signed: SignedPaymentReceipt =
+        rmp_serde::from_slice(&bytes).map_err(|_| ProtocolError::ReceiptInvalid)?;
+
+    // the quote id is only unique to the quote, not to the record
+    let (_, receipt) = signed.verify()?;
+    if receipt.record != key {
+        return Err(not_found());
+    }
+    Ok(signed)
+}
+
+fn write_receipt(network: &Network, receipt: &PaymentReceipt) -> Result<()> {
+    let signed = SignedPaymentReceipt::sign(network.keypair(), receipt)?;
+    let dir = receipts_dir(network);
+    fs::create_dir_all(&dir)?;
+    let bytes = rmp_serde::to_vec(&signed).map_err(|_| ProtocolError::ReceiptSigningFailed)?;
+    fs::write(dir.join(hex::encode(receipt.quote_id)), bytes)?;
+    Ok(())
+}
+
+fn prune_receipts(dir: &Path, now: SystemTime) -> Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+    let mut pruned = 0;
+    for entry in entries {
+        let entry = entry?;
+        let signed_at = entry.metadata()?.modified()?;
+        if now.duration_since(signed_at).unwrap_or_default() >= RECEIPTS_RETENTION {
+            fs::remove_file(entry.path())?;
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+    use sn_protocol::storage::ChunkAddress;
+    use sn_transfers::NanoTokens;
+
+    #[test]
+    fn receipts_should_be_kept_until_fetched_or_stale() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let key = NetworkAddress::from_chunk_address(ChunkAddress::new(XorName([1; 32])));
+        let quote_id = XorName([2; 32]);
+        let receipt = PaymentReceipt {
+            record: key.clone(),
+            amount: NanoTokens::from(42),
+            quote_id,
+        };
+        let signed = SignedPaymentReceipt::sign(&Keypair::generate_ed25519(), &receipt)?;
+        fs::write(
+            dir.path().join(hex::encode(quote_id)),
+            rmp_serde::to_vec(&signed)?,
+        )?;
+
+        assert_eq!(read_receipt(dir.path(), key.clone(), quote_id)?, signed);
+        // the receipt of a quote is not handed for another record
+        let other = NetworkAddress::from_chunk_address(ChunkAddress::new(XorName([3; 32])));
+        assert!(matches!(
+            read_receipt(dir.path(), other, quote_id),
+            Err(ProtocolError::ReceiptNotFound { .. })
+        ));
+
+        assert_eq!(prune_receipts(dir.path(), SystemTime::now())?, 0);
+        let later = SystemTime::now() + RECEIPTS_RETENTION;
+        assert_eq!(prune_receipts(dir.path(), later)?, 1);
+        assert!(matches!(
+            read_receipt(dir.path(), key, quote_id),
+            Err(ProtocolError::ReceiptNotFound { .. })
+        ));
+        Ok(())
+    }
+}
@@ -18,6 +18,7 @@ use sn_protocol::{
     NetworkAddress, PrettyPrintRecordKey,
 };
 use tokio::task::spawn;
+use tracing::Instrument;
 
 impl Node {
     /// Sends _all_ record keys every interval to all peers within the REPLICATE_RANGE.
@@ -38,6 +39,11 @@ impl Node {
         for (holder, key) in keys_to_fetch {
             let node = self.clone();
             let requester = NetworkAddress::from_peer(self.network().peer_id());
+            let span = info_span!(
+                "fetch_replicated_record",
+                record_key = %PrettyPrintRecordKey::from(&key),
+                %holder
+            );
             let _handle = spawn(async move {
                 let pretty_key = PrettyPrintRecordKey::from(&key).into_owned();
                 debug!("Fetching record {pretty_key:?} from node {holder:?}");
@@ -93,7 +99,7 @@ impl Node {
                 } else {
                     debug!("Completed storing Replication Record {pretty_key:?} from network.");
                 }
-            });
+            }.instrument(span));
         }
         Ok(())
     }
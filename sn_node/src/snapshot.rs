@@ -0,0 +1,278 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Cold-start of a node from a snapshot of the records of its address range, taken by a nearby
+//! node, rather than fetching them one by one through replication.
+
+use crate::{
+    error::{Error, Result},
+    node::Node,
+};
+use libp2p::{kad::Record, PeerId};
+use sn_networking::{Network, MAX_PACKET_SIZE};
+use sn_protocol::{
+    error::Error as ProtocolError,
+    messages::{Query, QueryResponse, RecordSnapshot, Request, Response, SignedRecordSnapshot},
+    NetworkAddress, PrettyPrintRecordKey,
+};
+use std::path::{Path, PathBuf};
+
+/// The max size of the records sent in a single page of a snapshot, kept well below the max size of
+/// the responses for the keys and the encoding to fit as well.
+const SNAPSHOT_PAGE_MAX_SIZE: usize = MAX_PACKET_SIZE / 2;
+
+/// Where a joining node gets the snapshot of the records of its address range from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotSource {
+    /// The closest peer to the node, once it is connected to the network.
+    ClosestPeer,
+    /// An archive provided by the operator, as written with [`SnapshotSource::ClosestPeer`] and an
+    /// archive path.
+    Archive(PathBuf),
+}
+
+/// The counts of the records found in a snapshot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SnapshotImport {
+    pub(crate) imported: usize,
+    pub(crate) rejected: usize,
+}
+
+impl Node {
+    /// Take a page of the snapshot of the records we hold for the address range of `requester`,
+    /// starting right after the record `after`, or at the index `start` for the peers that do not
+    /// page by key.
+    ///
+    /// The records are the ones the requester is closer to than us: as we are responsible for them,
+    /// so will it be. They are sorted by their distance to the requester, closest first, so that
+    /// the records stored or removed between two pages do not shift the following ones.
+    pub(crate) async fn take_record_snapshot(
+        network: &Network,
+        requester: NetworkAddress,
+        start: u32,
+        after: Option<NetworkAddress>,
+    ) -> Result<SignedRecordSnapshot, ProtocolError> {
+        let our_address = NetworkAddress::from_peer(network.peer_id());
+        let addresses = network
+            .get_all_local_record_addresses()
+            .await
+            .map_err(|_| ProtocolError::SnapshotSigningFailed)?;
+        let mut addresses: Vec<_> = addresses
+            .into_keys()
+            .filter(|addr| requester.distance(addr) < our_address.distance(addr))
+            .collect();
+        addresses.sort_by_key(|addr| requester.distance(addr));
+        let first = match &after {
+            Some(after) => addresses
+                .partition_point(|addr| requester.distance(addr) <= requester.distance(after)),
+            None => start as usize,
+        };
+
+        let mut records = vec![];
+        let mut size = 0;
+        let mut next = None;
+        for (index, addr) in addresses.iter().enumerate().skip(first) {
+            let Some(key) = addr.as_record_key() else {
+                continue;
+            };
+            let Ok(Some(record)) = network.get_local_record(&key).await else {
+                continue;
+            };
+            // a page holds at least one record, however large
+            let record_size = key.as_ref().len() + record.value.len();
+            if !records.is_empty() && size + record_size > SNAPSHOT_PAGE_MAX_SIZE {
+                next = Some(index as u32);
+                break;
+            }
+            size += record_size;
+            records.push((addr.clone(), record.value.into()));
+        }
+        debug!(
+            "Took a snapshot of {} records for {requester:?}, starting at {first}",
+            records.len()
+        );
+
+        let snapshot = RecordSnapshot {
+            requester,
+            records,
+            next,
+        };
+        SignedRecordSnapshot::sign(network.keypair(), &snapshot)
+    }
+
+    /// Import the records of the snapshot of our address range, validating each of them as if
+    /// they were replicated to us.
+    ///
+    /// The snapshot fetched from a peer is written to `archive` if set, for it to be imported
+    /// by other nodes with the same address range, e.g. when the node is reset.
+    pub(crate) async fn bootstrap_from_snapshot(
+        &self,
+        source: SnapshotSource,
+        archive: Option<PathBuf>,
+    ) -> Result<SnapshotImport> {
+        let pages = match source {
+            SnapshotSource::ClosestPeer => {
+                let pages = self.fetch_record_snapshot().await?;
+                if let Some(path) = archive {
+                    write_archive(&path, &pages)?;
+                    info!("Wrote the record snapshot to {path:?}");
+                }
+                pages
+            }
+            SnapshotSource::Archive(path) => read_archive(&path)?,
+        };
+
+        let mut import = SnapshotImport::default();
+        for page in pages {
+            let (signer, snapshot) = match page.verify() {
+                Ok(verified) => verified,
+                Err(err) => {
+                    warn!("Skipping a page of the record snapshot: {err:?}");
+                    continue;
+                }
+            };
+            debug!(
+                "Importing {} records of the snapshot signed by {signer:?}",
+                snapshot.records.len()
+            );
+            for (addr, value) in snapshot.records {
+                let Some(key) = addr.as_record_key() else {
+                    import.rejected += 1;
+                    continue;
+                };
+                let pretty_key = PrettyPrintRecordKey::from(&key).into_owned();
+                match self
+                    .store_replicated_in_record(Record::new(key, value.to_vec()))
+                    .await
+                {
+                    Ok(()) => import.imported += 1,
+                    Err(err) => {
+                        warn!("Rejected record {pretty_key:?} of the snapshot: {err:?}");
+                        import.rejected += 1;
+                    }
+                }
+            }
+        }
+        info!(
+            "Imported {} records from the snapshot, rejected {}",
+            import.imported, import.rejected
+        );
+        Ok(import)
+    }
+
+    /// Fetch all the pages of the snapshot of our address range from the closest peer that
+    /// answers.
+    async fn fetch_record_snapshot(&self) -> Result<Vec<SignedRecordSnapshot>> {
+        let network = self.network();
+        let our_id = network.peer_id();
+        let our_address = NetworkAddress::from_peer(our_id);
+        let mut peers: Vec<PeerId> = network
+            .get_closest_k_value_local_peers()
+            .await?
+            .into_iter()
+            .filter(|peer| *peer != our_id)
+            .collect();
+        peers.sort_by_key(|peer| our_address.distance(&NetworkAddress::from_peer(*peer)));
+
+        for peer in peers {
+            match self.fetch_record_snapshot_from(peer, &our_address).await {
+                Ok(pages) => return Ok(pages),
+                Err(err) => warn!("Could not fetch the record snapshot from {peer:?}: {err:?}"),
+            }
+        }
+        Err(Error::SnapshotUnavailable)
+    }
+
+    async fn fetch_record_snapshot_from(
+        &self,
+        peer: PeerId,
+        our_address: &NetworkAddress,
+    ) -> Result<Vec<SignedRecordSnapshot>> {
+        let mut pages = vec![];
+        let mut start = 0;
+        let mut after: Option<NetworkAddress> = None;
+        loop {
+            let req = Request::Query(Query::GetRecordSnapshot {
+                requester: our_address.clone(),
+                start,
+                after: after.clone(),
+            });
+            let page = match self.network().send_request(req, peer).await? {
+                Response::Query(QueryResponse::GetRecordSnapshot(result)) => result?,
+                other => {
+                    return Err(Error::InvalidRequest(format!(
+                        "Unexpected response to the record snapshot query: {other:?}"
+                    )))
+                }
+            };
+            let (signer, snapshot) = page.verify()?;
+            if signer != peer || &snapshot.requester != our_address {
+                return Err(ProtocolError::SnapshotInvalid.into());
+            }
+            pages.push(page);
+            let (Some(next), Some((last, _))) = (snapshot.next, snapshot.records.last()) else {
+                return Ok(pages);
+            };
+            // the pages must move away from us, for the peer not to keep us paging forever
+            if after
+                .as_ref()
+                .is_some_and(|after| our_address.distance(last) <= our_address.distance(after))
+            {
+                return Err(ProtocolError::SnapshotInvalid.into());
+            }
+            // the index is only used by the peers that do not page by key
+            start = next;
+            after = Some(last.clone());
+        }
+    }
+}
+
+/// Read the pages of a snapshot written by [`write_archive`].
+pub(crate) fn read_archive(path: &Path) -> Result<Vec<SignedRecordSnapshot>> {
+    let bytes = std::fs::read(path).map_err(|err| Error::SnapshotArchive(err.to_string()))?;
+    rmp_serde::from_slice(&bytes).map_err(|err| Error::SnapshotArchive(err.to_string()))
+}
+
+/// Write the pages of a snapshot to an archive.
+pub(crate) fn write_archive(path: &Path, pages: &[SignedRecordSnapshot]) -> Result<()> {
+    let bytes = rmp_serde::to_vec(pages).map_err(|err| Error::SnapshotArchive(err.to_string()))?;
+    std::fs::write(path, bytes).map_err(|err| Error::SnapshotArchive(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use libp2p::identity::Keypair;
+
+    #[test]
+    fn archives_should_be_read_back() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("snapshot");
+        let snapshot = RecordSnapshot {
+            requester: NetworkAddress::from_peer(PeerId::random()),
+            records: vec![(
+                NetworkAddress::from_peer(PeerId::random()),
+                Bytes::from_static(b"record"),
+            )],
+            next: None,
+        };
+        let pages = vec![SignedRecordSnapshot::sign(
+            &Keypair::generate_ed25519(),
+            &snapshot,
+        )?];
+
+        write_archive(&path, &pages)?;
+        let read = read_archive(&path)?;
+        assert_eq!(read, pages);
+        assert_eq!(read[0].verify()?.1, snapshot);
+
+        assert!(read_archive(&dir.path().join("missing")).is_err());
+        Ok(())
+    }
+}
@@ -296,7 +296,9 @@ impl Droplet {
             .expect("Client shall be successfully created.")
     }
 
-    // Create a wallet at root_dir and fetch the amount from the faucet url
+    // Create a wallet at root_dir and fetch the amount from the faucet url.
+    // The requests are sent back to back, so the faucet has to run without rate limits, i.e. with
+    // `--ip-interval 0 --wallet-interval 0`.
     async fn get_funded_wallet(
         client: &Client,
         root_dir: &Path,
@@ -336,7 +338,11 @@ impl Droplet {
             let faucet_url = format!("http://{faucet_socket}/{address_hex}");
 
             // Get transfer from faucet
-            let transfer = reqwest::get(&faucet_url).await?.text().await?;
+            let response = reqwest::get(&faucet_url).await?;
+            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                bail!("The faucet at {faucet_socket} rate limits the requests, it has to run with `--ip-interval 0 --wallet-interval 0` for the tests");
+            }
+            let transfer = response.text().await?;
             let transfer = match Transfer::from_hex(&transfer) {
                 Ok(transfer) => transfer,
                 Err(err) => {
@@ -238,6 +238,44 @@ pub enum SubCmd {
     Faucet(FaucetSubCmd),
     #[clap(subcommand)]
     Local(LocalSubCmd),
+    /// Watch the running safenode services, restarting those that crashed or no longer answer
+    /// their RPC.
+    ///
+    /// Only the services started through the manager, and not stopped since, are watched. The
+    /// command runs until interrupted.
+    ///
+    /// If no peer ID(s) or service name(s) are supplied, all services will be watched.
+    ///
+    /// On Windows, this command must run as the administrative user. On Linux/macOS, run using
+    /// sudo if you defined system-wide services; otherwise, do not run the command elevated.
+    #[clap(name = "monitor")]
+    Monitor {
+        /// The max time in seconds to wait for a restarted node to connect to the network.
+        ///
+        /// Defaults to 300s.
+        #[clap(long, default_value_t = DEFAULT_NODE_STARTUP_CONNECTION_TIMEOUT_S)]
+        connection_timeout: u64,
+        /// The interval between checks of the services.
+        ///
+        /// Units are seconds.
+        #[clap(long, default_value_t = 60)]
+        interval: u64,
+        /// The number of times a service is restarted before it is left stopped.
+        ///
+        /// By default, services are always restarted.
+        #[clap(long)]
+        max_restarts: Option<u32>,
+        /// The peer ID of the service to watch.
+        ///
+        /// The argument can be used multiple times to watch many services.
+        #[clap(long)]
+        peer_id: Vec<String>,
+        /// The name of the service to watch.
+        ///
+        /// The argument can be used multiple times to watch many services.
+        #[clap(long, conflicts_with = "peer_id")]
+        service_name: Vec<String>,
+    },
     #[clap(subcommand)]
     NatDetection(NatDetectionSubCmd),
     /// Remove safenode service(s).
@@ -398,6 +436,11 @@ pub enum SubCmd {
         /// Units are milliseconds.
         #[clap(long, conflicts_with = "connection-timeout")]
         interval: Option<u64>,
+        /// Set this flag to stop upgrading services once one of them fails to be upgraded or started.
+        ///
+        /// The services not reached yet keep running their current version.
+        #[clap(long)]
+        halt_on_failure: bool,
         /// Provide a path for the safenode binary to be used by the service.
         ///
         /// Useful for upgrading the service using a custom built binary.
@@ -1241,6 +1284,23 @@ async fn main() -> Result<()> {
             cmd::nat_detection::run_nat_detection(servers, true, path, url, version, verbosity)
                 .await
         }
+        SubCmd::Monitor {
+            connection_timeout,
+            interval,
+            max_restarts,
+            peer_id: peer_ids,
+            service_name: service_names,
+        } => {
+            cmd::node::monitor(
+                connection_timeout,
+                interval,
+                max_restarts,
+                peer_ids,
+                service_names,
+                verbosity,
+            )
+            .await
+        }
         SubCmd::Remove {
             keep_directories,
             peer_id: peer_ids,
@@ -1275,6 +1335,7 @@ async fn main() -> Result<()> {
             connection_timeout,
             do_not_start,
             force,
+            halt_on_failure,
             interval,
             path,
             peer_id: peer_ids,
@@ -1289,6 +1350,7 @@ async fn main() -> Result<()> {
                 path,
                 force,
                 interval,
+                halt_on_failure,
                 peer_ids,
                 provided_env_variable,
                 service_names,
@@ -16,7 +16,7 @@ use crate::{
     },
     config::{self, is_running_as_root},
     helpers::{download_and_extract_release, get_bin_version},
-    print_banner, refresh_node_registry, status_report, ServiceManager, VerbosityLevel,
+    print_banner, refresh_node_registry, status_report, NodeHealth, ServiceManager, VerbosityLevel,
 };
 use color_eyre::{eyre::eyre, Help, Result};
 use colored::Colorize;
@@ -31,7 +31,10 @@ use sn_service_management::{
     NodeRegistry, NodeService, ServiceStateActions, ServiceStatus, UpgradeOptions, UpgradeResult,
 };
 use sn_transfers::HotWallet;
-use std::{cmp::Ordering, io::Write, net::Ipv4Addr, path::PathBuf, str::FromStr, time::Duration};
+use std::{
+    cmp::Ordering, collections::HashMap, io::Write, net::Ipv4Addr, path::PathBuf, str::FromStr,
+    time::Duration,
+};
 use tracing::debug;
 
 /// Returns the added service names
@@ -452,6 +455,7 @@ pub async fn upgrade(
     custom_bin_path: Option<PathBuf>,
     force: bool,
     fixed_interval: Option<u64>,
+    halt_on_failure: bool,
     peer_ids: Vec<String>,
     provided_env_variables: Option<Vec<(String, String)>>,
     service_names: Vec<String>,
@@ -572,6 +576,26 @@ pub async fn upgrade(
                 node_registry.save()?;
             }
         }
+
+        if halt_on_failure
+            && upgrade_summary.last().is_some_and(|(_, r)| {
+                matches!(
+                    r,
+                    UpgradeResult::Error(_) | UpgradeResult::UpgradedButNotStarted(_, _, _)
+                )
+            })
+        {
+            // The remaining nodes keep running their current version rather than being taken
+            // down by a binary that could not be started.
+            warn!("Halting the upgrade after {service_name} failed");
+            if verbosity != VerbosityLevel::Minimal {
+                println!(
+                    "{} Halting the upgrade: {service_name} could not be upgraded and started",
+                    "✕".red()
+                );
+            }
+            break;
+        }
     }
 
     print_upgrade_summary(upgrade_summary.clone());
@@ -588,6 +612,78 @@ pub async fn upgrade(
     Ok(())
 }
 
+/// Watch the nodes recorded as running, restarting those that crashed or no longer answer their
+/// RPC, until interrupted.
+///
+/// The nodes are checked every `interval_s` seconds. A node is restarted at most `max_restarts`
+/// times, if set, after which it is left for the operator to look at.
+pub async fn monitor(
+    connection_timeout_s: u64,
+    interval_s: u64,
+    max_restarts: Option<u32>,
+    peer_ids: Vec<String>,
+    service_names: Vec<String>,
+    verbosity: VerbosityLevel,
+) -> Result<()> {
+    if verbosity != VerbosityLevel::Minimal {
+        print_banner("Monitor Safenode Services");
+    }
+    info!("Monitoring safenode services every {interval_s}s for: {peer_ids:?}, {service_names:?}");
+
+    let mut restarts: HashMap<String, u32> = HashMap::new();
+    loop {
+        let mut node_registry = NodeRegistry::load(&config::get_node_registry_path()?)?;
+        let service_indices =
+            get_services_for_ops(&node_registry, peer_ids.clone(), service_names.clone())?;
+
+        for &index in &service_indices {
+            let node = &mut node_registry.nodes[index];
+            let service_name = node.service_name.clone();
+            let restart_count = restarts.get(&service_name).copied().unwrap_or_default();
+            if max_restarts.is_some_and(|max| restart_count >= max) {
+                continue;
+            }
+
+            let mut rpc_client = RpcClient::from_socket_addr(node.rpc_socket_addr);
+            rpc_client.set_max_attempts(1);
+            let service = NodeService::new(node, Box::new(rpc_client))
+                .with_connection_timeout(Duration::from_secs(connection_timeout_s));
+            let mut service_manager =
+                ServiceManager::new(service, Box::new(ServiceController {}), verbosity);
+
+            match service_manager.restart_if_unhealthy().await {
+                Ok(None) | Ok(Some(NodeHealth::Healthy)) => continue,
+                Ok(Some(health)) => {
+                    info!("Service {service_name} was {health} and has been restarted");
+                }
+                Err(err) => {
+                    error!("Failed to restart service {service_name}: {err}");
+                    if verbosity != VerbosityLevel::Minimal {
+                        println!("{} Failed to restart {service_name}: {err}", "✕".red());
+                    }
+                }
+            }
+
+            let restart_count = restart_count + 1;
+            let _ = restarts.insert(service_name.clone(), restart_count);
+            if max_restarts.is_some_and(|max| restart_count >= max) {
+                warn!(
+                    "Service {service_name} was restarted {restart_count} times, giving up on it"
+                );
+                if verbosity != VerbosityLevel::Minimal {
+                    println!(
+                        "Service {service_name} was restarted {restart_count} times and will no \
+                        longer be restarted"
+                    );
+                }
+            }
+        }
+
+        node_registry.save()?;
+        tokio::time::sleep(Duration::from_secs(interval_s)).await;
+    }
+}
+
 /// Ensure n nodes are running by stopping nodes or by adding and starting nodes if required.
 ///
 /// The arguments here are mostly mirror those used in `add`.
@@ -371,6 +371,74 @@ impl<T: ServiceStateActions + Send> ServiceManager<T> {
     }
 }
 
+/// The health of a node service recorded as running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeHealth {
+    /// The process is running and answers its RPC.
+    Healthy,
+    /// The process is no longer running.
+    Crashed,
+    /// The process is running but does not answer its RPC.
+    Unresponsive,
+}
+
+impl std::fmt::Display for NodeHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NodeHealth::Healthy => write!(f, "healthy"),
+            NodeHealth::Crashed => write!(f, "crashed"),
+            NodeHealth::Unresponsive => write!(f, "unresponsive"),
+        }
+    }
+}
+
+impl ServiceManager<NodeService<'_>> {
+    /// Check the node is still running: its process must be alive and answer its RPC.
+    pub async fn health(&self) -> NodeHealth {
+        if self
+            .service_control
+            .get_process_pid(&self.service.bin_path())
+            .is_err()
+        {
+            return NodeHealth::Crashed;
+        }
+        match self.service.rpc_actions.node_info().await {
+            Ok(_) => NodeHealth::Healthy,
+            Err(err) => {
+                debug!(
+                    "The {} service does not answer its RPC: {err}",
+                    self.service.name()
+                );
+                NodeHealth::Unresponsive
+            }
+        }
+    }
+
+    /// Restart the node if it is recorded as running but is no longer healthy, returning the health
+    /// it was found in.
+    ///
+    /// The nodes stopped on purpose, or never started, are not checked and `None` is returned.
+    pub async fn restart_if_unhealthy(&mut self) -> Result<Option<NodeHealth>> {
+        if self.service.status() != ServiceStatus::Running {
+            return Ok(None);
+        }
+        let health = self.health().await;
+        match health {
+            NodeHealth::Healthy => {}
+            NodeHealth::Crashed => {
+                warn!("The {} service has crashed", self.service.name());
+                self.start().await?;
+            }
+            NodeHealth::Unresponsive => {
+                warn!("The {} service is unresponsive", self.service.name());
+                self.stop().await?;
+                self.start().await?;
+            }
+        }
+        Ok(Some(health))
+    }
+}
+
 pub async fn status_report(
     node_registry: &mut NodeRegistry,
     service_control: &dyn ServiceControl,
@@ -668,6 +736,7 @@ mod tests {
     use assert_matches::assert_matches;
     use async_trait::async_trait;
     use color_eyre::eyre::Result;
+    use libp2p::kad::RecordKey;
     use libp2p_identity::PeerId;
     use mockall::{mock, predicate::*};
     use predicates::prelude::*;
@@ -676,7 +745,7 @@ mod tests {
     use sn_service_management::{
         error::{Error as ServiceControlError, Result as ServiceControlResult},
         node::{NodeService, NodeServiceData},
-        rpc::{NetworkInfo, NodeInfo, RecordAddress, RpcActions},
+        rpc::{CloseGroupConsistency, NetworkInfo, NodeInfo, RecordAddress, RpcActions},
         UpgradeOptions, UpgradeResult,
     };
     use sn_transfers::NanoTokens;
@@ -700,6 +769,8 @@ mod tests {
             async fn node_update(&self, delay_millis: u64) -> ServiceControlResult<()>;
             async fn is_node_connected_to_network(&self, timeout: std::time::Duration) -> ServiceControlResult<()>;
             async fn update_log_level(&self, log_levels: String) -> ServiceControlResult<()>;
+            async fn merge_log_level(&self, log_levels: String) -> ServiceControlResult<String>;
+            async fn close_group_consistency(&self, key: RecordKey) -> ServiceControlResult<CloseGroupConsistency>;
         }
     }
 
@@ -760,6 +831,7 @@ mod tests {
                         "12D3KooWS2tpXGGTmg2AHFiDh57yPQnat49YHnyqoggzXZWpqkCR",
                     )?],
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -861,6 +933,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -1036,6 +1109,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -1204,6 +1278,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -1292,6 +1367,7 @@ mod tests {
                         "12D3KooWS2tpXGGTmg2AHFiDh57yPQnat49YHnyqoggzXZWpqkCR",
                     )?],
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -1680,6 +1756,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -1899,6 +1976,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -2043,6 +2121,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -2322,6 +2401,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -2492,6 +2572,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -2645,6 +2726,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -2801,6 +2883,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -2954,6 +3037,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -3107,6 +3191,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -3263,6 +3348,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -3419,6 +3505,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -3575,6 +3662,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -3730,6 +3818,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -4102,4 +4191,234 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn restart_if_unhealthy_should_not_restart_a_healthy_service() -> Result<()> {
+        let mut mock_service_control = MockServiceControl::new();
+        let mut mock_rpc_client = MockRpcClient::new();
+
+        mock_service_control
+            .expect_get_process_pid()
+            .with(eq(PathBuf::from(
+                "/var/safenode-manager/services/safenode1/safenode",
+            )))
+            .times(1)
+            .returning(|_| Ok(1000));
+        mock_service_control.expect_start().times(0);
+        mock_service_control.expect_stop().times(0);
+        mock_rpc_client.expect_node_info().times(1).returning(|| {
+            Ok(NodeInfo {
+                pid: 1000,
+                peer_id: PeerId::from_str("12D3KooWS2tpXGGTmg2AHFiDh57yPQnat49YHnyqoggzXZWpqkCR")?,
+                data_path: PathBuf::from("/var/safenode-manager/services/safenode1"),
+                log_path: PathBuf::from("/var/log/safenode/safenode1"),
+                version: "0.98.1".to_string(),
+                uptime: std::time::Duration::from_secs(3600),
+                wallet_balance: 0,
+            })
+        });
+
+        let mut service_data = NodeServiceData {
+            auto_restart: false,
+            connected_peers: None,
+            data_dir_path: PathBuf::from("/var/safenode-manager/services/safenode1"),
+            genesis: false,
+            home_network: false,
+            listen_addr: None,
+            local: false,
+            log_dir_path: PathBuf::from("/var/log/safenode/safenode1"),
+            log_format: None,
+            metrics_port: None,
+            node_port: None,
+            number: 1,
+            owner: None,
+            peer_id: Some(PeerId::from_str(
+                "12D3KooWS2tpXGGTmg2AHFiDh57yPQnat49YHnyqoggzXZWpqkCR",
+            )?),
+            pid: Some(1000),
+            reward_balance: Some(NanoTokens::zero()),
+            rpc_socket_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8081),
+            safenode_path: PathBuf::from("/var/safenode-manager/services/safenode1/safenode"),
+            service_name: "safenode1".to_string(),
+            status: ServiceStatus::Running,
+            upnp: false,
+            user: Some("safe".to_string()),
+            user_mode: false,
+            version: "0.98.1".to_string(),
+        };
+        let service = NodeService::new(&mut service_data, Box::new(mock_rpc_client));
+
+        let mut service_manager = ServiceManager::new(
+            service,
+            Box::new(mock_service_control),
+            VerbosityLevel::Normal,
+        );
+
+        let health = service_manager.restart_if_unhealthy().await?;
+
+        assert_eq!(health, Some(NodeHealth::Healthy));
+        assert_eq!(service_manager.service.service_data.pid, Some(1000));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restart_if_unhealthy_should_restart_a_crashed_service() -> Result<()> {
+        let mut mock_service_control = MockServiceControl::new();
+        let mut mock_rpc_client = MockRpcClient::new();
+
+        // Checked once for the health, then once again when starting.
+        mock_service_control
+            .expect_get_process_pid()
+            .with(eq(PathBuf::from(
+                "/var/safenode-manager/services/safenode1/safenode",
+            )))
+            .times(2)
+            .returning(|_| {
+                Err(ServiceError::ServiceProcessNotFound(
+                    "Could not find process at '/var/safenode-manager/services/safenode1/safenode'"
+                        .to_string(),
+                ))
+            });
+        mock_service_control
+            .expect_start()
+            .with(eq("safenode1"), eq(false))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mock_service_control
+            .expect_wait()
+            .with(eq(3000))
+            .times(1)
+            .returning(|_| ());
+        mock_service_control
+            .expect_get_process_pid()
+            .with(eq(PathBuf::from(
+                "/var/safenode-manager/services/safenode1/safenode",
+            )))
+            .times(1)
+            .returning(|_| Ok(1001));
+        mock_rpc_client.expect_node_info().times(1).returning(|| {
+            Ok(NodeInfo {
+                pid: 1001,
+                peer_id: PeerId::from_str("12D3KooWS2tpXGGTmg2AHFiDh57yPQnat49YHnyqoggzXZWpqkCR")?,
+                data_path: PathBuf::from("/var/safenode-manager/services/safenode1"),
+                log_path: PathBuf::from("/var/log/safenode/safenode1"),
+                version: "0.98.1".to_string(),
+                uptime: std::time::Duration::from_secs(1), // the service was just restarted
+                wallet_balance: 0,
+            })
+        });
+        mock_rpc_client
+            .expect_network_info()
+            .times(1)
+            .returning(|| {
+                Ok(NetworkInfo {
+                    connected_peers: Vec::new(),
+                    listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
+                })
+            });
+
+        let mut service_data = NodeServiceData {
+            auto_restart: false,
+            connected_peers: None,
+            data_dir_path: PathBuf::from("/var/safenode-manager/services/safenode1"),
+            genesis: false,
+            home_network: false,
+            listen_addr: None,
+            local: false,
+            log_dir_path: PathBuf::from("/var/log/safenode/safenode1"),
+            log_format: None,
+            metrics_port: None,
+            node_port: None,
+            number: 1,
+            owner: None,
+            peer_id: Some(PeerId::from_str(
+                "12D3KooWS2tpXGGTmg2AHFiDh57yPQnat49YHnyqoggzXZWpqkCR",
+            )?),
+            pid: Some(1000),
+            reward_balance: Some(NanoTokens::zero()),
+            rpc_socket_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8081),
+            safenode_path: PathBuf::from("/var/safenode-manager/services/safenode1/safenode"),
+            service_name: "safenode1".to_string(),
+            status: ServiceStatus::Running,
+            upnp: false,
+            user: Some("safe".to_string()),
+            user_mode: false,
+            version: "0.98.1".to_string(),
+        };
+        let service = NodeService::new(&mut service_data, Box::new(mock_rpc_client));
+
+        let mut service_manager = ServiceManager::new(
+            service,
+            Box::new(mock_service_control),
+            VerbosityLevel::Normal,
+        );
+
+        let health = service_manager.restart_if_unhealthy().await?;
+
+        assert_eq!(health, Some(NodeHealth::Crashed));
+        assert_eq!(service_manager.service.service_data.pid, Some(1001));
+        assert_matches!(
+            service_manager.service.service_data.status,
+            ServiceStatus::Running
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restart_if_unhealthy_should_not_check_a_stopped_service() -> Result<()> {
+        let mut mock_service_control = MockServiceControl::new();
+        let mock_rpc_client = MockRpcClient::new();
+
+        mock_service_control.expect_get_process_pid().times(0);
+        mock_service_control.expect_start().times(0);
+
+        let mut service_data = NodeServiceData {
+            auto_restart: false,
+            connected_peers: None,
+            data_dir_path: PathBuf::from("/var/safenode-manager/services/safenode1"),
+            genesis: false,
+            home_network: false,
+            listen_addr: None,
+            local: false,
+            log_dir_path: PathBuf::from("/var/log/safenode/safenode1"),
+            log_format: None,
+            metrics_port: None,
+            node_port: None,
+            number: 1,
+            owner: None,
+            peer_id: Some(PeerId::from_str(
+                "12D3KooWS2tpXGGTmg2AHFiDh57yPQnat49YHnyqoggzXZWpqkCR",
+            )?),
+            pid: None,
+            reward_balance: Some(NanoTokens::zero()),
+            rpc_socket_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8081),
+            safenode_path: PathBuf::from("/var/safenode-manager/services/safenode1/safenode"),
+            service_name: "safenode1".to_string(),
+            status: ServiceStatus::Stopped,
+            upnp: false,
+            user: Some("safe".to_string()),
+            user_mode: false,
+            version: "0.98.1".to_string(),
+        };
+        let service = NodeService::new(&mut service_data, Box::new(mock_rpc_client));
+
+        let mut service_manager = ServiceManager::new(
+            service,
+            Box::new(mock_service_control),
+            VerbosityLevel::Normal,
+        );
+
+        let health = service_manager.restart_if_unhealthy().await?;
+
+        assert_eq!(health, None);
+        assert_matches!(
+            service_manager.service.service_data.status,
+            ServiceStatus::Stopped
+        );
+
+        Ok(())
+    }
 }
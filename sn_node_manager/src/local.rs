@@ -62,10 +62,15 @@ impl Launcher for LocalSafeLauncher {
 
     fn launch_faucet(&self, genesis_multiaddr: &Multiaddr) -> Result<u32> {
         info!("Launching the faucet server...");
+        // The test clients request gifts back to back, so the rate limits are disabled.
         let args = vec![
             "--peer".to_string(),
             genesis_multiaddr.to_string(),
             "server".to_string(),
+            "--ip-interval".to_string(),
+            "0".to_string(),
+            "--wallet-interval".to_string(),
+            "0".to_string(),
         ];
         let child = Command::new(self.faucet_bin_path.clone())
             .args(args)
@@ -521,12 +526,13 @@ fn get_node_owner(
 mod tests {
     use super::*;
     use async_trait::async_trait;
+    use libp2p::kad::RecordKey;
     use libp2p_identity::PeerId;
     use mockall::mock;
     use mockall::predicate::*;
     use sn_service_management::{
         error::Result as RpcResult,
-        rpc::{NetworkInfo, NodeInfo, RecordAddress, RpcActions},
+        rpc::{CloseGroupConsistency, NetworkInfo, NodeInfo, RecordAddress, RpcActions},
     };
     use std::str::FromStr;
 
@@ -542,6 +548,8 @@ mod tests {
             async fn node_update(&self, delay_millis: u64) -> RpcResult<()>;
             async fn is_node_connected_to_network(&self, timeout: std::time::Duration) -> RpcResult<()>;
             async fn update_log_level(&self, log_levels: String) -> RpcResult<()>;
+            async fn merge_log_level(&self, log_levels: String) -> RpcResult<String>;
+            async fn close_group_consistency(&self, key: RecordKey) -> RpcResult<CloseGroupConsistency>;
         }
     }
 
@@ -595,6 +603,7 @@ mod tests {
                 Ok(NetworkInfo {
                     connected_peers: Vec::new(),
                     listeners: Vec::new(),
+                    peer_protocol_versions: Default::default(),
                 })
             });
 
@@ -9,6 +9,7 @@
 
 use clap::Parser;
 use color_eyre::eyre::Result;
+use libp2p::kad::RecordKey;
 
 use sn_logging::{Level, LogBuilder};
 use sn_node::NodeEvent;
@@ -75,6 +76,19 @@ enum Cmd {
         /// Example: --level libp2p=DEBUG,tokio=INFO,all,sn_client=ERROR
         #[clap(name = "level", long)]
         log_level: String,
+        /// Change only the levels of the modules given, keeping the levels of the others.
+        ///
+        /// Example: --level sn_networking=DEBUG --merge
+        #[clap(long)]
+        merge: bool,
+    },
+    /// Compare the copies of a record held by the nodes of its close group.
+    ///
+    /// Each node of the group is asked whether it holds the record and for the digest of its copy.
+    #[clap(name = "close-group")]
+    CloseGroup {
+        /// The hex-encoded key of the record
+        key: String,
     },
 }
 
@@ -102,7 +116,8 @@ async fn main() -> Result<()> {
         } => node_restart(addr, delay_millis, retain_peer_id).await,
         Cmd::Stop { delay_millis } => node_stop(addr, delay_millis).await,
         Cmd::Update { delay_millis } => node_update(addr, delay_millis).await,
-        Cmd::Log { log_level } => update_log_level(addr, log_level).await,
+        Cmd::Log { log_level, merge } => update_log_level(addr, log_level, merge).await,
+        Cmd::CloseGroup { key } => close_group_consistency(addr, key).await,
     }
 }
 
@@ -141,6 +156,12 @@ pub async fn network_info(addr: SocketAddr) -> Result<()> {
         println!("Listener: {multiaddr_str}");
     }
 
+    println!();
+    println!("Protocol versions of the connected peers:");
+    for (peer_id, protocol_version) in network_info.peer_protocol_versions.iter() {
+        println!("Peer: {peer_id} {protocol_version}");
+    }
+
     Ok(())
 }
 
@@ -212,11 +233,49 @@ pub async fn node_update(addr: SocketAddr, delay_millis: u64) -> Result<()> {
     Ok(())
 }
 
-pub async fn update_log_level(addr: SocketAddr, log_levels: String) -> Result<()> {
+pub async fn update_log_level(addr: SocketAddr, log_levels: String, merge: bool) -> Result<()> {
     let endpoint = format!("https://{addr}");
     let client = RpcClient::new(&endpoint);
 
-    client.update_log_level(log_levels.clone()).await?;
-    println!("Node successfully received the request to update the log level to {log_levels:?}",);
+    if merge {
+        let log_level = client.merge_log_level(log_levels).await?;
+        println!("Node successfully updated its log levels, now {log_level:?}");
+    } else {
+        client.update_log_level(log_levels.clone()).await?;
+        println!(
+            "Node successfully received the request to update the log level to {log_levels:?}",
+        );
+    }
+    Ok(())
+}
+
+pub async fn close_group_consistency(addr: SocketAddr, key: String) -> Result<()> {
+    let endpoint = format!("https://{addr}");
+    let client = RpcClient::new(&endpoint);
+    let key = RecordKey::new(&hex::decode(key)?);
+    let report = client.close_group_consistency(key).await?;
+
+    for (digest, peers) in &report.copies {
+        println!("Copy {digest} held by {} node(s):", peers.len());
+        for peer in peers {
+            println!("    {peer}");
+        }
+    }
+    for peer in &report.missing {
+        println!("Missing from {peer}");
+    }
+    for (peer, reason) in &report.unreachable {
+        println!("No answer from {peer}: {reason}");
+    }
+    if report.is_consistent() {
+        println!("The close group holds a single copy of the record.");
+    } else {
+        println!(
+            "The close group diverges: {} distinct copies, {} missing, {} unreachable.",
+            report.copies.len(),
+            report.missing.len(),
+            report.unreachable.len()
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,346 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The compact binary encoding of a `NetworkAddress`.
+//!
+//! The encoding is a version byte, a tag byte for the kind of address and then the raw bytes of
+//! the address:
+//! - `PeerId` and `RecordKey`: the bytes they hold, of any length
+//! - `ChunkAddress` and `SpendAddress`: the 32 bytes of their xorname
+//! - `RegisterAddress`: the 32 bytes of its meta followed by the 48 bytes of its owner
+//! - `ScratchpadAddress`: the 48 bytes of its owner
+//!
+//! Both the compact form and the derived form used before are accepted when deserializing, but
+//! addresses are still serialized in the derived form: the peers of the previous release, within
+//! the `PROTOCOL_COMPATIBILITY_WINDOW`, only read that one.
+//!
+//! The compact form is written with the `compact-network-address` feature. It is to be enabled by
+//! default together with the next bump of the protocol major version, once every release a peer
+//! can talk to reads it. It can't come with a minor bump: signed messages, e.g. authenticated
+//! requests, are verified over their serialization by the receiver, which would differ between
+//! peers writing either form. The derived form is then still read, from the data stored on disk.
+
+use crate::{
+    error::{Error, Result},
+    storage::{ChunkAddress, RegisterAddress, ScratchpadAddress, SpendAddress},
+    NetworkAddress,
+};
+use bytes::Bytes;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use xor_name::{XorName, XOR_NAME_LEN};
+
+/// The version of the encoding, written as the first byte.
+pub const NETWORK_ADDRESS_ENCODING_VERSION: u8 = 1;
+
+const PEER_ID_TAG: u8 = 0;
+const CHUNK_ADDRESS_TAG: u8 = 1;
+const SPEND_ADDRESS_TAG: u8 = 2;
+const REGISTER_ADDRESS_TAG: u8 = 3;
+const RECORD_KEY_TAG: u8 = 4;
+const SCRATCHPAD_ADDRESS_TAG: u8 = 5;
+
+impl NetworkAddress {
+    /// Encode the address in its compact binary form.
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![NETWORK_ADDRESS_ENCODING_VERSION];
+        match self {
+            NetworkAddress::PeerId(peer_id) => {
+                bytes.push(PEER_ID_TAG);
+                bytes.extend_from_slice(peer_id);
+            }
+            NetworkAddress::ChunkAddress(address) => {
+                bytes.push(CHUNK_ADDRESS_TAG);
+                bytes.extend_from_slice(&address.xorname().0);
+            }
+            NetworkAddress::SpendAddress(address) => {
+                bytes.push(SPEND_ADDRESS_TAG);
+                bytes.extend_from_slice(&address.xorname().0);
+            }
+            NetworkAddress::RegisterAddress(address) => {
+                bytes.push(REGISTER_ADDRESS_TAG);
+                bytes.extend_from_slice(&address.meta().0);
+                bytes.extend_from_slice(&address.owner().to_bytes());
+            }
+            NetworkAddress::RecordKey(key) => {
+                bytes.push(RECORD_KEY_TAG);
+                bytes.extend_from_slice(key);
+            }
+            NetworkAddress::ScratchpadAddress(address) => {
+                bytes.push(SCRATCHPAD_ADDRESS_TAG);
+                bytes.extend_from_slice(&address.owner().to_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Decode an address from its compact binary form.
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self> {
+        let [version, tag, payload @ ..] = bytes else {
+            return Err(Error::NetworkAddressDecodingFailed);
+        };
+        if *version != NETWORK_ADDRESS_ENCODING_VERSION {
+            warn!("Unsupported NetworkAddress encoding version {version}");
+            return Err(Error::NetworkAddressDecodingFailed);
+        }
+
+        let address = match *tag {
+            PEER_ID_TAG => NetworkAddress::PeerId(Bytes::copy_from_slice(payload)),
+            CHUNK_ADDRESS_TAG => NetworkAddress::ChunkAddress(ChunkAddress::new(xorname(payload)?)),
+            SPEND_ADDRESS_TAG => NetworkAddress::SpendAddress(SpendAddress::new(xorname(payload)?)),
+            REGISTER_ADDRESS_TAG => {
+                if payload.len() != XOR_NAME_LEN + bls::PK_SIZE {
+                    return Err(Error::NetworkAddressDecodingFailed);
+                }
+                let (meta, owner) = payload.split_at(XOR_NAME_LEN);
+                NetworkAddress::RegisterAddress(RegisterAddress::new(
+                    xorname(meta)?,
+                    public_key(owner)?,
+                ))
+            }
+            RECORD_KEY_TAG => NetworkAddress::RecordKey(Bytes::copy_from_slice(payload)),
+            SCRATCHPAD_ADDRESS_TAG => {
+                NetworkAddress::ScratchpadAddress(ScratchpadAddress::new(public_key(payload)?))
+            }
+            _ => return Err(Error::NetworkAddressDecodingFailed),
+        };
+        Ok(address)
+    }
+}
+
+fn xorname(bytes: &[u8]) -> Result<XorName> {
+    let bytes = bytes
+        .try_into()
+        .map_err(|_| Error::NetworkAddressDecodingFailed)?;
+    Ok(XorName(bytes))
+}
+
+fn public_key(bytes: &[u8]) -> Result<bls::PublicKey> {
+    let bytes = bytes
+        .try_into()
+        .map_err(|_| Error::NetworkAddressDecodingFailed)?;
+    bls::PublicKey::from_bytes(bytes).map_err(|_| Error::NetworkAddressDecodingFailed)
+}
+
+impl Serialize for NetworkAddress {
+    #[cfg(not(feature = "compact-network-address"))]
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        LegacyNetworkAddress::from(self).serialize(serializer)
+    }
+
+    #[cfg(feature = "compact-network-address")]
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_compact_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for NetworkAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        deserializer.deserialize_any(NetworkAddressVisitor)
+    }
+}
+
+struct NetworkAddressVisitor;
+
+impl<'de> de::Visitor<'de> for NetworkAddressVisitor {
+    type Value = NetworkAddress;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a compact encoded NetworkAddress")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> std::result::Result<Self::Value, E> {
+        NetworkAddress::from_compact_bytes(bytes).map_err(E::custom)
+    }
+
+    // formats without a bytes type, e.g. json, serialize them as a sequence
+    fn visit_seq<A: de::SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        self.visit_bytes(&bytes)
+    }
+
+    // the legacy form is an externally tagged enum, i.e. a single entry map
+    fn visit_map<A: de::MapAccess<'de>>(
+        self,
+        map: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        LegacyNetworkAddress::deserialize(de::value::MapAccessDeserializer::new(map))
+            .map(NetworkAddress::from)
+    }
+}
+
+/// The derived form `NetworkAddress` was serialized in before the compact encoding, still
+/// written for the peers that have not been upgraded yet.
+#[cfg_attr(not(feature = "compact-network-address"), derive(Serialize))]
+#[derive(Deserialize)]
+enum LegacyNetworkAddress {
+    PeerId(Bytes),
+    ChunkAddress(ChunkAddress),
+    SpendAddress(SpendAddress),
+    RegisterAddress(RegisterAddress),
+    RecordKey(Bytes),
+    ScratchpadAddress(ScratchpadAddress),
+}
+
+impl From<LegacyNetworkAddress> for NetworkAddress {
+    fn from(address: LegacyNetworkAddress) -> Self {
+        match address {
+            LegacyNetworkAddress::PeerId(bytes) => NetworkAddress::PeerId(bytes),
+            LegacyNetworkAddress::ChunkAddress(address) => NetworkAddress::ChunkAddress(address),
+            LegacyNetworkAddress::SpendAddress(address) => NetworkAddress::SpendAddress(address),
+            LegacyNetworkAddress::RegisterAddress(address) => {
+                NetworkAddress::RegisterAddress(address)
+            }
+            LegacyNetworkAddress::RecordKey(bytes) => NetworkAddress::RecordKey(bytes),
+            LegacyNetworkAddress::ScratchpadAddress(address) => {
+                NetworkAddress::ScratchpadAddress(address)
+            }
+        }
+    }
+}
+
+impl From<&NetworkAddress> for LegacyNetworkAddress {
+    fn from(address: &NetworkAddress) -> Self {
+        match address.clone() {
+            NetworkAddress::PeerId(bytes) => LegacyNetworkAddress::PeerId(bytes),
+            NetworkAddress::ChunkAddress(address) => LegacyNetworkAddress::ChunkAddress(address),
+            NetworkAddress::SpendAddress(address) => LegacyNetworkAddress::SpendAddress(address),
+            NetworkAddress::RegisterAddress(address) => {
+                LegacyNetworkAddress::RegisterAddress(address)
+            }
+            NetworkAddress::RecordKey(bytes) => LegacyNetworkAddress::RecordKey(bytes),
+            NetworkAddress::ScratchpadAddress(address) => {
+                LegacyNetworkAddress::ScratchpadAddress(address)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::{kad::RecordKey, PeerId};
+
+    fn all_kinds_of_addresses() -> Vec<NetworkAddress> {
+        let mut rng = bls::rand::thread_rng();
+        let owner = bls::SecretKey::random().public_key();
+        vec![
+            NetworkAddress::from_peer(PeerId::random()),
+            NetworkAddress::from_chunk_address(ChunkAddress::new(XorName::random(&mut rng))),
+            NetworkAddress::from_spend_address(SpendAddress::new(XorName::random(&mut rng))),
+            NetworkAddress::from_register_address(RegisterAddress::new(
+                XorName::random(&mut rng),
+                owner,
+            )),
+            NetworkAddress::from_record_key(&RecordKey::new(&XorName::random(&mut rng))),
+            NetworkAddress::ScratchpadAddress(ScratchpadAddress::new(owner)),
+        ]
+    }
+
+    /// A chunk address serialized by a peer of the previous release, in the derived form.
+    const LEGACY_CHUNK_ADDRESS: &str = "81ac4368756e6b41646472657373dc00201111111111111111111111111111111111111111111111111111111111111111";
+
+    /// The form an address serialized compactly takes, e.g. by a peer of the next release.
+    fn compact(address: &NetworkAddress) -> Bytes {
+        Bytes::from(address.to_compact_bytes())
+    }
+
+    #[test]
+    fn compact_encoding_should_round_trip() -> Result<()> {
+        for address in all_kinds_of_addresses() {
+            let bytes = address.to_compact_bytes();
+            assert_eq!(NetworkAddress::from_compact_bytes(&bytes)?, address);
+
+            let serialized =
+                rmp_serde::to_vec(&compact(&address)).map_err(|_| Error::RecordParsingFailed)?;
+            let deserialized: NetworkAddress =
+                rmp_serde::from_slice(&serialized).map_err(|_| Error::RecordParsingFailed)?;
+            assert_eq!(deserialized, address);
+
+            let json = serde_json::to_string(&compact(&address))
+                .map_err(|_| Error::RecordParsingFailed)?;
+            let deserialized: NetworkAddress =
+                serde_json::from_str(&json).map_err(|_| Error::RecordParsingFailed)?;
+            assert_eq!(deserialized, address);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_compact_bytes_should_be_rejected() {
+        let chunk = NetworkAddress::from_chunk_address(ChunkAddress::new(XorName::default()));
+        let mut bytes = chunk.to_compact_bytes();
+
+        bytes.pop();
+        assert!(NetworkAddress::from_compact_bytes(&bytes).is_err());
+        assert!(NetworkAddress::from_compact_bytes(&[NETWORK_ADDRESS_ENCODING_VERSION]).is_err());
+        assert!(
+            NetworkAddress::from_compact_bytes(&[NETWORK_ADDRESS_ENCODING_VERSION, 42]).is_err()
+        );
+
+        let mut bytes = chunk.to_compact_bytes();
+        bytes[0] = NETWORK_ADDRESS_ENCODING_VERSION + 1;
+        assert!(NetworkAddress::from_compact_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn legacy_serialization_should_still_be_read() -> Result<()> {
+        let legacy = hex::decode(LEGACY_CHUNK_ADDRESS).map_err(|_| Error::RecordParsingFailed)?;
+        let address = NetworkAddress::from_chunk_address(ChunkAddress::new(XorName([0x11; 32])));
+
+        let deserialized: NetworkAddress =
+            rmp_serde::from_slice(&legacy).map_err(|_| Error::RecordParsingFailed)?;
+        assert_eq!(deserialized, address);
+
+        // the form written, which is the one read back by the peers, depends on the feature
+        let serialized = rmp_serde::to_vec(&address).map_err(|_| Error::RecordParsingFailed)?;
+        if cfg!(feature = "compact-network-address") {
+            assert_eq!(
+                serialized,
+                rmp_serde::to_vec(&compact(&address)).map_err(|_| Error::RecordParsingFailed)?
+            );
+        } else {
+            assert_eq!(serialized, legacy);
+        }
+
+        for address in all_kinds_of_addresses() {
+            let serialized = rmp_serde::to_vec(&address).map_err(|_| Error::RecordParsingFailed)?;
+            let deserialized: NetworkAddress =
+                rmp_serde::from_slice(&serialized).map_err(|_| Error::RecordParsingFailed)?;
+            assert_eq!(deserialized, address);
+
+            let json = serde_json::to_string(&address).map_err(|_| Error::RecordParsingFailed)?;
+            let deserialized: NetworkAddress =
+                serde_json::from_str(&json).map_err(|_| Error::RecordParsingFailed)?;
+            assert_eq!(deserialized, address);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(not(feature = "compact-network-address"))]
+    fn compact_encoding_should_be_smaller_than_the_legacy_one() -> Result<()> {
+        for address in all_kinds_of_addresses() {
+            let legacy = rmp_serde::to_vec(&address).map_err(|_| Error::RecordParsingFailed)?;
+            let compact =
+                rmp_serde::to_vec(&compact(&address)).map_err(|_| Error::RecordParsingFailed)?;
+            assert!(
+                compact.len() <= legacy.len(),
+                "{address:?} encoded in {} bytes, against {} before",
+                compact.len(),
+                legacy.len()
+            );
+        }
+        Ok(())
+    }
+}
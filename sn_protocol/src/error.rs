@@ -6,15 +6,31 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::messages::schema::KnownVariant;
 use crate::{storage::RegisterAddress, NetworkAddress, PrettyPrintRecordKey};
-use serde::{Deserialize, Serialize};
+use bytes::Bytes;
+use serde::{
+    de::{EnumAccess, VariantAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use thiserror::Error;
+use xor_name::XorName;
 
 /// A specialised `Result` type for protocol crate.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The version of the numeric codes returned by [`Error::code`].
+///
+/// New codes can be added freely, this is only bumped when the meaning of an existing code changes.
+pub const ERROR_CODES_VERSION: u16 = 1;
+
 /// Main error types for the SAFE protocol.
+///
+/// Sent to peers along with its [`code`](Error::code), its retry hint and its message, so that
+/// peers not knowing the error still get those, as an [`Error::Unknown`]. Its derived
+/// serialization is only that of its detail, see `WireError`.
 #[derive(Error, Clone, PartialEq, Eq, Serialize, Deserialize, custom_debug::Debug)]
+#[serde(remote = "Self")]
 #[non_exhaustive]
 pub enum Error {
     // ---------- Misc errors
@@ -26,6 +42,12 @@ pub enum Error {
     ParseRetryStrategyError,
     #[error("Could not obtain data dir")]
     CouldNotObtainDataDir,
+    #[error("Could not parse PaymentStrategy")]
+    ParsePaymentStrategyError,
+
+    // ---------- Protocol version errors
+    #[error("Incompatible protocol versions. Ours: {ours}, theirs: {theirs}")]
+    IncompatibleProtocol { ours: String, theirs: String },
 
     // ---------- Chunk Proof errors
     #[error("Chunk does not exist {0:?}")]
@@ -48,6 +70,12 @@ pub enum Error {
     /// The provided String can't be deserialized as a RegisterAddress
     #[error("Failed to deserialize hex ScratchpadAddress")]
     ScratchpadHexDeserializeFailed,
+    /// The data held by the Scratchpad is not a valid cipher text
+    #[error("Scratchpad data is not a valid cipher text")]
+    ScratchpadCipherTextInvalid,
+    /// The data held by the Scratchpad could not be decrypted with the given key
+    #[error("Failed to decrypt the Scratchpad data")]
+    ScratchpadCipherTextFailedToDecrypt,
 
     // ---------- payment errors
     #[error("There was an error getting the storecost from kademlia store")]
@@ -75,4 +103,298 @@ pub enum Error {
     // The record already exists at this node
     #[error("The record already exists, so do not charge for it: {0:?}")]
     RecordExists(PrettyPrintRecordKey<'static>),
+    // The record is of a format this version cannot read
+    #[error("Unsupported record format version {version} with flags {flags:#04x}")]
+    UnsupportedRecordFormat { version: u8, flags: u8 },
+
+    #[error("Could not decode the NetworkAddress")]
+    NetworkAddressDecodingFailed,
+
+    // ---------- request authentication errors
+    #[error("Could not sign the request")]
+    RequestSigningFailed,
+    #[error("The signature of the request is invalid")]
+    RequestAuthInvalid,
+
+    // ---------- record snapshot errors
+    #[error("Could not sign the record snapshot")]
+    SnapshotSigningFailed,
+    #[error("The record snapshot is malformed or its signature is invalid")]
+    SnapshotInvalid,
+    #[error("The record snapshot was queried for {requester:?} by another peer")]
+    SnapshotRequesterMismatch { requester: Box<NetworkAddress> },
+
+    // ---------- payment receipt errors
+    #[error("Could not sign the payment receipt")]
+    ReceiptSigningFailed,
+    #[error("The payment receipt is malformed or its signature is invalid")]
+    ReceiptInvalid,
+    #[error("No payment receipt for the quote {quote_id:?} of the record {key:?}")]
+    ReceiptNotFound {
+        key: Box<NetworkAddress>,
+        quote_id: XorName,
+    },
+
+    // ---------- message errors
+    #[error("Could not serialize the message: {0}")]
+    MessageSerializationFailed(String),
+    #[error("Could not deserialize the message: {0}")]
+    MessageDeserializationFailed(String),
+    #[error("The message is too large: {size} bytes, max: {max}")]
+    MessageTooLarge { size: usize, max: usize },
+
+    // ---------- errors of other versions
+    /// An error received from a peer, of a code this version does not know.
+    #[error("Error {code} unknown to this version: {message}")]
+    Unknown {
+        code: u16,
+        retryable: bool,
+        message: String,
+    },
+}
+
+impl Error {
+    /// A stable numeric code for the error, so that the errors received in responses can be told
+    /// apart without relying on their textual representation.
+    ///
+    /// Codes are grouped by the kind of error and are never reused, see [`ERROR_CODES_VERSION`].
+    pub fn code(&self) -> u16 {
+        match self {
+            Error::UserDataDirectoryNotObtainable => 1,
+            Error::CouldNotObtainPortFromMultiAddr => 2,
+            Error::ParseRetryStrategyError => 3,
+            Error::CouldNotObtainDataDir => 4,
+            Error::ParsePaymentStrategyError => 5,
+            Error::IncompatibleProtocol { .. } => 100,
+            Error::ChunkDoesNotExist(_) => 200,
+            Error::RegisterNotFound(_) => 300,
+            Error::RegisterAlreadyClaimed(_) => 301,
+            Error::RegisterRecordNotFound { .. } => 302,
+            Error::ScratchpadHexDeserializeFailed => 400,
+            Error::ScratchpadCipherTextInvalid => 401,
+            Error::ScratchpadCipherTextFailedToDecrypt => 402,
+            Error::GetStoreCostFailed => 500,
+            Error::QuoteGenerationFailed => 501,
+            Error::ReplicatedRecordNotFound { .. } => 600,
+            Error::RecordHeaderParsingFailed => 700,
+            Error::RecordParsingFailed => 701,
+            Error::RecordExists(_) => 702,
+            Error::NetworkAddressDecodingFailed => 703,
+            Error::UnsupportedRecordFormat { .. } => 704,
+            Error::RequestSigningFailed => 800,
+            Error::RequestAuthInvalid => 801,
+            Error::MessageSerializationFailed(_) => 900,
+            Error::MessageDeserializationFailed(_) => 901,
+            Error::MessageTooLarge { .. } => 902,
+            Error::SnapshotSigningFailed => 1000,
+            Error::SnapshotInvalid => 1001,
+            Error::SnapshotRequesterMismatch { .. } => 1002,
+            Error::ReceiptSigningFailed => 1100,
+            Error::ReceiptInvalid => 1101,
+            Error::ReceiptNotFound { .. } => 1102,
+            Error::Unknown { code, .. } => *code,
+        }
+    }
+
+    /// Whether the same request might succeed if it is sent again, possibly to another peer.
+    pub fn is_retryable(&self) -> bool {
+        if let Error::Unknown { retryable, .. } = self {
+            return *retryable;
+        }
+        matches!(
+            self,
+            Error::ChunkDoesNotExist(_)
+                | Error::RegisterRecordNotFound { .. }
+                | Error::GetStoreCostFailed
+                | Error::QuoteGenerationFailed
+                | Error::ReplicatedRecordNotFound { .. }
+        )
+    }
+}
+
+/// The variant errors are sent to peers as, as a `WireError`. The other variants are those the
+/// errors were sent as before, which are still read.
+const CODED_VARIANT: &str = "Coded";
+
+/// The form errors are sent to peers in.
+#[derive(Serialize, Deserialize)]
+struct WireError {
+    version: u16,
+    code: u16,
+    retryable: bool,
+    message: String,
+    /// The error itself, which only peers knowing its code can read.
+    detail: Bytes,
+}
+
+/// The detail of a [`WireError`], serialized as derived.
+#[derive(Serialize, Deserialize)]
+struct Detail(#[serde(with = "Error")] Error);
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let detail = rmp_serde::to_vec(&Detail(self.clone())).map_err(serde::ser::Error::custom)?;
+        let wire = WireError {
+            version: ERROR_CODES_VERSION,
+            code: self.code(),
+            retryable: self.is_retryable(),
+            message: self.to_string(),
+            detail: Bytes::from(detail),
+        };
+        // the variant index is not written, variants being tagged by their name
+        serializer.serialize_newtype_variant("Error", 0, CODED_VARIANT, &wire)
+    }
+}
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct ErrorVisitor;
+
+        impl<'de> Visitor<'de> for ErrorVisitor {
+            type Value = Error;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a protocol error")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(
+                self,
+                data: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let (tag, variant) = data.variant::<String>()?;
+                if tag != CODED_VARIANT {
+                    return Error::deserialize(KnownVariant::new(tag, variant));
+                }
+                let wire: WireError = variant.newtype_variant()?;
+                match rmp_serde::from_slice(&wire.detail) {
+                    Ok(Detail(error)) if error.code() == wire.code => Ok(error),
+                    _ => Ok(Error::Unknown {
+                        code: wire.code,
+                        retryable: wire.retryable,
+                        message: wire.message,
+                    }),
+                }
+            }
+        }
+
+        deserializer.deserialize_enum("Error", &[CODED_VARIANT], ErrorVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::ChunkAddress;
+    use std::collections::BTreeSet;
+    use xor_name::XorName;
+
+    #[test]
+    fn error_codes_should_be_unique() {
+        let address = NetworkAddress::from_chunk_address(ChunkAddress::new(XorName::default()));
+        let errors = [
+            Error::UserDataDirectoryNotObtainable,
+            Error::CouldNotObtainPortFromMultiAddr,
+            Error::ParseRetryStrategyError,
+            Error::CouldNotObtainDataDir,
+            Error::ParsePaymentStrategyError,
+            Error::IncompatibleProtocol {
+                ours: "1.0".to_string(),
+                theirs: "2.0".to_string(),
+            },
+            Error::ChunkDoesNotExist(address.clone()),
+            Error::RegisterNotFound(Box::new(RegisterAddress::new(
+                XorName::default(),
+                bls::SecretKey::random().public_key(),
+            ))),
+            Error::RegisterAlreadyClaimed(bls::SecretKey::random().public_key()),
+            Error::RegisterRecordNotFound {
+                holder: Box::new(address.clone()),
+                key: Box::new(address.clone()),
+            },
+            Error::ScratchpadHexDeserializeFailed,
+            Error::ScratchpadCipherTextInvalid,
+            Error::ScratchpadCipherTextFailedToDecrypt,
+            Error::GetStoreCostFailed,
+            Error::QuoteGenerationFailed,
+            Error::ReplicatedRecordNotFound {
+                holder: Box::new(address.clone()),
+                key: Box::new(address.clone()),
+            },
+            Error::RecordHeaderParsingFailed,
+            Error::RecordParsingFailed,
+            Error::RecordExists(PrettyPrintRecordKey::from(&address.to_record_key()).into_owned()),
+            Error::NetworkAddressDecodingFailed,
+            Error::UnsupportedRecordFormat {
+                version: 1,
+                flags: 0,
+            },
+            Error::RequestSigningFailed,
+            Error::RequestAuthInvalid,
+            Error::MessageSerializationFailed("eof".to_string()),
+            Error::MessageDeserializationFailed("eof".to_string()),
+            Error::MessageTooLarge { size: 2, max: 1 },
+            Error::SnapshotSigningFailed,
+            Error::SnapshotInvalid,
+            Error::SnapshotRequesterMismatch {
+                requester: Box::new(address.clone()),
+            },
+            Error::ReceiptSigningFailed,
+            Error::ReceiptInvalid,
+            Error::ReceiptNotFound {
+                key: Box::new(address.clone()),
+                quote_id: XorName::default(),
+            },
+        ];
+
+        let codes: BTreeSet<_> = errors.iter().map(Error::code).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert!(Error::GetStoreCostFailed.is_retryable());
+        assert!(!Error::RecordParsingFailed.is_retryable());
+    }
+
+    /// The wire form of an error, as a peer of any version sends it.
+    #[derive(Serialize, Deserialize)]
+    enum SentError {
+        Coded(WireError),
+    }
+
+    #[test]
+    fn errors_should_be_sent_with_their_code() -> color_eyre::Result<()> {
+        let address = NetworkAddress::from_chunk_address(ChunkAddress::new(XorName::default()));
+        let error = Error::ChunkDoesNotExist(address);
+        let bytes = rmp_serde::to_vec(&error)?;
+        assert_eq!(rmp_serde::from_slice::<Error>(&bytes)?, error);
+
+        let SentError::Coded(wire) = rmp_serde::from_slice(&bytes)?;
+        assert_eq!(wire.version, ERROR_CODES_VERSION);
+        assert_eq!(wire.code, 200);
+        assert!(wire.retryable);
+        assert_eq!(wire.message, error.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn errors_of_unknown_codes_should_keep_their_code_and_retry_hint() -> color_eyre::Result<()> {
+        // as sent by a peer of a later version, with an error this version does not know
+        let bytes = rmp_serde::to_vec(&SentError::Coded(WireError {
+            version: ERROR_CODES_VERSION + 1,
+            code: 4242,
+            retryable: true,
+            message: "Node is busy".to_string(),
+            detail: Bytes::from_static(b"\x81\xa8NodeBusy\xc0"),
+        }))?;
+
+        let error: Error = rmp_serde::from_slice(&bytes)?;
+        assert_eq!(
+            error,
+            Error::Unknown {
+                code: 4242,
+                retryable: true,
+                message: "Node is busy".to_string(),
+            }
+        );
+        assert_eq!(error.code(), 4242);
+        assert!(error.is_retryable());
+        Ok(())
+    }
 }
@@ -9,6 +9,8 @@
 #[macro_use]
 extern crate tracing;
 
+/// Compact encoding of the network addresses.
+mod address_encoding;
 /// Errors.
 pub mod error;
 /// Messages types
@@ -28,6 +30,7 @@ pub mod version;
 pub mod safenode_proto {
     tonic::include_proto!("safenode_proto");
 }
+pub use address_encoding::NETWORK_ADDRESS_ENCODING_VERSION;
 pub use error::Error;
 use storage::ScratchpadAddress;
 
@@ -71,7 +74,11 @@ pub fn get_port_from_multiaddr(multi_addr: &Multiaddr) -> Option<u16> {
 /// to the key used in the Kademlia DHT.
 /// All our xorname calculations shall be replaced with the `KBucketKey` calculations,
 /// for getting proximity/distance to other items (whether nodes or data).
-#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+///
+/// It is deserialized from either its derived or its compact binary form, see
+/// [`NetworkAddress::to_compact_bytes`], and still serialized in the derived one unless the
+/// `compact-network-address` feature is enabled.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum NetworkAddress {
     /// The NetworkAddress is representing a PeerId.
     PeerId(Bytes),
@@ -7,52 +7,157 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 //! Data messages and their possible responses.
+//!
+//! The messages are exchanged between peers running different versions, so their schema can only
+//! evolve in a compatible way:
+//! - new variants are only ever added, existing ones are never renamed or removed
+//! - the fields of existing variants are never changed
+//! - a message of a kind unknown to a peer, e.g. one added in a later version, is read as
+//!   `Unknown` instead of failing the whole exchange
+//!
+//! Every version of the schema is covered by the fixtures under `tests/fixtures`, which all later
+//! versions must keep reading.
 mod chunk_proof;
 mod cmd;
 mod node_id;
+mod payment_receipt;
 mod query;
+mod record_snapshot;
 mod register;
+mod request_auth;
 mod response;
+pub(crate) mod schema;
 
 pub use self::{
     chunk_proof::{ChunkProof, Nonce},
     cmd::{Cmd, Hash},
     node_id::NodeId,
+    payment_receipt::{PaymentReceipt, SignedPaymentReceipt, MAX_PAYMENT_RECEIPTS_PER_QUERY},
     query::Query,
+    record_snapshot::{RecordSnapshot, SignedRecordSnapshot},
     register::RegisterCmd,
+    request_auth::{RequestAuth, RequestSigner},
     response::{CmdResponse, QueryResponse},
+    schema::UnknownMessage,
 };
 
-use super::NetworkAddress;
+use super::{
+    error::{Error, Result},
+    NetworkAddress,
+};
+
+use schema::impl_message_serde;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use serde::{Deserialize, Serialize};
+/// The maximum size of a serialized message, matching the limit of the request/response codec.
+pub const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
 
 /// A request to peers in the network
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum Request {
     /// A cmd sent to peers. Cmds are writes, i.e. can cause mutation.
     Cmd(Cmd),
     /// A query sent to peers. Queries are read-only.
     Query(Query),
+    /// A request signed by its sender, for the operations where the identity of the sender
+    /// matters.
+    Authenticated {
+        request: Box<Request>,
+        auth: RequestAuth,
+    },
+    /// A request this version does not know about, sent by a newer peer.
+    Unknown(UnknownMessage),
 }
 
+impl_message_serde!(Request, ["Cmd", "Query", "Authenticated"]);
+
 /// A response to peers in the network.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum Response {
     /// The response to a cmd.
     Cmd(CmdResponse),
     /// The response to a query.
     Query(QueryResponse),
+    /// The request was rejected before being handled, e.g. for an invalid signature.
+    Rejected(Error),
+    /// A response this version does not know about, sent by a newer peer.
+    Unknown(UnknownMessage),
 }
 
+impl_message_serde!(Response, ["Cmd", "Query", "Rejected"]);
+
 impl Request {
+    /// Serialize the request in its wire format, the inverse of [`Request::try_from_bytes`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        to_bytes(self)
+    }
+
+    /// Deserialize a request from untrusted bytes in its wire format, failing on oversized or
+    /// malformed input. The authentication of the request is not verified.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        try_from_bytes(bytes)
+    }
+
     /// Used to send a request to the close group of the address.
-    pub fn dst(&self) -> NetworkAddress {
+    ///
+    /// Unknown requests have no destination, they are never sent.
+    pub fn dst(&self) -> Option<NetworkAddress> {
         match self {
             Request::Cmd(cmd) => cmd.dst(),
             Request::Query(query) => query.dst(),
+            Request::Authenticated { request, .. } => request.dst(),
+            Request::Unknown(_) => None,
         }
     }
+
+    /// Sign the request with a client BLS key.
+    pub fn sign_with_bls(self, secret_key: &bls::SecretKey) -> Result<Self> {
+        let auth = RequestAuth::sign_with_bls(secret_key, &self)?;
+        Ok(Request::Authenticated {
+            request: Box::new(self),
+            auth,
+        })
+    }
+
+    /// Sign the request with an ed25519 keypair.
+    pub fn sign_with_ed25519(self, keypair: &libp2p::identity::Keypair) -> Result<Self> {
+        let auth = RequestAuth::sign_with_ed25519(keypair, &self)?;
+        Ok(Request::Authenticated {
+            request: Box::new(self),
+            auth,
+        })
+    }
+}
+
+impl Response {
+    /// Serialize the response in its wire format, the inverse of [`Response::try_from_bytes`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        to_bytes(self)
+    }
+
+    /// Deserialize a response from untrusted bytes in its wire format, failing on oversized or
+    /// malformed input.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        try_from_bytes(bytes)
+    }
+}
+
+fn to_bytes<T: Serialize>(message: &T) -> Result<Vec<u8>> {
+    cbor4ii::serde::to_vec(Vec::new(), message)
+        .map_err(|err| Error::MessageSerializationFailed(err.to_string()))
+}
+
+fn try_from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    if bytes.len() > MAX_MESSAGE_SIZE {
+        return Err(Error::MessageTooLarge {
+            size: bytes.len(),
+            max: MAX_MESSAGE_SIZE,
+        });
+    }
+    cbor4ii::serde::from_slice(bytes)
+        .map_err(|err| Error::MessageDeserializationFailed(err.to_string()))
 }
 
 impl std::fmt::Display for Response {
@@ -29,7 +29,7 @@ impl ChunkProof {
     }
 
     /// Serialize this `ChunkProof` instance to a hex string.
-    fn to_hex(&self) -> String {
+    pub fn to_hex(&self) -> String {
         hex::encode(self.0)
     }
 }
@@ -7,6 +7,7 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 #![allow(clippy::mutable_key_type)] // for Bytes in NetworkAddress
 
+use super::{schema::impl_message_serde, UnknownMessage};
 use crate::{storage::RecordType, NetworkAddress};
 use serde::{Deserialize, Serialize};
 // TODO: remove this dependency and define these types herein.
@@ -19,6 +20,7 @@ pub use sn_transfers::{Hash, PaymentQuote};
 ///
 /// [`protocol`]: crate
 #[derive(Eq, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum Cmd {
     /// Write operation to notify peer fetch a list of [`NetworkAddress`] from the holder.
     ///
@@ -40,8 +42,15 @@ pub enum Cmd {
         bad_peer: NetworkAddress,
         bad_behaviour: String,
     },
+    /// A cmd this version does not know about, sent by a newer peer.
+    Unknown(UnknownMessage),
 }
 
+impl_message_serde!(
+    Cmd,
+    ["Replicate", "QuoteVerification", "PeerConsideredAsBad"]
+);
+
 impl std::fmt::Debug for Cmd {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -68,17 +77,21 @@ impl std::fmt::Debug for Cmd {
                 .field("bad_peer", bad_peer)
                 .field("bad_behaviour", bad_behaviour)
                 .finish(),
+            Cmd::Unknown(_) => write!(f, "Cmd::Unknown"),
         }
     }
 }
 
 impl Cmd {
     /// Used to send a cmd to the close group of the address.
-    pub fn dst(&self) -> NetworkAddress {
+    ///
+    /// Unknown cmds have no destination, they are never sent.
+    pub fn dst(&self) -> Option<NetworkAddress> {
         match self {
-            Cmd::Replicate { holder, .. } => holder.clone(),
-            Cmd::QuoteVerification { target, .. } => target.clone(),
-            Cmd::PeerConsideredAsBad { bad_peer, .. } => bad_peer.clone(),
+            Cmd::Replicate { holder, .. } => Some(holder.clone()),
+            Cmd::QuoteVerification { target, .. } => Some(target.clone()),
+            Cmd::PeerConsideredAsBad { bad_peer, .. } => Some(bad_peer.clone()),
+            Cmd::Unknown(_) => None,
         }
     }
 }
@@ -110,6 +123,7 @@ impl std::fmt::Display for Cmd {
                     f,
                     "Cmd::PeerConsideredAsBad({detected_by:?} consider peer {bad_peer:?} as bad, due to {bad_behaviour:?})")
             }
+            Cmd::Unknown(_) => write!(f, "Cmd::Unknown"),
         }
     }
 }
@@ -0,0 +1,119 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::{Error, Result},
+    NetworkAddress,
+};
+use bytes::Bytes;
+use libp2p::{
+    identity::{KeyType, Keypair, PublicKey},
+    PeerId,
+};
+use serde::{Deserialize, Serialize};
+use sn_transfers::NanoTokens;
+use xor_name::XorName;
+
+/// The most receipts a node returns for a single [`GetPaymentReceipts`] query.
+///
+/// [`GetPaymentReceipts`]: super::Query::GetPaymentReceipts
+pub const MAX_PAYMENT_RECEIPTS_PER_QUERY: usize = 256;
+
+/// The acknowledgment by a node that it accepted a paid PUT of a record.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentReceipt {
+    /// The address of the record paid for.
+    pub record: NetworkAddress,
+    /// The amount the node was credited with, network royalties included.
+    pub amount: NanoTokens,
+    /// The id of the quote that was paid, see [`PaymentQuote::id`].
+    ///
+    /// [`PaymentQuote::id`]: sn_transfers::PaymentQuote::id
+    pub quote_id: XorName,
+}
+
+/// A [`PaymentReceipt`] signed by the node that was paid, as a proof of the payment it accepted.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, custom_debug::Debug)]
+pub struct SignedPaymentReceipt {
+    /// The ed25519 key of the node, in the protobuf encoding of libp2p.
+    #[debug(skip)]
+    signer: Vec<u8>,
+    /// The serialized receipt, as signed.
+    #[debug(skip)]
+    receipt: Bytes,
+    #[debug(skip)]
+    signature: Vec<u8>,
+}
+
+impl SignedPaymentReceipt {
+    /// Sign the receipt with the ed25519 keypair of a node.
+    pub fn sign(keypair: &Keypair, receipt: &PaymentReceipt) -> Result<Self> {
+        if keypair.key_type() != KeyType::Ed25519 {
+            return Err(Error::ReceiptSigningFailed);
+        }
+        let receipt = rmp_serde::to_vec(receipt).map_err(|_| Error::ReceiptSigningFailed)?;
+        let signature = keypair
+            .sign(&receipt)
+            .map_err(|_| Error::ReceiptSigningFailed)?;
+        Ok(Self {
+            signer: keypair.public().encode_protobuf(),
+            receipt: Bytes::from(receipt),
+            signature,
+        })
+    }
+
+    /// Verify the signature and return the receipt along with the node that signed it.
+    pub fn verify(&self) -> Result<(PeerId, PaymentReceipt)> {
+        let public_key =
+            PublicKey::try_decode_protobuf(&self.signer).map_err(|_| Error::ReceiptInvalid)?;
+        if public_key.key_type() != KeyType::Ed25519
+            || !public_key.verify(&self.receipt, &self.signature)
+        {
+            return Err(Error::ReceiptInvalid);
+        }
+        let receipt = rmp_serde::from_slice(&self.receipt).map_err(|_| Error::ReceiptInvalid)?;
+        Ok((public_key.to_peer_id(), receipt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::ChunkAddress;
+
+    #[test]
+    fn signed_receipts_should_only_verify_unchanged() -> Result<()> {
+        let keypair = Keypair::generate_ed25519();
+        let receipt = PaymentReceipt {
+            record: NetworkAddress::from_chunk_address(ChunkAddress::new(XorName([1; 32]))),
+            amount: NanoTokens::from(42),
+            quote_id: XorName([2; 32]),
+        };
+
+        let signed = SignedPaymentReceipt::sign(&keypair, &receipt)?;
+        assert_eq!(
+            signed.verify()?,
+            (keypair.public().to_peer_id(), receipt.clone())
+        );
+
+        let mut tampered = signed.clone();
+        tampered.receipt = Bytes::from(
+            rmp_serde::to_vec(&PaymentReceipt {
+                amount: NanoTokens::from(4200),
+                ..receipt
+            })
+            .map_err(|_| Error::ReceiptSigningFailed)?,
+        );
+        assert_eq!(tampered.verify(), Err(Error::ReceiptInvalid));
+
+        let mut resigned = signed;
+        resigned.signer = Keypair::generate_ed25519().public().encode_protobuf();
+        assert_eq!(resigned.verify(), Err(Error::ReceiptInvalid));
+        Ok(())
+    }
+}
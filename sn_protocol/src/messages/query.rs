@@ -6,8 +6,10 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::{schema::impl_message_serde, UnknownMessage};
 use crate::{messages::Nonce, NetworkAddress};
 use serde::{Deserialize, Serialize};
+use xor_name::XorName;
 
 /// Data queries - retrieving data and inspecting their structure.
 ///
@@ -16,6 +18,7 @@ use serde::{Deserialize, Serialize};
 ///
 /// [`protocol`]: crate
 #[derive(Eq, PartialEq, PartialOrd, Clone, Serialize, Deserialize, Debug)]
+#[serde(remote = "Self")]
 pub enum Query {
     /// Retrieve the cost of storing a record at the given address.
     GetStoreCost(NetworkAddress),
@@ -50,18 +53,77 @@ pub enum Query {
     },
     /// Queries close_group peers whether the target peer is a bad_node
     CheckNodeInProblem(NetworkAddress),
+    /// Retrieve a page of the records a nearby peer holds for the address range of the requester,
+    /// so that a joining node can start with them.
+    ///
+    /// This should eventually lead to a [`GetRecordSnapshot`] response.
+    ///
+    /// [`GetRecordSnapshot`]: super::QueryResponse::GetRecordSnapshot
+    GetRecordSnapshot {
+        /// Sender of the query
+        requester: NetworkAddress,
+        /// Where the page starts, as returned with the previous page, for peers that do not page
+        /// by key
+        start: u32,
+        /// The key of the last record of the previous page, the page starting right after it
+        #[serde(default)]
+        after: Option<NetworkAddress>,
+    },
+    /// Retrieve the receipt a node signed when it accepted a paid PUT of a record.
+    ///
+    /// This should eventually lead to a [`GetPaymentReceipt`] response.
+    ///
+    /// [`GetPaymentReceipt`]: super::QueryResponse::GetPaymentReceipt
+    GetPaymentReceipt {
+        /// Key of the record paid for
+        key: NetworkAddress,
+        /// The id of the quote that was paid
+        quote_id: XorName,
+    },
+    /// Retrieve the receipts a node signed for several paid PUTs at once, each by the key of the
+    /// record and the id of the quote that was paid, at most [`MAX_PAYMENT_RECEIPTS_PER_QUERY`].
+    ///
+    /// This should eventually lead to a [`GetPaymentReceipts`] response.
+    ///
+    /// [`MAX_PAYMENT_RECEIPTS_PER_QUERY`]: super::MAX_PAYMENT_RECEIPTS_PER_QUERY
+    /// [`GetPaymentReceipts`]: super::QueryResponse::GetPaymentReceipts
+    GetPaymentReceipts(Vec<(NetworkAddress, XorName)>),
+    /// A query this version does not know about, sent by a newer peer.
+    Unknown(UnknownMessage),
 }
 
+impl_message_serde!(
+    Query,
+    [
+        "GetStoreCost",
+        "GetReplicatedRecord",
+        "GetRegisterRecord",
+        "GetChunkExistenceProof",
+        "CheckNodeInProblem",
+        "GetRecordSnapshot",
+        "GetPaymentReceipt",
+        "GetPaymentReceipts",
+    ]
+);
+
 impl Query {
     /// Used to send a query to the close group of the address.
-    pub fn dst(&self) -> NetworkAddress {
+    ///
+    /// Unknown queries have no destination, they are never sent.
+    pub fn dst(&self) -> Option<NetworkAddress> {
         match self {
-            Query::GetStoreCost(address) | Query::CheckNodeInProblem(address) => address.clone(),
+            Query::GetStoreCost(address) | Query::CheckNodeInProblem(address) => {
+                Some(address.clone())
+            }
             // Shall not be called for this, as this is a `one-to-one` message,
             // and the destination shall be decided by the requester already.
             Query::GetReplicatedRecord { key, .. }
             | Query::GetRegisterRecord { key, .. }
-            | Query::GetChunkExistenceProof { key, .. } => key.clone(),
+            | Query::GetChunkExistenceProof { key, .. }
+            | Query::GetPaymentReceipt { key, .. } => Some(key.clone()),
+            Query::GetRecordSnapshot { requester, .. } => Some(requester.clone()),
+            Query::GetPaymentReceipts(receipts) => receipts.first().map(|(key, _)| key.clone()),
+            Query::Unknown(_) => None,
         }
     }
 }
@@ -84,6 +146,23 @@ impl std::fmt::Display for Query {
             Query::CheckNodeInProblem(address) => {
                 write!(f, "Query::CheckNodeInProblem({address:?})")
             }
+            Query::GetRecordSnapshot {
+                requester,
+                start,
+                after,
+            } => match after {
+                Some(after) => {
+                    write!(f, "Query::GetRecordSnapshot({requester:?} after {after:?})")
+                }
+                None => write!(f, "Query::GetRecordSnapshot({requester:?} from {start})"),
+            },
+            Query::GetPaymentReceipt { key, quote_id } => {
+                write!(f, "Query::GetPaymentReceipt({key:?} {quote_id:?})")
+            }
+            Query::GetPaymentReceipts(receipts) => {
+                write!(f, "Query::GetPaymentReceipts(len: {})", receipts.len())
+            }
+            Query::Unknown(_) => write!(f, "Query::Unknown"),
         }
     }
 }
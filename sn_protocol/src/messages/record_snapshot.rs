@@ -0,0 +1,131 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::{Error, Result},
+    NetworkAddress,
+};
+use bytes::Bytes;
+use libp2p::{
+    identity::{KeyType, Keypair, PublicKey},
+    PeerId,
+};
+use serde::{Deserialize, Serialize};
+
+/// A page of the records a node holds for the address range of a joining node, letting the
+/// latter start with them instead of fetching them one by one through replication.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, custom_debug::Debug)]
+pub struct RecordSnapshot {
+    /// The node the snapshot was taken for.
+    pub requester: NetworkAddress,
+    /// The keys and serialized values of the records.
+    #[debug(skip)]
+    pub records: Vec<(NetworkAddress, Bytes)>,
+    /// Where the next page starts, if there are more records.
+    ///
+    /// The index only makes sense to peers that page by index, the others query the page following
+    /// the key of the last record of this one.
+    pub next: Option<u32>,
+}
+
+/// A [`RecordSnapshot`] signed by the node that took it.
+///
+/// The signature only proves where the snapshot comes from, each of its records must still be
+/// validated before being stored.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, custom_debug::Debug)]
+pub struct SignedRecordSnapshot {
+    /// The ed25519 key of the node, in the protobuf encoding of libp2p.
+    #[debug(skip)]
+    signer: Vec<u8>,
+    /// The serialized snapshot, as signed.
+    #[debug(skip)]
+    snapshot: Bytes,
+    #[debug(skip)]
+    signature: Vec<u8>,
+}
+
+impl SignedRecordSnapshot {
+    /// Sign the snapshot with the ed25519 keypair of a node.
+    pub fn sign(keypair: &Keypair, snapshot: &RecordSnapshot) -> Result<Self> {
+        if keypair.key_type() != KeyType::Ed25519 {
+            return Err(Error::SnapshotSigningFailed);
+        }
+        let snapshot = rmp_serde::to_vec(snapshot).map_err(|_| Error::SnapshotSigningFailed)?;
+        let signature = keypair
+            .sign(&snapshot)
+            .map_err(|_| Error::SnapshotSigningFailed)?;
+        Ok(Self {
+            signer: keypair.public().encode_protobuf(),
+            snapshot: Bytes::from(snapshot),
+            signature,
+        })
+    }
+
+    /// Verify the signature and return the snapshot along with the node that signed it.
+    pub fn verify(&self) -> Result<(PeerId, RecordSnapshot)> {
+        let public_key =
+            PublicKey::try_decode_protobuf(&self.signer).map_err(|_| Error::SnapshotInvalid)?;
+        if public_key.key_type() != KeyType::Ed25519
+            || !public_key.verify(&self.snapshot, &self.signature)
+        {
+            return Err(Error::SnapshotInvalid);
+        }
+        let snapshot = rmp_serde::from_slice(&self.snapshot).map_err(|_| Error::SnapshotInvalid)?;
+        Ok((public_key.to_peer_id(), snapshot))
+    }
+
+    /// The size of the signed snapshot, roughly the size of its records.
+    pub fn len(&self) -> usize {
+        self.snapshot.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshot.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::ChunkAddress;
+    use xor_name::XorName;
+
+    #[test]
+    fn signed_snapshots_should_only_verify_unchanged() -> Result<()> {
+        let keypair = Keypair::generate_ed25519();
+        let snapshot = RecordSnapshot {
+            requester: NetworkAddress::from_peer(PeerId::random()),
+            records: vec![(
+                NetworkAddress::from_chunk_address(ChunkAddress::new(XorName([1; 32]))),
+                Bytes::from_static(b"record"),
+            )],
+            next: Some(1),
+        };
+
+        let signed = SignedRecordSnapshot::sign(&keypair, &snapshot)?;
+        assert_eq!(
+            signed.verify()?,
+            (keypair.public().to_peer_id(), snapshot.clone())
+        );
+
+        let mut tampered = signed.clone();
+        tampered.snapshot = Bytes::from(
+            rmp_serde::to_vec(&RecordSnapshot {
+                next: None,
+                ..snapshot
+            })
+            .map_err(|_| Error::SnapshotSigningFailed)?,
+        );
+        assert_eq!(tampered.verify(), Err(Error::SnapshotInvalid));
+
+        let mut resigned = signed;
+        resigned.signer = Keypair::generate_ed25519().public().encode_protobuf();
+        assert_eq!(resigned.verify(), Err(Error::SnapshotInvalid));
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::Request;
+use crate::error::{Error, Result};
+use libp2p::{
+    identity::{KeyType, Keypair, PublicKey},
+    PeerId,
+};
+use serde::{Deserialize, Serialize};
+
+/// The key a request has been signed with, i.e. the identity of its sender.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RequestSigner {
+    /// A client BLS key.
+    Bls(bls::PublicKey),
+    /// An ed25519 key, in the protobuf encoding of libp2p.
+    Ed25519(Vec<u8>),
+}
+
+impl RequestSigner {
+    /// The `PeerId` of an ed25519 signer, i.e. of a node or client signing with its libp2p
+    /// identity.
+    pub fn peer_id(&self) -> Option<PeerId> {
+        match self {
+            RequestSigner::Bls(_) => None,
+            RequestSigner::Ed25519(encoded_key) => PublicKey::try_decode_protobuf(encoded_key)
+                .ok()
+                .map(|public_key| public_key.to_peer_id()),
+        }
+    }
+}
+
+/// The signature of a request, proving the identity of its sender to the receiving node.
+///
+/// The signature only covers the request itself, it does not prevent the request from being
+/// replayed.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, custom_debug::Debug)]
+pub struct RequestAuth {
+    pub signer: RequestSigner,
+    #[debug(skip)]
+    signature: Vec<u8>,
+}
+
+impl RequestAuth {
+    /// Sign the request with a client BLS key.
+    pub fn sign_with_bls(secret_key: &bls::SecretKey, request: &Request) -> Result<Self> {
+        let signature = secret_key.sign(bytes_to_sign(request)?);
+        Ok(Self {
+            signer: RequestSigner::Bls(secret_key.public_key()),
+            signature: signature.to_bytes().to_vec(),
+        })
+    }
+
+    /// Sign the request with an ed25519 keypair.
+    pub fn sign_with_ed25519(keypair: &Keypair, request: &Request) -> Result<Self> {
+        if keypair.key_type() != KeyType::Ed25519 {
+            return Err(Error::RequestSigningFailed);
+        }
+        let signature = keypair
+            .sign(&bytes_to_sign(request)?)
+            .map_err(|_| Error::RequestSigningFailed)?;
+        Ok(Self {
+            signer: RequestSigner::Ed25519(keypair.public().encode_protobuf()),
+            signature,
+        })
+    }
+
+    /// Verify the signature is the signer's one over the request.
+    pub fn verify(&self, request: &Request) -> Result<()> {
+        let bytes = bytes_to_sign(request)?;
+        let is_valid = match &self.signer {
+            RequestSigner::Bls(public_key) => {
+                let signature_bytes: [u8; bls::SIG_SIZE] = self
+                    .signature
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| Error::RequestAuthInvalid)?;
+                let signature = bls::Signature::from_bytes(signature_bytes)
+                    .map_err(|_| Error::RequestAuthInvalid)?;
+                public_key.verify(&signature, bytes)
+            }
+            RequestSigner::Ed25519(encoded_key) => {
+                let public_key = PublicKey::try_decode_protobuf(encoded_key)
+                    .map_err(|_| Error::RequestAuthInvalid)?;
+                public_key.key_type() == KeyType::Ed25519
+                    && public_key.verify(&bytes, &self.signature)
+            }
+        };
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(Error::RequestAuthInvalid)
+        }
+    }
+}
+
+fn bytes_to_sign(request: &Request) -> Result<Vec<u8>> {
+    rmp_serde::to_vec(request).map_err(|_| Error::RequestSigningFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{messages::Query, NetworkAddress};
+
+    #[test]
+    fn signed_requests_should_only_verify_unchanged() -> Result<()> {
+        let request = Request::Query(Query::CheckNodeInProblem(NetworkAddress::from_peer(
+            PeerId::random(),
+        )));
+        let other_request = Request::Query(Query::CheckNodeInProblem(NetworkAddress::from_peer(
+            PeerId::random(),
+        )));
+
+        let auth = RequestAuth::sign_with_bls(&bls::SecretKey::random(), &request)?;
+        auth.verify(&request)?;
+        assert_eq!(auth.verify(&other_request), Err(Error::RequestAuthInvalid));
+
+        let keypair = Keypair::generate_ed25519();
+        let auth = RequestAuth::sign_with_ed25519(&keypair, &request)?;
+        auth.verify(&request)?;
+        assert_eq!(auth.verify(&other_request), Err(Error::RequestAuthInvalid));
+        assert_eq!(auth.signer.peer_id(), Some(keypair.public().to_peer_id()));
+
+        Ok(())
+    }
+}
@@ -8,7 +8,10 @@
 
 use crate::{error::Result, NetworkAddress};
 
-use super::ChunkProof;
+use super::{
+    schema::impl_message_serde, ChunkProof, SignedPaymentReceipt, SignedRecordSnapshot,
+    UnknownMessage,
+};
 use bytes::Bytes;
 use core::fmt;
 use serde::{Deserialize, Serialize};
@@ -17,6 +20,7 @@ use std::fmt::Debug;
 
 /// The response to a query, containing the query result.
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum QueryResponse {
     // ===== GetStoreCost =====
     //
@@ -57,8 +61,40 @@ pub enum QueryResponse {
     ///
     /// [`GetChunkExistenceProof`]: crate::messages::Query::GetChunkExistenceProof
     GetChunkExistenceProof(Result<ChunkProof>),
+    // ===== RecordSnapshot =====
+    //
+    /// Response to [`GetRecordSnapshot`]
+    ///
+    /// [`GetRecordSnapshot`]: crate::messages::Query::GetRecordSnapshot
+    GetRecordSnapshot(Result<SignedRecordSnapshot>),
+    // ===== PaymentReceipt =====
+    //
+    /// Response to [`GetPaymentReceipt`]
+    ///
+    /// [`GetPaymentReceipt`]: crate::messages::Query::GetPaymentReceipt
+    GetPaymentReceipt(Result<SignedPaymentReceipt>),
+    /// Response to [`GetPaymentReceipts`], a result per receipt asked for, in the same order
+    ///
+    /// [`GetPaymentReceipts`]: crate::messages::Query::GetPaymentReceipts
+    GetPaymentReceipts(Vec<Result<SignedPaymentReceipt>>),
+    /// A response this version does not know about, sent by a newer peer.
+    Unknown(UnknownMessage),
 }
 
+impl_message_serde!(
+    QueryResponse,
+    [
+        "GetStoreCost",
+        "CheckNodeInProblem",
+        "GetReplicatedRecord",
+        "GetRegisterRecord",
+        "GetChunkExistenceProof",
+        "GetRecordSnapshot",
+        "GetPaymentReceipt",
+        "GetPaymentReceipts",
+    ]
+);
+
 // Debug implementation for QueryResponse, to avoid printing Vec<u8>
 impl Debug for QueryResponse {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -112,12 +148,28 @@ impl Debug for QueryResponse {
             QueryResponse::GetChunkExistenceProof(proof) => {
                 write!(f, "GetChunkExistenceProof(proof: {proof:?})")
             }
+            QueryResponse::GetRecordSnapshot(result) => match result {
+                Ok(snapshot) => {
+                    write!(f, "GetRecordSnapshot(Ok(len: {:?}))", snapshot.len())
+                }
+                Err(err) => {
+                    write!(f, "GetRecordSnapshot(Err({err:?}))")
+                }
+            },
+            QueryResponse::GetPaymentReceipt(result) => {
+                write!(f, "GetPaymentReceipt({result:?})")
+            }
+            QueryResponse::GetPaymentReceipts(results) => {
+                write!(f, "GetPaymentReceipts(len: {})", results.len())
+            }
+            QueryResponse::Unknown(_) => write!(f, "Unknown"),
         }
     }
 }
 
 /// The response to a Cmd, containing the query result.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub enum CmdResponse {
     //
     // ===== Replication =====
@@ -134,4 +186,14 @@ pub enum CmdResponse {
     //
     /// Response to the considered as bad notification
     PeerConsideredAsBad(Result<()>),
+    //
+    // ===== Unknown =====
+    //
+    /// A response this version does not know about, sent by a newer peer.
+    Unknown(UnknownMessage),
 }
+
+impl_message_serde!(
+    CmdResponse,
+    ["Replicate", "QuoteVerification", "PeerConsideredAsBad"]
+);
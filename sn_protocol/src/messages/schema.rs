@@ -0,0 +1,162 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Reading the message variants unknown to this version.
+//!
+//! The message enums derive their serde implementations with `#[serde(remote = "Self")]`, which
+//! only generates them as inherent functions. Their trait implementations, generated by
+//! `impl_message_serde`, read the variant tag first: a known variant is then replayed to the
+//! derived implementation, while the value of an unknown one is skipped and read as `Unknown`.
+
+use serde::{
+    de::{
+        self, value::StringDeserializer, DeserializeSeed, EnumAccess, IgnoredAny, IntoDeserializer,
+        VariantAccess, Visitor,
+    },
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Stands for a message variant unknown to this version, sent by a newer peer.
+///
+/// Its content is skipped when it is read, it is only ever obtained by deserializing a message.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd)]
+pub struct UnknownMessage(());
+
+impl UnknownMessage {
+    pub(crate) fn new() -> Self {
+        UnknownMessage(())
+    }
+}
+
+impl Serialize for UnknownMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit()
+    }
+}
+
+impl<'de> Deserialize<'de> for UnknownMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        IgnoredAny::deserialize(deserializer)?;
+        Ok(UnknownMessage::new())
+    }
+}
+
+/// Skips the value of a variant unknown to this version.
+pub(crate) fn skip_unknown_variant<'de, V: VariantAccess<'de>>(
+    message: &str,
+    tag: &str,
+    variant: V,
+) -> Result<UnknownMessage, V::Error> {
+    debug!("Skipping the {message} variant {tag:?}, unknown to this version");
+    variant.newtype_variant::<IgnoredAny>()?;
+    Ok(UnknownMessage::new())
+}
+
+/// Implements `Serialize` and `Deserialize` for a message enum deriving them with
+/// `#[serde(remote = "Self")]` and having an `Unknown(UnknownMessage)` variant.
+///
+/// All the other variants must be listed.
+macro_rules! impl_message_serde {
+    ($message:ident, [$($variant:literal),+ $(,)?]) => {
+        impl serde::Serialize for $message {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                $message::serialize(self, serializer)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $message {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                const VARIANTS: &[&str] = &[$($variant),+];
+
+                struct MessageVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for MessageVisitor {
+                    type Value = $message;
+
+                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        formatter.write_str(concat!("a ", stringify!($message), " message"))
+                    }
+
+                    fn visit_enum<A: serde::de::EnumAccess<'de>>(
+                        self,
+                        data: A,
+                    ) -> std::result::Result<Self::Value, A::Error> {
+                        let (tag, variant) = data.variant::<String>()?;
+                        if VARIANTS.contains(&tag.as_str()) {
+                            $message::deserialize($crate::messages::schema::KnownVariant::new(
+                                tag, variant,
+                            ))
+                        } else {
+                            $crate::messages::schema::skip_unknown_variant(
+                                stringify!($message),
+                                &tag,
+                                variant,
+                            )
+                            .map($message::Unknown)
+                        }
+                    }
+                }
+
+                deserializer.deserialize_enum(stringify!($message), VARIANTS, MessageVisitor)
+            }
+        }
+    };
+}
+pub(crate) use impl_message_serde;
+
+/// A variant whose tag has already been read, replayed to the derived deserialization.
+pub(crate) struct KnownVariant<V> {
+    tag: String,
+    variant: V,
+}
+
+impl<V> KnownVariant<V> {
+    pub(crate) fn new(tag: String, variant: V) -> Self {
+        Self { tag, variant }
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> Deserializer<'de> for KnownVariant<A> {
+    type Error = A::Error;
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, A::Error> {
+        Err(de::Error::custom(
+            "a message variant can only be read as an enum",
+        ))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> EnumAccess<'de> for KnownVariant<A> {
+    type Error = A::Error;
+    type Variant = A;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, A), A::Error> {
+        let tag: StringDeserializer<A::Error> = self.tag.into_deserializer();
+        Ok((seed.deserialize(tag)?, self.variant))
+    }
+}
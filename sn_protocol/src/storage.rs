@@ -13,13 +13,17 @@ mod scratchpad;
 
 use crate::error::Error;
 use core::fmt;
+use serde::{Deserialize, Serialize};
 use std::{str::FromStr, time::Duration};
 
 pub use self::{
     address::{ChunkAddress, RegisterAddress, ScratchpadAddress, SpendAddress},
     chunks::Chunk,
-    header::{try_deserialize_record, try_serialize_record, RecordHeader, RecordKind, RecordType},
-    scratchpad::Scratchpad,
+    header::{
+        try_deserialize_record, try_serialize_record, try_serialize_record_with_header,
+        RecordFlags, RecordHeader, RecordKind, RecordType, RECORD_FORMAT_VERSION,
+    },
+    scratchpad::{Scratchpad, MAX_SCRATCHPAD_SIZE},
 };
 
 /// Represents the strategy for retrying operations. This encapsulates both the duration it may take for an operation to
@@ -73,3 +77,37 @@ impl fmt::Display for RetryStrategy {
         write!(f, "{self:?}")
     }
 }
+
+/// Represents the strategy for choosing which of the close group nodes to pay, when they quote
+/// different costs to store a record.
+///
+/// The quotes considered are those of the majority of the close group closest to the record.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaymentStrategy {
+    /// CheapestMajority: Pays the node quoting the lowest cost.
+    #[default]
+    CheapestMajority,
+    /// Median: Pays the node quoting the median cost, avoiding the outliers on either side.
+    Median,
+    /// All: Pays every one of the nodes, each with its own quote.
+    All,
+}
+
+impl FromStr for PaymentStrategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cheapest" | "cheapestmajority" => Ok(PaymentStrategy::CheapestMajority),
+            "median" => Ok(PaymentStrategy::Median),
+            "all" => Ok(PaymentStrategy::All),
+            _ => Err(Error::ParsePaymentStrategyError),
+        }
+    }
+}
+
+impl fmt::Display for PaymentStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
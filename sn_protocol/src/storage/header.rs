@@ -25,9 +25,57 @@ pub enum RecordType {
     NonChunk(XorName),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The format version of the record content written by this version.
+///
+/// Records of a later version, or with flags this version cannot handle, are refused rather than
+/// read as if they were of the current format.
+pub const RECORD_FORMAT_VERSION: u8 = 0;
+
+/// The header prefixing every record value, describing how its content is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RecordHeader {
     pub kind: RecordKind,
+    /// The format version of the content.
+    pub version: u8,
+    pub flags: RecordFlags,
+}
+
+/// Flags describing the encoding of the record content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RecordFlags(u8);
+
+impl RecordFlags {
+    /// The content is compressed.
+    pub const COMPRESSED: Self = Self(0b01);
+    /// The content is encrypted, it is only readable by its owner.
+    pub const ENCRYPTED: Self = Self(0b10);
+
+    /// The flags this version can read the content of.
+    const SUPPORTED: Self = Self::ENCRYPTED;
+
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn with(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
@@ -41,20 +89,42 @@ pub enum RecordKind {
     ScratchpadWithPayment,
 }
 
+impl RecordKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::ChunkWithPayment => 0,
+            Self::Chunk => 1,
+            Self::Spend => 2,
+            Self::Register => 3,
+            Self::RegisterWithPayment => 4,
+            Self::Scratchpad => 5,
+            Self::ScratchpadWithPayment => 6,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, Error> {
+        match byte {
+            0 => Ok(Self::ChunkWithPayment),
+            1 => Ok(Self::Chunk),
+            2 => Ok(Self::Spend),
+            3 => Ok(Self::Register),
+            4 => Ok(Self::RegisterWithPayment),
+            5 => Ok(Self::Scratchpad),
+            6 => Ok(Self::ScratchpadWithPayment),
+            _ => {
+                error!("Unexpected byte {byte} for RecordKind variant");
+                Err(Error::RecordHeaderParsingFailed)
+            }
+        }
+    }
+}
+
 impl Serialize for RecordKind {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        match *self {
-            Self::ChunkWithPayment => serializer.serialize_u32(0),
-            Self::Chunk => serializer.serialize_u32(1),
-            Self::Spend => serializer.serialize_u32(2),
-            Self::Register => serializer.serialize_u32(3),
-            Self::RegisterWithPayment => serializer.serialize_u32(4),
-            Self::Scratchpad => serializer.serialize_u32(5),
-            Self::ScratchpadWithPayment => serializer.serialize_u32(6),
-        }
+        serializer.serialize_u32(self.to_byte() as u32)
     }
 }
 
@@ -64,18 +134,10 @@ impl<'de> Deserialize<'de> for RecordKind {
         D: serde::Deserializer<'de>,
     {
         let num = u32::deserialize(deserializer)?;
-        match num {
-            0 => Ok(Self::ChunkWithPayment),
-            1 => Ok(Self::Chunk),
-            2 => Ok(Self::Spend),
-            3 => Ok(Self::Register),
-            4 => Ok(Self::RegisterWithPayment),
-            5 => Ok(Self::Scratchpad),
-            6 => Ok(Self::ScratchpadWithPayment),
-            _ => Err(serde::de::Error::custom(
-                "Unexpected integer for RecordKind variant",
-            )),
-        }
+        u8::try_from(num)
+            .ok()
+            .and_then(|byte| Self::from_byte(byte).ok())
+            .ok_or_else(|| serde::de::Error::custom("Unexpected integer for RecordKind variant"))
     }
 }
 impl Display for RecordKind {
@@ -84,49 +146,112 @@ impl Display for RecordKind {
     }
 }
 
+// The header is encoded as a MessagePack array: the original headers only held the kind, as
+// `[0x91, kind]`, which is still written for the current format so that the records are unchanged.
+// Any other version or flags are written in the extended form, `[0x93, kind, version, flags]`.
+const BASIC_HEADER_MARKER: u8 = 0x91;
+const EXTENDED_HEADER_MARKER: u8 = 0x93;
+
 impl RecordHeader {
+    /// The size of the header of the records of the current format.
     pub const SIZE: usize = 2;
+    /// The size of the header of the records with a later version or any flags.
+    pub const EXTENDED_SIZE: usize = 4;
 
-    pub fn try_serialize(self) -> Result<BytesMut, Error> {
-        let bytes = BytesMut::new();
-        let mut buf = bytes.writer();
+    /// The header of a record of the current format, without any flags.
+    pub fn new(kind: RecordKind) -> Self {
+        Self {
+            kind,
+            version: RECORD_FORMAT_VERSION,
+            flags: RecordFlags::empty(),
+        }
+    }
 
-        self.serialize(&mut Serializer::new(&mut buf))
-            .map_err(|err| {
-                error!("Failed to serialized RecordHeader {self:?} with error: {err:?}");
-                Error::RecordHeaderParsingFailed
-            })?;
+    /// The number of bytes the header is encoded in.
+    pub fn encoded_len(&self) -> usize {
+        if self.is_basic() {
+            Self::SIZE
+        } else {
+            Self::EXTENDED_SIZE
+        }
+    }
 
-        let b = buf.into_inner();
+    fn is_basic(&self) -> bool {
+        self.version == RECORD_FORMAT_VERSION && self.flags.is_empty()
+    }
 
-        Ok(b)
+    pub fn try_serialize(self) -> Result<BytesMut, Error> {
+        let mut bytes = BytesMut::with_capacity(self.encoded_len());
+        if self.is_basic() {
+            bytes.put_u8(BASIC_HEADER_MARKER);
+            bytes.put_u8(self.kind.to_byte());
+        } else {
+            bytes.put_u8(EXTENDED_HEADER_MARKER);
+            bytes.put_u8(self.kind.to_byte());
+            // MessagePack positive fixints, which only go up to 0x7f
+            if self.version > 0x7f || self.flags.bits() > 0x7f {
+                error!("Failed to serialize RecordHeader {self:?}, its version or flags are out of range");
+                return Err(Error::RecordHeaderParsingFailed);
+            }
+            bytes.put_u8(self.version);
+            bytes.put_u8(self.flags.bits());
+        }
+        Ok(bytes)
     }
 
+    /// Read the header at the start of `bytes`, any bytes after it are ignored.
     pub fn try_deserialize(bytes: &[u8]) -> Result<Self, Error> {
-        rmp_serde::from_slice(bytes).map_err(|err| {
-            error!("Failed to deserialize RecordHeader with error: {err:?}");
-            Error::RecordHeaderParsingFailed
-        })
+        let header = match bytes {
+            [BASIC_HEADER_MARKER, kind, ..] => Self::new(RecordKind::from_byte(*kind)?),
+            [EXTENDED_HEADER_MARKER, kind, version, flags, ..]
+                if *version <= 0x7f && *flags <= 0x7f =>
+            {
+                Self {
+                    kind: RecordKind::from_byte(*kind)?,
+                    version: *version,
+                    flags: RecordFlags::from_bits(*flags),
+                }
+            }
+            _ => {
+                error!(
+                    "Failed to deserialize RecordHeader from {} bytes",
+                    bytes.len()
+                );
+                return Err(Error::RecordHeaderParsingFailed);
+            }
+        };
+        Ok(header)
     }
 
     pub fn from_record(record: &Record) -> Result<Self, Error> {
-        if record.value.len() < RecordHeader::SIZE + 1 {
-            return Err(Error::RecordHeaderParsingFailed);
-        }
-        Self::try_deserialize(&record.value[..RecordHeader::SIZE + 1])
+        Self::try_deserialize(&record.value)
     }
 
     pub fn is_record_of_type_chunk(record: &Record) -> Result<bool, Error> {
         let kind = Self::from_record(record)?.kind;
         Ok(kind == RecordKind::Chunk)
     }
+
+    /// Check this version can read the content of the record.
+    pub fn ensure_supported(&self) -> Result<(), Error> {
+        let unsupported_flags = self.flags.bits() & !RecordFlags::SUPPORTED.bits();
+        if self.version > RECORD_FORMAT_VERSION || unsupported_flags != 0 {
+            return Err(Error::UnsupportedRecordFormat {
+                version: self.version,
+                flags: self.flags.bits(),
+            });
+        }
+        Ok(())
+    }
 }
 
 /// Utility to deserialize a `KAD::Record` into any type.
 /// Use `RecordHeader::from_record` if you want the `RecordHeader` instead.
 pub fn try_deserialize_record<T: serde::de::DeserializeOwned>(record: &Record) -> Result<T, Error> {
-    let bytes = if record.value.len() > RecordHeader::SIZE {
-        &record.value[RecordHeader::SIZE..]
+    let header = RecordHeader::from_record(record)?;
+    header.ensure_supported()?;
+    let bytes = if record.value.len() > header.encoded_len() {
+        &record.value[header.encoded_len()..]
     } else {
         return Err(Error::RecordParsingFailed);
     };
@@ -145,7 +270,15 @@ pub fn try_serialize_record<T: serde::Serialize>(
     data: &T,
     record_kind: RecordKind,
 ) -> Result<Bytes, Error> {
-    let mut buf = RecordHeader { kind: record_kind }.try_serialize()?.writer();
+    try_serialize_record_with_header(data, RecordHeader::new(record_kind))
+}
+
+/// Utility to serialize the provided data along with a custom header, e.g. one with flags.
+pub fn try_serialize_record_with_header<T: serde::Serialize>(
+    data: &T,
+    header: RecordHeader,
+) -> Result<Bytes, Error> {
+    let mut buf = header.try_serialize()?.writer();
     data.serialize(&mut Serializer::new(&mut buf))
         .map_err(|err| {
             error!("Failed to serialized Records with error: {err:?}");
@@ -157,53 +290,101 @@ pub fn try_serialize_record<T: serde::Serialize>(
 
 #[cfg(test)]
 mod tests {
-    use super::{RecordHeader, RecordKind};
-    use crate::error::Result;
+    use super::{
+        try_deserialize_record, try_serialize_record_with_header, RecordFlags, RecordHeader,
+        RecordKind, RECORD_FORMAT_VERSION,
+    };
+    use crate::error::{Error, Result};
+    use libp2p::kad::{Record, RecordKey};
 
     #[test]
     fn verify_record_header_encoded_size() -> Result<()> {
-        let chunk_with_payment = RecordHeader {
-            kind: RecordKind::ChunkWithPayment,
-        }
-        .try_serialize()?;
+        let chunk_with_payment = RecordHeader::new(RecordKind::ChunkWithPayment).try_serialize()?;
         assert_eq!(chunk_with_payment.len(), RecordHeader::SIZE);
 
-        let reg_with_payment = RecordHeader {
-            kind: RecordKind::RegisterWithPayment,
-        }
-        .try_serialize()?;
+        let reg_with_payment =
+            RecordHeader::new(RecordKind::RegisterWithPayment).try_serialize()?;
         assert_eq!(reg_with_payment.len(), RecordHeader::SIZE);
 
-        let chunk = RecordHeader {
-            kind: RecordKind::Chunk,
-        }
-        .try_serialize()?;
+        let chunk = RecordHeader::new(RecordKind::Chunk).try_serialize()?;
         assert_eq!(chunk.len(), RecordHeader::SIZE);
 
-        let spend = RecordHeader {
-            kind: RecordKind::Spend,
-        }
-        .try_serialize()?;
+        let spend = RecordHeader::new(RecordKind::Spend).try_serialize()?;
         assert_eq!(spend.len(), RecordHeader::SIZE);
 
-        let register = RecordHeader {
-            kind: RecordKind::Register,
-        }
-        .try_serialize()?;
+        let register = RecordHeader::new(RecordKind::Register).try_serialize()?;
         assert_eq!(register.len(), RecordHeader::SIZE);
 
-        let scratchpad = RecordHeader {
-            kind: RecordKind::Scratchpad,
-        }
-        .try_serialize()?;
+        let scratchpad = RecordHeader::new(RecordKind::Scratchpad).try_serialize()?;
         assert_eq!(scratchpad.len(), RecordHeader::SIZE);
 
-        let scratchpad_with_payment = RecordHeader {
-            kind: RecordKind::ScratchpadWithPayment,
-        }
-        .try_serialize()?;
+        let scratchpad_with_payment =
+            RecordHeader::new(RecordKind::ScratchpadWithPayment).try_serialize()?;
         assert_eq!(scratchpad_with_payment.len(), RecordHeader::SIZE);
 
         Ok(())
     }
+
+    #[test]
+    fn record_header_should_keep_the_original_encoding() -> Result<()> {
+        // as written by the versions before the header had a version and flags
+        let legacy = rmp_serde::to_vec(&(RecordKind::Spend,)).expect("header to be serialized");
+        assert_eq!(
+            legacy,
+            RecordHeader::new(RecordKind::Spend).try_serialize()?
+        );
+        assert_eq!(
+            RecordHeader::try_deserialize(&legacy)?,
+            RecordHeader::new(RecordKind::Spend)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extended_record_header_should_round_trip() -> Result<()> {
+        let header = RecordHeader {
+            kind: RecordKind::Scratchpad,
+            version: RECORD_FORMAT_VERSION,
+            flags: RecordFlags::ENCRYPTED,
+        };
+        let bytes = header.try_serialize()?;
+        assert_eq!(bytes.len(), RecordHeader::EXTENDED_SIZE);
+        assert_eq!(RecordHeader::try_deserialize(&bytes)?, header);
+
+        let record = Record::new(
+            RecordKey::new(&[1]),
+            try_serialize_record_with_header(&"content", header)?.to_vec(),
+        );
+        assert_eq!(try_deserialize_record::<String>(&record)?, "content");
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_record_format_should_be_refused() -> Result<()> {
+        for header in [
+            RecordHeader {
+                kind: RecordKind::Chunk,
+                version: RECORD_FORMAT_VERSION + 1,
+                flags: RecordFlags::empty(),
+            },
+            RecordHeader {
+                kind: RecordKind::Chunk,
+                version: RECORD_FORMAT_VERSION,
+                flags: RecordFlags::COMPRESSED,
+            },
+        ] {
+            let record = Record::new(
+                RecordKey::new(&[1]),
+                try_serialize_record_with_header(&"content", header)?.to_vec(),
+            );
+            assert_eq!(
+                try_deserialize_record::<String>(&record),
+                Err(Error::UnsupportedRecordFormat {
+                    version: header.version,
+                    flags: header.flags.bits(),
+                })
+            );
+        }
+        Ok(())
+    }
 }
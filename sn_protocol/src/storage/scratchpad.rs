@@ -7,13 +7,19 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::ScratchpadAddress;
-use crate::NetworkAddress;
-use bls::{PublicKey, Signature};
+use crate::{
+    error::{Error, Result},
+    NetworkAddress,
+};
+use bls::{Ciphertext, PublicKey, SecretKey, Signature};
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 
 use xor_name::XorName;
 
+/// The maximum size of the encrypted data held by a `Scratchpad`.
+pub const MAX_SCRATCHPAD_SIZE: usize = 4 * 1024 * 1024;
+
 /// Scratchpad, an mutable address for encrypted data
 #[derive(
     Hash, Eq, PartialEq, PartialOrd, Ord, Clone, custom_debug::Debug, Serialize, Deserialize,
@@ -47,15 +53,39 @@ impl Scratchpad {
         }
     }
 
+    /// Creates a `Scratchpad` holding `data` encrypted to, and signed by, the `owner`.
+    pub fn new_signed(owner: &SecretKey, data: &[u8], counter: u64) -> Self {
+        let encrypted_data = Bytes::from(owner.public_key().encrypt(data).to_bytes());
+        let signature = owner.sign(Self::bytes_for_signature(counter, &encrypted_data));
+        Self::new(owner.public_key(), encrypted_data, counter, signature)
+    }
+
+    /// The bytes the owner signs: the counter followed by the hash of the encrypted data.
+    pub fn bytes_for_signature(counter: u64, encrypted_data: &[u8]) -> Vec<u8> {
+        let mut signing_bytes = counter.to_be_bytes().to_vec();
+        signing_bytes.extend(XorName::from_content(encrypted_data).to_vec());
+        signing_bytes
+    }
+
     /// Verifies the signature and content of the scratchpad are valid for the
     /// owner's public key.
     pub fn is_valid(&self) -> bool {
-        let mut signing_bytes = self.counter.to_be_bytes().to_vec();
-        signing_bytes.extend(self.encrypted_data_hash().to_vec()); // add the count
-
+        let signing_bytes = Self::bytes_for_signature(self.counter, &self.encrypted_data);
         self.owner().verify(&self.signature, &signing_bytes)
     }
 
+    /// Decrypts the data with the owner's secret key.
+    ///
+    /// The key is not authenticated, decrypting with another key gives meaningless data.
+    pub fn decrypt_data(&self, owner: &SecretKey) -> Result<Bytes> {
+        let cipher_text = Ciphertext::from_bytes(&self.encrypted_data)
+            .map_err(|_| Error::ScratchpadCipherTextInvalid)?;
+        let data = owner
+            .decrypt(&cipher_text)
+            .ok_or(Error::ScratchpadCipherTextFailedToDecrypt)?;
+        Ok(Bytes::from(data))
+    }
+
     /// Returns the encrypted_data.
     pub fn encrypted_data(&self) -> &Bytes {
         &self.encrypted_data
@@ -91,3 +121,28 @@ impl Scratchpad {
         self.encrypted_data.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_scratchpad_should_be_valid_and_decryptable() -> Result<()> {
+        let owner = SecretKey::random();
+        let scratchpad = Scratchpad::new_signed(&owner, b"app state head", 3);
+
+        assert!(scratchpad.is_valid());
+        assert_eq!(scratchpad.owner(), &owner.public_key());
+        assert_eq!(
+            scratchpad.decrypt_data(&owner)?,
+            Bytes::from_static(b"app state head")
+        );
+
+        // bumping the counter without re-signing invalidates it
+        let mut replayed = scratchpad.clone();
+        replayed.counter += 1;
+        assert!(!replayed.is_valid());
+
+        Ok(())
+    }
+}
@@ -6,8 +6,14 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::error::{Error, Result};
 use lazy_static::lazy_static;
 use sn_transfers::{FOUNDATION_PK, GENESIS_PK, NETWORK_ROYALTIES_PK, PAYMENT_FORWARD_PK};
+use std::sync::OnceLock;
+
+/// The number of minor protocol versions, behind or ahead of ours, that peers are still accepted
+/// from. Accepting the previous version allows the nodes of a network to be upgraded one by one.
+pub const PROTOCOL_COMPATIBILITY_WINDOW: u64 = 1;
 
 lazy_static! {
     /// The node version used during Identify Behaviour.
@@ -45,6 +51,102 @@ lazy_static! {
             get_truncate_version_str(),
             get_key_version_str(),
         );
+
+    /// The req/response protocol versions that are supported, ours first followed by the older
+    /// ones within the `PROTOCOL_COMPATIBILITY_WINDOW`.
+    pub static ref REQ_RESPONSE_COMPATIBLE_VERSION_STRS: Vec<String> = {
+        let (major, minor) = get_major_minor_version();
+        (0..=PROTOCOL_COMPATIBILITY_WINDOW.min(minor))
+            .map(|behind| {
+                format!(
+                    "/safe{}/node/{major}.{}/{}",
+                    write_network_version_with_slash(),
+                    minor - behind,
+                    get_key_version_str(),
+                )
+            })
+            .collect()
+    };
+}
+
+/// The components of an identify protocol version string, e.g. `safe/0.17/a1b2c3_d4e5f6_..`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolVersion {
+    /// The network version, empty if no network versioning is applied.
+    pub network_version: String,
+    pub major: u64,
+    pub minor: u64,
+    /// Identifies the genesis and network keys in use.
+    pub key_version: String,
+}
+
+impl ProtocolVersion {
+    /// The protocol version we are running.
+    pub fn ours() -> Self {
+        let (major, minor) = get_major_minor_version();
+        Self {
+            network_version: get_network_version().to_string(),
+            major,
+            minor,
+            key_version: get_key_version_str(),
+        }
+    }
+
+    /// Parses an identify protocol version string, as advertised by a peer.
+    pub fn parse(protocol_str: &str) -> Option<Self> {
+        let rest = protocol_str.strip_prefix("safe")?;
+        // the network version can contain slashes, so it is whatever remains after the last two parts
+        let mut parts = rest.rsplitn(3, '/');
+        let key_version = parts.next()?;
+        let (major, minor) = parts.next()?.split_once('.')?;
+        let network_version = match parts.next()? {
+            "" => "",
+            network_version => network_version.strip_prefix('/')?,
+        };
+
+        Some(Self {
+            network_version: network_version.to_string(),
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+            key_version: key_version.to_string(),
+        })
+    }
+
+    /// Whether a peer running `other` can be talked to. The network and the keys have to be the
+    /// same, and the versions have to be within the `PROTOCOL_COMPATIBILITY_WINDOW` of each other.
+    pub fn is_compatible_with(&self, other: &Self) -> bool {
+        self.network_version == other.network_version
+            && self.key_version == other.key_version
+            && self.major == other.major
+            && self.minor.abs_diff(other.minor) <= PROTOCOL_COMPATIBILITY_WINDOW
+    }
+}
+
+/// Checks the identify protocol version advertised by a peer against ours.
+pub fn check_protocol_compatibility(their_protocol: &str) -> Result<()> {
+    let is_compatible = their_protocol == IDENTIFY_PROTOCOL_STR.as_str()
+        || ProtocolVersion::parse(their_protocol)
+            .is_some_and(|theirs| ProtocolVersion::ours().is_compatible_with(&theirs));
+    if is_compatible {
+        Ok(())
+    } else {
+        Err(Error::IncompatibleProtocol {
+            ours: IDENTIFY_PROTOCOL_STR.to_string(),
+            theirs: their_protocol.to_string(),
+        })
+    }
+}
+
+/// The network version, set by `set_network_version` or else the one built with.
+static NETWORK_VERSION: OnceLock<String> = OnceLock::new();
+
+/// Use the given network version instead of the one built with, e.g. the one of the network a
+/// client is run against. It has to be set before the version is first read, which it is then
+/// for the rest of the process: `false` is returned, leaving the version as it was, otherwise.
+pub fn set_network_version(version: &str) -> bool {
+    NETWORK_VERSION
+        .set(network_version_of_mode(version))
+        .is_ok()
 }
 
 /// Get the network version string.
@@ -54,15 +156,18 @@ lazy_static! {
 pub fn get_network_version() -> &'static str {
     // Set this env variable to provide custom network versioning. If it is set to 'restricted', then the git branch name
     // is used as the version string. Else we directly use the passed in string as the version.
-    match option_env!("NETWORK_VERSION_MODE") {
-        Some(value) => {
-            if value == "restricted" {
-                sn_build_info::git_branch()
-            } else {
-                value
-            }
-        }
-        _ => "",
+    NETWORK_VERSION.get_or_init(|| {
+        option_env!("NETWORK_VERSION_MODE")
+            .map(network_version_of_mode)
+            .unwrap_or_default()
+    })
+}
+
+fn network_version_of_mode(mode: &str) -> String {
+    if mode == "restricted" {
+        sn_build_info::git_branch().to_string()
+    } else {
+        mode.to_string()
     }
 }
 
@@ -79,12 +184,19 @@ fn write_network_version_with_slash() -> String {
 // Protocol support shall be downward compatible for patch only version update.
 // i.e. versions of `A.B.X` or `A.B.X-alpha.Y` shall be considered as a same protocol of `A.B`
 fn get_truncate_version_str() -> String {
+    let (major, minor) = get_major_minor_version();
+    format!("{major}.{minor}")
+}
+
+fn get_major_minor_version() -> (u64, u64) {
     let version_str = env!("CARGO_PKG_VERSION");
     let parts = version_str.split('.').collect::<Vec<_>>();
-    if parts.len() >= 2 {
-        format!("{}.{}", parts[0], parts[1])
-    } else {
-        panic!("Cannot obtain truncated version str for {version_str:?}: {parts:?}");
+    match (
+        parts.first().and_then(|major| major.parse().ok()),
+        parts.get(1).and_then(|minor| minor.parse().ok()),
+    ) {
+        (Some(major), Some(minor)) => (major, minor),
+        _ => panic!("Cannot obtain truncated version str for {version_str:?}: {parts:?}"),
     }
 }
 
@@ -102,3 +214,63 @@ fn get_key_version_str() -> String {
     let _ = p_k_str.split_off(6);
     format!("{f_k_str}_{g_k_str}_{n_k_str}_{p_k_str}")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn our_protocol_version_should_be_parsed() {
+        assert_eq!(
+            ProtocolVersion::parse(&IDENTIFY_PROTOCOL_STR),
+            Some(ProtocolVersion::ours())
+        );
+        assert_eq!(
+            ProtocolVersion::parse("safe/some/branch/0.17/abc_def"),
+            Some(ProtocolVersion {
+                network_version: "some/branch".to_string(),
+                major: 0,
+                minor: 17,
+                key_version: "abc_def".to_string(),
+            })
+        );
+        assert_eq!(ProtocolVersion::parse("safe/0.17"), None);
+        assert_eq!(ProtocolVersion::parse("ipfs/0.1.0"), None);
+    }
+
+    #[test]
+    fn only_versions_within_the_window_should_be_compatible() {
+        let ours = ProtocolVersion::ours();
+        let with_minor = |minor| ProtocolVersion {
+            minor,
+            ..ours.clone()
+        };
+
+        assert!(check_protocol_compatibility(&IDENTIFY_PROTOCOL_STR).is_ok());
+        assert!(ours.is_compatible_with(&with_minor(ours.minor + PROTOCOL_COMPATIBILITY_WINDOW)));
+        assert!(
+            !ours.is_compatible_with(&with_minor(ours.minor + PROTOCOL_COMPATIBILITY_WINDOW + 1))
+        );
+        assert!(!ours.is_compatible_with(&ProtocolVersion {
+            key_version: "other_keys".to_string(),
+            ..ours.clone()
+        }));
+        assert!(!ours.is_compatible_with(&ProtocolVersion {
+            network_version: "other_network".to_string(),
+            ..ours.clone()
+        }));
+
+        match check_protocol_compatibility("safe/not_a_version") {
+            Err(Error::IncompatibleProtocol { ours, theirs }) => {
+                assert_eq!(ours, *IDENTIFY_PROTOCOL_STR);
+                assert_eq!(theirs, "safe/not_a_version");
+            }
+            result => panic!("Unexpected result {result:?}"),
+        }
+
+        assert_eq!(
+            REQ_RESPONSE_COMPATIBLE_VERSION_STRS[0],
+            *REQ_RESPONSE_VERSION_STR
+        );
+    }
+}
@@ -0,0 +1,447 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Checks the messages serialized by every version of the schema can still be read.
+//!
+//! The fixtures are the CBOR encoding of the messages, as sent over the wire, in hex. When adding
+//! messages to the schema, add their fixtures to a new version directory, written by running
+//! these tests with `SN_WRITE_MESSAGE_FIXTURES=<version>`. Existing fixtures are never modified.
+//!
+//! Some fixtures hold payloads signed over addresses in their derived form, so they are not checked
+//! with the `compact-network-address` feature, which is a protocol major version of its own.
+#![cfg(not(feature = "compact-network-address"))]
+
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use sn_protocol::{
+    error::Error as ProtocolError,
+    messages::{
+        ChunkProof, Cmd, CmdResponse, PaymentReceipt, Query, QueryResponse, RecordSnapshot,
+        Request, Response, SignedPaymentReceipt, SignedRecordSnapshot,
+    },
+    storage::{ChunkAddress, RecordType},
+    NetworkAddress,
+};
+use sn_transfers::{MainPubkey, NanoTokens, PaymentQuote};
+use std::{path::PathBuf, time::UNIX_EPOCH};
+use xor_name::XorName;
+
+const WRITE_FIXTURES_ENV: &str = "SN_WRITE_MESSAGE_FIXTURES";
+
+fn fixtures_dir(version: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(version)
+}
+
+fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    let mut bytes = vec![];
+    cbor4ii::serde::to_writer(&mut bytes, message).expect("message to be encoded");
+    bytes
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> T {
+    cbor4ii::serde::from_slice(bytes).expect("message to be decoded")
+}
+
+/// Checks every fixture of `version` against the message it is expected to hold, or writes the
+/// fixtures if requested.
+fn check_fixtures<T>(version: &str, messages: Vec<(&str, T)>)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let dir = fixtures_dir(version);
+    let write = std::env::var(WRITE_FIXTURES_ENV).is_ok_and(|v| v == version);
+
+    for (name, message) in messages {
+        let path = dir.join(format!("{name}.cbor.hex"));
+        if write {
+            std::fs::create_dir_all(&dir).expect("fixtures dir to be created");
+            std::fs::write(&path, hex::encode(encode(&message))).expect("fixture to be written");
+            continue;
+        }
+
+        let fixture = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("fixture {path:?} to be read: {err}"));
+        let bytes = hex::decode(fixture.trim()).expect("fixture to be hex");
+        assert_eq!(decode::<T>(&bytes), message, "fixture {name} of {version}");
+    }
+}
+
+fn address(byte: u8) -> NetworkAddress {
+    NetworkAddress::from_chunk_address(ChunkAddress::new(XorName([byte; 32])))
+}
+
+fn peer_address(byte: u8) -> NetworkAddress {
+    NetworkAddress::PeerId(Bytes::from(vec![byte; 38]))
+}
+
+#[test]
+fn v1_requests_should_be_read() {
+    check_fixtures(
+        "v1",
+        vec![
+            (
+                "request_get_store_cost",
+                Request::Query(Query::GetStoreCost(address(1))),
+            ),
+            (
+                "request_get_replicated_record",
+                Request::Query(Query::GetReplicatedRecord {
+                    requester: peer_address(2),
+                    key: address(3),
+                }),
+            ),
+            (
+                "request_get_register_record",
+                Request::Query(Query::GetRegisterRecord {
+                    requester: peer_address(4),
+                    key: address(5),
+                }),
+            ),
+            (
+                "request_get_chunk_existence_proof",
+                Request::Query(Query::GetChunkExistenceProof {
+                    key: address(6),
+                    nonce: 7,
+                }),
+            ),
+            (
+                "request_check_node_in_problem",
+                Request::Query(Query::CheckNodeInProblem(peer_address(8))),
+            ),
+            (
+                "request_replicate",
+                Request::Cmd(Cmd::Replicate {
+                    holder: peer_address(9),
+                    keys: vec![
+                        (address(10), RecordType::Chunk),
+                        (address(11), RecordType::NonChunk(XorName([12; 32]))),
+                    ],
+                }),
+            ),
+            (
+                "request_peer_considered_as_bad",
+                Request::Cmd(Cmd::PeerConsideredAsBad {
+                    detected_by: peer_address(13),
+                    bad_peer: peer_address(14),
+                    bad_behaviour: "ReplicationFailure".to_string(),
+                }),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn v1_responses_should_be_read() {
+    let mut quote = PaymentQuote::zero();
+    quote.timestamp = UNIX_EPOCH;
+    let payment_address = MainPubkey::new(
+        bls::SecretKey::from_bytes([1; bls::SK_SIZE])
+            .expect("secret key to be valid")
+            .public_key(),
+    );
+
+    check_fixtures(
+        "v1",
+        vec![
+            (
+                "response_get_store_cost",
+                Response::Query(QueryResponse::GetStoreCost {
+                    quote: Ok(quote),
+                    payment_address,
+                    peer_address: peer_address(1),
+                }),
+            ),
+            (
+                "response_get_replicated_record",
+                Response::Query(QueryResponse::GetReplicatedRecord(Ok((
+                    peer_address(2),
+                    Bytes::from_static(b"record"),
+                )))),
+            ),
+            (
+                "response_get_chunk_existence_proof",
+                Response::Query(QueryResponse::GetChunkExistenceProof(Ok(ChunkProof::new(
+                    b"chunk", 3,
+                )))),
+            ),
+            (
+                "response_record_not_found",
+                Response::Query(QueryResponse::GetRegisterRecord(Err(
+                    ProtocolError::RegisterRecordNotFound {
+                        holder: Box::new(peer_address(4)),
+                        key: Box::new(address(5)),
+                    },
+                ))),
+            ),
+            (
+                "response_replicate",
+                Response::Cmd(CmdResponse::Replicate(Ok(()))),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn v2_messages_should_be_read() {
+    check_fixtures(
+        "v2",
+        vec![(
+            "request_get_record_snapshot",
+            Request::Query(Query::GetRecordSnapshot {
+                requester: peer_address(1),
+                start: 2,
+                after: None,
+            }),
+        )],
+    );
+
+    // ed25519 signatures are deterministic, so is the fixture
+    let keypair =
+        libp2p::identity::Keypair::ed25519_from_bytes([3; 32]).expect("keypair to be valid");
+    let snapshot = RecordSnapshot {
+        requester: peer_address(4),
+        records: vec![(address(5), Bytes::from_static(b"record"))],
+        next: Some(6),
+    };
+    check_fixtures(
+        "v2",
+        vec![(
+            "response_get_record_snapshot",
+            Response::Query(QueryResponse::GetRecordSnapshot(Ok(
+                SignedRecordSnapshot::sign(&keypair, &snapshot).expect("snapshot to be signed"),
+            ))),
+        )],
+    );
+}
+
+#[test]
+fn v3_messages_should_be_read() {
+    check_fixtures(
+        "v3",
+        vec![(
+            "request_get_payment_receipt",
+            Request::Query(Query::GetPaymentReceipt {
+                key: address(1),
+                quote_id: XorName([2; 32]),
+            }),
+        )],
+    );
+
+    let keypair =
+        libp2p::identity::Keypair::ed25519_from_bytes([3; 32]).expect("keypair to be valid");
+    let receipt = PaymentReceipt {
+        record: address(4),
+        amount: NanoTokens::from(5),
+        quote_id: XorName([6; 32]),
+    };
+    check_fixtures(
+        "v3",
+        vec![
+            (
+                "response_get_payment_receipt",
+                Response::Query(QueryResponse::GetPaymentReceipt(Ok(
+                    SignedPaymentReceipt::sign(&keypair, &receipt).expect("receipt to be signed"),
+                ))),
+            ),
+            (
+                "response_payment_receipt_not_found",
+                Response::Query(QueryResponse::GetPaymentReceipt(Err(
+                    ProtocolError::ReceiptNotFound {
+                        key: Box::new(address(7)),
+                        quote_id: XorName([8; 32]),
+                    },
+                ))),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn v4_messages_should_be_read() {
+    check_fixtures(
+        "v4",
+        vec![(
+            "request_get_record_snapshot_after",
+            Request::Query(Query::GetRecordSnapshot {
+                requester: peer_address(1),
+                start: 0,
+                after: Some(address(2)),
+            }),
+        )],
+    );
+    check_fixtures(
+        "v4",
+        vec![(
+            "response_record_snapshot_requester_mismatch",
+            Response::Query(QueryResponse::GetRecordSnapshot(Err(
+                ProtocolError::SnapshotRequesterMismatch {
+                    requester: Box::new(peer_address(3)),
+                },
+            ))),
+        )],
+    );
+}
+
+#[test]
+fn v5_messages_should_be_read() {
+    check_fixtures(
+        "v5",
+        vec![(
+            "request_get_payment_receipts",
+            Request::Query(Query::GetPaymentReceipts(vec![
+                (address(1), XorName([2; 32])),
+                (address(3), XorName([4; 32])),
+            ])),
+        )],
+    );
+
+    let keypair =
+        libp2p::identity::Keypair::ed25519_from_bytes([5; 32]).expect("keypair to be valid");
+    let receipt = PaymentReceipt {
+        record: address(6),
+        amount: NanoTokens::from(7),
+        quote_id: XorName([8; 32]),
+    };
+    check_fixtures(
+        "v5",
+        vec![
+            (
+                "response_get_payment_receipts",
+                Response::Query(QueryResponse::GetPaymentReceipts(vec![
+                    Ok(SignedPaymentReceipt::sign(&keypair, &receipt)
+                        .expect("receipt to be signed")),
+                    Err(ProtocolError::ReceiptNotFound {
+                        key: Box::new(address(9)),
+                        quote_id: XorName([10; 32]),
+                    }),
+                ])),
+            ),
+            (
+                "response_rejected",
+                Response::Rejected(ProtocolError::RequestAuthInvalid),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn v6_messages_should_be_read() {
+    // errors are sent with their code from this version on
+    check_fixtures(
+        "v6",
+        vec![
+            (
+                "response_get_chunk_existence_proof_coded_error",
+                Response::Query(QueryResponse::GetChunkExistenceProof(Err(
+                    ProtocolError::ChunkDoesNotExist(address(1)),
+                ))),
+            ),
+            (
+                "response_rejected_coded_error",
+                Response::Rejected(ProtocolError::RequestAuthInvalid),
+            ),
+        ],
+    );
+}
+
+/// The messages of a later version, with variants this version does not know about.
+mod later_version {
+    use super::*;
+
+    #[derive(Serialize)]
+    pub enum Request {
+        Cmd(Cmd),
+        Query(Query),
+        Subscribe { topic: String },
+    }
+
+    #[derive(Serialize)]
+    pub enum Cmd {
+        Replicate {
+            holder: NetworkAddress,
+            keys: Vec<(NetworkAddress, RecordType)>,
+        },
+        Delete(NetworkAddress),
+    }
+
+    #[derive(Serialize)]
+    pub enum Response {
+        Query(QueryResponse),
+    }
+
+    #[derive(Serialize)]
+    pub enum QueryResponse {
+        GetRecordCount(u64),
+    }
+}
+
+#[test]
+fn unknown_messages_should_be_read_as_unknown() {
+    let request = later_version::Request::Subscribe {
+        topic: "spends".to_string(),
+    };
+    assert!(matches!(
+        decode::<Request>(&encode(&request)),
+        Request::Unknown(_)
+    ));
+
+    let request = later_version::Request::Cmd(later_version::Cmd::Delete(address(1)));
+    assert!(matches!(
+        decode::<Request>(&encode(&request)),
+        Request::Cmd(Cmd::Unknown(_))
+    ));
+
+    // the variants known to both versions are still read
+    let request = later_version::Request::Cmd(later_version::Cmd::Replicate {
+        holder: peer_address(2),
+        keys: vec![(address(3), RecordType::Chunk)],
+    });
+    assert_eq!(
+        decode::<Request>(&encode(&request)),
+        Request::Cmd(Cmd::Replicate {
+            holder: peer_address(2),
+            keys: vec![(address(3), RecordType::Chunk)],
+        })
+    );
+    let request = later_version::Request::Query(Query::GetStoreCost(address(4)));
+    assert_eq!(
+        decode::<Request>(&encode(&request)),
+        Request::Query(Query::GetStoreCost(address(4)))
+    );
+
+    let response = later_version::Response::Query(later_version::QueryResponse::GetRecordCount(5));
+    assert!(matches!(
+        decode::<Response>(&encode(&response)),
+        Response::Query(QueryResponse::Unknown(_))
+    ));
+}
+
+#[test]
+fn malformed_messages_should_fail_to_be_read() {
+    let request = Request::Query(Query::GetStoreCost(address(1)));
+    let bytes = request.to_bytes().expect("request to be encoded");
+    assert_eq!(bytes, encode(&request));
+    assert_eq!(Request::try_from_bytes(&bytes).ok(), Some(request));
+
+    assert!(matches!(
+        Request::try_from_bytes(&bytes[..bytes.len() - 1]),
+        Err(ProtocolError::MessageDeserializationFailed(_))
+    ));
+    // deeply nested arrays must not overflow the stack
+    assert!(matches!(
+        Response::try_from_bytes(&[0x81; 100_000]),
+        Err(ProtocolError::MessageDeserializationFailed(_))
+    ));
+    assert!(matches!(
+        Response::try_from_bytes(&vec![0; sn_protocol::messages::MAX_MESSAGE_SIZE + 1]),
+        Err(ProtocolError::MessageTooLarge { .. })
+    ));
+}
@@ -45,6 +45,17 @@ pub enum Error {
     /// Serialisation Failed
     #[error("Serialisation failed")]
     SerialisationFailed,
+    /// Deserialisation Failed
+    #[error("Deserialisation failed: {0}")]
+    DeserialisationFailed(String),
+    /// The serialised data is too big to be deserialised
+    #[error("Serialised data is too big to be deserialised: {size}, max: {max}")]
+    SerialisedSizeTooLarge {
+        /// Size of the serialised data
+        size: usize,
+        /// Maximum size allowed
+        max: usize,
+    },
     /// SignedRegister Merge only works when both registers have the same base register (owner/permissions/etc)
     #[error("SignedRegister Merge failed because base Register was different")]
     DifferentBaseRegister,
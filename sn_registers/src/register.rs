@@ -23,6 +23,20 @@ const MAX_REG_ENTRY_SIZE: usize = 1024;
 /// Maximum number of entries of a register.
 const MAX_REG_NUM_ENTRIES: u16 = 1024;
 
+/// Maximum size of serialised register data accepted for deserialisation.
+pub(crate) const MAX_SERIALISED_SIZE: usize = 5 * 1024 * 1024;
+
+/// Fail if the serialised data is too big to be deserialised.
+pub(crate) fn check_serialised_size(bytes: &[u8]) -> Result<()> {
+    if bytes.len() > MAX_SERIALISED_SIZE {
+        return Err(Error::SerialisedSizeTooLarge {
+            size: bytes.len(),
+            max: MAX_SERIALISED_SIZE,
+        });
+    }
+    Ok(())
+}
+
 /// A Register on the SAFE Network
 #[derive(Clone, Eq, PartialEq, PartialOrd, Hash, Serialize, Deserialize, Debug)]
 pub struct Register {
@@ -57,6 +71,18 @@ impl SignedRegister {
         }
     }
 
+    /// Serialise the SignedRegister, the inverse of [`SignedRegister::try_from_bytes`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|_| Error::SerialisationFailed)
+    }
+
+    /// Deserialise a SignedRegister from untrusted bytes, failing on oversized or malformed
+    /// input. The signatures are not verified, see [`SignedRegister::verify`].
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        check_serialised_size(bytes)?;
+        rmp_serde::from_slice(bytes).map_err(|err| Error::DeserialisationFailed(err.to_string()))
+    }
+
     /// Return the base register. This is the register before any operations have been applied.
     pub fn base_register(&self) -> &Register {
         &self.base_register
@@ -385,7 +411,8 @@ mod tests {
     use crate::RegisterOp;
 
     use super::{
-        EntryHash, Error, Permissions, Register, RegisterAddress, Result, MAX_REG_NUM_ENTRIES,
+        EntryHash, Error, Permissions, Register, RegisterAddress, Result, SignedRegister,
+        MAX_REG_NUM_ENTRIES, MAX_SERIALISED_SIZE,
     };
 
     use bls::SecretKey;
@@ -441,6 +468,38 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn try_from_bytes_should_reject_malformed_bytes() -> eyre::Result<()> {
+        let owner_sk = SecretKey::random();
+        let mut register = Register::new(
+            owner_sk.public_key(),
+            xor_name::rand::random(),
+            Permissions::default(),
+        );
+        let (_, op) = register.write(random_register_entry(), &BTreeSet::new(), &owner_sk)?;
+        let mut signed_register = register.into_signed(&owner_sk)?;
+        signed_register.add_op(op.clone())?;
+
+        let bytes = signed_register.to_bytes()?;
+        assert_eq!(SignedRegister::try_from_bytes(&bytes)?, signed_register);
+        assert!(matches!(
+            SignedRegister::try_from_bytes(&bytes[..bytes.len() / 2]),
+            Err(Error::DeserialisationFailed(_))
+        ));
+
+        let bytes = op.to_bytes()?;
+        assert_eq!(RegisterOp::try_from_bytes(&bytes)?, op);
+        assert!(matches!(
+            RegisterOp::try_from_bytes(&[0xff; 16]),
+            Err(Error::DeserialisationFailed(_))
+        ));
+        assert!(matches!(
+            RegisterOp::try_from_bytes(&vec![0; MAX_SERIALISED_SIZE + 1]),
+            Err(Error::SerialisedSizeTooLarge { .. })
+        ));
+        Ok(())
+    }
+
     #[test]
     fn register_permissions() -> eyre::Result<()> {
         let owner_sk = SecretKey::random();
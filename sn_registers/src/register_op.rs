@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{error::Result, Entry, Error, RegisterAddress};
+use crate::{error::Result, register::check_serialised_size, Entry, Error, RegisterAddress};
 
 use bls::{PublicKey, SecretKey};
 use crdts::merkle_reg::Node as MerkleDagEntry;
@@ -54,6 +54,18 @@ impl RegisterOp {
         }
     }
 
+    /// Serialise the op, the inverse of [`RegisterOp::try_from_bytes`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(self).map_err(|_| Error::SerialisationFailed)
+    }
+
+    /// Deserialise an op from untrusted bytes, failing on oversized or malformed input.
+    /// The signature is not verified, see [`RegisterOp::verify_signature`].
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        check_serialised_size(bytes)?;
+        rmp_serde::from_slice(bytes).map_err(|err| Error::DeserialisationFailed(err.to_string()))
+    }
+
     /// address of the register this op is destined for
     pub fn address(&self) -> RegisterAddress {
         self.address
@@ -11,12 +11,13 @@ use async_trait::async_trait;
 use libp2p::{kad::RecordKey, Multiaddr, PeerId};
 use sn_protocol::{
     safenode_proto::{
-        safe_node_client::SafeNodeClient, NetworkInfoRequest, NodeInfoRequest,
-        RecordAddressesRequest, RestartRequest, StopRequest, UpdateLogLevelRequest, UpdateRequest,
+        safe_node_client::SafeNodeClient, CloseGroupConsistencyRequest, NetworkInfoRequest,
+        NodeInfoRequest, RecordAddressesRequest, RestartRequest, StopRequest,
+        UpdateLogLevelRequest, UpdateRequest,
     },
     CLOSE_GROUP_SIZE,
 };
-use std::{net::SocketAddr, path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, str::FromStr};
 use tokio::time::Duration;
 use tonic::Request;
 use tracing::error;
@@ -36,6 +37,8 @@ pub struct NodeInfo {
 pub struct NetworkInfo {
     pub connected_peers: Vec<PeerId>,
     pub listeners: Vec<Multiaddr>,
+    /// The identify protocol version advertised by each connected peer.
+    pub peer_protocol_versions: BTreeMap<PeerId, String>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +46,24 @@ pub struct RecordAddress {
     pub key: RecordKey,
 }
 
+/// The copies of a record held by the peers of its close group, as checked by a node.
+#[derive(Debug, Clone)]
+pub struct CloseGroupConsistency {
+    /// The digests of the distinct copies, each with the peers holding it, the most held first
+    pub copies: Vec<(String, Vec<PeerId>)>,
+    /// The peers answering that they do not hold the record
+    pub missing: Vec<PeerId>,
+    /// The peers which did not answer, with the reason
+    pub unreachable: Vec<(PeerId, String)>,
+}
+
+impl CloseGroupConsistency {
+    /// Whether the whole group answered and holds the same copy of the record.
+    pub fn is_consistent(&self) -> bool {
+        self.copies.len() == 1 && self.missing.is_empty() && self.unreachable.is_empty()
+    }
+}
+
 #[async_trait]
 pub trait RpcActions: Sync {
     async fn node_info(&self) -> Result<NodeInfo>;
@@ -53,6 +74,10 @@ pub trait RpcActions: Sync {
     async fn node_update(&self, delay_millis: u64) -> Result<()>;
     async fn is_node_connected_to_network(&self, timeout: Duration) -> Result<()>;
     async fn update_log_level(&self, log_levels: String) -> Result<()>;
+    /// Change only the levels of the targets given, returning the levels in effect.
+    async fn merge_log_level(&self, log_levels: String) -> Result<String>;
+    /// Have the node compare the copies of the record held by the peers of its close group.
+    async fn close_group_consistency(&self, key: RecordKey) -> Result<CloseGroupConsistency>;
 }
 
 #[derive(Debug, Clone)]
@@ -165,9 +190,16 @@ impl RpcActions for RpcClient {
             listeners.push(multiaddr);
         }
 
+        let mut peer_protocol_versions = BTreeMap::new();
+        for peer_version in network_info.peer_protocol_versions.iter() {
+            let peer_id = PeerId::from_bytes(&peer_version.peer)?;
+            peer_protocol_versions.insert(peer_id, peer_version.protocol_version.clone());
+        }
+
         Ok(NetworkInfo {
             connected_peers,
             listeners,
+            peer_protocol_versions,
         })
     }
 
@@ -275,6 +307,7 @@ impl RpcActions for RpcClient {
         let _response = client
             .update_log_level(Request::new(UpdateLogLevelRequest {
                 log_level: log_levels,
+                merge: false,
             }))
             .await
             .map_err(|e| {
@@ -283,4 +316,60 @@ impl RpcActions for RpcClient {
             })?;
         Ok(())
     }
+
+    async fn merge_log_level(&self, log_levels: String) -> Result<String> {
+        let mut client = self.connect_with_retry().await?;
+        let response = client
+            .update_log_level(Request::new(UpdateLogLevelRequest {
+                log_level: log_levels,
+                merge: true,
+            }))
+            .await
+            .map_err(|e| {
+                error!("Could not update node through RPC: {e:?}");
+                Error::RpcNodeUpdateError(e.to_string())
+            })?;
+        Ok(response.get_ref().log_level.clone())
+    }
+
+    async fn close_group_consistency(&self, key: RecordKey) -> Result<CloseGroupConsistency> {
+        let mut client = self.connect_with_retry().await?;
+        let response = client
+            .close_group_consistency(Request::new(CloseGroupConsistencyRequest {
+                key: key.to_vec(),
+            }))
+            .await
+            .map_err(|e| {
+                error!("Could not check the close group of a record through RPC: {e:?}");
+                Error::RpcCloseGroupConsistencyError(e.to_string())
+            })?;
+        let response = response.into_inner();
+        let copies = response
+            .copies
+            .into_iter()
+            .map(|copy| {
+                let peers = copy
+                    .peers
+                    .iter()
+                    .map(|bytes| PeerId::from_bytes(bytes))
+                    .collect::<std::result::Result<_, _>>()?;
+                Ok((copy.digest, peers))
+            })
+            .collect::<Result<_>>()?;
+        let missing = response
+            .missing
+            .iter()
+            .map(|bytes| PeerId::from_bytes(bytes))
+            .collect::<std::result::Result<_, _>>()?;
+        let unreachable = response
+            .unreachable
+            .into_iter()
+            .map(|unreachable| Ok((PeerId::from_bytes(&unreachable.peer)?, unreachable.reason)))
+            .collect::<Result<_>>()?;
+        Ok(CloseGroupConsistency {
+            copies,
+            missing,
+            unreachable,
+        })
+    }
 }
@@ -0,0 +1,117 @@
+// Copyright 2024 MaidSafe.net limited.
+
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#![allow(clippy::unwrap_used)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sn_transfers::{
+    create_first_cash_note_from_key, rng, CashNote, DerivationIndex, HotWallet, MainSecretKey,
+    NanoTokens, SignedTransaction, SpendReason,
+};
+
+const N_WALLET_CASH_NOTES: u64 = 10_000;
+const N_INPUTS: u64 = 1_000;
+const BATCH_SIZE: u64 = 100;
+
+/// Split the genesis cash_note into `batches * batch_size` cash_notes of 1 nano, all owned by the
+/// returned key.
+///
+/// Each cash_note holds its parent spend, which lists all its siblings, so the split is done in
+/// two steps to keep the size of the outputs linear.
+fn split_genesis(batches: u64, batch_size: u64) -> (Vec<CashNote>, MainSecretKey) {
+    let mut rng = rng::from_seed([0u8; 32]);
+    let genesis_key = MainSecretKey::random_from_rng(&mut rng);
+    let genesis = create_first_cash_note_from_key(&genesis_key).unwrap();
+    let owner = MainSecretKey::random_from_rng(&mut rng);
+
+    let mut split = |cash_notes: Vec<CashNote>, count: u64, amount: u64, key: &MainSecretKey| {
+        let recipients = (0..count)
+            .map(|_| {
+                (
+                    NanoTokens::from(amount),
+                    owner.main_pubkey(),
+                    DerivationIndex::random(&mut rng),
+                    false,
+                )
+            })
+            .collect();
+        SignedTransaction::new(
+            cash_notes,
+            recipients,
+            key.main_pubkey(),
+            SpendReason::default(),
+            key,
+        )
+        .unwrap()
+        .output_cashnotes
+    };
+
+    let batch_cash_notes = split(vec![genesis], batches, batch_size, &genesis_key);
+    let cash_notes = batch_cash_notes
+        .into_iter()
+        .flat_map(|cash_note| split(vec![cash_note], batch_size, 1, &owner))
+        .collect();
+    (cash_notes, owner)
+}
+
+fn bench_wallet_load(c: &mut Criterion) {
+    let (cash_notes, owner) = split_genesis(N_WALLET_CASH_NOTES / BATCH_SIZE, BATCH_SIZE);
+    let dir = tempfile::tempdir().unwrap();
+    let mut wallet = HotWallet::create_from_key(dir.path(), owner, None).unwrap();
+    wallet.deposit_and_store_to_disk(&cash_notes).unwrap();
+    drop(wallet);
+
+    let _ = c.bench_function(
+        &format!("load wallet with {N_WALLET_CASH_NOTES} cash_notes"),
+        |b| {
+            b.iter(|| {
+                let mut wallet = HotWallet::load_from(dir.path()).unwrap();
+                let (available, _exclusive_access) = wallet.available_cash_notes().unwrap();
+                assert_eq!(black_box(available).len() as u64, N_WALLET_CASH_NOTES);
+            });
+        },
+    );
+}
+
+fn bench_offline_transfer(c: &mut Criterion) {
+    let (cash_notes, owner) = split_genesis(N_INPUTS / BATCH_SIZE, BATCH_SIZE);
+    let mut rng = rng::from_seed([1u8; 32]);
+    let recipient = MainSecretKey::random_from_rng(&mut rng).main_pubkey();
+    let to = vec![(
+        NanoTokens::from(N_INPUTS),
+        recipient,
+        DerivationIndex::random(&mut rng),
+        false,
+    )];
+
+    let _ = c.bench_function(
+        &format!("create offline transfer of {N_INPUTS} inputs"),
+        |b| {
+            b.iter(|| {
+                let _ = black_box(
+                    SignedTransaction::new(
+                        cash_notes.clone(),
+                        to.clone(),
+                        owner.main_pubkey(),
+                        SpendReason::default(),
+                        &owner,
+                    )
+                    .unwrap(),
+                );
+            });
+        },
+    );
+}
+
+criterion_group! {
+    name = wallet;
+    config = Criterion::default().sample_size(10);
+    targets = bench_wallet_load, bench_offline_transfer
+}
+
+criterion_main!(wallet);
@@ -0,0 +1,85 @@
+#ifndef SN_TRANSFERS_H
+#define SN_TRANSFERS_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The outcome of a call.
+typedef enum SnStatus {
+  SN_STATUS_OK = 0,
+  SN_STATUS_NULL_POINTER = 1,
+  SN_STATUS_INVALID_ARGUMENT = 2,
+  SN_STATUS_WALLET_ERROR = 3,
+  SN_STATUS_TRANSFER_ERROR = 4,
+  SN_STATUS_PANIC = 5,
+} SnStatus;
+
+// A hot wallet, only handled through pointers.
+typedef struct SnWallet SnWallet;
+
+// The message of the last error of the calling thread, or null if there was none.
+//
+// The string is owned by the library and valid until the next call failing on this thread.
+const char *sn_last_error(void);
+
+// Free a string returned by this library.
+//
+// # Safety
+// `string` must be null or a string returned through an out pointer, not yet freed.
+void sn_string_free(char *string);
+
+// Create a wallet under `root_dir`, of the hex encoded `secret_key`, or of a random key if it is
+// null.
+//
+// # Safety
+// `root_dir` and `secret_key` must be null or valid NUL-terminated strings, `out` valid for
+// writes.
+enum SnStatus sn_wallet_create(const char *root_dir, const char *secret_key, struct SnWallet **out);
+
+// Load the wallet stored under `root_dir`.
+//
+// # Safety
+// `root_dir` must be null or a valid NUL-terminated string, `out` valid for writes.
+enum SnStatus sn_wallet_load(const char *root_dir, struct SnWallet **out);
+
+// Free a wallet.
+//
+// # Safety
+// `wallet` must be null or a pointer returned by `sn_wallet_create` or `sn_wallet_load`, not yet
+// freed.
+void sn_wallet_free(struct SnWallet *wallet);
+
+// The balance of the wallet, in nanos.
+//
+// # Safety
+// `wallet` must be a live wallet, `out_nanos` valid for writes.
+enum SnStatus sn_wallet_balance(struct SnWallet *wallet, uint64_t *out_nanos);
+
+// The hex encoded public key of the wallet, to receive tokens at.
+//
+// # Safety
+// `wallet` must be a live wallet, `out_address` valid for writes.
+enum SnStatus sn_wallet_address(struct SnWallet *wallet, char **out_address);
+
+// Send `amount_nanos` to the hex encoded public key `recipient`, giving the hex encoded cash note
+// to be handed to the recipient.
+//
+// # Safety
+// `wallet` must be a live wallet, `recipient` a valid NUL-terminated string, `out_cash_note`
+// valid for writes.
+enum SnStatus sn_wallet_send(struct SnWallet *wallet,
+                             const char *recipient,
+                             uint64_t amount_nanos,
+                             char **out_cash_note);
+
+// Deposit the hex encoded cash note received by the wallet.
+//
+// # Safety
+// `wallet` must be a live wallet, `cash_note` a valid NUL-terminated string.
+enum SnStatus sn_wallet_deposit(struct SnWallet *wallet, const char *cash_note);
+
+#endif  /* SN_TRANSFERS_H */
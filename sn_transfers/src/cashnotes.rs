@@ -160,4 +160,38 @@ pub(crate) mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn try_from_bytes_should_reject_malformed_bytes() -> Result<(), TransferError> {
+        let mut rng = crate::rng::from_seed([0u8; 32]);
+        let main_key = MainSecretKey::random_from_rng(&mut rng);
+        let derivation_index = DerivationIndex::random(&mut rng);
+        let parent_spends = generate_parent_spends(
+            main_key.derive_key(&DerivationIndex::random(&mut rng)),
+            100,
+            main_key.derive_key(&derivation_index).unique_pubkey(),
+        );
+        let cashnote = CashNote {
+            parent_spends,
+            main_pubkey: main_key.main_pubkey(),
+            derivation_index,
+        };
+
+        let bytes = cashnote.to_bytes()?;
+        assert_eq!(CashNote::try_from_bytes(&bytes)?, cashnote);
+
+        for len in 0..bytes.len() {
+            assert!(CashNote::try_from_bytes(&bytes[..len]).is_err());
+        }
+        for malformed in [vec![0xff; 64], vec![0xdd, 0xff, 0xff, 0xff, 0xff]] {
+            assert!(CashNote::try_from_bytes(&malformed).is_err());
+            assert!(SignedSpend::try_from_bytes(&malformed).is_err());
+        }
+        assert!(matches!(
+            CashNote::try_from_bytes(&vec![0; crate::MAX_SERIALIZED_SIZE + 1]),
+            Err(TransferError::SerializedSizeTooLarge { .. })
+        ));
+
+        Ok(())
+    }
 }
@@ -170,14 +170,34 @@ impl CashNote {
         Ok(())
     }
 
+    /// Serialize this `CashNote` instance to bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, TransferError> {
+        rmp_serde::to_vec(&self).map_err(|e| TransferError::HexSerializationFailed(e.to_string()))
+    }
+
+    /// Deserializes a `CashNote` from bytes, which may come from anyone.
+    ///
+    /// Malformed or oversized bytes are rejected with an error.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, TransferError> {
+        crate::check_serialized_size(bytes)?;
+        rmp_serde::from_slice(bytes)
+            .map_err(|e| TransferError::CashNoteDeserializationFailed(e.to_string()))
+    }
+
     /// Deserializes a `CashNote` represented as a hex string to a `CashNote`.
     pub fn from_hex(hex: &str) -> Result<Self, TransferError> {
+        if hex.len() > 2 * crate::MAX_SERIALIZED_SIZE {
+            return Err(TransferError::HexDeserializationFailed(format!(
+                "{} hex characters are more than the maximum of {}",
+                hex.len(),
+                2 * crate::MAX_SERIALIZED_SIZE
+            )));
+        }
         let mut bytes =
             hex::decode(hex).map_err(|e| TransferError::HexDeserializationFailed(e.to_string()))?;
         bytes.reverse();
-        let cashnote: CashNote = rmp_serde::from_slice(&bytes)
-            .map_err(|e| TransferError::HexDeserializationFailed(e.to_string()))?;
-        Ok(cashnote)
+        Self::try_from_bytes(&bytes)
+            .map_err(|e| TransferError::HexDeserializationFailed(e.to_string()))
     }
 
     /// Serialize this `CashNote` instance to a hex string.
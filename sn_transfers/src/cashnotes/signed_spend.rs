@@ -8,10 +8,9 @@
 
 use super::spend_reason::SpendReason;
 use super::{Hash, NanoTokens, UniquePubkey};
-use crate::{
-    DerivationIndex, DerivedSecretKey, Result, Signature, SpendAddress, TransferError,
-    NETWORK_ROYALTIES_PK,
-};
+#[cfg(feature = "std")]
+use crate::NETWORK_ROYALTIES_PK;
+use crate::{DerivationIndex, DerivedSecretKey, Result, Signature, SpendAddress, TransferError};
 
 use custom_debug::Debug;
 use serde::{Deserialize, Serialize};
@@ -63,6 +62,16 @@ impl SignedSpend {
         &self.spend.reason
     }
 
+    /// Deserializes a `SignedSpend` from its serde (MessagePack) serialization, which may come
+    /// from anyone. This is not the inverse of `to_bytes`, which is only meant for hashing.
+    ///
+    /// Malformed or oversized bytes are rejected with an error.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        crate::check_serialized_size(bytes)?;
+        rmp_serde::from_slice(bytes)
+            .map_err(|e| TransferError::SignedSpendDeserializationFailed(e.to_string()))
+    }
+
     /// Represent this SignedSpend as bytes.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes: Vec<u8> = Default::default();
@@ -261,6 +270,7 @@ impl Spend {
     }
 
     /// Returns the royalties descendants of this Spend
+    #[cfg(feature = "std")]
     pub fn network_royalties(&self) -> BTreeSet<(UniquePubkey, NanoTokens, DerivationIndex)> {
         let roy_pks: BTreeMap<UniquePubkey, DerivationIndex> = self
             .royalties
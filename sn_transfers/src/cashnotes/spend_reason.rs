@@ -39,6 +39,7 @@ impl SpendReason {
         }
     }
 
+    #[cfg(feature = "std")]
     pub fn create_reward_tracking_reason(input_str: &str) -> Result<Self> {
         let input_pk = crate::PAYMENT_FORWARD_PK.public_key();
         Ok(Self::BetaRewardTracking(DiscordNameCipher::create(
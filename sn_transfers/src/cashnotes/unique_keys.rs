@@ -7,7 +7,7 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::rand::{distributions::Standard, Rng, RngCore};
-use crate::wallet::{Error, Result};
+use crate::{Result, TransferError as Error};
 
 use bls::{serde_impl::SerdeSecret, PublicKey, SecretKey, PK_SIZE};
 use serde::{Deserialize, Serialize};
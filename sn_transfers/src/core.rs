@@ -0,0 +1,70 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The primitives of the transfer system, without the wallet and its files.
+//!
+//! This is everything a signer needs to construct and verify transfers: keys, cash notes, spends,
+//! transactions, the signing of a transaction by a `WalletSigner` and the checks of their
+//! invariants. Nothing here touches the filesystem, the environment or a global RNG when the
+//! `_with_rng` constructors are used.
+//!
+//! Without the default `std` feature, only this module is built: the wallet, the genesis and the
+//! network keys are left out, together with the dependencies touching the filesystem, the
+//! environment or threads. This is checked in CI with `--no-default-features`. The few primitives
+//! relying on the network keys, `Spend::network_royalties` and
+//! `SpendReason::create_reward_tracking_reason`, need the `std` feature.
+//!
+//! The crate can't be `#![no_std]` yet, its remaining dependencies requiring std: `blsttc`,
+//! `rmp-serde`, `serde_json` and `thiserror` 1. Code depending on this module only will not need
+//! to change once they are replaced.
+
+pub use crate::{
+    cashnotes::{
+        CashNote, DerivationIndex, DerivedSecretKey, Hash, MainPubkey, MainSecretKey, NanoTokens,
+        SignedSpend, Spend, SpendAddress, SpendReason, UniquePubkey,
+    },
+    error::{Result, TransferError},
+    transfers::{
+        CashNoteRedemption, SignedTransaction, Transfer, UnsignedTransaction, WalletSigner,
+    },
+    verification, MAX_SERIALIZED_SIZE,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rng, test_utils::fake_cash_note};
+
+    #[test]
+    fn transfers_should_be_built_and_verified_from_core() -> eyre::Result<()> {
+        let mut rng = rng::from_seed([0u8; 32]);
+        let key = MainSecretKey::random_from_rng(&mut rng);
+        let cash_note = fake_cash_note(key.main_pubkey(), NanoTokens::from(100));
+        let recipient = MainSecretKey::random_from_rng(&mut rng).main_pubkey();
+
+        let tx = SignedTransaction::new_with_rng(
+            vec![cash_note],
+            vec![(
+                NanoTokens::from(60),
+                recipient,
+                DerivationIndex::random(&mut rng),
+                false,
+            )],
+            key.main_pubkey(),
+            SpendReason::default(),
+            &key,
+            &mut rng,
+        )?;
+        tx.verify()?;
+        verification::check_transaction(&tx)?;
+
+        let transfer = Transfer::transfer_from_cash_note(&tx.output_cashnotes[0])?;
+        assert_eq!(Transfer::try_from_bytes(&transfer.to_bytes()?)?, transfer);
+        Ok(())
+    }
+}
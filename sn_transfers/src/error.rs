@@ -38,14 +38,22 @@ pub enum TransferError {
     HexDeserializationFailed(String),
     #[error("Could not serialize CashNote to hex: {0}")]
     HexSerializationFailed(String),
+    #[error("Could not deserialize the bytes to a CashNote: {0}")]
+    CashNoteDeserializationFailed(String),
+    #[error("Could not deserialize the bytes to a SignedSpend: {0}")]
+    SignedSpendDeserializationFailed(String),
+    #[error("The {size} bytes to deserialize are more than the maximum of {max}")]
+    SerializedSizeTooLarge { size: usize, max: usize },
     #[error("CashNote must have at least one ancestor.")]
     CashNoteMissingAncestors,
     #[error("The spends don't match the inputs of the Transaction.")]
     SpendsDoNotMatchInputs,
     #[error("Overflow occurred while adding values")]
     NumericOverflow,
-    #[error("Not enough balance, {0} available, {1} required")]
+    #[error("Not enough balance, {0} available, {1} required: {} missing", missing_amount(.0, .1))]
     NotEnoughBalance(NanoTokens, NanoTokens),
+    #[error("The key to spend input {0:?} is not held")]
+    MissingInputKey(UniquePubkey),
 
     #[error("CashNoteRedemption serialisation failed")]
     CashNoteRedemptionSerialisationFailed,
@@ -74,4 +82,42 @@ pub enum TransferError {
     InvalidDecryptionKey,
     #[error("User name encryption failed")]
     DiscordNameCipherTooBig,
+    #[error("Failed to parse bls key")]
+    FailedToParseBlsKey,
+    #[error("Could not decode hex string to key")]
+    FailedToDecodeHexToKey,
+}
+
+impl TransferError {
+    /// How much was missing for a send which failed for lack of balance.
+    pub fn missing_amount(&self) -> Option<NanoTokens> {
+        match self {
+            Self::NotEnoughBalance(available, required) => {
+                Some(missing_amount(available, required))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn missing_amount(available: &NanoTokens, required: &NanoTokens) -> NanoTokens {
+    required
+        .checked_sub(*available)
+        .unwrap_or(NanoTokens::zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_enough_balance_should_tell_what_is_missing() {
+        let error = TransferError::NotEnoughBalance(NanoTokens::from(100), NanoTokens::from(105));
+        assert_eq!(error.missing_amount(), Some(NanoTokens::from(5)));
+        assert_eq!(
+            error.to_string(),
+            "Not enough balance, 0.000000100 available, 0.000000105 required: 0.000000005 missing"
+        );
+        assert_eq!(TransferError::ZeroOutputs.missing_amount(), None);
+    }
 }
@@ -0,0 +1,340 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A C ABI over the wallet and transfer operations, available with the `ffi` feature.
+//!
+//! The declarations are in `include/sn_transfers.h`, generated by `cbindgen` from this module.
+//! Every function returns an [`SnStatus`], the message of the last error of the calling thread
+//! being given by [`sn_last_error`]. Strings returned through out pointers are owned by the caller
+//! and freed with [`sn_string_free`], wallets with [`sn_wallet_free`].
+//!
+//! The transfers are built offline: the spends they create are kept by the wallet as unconfirmed,
+//! to be sent to the Network by a client.
+
+#![allow(unsafe_code)]
+
+use crate::{
+    bls_secret_from_hex, CashNote, HotWallet, MainPubkey, MainSecretKey, NanoTokens, TransferError,
+    WalletError,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+    ptr,
+};
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidArgument = 2,
+    WalletError = 3,
+    TransferError = 4,
+    Panic = 5,
+}
+
+/// A hot wallet, only handled through pointers.
+pub struct SnWallet(HotWallet);
+
+enum FfiError {
+    NullPointer(&'static str),
+    InvalidArgument(String),
+    Wallet(WalletError),
+    Transfer(TransferError),
+}
+
+impl From<WalletError> for FfiError {
+    fn from(err: WalletError) -> Self {
+        FfiError::Wallet(err)
+    }
+}
+
+impl From<TransferError> for FfiError {
+    fn from(err: TransferError) -> Self {
+        FfiError::Transfer(err)
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Run the call, recording its error and never unwinding into the caller.
+fn ffi_call(call: impl FnOnce() -> Result<(), FfiError>) -> SnStatus {
+    let (status, message) = match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => return SnStatus::Ok,
+        Ok(Err(FfiError::NullPointer(name))) => {
+            (SnStatus::NullPointer, format!("{name} is a null pointer"))
+        }
+        Ok(Err(FfiError::InvalidArgument(message))) => (SnStatus::InvalidArgument, message),
+        Ok(Err(FfiError::Wallet(err))) => (SnStatus::WalletError, err.to_string()),
+        Ok(Err(FfiError::Transfer(err))) => (SnStatus::TransferError, err.to_string()),
+        Err(_) => (SnStatus::Panic, "the call panicked".to_string()),
+    };
+    set_last_error(message);
+    status
+}
+
+/// # Safety
+/// `ptr` must be null or a valid NUL-terminated string.
+unsafe fn read_str<'a>(ptr: *const c_char, name: &'static str) -> Result<&'a str, FfiError> {
+    if ptr.is_null() {
+        return Err(FfiError::NullPointer(name));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| FfiError::InvalidArgument(format!("{name} is not valid UTF-8")))
+}
+
+/// # Safety
+/// `out` must be null or valid for writes.
+unsafe fn write_out<T>(out: *mut T, value: T, name: &'static str) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::NullPointer(name));
+    }
+    out.write(value);
+    Ok(())
+}
+
+fn into_c_string(value: String) -> Result<*mut c_char, FfiError> {
+    CString::new(value)
+        .map(CString::into_raw)
+        .map_err(|_| FfiError::InvalidArgument("the string holds a NUL byte".to_string()))
+}
+
+/// # Safety
+/// `wallet` must be null or a pointer returned by `sn_wallet_create` or `sn_wallet_load`, not yet
+/// freed.
+unsafe fn wallet_mut<'a>(wallet: *mut SnWallet) -> Result<&'a mut HotWallet, FfiError> {
+    wallet
+        .as_mut()
+        .map(|wallet| &mut wallet.0)
+        .ok_or(FfiError::NullPointer("wallet"))
+}
+
+/// The message of the last error of the calling thread, or null if there was none.
+///
+/// The string is owned by the library and valid until the next call failing on this thread.
+#[no_mangle]
+pub extern "C" fn sn_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Free a string returned by this library.
+///
+/// # Safety
+/// `string` must be null or a string returned through an out pointer, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn sn_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Create a wallet under `root_dir`, of the hex encoded `secret_key`, or of a random key if it is
+/// null.
+///
+/// # Safety
+/// `root_dir` and `secret_key` must be null or valid NUL-terminated strings, `out` valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn sn_wallet_create(
+    root_dir: *const c_char,
+    secret_key: *const c_char,
+    out: *mut *mut SnWallet,
+) -> SnStatus {
+    ffi_call(|| {
+        let root_dir = read_str(root_dir, "root_dir")?;
+        let key = if secret_key.is_null() {
+            MainSecretKey::random()
+        } else {
+            MainSecretKey::new(bls_secret_from_hex(read_str(secret_key, "secret_key")?)?)
+        };
+        let wallet = HotWallet::create_from_key(Path::new(root_dir), key, None)?;
+        write_out(out, Box::into_raw(Box::new(SnWallet(wallet))), "out")
+    })
+}
+
+/// Load the wallet stored under `root_dir`.
+///
+/// # Safety
+/// `root_dir` must be null or a valid NUL-terminated string, `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sn_wallet_load(
+    root_dir: *const c_char,
+    out: *mut *mut SnWallet,
+) -> SnStatus {
+    ffi_call(|| {
+        let root_dir = read_str(root_dir, "root_dir")?;
+        let wallet = HotWallet::load_from(Path::new(root_dir))?;
+        write_out(out, Box::into_raw(Box::new(SnWallet(wallet))), "out")
+    })
+}
+
+/// Free a wallet.
+///
+/// # Safety
+/// `wallet` must be null or a pointer returned by `sn_wallet_create` or `sn_wallet_load`, not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn sn_wallet_free(wallet: *mut SnWallet) {
+    if !wallet.is_null() {
+        drop(Box::from_raw(wallet));
+    }
+}
+
+/// The balance of the wallet, in nanos.
+///
+/// # Safety
+/// `wallet` must be a live wallet, `out_nanos` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sn_wallet_balance(wallet: *mut SnWallet, out_nanos: *mut u64) -> SnStatus {
+    ffi_call(|| {
+        let wallet = wallet_mut(wallet)?;
+        write_out(out_nanos, wallet.balance().as_nano(), "out_nanos")
+    })
+}
+
+/// The hex encoded public key of the wallet, to receive tokens at.
+///
+/// # Safety
+/// `wallet` must be a live wallet, `out_address` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sn_wallet_address(
+    wallet: *mut SnWallet,
+    out_address: *mut *mut c_char,
+) -> SnStatus {
+    ffi_call(|| {
+        let wallet = wallet_mut(wallet)?;
+        write_out(
+            out_address,
+            into_c_string(wallet.address().to_hex())?,
+            "out_address",
+        )
+    })
+}
+
+/// Send `amount_nanos` to the hex encoded public key `recipient`, giving the hex encoded cash note
+/// to be handed to the recipient.
+///
+/// # Safety
+/// `wallet` must be a live wallet, `recipient` a valid NUL-terminated string, `out_cash_note`
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sn_wallet_send(
+    wallet: *mut SnWallet,
+    recipient: *const c_char,
+    amount_nanos: u64,
+    out_cash_note: *mut *mut c_char,
+) -> SnStatus {
+    ffi_call(|| {
+        let wallet = wallet_mut(wallet)?;
+        let recipient = MainPubkey::from_hex(read_str(recipient, "recipient")?)?;
+        let cash_notes =
+            wallet.local_send(vec![(NanoTokens::from(amount_nanos), recipient)], None)?;
+        let cash_note = cash_notes.first().ok_or_else(|| {
+            FfiError::InvalidArgument("the transfer created no cash note".to_string())
+        })?;
+        write_out(
+            out_cash_note,
+            into_c_string(cash_note.to_hex()?)?,
+            "out_cash_note",
+        )
+    })
+}
+
+/// Deposit the hex encoded cash note received by the wallet.
+///
+/// # Safety
+/// `wallet` must be a live wallet, `cash_note` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sn_wallet_deposit(
+    wallet: *mut SnWallet,
+    cash_note: *const c_char,
+) -> SnStatus {
+    ffi_call(|| {
+        let wallet = wallet_mut(wallet)?;
+        let cash_note = CashNote::from_hex(read_str(cash_note, "cash_note")?)?;
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::WalletBuilder;
+
+    fn c_string(value: &str) -> CString {
+        CString::new(value).expect("no NUL byte")
+    }
+
+    #[test]
+    fn wallets_should_send_and_deposit_through_the_c_abi() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let sender_dir = dir.path().join("sender");
+        let _ = WalletBuilder::with_balance(NanoTokens::from(100)).build(&sender_dir)?;
+
+        unsafe {
+            let mut sender = ptr::null_mut();
+            let sender_dir = c_string(&sender_dir.to_string_lossy());
+            assert_eq!(
+                sn_wallet_load(sender_dir.as_ptr(), &mut sender),
+                SnStatus::Ok
+            );
+
+            let mut receiver = ptr::null_mut();
+            let receiver_dir = c_string(&dir.path().join("receiver").to_string_lossy());
+            assert_eq!(
+                sn_wallet_create(receiver_dir.as_ptr(), ptr::null(), &mut receiver),
+                SnStatus::Ok
+            );
+            let mut address = ptr::null_mut();
+            assert_eq!(sn_wallet_address(receiver, &mut address), SnStatus::Ok);
+
+            let mut cash_note = ptr::null_mut();
+            assert_eq!(
+                sn_wallet_send(sender, address, 40, &mut cash_note),
+                SnStatus::Ok
+            );
+            assert_eq!(sn_wallet_deposit(receiver, cash_note), SnStatus::Ok);
+
+            let mut balance = 0;
+            assert_eq!(sn_wallet_balance(sender, &mut balance), SnStatus::Ok);
+            assert_eq!(balance, 60);
+            assert_eq!(sn_wallet_balance(receiver, &mut balance), SnStatus::Ok);
+            assert_eq!(balance, 40);
+
+            assert_eq!(
+                sn_wallet_send(sender, ptr::null(), 1, &mut cash_note),
+                SnStatus::NullPointer
+            );
+            assert!(!sn_last_error().is_null());
+
+            sn_string_free(cash_note);
+            sn_string_free(address);
+            sn_wallet_free(sender);
+            sn_wallet_free(receiver);
+        }
+        Ok(())
+    }
+}
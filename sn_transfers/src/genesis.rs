@@ -18,7 +18,7 @@ use lazy_static::lazy_static;
 use std::{
     collections::{BTreeMap, BTreeSet},
     fmt::Debug,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use thiserror::Error;
 
@@ -77,8 +77,9 @@ pub enum Error {
 lazy_static! {
     pub static ref GENESIS_PK: MainPubkey = {
         let compile_time_key = option_env!("GENESIS_PK").unwrap_or(DEFAULT_LIVE_GENESIS_PK);
-        let runtime_key =
-            std::env::var("GENESIS_PK").unwrap_or_else(|_| compile_time_key.to_string());
+        let runtime_key = crate::network_keys::network_key(|keys| keys.genesis_pk)
+            .or_else(|| std::env::var("GENESIS_PK").ok())
+            .unwrap_or_else(|| compile_time_key.to_string());
 
         if runtime_key == DEFAULT_LIVE_GENESIS_PK {
             warn!("USING DEFAULT GENESIS SK (9934c2) FOR TESTING PURPOSES! EXPECTING PAIRED SK (23746b) TO BE USED!");
@@ -150,12 +151,18 @@ pub fn is_genesis_spend(spend: &SignedSpend) -> bool {
 }
 
 pub fn load_genesis_wallet() -> Result<HotWallet, Error> {
-    info!("Loading genesis...");
-    if let Ok(wallet) = get_existing_genesis_wallet() {
+    load_genesis_wallet_from(&get_genesis_dir())
+}
+
+/// Load the genesis wallet from `root_dir`, creating it with the genesis CashNote if there is none.
+/// This is useful to keep the genesis wallet of a test network apart from the default one.
+pub fn load_genesis_wallet_from(root_dir: &Path) -> Result<HotWallet, Error> {
+    info!("Loading genesis from {root_dir:?}...");
+    if let Ok(wallet) = get_existing_genesis_wallet(root_dir) {
         return Ok(wallet);
     }
 
-    let mut genesis_wallet = create_genesis_wallet();
+    let mut genesis_wallet = create_genesis_wallet(root_dir);
 
     info!(
         "Depositing genesis CashNote: {:?}",
@@ -172,22 +179,19 @@ pub fn load_genesis_wallet() -> Result<HotWallet, Error> {
     Ok(genesis_wallet)
 }
 
-fn create_genesis_wallet() -> HotWallet {
-    let root_dir = get_genesis_dir();
+fn create_genesis_wallet(root_dir: &Path) -> HotWallet {
     let wallet_dir = root_dir.join("wallet");
     std::fs::create_dir_all(&wallet_dir).expect("Genesis wallet path to be successfully created.");
 
     crate::wallet::store_new_keypair(&wallet_dir, &get_genesis_sk(), None)
         .expect("Genesis key shall be successfully stored.");
 
-    HotWallet::load_from(&root_dir)
+    HotWallet::load_from(root_dir)
         .expect("Faucet wallet (after genesis) shall be created successfully.")
 }
 
-fn get_existing_genesis_wallet() -> WalletResult<HotWallet> {
-    let root_dir = get_genesis_dir();
-
-    let mut wallet = HotWallet::load_from(&root_dir)?;
+fn get_existing_genesis_wallet(root_dir: &Path) -> WalletResult<HotWallet> {
+    let mut wallet = HotWallet::load_from(root_dir)?;
     wallet.try_load_cash_notes()?;
 
     Ok(wallet)
@@ -10,9 +10,21 @@
 extern crate tracing;
 
 mod cashnotes;
+pub mod core;
 mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
 mod genesis;
+#[cfg(feature = "std")]
+mod network_keys;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_vectors;
 mod transfers;
+pub mod verification;
+#[cfg(feature = "std")]
 mod wallet;
 
 /// Types used in the public API
@@ -22,129 +34,57 @@ pub use cashnotes::{
 };
 pub use error::{Result, TransferError};
 /// Utilities exposed
+#[cfg(feature = "std")]
 pub use genesis::{
     calculate_royalties_fee, create_first_cash_note_from_key, get_faucet_data_dir, get_genesis_sk,
-    is_genesis_spend, load_genesis_wallet, Error as GenesisError, GENESIS_CASHNOTE,
-    GENESIS_INPUT_DERIVATION_INDEX, GENESIS_OUTPUT_DERIVATION_INDEX, GENESIS_PK,
+    is_genesis_spend, load_genesis_wallet, load_genesis_wallet_from, Error as GenesisError,
+    GENESIS_CASHNOTE, GENESIS_INPUT_DERIVATION_INDEX, GENESIS_OUTPUT_DERIVATION_INDEX, GENESIS_PK,
     GENESIS_SPEND_UNIQUE_KEY, TOTAL_SUPPLY,
 };
-pub use transfers::{CashNoteRedemption, SignedTransaction, Transfer, UnsignedTransaction};
+#[cfg(feature = "std")]
+pub use network_keys::{
+    set_network_keys, NetworkKeys, DEFAULT_NETWORK_ROYALTIES_PK, DEFAULT_PAYMENT_FORWARD_SK,
+    FOUNDATION_PK, NETWORK_ROYALTIES_PK, PAYMENT_FORWARD_PK,
+};
+pub use transfers::{
+    CashNoteRedemption, SignedTransaction, Transfer, UnsignedTransaction, WalletSigner,
+};
+#[cfg(feature = "std")]
 pub use wallet::{
-    bls_secret_from_hex, wallet_lockfile_name, Error as WalletError, HotWallet, Payment,
-    PaymentQuote, QuotingMetrics, Result as WalletResult, WalletApi, WatchOnlyWallet,
-    QUOTE_EXPIRATION_SECS, WALLET_DIR_NAME,
+    bls_secret_from_hex, verify_message, wallet_lockfile_name, AddressBook, ApprovalRequest,
+    ApprovalResponse, ArchivedCashNote, BranchAndBound, CashNoteIndex, CashNoteMetadata,
+    CashNoteStatus, CoinSelection, DerivationIndexRegistry, DerivationPurpose, Disbursement,
+    DisbursementPlan, Error as WalletError, FileWalletStore, HandedOutIndex, History, HistoryEntry,
+    HistoryFilter, HistoryKind, HotWallet, LargestFirst, MemoryWalletStore, Payment,
+    PaymentDetails, PaymentQuote, PaymentRequest, QuotesByAddress, QuotingMetrics, Recipient,
+    ResendPolicy, Result as WalletResult, SentTranche, SmallestFirst, SpendApprovalConfig,
+    SpentArchive, TranchePlan, TransactionLimits, Treasury, UnconfirmedSpendMetadata, WalletApi,
+    WalletExclusiveAccess, WalletFileFormat, WalletHooks, WalletManager, WalletSnapshot,
+    WalletStore, WatchOnlyWallet, DEFAULT_APPROVAL_TIMEOUT, DEFAULT_MAX_TX_INPUTS,
+    DEFAULT_MAX_TX_OUTPUTS, DEFAULT_WALLET_NAME, NAMED_WALLETS_DIR_NAME, PAYMENT_URI_SCHEME,
+    QUOTE_EXPIRATION_SECS, WALLET_BACKUP_VERSION, WALLET_DIR_NAME, WALLET_FILE_VERSION,
 };
-
-use bls::SecretKey;
-use lazy_static::lazy_static;
-
-/// The following PKs shall be updated to match its correspondent SKs before the formal release
-///
-/// Foundation wallet public key (used to receive initial disbursment from the genesis wallet)
-const DEFAULT_FOUNDATION_PK_STR: &str = "8f73b97377f30bed96df1c92daf9f21b4a82c862615439fab8095e68860a5d0dff9f97dba5aef503a26c065e5cb3c7ca"; // DevSkim: ignore DS173237
-/// Public key where network royalties payments are expected to be made to.
-const DEFAULT_NETWORK_ROYALTIES_STR: &str = "b4243ec9ceaec374ef992684cd911b209758c5de53d1e406b395bc37ebc8ce50e68755ea6d32da480ae927e1af4ddadb"; // DevSkim: ignore DS173237
-/// Public key where payment forward to be targeted.
-const DEFAULT_PAYMENT_FORWARD_STR: &str = "a585839f0502713a0ed6a327f3bd0c301f9e8fe298c93dd00ed7869d8e6804244f0d3014e90df45cd344a7ccd702865c"; // DevSkim: ignore DS173237
-/// Default secrect key where payment forward to be targeted, for backward compatible purpose only.
-const DEFAULT_PAYMENT_FORWARD_SK_STR: &str =
-    "49113d2083f57a976076adbe85decb75115820de1e6e74b47e0429338cef124a"; // DevSkim: ignore DS173237
-
-lazy_static! {
-    pub static ref FOUNDATION_PK: MainPubkey = {
-        let compile_time_key = option_env!("FOUNDATION_PK").unwrap_or(DEFAULT_FOUNDATION_PK_STR);
-        let runtime_key =
-            std::env::var("FOUNDATION_PK").unwrap_or_else(|_| compile_time_key.to_string());
-
-        if runtime_key == DEFAULT_FOUNDATION_PK_STR {
-            warn!("Using default FOUNDATION_PK: {}", DEFAULT_FOUNDATION_PK_STR);
-        } else if runtime_key == compile_time_key {
-            warn!("Using compile-time FOUNDATION_PK: {}", compile_time_key);
-        } else {
-            warn!("Overridden by runtime FOUNDATION_PK: {}", runtime_key);
-        }
-
-        match MainPubkey::from_hex(&runtime_key) {
-            Ok(pk) => pk,
-            Err(err) => panic!("Failed to parse foundation PK: {err:?}"),
-        }
-    };
-}
-
-lazy_static! {
-    pub static ref NETWORK_ROYALTIES_PK: MainPubkey = {
-        let compile_time_key =
-            option_env!("NETWORK_ROYALTIES_PK").unwrap_or(DEFAULT_NETWORK_ROYALTIES_STR);
-        let runtime_key =
-            std::env::var("NETWORK_ROYALTIES_PK").unwrap_or_else(|_| compile_time_key.to_string());
-
-        if runtime_key == DEFAULT_NETWORK_ROYALTIES_STR {
-            warn!(
-                "Using default NETWORK_ROYALTIES_PK: {}",
-                DEFAULT_NETWORK_ROYALTIES_STR
-            );
-        } else if runtime_key == compile_time_key {
-            warn!(
-                "Using compile-time NETWORK_ROYALTIES_PK: {}",
-                compile_time_key
-            );
-        } else {
-            warn!(
-                "Overridden by runtime NETWORK_ROYALTIES_PK: {}",
-                runtime_key
-            );
-        }
-
-        match MainPubkey::from_hex(&runtime_key) {
-            Ok(pk) => pk,
-            Err(err) => panic!("Failed to parse network royalties PK: {err:?}"),
-        }
-    };
-    pub static ref DEFAULT_NETWORK_ROYALTIES_PK: MainPubkey = {
-        match MainPubkey::from_hex(DEFAULT_NETWORK_ROYALTIES_STR) {
-            Ok(pk) => pk,
-            Err(err) => panic!("Failed to parse default network royalties PK: {err:?}"),
-        }
-    };
-}
-
-lazy_static! {
-    pub static ref PAYMENT_FORWARD_PK: MainPubkey = {
-        let compile_time_key =
-            option_env!("PAYMENT_FORWARD_PK").unwrap_or(DEFAULT_PAYMENT_FORWARD_STR);
-        let runtime_key =
-            std::env::var("PAYMENT_FORWARD_PK").unwrap_or_else(|_| compile_time_key.to_string());
-
-        if runtime_key == DEFAULT_PAYMENT_FORWARD_STR {
-            warn!(
-                "Using default PAYMENT_FORWARD_PK: {}",
-                DEFAULT_PAYMENT_FORWARD_STR
-            );
-        } else if runtime_key == compile_time_key {
-            warn!(
-                "Using compile-time PAYMENT_FORWARD_PK: {}",
-                compile_time_key
-            );
-        } else {
-            warn!("Overridden by runtime PAYMENT_FORWARD_PK: {}", runtime_key);
-        }
-
-        match MainPubkey::from_hex(&runtime_key) {
-            Ok(pk) => pk,
-            Err(err) => panic!("Failed to parse payment forward PK: {err:?}"),
-        }
-    };
-    pub static ref DEFAULT_PAYMENT_FORWARD_SK: SecretKey = {
-        match SecretKey::from_hex(DEFAULT_PAYMENT_FORWARD_SK_STR) {
-            Ok(sk) => sk,
-            Err(err) => panic!("Failed to parse default payment forward SK: {err:?}"),
-        }
-    };
-}
+#[cfg(all(unix, feature = "std"))]
+pub use wallet::{ApprovalListener, PendingApproval};
 
 // re-export crates used in our public API
 pub use bls::{self, rand, Ciphertext, Signature};
 
+/// The maximum size of the bytes read by the `try_from_bytes` constructors, the maximum size of a
+/// record on the Network.
+pub const MAX_SERIALIZED_SIZE: usize = 5 * 1024 * 1024;
+
+/// Reject the bytes from the wire or the disk that are too large to be deserialized.
+pub(crate) fn check_serialized_size(bytes: &[u8]) -> Result<()> {
+    if bytes.len() > MAX_SERIALIZED_SIZE {
+        return Err(TransferError::SerializedSizeTooLarge {
+            size: bytes.len(),
+            max: MAX_SERIALIZED_SIZE,
+        });
+    }
+    Ok(())
+}
+
 /// This is a helper module to make it a bit easier
 /// and regular for API callers to instantiate
 /// an Rng when calling sn_transfers methods that require
@@ -0,0 +1,146 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The keys of the network: genesis aside, the ones payments are made to, read from the build,
+//! the environment or set at runtime.
+
+use crate::MainPubkey;
+use bls::SecretKey;
+use lazy_static::lazy_static;
+use std::sync::OnceLock;
+
+/// The following PKs shall be updated to match its correspondent SKs before the formal release
+///
+/// Foundation wallet public key (used to receive initial disbursment from the genesis wallet)
+const DEFAULT_FOUNDATION_PK_STR: &str = "8f73b97377f30bed96df1c92daf9f21b4a82c862615439fab8095e68860a5d0dff9f97dba5aef503a26c065e5cb3c7ca"; // DevSkim: ignore DS173237
+/// Public key where network royalties payments are expected to be made to.
+const DEFAULT_NETWORK_ROYALTIES_STR: &str = "b4243ec9ceaec374ef992684cd911b209758c5de53d1e406b395bc37ebc8ce50e68755ea6d32da480ae927e1af4ddadb"; // DevSkim: ignore DS173237
+/// Public key where payment forward to be targeted.
+const DEFAULT_PAYMENT_FORWARD_STR: &str = "a585839f0502713a0ed6a327f3bd0c301f9e8fe298c93dd00ed7869d8e6804244f0d3014e90df45cd344a7ccd702865c"; // DevSkim: ignore DS173237
+/// Default secrect key where payment forward to be targeted, for backward compatible purpose only.
+const DEFAULT_PAYMENT_FORWARD_SK_STR: &str =
+    "49113d2083f57a976076adbe85decb75115820de1e6e74b47e0429338cef124a"; // DevSkim: ignore DS173237
+
+/// The network keys to run with instead of the ones of the env or of the build, e.g. the ones of
+/// the network a client is run against.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkKeys {
+    pub genesis_pk: Option<MainPubkey>,
+    pub foundation_pk: Option<MainPubkey>,
+    pub network_royalties_pk: Option<MainPubkey>,
+    pub payment_forward_pk: Option<MainPubkey>,
+}
+
+static NETWORK_KEYS: OnceLock<NetworkKeys> = OnceLock::new();
+
+/// Run with the given network keys, which take precedence over the env variables of the same
+/// names. They have to be set before any network key is first read: `false` is returned, leaving
+/// the keys as they were, otherwise.
+pub fn set_network_keys(keys: NetworkKeys) -> bool {
+    NETWORK_KEYS.set(keys).is_ok()
+}
+
+/// The hex of the key set by `set_network_keys`, if any.
+pub(crate) fn network_key(key: impl FnOnce(&NetworkKeys) -> Option<MainPubkey>) -> Option<String> {
+    key(NETWORK_KEYS.get_or_init(NetworkKeys::default)).map(|pk| pk.to_hex())
+}
+
+lazy_static! {
+    pub static ref FOUNDATION_PK: MainPubkey = {
+        let compile_time_key = option_env!("FOUNDATION_PK").unwrap_or(DEFAULT_FOUNDATION_PK_STR);
+        let runtime_key = network_key(|keys| keys.foundation_pk)
+            .or_else(|| std::env::var("FOUNDATION_PK").ok())
+            .unwrap_or_else(|| compile_time_key.to_string());
+
+        if runtime_key == DEFAULT_FOUNDATION_PK_STR {
+            warn!("Using default FOUNDATION_PK: {}", DEFAULT_FOUNDATION_PK_STR);
+        } else if runtime_key == compile_time_key {
+            warn!("Using compile-time FOUNDATION_PK: {}", compile_time_key);
+        } else {
+            warn!("Overridden by runtime FOUNDATION_PK: {}", runtime_key);
+        }
+
+        match MainPubkey::from_hex(&runtime_key) {
+            Ok(pk) => pk,
+            Err(err) => panic!("Failed to parse foundation PK: {err:?}"),
+        }
+    };
+}
+
+lazy_static! {
+    pub static ref NETWORK_ROYALTIES_PK: MainPubkey = {
+        let compile_time_key =
+            option_env!("NETWORK_ROYALTIES_PK").unwrap_or(DEFAULT_NETWORK_ROYALTIES_STR);
+        let runtime_key = network_key(|keys| keys.network_royalties_pk)
+            .or_else(|| std::env::var("NETWORK_ROYALTIES_PK").ok())
+            .unwrap_or_else(|| compile_time_key.to_string());
+
+        if runtime_key == DEFAULT_NETWORK_ROYALTIES_STR {
+            warn!(
+                "Using default NETWORK_ROYALTIES_PK: {}",
+                DEFAULT_NETWORK_ROYALTIES_STR
+            );
+        } else if runtime_key == compile_time_key {
+            warn!(
+                "Using compile-time NETWORK_ROYALTIES_PK: {}",
+                compile_time_key
+            );
+        } else {
+            warn!(
+                "Overridden by runtime NETWORK_ROYALTIES_PK: {}",
+                runtime_key
+            );
+        }
+
+        match MainPubkey::from_hex(&runtime_key) {
+            Ok(pk) => pk,
+            Err(err) => panic!("Failed to parse network royalties PK: {err:?}"),
+        }
+    };
+    pub static ref DEFAULT_NETWORK_ROYALTIES_PK: MainPubkey = {
+        match MainPubkey::from_hex(DEFAULT_NETWORK_ROYALTIES_STR) {
+            Ok(pk) => pk,
+            Err(err) => panic!("Failed to parse default network royalties PK: {err:?}"),
+        }
+    };
+}
+
+lazy_static! {
+    pub static ref PAYMENT_FORWARD_PK: MainPubkey = {
+        let compile_time_key =
+            option_env!("PAYMENT_FORWARD_PK").unwrap_or(DEFAULT_PAYMENT_FORWARD_STR);
+        let runtime_key = network_key(|keys| keys.payment_forward_pk)
+            .or_else(|| std::env::var("PAYMENT_FORWARD_PK").ok())
+            .unwrap_or_else(|| compile_time_key.to_string());
+
+        if runtime_key == DEFAULT_PAYMENT_FORWARD_STR {
+            warn!(
+                "Using default PAYMENT_FORWARD_PK: {}",
+                DEFAULT_PAYMENT_FORWARD_STR
+            );
+        } else if runtime_key == compile_time_key {
+            warn!(
+                "Using compile-time PAYMENT_FORWARD_PK: {}",
+                compile_time_key
+            );
+        } else {
+            warn!("Overridden by runtime PAYMENT_FORWARD_PK: {}", runtime_key);
+        }
+
+        match MainPubkey::from_hex(&runtime_key) {
+            Ok(pk) => pk,
+            Err(err) => panic!("Failed to parse payment forward PK: {err:?}"),
+        }
+    };
+    pub static ref DEFAULT_PAYMENT_FORWARD_SK: SecretKey = {
+        match SecretKey::from_hex(DEFAULT_PAYMENT_FORWARD_SK_STR) {
+            Ok(sk) => sk,
+            Err(err) => panic!("Failed to parse default payment forward SK: {err:?}"),
+        }
+    };
+}
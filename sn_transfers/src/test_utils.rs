@@ -0,0 +1,134 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Builders of cash notes and wallets for tests.
+//!
+//! The cash notes are given by a fake parent spend, signed by a random key in the same way as the
+//! genesis cash note. They can be spent offline, but are unknown to any Network.
+
+use crate::{
+    wallet::Result as WalletResult, CashNote, DerivationIndex, HotWallet, MainPubkey,
+    MainSecretKey, NanoTokens, SignedSpend, Spend, SpendReason,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+/// A cash note of `amount` owned by `owner`, given by a fake parent spend.
+pub fn fake_cash_note(owner: MainPubkey, amount: NanoTokens) -> CashNote {
+    let mut rng = rand::thread_rng();
+    let input_sk = MainSecretKey::random().derive_key(&DerivationIndex::random(&mut rng));
+    let derivation_index = DerivationIndex::random(&mut rng);
+    let output_pk = owner.new_unique_pubkey(&derivation_index);
+
+    let parent_spend = Spend {
+        unique_pubkey: input_sk.unique_pubkey(),
+        reason: SpendReason::default(),
+        ancestors: BTreeSet::new(),
+        descendants: BTreeMap::from_iter([(output_pk, amount)]),
+        royalties: vec![],
+    };
+    CashNote {
+        parent_spends: BTreeSet::from_iter([SignedSpend::sign(parent_spend, &input_sk)]),
+        main_pubkey: owner,
+        derivation_index,
+    }
+}
+
+/// Builds a hot wallet holding some fake cash notes, e.g.
+/// `WalletBuilder::with_balance(amount).cash_notes(10).build(root_dir)`.
+pub struct WalletBuilder {
+    balance: NanoTokens,
+    cash_notes: u64,
+    key: Option<MainSecretKey>,
+}
+
+impl WalletBuilder {
+    /// A wallet holding `balance` in a single cash note, owned by a random key.
+    pub fn with_balance(balance: NanoTokens) -> Self {
+        Self {
+            balance,
+            cash_notes: 1,
+            key: None,
+        }
+    }
+
+    /// Split the balance into `count` cash notes, the last one getting the remainder.
+    pub fn cash_notes(mut self, count: u64) -> Self {
+        self.cash_notes = count;
+        self
+    }
+
+    /// The key owning the wallet.
+    pub fn key(mut self, key: MainSecretKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Create the wallet under `root_dir` and deposit its cash notes.
+    pub fn build(self, root_dir: &Path) -> WalletResult<HotWallet> {
+        let key = self.key.unwrap_or_else(MainSecretKey::random);
+        let owner = key.main_pubkey();
+        let mut wallet = HotWallet::create_from_key(root_dir, key, None)?;
+
+        let balance = self.balance.as_nano();
+        let cash_notes: Vec<_> = (0..self.cash_notes)
+            .map(|i| {
+                let mut amount = balance / self.cash_notes;
+                if i == self.cash_notes - 1 {
+                    amount += balance % self.cash_notes;
+                }
+                fake_cash_note(owner, NanoTokens::from(amount))
+            })
+            .collect();
+        wallet.deposit_and_store_to_disk(&cash_notes)?;
+        Ok(wallet)
+    }
+}
+
+/// Two wallets of random keys, each holding `balance`, created under `root_dir`.
+pub fn funded_wallet_pair(
+    root_dir: &Path,
+    balance: NanoTokens,
+) -> WalletResult<(HotWallet, HotWallet)> {
+    let first = WalletBuilder::with_balance(balance).build(&root_dir.join("first"))?;
+    let second = WalletBuilder::with_balance(balance).build(&root_dir.join("second"))?;
+    Ok((first, second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    #[test]
+    fn built_wallet_should_hold_its_balance() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000))
+            .cash_notes(3)
+            .build(dir.path())?;
+        assert_eq!(wallet.balance(), NanoTokens::from(1_000));
+        assert_eq!(wallet.available_cash_notes()?.0.len(), 3);
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _ = wallet.local_send(vec![(NanoTokens::from(600), recipient)], None)?;
+        assert_eq!(wallet.balance(), NanoTokens::from(400));
+        Ok(())
+    }
+
+    #[test]
+    fn wallet_pair_should_be_funded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (first, second) = funded_wallet_pair(dir.path(), NanoTokens::from(10))?;
+        assert_ne!(first.address(), second.address());
+        assert_eq!(first.balance(), NanoTokens::from(10));
+        assert_eq!(second.balance(), NanoTokens::from(10));
+        Ok(())
+    }
+}
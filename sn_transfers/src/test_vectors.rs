@@ -0,0 +1,686 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The canonical test vectors of the keys, cash notes, transfers and transactions, for other
+//! implementations, e.g. alternative wallets or the consumers of the C bindings, to prove they
+//! are compatible with this one.
+//!
+//! The vectors are kept in `test_vectors/wallet.json`, frozen: they are the reference, never to
+//! change but along with `TEST_VECTORS_VERSION`. Everything is hex encoded, as by the `to_hex` of
+//! the types, and the amounts are in nanos. Their inputs were drawn from seeded rngs, the
+//! outputs are what this implementation makes of them, except for the encrypted transfers, which
+//! are only to be decrypted since encryption is randomised.
+//!
+//! An implementation is checked against the vectors through the `WalletImplementation` trait,
+//! with `check`, which this crate passes with `SnTransfers`. An implementation in another
+//! language can read the JSON file and do the same.
+
+use crate::{
+    rng, wallet::bls_secret_from_hex, CashNote, CashNoteRedemption, DerivationIndex, MainPubkey,
+    MainSecretKey, NanoTokens, SignedSpend, SignedTransaction, Spend, SpendAddress, SpendReason,
+    Transfer, UniquePubkey,
+};
+use bls::rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The version of the layout and content of the test vectors.
+pub const TEST_VECTORS_VERSION: u32 = 1;
+
+/// The canonical test vectors, as JSON.
+pub const TEST_VECTORS_JSON: &str = include_str!("../test_vectors/wallet.json");
+
+/// The errors of an implementation, as told by it.
+pub type ImplementationResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub version: u32,
+    pub keys: Vec<KeyVector>,
+    pub derivations: Vec<DerivationVector>,
+    pub cash_notes: Vec<CashNoteVector>,
+    pub transfers: Vec<TransferVector>,
+    pub transactions: Vec<TransactionVector>,
+}
+
+/// A main secret key and its main pubkey.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyVector {
+    pub secret_key: String,
+    pub main_pubkey: String,
+}
+
+/// The unique pubkey derived at an index, from the main pubkey as from the main secret key, and
+/// the address of its spend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivationVector {
+    pub secret_key: String,
+    pub main_pubkey: String,
+    pub derivation_index: String,
+    pub unique_pubkey: String,
+    pub spend_address: String,
+}
+
+/// A cash note given by a single parent spend, built as the genesis cash note is: the parent
+/// spend has the key derived from `parent_secret_key` at `parent_derivation_index`, the default
+/// reason, no ancestors, no royalties and the cash note as its only descendant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CashNoteVector {
+    pub parent_secret_key: String,
+    pub parent_derivation_index: String,
+    pub owner: String,
+    pub derivation_index: String,
+    pub value: u64,
+    pub cash_note: String,
+    pub unique_pubkey: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    Encrypted,
+    Unencrypted,
+    NetworkRoyalties,
+}
+
+/// The transfer of a cash note to its owner, and the redemptions its owner gets out of it.
+///
+/// Only the transfers which are not encrypted can be made again identically.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferVector {
+    pub kind: TransferKind,
+    pub cash_note: String,
+    pub recipient_secret_key: String,
+    pub transfer: String,
+    pub redemptions: Vec<RedemptionVector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedemptionVector {
+    pub derivation_index: String,
+    pub parent_spends: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipientVector {
+    pub amount: u64,
+    pub main_pubkey: String,
+    pub derivation_index: String,
+    pub is_royalty: bool,
+}
+
+/// A transaction spending cash notes of `secret_key`, the biggest first, with the default reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionVector {
+    pub secret_key: String,
+    pub inputs: Vec<String>,
+    pub recipients: Vec<RecipientVector>,
+    pub change_to: String,
+    /// The derivation index of the change, drawn at random otherwise
+    pub change_derivation_index: String,
+    pub signed_transaction: String,
+}
+
+/// The operations of a wallet implementation covered by the test vectors, all in hex.
+pub trait WalletImplementation {
+    fn main_pubkey(&self, secret_key: &str) -> ImplementationResult<String>;
+
+    /// The unique pubkey at the index, derived from the main pubkey.
+    fn unique_pubkey(
+        &self,
+        main_pubkey: &str,
+        derivation_index: &str,
+    ) -> ImplementationResult<String>;
+
+    /// The unique pubkey of the secret key derived at the index from the main secret key.
+    fn derived_unique_pubkey(
+        &self,
+        secret_key: &str,
+        derivation_index: &str,
+    ) -> ImplementationResult<String>;
+
+    fn spend_address(&self, unique_pubkey: &str) -> ImplementationResult<String>;
+
+    /// The cash note described by the vector, its expected outputs left aside.
+    fn cash_note(&self, vector: &CashNoteVector) -> ImplementationResult<String>;
+
+    /// The unique pubkey of the cash note and its hash.
+    fn cash_note_ids(&self, cash_note: &str) -> ImplementationResult<(String, String)>;
+
+    /// The transfer of the cash note to its owner, which is not encrypted.
+    fn transfer(&self, cash_note: &str, kind: TransferKind) -> ImplementationResult<String>;
+
+    /// The redemptions of the transfer, decrypted with the secret key if they are encrypted.
+    fn redemptions(
+        &self,
+        transfer: &str,
+        recipient_secret_key: &str,
+    ) -> ImplementationResult<Vec<RedemptionVector>>;
+
+    /// The signed transaction described by the vector, its expected outputs left aside.
+    fn signed_transaction(&self, vector: &TransactionVector) -> ImplementationResult<String>;
+}
+
+/// A difference between what an implementation makes of a vector and the expected output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The vector, e.g. `cash_notes[2].hash`
+    pub vector: String,
+    pub expected: String,
+    /// The output of the implementation, or its error
+    pub got: String,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {}",
+            self.vector, self.expected, self.got
+        )
+    }
+}
+
+/// The canonical test vectors.
+pub fn canonical() -> TestVectors {
+    serde_json::from_str(TEST_VECTORS_JSON).expect("the test vectors to be valid JSON")
+}
+
+/// Check the implementation against every vector, returning the mismatches found, none for a
+/// compatible implementation.
+pub fn check(implementation: &dyn WalletImplementation, vectors: &TestVectors) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+    let mut compare = |vector: String, expected: &str, got: ImplementationResult<String>| {
+        let got = match got {
+            Ok(got) if got.eq_ignore_ascii_case(expected) => return,
+            Ok(got) => got,
+            Err(err) => format!("the error {err}"),
+        };
+        mismatches.push(Mismatch {
+            vector,
+            expected: expected.to_string(),
+            got,
+        });
+    };
+
+    for (i, key) in vectors.keys.iter().enumerate() {
+        compare(
+            format!("keys[{i}].main_pubkey"),
+            &key.main_pubkey,
+            implementation.main_pubkey(&key.secret_key),
+        );
+    }
+    for (i, derivation) in vectors.derivations.iter().enumerate() {
+        compare(
+            format!("derivations[{i}].unique_pubkey"),
+            &derivation.unique_pubkey,
+            implementation.unique_pubkey(&derivation.main_pubkey, &derivation.derivation_index),
+        );
+        compare(
+            format!("derivations[{i}].unique_pubkey, from the secret key"),
+            &derivation.unique_pubkey,
+            implementation
+                .derived_unique_pubkey(&derivation.secret_key, &derivation.derivation_index),
+        );
+        compare(
+            format!("derivations[{i}].spend_address"),
+            &derivation.spend_address,
+            implementation.spend_address(&derivation.unique_pubkey),
+        );
+    }
+    for (i, cash_note) in vectors.cash_notes.iter().enumerate() {
+        compare(
+            format!("cash_notes[{i}].cash_note"),
+            &cash_note.cash_note,
+            implementation.cash_note(cash_note),
+        );
+        let ids = implementation.cash_note_ids(&cash_note.cash_note);
+        let (unique_pubkey, hash) = match ids {
+            Ok((unique_pubkey, hash)) => (Ok(unique_pubkey), Ok(hash)),
+            Err(err) => (Err(err.to_string().into()), Err(err)),
+        };
+        compare(
+            format!("cash_notes[{i}].unique_pubkey"),
+            &cash_note.unique_pubkey,
+            unique_pubkey,
+        );
+        compare(format!("cash_notes[{i}].hash"), &cash_note.hash, hash);
+    }
+    for (i, transfer) in vectors.transfers.iter().enumerate() {
+        if transfer.kind != TransferKind::Encrypted {
+            compare(
+                format!("transfers[{i}].transfer"),
+                &transfer.transfer,
+                implementation.transfer(&transfer.cash_note, transfer.kind),
+            );
+        }
+        let expected = format!("{:?}", transfer.redemptions);
+        let redemptions = implementation
+            .redemptions(&transfer.transfer, &transfer.recipient_secret_key)
+            .map(|redemptions| format!("{redemptions:?}"));
+        compare(
+            format!("transfers[{i}].redemptions"),
+            &expected,
+            redemptions,
+        );
+    }
+    for (i, transaction) in vectors.transactions.iter().enumerate() {
+        compare(
+            format!("transactions[{i}].signed_transaction"),
+            &transaction.signed_transaction,
+            implementation.signed_transaction(transaction),
+        );
+    }
+    mismatches
+}
+
+/// This implementation, checked against the vectors by its tests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SnTransfers;
+
+impl WalletImplementation for SnTransfers {
+    fn main_pubkey(&self, secret_key: &str) -> ImplementationResult<String> {
+        Ok(main_secret_key(secret_key)?.main_pubkey().to_hex())
+    }
+
+    fn unique_pubkey(
+        &self,
+        main_pubkey: &str,
+        derivation_index: &str,
+    ) -> ImplementationResult<String> {
+        let main_pubkey = MainPubkey::from_hex(main_pubkey)?;
+        Ok(main_pubkey
+            .new_unique_pubkey(&parse_derivation_index(derivation_index)?)
+            .to_hex())
+    }
+
+    fn derived_unique_pubkey(
+        &self,
+        secret_key: &str,
+        derivation_index: &str,
+    ) -> ImplementationResult<String> {
+        let derived_key =
+            main_secret_key(secret_key)?.derive_key(&parse_derivation_index(derivation_index)?);
+        Ok(derived_key.unique_pubkey().to_hex())
+    }
+
+    fn spend_address(&self, unique_pubkey: &str) -> ImplementationResult<String> {
+        let unique_pubkey = UniquePubkey::from_hex(unique_pubkey)?;
+        Ok(SpendAddress::from_unique_pubkey(&unique_pubkey).to_hex())
+    }
+
+    fn cash_note(&self, vector: &CashNoteVector) -> ImplementationResult<String> {
+        let cash_note = build_cash_note(
+            &main_secret_key(&vector.parent_secret_key)?,
+            &parse_derivation_index(&vector.parent_derivation_index)?,
+            MainPubkey::from_hex(&vector.owner)?,
+            parse_derivation_index(&vector.derivation_index)?,
+            NanoTokens::from(vector.value),
+        );
+        Ok(cash_note.to_hex()?)
+    }
+
+    fn cash_note_ids(&self, cash_note: &str) -> ImplementationResult<(String, String)> {
+        let cash_note = CashNote::from_hex(cash_note)?;
+        Ok((
+            cash_note.unique_pubkey().to_hex(),
+            cash_note.hash().to_hex(),
+        ))
+    }
+
+    fn transfer(&self, cash_note: &str, kind: TransferKind) -> ImplementationResult<String> {
+        let cash_note = CashNote::from_hex(cash_note)?;
+        let transfer = match kind {
+            TransferKind::Encrypted => {
+                return Err("an encrypted transfer cannot be made again identically".into())
+            }
+            TransferKind::Unencrypted => Transfer::unencrypted_transfer_from_cash_note(&cash_note),
+            TransferKind::NetworkRoyalties => {
+                Transfer::royalties_transfer_from_cash_note(&cash_note)?
+            }
+        };
+        Ok(transfer.to_hex()?)
+    }
+
+    fn redemptions(
+        &self,
+        transfer: &str,
+        recipient_secret_key: &str,
+    ) -> ImplementationResult<Vec<RedemptionVector>> {
+        let transfer = Transfer::from_hex(transfer)?;
+        let redemptions = transfer.cashnote_redemptions(&main_secret_key(recipient_secret_key)?)?;
+        Ok(redemptions.iter().map(redemption_vector).collect())
+    }
+
+    fn signed_transaction(&self, vector: &TransactionVector) -> ImplementationResult<String> {
+        let inputs = vector
+            .inputs
+            .iter()
+            .map(|input| CashNote::from_hex(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let recipients = vector
+            .recipients
+            .iter()
+            .map(|recipient| {
+                Ok((
+                    NanoTokens::from(recipient.amount),
+                    MainPubkey::from_hex(&recipient.main_pubkey)?,
+                    parse_derivation_index(&recipient.derivation_index)?,
+                    recipient.is_royalty,
+                ))
+            })
+            .collect::<ImplementationResult<Vec<_>>>()?;
+        let signed_tx = SignedTransaction::new_with_rng(
+            inputs,
+            recipients,
+            MainPubkey::from_hex(&vector.change_to)?,
+            SpendReason::default(),
+            &main_secret_key(&vector.secret_key)?,
+            &mut FixedRng(parse_derivation_index(&vector.change_derivation_index)?.0),
+        )?;
+        Ok(signed_tx.to_hex()?)
+    }
+}
+
+/// Generate new test vectors, from the inputs drawn from the seeded rngs, with this
+/// implementation. The encrypted transfers differ on each call.
+///
+/// Only meant for a new `TEST_VECTORS_VERSION`: the vectors of a version are never to change.
+pub fn generate() -> crate::Result<TestVectors> {
+    let mut rng = rng::from_vec(b"sn_transfers test vectors");
+    let secret_keys: Vec<MainSecretKey> = (0..3)
+        .map(|_| MainSecretKey::random_from_rng(&mut rng))
+        .collect();
+    let indexes: Vec<DerivationIndex> = [DerivationIndex([0; 32]), DerivationIndex([0xff; 32])]
+        .into_iter()
+        .chain((0..2).map(|_| DerivationIndex::random(&mut rng)))
+        .collect();
+
+    let keys = secret_keys
+        .iter()
+        .map(|secret_key| KeyVector {
+            secret_key: secret_key_hex(secret_key),
+            main_pubkey: secret_key.main_pubkey().to_hex(),
+        })
+        .collect();
+    let derivations = secret_keys
+        .iter()
+        .flat_map(|secret_key| indexes.iter().map(move |index| (secret_key, index)))
+        .map(|(secret_key, index)| {
+            let unique_pubkey = secret_key.main_pubkey().new_unique_pubkey(index);
+            DerivationVector {
+                secret_key: secret_key_hex(secret_key),
+                main_pubkey: secret_key.main_pubkey().to_hex(),
+                derivation_index: hex::encode(index.0),
+                unique_pubkey: unique_pubkey.to_hex(),
+                spend_address: SpendAddress::from_unique_pubkey(&unique_pubkey).to_hex(),
+            }
+        })
+        .collect();
+
+    let values = [1, 1_000_000_000, crate::TOTAL_SUPPLY / 1_000];
+    let cash_notes: Vec<(CashNote, CashNoteVector)> = values
+        .iter()
+        .zip(&secret_keys)
+        .enumerate()
+        .map(|(i, (value, owner))| {
+            let parent = &secret_keys[(i + 1) % secret_keys.len()];
+            let parent_index = &indexes[(i + 1) % indexes.len()];
+            let index = &indexes[i % indexes.len()];
+            let cash_note = build_cash_note(
+                parent,
+                parent_index,
+                owner.main_pubkey(),
+                *index,
+                NanoTokens::from(*value),
+            );
+            let vector = CashNoteVector {
+                parent_secret_key: secret_key_hex(parent),
+                parent_derivation_index: hex::encode(parent_index.0),
+                owner: owner.main_pubkey().to_hex(),
+                derivation_index: hex::encode(index.0),
+                value: *value,
+                cash_note: cash_note.to_hex()?,
+                unique_pubkey: cash_note.unique_pubkey().to_hex(),
+                hash: cash_note.hash().to_hex(),
+            };
+            Ok((cash_note, vector))
+        })
+        .collect::<crate::Result<_>>()?;
+
+    let mut transfers = vec![];
+    for ((cash_note, _), owner) in cash_notes.iter().zip(&secret_keys) {
+        for (kind, transfer) in [
+            (
+                TransferKind::Encrypted,
+                Transfer::transfer_from_cash_note(cash_note)?,
+            ),
+            (
+                TransferKind::Unencrypted,
+                Transfer::unencrypted_transfer_from_cash_note(cash_note),
+            ),
+            (
+                TransferKind::NetworkRoyalties,
+                Transfer::royalties_transfer_from_cash_note(cash_note)?,
+            ),
+        ] {
+            transfers.push(TransferVector {
+                kind,
+                cash_note: cash_note.to_hex()?,
+                recipient_secret_key: secret_key_hex(owner),
+                transfer: transfer.to_hex()?,
+                redemptions: transfer
+                    .cashnote_redemptions(owner)?
+                    .iter()
+                    .map(redemption_vector)
+                    .collect(),
+            });
+        }
+    }
+
+    // the biggest cash note, paying the two other keys and a royalty, with the change to its owner
+    let (input, _) = &cash_notes[2];
+    let owner = &secret_keys[2];
+    let recipients = vec![
+        (
+            NanoTokens::from(1_000),
+            secret_keys[0].main_pubkey(),
+            DerivationIndex::random(&mut rng),
+            false,
+        ),
+        (
+            NanoTokens::from(2_000_000),
+            secret_keys[1].main_pubkey(),
+            DerivationIndex::random(&mut rng),
+            false,
+        ),
+        (
+            NanoTokens::from(15),
+            secret_keys[1].main_pubkey(),
+            DerivationIndex::random(&mut rng),
+            true,
+        ),
+    ];
+    let change_derivation_index = DerivationIndex::random(&mut rng);
+    let signed_tx = SignedTransaction::new_with_rng(
+        vec![input.clone()],
+        recipients.clone(),
+        owner.main_pubkey(),
+        SpendReason::default(),
+        owner,
+        &mut FixedRng(change_derivation_index.0),
+    )?;
+    let transactions = vec![TransactionVector {
+        secret_key: secret_key_hex(owner),
+        inputs: vec![input.to_hex()?],
+        recipients: recipients
+            .iter()
+            .map(
+                |(amount, main_pubkey, derivation_index, is_royalty)| RecipientVector {
+                    amount: amount.as_nano(),
+                    main_pubkey: main_pubkey.to_hex(),
+                    derivation_index: hex::encode(derivation_index.0),
+                    is_royalty: *is_royalty,
+                },
+            )
+            .collect(),
+        change_to: owner.main_pubkey().to_hex(),
+        change_derivation_index: hex::encode(change_derivation_index.0),
+        signed_transaction: signed_tx.to_hex()?,
+    }];
+
+    Ok(TestVectors {
+        version: TEST_VECTORS_VERSION,
+        keys,
+        derivations,
+        cash_notes: cash_notes.into_iter().map(|(_, vector)| vector).collect(),
+        transfers,
+        transactions,
+    })
+}
+
+fn build_cash_note(
+    parent: &MainSecretKey,
+    parent_index: &DerivationIndex,
+    owner: MainPubkey,
+    derivation_index: DerivationIndex,
+    value: NanoTokens,
+) -> CashNote {
+    let parent_key = parent.derive_key(parent_index);
+    let parent_spend = Spend {
+        unique_pubkey: parent_key.unique_pubkey(),
+        reason: SpendReason::default(),
+        ancestors: BTreeSet::new(),
+        descendants: BTreeMap::from_iter([(owner.new_unique_pubkey(&derivation_index), value)]),
+        royalties: vec![],
+    };
+    CashNote {
+        parent_spends: BTreeSet::from_iter([SignedSpend::sign(parent_spend, &parent_key)]),
+        main_pubkey: owner,
+        derivation_index,
+    }
+}
+
+fn redemption_vector(redemption: &CashNoteRedemption) -> RedemptionVector {
+    RedemptionVector {
+        derivation_index: hex::encode(redemption.derivation_index.0),
+        parent_spends: redemption
+            .parent_spends
+            .iter()
+            .map(SpendAddress::to_hex)
+            .collect(),
+    }
+}
+
+fn secret_key_hex(secret_key: &MainSecretKey) -> String {
+    hex::encode(secret_key.secret_key().to_bytes())
+}
+
+fn main_secret_key(hex: &str) -> ImplementationResult<MainSecretKey> {
+    Ok(MainSecretKey::new(bls_secret_from_hex(hex)?))
+}
+
+fn parse_derivation_index(hex: &str) -> ImplementationResult<DerivationIndex> {
+    let bytes: [u8; 32] = hex::decode(hex)?
+        .try_into()
+        .map_err(|_| "a derivation index is of 32 bytes")?;
+    Ok(DerivationIndex(bytes))
+}
+
+/// An rng drawing the given bytes, for the change of a transaction to be derived at the index of
+/// the vector. Never to be used for keys.
+struct FixedRng([u8; 32]);
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for (byte, fixed) in dest.iter_mut().zip(self.0.iter().cycle()) {
+            *byte = *fixed;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), bls::rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sn_transfers_should_match_the_canonical_vectors() {
+        let vectors = canonical();
+        assert_eq!(vectors.version, TEST_VECTORS_VERSION);
+        assert!(!vectors.transfers.is_empty() && !vectors.transactions.is_empty());
+
+        let mismatches = check(&SnTransfers, &vectors);
+        assert!(
+            mismatches.is_empty(),
+            "mismatches: {}",
+            mismatches
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    #[test]
+    fn generation_should_match_the_canonical_vectors_but_for_encryption() -> crate::Result<()> {
+        let mut generated = generate()?;
+        let canonical = canonical();
+        for (generated, canonical) in generated.transfers.iter_mut().zip(&canonical.transfers) {
+            if generated.kind == TransferKind::Encrypted {
+                generated.transfer.clone_from(&canonical.transfer);
+            }
+        }
+        assert_eq!(generated, canonical);
+        Ok(())
+    }
+
+    #[test]
+    fn check_should_tell_the_mismatches() {
+        let mut vectors = canonical();
+        vectors.keys[0].main_pubkey = vectors.keys[1].main_pubkey.clone();
+        vectors.cash_notes[1].value += 1;
+
+        let mismatches = check(&SnTransfers, &vectors);
+        let names: Vec<_> = mismatches.iter().map(|m| m.vector.as_str()).collect();
+        assert_eq!(names, ["keys[0].main_pubkey", "cash_notes[1].cash_note"]);
+    }
+
+    /// Writes new vectors, to be run only for a new `TEST_VECTORS_VERSION`:
+    /// `cargo test -p sn_transfers --lib test_vectors -- --ignored`
+    #[test]
+    #[ignore]
+    fn write_the_test_vectors() -> eyre::Result<()> {
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("test_vectors/wallet.json");
+        std::fs::create_dir_all(path.parent().expect("the file to be in a dir"))?;
+        let mut json = serde_json::to_string_pretty(&generate()?)?;
+        json.push('\n');
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
@@ -7,9 +7,11 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 mod signed_transaction;
+mod signer;
 mod transfer;
 mod unsigned_transaction;
 
 pub use signed_transaction::SignedTransaction;
+pub use signer::WalletSigner;
 pub use transfer::{CashNoteRedemption, Transfer};
 pub use unsigned_transaction::UnsignedTransaction;
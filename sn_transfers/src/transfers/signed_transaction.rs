@@ -9,6 +9,7 @@
 use std::collections::BTreeSet;
 
 use crate::error::Result;
+use crate::rand::{CryptoRng, RngCore};
 use crate::{
     CashNote, DerivationIndex, MainPubkey, MainSecretKey, NanoTokens, SignedSpend, SpendReason,
     TransferError, UnsignedTransaction,
@@ -42,11 +43,32 @@ impl SignedTransaction {
         input_reason_hash: SpendReason,
         main_key: &MainSecretKey,
     ) -> Result<Self> {
-        let unsigned_tx = UnsignedTransaction::new(
+        Self::new_with_rng(
             available_cash_notes,
             recipients,
             change_to,
             input_reason_hash,
+            main_key,
+            &mut crate::rand::thread_rng(),
+        )
+    }
+
+    /// Create a new `SignedTransaction` like `new`, drawing the derivation index of the change
+    /// from the given `rng`.
+    pub fn new_with_rng<R: RngCore + CryptoRng>(
+        available_cash_notes: Vec<CashNote>,
+        recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex, bool)>,
+        change_to: MainPubkey,
+        input_reason_hash: SpendReason,
+        main_key: &MainSecretKey,
+        rng: &mut R,
+    ) -> Result<Self> {
+        let unsigned_tx = UnsignedTransaction::new_with_rng(
+            available_cash_notes,
+            recipients,
+            change_to,
+            input_reason_hash,
+            rng,
         )?;
         let signed_tx = unsigned_tx.sign(main_key)?;
         Ok(signed_tx)
@@ -0,0 +1,110 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The signing of the spends of a wallet, kept apart from the wallet itself so the main key can be
+//! held elsewhere, e.g. in an HSM, by a remote signing service or on an air-gapped device.
+//!
+//! The wallet itself only needs the main pubkey: a `WatchOnlyWallet` builds the transactions,
+//! which any `WalletSigner` of its key can then sign.
+
+use crate::{
+    error::Result, DerivationIndex, MainPubkey, MainSecretKey, SignedSpend, Spend, UniquePubkey,
+};
+
+/// Holds, or has access to, the main key of a wallet, to sign the spends of its cash notes.
+pub trait WalletSigner: Send + Sync {
+    /// The main pubkey of the wallet whose spends are signed.
+    fn main_pubkey(&self) -> MainPubkey;
+
+    /// The public key derived from the main key at the index, i.e. the unique pubkey of the cash
+    /// notes spent with the key derived at that index.
+    fn derive_key(&self, index: &DerivationIndex) -> UniquePubkey;
+
+    /// Sign the spend of the cash note of the main key derived at the index.
+    fn sign_spend(&self, spend: Spend, index: &DerivationIndex) -> Result<SignedSpend>;
+}
+
+/// The main key held in memory, which is how a `HotWallet` signs.
+impl WalletSigner for MainSecretKey {
+    fn main_pubkey(&self) -> MainPubkey {
+        MainSecretKey::main_pubkey(self)
+    }
+
+    fn derive_key(&self, index: &DerivationIndex) -> UniquePubkey {
+        MainSecretKey::derive_key(self, index).unique_pubkey()
+    }
+
+    fn sign_spend(&self, spend: Spend, index: &DerivationIndex) -> Result<SignedSpend> {
+        Ok(SignedSpend::sign(
+            spend,
+            &MainSecretKey::derive_key(self, index),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        genesis::create_first_cash_note_from_key, wallet::Error, NanoTokens, TransferError,
+        WatchOnlyWallet,
+    };
+    use assert_fs::TempDir;
+    use eyre::Result;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Stands for a device holding the key, counting the spends it is asked to sign.
+    struct DeviceSigner {
+        key: MainSecretKey,
+        signed: AtomicUsize,
+    }
+
+    impl WalletSigner for DeviceSigner {
+        fn main_pubkey(&self) -> MainPubkey {
+            self.key.main_pubkey()
+        }
+
+        fn derive_key(&self, index: &DerivationIndex) -> UniquePubkey {
+            WalletSigner::derive_key(&self.key, index)
+        }
+
+        fn sign_spend(&self, spend: Spend, index: &DerivationIndex) -> crate::Result<SignedSpend> {
+            let _ = self.signed.fetch_add(1, Ordering::SeqCst);
+            self.key.sign_spend(spend, index)
+        }
+    }
+
+    #[test]
+    fn watch_only_transactions_should_be_signed_by_the_signer_of_their_key() -> Result<()> {
+        let dir = TempDir::new()?;
+        let key = MainSecretKey::random();
+        let cash_note = create_first_cash_note_from_key(&key)?;
+        let mut wallet = WatchOnlyWallet::new(key.main_pubkey(), dir.path(), Default::default());
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+        let signer = DeviceSigner {
+            key,
+            signed: AtomicUsize::new(0),
+        };
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let unsigned_tx =
+            wallet.build_unsigned_transaction(vec![(NanoTokens::from(100), recipient)], None)?;
+        let signed_tx = wallet.sign_with(unsigned_tx.clone(), &signer)?;
+        assert_eq!(signer.signed.load(Ordering::SeqCst), 1);
+        assert_eq!(signed_tx.spends.len(), 1);
+
+        let other_signer = MainSecretKey::random();
+        assert!(matches!(
+            wallet.sign_with(unsigned_tx, &other_signer),
+            Err(Error::CouldNotSignTransaction(
+                TransferError::MainPubkeyMismatch
+            ))
+        ));
+        Ok(())
+    }
+}
@@ -8,8 +8,8 @@
 
 use crate::{CashNote, Ciphertext, DerivationIndex, MainPubkey, MainSecretKey, SpendAddress};
 
-use rayon::iter::ParallelIterator;
-use rayon::prelude::IntoParallelRefIterator;
+#[cfg(feature = "std")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
@@ -27,6 +27,9 @@ pub enum Transfer {
     /// The network requires a payment as network royalties for storage which nodes can validate
     /// and verify, these CashNoteRedemptions need to be sent to storage nodes as payment proof as well.
     NetworkRoyalties(Vec<CashNoteRedemption>),
+    /// CashNoteRedemptions sent in clear to recipients that cannot decrypt them, such as the nodes
+    /// paying their rewards to an external key, so that they can still verify the payment.
+    Unencrypted(Vec<CashNoteRedemption>),
 }
 
 impl std::fmt::Debug for Transfer {
@@ -35,6 +38,9 @@ impl std::fmt::Debug for Transfer {
             Self::NetworkRoyalties(cn_redemptions) => {
                 write!(f, "Transfer::NetworkRoyalties: {cn_redemptions:?}")
             }
+            Self::Unencrypted(cn_redemptions) => {
+                write!(f, "Transfer::Unencrypted: {cn_redemptions:?}")
+            }
             Self::Encrypted(transfers) => {
                 // Iterate over the transfers and log the hash of each encrypted transfer
                 let hashed: Vec<_> = transfers
@@ -69,11 +75,18 @@ impl Transfer {
     /// This function is used to create a Network Royalties Transfer from a CashNote
     /// can be done offline, and sent to the recipient.
     /// Note that this type of transfer is not encrypted
+    #[cfg(feature = "std")]
     pub(crate) fn royalties_transfer_from_cash_note(cash_note: &CashNote) -> Result<Self> {
         let cnr = CashNoteRedemption::from_cash_note(cash_note);
         Ok(Self::NetworkRoyalties(vec![cnr]))
     }
 
+    /// This function is used to create a Transfer from a CashNote for a recipient that cannot
+    /// decrypt it, see [`Transfer::Unencrypted`].
+    pub fn unencrypted_transfer_from_cash_note(cash_note: &CashNote) -> Self {
+        Self::Unencrypted(vec![CashNoteRedemption::from_cash_note(cash_note)])
+    }
+
     /// Create a new transfer
     /// cashnote_redemptions: List of CashNoteRedemptions to be used for payment
     /// recipient: main Public key (donation key) of the recipient,
@@ -94,25 +107,42 @@ impl Transfer {
     pub fn cashnote_redemptions(&self, sk: &MainSecretKey) -> Result<Vec<CashNoteRedemption>> {
         match self {
             Self::Encrypted(cyphers) => {
+                #[cfg(feature = "std")]
+                let cyphers = cyphers.par_iter(); // Use Rayon's par_iter for parallel processing
+                #[cfg(not(feature = "std"))]
+                let cyphers = cyphers.iter();
                 let cashnote_redemptions: Result<Vec<_>> = cyphers
-                    .par_iter() // Use Rayon's par_iter for parallel processing
                     .map(|cypher| CashNoteRedemption::decrypt(cypher, sk)) // Decrypt each CashNoteRedemption
                     .collect(); // Collect results into a vector
                 let cashnote_redemptions = cashnote_redemptions?; // Propagate error if any
                 Ok(cashnote_redemptions)
             }
-            Self::NetworkRoyalties(cnr) => Ok(cnr.clone()),
+            Self::NetworkRoyalties(cnr) | Self::Unencrypted(cnr) => Ok(cnr.clone()),
         }
     }
 
+    /// Serialize this `Transfer` instance to bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(&self).map_err(|_| TransferError::TransferSerializationFailed)
+    }
+
+    /// Deserializes a `Transfer` from bytes, which may come from anyone.
+    ///
+    /// Malformed or oversized bytes are rejected with an error.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        crate::check_serialized_size(bytes)?;
+        rmp_serde::from_slice(bytes).map_err(|_| TransferError::TransferDeserializationFailed)
+    }
+
     /// Deserializes a `Transfer` represented as a hex string to a `Transfer`.
     pub fn from_hex(hex: &str) -> Result<Self> {
+        if hex.len() > 2 * crate::MAX_SERIALIZED_SIZE {
+            return Err(TransferError::TransferDeserializationFailed);
+        }
         let mut bytes =
             hex::decode(hex).map_err(|_| TransferError::TransferDeserializationFailed)?;
         bytes.reverse();
-        let transfer: Self = rmp_serde::from_slice(&bytes)
-            .map_err(|_| TransferError::TransferDeserializationFailed)?;
-        Ok(transfer)
+        Self::try_from_bytes(&bytes)
     }
 
     /// Serialize this `Transfer` instance to a readable hex string that a human can copy paste
@@ -10,10 +10,11 @@ use std::cmp::min;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
 
+use crate::rand::{CryptoRng, RngCore};
 use crate::UniquePubkey;
 use crate::{
     error::Result, CashNote, DerivationIndex, MainPubkey, MainSecretKey, NanoTokens, SignedSpend,
-    SignedTransaction, Spend, SpendReason, TransferError,
+    SignedTransaction, Spend, SpendReason, TransferError, WalletSigner,
 };
 
 use serde::{Deserialize, Serialize};
@@ -67,6 +68,64 @@ impl UnsignedTransaction {
         recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex, bool)>,
         change_to: MainPubkey,
         input_reason_hash: SpendReason,
+    ) -> Result<Self> {
+        Self::new_with_rng(
+            available_cash_notes,
+            recipients,
+            change_to,
+            input_reason_hash,
+            &mut crate::rand::thread_rng(),
+        )
+    }
+
+    /// Create a new `UnsignedTransaction` like `new`, drawing the derivation index of the change
+    /// from the given `rng`, so the transaction can be reproduced.
+    pub fn new_with_rng<R: RngCore + CryptoRng>(
+        available_cash_notes: Vec<CashNote>,
+        recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex, bool)>,
+        change_to: MainPubkey,
+        input_reason_hash: SpendReason,
+        rng: &mut R,
+    ) -> Result<Self> {
+        // order inputs by value, the biggest being spent first
+        let mut cashnotes_big_to_small = available_cash_notes;
+        cashnotes_big_to_small.sort_by_key(|b| std::cmp::Reverse(b.value()));
+        Self::new_with_ordered_inputs(
+            cashnotes_big_to_small,
+            recipients,
+            change_to,
+            input_reason_hash,
+            rng,
+        )
+    }
+
+    /// Create a new `UnsignedTransaction` like `new_with_rng`, spending the inputs in the given
+    /// order instead of the biggest first, e.g. as picked by a coin selection. The inputs left
+    /// once the recipients are paid are not spent.
+    pub fn new_with_ordered_inputs<R: RngCore + CryptoRng>(
+        inputs: Vec<CashNote>,
+        recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex, bool)>,
+        change_to: MainPubkey,
+        input_reason_hash: SpendReason,
+        rng: &mut R,
+    ) -> Result<Self> {
+        Self::new_with_change_index(
+            inputs,
+            recipients,
+            change_to,
+            DerivationIndex::random(rng),
+            input_reason_hash,
+        )
+    }
+
+    /// Create a new `UnsignedTransaction` like `new_with_ordered_inputs`, the change going to the
+    /// given derivation index, e.g. one handed out by the registry of the wallet.
+    pub fn new_with_change_index(
+        inputs: Vec<CashNote>,
+        recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex, bool)>,
+        change_to: MainPubkey,
+        change_derivation_index: DerivationIndex,
+        input_reason_hash: SpendReason,
     ) -> Result<Self> {
         // check output amounts (reject zeroes and overflowing values)
         let total_output_amount = recipients
@@ -84,7 +143,7 @@ impl UnsignedTransaction {
         }
 
         // check input amounts
-        let total_input_amount = available_cash_notes
+        let total_input_amount = inputs
             .iter()
             .map(|cn| cn.value())
             .try_fold(NanoTokens::zero(), |total, amount| {
@@ -111,11 +170,6 @@ impl UnsignedTransaction {
             })
             .collect();
 
-        // order inputs by value, re const after sorting
-        let mut cashnotes_big_to_small = available_cash_notes;
-        cashnotes_big_to_small.sort_by_key(|b| std::cmp::Reverse(b.value()));
-        let cashnotes_big_to_small = cashnotes_big_to_small;
-
         // distribute value from inputs to output cash notes
         let mut spends = Vec::new();
         let mut change_cn = None;
@@ -125,7 +179,7 @@ impl UnsignedTransaction {
             .map(|(_, amount, _)| amount.as_nano())
             .unwrap_or(0);
         let mut no_more_outputs = false;
-        for input in cashnotes_big_to_small {
+        for input in inputs {
             let input_key = input.unique_pubkey();
             let input_value = input.value();
             let input_ancestors = input
@@ -159,8 +213,6 @@ impl UnsignedTransaction {
                     }
                 } else {
                     // if we run out of outputs, send the rest as change
-                    let rng = &mut rand::thread_rng();
-                    let change_derivation_index = DerivationIndex::random(rng);
                     let change_key = change_to.new_unique_pubkey(&change_derivation_index);
                     donate_to.insert(change_key, NanoTokens::from(input_remaining_value));
 
@@ -206,15 +258,23 @@ impl UnsignedTransaction {
     /// and return the `SignedTransaction`
     /// It is advised to verify the `UnsignedTransaction` before signing if it comes from an external source
     pub fn sign(self, sk: &MainSecretKey) -> Result<SignedTransaction> {
-        // sign the spends
+        self.sign_with(sk)
+    }
+
+    /// Sign the `UnsignedTransaction` with the given signer, e.g. a device holding the key,
+    /// and return the `SignedTransaction`
+    pub fn sign_with(self, signer: &dyn WalletSigner) -> Result<SignedTransaction> {
+        // sign the spends, each with the key of its input
         let signed_spends: BTreeSet<SignedSpend> = self
             .spends
             .iter()
             .map(|(spend, derivation_index)| {
-                let derived_sk = sk.derive_key(derivation_index);
-                SignedSpend::sign(spend.clone(), &derived_sk)
+                if signer.derive_key(derivation_index) != spend.unique_pubkey {
+                    return Err(TransferError::MissingInputKey(spend.unique_pubkey));
+                }
+                signer.sign_spend(spend.clone(), derivation_index)
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         // distribute signed spends to their respective CashNotes
         let change_cashnote = self.change_cashnote_without_spends.map(|mut cn| {
@@ -321,6 +381,75 @@ impl UnsignedTransaction {
         Ok(())
     }
 
+    /// Verify the `UnsignedTransaction` before signing it. Unlike `verify`, the amounts of its
+    /// outputs, which are stripped of their parent spends, are those the spends give them.
+    pub fn verify_for_signing(&self) -> Result<()> {
+        // the amounts of the outputs, which are stripped of the parent spends they come from
+        let mut amounts_by_unique_pubkey = BTreeMap::new();
+        for (spend, _) in &self.spends {
+            for (k, v) in &spend.descendants {
+                amounts_by_unique_pubkey
+                    .entry(*k)
+                    .and_modify(|sum| *sum += v.as_nano())
+                    .or_insert(v.as_nano());
+            }
+        }
+
+        // verify that the tx is balanced, spending to its outputs only
+        let input_sum: u64 = self
+            .spends
+            .iter()
+            .map(|(spend, _)| spend.amount().as_nano())
+            .sum();
+        let output_sum: u64 = self
+            .output_cashnotes_without_spends
+            .iter()
+            .chain(self.change_cashnote_without_spends.iter())
+            .map(|cn| {
+                amounts_by_unique_pubkey
+                    .get(&cn.unique_pubkey())
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .sum();
+        if input_sum != output_sum {
+            return Err(TransferError::InvalidUnsignedTransaction(format!(
+                "Unbalanced transaction: input sum: {input_sum} != output sum {output_sum}"
+            )));
+        }
+
+        // verify that all spends have a unique pubkey
+        let mut unique_pubkeys = BTreeSet::new();
+        for (spend, _) in &self.spends {
+            let u = spend.unique_pubkey;
+            if !unique_pubkeys.insert(u) {
+                return Err(TransferError::InvalidUnsignedTransaction(format!(
+                    "Spends are not unique in this transaction, there are multiple spends for: {u}"
+                )));
+            }
+        }
+
+        // verify that all cash notes have a unique pubkey, distinct from spends, and are spent to
+        for cn in self
+            .output_cashnotes_without_spends
+            .iter()
+            .chain(self.change_cashnote_without_spends.iter())
+        {
+            let u = cn.unique_pubkey();
+            if !unique_pubkeys.insert(u) {
+                return Err(TransferError::InvalidUnsignedTransaction(
+                    format!("Cash note unique pubkeys are not unique in this transaction, there are multiple outputs for: {u}"),
+                ));
+            }
+            if !amounts_by_unique_pubkey.contains_key(&u) {
+                return Err(TransferError::InvalidUnsignedTransaction(format!(
+                    "CashNote {u} is not spent to by any of the spends"
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Return the unique keys of the CashNotes that have been spent along with their amounts
     pub fn spent_unique_keys(&self) -> BTreeSet<(UniquePubkey, NanoTokens)> {
         self.spends
@@ -337,6 +466,23 @@ impl UnsignedTransaction {
             .collect()
     }
 
+    /// Return the main pubkeys the outputs are sent to along with their amounts, leaving out
+    /// the change
+    pub fn outputs(&self) -> Vec<(NanoTokens, MainPubkey)> {
+        // the value of a CashNote comes from its parent spends, which these are stripped of
+        let amounts: BTreeMap<_, _> = self.output_unique_keys().into_iter().collect();
+        self.output_cashnotes_without_spends
+            .iter()
+            .map(|cash_note| {
+                let amount = amounts
+                    .get(&cash_note.unique_pubkey())
+                    .copied()
+                    .unwrap_or(NanoTokens::zero());
+                (amount, *cash_note.main_pubkey())
+            })
+            .collect()
+    }
+
     /// Create a new `UnsignedTransaction` from a hex string
     pub fn from_hex(hex: &str) -> Result<Self> {
         let decoded_hex = hex::decode(hex).map_err(|e| {
@@ -405,6 +551,36 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_unsigned_tx_signed_with_another_key_is_rejected() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let cnr_pk = MainSecretKey::random().main_pubkey();
+        let cnr_di = DerivationIndex::random(&mut rng);
+        let cnr_upk = cnr_pk.new_unique_pubkey(&cnr_di);
+        let spend = SignedSpend::random_spend_to(&mut rng, cnr_upk, 100);
+
+        let available_cash_notes = vec![CashNote {
+            parent_spends: BTreeSet::from_iter([spend]),
+            main_pubkey: cnr_pk,
+            derivation_index: cnr_di,
+        }];
+        let recipients = vec![(
+            NanoTokens::from(1),
+            MainSecretKey::random().main_pubkey(),
+            DerivationIndex::random(&mut rng),
+            false,
+        )];
+        let tx =
+            UnsignedTransaction::new(available_cash_notes, recipients, cnr_pk, Default::default())
+                .expect("UnsignedTransaction creation to succeed");
+
+        assert_eq!(
+            tx.sign(&MainSecretKey::random()),
+            Err(TransferError::MissingInputKey(cnr_upk))
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_unsigned_tx_empty_inputs_is_rejected() -> Result<()> {
         let mut rng = rand::thread_rng();
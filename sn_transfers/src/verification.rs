@@ -0,0 +1,451 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Checks of the invariants of the transfer system.
+//!
+//! These are meant for fuzzers, property tests and auditors: they check the structure of
+//! transactions and spends only, not their signatures nor their presence on the Network.
+
+use crate::{CashNote, SignedSpend, SignedTransaction, UniquePubkey};
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Box<InvariantViolation>>;
+
+/// A broken invariant of the transfer system.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    #[error("The value of the inputs {inputs} differs from the value of the outputs {outputs}")]
+    ValueNotConserved { inputs: u64, outputs: u64 },
+    #[error("The value of the transaction overflows")]
+    ValueOverflow,
+    #[error("A spend gives value to {0}, which is not an output of the transaction")]
+    UnknownOutput(UniquePubkey),
+    #[error("The output {0} has a parent spend which is not part of the transaction")]
+    UnknownParentSpend(UniquePubkey),
+    #[error("The unique pubkey {0} is used more than once")]
+    DuplicateUniquePubkey(UniquePubkey),
+    #[error("The output {0} has no parent spend")]
+    MissingParentSpends(UniquePubkey),
+    #[error("The spend {spend} lists {parent} as an ancestor, but no such parent was given")]
+    MissingParent {
+        spend: UniquePubkey,
+        parent: UniquePubkey,
+    },
+    #[error("The parent {parent} of {spend} is not one of its ancestors")]
+    UnexpectedParent {
+        spend: UniquePubkey,
+        parent: UniquePubkey,
+    },
+    #[error("The parent {parent} of {spend} does not give it any value")]
+    ParentDoesNotReferToSpend {
+        spend: UniquePubkey,
+        parent: UniquePubkey,
+    },
+    #[error("The parent {0} is double spent")]
+    DoubleSpentParent(UniquePubkey),
+    #[error("The spend {spend} of value {value} was given {given} by its parents")]
+    SpendValueMismatch {
+        spend: UniquePubkey,
+        value: u64,
+        given: u64,
+    },
+}
+
+/// Check all the invariants of a transaction: the conservation of value, and the uniqueness of
+/// its keys.
+pub fn check_transaction(tx: &SignedTransaction) -> Result<()> {
+    check_unique_keys(tx)?;
+    check_value_conservation(tx)
+}
+
+/// Check no value is created nor destroyed by the transaction.
+///
+/// The value of its inputs, given away by its spends, must all go to its outputs and change,
+/// which must get their value from these spends only. Every output counts towards the value of
+/// the outputs, an output listed twice being value created.
+pub fn check_value_conservation(tx: &SignedTransaction) -> Result<()> {
+    let output_keys: BTreeSet<UniquePubkey> = outputs(tx).map(CashNote::unique_pubkey).collect();
+
+    let mut inputs_value: u64 = 0;
+    for spend in &tx.spends {
+        for (descendant, amount) in &spend.spend.descendants {
+            if !output_keys.contains(descendant) {
+                return Err(InvariantViolation::UnknownOutput(*descendant).into());
+            }
+            inputs_value = inputs_value
+                .checked_add(amount.as_nano())
+                .ok_or(InvariantViolation::ValueOverflow)?;
+        }
+    }
+
+    let mut outputs_value: u64 = 0;
+    for cash_note in outputs(tx) {
+        let unique_pubkey = cash_note.unique_pubkey();
+        if cash_note.parent_spends.is_empty() {
+            return Err(InvariantViolation::MissingParentSpends(unique_pubkey).into());
+        }
+        if !cash_note.parent_spends.is_subset(&tx.spends) {
+            return Err(InvariantViolation::UnknownParentSpend(unique_pubkey).into());
+        }
+        for parent in &cash_note.parent_spends {
+            let amount = parent.spend.get_output_amount(&unique_pubkey).ok_or(
+                InvariantViolation::ParentDoesNotReferToSpend {
+                    spend: unique_pubkey,
+                    parent: *parent.unique_pubkey(),
+                },
+            )?;
+            outputs_value = outputs_value
+                .checked_add(amount.as_nano())
+                .ok_or(InvariantViolation::ValueOverflow)?;
+        }
+    }
+
+    if inputs_value != outputs_value {
+        return Err(InvariantViolation::ValueNotConserved {
+            inputs: inputs_value,
+            outputs: outputs_value,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Check the spends and outputs of the transaction all have distinct unique pubkeys.
+pub fn check_unique_keys(tx: &SignedTransaction) -> Result<()> {
+    let mut unique_pubkeys = BTreeSet::new();
+    let spent_keys = tx.spends.iter().map(|spend| *spend.unique_pubkey());
+    for unique_pubkey in spent_keys.chain(outputs(tx).map(CashNote::unique_pubkey)) {
+        if !unique_pubkeys.insert(unique_pubkey) {
+            return Err(InvariantViolation::DuplicateUniquePubkey(unique_pubkey).into());
+        }
+    }
+    Ok(())
+}
+
+/// Check a spend is consistent with its parents: they are exactly its ancestors, none of them is
+/// double spent, and together they give it its whole value.
+pub fn check_spend_parents(spend: &SignedSpend, parents: &BTreeSet<SignedSpend>) -> Result<()> {
+    let unique_pubkey = *spend.unique_pubkey();
+
+    let mut parents_by_key: BTreeMap<UniquePubkey, &SignedSpend> = BTreeMap::new();
+    for parent in parents {
+        let parent_key = *parent.unique_pubkey();
+        if parents_by_key.insert(parent_key, parent).is_some() {
+            return Err(InvariantViolation::DoubleSpentParent(parent_key).into());
+        }
+        if !spend.spend.ancestors.contains(&parent_key) {
+            return Err(InvariantViolation::UnexpectedParent {
+                spend: unique_pubkey,
+                parent: parent_key,
+            }
+            .into());
+        }
+    }
+
+    let mut given: u64 = 0;
+    for ancestor in &spend.spend.ancestors {
+        let parent = parents_by_key
+            .get(ancestor)
+            .ok_or(InvariantViolation::MissingParent {
+                spend: unique_pubkey,
+                parent: *ancestor,
+            })?;
+        let amount = parent.spend.get_output_amount(&unique_pubkey).ok_or(
+            InvariantViolation::ParentDoesNotReferToSpend {
+                spend: unique_pubkey,
+                parent: *ancestor,
+            },
+        )?;
+        given = given
+            .checked_add(amount.as_nano())
+            .ok_or(InvariantViolation::ValueOverflow)?;
+    }
+
+    let value = spend.amount().as_nano();
+    if value != given {
+        return Err(InvariantViolation::SpendValueMismatch {
+            spend: unique_pubkey,
+            value,
+            given,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+fn outputs(tx: &SignedTransaction) -> impl Iterator<Item = &CashNote> {
+    tx.output_cashnotes.iter().chain(tx.change_cashnote.iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        genesis::create_first_cash_note_from_key, DerivationIndex, MainSecretKey, NanoTokens,
+        Spend, SpendReason,
+    };
+    use eyre::Result;
+
+    fn transaction(
+        genesis: &CashNote,
+        key: &MainSecretKey,
+        indexes: [DerivationIndex; 2],
+    ) -> Result<SignedTransaction> {
+        let recipient = MainSecretKey::random().main_pubkey();
+        let tx = SignedTransaction::new(
+            vec![genesis.clone()],
+            vec![
+                (NanoTokens::from(100), recipient, indexes[0], false),
+                (NanoTokens::from(200), recipient, indexes[1], false),
+            ],
+            key.main_pubkey(),
+            SpendReason::default(),
+            key,
+        )?;
+        Ok(tx)
+    }
+
+    /// The genesis cash note and a valid transaction spending it.
+    fn genesis_and_transaction() -> Result<(CashNote, SignedTransaction)> {
+        let mut rng = rand::thread_rng();
+        let key = MainSecretKey::random();
+        let genesis = create_first_cash_note_from_key(&key)?;
+        let indexes = [
+            DerivationIndex::random(&mut rng),
+            DerivationIndex::random(&mut rng),
+        ];
+        let tx = transaction(&genesis, &key, indexes)?;
+        Ok((genesis, tx))
+    }
+
+    /// A copy of the spend, tampered with.
+    fn tampered(spend: &SignedSpend, tamper: impl FnOnce(&mut Spend)) -> SignedSpend {
+        let mut spend = spend.clone();
+        tamper(&mut spend.spend);
+        spend
+    }
+
+    /// Tamper with the spend of the transaction, consistently with the parents of its outputs.
+    fn tamper_spend(tx: &mut SignedTransaction, tamper: impl FnOnce(&mut Spend)) {
+        let spend = tampered(tx.spends.first().expect("a spend"), tamper);
+        tx.spends = BTreeSet::from([spend.clone()]);
+        for cash_note in tx
+            .output_cashnotes
+            .iter_mut()
+            .chain(tx.change_cashnote.iter_mut())
+        {
+            cash_note.parent_spends = BTreeSet::from([spend.clone()]);
+        }
+    }
+
+    fn violation(result: super::Result<()>) -> Option<InvariantViolation> {
+        result.err().map(|violation| *violation)
+    }
+
+    #[test]
+    fn valid_transaction_should_hold_the_invariants() -> Result<()> {
+        let (genesis, tx) = genesis_and_transaction()?;
+
+        check_transaction(&tx)?;
+        for spend in &tx.spends {
+            check_spend_parents(spend, &genesis.parent_spends)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn output_listed_twice_should_not_conserve_value() -> Result<()> {
+        let (_, mut tx) = genesis_and_transaction()?;
+        tx.output_cashnotes.push(tx.output_cashnotes[0].clone());
+
+        assert_eq!(
+            violation(check_value_conservation(&tx)),
+            Some(InvariantViolation::ValueNotConserved {
+                inputs: tx.spends.iter().map(|spend| spend.amount().as_nano()).sum(),
+                outputs: outputs(&tx)
+                    .map(|cash_note| cash_note.value().as_nano())
+                    .sum(),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn overflowing_value_should_be_detected() -> Result<()> {
+        let (_, mut tx) = genesis_and_transaction()?;
+        tamper_spend(&mut tx, |spend| {
+            for amount in spend.descendants.values_mut() {
+                *amount = NanoTokens::from(u64::MAX);
+            }
+        });
+
+        assert_eq!(
+            violation(check_value_conservation(&tx)),
+            Some(InvariantViolation::ValueOverflow)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn value_given_to_a_missing_output_should_be_detected() -> Result<()> {
+        let (_, mut tx) = genesis_and_transaction()?;
+        let change = tx.change_cashnote.take().expect("a change");
+
+        assert_eq!(
+            violation(check_value_conservation(&tx)),
+            Some(InvariantViolation::UnknownOutput(change.unique_pubkey()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn output_of_another_spend_should_be_detected() -> Result<()> {
+        let (_, mut tx) = genesis_and_transaction()?;
+        let (_, other_tx) = genesis_and_transaction()?;
+        let spend = other_tx.spends.first().expect("a spend").clone();
+        tx.output_cashnotes[0].parent_spends = BTreeSet::from([spend]);
+
+        assert_eq!(
+            violation(check_value_conservation(&tx)),
+            Some(InvariantViolation::UnknownParentSpend(
+                tx.output_cashnotes[0].unique_pubkey()
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn output_without_parents_should_be_detected() -> Result<()> {
+        let (_, mut tx) = genesis_and_transaction()?;
+        tx.output_cashnotes[0].parent_spends.clear();
+
+        assert_eq!(
+            violation(check_value_conservation(&tx)),
+            Some(InvariantViolation::MissingParentSpends(
+                tx.output_cashnotes[0].unique_pubkey()
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn output_not_given_value_by_its_parent_should_be_detected() -> Result<()> {
+        let (_, mut tx) = genesis_and_transaction()?;
+        let output = tx.output_cashnotes[0].unique_pubkey();
+        tamper_spend(&mut tx, |spend| {
+            let _ = spend.descendants.remove(&output);
+        });
+
+        assert_eq!(
+            violation(check_value_conservation(&tx)),
+            Some(InvariantViolation::ParentDoesNotReferToSpend {
+                spend: output,
+                parent: *tx.spends.first().expect("a spend").unique_pubkey(),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reused_unique_pubkey_should_be_detected() -> Result<()> {
+        let key = MainSecretKey::random();
+        let genesis = create_first_cash_note_from_key(&key)?;
+        let index = DerivationIndex::random(&mut rand::thread_rng());
+        let tx = transaction(&genesis, &key, [index, index])?;
+
+        assert_eq!(
+            violation(check_unique_keys(&tx)),
+            Some(InvariantViolation::DuplicateUniquePubkey(
+                tx.output_cashnotes[0].unique_pubkey()
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_parent_should_be_detected() -> Result<()> {
+        let (genesis, tx) = genesis_and_transaction()?;
+        let spend = tx.spends.first().expect("a spend");
+        let parent = genesis.parent_spends.first().expect("a parent");
+
+        assert_eq!(
+            violation(check_spend_parents(spend, &BTreeSet::new())),
+            Some(InvariantViolation::MissingParent {
+                spend: *spend.unique_pubkey(),
+                parent: *parent.unique_pubkey(),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parent_which_is_not_an_ancestor_should_be_detected() -> Result<()> {
+        let (genesis, tx) = genesis_and_transaction()?;
+        let spend = tx.spends.first().expect("a spend");
+        let mut parents = genesis.parent_spends.clone();
+        let _ = parents.insert(spend.clone());
+
+        assert_eq!(
+            violation(check_spend_parents(spend, &parents)),
+            Some(InvariantViolation::UnexpectedParent {
+                spend: *spend.unique_pubkey(),
+                parent: *spend.unique_pubkey(),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn double_spent_parent_should_be_detected() -> Result<()> {
+        let (genesis, tx) = genesis_and_transaction()?;
+        let (_, other_tx) = genesis_and_transaction()?;
+        let spend = tx.spends.first().expect("a spend");
+        let parent = genesis.parent_spends.first().expect("a parent");
+        // the same cash note spent again, signed differently
+        let mut double_spend = parent.clone();
+        double_spend.derived_key_sig = other_tx
+            .spends
+            .first()
+            .expect("a spend")
+            .derived_key_sig
+            .clone();
+        let mut parents = genesis.parent_spends.clone();
+        assert!(parents.insert(double_spend));
+
+        assert_eq!(
+            violation(check_spend_parents(spend, &parents)),
+            Some(InvariantViolation::DoubleSpentParent(
+                *parent.unique_pubkey()
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn spend_not_given_its_value_should_be_detected() -> Result<()> {
+        let (genesis, tx) = genesis_and_transaction()?;
+        let spend = tx.spends.first().expect("a spend");
+        let parent = tampered(genesis.parent_spends.first().expect("a parent"), |parent| {
+            for amount in parent.descendants.values_mut() {
+                *amount = NanoTokens::from(amount.as_nano() - 1);
+            }
+        });
+        let value = spend.amount().as_nano();
+
+        assert_eq!(
+            violation(check_spend_parents(spend, &BTreeSet::from([parent]))),
+            Some(InvariantViolation::SpendValueMismatch {
+                spend: *spend.unique_pubkey(),
+                value,
+                given: value - 1,
+            })
+        );
+        Ok(())
+    }
+}
@@ -52,45 +52,88 @@
 //! which eventually clears from the mempool and becomes spendable again.
 //!
 
+mod address_book;
 mod api;
+mod approval;
+#[cfg(feature = "async-io")]
+mod async_io;
 mod authentication;
+mod backup;
+mod cash_note_index;
+mod coin_selection;
 mod data_payments;
+mod derivation_indexes;
 mod encryption;
 mod error;
+mod history;
+mod hooks;
 mod hot_wallet;
 mod keys;
+mod manager;
+mod migrate;
+mod payment_request;
+mod snapshot;
+mod spent_archive;
+mod store;
+mod treasury;
+mod unconfirmed_spends;
 mod wallet_file;
 mod watch_only;
 
+#[cfg(unix)]
+pub use self::approval::{ApprovalListener, PendingApproval};
 pub use self::{
+    address_book::{AddressBook, Recipient},
     api::{WalletApi, WALLET_DIR_NAME},
-    data_payments::{Payment, PaymentQuote, QuotingMetrics, QUOTE_EXPIRATION_SECS},
+    approval::{ApprovalRequest, ApprovalResponse, SpendApprovalConfig, DEFAULT_APPROVAL_TIMEOUT},
+    backup::WALLET_BACKUP_VERSION,
+    cash_note_index::{CashNoteIndex, CashNoteMetadata, CashNoteStatus},
+    coin_selection::{BranchAndBound, CoinSelection, LargestFirst, SmallestFirst},
+    data_payments::{Payment, PaymentDetails, PaymentQuote, QuotingMetrics, QUOTE_EXPIRATION_SECS},
+    derivation_indexes::{DerivationIndexRegistry, DerivationPurpose, HandedOutIndex},
     error::{Error, Result},
-    hot_wallet::HotWallet,
-    keys::bls_secret_from_hex,
-    wallet_file::wallet_lockfile_name,
+    history::{History, HistoryEntry, HistoryFilter, HistoryKind},
+    hooks::WalletHooks,
+    hot_wallet::{
+        HotWallet, QuotesByAddress, TransactionLimits, DEFAULT_MAX_TX_INPUTS,
+        DEFAULT_MAX_TX_OUTPUTS,
+    },
+    keys::{bls_secret_from_hex, verify_message},
+    manager::{WalletManager, DEFAULT_WALLET_NAME, NAMED_WALLETS_DIR_NAME},
+    migrate::WALLET_FILE_VERSION,
+    payment_request::{PaymentRequest, PAYMENT_URI_SCHEME},
+    snapshot::WalletSnapshot,
+    spent_archive::{ArchivedCashNote, SpentArchive},
+    store::{FileWalletStore, MemoryWalletStore, WalletExclusiveAccess, WalletStore},
+    treasury::{Disbursement, DisbursementPlan, SentTranche, TranchePlan, Treasury},
+    unconfirmed_spends::{ResendPolicy, UnconfirmedSpendMetadata},
+    wallet_file::{wallet_lockfile_name, WalletFileFormat},
     watch_only::WatchOnlyWallet,
 };
 pub(crate) use keys::store_new_keypair;
 
 use crate::{NanoTokens, UniquePubkey};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fs, path::Path};
-use wallet_file::wallet_file_name;
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc};
+use wallet_file::{decode_wallet, wallet_file_name, wallet_file_version};
 
-#[derive(Default, Serialize, Deserialize)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct KeyLessWallet {
-    available_cash_notes: BTreeMap<UniquePubkey, NanoTokens>,
+    /// Shared with the snapshots of the wallet, and copied on write while they are held.
+    available_cash_notes: Arc<BTreeMap<UniquePubkey, NanoTokens>>,
 }
 
 impl KeyLessWallet {
     /// Returns `Some(KeyLessWallet)` or None if file doesn't exist.
     /// If the file is being written to, it will wait until the write is complete before reading.
+    /// A file written by an older version is upgraded to the current one as it is read, and
+    /// stored as such with the wallet.
     pub fn load_from(wallet_dir: &Path) -> Result<Option<Self>> {
         let path = wallet_file_name(wallet_dir);
         if !path.is_file() {
             return Ok(None);
         }
+        let version = wallet_file_version(wallet_dir)?;
 
         let mut attempts = 0;
         let mut wallet: Option<Self> = None;
@@ -100,8 +143,11 @@ impl KeyLessWallet {
         while wallet.is_none() && attempts < 10 {
             info!("Attempting to read wallet file");
             match fs::read(&path) {
-                Ok(data) => match rmp_serde::from_slice(&data) {
-                    Ok(deserialized_wallet) => wallet = Some(deserialized_wallet),
+                Ok(data) => match decode_wallet(&data, version) {
+                    Ok((deserialized_wallet, _)) => {
+                        wallet = Some(deserialized_wallet);
+                    }
+                    Err(err @ Error::InvalidWalletFile(_)) => return Err(err),
                     Err(_) => {
                         attempts += 1;
                         info!("Attempt {attempts} to read wallet file failed... ");
@@ -0,0 +1,201 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The address book of a wallet, persisted in the wallet dir, naming the recipients it sends to
+//! so a transfer can be made to a label instead of a raw pubkey.
+
+use super::{Error, Result};
+use crate::MainPubkey;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const ADDRESS_BOOK_FILE_NAME: &str = "address_book";
+
+/// Who a transfer is made to: a pubkey, or the label of one in the address book of the wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipient {
+    Pubkey(MainPubkey),
+    Label(String),
+}
+
+impl From<MainPubkey> for Recipient {
+    fn from(pubkey: MainPubkey) -> Self {
+        Self::Pubkey(pubkey)
+    }
+}
+
+/// A hex-encoded pubkey, else a label.
+impl FromStr for Recipient {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match MainPubkey::from_hex(s) {
+            Ok(pubkey) => Ok(Self::Pubkey(pubkey)),
+            Err(_) => Ok(Self::Label(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pubkey(pubkey) => write!(f, "{}", pubkey.to_hex()),
+            Self::Label(label) => write!(f, "{label}"),
+        }
+    }
+}
+
+/// The labelled recipients of a wallet.
+#[derive(Default)]
+pub struct AddressBook {
+    /// None for the empty address book of a wallet not stored on disk, which cannot be added to
+    path: Option<PathBuf>,
+    entries: BTreeMap<String, MainPubkey>,
+}
+
+impl AddressBook {
+    /// Load the address book of the wallet dir, empty if nothing has been added to it yet.
+    pub fn load(wallet_dir: &Path) -> Result<Self> {
+        let path = wallet_dir.join(ADDRESS_BOOK_FILE_NAME);
+        let entries = if path.exists() {
+            rmp_serde::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    fn store(&self) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or(Error::NotStoredOnDisk("address book"))?;
+        // written aside then renamed, for an interrupted write to never lose the address book
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, rmp_serde::to_vec(&self.entries)?)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Add the pubkey under the label, failing if the label is taken by another pubkey. A label
+    /// cannot be blank nor itself a hex-encoded pubkey, for a recipient to always be parsed the
+    /// same.
+    pub fn add(&mut self, label: &str, pubkey: MainPubkey) -> Result<()> {
+        let label = label.trim();
+        if label.is_empty() || MainPubkey::from_hex(label).is_ok() {
+            return Err(Error::InvalidAddressBookLabel(label.to_string()));
+        }
+        match self.entries.get(label) {
+            Some(existing) if *existing == pubkey => return Ok(()),
+            Some(_) => return Err(Error::AddressBookLabelTaken(label.to_string())),
+            None => {}
+        }
+        let _ = self.entries.insert(label.to_string(), pubkey);
+        self.store()
+    }
+
+    /// Remove the label, returning the pubkey it named if it was in the address book.
+    pub fn remove(&mut self, label: &str) -> Result<Option<MainPubkey>> {
+        let removed = self.entries.remove(label.trim());
+        if removed.is_some() {
+            self.store()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn get(&self, label: &str) -> Option<&MainPubkey> {
+        self.entries.get(label.trim())
+    }
+
+    /// The labels of the pubkey, e.g. to name the counterparty of a transfer.
+    pub fn labels_of(&self, pubkey: &MainPubkey) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| *entry == pubkey)
+            .map(|(label, _)| label.as_str())
+            .collect()
+    }
+
+    /// The labelled pubkeys, ordered by label.
+    pub fn list(&self) -> impl Iterator<Item = (&str, &MainPubkey)> {
+        self.entries
+            .iter()
+            .map(|(label, pubkey)| (label.as_str(), pubkey))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The pubkey of the recipient, looking its label up in the address book.
+    pub fn resolve(&self, recipient: &Recipient) -> Result<MainPubkey> {
+        match recipient {
+            Recipient::Pubkey(pubkey) => Ok(*pubkey),
+            Recipient::Label(label) => self
+                .get(label)
+                .copied()
+                .ok_or_else(|| Error::UnknownRecipient(label.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainSecretKey;
+    use assert_fs::TempDir;
+    use eyre::Result;
+
+    #[test]
+    fn labels_should_be_persisted_and_resolved() -> Result<()> {
+        let dir = TempDir::new()?;
+        let alice = MainSecretKey::random().main_pubkey();
+        let bob = MainSecretKey::random().main_pubkey();
+
+        let mut book = AddressBook::load(dir.path())?;
+        book.add("alice", alice)?;
+        book.add(" bob ", bob)?;
+        // adding the same entry again is a no-op, taking the label of another is refused
+        book.add("alice", alice)?;
+        assert!(matches!(
+            book.add("alice", bob),
+            Err(Error::AddressBookLabelTaken(_))
+        ));
+        assert!(book.add("", bob).is_err());
+        assert!(book.add(&bob.to_hex(), bob).is_err());
+
+        let book = AddressBook::load(dir.path())?;
+        assert_eq!(
+            book.list().collect::<Vec<_>>(),
+            vec![("alice", &alice), ("bob", &bob)]
+        );
+        assert_eq!(book.resolve(&"bob".parse()?)?, bob);
+        assert_eq!(book.resolve(&alice.to_hex().parse()?)?, alice);
+        assert!(matches!(
+            book.resolve(&"carol".parse()?),
+            Err(Error::UnknownRecipient(_))
+        ));
+
+        let mut book = book;
+        assert_eq!(book.remove("alice")?, Some(alice));
+        assert_eq!(book.remove("alice")?, None);
+        assert_eq!(AddressBook::load(dir.path())?.len(), 1);
+        Ok(())
+    }
+}
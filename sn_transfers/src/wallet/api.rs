@@ -7,12 +7,13 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{data_payments::PaymentDetails, Result};
-use crate::WalletError;
+use crate::{SpendAddress, WalletError};
 use serde::Serialize;
 use std::{
+    collections::{BTreeMap, BTreeSet},
     fs,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex, MutexGuard},
 };
 use xor_name::XorName;
 
@@ -26,6 +27,16 @@ pub struct WalletApi {
     wallet_dir: Arc<PathBuf>,
     /// Cached version of `root_dir/wallet_dir/payments`
     payment_dir: Arc<PathBuf>,
+    /// The payments kept in memory instead of the `payments` dir, for a wallet not stored on disk
+    #[serde(skip)]
+    memory: Option<Arc<Mutex<MemoryPayments>>>,
+}
+
+/// The payments of a wallet not stored on disk, as they would be in the `payments` dir.
+#[derive(Default)]
+struct MemoryPayments {
+    payments: BTreeMap<XorName, Vec<PaymentDetails>>,
+    spends: BTreeMap<XorName, BTreeSet<SpendAddress>>,
 }
 
 impl WalletApi {
@@ -35,6 +46,7 @@ impl WalletApi {
         Self {
             payment_dir: Arc::new(wallet_dir.join(PAYMENTS_DIR_NAME)),
             wallet_dir: Arc::new(wallet_dir),
+            memory: None,
         }
     }
 
@@ -43,9 +55,29 @@ impl WalletApi {
         Self {
             wallet_dir: Arc::new(wallet_dir.to_path_buf()),
             payment_dir: Arc::new(wallet_dir.join(PAYMENTS_DIR_NAME)),
+            memory: None,
+        }
+    }
+
+    /// Create a new instance keeping the payments in memory, for a wallet not stored on disk. Its
+    /// wallet dir is empty.
+    pub fn in_memory() -> Self {
+        Self {
+            wallet_dir: Default::default(),
+            payment_dir: Default::default(),
+            memory: Some(Default::default()),
         }
     }
 
+    fn memory(&self) -> Option<MutexGuard<'_, MemoryPayments>> {
+        // the payments are left consistent by each access, so a panic of another one can be ignored
+        self.memory.as_ref().map(|memory| {
+            memory
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        })
+    }
+
     /// Returns the most recent PaymentDetails for the given xorname if cached.
     /// If multiple payments have been made to the same xorname, then we pick the last one as it is the most recent.
     pub fn get_recent_payment(&self, xorname: &XorName) -> Result<PaymentDetails> {
@@ -76,6 +108,10 @@ impl WalletApi {
     /// Insert a payment and write it to the `payments` dir.
     /// If a prior payment has been made to the same xorname, then the new payment is pushed to the end of the list.
     pub fn insert_payment_transaction(&self, name: XorName, payment: PaymentDetails) -> Result<()> {
+        if let Some(mut memory) = self.memory() {
+            memory.payments.entry(name).or_default().push(payment);
+            return Ok(());
+        }
         // try to read the previous payments and push the new payment at the end
         let payments = match self.read_payment_transactions(&name) {
             Ok(mut stored_payments) => {
@@ -97,19 +133,77 @@ impl WalletApi {
     }
 
     pub fn remove_payment_transaction(&self, name: &XorName) {
+        if let Some(mut memory) = self.memory() {
+            let _ = memory.payments.remove(name);
+            let _ = memory.spends.remove(name);
+            return;
+        }
         let unique_file_name = format!("{}.payment", hex::encode(*name));
         let payment_file_path = self.payment_dir.join(unique_file_name);
 
         debug!("Removing payment from {payment_file_path:?}");
         let _ = fs::remove_file(payment_file_path);
+
+        let spends_file_path = self
+            .payment_dir
+            .join(format!("{}.spends", hex::encode(*name)));
+        let _ = fs::remove_file(spends_file_path);
+    }
+
+    /// Write the addresses of the spends of the transaction paying for the given xorname, so the
+    /// payment can be told confirmed once they are.
+    pub fn insert_payment_spends(
+        &self,
+        name: XorName,
+        spends: &BTreeSet<SpendAddress>,
+    ) -> Result<()> {
+        if let Some(mut memory) = self.memory() {
+            let _ = memory.spends.insert(name, spends.clone());
+            return Ok(());
+        }
+        fs::create_dir_all(self.payment_dir.as_ref())?;
+        let spends_file_path = self
+            .payment_dir
+            .join(format!("{}.spends", hex::encode(name)));
+        debug!("Writing payment spends to {spends_file_path:?}");
+
+        let mut file = fs::File::create(spends_file_path)?;
+        let mut serialiser = rmp_serde::encode::Serializer::new(&mut file);
+        spends.serialize(&mut serialiser)?;
+        Ok(())
     }
 
+    /// Returns the addresses of the spends of the transaction paying for the given xorname.
+    pub fn payment_spends(&self, name: &XorName) -> Result<BTreeSet<SpendAddress>> {
+        if let Some(memory) = self.memory() {
+            return memory
+                .spends
+                .get(name)
+                .cloned()
+                .ok_or(WalletError::NoPaymentForAddress(*name));
+        }
+        let spends_file_path = self
+            .payment_dir
+            .join(format!("{}.spends", hex::encode(*name)));
+        if !spends_file_path.is_file() {
+            return Err(WalletError::NoPaymentForAddress(*name));
+        }
+
+        let file = fs::File::open(&spends_file_path)?;
+        let spends = rmp_serde::from_read(&file)?;
+        Ok(spends)
+    }
+
+    /// The wallet dir, empty when the payments are kept in memory.
     pub fn wallet_dir(&self) -> &Path {
         &self.wallet_dir
     }
 
     /// Read all the payments made to the provided xorname
     fn read_payment_transactions(&self, name: &XorName) -> Result<Vec<PaymentDetails>> {
+        if let Some(memory) = self.memory() {
+            return Ok(memory.payments.get(name).cloned().unwrap_or_default());
+        }
         let unique_file_name = format!("{}.payment", hex::encode(*name));
         let payment_file_path = self.payment_dir.join(unique_file_name);
 
@@ -144,6 +238,7 @@ mod tests {
             transfer: (transfer.clone(), NanoTokens::zero()),
             royalties: (transfer.clone(), NanoTokens::zero()),
             quote: PaymentQuote::zero(),
+            other_payees: vec![],
         };
         let _ = wallet_api.insert_payment_transaction(chunk_name, payment_details_1);
 
@@ -154,6 +249,7 @@ mod tests {
             transfer: (transfer.clone(), NanoTokens::zero()),
             royalties: (transfer, NanoTokens::zero()),
             quote: PaymentQuote::zero(),
+            other_payees: vec![],
         };
         let _ = wallet_api.insert_payment_transaction(chunk_name, payment_details_2.clone());
 
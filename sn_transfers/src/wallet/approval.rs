@@ -0,0 +1,423 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The interactive approval of the spends of a wallet above an amount, protecting the users of
+//! applications sharing their wallet from having it drained silently.
+//!
+//! Once configured in the wallet dir, a spend sending more than the threshold out of the wallet,
+//! counting the spends sent without an approval within the window before it, is sent as a
+//! request to the approver listening on a local socket, e.g. a GUI or `safe wallet approve`, and
+//! only goes ahead once it is approved. A spend is denied if the approver is not listening or
+//! does not answer in time. Each decision is appended to an audit log of the wallet dir.
+//!
+//! The approval is asked before the wallet is locked, for the other users of the wallet not to
+//! wait on the approver.
+//!
+//! It guards the spends made through the wallet API: an application able to rewrite the files of
+//! the wallet dir can remove the configuration.
+
+use super::{Error, Result};
+use crate::{MainPubkey, NanoTokens};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const SPEND_APPROVAL_FILE_NAME: &str = "spend_approval";
+const SPEND_APPROVAL_LOG_FILE_NAME: &str = "spend_approvals.log";
+const SPEND_APPROVAL_SOCKET_FILE_NAME: &str = "spend_approval.sock";
+/// How long an approver has to answer a request by default.
+pub const DEFAULT_APPROVAL_TIMEOUT: Duration = Duration::from_secs(120);
+/// How far back the spends made without an approval count towards the threshold by default.
+pub const DEFAULT_APPROVAL_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The spends needing an approval, as configured in the wallet dir.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendApprovalConfig {
+    /// The spends sending more than this out of the wallet have to be approved
+    pub threshold: NanoTokens,
+    /// The socket the approver listens on
+    pub socket_path: PathBuf,
+    pub timeout_secs: u64,
+    /// The spends made without an approval within this many seconds before a spend count
+    /// towards the threshold along with it, for splitting a payment not to avoid the approval
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+}
+
+fn default_window_secs() -> u64 {
+    DEFAULT_APPROVAL_WINDOW.as_secs()
+}
+
+impl SpendApprovalConfig {
+    /// Approvals of the spends above the threshold, asked on the default socket of the wallet dir.
+    pub fn new(wallet_dir: &Path, threshold: NanoTokens) -> Self {
+        Self {
+            threshold,
+            socket_path: wallet_dir.join(SPEND_APPROVAL_SOCKET_FILE_NAME),
+            timeout_secs: DEFAULT_APPROVAL_TIMEOUT.as_secs(),
+            window_secs: default_window_secs(),
+        }
+    }
+
+    /// The configuration of the wallet dir, if its spends need an approval.
+    pub fn load(wallet_dir: &Path) -> Result<Option<Self>> {
+        let path = wallet_dir.join(SPEND_APPROVAL_FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(path)?)?))
+    }
+
+    pub fn store(&self, wallet_dir: &Path) -> Result<()> {
+        let path = wallet_dir.join(SPEND_APPROVAL_FILE_NAME);
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Stop requiring approvals for the spends of the wallet dir.
+    pub fn remove(wallet_dir: &Path) -> Result<()> {
+        let path = wallet_dir.join(SPEND_APPROVAL_FILE_NAME);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// A spend awaiting approval, sent to the approver as a line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    /// Identifies the request in its response and in the audit log
+    pub id: String,
+    /// The hex-encoded address of the wallet spending
+    pub wallet: String,
+    /// The total sent out of the wallet, excluding what the wallet sends to itself
+    pub amount: NanoTokens,
+    /// The hex-encoded main pubkeys sent to, with their amount
+    pub recipients: Vec<(String, NanoTokens)>,
+    /// The number of records paid for by the spend, if it pays for storage
+    pub storage_payments: usize,
+}
+
+impl ApprovalRequest {
+    fn new(
+        wallet: &MainPubkey,
+        outputs: &[(NanoTokens, MainPubkey)],
+        storage_payments: usize,
+    ) -> Self {
+        let recipients: Vec<_> = outputs
+            .iter()
+            .filter(|(_, recipient)| recipient != wallet)
+            .map(|(amount, recipient)| (recipient.to_hex(), *amount))
+            .collect();
+        Self {
+            id: hex::encode(rand::random::<[u8; 8]>()),
+            wallet: wallet.to_hex(),
+            amount: outgoing_amount(wallet, outputs),
+            recipients,
+            storage_payments,
+        }
+    }
+}
+
+/// The total of the outputs sent out of the wallet, leaving out the ones it sends to itself, e.g.
+/// when consolidating or splitting its cash notes.
+pub(super) fn outgoing_amount(
+    wallet: &MainPubkey,
+    outputs: &[(NanoTokens, MainPubkey)],
+) -> NanoTokens {
+    outputs
+        .iter()
+        .filter(|(_, recipient)| recipient != wallet)
+        .fold(NanoTokens::zero(), |total, (amount, _)| {
+            total
+                .checked_add(*amount)
+                .unwrap_or(NanoTokens::from(u64::MAX))
+        })
+}
+
+/// The answer of the approver to a request, sent back as a line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalResponse {
+    pub id: String,
+    pub approved: bool,
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    time: String,
+    #[serde(flatten)]
+    request: &'a ApprovalRequest,
+    outcome: &'a str,
+}
+
+/// What is read back of a record of the audit log.
+#[derive(Deserialize)]
+struct LoggedSpend {
+    time: String,
+    amount: NanoTokens,
+    outcome: String,
+}
+
+/// The outcome logged for the spends which needed no approval.
+const BELOW_THRESHOLD_OUTCOME: &str = "below threshold";
+
+/// Have the spend of the outputs approved if the wallet dir requires it, failing with
+/// [`Error::SpendNotApproved`] otherwise. This blocks until the approver answers or times out.
+pub(super) fn check_spend_approval(
+    wallet_dir: &Path,
+    wallet: &MainPubkey,
+    outputs: &[(NanoTokens, MainPubkey)],
+    storage_payments: usize,
+) -> Result<()> {
+    let Some(config) = SpendApprovalConfig::load(wallet_dir)? else {
+        return Ok(());
+    };
+    let request = ApprovalRequest::new(wallet, outputs, storage_payments);
+    if request.amount.is_zero() {
+        return Ok(());
+    }
+    let unapproved = unapproved_amount(wallet_dir, Duration::from_secs(config.window_secs))?;
+    if request
+        .amount
+        .as_nano()
+        .saturating_add(unapproved.as_nano())
+        <= config.threshold.as_nano()
+    {
+        return audit_log(wallet_dir, &request, BELOW_THRESHOLD_OUTCOME);
+    }
+
+    info!(
+        "Spend {} of {} awaits approval on {:?}",
+        request.id, request.amount, config.socket_path
+    );
+    let outcome = ask_approver(&config, &request);
+    let logged_outcome = match &outcome {
+        Ok(true) => "approved".to_string(),
+        Ok(false) => "denied".to_string(),
+        Err(err) => format!("failed: {err}"),
+    };
+    audit_log(wallet_dir, &request, &logged_outcome)?;
+    match outcome {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::SpendNotApproved {
+            amount: request.amount,
+            reason: "denied by the approver".to_string(),
+        }),
+        Err(reason) => Err(Error::SpendNotApproved {
+            amount: request.amount,
+            reason,
+        }),
+    }
+}
+
+/// The total of the spends made without an approval within the `window` before now.
+fn unapproved_amount(wallet_dir: &Path, window: Duration) -> Result<NanoTokens> {
+    let path = wallet_dir.join(SPEND_APPROVAL_LOG_FILE_NAME);
+    if window.is_zero() || !path.is_file() {
+        return Ok(NanoTokens::zero());
+    }
+    let since = chrono::Duration::from_std(window)
+        .ok()
+        .and_then(|window| chrono::Utc::now().checked_sub_signed(window));
+    let total = fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LoggedSpend>(line).ok())
+        .filter(|spend| spend.outcome == BELOW_THRESHOLD_OUTCOME)
+        .filter(|spend| {
+            chrono::DateTime::parse_from_rfc3339(&spend.time)
+                .map(|time| since.is_none_or(|since| time >= since))
+                .unwrap_or(false)
+        })
+        .fold(0, |total: u64, spend| {
+            total.saturating_add(spend.amount.as_nano())
+        });
+    Ok(NanoTokens::from(total))
+}
+
+fn audit_log(wallet_dir: &Path, request: &ApprovalRequest, outcome: &str) -> Result<()> {
+    let record = AuditRecord {
+        time: chrono::Utc::now().to_rfc3339(),
+        request,
+        outcome,
+    };
+    let mut line = serde_json::to_vec(&record)?;
+    line.push(b'\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(wallet_dir.join(SPEND_APPROVAL_LOG_FILE_NAME))?
+        .write_all(&line)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn ask_approver(
+    config: &SpendApprovalConfig,
+    request: &ApprovalRequest,
+) -> std::result::Result<bool, String> {
+    use std::{
+        io::{BufRead, BufReader},
+        os::unix::net::UnixStream,
+    };
+
+    let mut stream = UnixStream::connect(&config.socket_path)
+        .map_err(|err| format!("no approver listening on {:?}: {err}", config.socket_path))?;
+    let timeout = Some(config.timeout()).filter(|timeout| !timeout.is_zero());
+    stream
+        .set_read_timeout(timeout)
+        .and_then(|()| stream.set_write_timeout(timeout))
+        .map_err(|err| err.to_string())?;
+
+    let mut line = serde_json::to_vec(request).map_err(|err| err.to_string())?;
+    line.push(b'\n');
+    stream
+        .write_all(&line)
+        .map_err(|err| format!("could not send the request: {err}"))?;
+
+    let mut response = String::new();
+    let _ = BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|err| format!("no answer from the approver: {err}"))?;
+    let response: ApprovalResponse = serde_json::from_str(&response)
+        .map_err(|err| format!("invalid answer from the approver: {err}"))?;
+    if response.id != request.id {
+        return Err(format!(
+            "answer to request {} instead of {}",
+            response.id, request.id
+        ));
+    }
+    Ok(response.approved)
+}
+
+#[cfg(not(unix))]
+fn ask_approver(
+    _config: &SpendApprovalConfig,
+    _request: &ApprovalRequest,
+) -> std::result::Result<bool, String> {
+    Err("spend approvals are not supported on this platform".to_string())
+}
+
+/// The listening end of the approvals, receiving the requests of the wallets configured with
+/// its socket.
+#[cfg(unix)]
+pub struct ApprovalListener {
+    listener: std::os::unix::net::UnixListener,
+    socket_path: PathBuf,
+}
+
+#[cfg(unix)]
+impl ApprovalListener {
+    /// Listen on the socket, replacing the one left by a previous approver. Fails while another
+    /// approver listens on it, or if something else than a socket is at its path.
+    pub fn bind(socket_path: &Path) -> Result<Self> {
+        use std::{
+            io,
+            os::unix::{fs::FileTypeExt, net::UnixStream},
+        };
+
+        if let Ok(metadata) = fs::symlink_metadata(socket_path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{socket_path:?} is not a socket"),
+                )
+                .into());
+            }
+            if UnixStream::connect(socket_path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("an approver already listens on {socket_path:?}"),
+                )
+                .into());
+            }
+            fs::remove_file(socket_path)?;
+        }
+        let listener = std::os::unix::net::UnixListener::bind(socket_path)?;
+        Ok(Self {
+            listener,
+            socket_path: socket_path.to_path_buf(),
+        })
+    }
+
+    /// Wait for the next request.
+    pub fn next_request(&self) -> Result<PendingApproval> {
+        use std::io::{BufRead, BufReader};
+
+        let (stream, _) = self.listener.accept()?;
+        let mut line = String::new();
+        let _ = BufReader::new(&stream).read_line(&mut line)?;
+        let request = serde_json::from_str(&line)?;
+        Ok(PendingApproval { request, stream })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ApprovalListener {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.socket_path);
+    }
+}
+
+/// A request received by the approver, whose wallet awaits the answer.
+#[cfg(unix)]
+pub struct PendingApproval {
+    pub request: ApprovalRequest,
+    stream: std::os::unix::net::UnixStream,
+}
+
+#[cfg(unix)]
+impl PendingApproval {
+    pub fn respond(mut self, approved: bool) -> Result<()> {
+        let response = ApprovalResponse {
+            id: self.request.id.clone(),
+            approved,
+        };
+        let mut line = serde_json::to_vec(&response)?;
+        line.push(b'\n');
+        self.stream.write_all(&line)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::ApprovalListener;
+    use eyre::Result;
+    use std::{fs, os::unix::net::UnixListener};
+
+    #[test]
+    fn an_approver_should_only_replace_a_stale_socket() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let socket_path = dir.path().join("approval.sock");
+
+        let listener = ApprovalListener::bind(&socket_path)?;
+        assert!(ApprovalListener::bind(&socket_path).is_err());
+        drop(listener);
+
+        // left behind by an approver which did not remove it
+        drop(UnixListener::bind(&socket_path)?);
+        assert!(socket_path.exists());
+        let _listener = ApprovalListener::bind(&socket_path)?;
+
+        let file_path = dir.path().join("not_a_socket");
+        fs::write(&file_path, b"")?;
+        assert!(ApprovalListener::bind(&file_path).is_err());
+        assert!(file_path.is_file());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The support of the `_async` variants of the wallet operations touching its store, available
+//! with the `async-io` feature.
+//!
+//! The stores are blocking, e.g. `std::fs` and the file lock of the wallet dir, so an operation is
+//! run on the blocking threads of the tokio runtime. It is given the store of the wallet and what
+//! it changes only, e.g. the received cash notes, the state of the wallet being replaced once it
+//! is done. A failed operation leaves the wallet unchanged, as its blocking variant does.
+//!
+//! Dropping the future of an operation does not cancel it once started: it still completes on
+//! the store, the wallet being left as it was in memory. It is then behind its store until
+//! reloaded, which every operation storing the wallet does first, under its lock.
+
+use super::{Error, Result};
+
+/// Run the blocking `operation` off the async threads of the runtime.
+pub(super) async fn spawn_blocking<T, F>(operation: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(operation)
+        .await
+        .map_err(|err| Error::Io(err.into()))?
+}
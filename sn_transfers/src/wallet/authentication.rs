@@ -23,8 +23,9 @@ pub struct AuthenticationManager {
     /// Expiry time of the password.
     /// Has to be provided by the user again after a certain amount of time
     password_expires_at: Option<DateTime<Utc>>,
-    /// Path to the root directory of the wallet
-    wallet_dir: PathBuf,
+    /// Path to the root directory of the wallet, none for a wallet not stored on disk, whose
+    /// key is never encrypted
+    wallet_dir: Option<PathBuf>,
 }
 
 impl AuthenticationManager {
@@ -32,7 +33,16 @@ impl AuthenticationManager {
         Self {
             password: None,
             password_expires_at: None,
-            wallet_dir,
+            wallet_dir: Some(wallet_dir),
+        }
+    }
+
+    /// The manager of a wallet not stored on disk, which needs no password.
+    pub fn in_memory() -> Self {
+        Self {
+            password: None,
+            password_expires_at: None,
+            wallet_dir: None,
         }
     }
 
@@ -47,7 +57,11 @@ impl AuthenticationManager {
 
     /// Verifies the provided password against the encrypted secret key.
     fn verify_password(&self, password: &str) -> Result<()> {
-        let encrypted_secret_key = EncryptedSecretKey::from_file(self.wallet_dir.as_path())?;
+        let wallet_dir = self
+            .wallet_dir
+            .as_deref()
+            .ok_or(Error::NotStoredOnDisk("encrypted key"))?;
+        let encrypted_secret_key = EncryptedSecretKey::from_file(wallet_dir)?;
         // Check if password is correct by trying to decrypt
         encrypted_secret_key.decrypt(password)?;
         Ok(())
@@ -72,7 +86,11 @@ impl AuthenticationManager {
     /// - `Error::WalletPasswordRequired`: The wallet is encrypted but no password is set. The user needs to authenticate with a valid password using `authenticate_with_password()`.
     pub fn authenticate(&mut self) -> Result<Option<String>> {
         // If wallet is encrypted, require a valid password
-        if EncryptedSecretKey::file_exists(self.wallet_dir.as_path()) {
+        let is_encrypted = self
+            .wallet_dir
+            .as_deref()
+            .is_some_and(EncryptedSecretKey::file_exists);
+        if is_encrypted {
             // Check if a password is set
             if let (Some(password), Some(expiration_time)) =
                 (&self.password.to_owned(), self.password_expires_at)
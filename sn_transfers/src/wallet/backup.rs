@@ -0,0 +1,125 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The backup of a wallet to a single file, encrypted with a passphrase: it holds the secret key,
+//! the available cash notes and the spends not confirmed yet, everything needed to restore the
+//! funds of the wallet.
+
+use super::{
+    encryption::{decrypt_data, encrypt_data},
+    Error, KeyLessWallet, Result,
+};
+use crate::{CashNote, MainSecretKey, SignedSpend};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, fs, path::Path};
+
+/// The version of the format of the wallet backups, bumped on any incompatible change to it.
+pub const WALLET_BACKUP_VERSION: u32 = 1;
+
+/// The backup file as written to disk: only its version is readable without the passphrase.
+#[derive(Serialize, Deserialize)]
+struct EncryptedWalletBackup {
+    version: u32,
+    #[serde(with = "serde_bytes")]
+    salt: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    nonce: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    encrypted: Vec<u8>,
+}
+
+/// The content of a wallet backup.
+#[derive(Serialize, Deserialize)]
+pub(super) struct WalletBackup {
+    #[serde(with = "serde_bytes")]
+    pub(super) main_secret_key: Vec<u8>,
+    pub(super) wallet: KeyLessWallet,
+    pub(super) unconfirmed_spend_requests: BTreeSet<SignedSpend>,
+    /// The cash notes of the `cash_notes` dir of the wallet.
+    pub(super) cash_notes: Vec<CashNote>,
+}
+
+impl WalletBackup {
+    /// Encrypt the backup with the passphrase and write it to `path`.
+    pub(super) fn write(&self, path: &Path, passphrase: &str) -> Result<()> {
+        let (salt, nonce, encrypted) = encrypt_data(&rmp_serde::to_vec(self)?, passphrase)?;
+        let backup = EncryptedWalletBackup {
+            version: WALLET_BACKUP_VERSION,
+            salt,
+            nonce,
+            encrypted,
+        };
+
+        // Written aside then renamed, for an interrupted backup to never replace a previous one
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, rmp_serde::to_vec(&backup)?)?;
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Read the backup at `path` and decrypt it with the passphrase.
+    pub(super) fn read(path: &Path, passphrase: &str) -> Result<Self> {
+        let backup: EncryptedWalletBackup = rmp_serde::from_slice(&fs::read(path)?)
+            .map_err(|err| Error::InvalidWalletBackup(format!("unreadable file: {err}")))?;
+        if backup.version > WALLET_BACKUP_VERSION {
+            return Err(Error::InvalidWalletBackup(format!(
+                "version {} is not supported, the latest being {WALLET_BACKUP_VERSION}",
+                backup.version
+            )));
+        }
+
+        let decrypted = decrypt_data(&backup.salt, &backup.nonce, backup.encrypted, passphrase)
+            .map_err(|_| {
+                Error::InvalidWalletBackup("wrong passphrase, or corrupted file".to_string())
+            })?;
+        rmp_serde::from_slice(&decrypted)
+            .map_err(|err| Error::InvalidWalletBackup(format!("undecodable content: {err}")))
+    }
+
+    /// Check the backup is consistent, returning its secret key: its cash notes and spends must
+    /// all be of the key, and the available cash notes all be backed up.
+    pub(super) fn validate(&self) -> Result<MainSecretKey> {
+        let bytes: [u8; bls::SK_SIZE] = self
+            .main_secret_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::InvalidWalletBackup("invalid secret key".to_string()))?;
+        let main_key = MainSecretKey::new(bls::SecretKey::from_bytes(bytes)?);
+        let main_pubkey = main_key.main_pubkey();
+
+        for cash_note in &self.cash_notes {
+            if cash_note.derived_pubkey(&main_pubkey).is_err() {
+                return Err(Error::InvalidWalletBackup(format!(
+                    "cash note {:?} is not of the wallet key",
+                    cash_note.unique_pubkey()
+                )));
+            }
+        }
+        for unique_pubkey in self.wallet.available_cash_notes.keys() {
+            if !self
+                .cash_notes
+                .iter()
+                .any(|cash_note| &cash_note.unique_pubkey() == unique_pubkey)
+            {
+                return Err(Error::InvalidWalletBackup(format!(
+                    "available cash note {unique_pubkey:?} is missing"
+                )));
+            }
+        }
+        for signed_spend in &self.unconfirmed_spend_requests {
+            signed_spend.verify().map_err(|err| {
+                Error::InvalidWalletBackup(format!(
+                    "unconfirmed spend {:?} is not valid: {err}",
+                    signed_spend.address()
+                ))
+            })?;
+        }
+
+        Ok(main_key)
+    }
+}
@@ -0,0 +1,453 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The index of the cash notes of the `cash_notes` dir of a wallet, giving the value, file and
+//! status of each without opening their files, which no longer scales for wallets of 100k+ notes.
+//!
+//! It is an append-only log of records, the last one of a cash note winning, maintained as cash
+//! notes are stored, spent and removed. The log is compacted once most of its records are stale.
+//! A cash note is indexed once the wallet holding it is stored, for an interrupted deposit to
+//! leave its file to be loaded again rather than indexed as held.
+//!
+//! The length of the log and its number of records are kept aside, for appending not to replay
+//! it. It is replayed when they don't match the log, e.g. after an interrupted write.
+
+use super::{
+    wallet_file::{cash_note_file_name, CASHNOTES_DIR_NAME},
+    Result,
+};
+use crate::{NanoTokens, UniquePubkey};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const CASH_NOTE_INDEX_FILE_NAME: &str = "cash_notes.index";
+const CASH_NOTE_INDEX_STATE_FILE_NAME: &str = "cash_notes.index.state";
+const RECORD_LEN_SIZE: usize = 4;
+/// The number of stale records tolerated in the log before it is compacted, besides one per
+/// indexed cash note.
+const COMPACTION_SLACK: usize = 1_000;
+
+/// Whether an indexed cash note can still be spent by the wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CashNoteStatus {
+    Available,
+    Spent,
+}
+
+/// What is indexed of a cash note.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CashNoteMetadata {
+    pub value: NanoTokens,
+    /// The name of its file in the `cash_notes` dir
+    pub file_name: String,
+    pub status: CashNoteStatus,
+    /// When it was found spent, in seconds since the Unix epoch, unknown for the cash notes
+    /// spent before it was indexed
+    #[serde(default)]
+    pub spent_at: Option<u64>,
+}
+
+impl CashNoteMetadata {
+    fn available(value: NanoTokens, file_name: String) -> Self {
+        Self {
+            value,
+            file_name,
+            status: CashNoteStatus::Available,
+            spent_at: None,
+        }
+    }
+}
+
+/// What is kept aside of the log.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct LogState {
+    /// The length of the log its records fill
+    len: u64,
+    record_count: usize,
+    /// The number of indexed cash notes when the log was last replayed
+    indexed_count: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+enum IndexRecord {
+    Upsert(UniquePubkey, CashNoteMetadata),
+    Remove(UniquePubkey),
+}
+
+/// The cash notes indexed for a wallet.
+#[derive(Debug, Clone, Default)]
+pub struct CashNoteIndex {
+    entries: BTreeMap<UniquePubkey, CashNoteMetadata>,
+}
+
+impl CashNoteIndex {
+    /// Load the index of the wallet dir, empty if nothing has been indexed yet.
+    pub fn load(wallet_dir: &Path) -> Result<Self> {
+        let (index, _, _) = Self::read_log(wallet_dir)?;
+        Ok(index)
+    }
+
+    pub fn get(&self, unique_pubkey: &UniquePubkey) -> Option<&CashNoteMetadata> {
+        self.entries.get(unique_pubkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&UniquePubkey, &CashNoteMetadata)> {
+        self.entries.iter()
+    }
+
+    /// The indexed cash notes which are available, along with their values.
+    pub fn available(&self) -> impl Iterator<Item = (&UniquePubkey, NanoTokens)> {
+        self.entries
+            .iter()
+            .filter(|(_, metadata)| metadata.status == CashNoteStatus::Available)
+            .map(|(unique_pubkey, metadata)| (unique_pubkey, metadata.value))
+    }
+
+    /// The total value of the available cash notes.
+    pub fn balance(&self) -> NanoTokens {
+        NanoTokens::from(
+            self.available()
+                .map(|(_, value)| value.as_nano())
+                .fold(0, u64::saturating_add),
+        )
+    }
+
+    /// The names of the files of the indexed cash notes.
+    pub fn file_names(&self) -> BTreeSet<&str> {
+        self.entries
+            .values()
+            .map(|metadata| metadata.file_name.as_str())
+            .collect()
+    }
+
+    /// Index the cash notes stored to the `cash_notes` dir as available.
+    #[cfg(test)]
+    fn insert<I>(wallet_dir: &Path, cash_notes: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (UniquePubkey, NanoTokens, String)>,
+    {
+        let records = cash_notes
+            .into_iter()
+            .map(|(unique_pubkey, value, file_name)| {
+                IndexRecord::Upsert(unique_pubkey, CashNoteMetadata::available(value, file_name))
+            })
+            .collect();
+        Self::append(wallet_dir, records)
+    }
+
+    /// Drop the cash notes removed from the `cash_notes` dir from the index.
+    pub(super) fn remove<'a, I>(wallet_dir: &Path, unique_pubkeys: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a UniquePubkey>,
+    {
+        let records = unique_pubkeys
+            .into_iter()
+            .map(|unique_pubkey| IndexRecord::Remove(*unique_pubkey))
+            .collect();
+        Self::append(wallet_dir, records)
+    }
+
+    /// Bring the index in line with the wallet just stored: the cash notes it holds which are not
+    /// indexed yet are indexed as available, and the indexed ones it no longer holds as spent.
+    pub(super) fn sync(
+        wallet_dir: &Path,
+        available: &BTreeMap<UniquePubkey, NanoTokens>,
+    ) -> Result<()> {
+        let index = Self::load(wallet_dir)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+        let cash_notes_path = wallet_dir.join(CASHNOTES_DIR_NAME);
+        let mut records = vec![];
+        for (unique_pubkey, value) in available {
+            if index.entries.contains_key(unique_pubkey) {
+                continue;
+            }
+            // a cash note of a file dropped in the dir has been renamed after it by now
+            let file_name = cash_note_file_name(unique_pubkey);
+            if cash_notes_path.join(&file_name).is_file() {
+                let metadata = CashNoteMetadata::available(*value, file_name);
+                records.push(IndexRecord::Upsert(*unique_pubkey, metadata));
+            }
+        }
+        for (unique_pubkey, mut metadata) in index.entries {
+            if metadata.status == CashNoteStatus::Available
+                && !available.contains_key(&unique_pubkey)
+            {
+                metadata.status = CashNoteStatus::Spent;
+                metadata.spent_at = Some(now);
+                records.push(IndexRecord::Upsert(unique_pubkey, metadata));
+            }
+        }
+        Self::append(wallet_dir, records)
+    }
+
+    /// Append the records to the log, compacting it instead once it holds too many stale ones.
+    ///
+    /// The caller must hold the wallet lock, so that concurrent writers don't interleave records.
+    fn append(wallet_dir: &Path, records: Vec<IndexRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let path = wallet_dir.join(CASH_NOTE_INDEX_FILE_NAME);
+        let log_len = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+        let state = match read_state(wallet_dir) {
+            Some(state) if state.len == log_len => state,
+            _ => {
+                let (index, record_count, len) = Self::read_log(wallet_dir)?;
+                LogState {
+                    len,
+                    record_count,
+                    indexed_count: index.len(),
+                }
+            }
+        };
+
+        let record_count = state.record_count + records.len();
+        let state = if record_count > state.indexed_count * 2 + COMPACTION_SLACK {
+            let (mut index, _, _) = Self::read_log(wallet_dir)?;
+            for record in &records {
+                index.apply(record);
+            }
+            debug!(
+                "Compacting the cash note index of {record_count} records into {}",
+                index.len()
+            );
+            LogState {
+                len: index.write(wallet_dir)?,
+                record_count: index.len(),
+                indexed_count: index.len(),
+            }
+        } else {
+            // drop what is left of a record whose write was interrupted
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+            if log_len > state.len {
+                warn!("Dropping the interrupted last record of the cash note index");
+                file.set_len(state.len)?;
+            }
+            let mut bytes = vec![];
+            for record in &records {
+                encode_record(record, &mut bytes)?;
+            }
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+            LogState {
+                len: state.len + bytes.len() as u64,
+                record_count,
+                indexed_count: state.indexed_count,
+            }
+        };
+        write_state(wallet_dir, &state)
+    }
+
+    /// Write the whole index as a new log, replacing the current one, returning its length.
+    fn write(&self, wallet_dir: &Path) -> Result<u64> {
+        let mut bytes = vec![];
+        for (unique_pubkey, metadata) in &self.entries {
+            encode_record(
+                &IndexRecord::Upsert(*unique_pubkey, metadata.clone()),
+                &mut bytes,
+            )?;
+        }
+        let path = wallet_dir.join(CASH_NOTE_INDEX_FILE_NAME);
+        let tmp_path = path.with_extension("index.tmp");
+        fs::write(&tmp_path, &bytes)?;
+        fs::rename(tmp_path, path)?;
+        Ok(bytes.len() as u64)
+    }
+
+    fn apply(&mut self, record: &IndexRecord) {
+        match record {
+            IndexRecord::Upsert(unique_pubkey, metadata) => {
+                let _ = self.entries.insert(*unique_pubkey, metadata.clone());
+            }
+            IndexRecord::Remove(unique_pubkey) => {
+                let _ = self.entries.remove(unique_pubkey);
+            }
+        }
+    }
+
+    /// Replay the log, returning the index along with its number of records and the length of
+    /// the log they fill, which is short of the file length if the last write was interrupted.
+    fn read_log(wallet_dir: &Path) -> Result<(Self, usize, u64)> {
+        let mut index = Self::default();
+        let path = wallet_dir.join(CASH_NOTE_INDEX_FILE_NAME);
+        if !path.exists() {
+            return Ok((index, 0, 0));
+        }
+
+        let bytes = fs::read(&path)?;
+        let mut record_count = 0;
+        let mut pos = 0;
+        while let Some(len_bytes) = bytes.get(pos..pos + RECORD_LEN_SIZE) {
+            let mut len = [0; RECORD_LEN_SIZE];
+            len.copy_from_slice(len_bytes);
+            let start = pos + RECORD_LEN_SIZE;
+            let Some(record_bytes) = bytes.get(start..start + u32::from_le_bytes(len) as usize)
+            else {
+                break;
+            };
+            index.apply(&rmp_serde::from_slice(record_bytes)?);
+            record_count += 1;
+            pos = start + record_bytes.len();
+        }
+        Ok((index, record_count, pos as u64))
+    }
+}
+
+/// The state kept aside of the log, none if it can't be read.
+fn read_state(wallet_dir: &Path) -> Option<LogState> {
+    let bytes = fs::read(wallet_dir.join(CASH_NOTE_INDEX_STATE_FILE_NAME)).ok()?;
+    rmp_serde::from_slice(&bytes).ok()
+}
+
+fn write_state(wallet_dir: &Path, state: &LogState) -> Result<()> {
+    let path = wallet_dir.join(CASH_NOTE_INDEX_STATE_FILE_NAME);
+    let tmp_path = path.with_extension("state.tmp");
+    fs::write(&tmp_path, rmp_serde::to_vec(state)?)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+fn encode_record(record: &IndexRecord, bytes: &mut Vec<u8>) -> Result<()> {
+    let record_bytes = rmp_serde::to_vec(record)?;
+    bytes.extend((record_bytes.len() as u32).to_le_bytes());
+    bytes.extend(record_bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{fake_cash_note, WalletBuilder},
+        MainSecretKey,
+    };
+    use eyre::Result;
+
+    #[test]
+    fn cash_notes_should_be_indexed_as_they_are_stored_and_spent() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let wallet_dir = wallet.api().wallet_dir().to_path_buf();
+
+        let index = CashNoteIndex::load(&wallet_dir)?;
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.balance(), NanoTokens::from(1_000));
+        let (deposited, _) = index.iter().next().expect("one cash note to be indexed");
+        let deposited = *deposited;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+
+        let index = CashNoteIndex::load(&wallet_dir)?;
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index.get(&deposited).map(|metadata| metadata.status),
+            Some(CashNoteStatus::Spent)
+        );
+        assert_eq!(index.balance(), wallet.balance());
+
+        CashNoteIndex::remove(&wallet_dir, [&deposited])?;
+        assert_eq!(CashNoteIndex::load(&wallet_dir)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn cash_notes_should_only_be_indexed_once_the_wallet_is_stored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let wallet_dir = wallet.api().wallet_dir().to_path_buf();
+
+        // as left by a deposit interrupted before the wallet was stored
+        let cash_note = fake_cash_note(wallet.address(), NanoTokens::from(100));
+        wallet.store_cash_notes_to_disk([&cash_note])?;
+        assert!(CashNoteIndex::load(&wallet_dir)?
+            .get(&cash_note.unique_pubkey())
+            .is_none());
+
+        wallet.try_load_cash_notes()?;
+        assert_eq!(wallet.balance(), NanoTokens::from(1_100));
+        assert_eq!(
+            CashNoteIndex::load(&wallet_dir)?.balance(),
+            NanoTokens::from(1_100)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn an_interrupted_record_should_be_dropped() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let record = || {
+            let unique_pubkey = MainSecretKey::random()
+                .main_pubkey()
+                .new_unique_pubkey(&crate::DerivationIndex::random(&mut rand::thread_rng()));
+            (unique_pubkey, NanoTokens::from(1), "file".to_string())
+        };
+        CashNoteIndex::insert(dir.path(), [record()])?;
+        let log_path = dir.path().join(CASH_NOTE_INDEX_FILE_NAME);
+        let mut log = OpenOptions::new().append(true).open(&log_path)?;
+        log.write_all(&[42, 0, 0, 0, 1])?;
+
+        CashNoteIndex::insert(dir.path(), [record()])?;
+        let (index, record_count, len) = CashNoteIndex::read_log(dir.path())?;
+        assert_eq!((index.len(), record_count), (2, 2));
+        assert_eq!(fs::metadata(&log_path)?.len(), len);
+        assert_eq!(
+            read_state(dir.path()),
+            Some(LogState {
+                len,
+                record_count: 2,
+                indexed_count: 1
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn the_log_should_be_compacted() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let unique_pubkey = MainSecretKey::random()
+            .main_pubkey()
+            .new_unique_pubkey(&crate::DerivationIndex::random(&mut rand::thread_rng()));
+        let record = |value| (unique_pubkey, NanoTokens::from(value), "file".to_string());
+        CashNoteIndex::insert(dir.path(), (0..COMPACTION_SLACK as u64).map(record))?;
+        assert_eq!(CashNoteIndex::read_log(dir.path())?.1, COMPACTION_SLACK);
+
+        // all the records of the cash note but the last one are stale
+        CashNoteIndex::insert(
+            dir.path(),
+            (COMPACTION_SLACK as u64..COMPACTION_SLACK as u64 + 10).map(record),
+        )?;
+        let (index, record_count, _) = CashNoteIndex::read_log(dir.path())?;
+        assert_eq!(record_count, 1);
+        assert_eq!(
+            index.get(&unique_pubkey).map(|metadata| metadata.value),
+            Some(NanoTokens::from(COMPACTION_SLACK as u64 + 9))
+        );
+        Ok(())
+    }
+}
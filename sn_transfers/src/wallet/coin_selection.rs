@@ -0,0 +1,223 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The strategies picking the cash notes a send spends, trading off the number of inputs, the
+//! fragmentation of the wallet and the privacy of the change.
+//!
+//! They pick among the values of the cash notes only, for the wallet to read the files of the
+//! picked ones alone.
+
+use crate::{NanoTokens, UniquePubkey};
+use std::fmt::Debug;
+
+/// Picks the cash notes spent to send an amount.
+pub trait CoinSelection: Debug + Send + Sync {
+    /// Select among the available cash notes, given with their values, the ones to spend for
+    /// `amount`, in the order they are to be spent. When they are not enough, all of them are
+    /// returned, for the transaction to report the missing balance.
+    fn select(
+        &self,
+        available: Vec<(UniquePubkey, NanoTokens)>,
+        amount: NanoTokens,
+    ) -> Vec<UniquePubkey>;
+}
+
+/// Spend the biggest cash notes first, for as few inputs as possible: the default of the wallet.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LargestFirst;
+
+impl CoinSelection for LargestFirst {
+    fn select(
+        &self,
+        available: Vec<(UniquePubkey, NanoTokens)>,
+        amount: NanoTokens,
+    ) -> Vec<UniquePubkey> {
+        let mut sorted = with_values(available);
+        sorted.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+        take_until_covered(sorted, amount)
+    }
+}
+
+/// Spend the smallest cash notes first, consolidating the wallet into fewer notes as it sends.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmallestFirst;
+
+impl CoinSelection for SmallestFirst {
+    fn select(
+        &self,
+        available: Vec<(UniquePubkey, NanoTokens)>,
+        amount: NanoTokens,
+    ) -> Vec<UniquePubkey> {
+        let mut sorted = with_values(available);
+        sorted.sort_by_key(|(value, _)| *value);
+        take_until_covered(sorted, amount)
+    }
+}
+
+/// Search for cash notes adding up to exactly the amount, so the send has no change linking it
+/// back to the wallet, falling back to the biggest first when there are none.
+#[derive(Debug, Clone, Copy)]
+pub struct BranchAndBound {
+    /// The number of branches explored before giving up the search for an exact match
+    pub max_tries: usize,
+}
+
+impl Default for BranchAndBound {
+    fn default() -> Self {
+        Self { max_tries: 100_000 }
+    }
+}
+
+impl CoinSelection for BranchAndBound {
+    fn select(
+        &self,
+        available: Vec<(UniquePubkey, NanoTokens)>,
+        amount: NanoTokens,
+    ) -> Vec<UniquePubkey> {
+        let mut sorted = with_values(available);
+        sorted.sort_by_key(|(value, _)| std::cmp::Reverse(*value));
+        let values: Vec<u64> = sorted.iter().map(|(value, _)| *value).collect();
+
+        let mut search = ExactMatchSearch {
+            values: &values,
+            remaining_after: remaining_sums(&values),
+            tries_left: self.max_tries,
+            picked: vec![],
+        };
+        if search.explore(0, amount.as_nano()) {
+            let picked = search.picked;
+            return sorted
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| picked.contains(i))
+                .map(|(_, (_, unique_pubkey))| unique_pubkey)
+                .collect();
+        }
+
+        debug!("No exact match found for {amount}, spending the biggest cash notes first");
+        take_until_covered(sorted, amount)
+    }
+}
+
+/// The depth-first search of the cash notes adding up to an exact amount.
+struct ExactMatchSearch<'a> {
+    /// The values of the cash notes, biggest first
+    values: &'a [u64],
+    /// The sum of the values from each position to the end
+    remaining_after: Vec<u64>,
+    tries_left: usize,
+    /// The positions of the values picked so far
+    picked: Vec<usize>,
+}
+
+impl ExactMatchSearch<'_> {
+    /// Whether `target` can be reached with the values from `position` on.
+    fn explore(&mut self, position: usize, target: u64) -> bool {
+        if target == 0 {
+            return true;
+        }
+        if position == self.values.len()
+            || self.remaining_after[position] < target
+            || self.tries_left == 0
+        {
+            return false;
+        }
+        self.tries_left -= 1;
+
+        let value = self.values[position];
+        if value <= target {
+            self.picked.push(position);
+            if self.explore(position + 1, target - value) {
+                return true;
+            }
+            let _ = self.picked.pop();
+        }
+        self.explore(position + 1, target)
+    }
+}
+
+fn with_values(available: Vec<(UniquePubkey, NanoTokens)>) -> Vec<(u64, UniquePubkey)> {
+    available
+        .into_iter()
+        .map(|(unique_pubkey, value)| (value.as_nano(), unique_pubkey))
+        .collect()
+}
+
+fn remaining_sums(values: &[u64]) -> Vec<u64> {
+    let mut sums = vec![0u64; values.len() + 1];
+    for (i, value) in values.iter().enumerate().rev() {
+        sums[i] = sums[i + 1].saturating_add(*value);
+    }
+    sums
+}
+
+fn take_until_covered(sorted: Vec<(u64, UniquePubkey)>, amount: NanoTokens) -> Vec<UniquePubkey> {
+    let mut selected = vec![];
+    let mut total: u64 = 0;
+    for (value, unique_pubkey) in sorted {
+        if total >= amount.as_nano() {
+            break;
+        }
+        total = total.saturating_add(value);
+        selected.push(unique_pubkey);
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DerivationIndex, MainSecretKey};
+    use std::collections::BTreeMap;
+
+    fn select(selection: &dyn CoinSelection, values: &[u64], amount: u64) -> Vec<u64> {
+        let mut rng = rand::thread_rng();
+        let main_pubkey = MainSecretKey::random().main_pubkey();
+        let available: Vec<_> = values
+            .iter()
+            .map(|value| {
+                let derivation_index = DerivationIndex::random(&mut rng);
+                let unique_pubkey = main_pubkey.new_unique_pubkey(&derivation_index);
+                (unique_pubkey, NanoTokens::from(*value))
+            })
+            .collect();
+        let values: BTreeMap<_, _> = available.iter().copied().collect();
+        selection
+            .select(available, NanoTokens::from(amount))
+            .iter()
+            .map(|unique_pubkey| values[unique_pubkey].as_nano())
+            .collect()
+    }
+
+    #[test]
+    fn strategies_should_pick_the_notes_in_their_order() {
+        let values = [5, 1, 20, 3, 8];
+        assert_eq!(select(&LargestFirst, &values, 22), vec![20, 8]);
+        assert_eq!(select(&SmallestFirst, &values, 8), vec![1, 3, 5]);
+        // not enough balance: everything is returned
+        assert_eq!(select(&SmallestFirst, &values, 100), vec![1, 3, 5, 8, 20]);
+    }
+
+    #[test]
+    fn branch_and_bound_should_find_an_exact_match() {
+        let values = [5, 1, 20, 3, 8];
+        assert_eq!(
+            select(&BranchAndBound::default(), &values, 12),
+            vec![8, 3, 1]
+        );
+        // no exact match: biggest first
+        assert_eq!(
+            select(&BranchAndBound::default(), &[10, 10], 15),
+            vec![10, 10]
+        );
+        assert_eq!(
+            select(&BranchAndBound { max_tries: 0 }, &values, 12),
+            vec![20]
+        );
+    }
+}
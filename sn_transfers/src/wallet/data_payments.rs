@@ -6,15 +6,24 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{MainPubkey, NanoTokens, Transfer};
+use super::{Error, Result};
+use crate::{DerivationIndex, MainPubkey, NanoTokens, Transfer};
 use libp2p::{identity::PublicKey, PeerId};
-use serde::{Deserialize, Serialize};
-use std::time::SystemTime;
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+use std::time::{Duration, SystemTime};
 use xor_name::XorName;
 
 /// The time in seconds that a quote is valid for
 pub const QUOTE_EXPIRATION_SECS: u64 = 3600;
 
+/// The time in seconds a quote must still be valid for to be paid, leaving the time to upload the
+/// record before the node verifies the payment
+pub const QUOTE_MIN_VALIDITY_SECS: u64 = 60;
+
+/// The time in seconds the clock of a node may be ahead or behind ours, a quote being neither
+/// taken as issued in the future nor paid that late within that margin
+const QUOTE_CLOCK_SKEW_SECS: u64 = 30;
+
 /// The margin allowed for live_time
 const LIVE_TIME_MARGIN: u64 = 10;
 
@@ -25,6 +34,17 @@ pub struct Payment {
     pub transfers: Vec<Transfer>,
     /// The Quote we're paying for
     pub quote: PaymentQuote,
+    /// The quotes of the other holders paid for the same record, when paying more than one.
+    /// Each paid holder finds its own quote among `quote` and these by its signature.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other_quotes: Vec<PaymentQuote>,
+}
+
+impl Payment {
+    /// All the quotes paid by this payment.
+    pub fn quotes(&self) -> impl Iterator<Item = &PaymentQuote> {
+        std::iter::once(&self.quote).chain(self.other_quotes.iter())
+    }
 }
 
 /// Information relating to a data payment for one address
@@ -42,16 +62,35 @@ pub struct PaymentDetails {
     pub royalties: (Transfer, NanoTokens),
     /// The original quote
     pub quote: PaymentQuote,
+    /// The payments made to the other holders of the same address, when paying more than one.
+    #[serde(default)]
+    pub other_payees: Vec<PaymentDetails>,
 }
 
 impl PaymentDetails {
     /// create a Payment for a PaymentDetails
     pub fn to_payment(&self) -> Payment {
+        let mut transfers = vec![self.transfer.0.clone(), self.royalties.0.clone()];
+        for other in self.other_payees.iter() {
+            transfers.push(other.transfer.0.clone());
+            transfers.push(other.royalties.0.clone());
+        }
         Payment {
-            transfers: vec![self.transfer.0.clone(), self.royalties.0.clone()],
+            transfers,
             quote: self.quote.clone(),
+            other_quotes: self
+                .other_payees
+                .iter()
+                .map(|other| other.quote.clone())
+                .collect(),
         }
     }
+
+    /// The PeerIds (as bytes) of all the nodes paid.
+    pub fn all_peer_id_bytes(&self) -> impl Iterator<Item = &Vec<u8>> {
+        std::iter::once(&self.peer_id_bytes)
+            .chain(self.other_payees.iter().map(|other| &other.peer_id_bytes))
+    }
 }
 
 /// A generic type for signatures
@@ -91,12 +130,16 @@ impl Default for QuotingMetrics {
     }
 }
 
-/// A payment quote to store data given by a node to a client
-/// Note that the PaymentQuote is a contract between the node and itself to make sure the clients aren’t mispaying.
-/// It is NOT a contract between the client and the node.
-#[derive(
-    Clone, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize, custom_debug::Debug,
-)]
+/// A payment quote to store data given by a node to a client.
+///
+/// The quote is signed by the quoting node, over the content it is for, its cost and its expiry.
+/// Clients check it before paying, and the node checks it again when accepting the payment, so
+/// the price cannot change between the quote and the payment.
+///
+/// A quote is serialized as a map of its fields rather than as the array `rmp_serde` makes of
+/// structs, so that the peers predating a field skip it instead of failing to read the quote.
+/// Quotes serialized as an array by these peers are still read.
+#[derive(Clone, Eq, PartialEq, PartialOrd, Ord, Hash, Deserialize, custom_debug::Debug)]
 pub struct PaymentQuote {
     /// the content paid for
     pub content: XorName,
@@ -111,6 +154,36 @@ pub struct PaymentQuote {
     pub pub_key: Vec<u8>,
     #[debug(skip)]
     pub signature: QuoteSignature,
+    /// when the quote stops being valid.
+    /// The quotes of older nodes do not have one, they expire `QUOTE_EXPIRATION_SECS` after
+    /// their timestamp.
+    #[serde(default)]
+    pub expiry: Option<SystemTime>,
+    /// The external key the node's earnings are paid to, rather than to its own wallet.
+    /// As the node cannot decrypt the payments to it, they are to be sent unencrypted.
+    #[serde(default)]
+    pub reward_address: Option<MainPubkey>,
+}
+
+impl Serialize for PaymentQuote {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let len =
+            6 + usize::from(self.expiry.is_some()) + usize::from(self.reward_address.is_some());
+        let mut map = serializer.serialize_map(Some(len))?;
+        map.serialize_entry("content", &self.content)?;
+        map.serialize_entry("cost", &self.cost)?;
+        map.serialize_entry("timestamp", &self.timestamp)?;
+        map.serialize_entry("quoting_metrics", &self.quoting_metrics)?;
+        map.serialize_entry("pub_key", &self.pub_key)?;
+        map.serialize_entry("signature", &self.signature)?;
+        if let Some(expiry) = &self.expiry {
+            map.serialize_entry("expiry", expiry)?;
+        }
+        if let Some(reward_address) = &self.reward_address {
+            map.serialize_entry("reward_address", reward_address)?;
+        }
+        map.end()
+    }
 }
 
 impl PaymentQuote {
@@ -123,6 +196,8 @@ impl PaymentQuote {
             quoting_metrics: Default::default(),
             pub_key: vec![],
             signature: vec![],
+            expiry: None,
+            reward_address: None,
         }
     }
 
@@ -132,21 +207,49 @@ impl PaymentQuote {
         cost: NanoTokens,
         timestamp: SystemTime,
         quoting_metrics: &QuotingMetrics,
+        expiry: Option<SystemTime>,
+        reward_address: Option<MainPubkey>,
     ) -> Vec<u8> {
         let mut bytes = xorname.to_vec();
         bytes.extend_from_slice(&cost.to_bytes());
-        bytes.extend_from_slice(
-            &timestamp
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .expect("Unix epoch to be in the past")
-                .as_secs()
-                .to_le_bytes(),
-        );
+        bytes.extend_from_slice(&secs_since_epoch(timestamp).to_le_bytes());
         let serialised_quoting_metrics = rmp_serde::to_vec(quoting_metrics).unwrap_or_default();
         bytes.extend_from_slice(&serialised_quoting_metrics);
+        // not part of the bytes signed by older nodes
+        if let Some(expiry) = expiry {
+            bytes.extend_from_slice(&secs_since_epoch(expiry).to_le_bytes());
+        }
+        if let Some(reward_address) = reward_address {
+            bytes.extend_from_slice(&reward_address.to_bytes());
+        }
         bytes
     }
 
+    /// returns the bytes signed by the quoting node
+    fn signed_bytes(&self) -> Vec<u8> {
+        Self::bytes_for_signing(
+            self.content,
+            self.cost,
+            self.timestamp,
+            &self.quoting_metrics,
+            self.expiry,
+            self.reward_address,
+        )
+    }
+
+    /// A unique id of the quote, by which the nodes acknowledge the payments they were made for it
+    pub fn id(&self) -> XorName {
+        XorName::from_content_parts(&[&self.signed_bytes(), &self.signature])
+    }
+
+    /// The derivation index of the cash note paying this quote to an external reward address.
+    ///
+    /// The nodes sharing a reward address would otherwise all accept the same cash note, their
+    /// own record of the cash notes accepted being the only protection against its reuse.
+    pub fn derivation_index(&self) -> DerivationIndex {
+        DerivationIndex(self.id().0)
+    }
+
     /// Check self is signed by the claimed peer
     pub fn check_is_signed_by_claimed_peer(&self, claimed_peer: PeerId) -> bool {
         let pub_key = if let Ok(pub_key) = PublicKey::try_decode_protobuf(&self.pub_key) {
@@ -163,14 +266,7 @@ impl PaymentQuote {
             return false;
         }
 
-        let bytes = Self::bytes_for_signing(
-            self.content,
-            self.cost,
-            self.timestamp,
-            &self.quoting_metrics,
-        );
-
-        if !pub_key.verify(&bytes, &self.signature) {
+        if !pub_key.verify(&self.signed_bytes(), &self.signature) {
             error!("Signature is not signed by claimed pub_key");
             return false;
         }
@@ -178,15 +274,61 @@ impl PaymentQuote {
         true
     }
 
-    /// Returns true) if the quote has not yet expired
+    /// Returns when the quote stops being valid
+    pub fn expires_at(&self) -> SystemTime {
+        self.expiry
+            .unwrap_or(self.timestamp + Duration::from_secs(QUOTE_EXPIRATION_SECS))
+    }
+
+    /// Returns true if the quote has expired
     pub fn has_expired(&self) -> bool {
         let now = std::time::SystemTime::now();
 
-        let dur_s = match now.duration_since(self.timestamp) {
-            Ok(dur) => dur.as_secs(),
-            Err(_) => return true,
-        };
-        dur_s > QUOTE_EXPIRATION_SECS
+        // a quote from the future is not trusted either
+        if now < self.timestamp {
+            return true;
+        }
+        now > self.expires_at()
+    }
+
+    /// Check self is a quote the claimed peer signed for the content, and that it has not
+    /// expired, i.e. that it can still be paid.
+    pub fn is_valid_for(&self, claimed_peer: PeerId, content: XorName) -> bool {
+        if self.content != content {
+            error!(
+                "This quote {self:?} is for {:?} rather than {content:?}",
+                self.content
+            );
+            return false;
+        }
+        if self.has_expired() {
+            error!("This quote {self:?} has expired");
+            return false;
+        }
+        self.check_is_signed_by_claimed_peer(claimed_peer)
+    }
+
+    /// Check self can be paid for storing the content to the payee: it is a quote for this
+    /// content, that remains valid long enough for the record to be uploaded, and that rewards
+    /// the payee if it names a reward address.
+    pub fn check_payable(&self, content: XorName, payee: &MainPubkey) -> Result<()> {
+        if self.content != content {
+            return Err(Error::QuoteContentMismatch {
+                content,
+                quoted: self.content,
+            });
+        }
+        let now = SystemTime::now();
+        let skew = Duration::from_secs(QUOTE_CLOCK_SKEW_SECS);
+        if now + skew < self.timestamp
+            || now + Duration::from_secs(QUOTE_MIN_VALIDITY_SECS) + skew > self.expires_at()
+        {
+            return Err(Error::QuoteExpired(content));
+        }
+        if matches!(self.reward_address, Some(reward_address) if reward_address != *payee) {
+            return Err(Error::QuotePayeeMismatch(content));
+        }
+        Ok(())
     }
 
     /// test utility to create a dummy quote
@@ -198,6 +340,8 @@ impl PaymentQuote {
             quoting_metrics: Default::default(),
             pub_key: vec![],
             signature: vec![],
+            expiry: None,
+            reward_address: None,
         }
     }
 
@@ -265,6 +409,12 @@ impl PaymentQuote {
     }
 }
 
+fn secs_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .expect("Unix epoch to be in the past")
+        .as_secs()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,6 +422,79 @@ mod tests {
     use libp2p::identity::Keypair;
     use std::{thread::sleep, time::Duration};
 
+    /// A quote as read and written by the peers predating its optional fields.
+    #[derive(Serialize, Deserialize)]
+    struct LegacyPaymentQuote {
+        content: XorName,
+        cost: NanoTokens,
+        timestamp: SystemTime,
+        quoting_metrics: QuotingMetrics,
+        pub_key: Vec<u8>,
+        signature: QuoteSignature,
+    }
+
+    #[test]
+    fn quotes_should_be_read_by_older_and_newer_peers() -> eyre::Result<()> {
+        let mut quote =
+            PaymentQuote::test_dummy(XorName::random(&mut rand::thread_rng()), 100.into());
+        quote.expiry = Some(SystemTime::now());
+        quote.reward_address = Some(crate::MainSecretKey::random().main_pubkey());
+
+        // the peers predating the optional fields skip them
+        let legacy: LegacyPaymentQuote = rmp_serde::from_slice(&rmp_serde::to_vec(&quote)?)?;
+        assert_eq!(legacy.content, quote.content);
+        assert_eq!(legacy.signature, quote.signature);
+
+        // and the quotes they serialize as an array are read without them
+        let read: PaymentQuote = rmp_serde::from_slice(&rmp_serde::to_vec(&legacy)?)?;
+        assert_eq!(read.content, quote.content);
+        assert_eq!(read.expiry, None);
+        assert_eq!(read.reward_address, None);
+
+        let read: PaymentQuote = rmp_serde::from_slice(&rmp_serde::to_vec(&quote)?)?;
+        assert_eq!(read, quote);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_payable() {
+        let mut rng = bls::rand::thread_rng();
+        let content = XorName::random(&mut rng);
+        let payee = crate::MainSecretKey::random().main_pubkey();
+        let quote = PaymentQuote::test_dummy(content, NanoTokens::from(100));
+        assert!(quote.check_payable(content, &payee).is_ok());
+
+        let other_content = XorName::random(&mut rng);
+        assert!(matches!(
+            quote.check_payable(other_content, &payee),
+            Err(Error::QuoteContentMismatch { .. })
+        ));
+
+        let mut expiring_quote = quote.clone();
+        expiring_quote.expiry = Some(SystemTime::now() + Duration::from_secs(10));
+        assert!(matches!(
+            expiring_quote.check_payable(content, &payee),
+            Err(Error::QuoteExpired(_))
+        ));
+
+        // the clock of the node may be a little ahead of ours
+        let mut skewed_quote = quote.clone();
+        skewed_quote.timestamp = SystemTime::now() + Duration::from_secs(10);
+        assert!(skewed_quote.check_payable(content, &payee).is_ok());
+        skewed_quote.timestamp = SystemTime::now() + Duration::from_secs(QUOTE_CLOCK_SKEW_SECS * 2);
+        assert!(matches!(
+            skewed_quote.check_payable(content, &payee),
+            Err(Error::QuoteExpired(_))
+        ));
+
+        let mut rewarding_quote = quote;
+        rewarding_quote.reward_address = Some(crate::MainSecretKey::random().main_pubkey());
+        assert!(matches!(
+            rewarding_quote.check_payable(content, &payee),
+            Err(Error::QuotePayeeMismatch(_))
+        ));
+    }
+
     #[test]
     fn test_is_newer_than() {
         let old_quote = PaymentQuote::zero();
@@ -294,6 +517,8 @@ mod tests {
             quote.cost,
             quote.timestamp,
             &quote.quoting_metrics,
+            quote.expiry,
+            quote.reward_address,
         );
         let signature = if let Ok(sig) = keypair.sign(&bytes) {
             sig
@@ -320,6 +545,43 @@ mod tests {
         quote.pub_key = Keypair::generate_ed25519().public().encode_protobuf();
         assert!(!quote.check_is_signed_by_claimed_peer(peer_id));
         assert!(!quote.check_is_signed_by_claimed_peer(false_peer));
+
+        // Check failed with a reward address the node did not sign
+        quote.pub_key = keypair.public().encode_protobuf();
+        quote.reward_address = Some(crate::MainSecretKey::random().main_pubkey());
+        assert!(!quote.check_is_signed_by_claimed_peer(peer_id));
+    }
+
+    #[test]
+    fn test_is_valid_for() {
+        let keypair = Keypair::generate_ed25519();
+        let peer_id = keypair.public().to_peer_id();
+        let content = XorName::random(&mut rand::thread_rng());
+
+        let sign = |mut quote: PaymentQuote| {
+            quote.pub_key = keypair.public().encode_protobuf();
+            quote.signature = keypair
+                .sign(&quote.signed_bytes())
+                .expect("quote to be signed");
+            quote
+        };
+        let mut quote = PaymentQuote::test_dummy(content, NanoTokens::from(10));
+        quote.expiry = Some(SystemTime::now() + Duration::from_secs(60));
+        let quote = sign(quote);
+
+        assert!(quote.is_valid_for(peer_id, content));
+        assert!(!quote.is_valid_for(PeerId::random(), content));
+        assert!(!quote.is_valid_for(peer_id, XorName::random(&mut rand::thread_rng())));
+
+        // the expiry is covered by the signature
+        let mut extended_quote = quote.clone();
+        extended_quote.expiry = Some(SystemTime::now() + Duration::from_secs(7200));
+        assert!(!extended_quote.is_valid_for(peer_id, content));
+
+        let mut expired_quote = quote.clone();
+        expired_quote.expiry = Some(SystemTime::now() - Duration::from_secs(1));
+        let expired_quote = sign(expired_quote);
+        assert!(!expired_quote.is_valid_for(peer_id, content));
     }
 
     #[test]
@@ -0,0 +1,355 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The registry of the derivation indexes a wallet handed out, persisted in the wallet dir so an
+//! index is never handed out twice, even across restarts.
+//!
+//! Besides the random indexes, e.g. of the change, the wallet hands out sequential ones, derived
+//! from a secret seed and a sequence number per purpose. Those can be derived again from the key
+//! alone, which is what allows a "gap-limit" scan when recovering a wallet.
+//!
+//! Only the last [`MAX_RECORDED_INDEXES`] handed out are recorded, for the file not to grow with
+//! every transaction: a sequence only ever moves forward, and a random index never comes up again
+//! by chance. What the record still guards against is an index handed out again on purpose, and
+//! the wallet looks an index up while the transaction it was handed out for is applied.
+
+use super::{Error, Result};
+use crate::{DerivationIndex, Hash};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+const DERIVATION_INDEXES_FILE_NAME: &str = "derivation_indexes";
+
+/// The number of the last indexes handed out the registry records.
+const MAX_RECORDED_INDEXES: usize = 10_000;
+
+/// What a derivation index is handed out for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DerivationPurpose {
+    /// Receiving a payment requested from someone else
+    Invoice,
+    /// Receiving the change of a transfer
+    Change,
+    /// Keeping apart the funds of a sub-account of the wallet
+    SubAccount,
+}
+
+impl DerivationPurpose {
+    fn tag(&self) -> &'static [u8] {
+        match self {
+            Self::Invoice => b"invoice",
+            Self::Change => b"change",
+            Self::SubAccount => b"sub-account",
+        }
+    }
+}
+
+/// The record of a derivation index handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandedOutIndex {
+    pub purpose: DerivationPurpose,
+    /// Its number in the sequence of the purpose, none for a random index
+    pub sequence: Option<u64>,
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+struct RegistryContent {
+    next_sequences: BTreeMap<DerivationPurpose, u64>,
+    handed_out: BTreeMap<DerivationIndex, HandedOutIndex>,
+    /// The recorded indexes in the order they were handed out, the oldest first
+    #[serde(default)]
+    order: VecDeque<DerivationIndex>,
+}
+
+/// Where the registry records the indexes.
+enum RegistryBacking {
+    File(PathBuf),
+    /// Shared by the clones of the registry, e.g. of a wallet kept in memory only
+    Memory(Arc<Mutex<RegistryContent>>),
+}
+
+/// The derivation indexes handed out by a wallet.
+pub struct DerivationIndexRegistry {
+    backing: RegistryBacking,
+    content: RegistryContent,
+}
+
+impl Default for DerivationIndexRegistry {
+    /// An empty registry recording the indexes in memory only, shared with its clones.
+    fn default() -> Self {
+        Self {
+            backing: RegistryBacking::Memory(Default::default()),
+            content: RegistryContent::default(),
+        }
+    }
+}
+
+impl Clone for DerivationIndexRegistry {
+    fn clone(&self) -> Self {
+        match &self.backing {
+            RegistryBacking::File(path) => Self {
+                backing: RegistryBacking::File(path.clone()),
+                content: self.content.clone(),
+            },
+            // the clone starts from what the others recorded since this one was loaded
+            RegistryBacking::Memory(shared) => Self {
+                backing: RegistryBacking::Memory(Arc::clone(shared)),
+                content: shared
+                    .lock()
+                    .map(|content| content.clone())
+                    .unwrap_or_else(|_| self.content.clone()),
+            },
+        }
+    }
+}
+
+impl DerivationIndexRegistry {
+    /// Load the registry of the wallet dir, empty if nothing has been handed out yet.
+    pub fn load(wallet_dir: &Path) -> Result<Self> {
+        let path = wallet_dir.join(DERIVATION_INDEXES_FILE_NAME);
+        let content = if path.exists() {
+            let mut content: RegistryContent = rmp_serde::from_slice(&fs::read(&path)?)?;
+            // recorded before their order was
+            if content.order.len() != content.handed_out.len() {
+                content.order = content.handed_out.keys().copied().collect();
+            }
+            content
+        } else {
+            RegistryContent::default()
+        };
+        Ok(Self {
+            backing: RegistryBacking::File(path),
+            content,
+        })
+    }
+
+    fn store(&self) -> Result<()> {
+        match &self.backing {
+            // written aside then renamed, for an interrupted write to never lose the record
+            RegistryBacking::File(path) => {
+                let tmp_path = path.with_extension("tmp");
+                fs::write(&tmp_path, rmp_serde::to_vec(&self.content)?)?;
+                fs::rename(tmp_path, path)?;
+            }
+            RegistryBacking::Memory(shared) => {
+                if let Ok(mut shared) = shared.lock() {
+                    *shared = self.content.clone();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The record of the index, if it is among the last ones handed out.
+    pub fn get(&self, index: &DerivationIndex) -> Option<&HandedOutIndex> {
+        self.content.handed_out.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.content.handed_out.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.content.handed_out.is_empty()
+    }
+
+    /// The index at the given number of the sequence of the purpose, derived from the seed.
+    pub fn sequential_index(
+        seed: &Hash,
+        purpose: DerivationPurpose,
+        sequence: u64,
+    ) -> DerivationIndex {
+        let input = [seed.slice(), purpose.tag(), &sequence.to_be_bytes()].concat();
+        DerivationIndex(*Hash::hash(&input).slice())
+    }
+
+    /// Hand out the next index of the sequence of the purpose. It is recorded to disk before
+    /// being returned, for it to never be handed out again.
+    pub fn next_index(
+        &mut self,
+        seed: &Hash,
+        purpose: DerivationPurpose,
+    ) -> Result<DerivationIndex> {
+        let mut sequence = self.next_sequence(purpose);
+        let mut index = Self::sequential_index(seed, purpose, sequence);
+        while self.content.handed_out.contains_key(&index) {
+            sequence += 1;
+            index = Self::sequential_index(seed, purpose, sequence);
+        }
+        self.insert(index, purpose, Some(sequence));
+        self.store()?;
+        Ok(index)
+    }
+
+    /// Record an index handed out for the purpose, e.g. a random one, failing if it has already
+    /// been handed out.
+    pub fn register(&mut self, index: DerivationIndex, purpose: DerivationPurpose) -> Result<()> {
+        if self.content.handed_out.contains_key(&index) {
+            return Err(Error::DerivationIndexReused(index));
+        }
+        self.insert(index, purpose, None);
+        self.store()
+    }
+
+    /// Scan the sequence of the purpose from its start, recording the indexes found used by
+    /// `is_used`, until `gap_limit` indexes in a row are not. This recovers the indexes handed
+    /// out by a wallet restored from its key, the sequence resuming after the last used one.
+    ///
+    /// Returns the used indexes, in the order of the sequence.
+    pub fn scan<F>(
+        &mut self,
+        seed: &Hash,
+        purpose: DerivationPurpose,
+        gap_limit: u64,
+        mut is_used: F,
+    ) -> Result<Vec<DerivationIndex>>
+    where
+        F: FnMut(&DerivationIndex) -> Result<bool>,
+    {
+        let mut used = vec![];
+        let mut sequence = 0;
+        let mut gap = 0;
+        while gap < gap_limit {
+            let index = Self::sequential_index(seed, purpose, sequence);
+            if is_used(&index)? {
+                self.insert(index, purpose, Some(sequence));
+                used.push(index);
+                gap = 0;
+            } else {
+                gap += 1;
+            }
+            sequence += 1;
+        }
+        self.store()?;
+        Ok(used)
+    }
+
+    fn next_sequence(&self, purpose: DerivationPurpose) -> u64 {
+        self.content
+            .next_sequences
+            .get(&purpose)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn insert(
+        &mut self,
+        index: DerivationIndex,
+        purpose: DerivationPurpose,
+        sequence: Option<u64>,
+    ) {
+        if let Some(sequence) = sequence {
+            if sequence >= self.next_sequence(purpose) {
+                let _ = self.content.next_sequences.insert(purpose, sequence + 1);
+            }
+        }
+        if self
+            .content
+            .handed_out
+            .insert(index, HandedOutIndex { purpose, sequence })
+            .is_none()
+        {
+            self.content.order.push_back(index);
+        }
+        while self.content.order.len() > MAX_RECORDED_INDEXES {
+            if let Some(oldest) = self.content.order.pop_front() {
+                let _ = self.content.handed_out.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use eyre::Result;
+
+    #[test]
+    fn handed_out_indexes_should_never_be_reused() -> Result<()> {
+        let dir = TempDir::new()?;
+        let seed = Hash::hash(b"seed");
+        let mut registry = DerivationIndexRegistry::load(dir.path())?;
+
+        let first = registry.next_index(&seed, DerivationPurpose::Invoice)?;
+        let second = registry.next_index(&seed, DerivationPurpose::Invoice)?;
+        let sub_account = registry.next_index(&seed, DerivationPurpose::SubAccount)?;
+        assert_ne!(first, second);
+        assert_ne!(first, sub_account);
+        assert!(matches!(
+            registry.register(first, DerivationPurpose::Change),
+            Err(Error::DerivationIndexReused(_))
+        ));
+
+        // the registry survives a restart
+        let mut registry = DerivationIndexRegistry::load(dir.path())?;
+        assert_eq!(registry.len(), 3);
+        assert_eq!(
+            registry.get(&second),
+            Some(&HandedOutIndex {
+                purpose: DerivationPurpose::Invoice,
+                sequence: Some(1)
+            })
+        );
+        let third = registry.next_index(&seed, DerivationPurpose::Invoice)?;
+        assert_eq!(
+            third,
+            DerivationIndexRegistry::sequential_index(&seed, DerivationPurpose::Invoice, 2)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn scan_should_stop_after_the_gap_limit() -> Result<()> {
+        let dir = TempDir::new()?;
+        let seed = Hash::hash(b"seed");
+        let index = |sequence| {
+            DerivationIndexRegistry::sequential_index(&seed, DerivationPurpose::Invoice, sequence)
+        };
+        let used = [index(0), index(3), index(10)];
+
+        let mut registry = DerivationIndexRegistry::load(dir.path())?;
+        let found = registry.scan(&seed, DerivationPurpose::Invoice, 5, |index| {
+            Ok(used.contains(index))
+        })?;
+        // the last one is beyond the gap limit
+        assert_eq!(found, vec![index(0), index(3)]);
+        assert_eq!(
+            registry.next_index(&seed, DerivationPurpose::Invoice)?,
+            index(4)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_last_indexes_should_be_recorded() -> Result<()> {
+        let dir = TempDir::new()?;
+        let seed = Hash::hash(b"seed");
+        let mut registry = DerivationIndexRegistry::load(dir.path())?;
+        let mut scanned = 0;
+        let used = registry.scan(&seed, DerivationPurpose::Change, 1, |_| {
+            scanned += 1;
+            Ok(scanned <= MAX_RECORDED_INDEXES + 10)
+        })?;
+        assert_eq!(used.len(), MAX_RECORDED_INDEXES + 10);
+
+        let mut registry = DerivationIndexRegistry::load(dir.path())?;
+        assert_eq!(registry.len(), MAX_RECORDED_INDEXES);
+        assert_eq!(registry.get(&used[0]), None);
+        assert!(registry.get(&used[used.len() - 1]).is_some());
+        // the sequence still moves on from the last one
+        let next = registry.next_index(&seed, DerivationPurpose::Change)?;
+        assert!(!used.contains(&next));
+        Ok(())
+    }
+}
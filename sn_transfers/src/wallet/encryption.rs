@@ -45,7 +45,10 @@ impl EncryptedSecretKey {
 
         let encrypted_secret_key_path = wallet_dir.join(ENCRYPTED_MAIN_SECRET_KEY_FILENAME);
 
-        std::fs::write(encrypted_secret_key_path, serialized_data)?;
+        // Written aside then renamed, for a changed password to never leave a partial file behind
+        let tmp_path = encrypted_secret_key_path.with_extension("encrypted.tmp");
+        std::fs::write(&tmp_path, serialized_data)?;
+        std::fs::rename(tmp_path, encrypted_secret_key_path)?;
 
         Ok(())
     }
@@ -145,6 +148,64 @@ impl NonceSequence for NonceSeq {
     }
 }
 
+/// Encrypts arbitrary data with a key derived from the password, the same way as the secret key.
+/// Returns the salt and nonce it was encrypted with, along with the encrypted data.
+pub(crate) fn encrypt_data(data: &[u8], password: &str) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill(&mut salt);
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill(&mut nonce);
+
+    let mut key = [0; 32];
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA512,
+        ITERATIONS,
+        &salt,
+        password.as_bytes(),
+        &mut key,
+    );
+    let unbound_key = ring::aead::UnboundKey::new(&ring::aead::CHACHA20_POLY1305, &key)
+        .map_err(|_| Error::FailedToEncryptKey(String::from("Could not create unbound key.")))?;
+    let mut sealing_key = ring::aead::SealingKey::new(unbound_key, NonceSeq(nonce));
+
+    let mut encrypted = data.to_vec();
+    sealing_key
+        .seal_in_place_append_tag(ring::aead::Aad::from(&[]), &mut encrypted)
+        .map_err(|_| Error::FailedToEncryptKey(String::from("Could not seal the data.")))?;
+
+    Ok((salt.to_vec(), nonce.to_vec(), encrypted))
+}
+
+/// Decrypts the data encrypted by [`encrypt_data`] with the password, failing if it is wrong or
+/// the data was tampered with.
+pub(crate) fn decrypt_data(
+    salt: &[u8],
+    nonce: &[u8],
+    mut encrypted: Vec<u8>,
+    password: &str,
+) -> Result<Vec<u8>> {
+    let nonce: [u8; 12] = nonce
+        .try_into()
+        .map_err(|_| Error::FailedToDecryptKey(String::from("Invalid nonce length.")))?;
+
+    let mut key = [0; 32];
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA512,
+        ITERATIONS,
+        salt,
+        password.as_bytes(),
+        &mut key,
+    );
+    let unbound_key = ring::aead::UnboundKey::new(&ring::aead::CHACHA20_POLY1305, &key)
+        .map_err(|_| Error::FailedToDecryptKey(String::from("Could not create unbound key.")))?;
+    let mut opening_key = ring::aead::OpeningKey::new(unbound_key, NonceSeq(nonce));
+
+    let decrypted = opening_key
+        .open_in_place(ring::aead::Aad::from(&[]), &mut encrypted)
+        .map_err(|_| Error::FailedToDecryptKey(String::from("Could not open the data.")))?;
+    Ok(decrypted.to_vec())
+}
+
 /// Encrypts secret key using pbkdf2 with HMAC<Sha512>.
 pub(crate) fn encrypt_secret_key(
     secret_key: &MainSecretKey,
@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::UniquePubkey;
+use crate::{DerivationIndex, Hash, MainPubkey, NanoTokens, UniquePubkey};
 use std::{collections::BTreeSet, path::PathBuf};
 use thiserror::Error;
 use xor_name::XorName;
@@ -34,9 +34,37 @@ pub enum Error {
     /// A general error when a transfer fails
     #[error("Failed to send tokens due to {0}")]
     CouldNotSendMoney(String),
+    /// A transaction would spend more cash notes than the wallet allows
+    #[error("The transaction would spend {needed} cash notes, more than the {max} allowed: consolidate the wallet first")]
+    TooManyInputs { needed: usize, max: usize },
+    /// A transaction would create more cash notes than the wallet allows
+    #[error("The transaction would create {needed} cash notes, more than the {max} allowed")]
+    TooManyOutputs { needed: usize, max: usize },
+    /// The balance does not exceed what is to be kept, there is nothing to sweep
+    #[error("Nothing to sweep: the balance of {balance} does not exceed the {keep} to keep")]
+    NothingToSweep {
+        balance: NanoTokens,
+        keep: NanoTokens,
+    },
+    /// No quote was given to pay for the content
+    #[error("No quote found for {0:?}")]
+    NoQuoteFound(XorName),
+    /// The cash note paying for the content is not among the outputs of the payment
+    #[error("No cash note found to pay {payee} for {content:?}")]
+    PaymentCashNoteNotFound {
+        content: XorName,
+        /// Who was to be paid, i.e. the node or the royalties
+        payee: &'static str,
+    },
+    /// The transaction to sign is not valid
+    #[error("The transaction to sign is not valid")]
+    InvalidUnsignedTransaction(#[source] crate::TransferError),
     /// Failed to sign a transaction
-    #[error("Failed to sign a transaction: {0}")]
-    CouldNotSignTransaction(String),
+    #[error("Failed to sign a transaction")]
+    CouldNotSignTransaction(#[source] crate::TransferError),
+    /// The transaction is not valid once signed
+    #[error("The signed transaction is not valid")]
+    InvalidSignedTransaction(#[source] crate::TransferError),
     /// A general error when receiving a transfer fails
     #[error("Failed to receive transfer due to {0}")]
     CouldNotReceiveMoney(String),
@@ -90,16 +118,92 @@ pub enum Error {
     FailedToHexEncodeKey(String),
     /// Failed to serialize a cashnote to a hex
     #[error("Could not encode cashnote to hex")]
-    FailedToHexEncodeCashNote,
+    FailedToHexEncodeCashNote(#[source] crate::TransferError),
+    /// The file of a cash note of the wallet could not be parsed
+    #[error("Cash note file {path:?} is not valid")]
+    InvalidCashNoteFile {
+        path: PathBuf,
+        #[source]
+        source: Box<crate::TransferError>,
+    },
     /// Failed to decypher transfer with our key, maybe it was encrypted to another key
     #[error("Failed to decypher transfer with our key, maybe it was not for us")]
-    FailedToDecypherTransfer,
+    FailedToDecypherTransfer(#[source] crate::TransferError),
     /// No cached payment found for address
     #[error("No ongoing payment found for address {0:?}")]
     NoPaymentForAddress(XorName),
     /// The payment Quote has expired.
-    #[error("The payment quote made for {0:?} has expired")]
+    #[error("The payment quote made for {0:?} has expired, or is about to: a new quote is needed")]
     QuoteExpired(XorName),
+    /// The payment Quote is for other content
+    #[error("The payment quote made for {quoted:?} cannot pay for {content:?}: a quote for it is needed")]
+    QuoteContentMismatch { content: XorName, quoted: XorName },
+    /// The payment Quote rewards another key than the payee
+    #[error("The payment quote made for {0:?} rewards another key than the payee: a new quote is needed")]
+    QuotePayeeMismatch(XorName),
+    /// The disbursement cannot be planned as given
+    #[error("Invalid disbursement: {0}")]
+    InvalidDisbursement(String),
+    /// The approval of a disbursement is not valid
+    #[error("Invalid disbursement approval: {0}")]
+    InvalidApproval(String),
+    /// No disbursement is recorded with this id
+    #[error("No disbursement found with id {0:?}")]
+    DisbursementNotFound(Hash),
+    /// The wallet backup could not be read or is not valid
+    #[error("Invalid wallet backup: {0}")]
+    InvalidWalletBackup(String),
+    /// The wallet backup is of another key than the wallet it is imported into
+    #[error("The wallet backup is of another key than the wallet of {0:?}")]
+    WalletBackupKeyMismatch(MainPubkey),
+    /// The name of a wallet is not valid
+    #[error("Invalid wallet name {0:?}: only letters, digits, '-' and '_' are allowed")]
+    InvalidWalletName(String),
+    /// A wallet with this name already exists
+    #[error("Wallet {0:?} already exists")]
+    WalletAlreadyExists(String),
+    /// No wallet with this name exists
+    #[error("Wallet {0:?} not found")]
+    WalletNotFound(String),
+    /// The wallet still has a balance, deleting it would lose its funds
+    #[error("Wallet {name:?} still has a balance of {balance}, which would be lost")]
+    WalletNotEmpty { name: String, balance: NanoTokens },
+    /// The wallet has spends pending, deleting it would lose their change
+    #[error("Wallet {name:?} has {count} pending spends, whose change would be lost")]
+    WalletHasPendingSpends { name: String, count: usize },
+    /// The wallet file could not be decoded
+    #[error("Invalid wallet file: {0}")]
+    InvalidWalletFile(String),
+    /// The label cannot name a recipient in the address book
+    #[error("Invalid address book label {0:?}: it can be neither blank nor a pubkey")]
+    InvalidAddressBookLabel(String),
+    /// The label already names another recipient in the address book
+    #[error("The address book label {0:?} already names another recipient")]
+    AddressBookLabelTaken(String),
+    /// No recipient of the address book has this label
+    #[error("No recipient labelled {0:?} in the address book")]
+    UnknownRecipient(String),
+    /// The payment request could not be parsed
+    #[error("Invalid payment request {0:?}")]
+    InvalidPaymentRequest(String),
+    /// The payment request is no longer payable
+    #[error("The payment request has expired")]
+    PaymentRequestExpired,
+    /// The payment request leaves the amount to the payer, who gave none
+    #[error("The payment request has no amount, one has to be given")]
+    PaymentRequestAmountMissing,
+    /// The amount given differs from the one requested
+    #[error("The payment request is for {requested}, not {given}")]
+    PaymentRequestAmountMismatch {
+        requested: NanoTokens,
+        given: NanoTokens,
+    },
+    /// The spend needed an approval it did not get
+    #[error("The spend of {amount} was not approved: {reason}")]
+    SpendNotApproved { amount: NanoTokens, reason: String },
+    /// The derivation index has already been handed out
+    #[error("Derivation index {0:?} has already been handed out")]
+    DerivationIndexReused(DerivationIndex),
 
     /// DAG error
     #[error("DAG error: {0}")]
@@ -117,9 +221,18 @@ pub enum Error {
     #[error("MsgPack deserialisation error:: {0}")]
     Deserialisation(#[from] rmp_serde::decode::Error),
     /// I/O error
+    #[error("JSON serialisation error: {0}")]
+    Json(#[from] serde_json::Error),
+
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// What is asked of the wallet is only kept in the files of a wallet dir
+    #[error("The wallet is not stored on disk, it keeps no {0}")]
+    NotStoredOnDisk(&'static str),
+    /// The wallet is locked by another process
+    #[error("Wallet at {0:?} is locked by another process")]
+    WalletLocked(std::path::PathBuf),
     /// Wallet password is incorrect
     #[error("Wallet password is incorrect")]
     WalletPasswordIncorrect,
@@ -132,4 +245,19 @@ pub enum Error {
     /// Wallet is already encrypted
     #[error("Wallet is already encrypted")]
     WalletAlreadyEncrypted,
+    /// Wallet is not encrypted
+    #[error("Wallet is not encrypted")]
+    WalletNotEncrypted,
+    /// An idempotent send id was reused for another transfer
+    #[error("The send id {0:?} was already used for another amount or recipient")]
+    IdempotentSendMismatch(String),
+    /// The cash notes of an idempotent send were spent by another transaction before it was
+    /// applied
+    #[error(
+        "The send id {0:?} was never applied, its cash notes being spent by another transaction"
+    )]
+    IdempotentSendSuperseded(String),
+    /// A hook of the wallet aborted the mutation
+    #[error("Wallet hook failed: {0}")]
+    HookFailed(String),
 }
@@ -0,0 +1,361 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The history of the tokens received and sent by a wallet, recorded in the wallet dir as they are
+//! deposited and spent locally.
+
+use super::Result;
+use crate::{Hash, MainPubkey, NanoTokens, SignedTransaction, UniquePubkey};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The history recorded before it became an append-only log, as a single list of entries.
+const HISTORY_FILE_NAME: &str = "history";
+/// The append-only log of the history, each entry prefixed by its length.
+const HISTORY_LOG_FILE_NAME: &str = "history.log";
+const ENTRY_LEN_SIZE: usize = 4;
+
+/// What moved the tokens of an entry of the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryKind {
+    /// Tokens deposited to the wallet
+    Received,
+    /// Tokens sent to other wallets
+    Sent,
+    /// Tokens paid to nodes for storing data, the fee being the network royalties
+    StoragePayment,
+}
+
+/// An entry of the history of a wallet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When it was recorded, in seconds since the Unix epoch
+    pub time: u64,
+    pub kind: HistoryKind,
+    /// The amount received or sent, without the fee
+    pub amount: NanoTokens,
+    pub fee: NanoTokens,
+    /// The recipient of the tokens sent, if there is a single one
+    pub counterparty: Option<MainPubkey>,
+    /// The cash notes received, or the ones spent to send the tokens
+    pub cash_notes: Vec<UniquePubkey>,
+    /// The hash of the reason of the spends sending the tokens
+    #[serde(default)]
+    pub reason_hash: Hash,
+    /// The cash notes created by the spends sending the tokens, the change included
+    #[serde(default)]
+    pub created_cash_notes: Vec<UniquePubkey>,
+}
+
+impl HistoryEntry {
+    /// A new entry, recorded now.
+    pub fn new(
+        kind: HistoryKind,
+        amount: NanoTokens,
+        fee: NanoTokens,
+        counterparty: Option<MainPubkey>,
+        cash_notes: Vec<UniquePubkey>,
+    ) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        Self {
+            time,
+            kind,
+            amount,
+            fee,
+            counterparty,
+            cash_notes,
+            reason_hash: Hash::default(),
+            created_cash_notes: vec![],
+        }
+    }
+
+    /// Set the cash notes spent, the reason and the created cash notes of the transaction sending
+    /// the tokens.
+    pub fn with_transaction(mut self, signed_tx: &SignedTransaction) -> Self {
+        self.cash_notes = signed_tx
+            .spends
+            .iter()
+            .map(|spend| *spend.unique_pubkey())
+            .collect();
+        self.reason_hash = signed_tx
+            .spends
+            .first()
+            .map(|spend| spend.reason().hash())
+            .unwrap_or_default();
+        self.created_cash_notes = signed_tx
+            .output_cashnotes
+            .iter()
+            .chain(&signed_tx.change_cashnote)
+            .map(|cash_note| cash_note.unique_pubkey())
+            .collect();
+        self
+    }
+}
+
+/// The criteria selecting entries of the history, all of the given ones to be met.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    /// Only the entries recorded at or after this time, in seconds since the Unix epoch
+    pub since: Option<u64>,
+    /// Only the entries recorded before this time, in seconds since the Unix epoch
+    pub until: Option<u64>,
+    pub kind: Option<HistoryKind>,
+    pub counterparty: Option<MainPubkey>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        !matches!(self.since, Some(since) if entry.time < since)
+            && !matches!(self.until, Some(until) if entry.time >= until)
+            && !matches!(self.kind, Some(kind) if entry.kind != kind)
+            && !matches!(self.counterparty, Some(pk) if entry.counterparty != Some(pk))
+    }
+}
+
+/// The history of a wallet, oldest entry first.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Load the history recorded in the wallet dir, empty if there is no record yet
+    pub fn load(wallet_dir: &Path) -> Result<Self> {
+        Self::load_up_to(wallet_dir, u64::MAX)
+    }
+
+    /// Load the history recorded in the wallet dir by the time its log was `log_len` long,
+    /// leaving out the entries appended since.
+    pub(super) fn load_up_to(wallet_dir: &Path, log_len: u64) -> Result<Self> {
+        let path = wallet_dir.join(HISTORY_FILE_NAME);
+        let mut entries: Vec<HistoryEntry> = if path.exists() {
+            rmp_serde::from_slice(&fs::read(&path)?)?
+        } else {
+            vec![]
+        };
+        let (logged, _) = Self::read_log(wallet_dir, log_len)?;
+        entries.extend(logged);
+        Ok(Self { entries })
+    }
+
+    /// The current length of the log of the history recorded in the wallet dir.
+    pub(super) fn log_len(wallet_dir: &Path) -> Result<u64> {
+        match fs::metadata(wallet_dir.join(HISTORY_LOG_FILE_NAME)) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// The entries matching the filter, oldest first, skipping the first `offset` of them and
+    /// returning `limit` of them at most.
+    pub fn query(&self, filter: &HistoryFilter, offset: usize, limit: usize) -> Vec<&HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .skip(offset)
+            .take(limit)
+            .collect()
+    }
+
+    /// Append an entry to the history recorded in the wallet dir.
+    ///
+    /// The caller must hold the wallet lock, so that concurrent writers don't interleave entries.
+    pub(super) fn record(wallet_dir: &Path, entry: HistoryEntry) -> Result<()> {
+        let bytes = rmp_serde::to_vec(&entry)?;
+        let len = (bytes.len() as u32).to_le_bytes();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(wallet_dir.join(HISTORY_LOG_FILE_NAME))?;
+        // drop what is left of an entry whose write was interrupted
+        let valid_len = Self::valid_log_len(&mut file)?;
+        if file.metadata()?.len() > valid_len {
+            warn!("Dropping the interrupted last entry of the wallet history");
+            file.set_len(valid_len)?;
+        }
+        file.write_all(&[len.as_slice(), &bytes].concat())?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// The length of the log filled by whole entries, found from their lengths alone.
+    fn valid_log_len(file: &mut File) -> Result<u64> {
+        let file_len = file.metadata()?.len();
+        let mut pos = file.seek(SeekFrom::Start(0))?;
+        let mut len = [0; ENTRY_LEN_SIZE];
+        while pos + ENTRY_LEN_SIZE as u64 <= file_len {
+            file.read_exact(&mut len)?;
+            let end = pos + ENTRY_LEN_SIZE as u64 + u64::from(u32::from_le_bytes(len));
+            if end > file_len {
+                break;
+            }
+            pos = file.seek(SeekFrom::Start(end))?;
+        }
+        Ok(pos)
+    }
+
+    /// Read the entries of the first `max_len` bytes of the log, along with the length of the log
+    /// they fill, which is short of the file length if the write of the last entry was interrupted.
+    fn read_log(wallet_dir: &Path, max_len: u64) -> Result<(Vec<HistoryEntry>, u64)> {
+        let path = wallet_dir.join(HISTORY_LOG_FILE_NAME);
+        if !path.exists() {
+            return Ok((vec![], 0));
+        }
+
+        let mut bytes = fs::read(&path)?;
+        bytes.truncate(usize::try_from(max_len).unwrap_or(usize::MAX));
+        let mut entries = vec![];
+        let mut pos = 0;
+        while let Some(len_bytes) = bytes.get(pos..pos + ENTRY_LEN_SIZE) {
+            let mut len = [0; ENTRY_LEN_SIZE];
+            len.copy_from_slice(len_bytes);
+            let start = pos + ENTRY_LEN_SIZE;
+            let Some(entry_bytes) = bytes.get(start..start + u32::from_le_bytes(len) as usize)
+            else {
+                break;
+            };
+            entries.push(rmp_serde::from_slice(entry_bytes)?);
+            pos = start + entry_bytes.len();
+        }
+        Ok((entries, pos as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::WalletBuilder, MainSecretKey};
+    use eyre::Result;
+
+    #[test]
+    fn deposits_and_sends_should_be_recorded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+
+        let history = History::load(wallet.api().wallet_dir())?;
+        let entries = history.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, HistoryKind::Received);
+        assert_eq!(entries[0].amount, NanoTokens::from(1_000));
+        assert_eq!(entries[1].kind, HistoryKind::Sent);
+        assert_eq!(entries[1].amount, NanoTokens::from(100));
+        assert_eq!(entries[1].counterparty, Some(recipient));
+        assert!(!entries[1].cash_notes.is_empty());
+        assert!(!entries[1].created_cash_notes.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn sends_should_not_fail_on_the_history() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let wallet_dir = wallet.api().wallet_dir().to_path_buf();
+        // the log cannot be opened as a file
+        fs::remove_file(wallet_dir.join(HISTORY_LOG_FILE_NAME))?;
+        fs::create_dir(wallet_dir.join(HISTORY_LOG_FILE_NAME))?;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        assert_eq!(wallet.balance(), NanoTokens::from(900));
+        Ok(())
+    }
+
+    #[test]
+    fn entries_should_be_queried_by_filter_and_page() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let recipient = MainSecretKey::random().main_pubkey();
+        for time in 0..10 {
+            let counterparty = (time % 2 == 0).then_some(recipient);
+            let mut entry = HistoryEntry::new(
+                HistoryKind::Sent,
+                NanoTokens::from(time),
+                NanoTokens::zero(),
+                counterparty,
+                vec![],
+            );
+            entry.time = time;
+            History::record(dir.path(), entry)?;
+        }
+        let history = History::load(dir.path())?;
+
+        let filter = HistoryFilter {
+            since: Some(2),
+            until: Some(9),
+            counterparty: Some(recipient),
+            ..Default::default()
+        };
+        let times = |entries: Vec<&HistoryEntry>| -> Vec<u64> {
+            entries.iter().map(|entry| entry.time).collect()
+        };
+        assert_eq!(times(history.query(&filter, 0, 10)), vec![2, 4, 6, 8]);
+        assert_eq!(times(history.query(&filter, 1, 2)), vec![4, 6]);
+        assert!(history
+            .query(
+                &HistoryFilter {
+                    kind: Some(HistoryKind::Received),
+                    ..Default::default()
+                },
+                0,
+                10
+            )
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn interrupted_entries_should_be_dropped_and_legacy_ones_kept() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let entry = |amount| {
+            HistoryEntry::new(
+                HistoryKind::Received,
+                NanoTokens::from(amount),
+                NanoTokens::zero(),
+                None,
+                vec![],
+            )
+        };
+        fs::write(
+            dir.path().join(HISTORY_FILE_NAME),
+            rmp_serde::to_vec(&vec![entry(1)])?,
+        )?;
+        History::record(dir.path(), entry(2))?;
+
+        // an entry whose write was interrupted
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(HISTORY_LOG_FILE_NAME))?;
+        file.write_all(&[100, 0, 0, 0, 1, 2])?;
+        assert_eq!(History::load(dir.path())?.entries().len(), 2);
+
+        History::record(dir.path(), entry(3))?;
+        let amounts: Vec<_> = History::load(dir.path())?
+            .entries()
+            .iter()
+            .map(|entry| entry.amount.as_nano())
+            .collect();
+        assert_eq!(amounts, vec![1, 2, 3]);
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The hooks an application registers on a wallet to mirror its mutations into its own storage,
+//! e.g. the ledger of an exchange, keeping it consistent with the wallet without polling its files.
+
+use super::{PaymentDetails, Result};
+use crate::{CashNote, SignedTransaction};
+use std::collections::BTreeMap;
+use xor_name::XorName;
+
+/// Callbacks invoked on the mutations of a wallet, before they are applied.
+///
+/// A hook returning an error aborts the mutation, which leaves the wallet as it was. Return
+/// [`Error::HookFailed`] to report the failures of the application storage.
+///
+/// The converse does not hold: the wallet and the application storage are not written in one
+/// transaction. A mutation can still fail once its hook succeeded, e.g. when storing the wallet
+/// to disk, or be lost if the process stops before it is stored, leaving the application records
+/// ahead of the wallet. The same cash notes may then be deposited again, so the hooks should be
+/// idempotent, and the application should reconcile its records with the wallet, e.g. on start.
+///
+/// [`Error::HookFailed`]: super::Error::HookFailed
+pub trait WalletHooks: Send + Sync {
+    /// Called with the cash notes deposited to the wallet, excluding the ones it already had.
+    fn on_deposit(&self, _cash_notes: &[CashNote]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with a transaction spending cash notes of the wallet, along with the payments it
+    /// makes for the storage of records, if any.
+    fn on_spend(
+        &self,
+        _signed_tx: &SignedTransaction,
+        _storage_payments: &BTreeMap<XorName, PaymentDetails>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
@@ -6,18 +6,37 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+#[cfg(feature = "async-io")]
+use super::async_io::spawn_blocking;
 use super::{
+    address_book::{AddressBook, Recipient},
     api::{WalletApi, WALLET_DIR_NAME},
+    approval::{check_spend_approval, outgoing_amount},
+    backup::WalletBackup,
+    cash_note_index::{CashNoteIndex, CashNoteStatus},
+    coin_selection::{CoinSelection, LargestFirst},
     data_payments::{PaymentDetails, PaymentQuote},
-    keys::{get_main_key_from_disk, store_new_keypair},
+    derivation_indexes::{DerivationIndexRegistry, DerivationPurpose},
+    history::{History, HistoryEntry, HistoryKind},
+    hooks::WalletHooks,
+    keys::{get_main_key_from_disk, signed_message_bytes, store_new_keypair},
+    payment_request::PaymentRequest,
+    spent_archive::{ArchivedCashNote, SpentArchive},
+    store::{MemoryWalletStore, WalletExclusiveAccess, WalletStore},
+    unconfirmed_spends::{
+        load_unconfirmed_spend_metadata, remove_unconfirmed_spend_metadata,
+        store_unconfirmed_spend_metadata, sync_unconfirmed_spend_metadata, ResendPolicy,
+        UnconfirmedSpendMetadata,
+    },
     wallet_file::{
-        get_confirmed_spend, get_unconfirmed_spend_requests, has_confirmed_spend,
-        load_created_cash_note, remove_cash_notes, remove_unconfirmed_spend_requests,
-        store_created_cash_notes, store_unconfirmed_spend_requests,
+        get_idempotent_send, get_unconfirmed_spend_requests, load_created_cash_notes,
+        load_indexed_cash_notes, remove_cash_notes, store_created_cash_notes,
+        store_idempotent_send, WalletFileFormat,
     },
     watch_only::WatchOnlyWallet,
-    Error, KeyLessWallet, Result,
+    Error, KeyLessWallet, Result, WalletSnapshot,
 };
+use crate::rand::{rngs::OsRng, CryptoRng, RngCore};
 use crate::wallet::authentication::AuthenticationManager;
 use crate::wallet::encryption::EncryptedSecretKey;
 use crate::wallet::keys::{
@@ -26,20 +45,53 @@ use crate::wallet::keys::{
 };
 use crate::{
     calculate_royalties_fee, transfers::SignedTransaction, CashNote, CashNoteRedemption,
-    DerivationIndex, DerivedSecretKey, MainPubkey, MainSecretKey, NanoTokens, SignedSpend,
+    DerivationIndex, DerivedSecretKey, Hash, MainPubkey, MainSecretKey, NanoTokens, SignedSpend,
     SpendAddress, SpendReason, Transfer, UniquePubkey, UnsignedTransaction, WalletError,
     NETWORK_ROYALTIES_PK,
 };
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{BTreeMap, BTreeSet, HashSet},
-    fs::File,
+    collections::{BTreeMap, BTreeSet, HashSet, VecDeque},
     path::{Path, PathBuf},
-    time::Instant,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use xor_name::XorName;
 
-/// A locked file handle, that when dropped releases the lock.
-pub type WalletExclusiveAccess = File;
+/// The payees quoted for each content address paid for, with the quote and the payee's peer id
+/// bytes.
+pub type QuotesByAddress = BTreeMap<XorName, Vec<(MainPubkey, PaymentQuote, Vec<u8>)>>;
+
+/// The record of a send made with [`HotWallet::send_idempotent`].
+#[derive(Serialize, Deserialize)]
+struct IdempotentSend {
+    amount: NanoTokens,
+    to: MainPubkey,
+    signed_tx: SignedTransaction,
+}
+
+/// The maximum number of cash notes a transaction of the wallet spends by default.
+pub const DEFAULT_MAX_TX_INPUTS: usize = 100;
+/// The maximum number of cash notes a transaction of the wallet creates by default, change
+/// included.
+pub const DEFAULT_MAX_TX_OUTPUTS: usize = 1_000;
+
+/// The limits on the size of the transactions of the wallet. A payment exceeding them is split
+/// into several transactions when it can be, e.g. the storage payment of many records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionLimits {
+    pub max_inputs: usize,
+    pub max_outputs: usize,
+}
+
+impl Default for TransactionLimits {
+    fn default() -> Self {
+        Self {
+            max_inputs: DEFAULT_MAX_TX_INPUTS,
+            max_outputs: DEFAULT_MAX_TX_OUTPUTS,
+        }
+    }
+}
 
 /// A hot-wallet.
 pub struct HotWallet {
@@ -50,9 +102,16 @@ pub struct HotWallet {
     watchonly_wallet: WatchOnlyWallet,
     /// These have not yet been successfully sent to the network
     /// and need to be, to reach network validity.
-    unconfirmed_spend_requests: BTreeSet<SignedSpend>,
+    /// Shared with the snapshots of the wallet, and copied on write while they are held.
+    unconfirmed_spend_requests: Arc<BTreeSet<SignedSpend>>,
+    /// When the unconfirmed spends were created and how their resends went.
+    unconfirmed_spend_metadata: BTreeMap<SpendAddress, UnconfirmedSpendMetadata>,
     /// Handles authentication of (encrypted) wallets.
     authentication_manager: AuthenticationManager,
+    /// The limits on the size of the transactions of the wallet.
+    tx_limits: TransactionLimits,
+    /// What is left to send of the spends approved ahead, e.g. by `approve_spend_async`.
+    approved_allowance: NanoTokens,
 }
 
 impl HotWallet {
@@ -64,10 +123,49 @@ impl HotWallet {
             key,
             watchonly_wallet,
             unconfirmed_spend_requests: Default::default(),
+            unconfirmed_spend_metadata: Default::default(),
             authentication_manager: AuthenticationManager::new(wallet_dir),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
         }
     }
 
+    /// Loads the wallet of the key from the store, or creates it there.
+    ///
+    /// With a store which is not on disk, e.g. a `MemoryWalletStore`, nothing is written to disk:
+    /// the wallet keeps no history, index of its cash notes nor any of the other files of a
+    /// wallet dir then.
+    pub fn with_store(key: MainSecretKey, store: Arc<dyn WalletStore>) -> Result<Self> {
+        let authentication_manager = match store.wallet_dir() {
+            Some(wallet_dir) => AuthenticationManager::new(wallet_dir.to_path_buf()),
+            None => AuthenticationManager::in_memory(),
+        };
+        let unconfirmed_spend_requests =
+            store.load_unconfirmed_spend_requests()?.unwrap_or_default();
+        let unconfirmed_spend_metadata = match store.wallet_dir() {
+            Some(wallet_dir) => {
+                load_unconfirmed_spend_metadata(wallet_dir, &unconfirmed_spend_requests)?
+            }
+            None => Default::default(),
+        };
+        let watchonly_wallet = WatchOnlyWallet::with_store(key.main_pubkey(), store)?;
+
+        Ok(Self {
+            key,
+            watchonly_wallet,
+            unconfirmed_spend_requests: Arc::new(unconfirmed_spend_requests),
+            unconfirmed_spend_metadata,
+            authentication_manager,
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
+        })
+    }
+
+    /// Creates an empty wallet of the key, kept in memory only.
+    pub fn in_memory(key: MainSecretKey) -> Result<Self> {
+        Self::with_store(key, Arc::new(MemoryWalletStore::default()))
+    }
+
     pub fn key(&self) -> &MainSecretKey {
         &self.key
     }
@@ -88,6 +186,16 @@ impl HotWallet {
         &mut self.watchonly_wallet
     }
 
+    /// Register the hooks to call on the mutations of the wallet, replacing any previous ones.
+    pub fn set_hooks(&mut self, hooks: Arc<dyn WalletHooks>) {
+        self.watchonly_wallet.set_hooks(hooks);
+    }
+
+    /// Set the limits on the size of the transactions of the wallet.
+    pub fn set_transaction_limits(&mut self, tx_limits: TransactionLimits) {
+        self.tx_limits = tx_limits;
+    }
+
     /// Returns whether a wallet in the specified directory is encrypted or not.
     pub fn is_encrypted(root_dir: &Path) -> bool {
         let wallet_dir = root_dir.join(WALLET_DIR_NAME);
@@ -102,10 +210,22 @@ impl HotWallet {
 
     /// Reloads the wallet from disk. If the wallet secret key is encrypted, you'll need to specify the password.
     fn reload(&mut self) -> Result<()> {
+        if self.watchonly_wallet.dir_on_disk().is_none() {
+            // the key is not stored along with the state then, only the state is to reload
+            self.watchonly_wallet.reload()?;
+            self.unconfirmed_spend_requests = Arc::new(
+                self.watchonly_wallet
+                    .wallet_store()
+                    .load_unconfirmed_spend_requests()?
+                    .unwrap_or_default(),
+            );
+            return Ok(());
+        }
+
         // Password needed to decrypt wallet if it is encrypted
         let opt_password = self.authenticate()?;
 
-        let wallet =
+        let mut wallet =
             Self::load_from_path_and_key(self.watchonly_wallet.wallet_dir(), None, opt_password)?;
 
         if *wallet.key.secret_key() != *self.key.secret_key() {
@@ -114,7 +234,11 @@ impl HotWallet {
             ));
         }
 
-        // if it's a matching key, we can overwrite our wallet
+        // if it's a matching key, we can overwrite our wallet, keeping its hooks and limits
+        if let Some(hooks) = self.watchonly_wallet.hooks() {
+            wallet.set_hooks(hooks);
+        }
+        wallet.tx_limits = self.tx_limits;
         *self = wallet;
         Ok(())
     }
@@ -163,52 +287,231 @@ impl HotWallet {
         Ok(())
     }
 
+    /// Changes the password of an encrypted wallet.
+    ///
+    /// Fails if wallet is not encrypted, or if the current password is incorrect.
+    pub fn change_password(root_dir: &Path, password: &str, new_password: &str) -> Result<()> {
+        if !Self::is_encrypted(root_dir) {
+            return Err(Error::WalletNotEncrypted);
+        }
+
+        let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+        let wallet_key = EncryptedSecretKey::from_file(&wallet_dir)?
+            .decrypt(password)
+            .map_err(|_| Error::WalletPasswordIncorrect)?;
+
+        // The encrypted secret key file is replaced at once, never leaving the key unreadable
+        store_main_secret_key(&wallet_dir, &wallet_key, Some(new_password.to_owned()))
+    }
+
+    /// Removes the password of an encrypted wallet, its secret key being stored unencrypted again.
+    ///
+    /// Fails if wallet is not encrypted, or if the password is incorrect.
+    pub fn decrypt(root_dir: &Path, password: &str) -> Result<()> {
+        if !Self::is_encrypted(root_dir) {
+            return Err(Error::WalletNotEncrypted);
+        }
+
+        let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+        let wallet_key = EncryptedSecretKey::from_file(&wallet_dir)?
+            .decrypt(password)
+            .map_err(|_| Error::WalletPasswordIncorrect)?;
+
+        // Save the secret key as a plain file
+        store_main_secret_key(&wallet_dir, &wallet_key, None)?;
+
+        // Delete the encrypted secret key file
+        // Cleanup if it fails
+        if let Err(err) = delete_encrypted_main_secret_key(&wallet_dir) {
+            let _ = delete_unencrypted_main_secret_key(&wallet_dir);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     /// Locks the wallet and returns exclusive access to the wallet
     /// This lock prevents any other process from locking the wallet dir, effectively acts as a mutex for the wallet
     pub fn lock(&self) -> Result<WalletExclusiveAccess> {
         self.watchonly_wallet.lock()
     }
 
+    /// Locks the wallet like `lock`, but returns `Error::WalletLocked` right away instead of
+    /// waiting when another process holds the lock.
+    pub fn try_lock(&self) -> Result<WalletExclusiveAccess> {
+        self.watchonly_wallet.try_lock()
+    }
+
     /// Stores the given cash_notes to the `created cash_notes dir` in the wallet dir.
     /// These can then be sent to the recipients out of band, over any channel preferred.
     pub fn store_cash_notes_to_disk<'a, T>(&self, cash_notes: T) -> Result<()>
     where
         T: IntoIterator<Item = &'a CashNote>,
     {
-        store_created_cash_notes(cash_notes, self.watchonly_wallet.wallet_dir())
+        let cash_notes: Vec<_> = cash_notes.into_iter().collect();
+        self.watchonly_wallet
+            .wallet_store()
+            .store_cash_notes(&cash_notes)
     }
     /// Removes the given cash_notes from the `created cash_notes dir` in the wallet dir.
     pub fn remove_cash_notes_from_disk<'a, T>(&self, cash_notes: T) -> Result<()>
     where
         T: IntoIterator<Item = &'a UniquePubkey>,
     {
-        remove_cash_notes(cash_notes, self.watchonly_wallet.wallet_dir())
+        let unique_pubkeys: Vec<_> = cash_notes.into_iter().copied().collect();
+        self.watchonly_wallet
+            .wallet_store()
+            .remove_cash_notes(&unique_pubkeys)
     }
 
     /// Store unconfirmed_spend_requests to disk.
     pub fn store_unconfirmed_spend_requests(&mut self) -> Result<()> {
-        store_unconfirmed_spend_requests(
-            self.watchonly_wallet.wallet_dir(),
-            self.unconfirmed_spend_requests(),
-        )
+        self.watchonly_wallet
+            .wallet_store()
+            .store_unconfirmed_spend_requests(self.unconfirmed_spend_requests())?;
+        self.sync_unconfirmed_spend_metadata();
+        match self.watchonly_wallet.dir_on_disk() {
+            Some(wallet_dir) => {
+                store_unconfirmed_spend_metadata(wallet_dir, &self.unconfirmed_spend_metadata)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Like `store_unconfirmed_spend_requests`, without blocking the async runtime meanwhile.
+    #[cfg(feature = "async-io")]
+    pub async fn store_unconfirmed_spend_requests_async(&mut self) -> Result<()> {
+        let store = self.watchonly_wallet.wallet_store();
+        let spends = Arc::clone(&self.unconfirmed_spend_requests);
+        self.sync_unconfirmed_spend_metadata();
+        let metadata = self.unconfirmed_spend_metadata.clone();
+        spawn_blocking(move || {
+            store.store_unconfirmed_spend_requests(&spends)?;
+            match store.wallet_dir() {
+                Some(wallet_dir) => store_unconfirmed_spend_metadata(wallet_dir, &metadata),
+                None => Ok(()),
+            }
+        })
+        .await
+    }
+
+    /// What is recorded of the unconfirmed spends, by their address.
+    pub fn unconfirmed_spend_metadata(
+        &mut self,
+    ) -> &BTreeMap<SpendAddress, UnconfirmedSpendMetadata> {
+        self.sync_unconfirmed_spend_metadata();
+        &self.unconfirmed_spend_metadata
+    }
+
+    /// Record a resend of the unconfirmed spend at the given address, with its error if it failed,
+    /// returning the number of times it was resent. It is stored along with the spends.
+    pub fn record_resend_attempt(&mut self, address: SpendAddress, error: Option<String>) -> u32 {
+        self.sync_unconfirmed_spend_metadata();
+        match self.unconfirmed_spend_metadata.get_mut(&address) {
+            Some(metadata) => {
+                metadata.record_attempt(SystemTime::now(), error);
+                metadata.attempts
+            }
+            None => {
+                warn!("Could not record the resend of {address:?}, which is not unconfirmed");
+                0
+            }
+        }
+    }
+
+    /// When the next unconfirmed spend is due to be resent as per the policy, if there is any.
+    pub fn next_resend_at(&mut self, policy: &ResendPolicy) -> Option<SystemTime> {
+        self.unconfirmed_spend_metadata()
+            .values()
+            .map(|metadata| policy.next_attempt_at(metadata))
+            .min()
+    }
+
+    /// The unconfirmed spends due to be resent as per the policy.
+    pub fn due_unconfirmed_spend_requests(&mut self, policy: &ResendPolicy) -> Vec<SignedSpend> {
+        let now = SystemTime::now();
+        self.sync_unconfirmed_spend_metadata();
+        self.unconfirmed_spend_requests
+            .iter()
+            .filter(|spend| {
+                self.unconfirmed_spend_metadata
+                    .get(&spend.address())
+                    .is_some_and(|metadata| policy.is_due(metadata, now))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Give up the unconfirmed spends older than the maximum age of the policy, returning them.
+    ///
+    /// Their cash notes are not made available again, as the spends may have reached the network.
+    pub fn prune_expired_spend_requests(
+        &mut self,
+        policy: &ResendPolicy,
+    ) -> Result<Vec<SignedSpend>> {
+        let now = SystemTime::now();
+        self.sync_unconfirmed_spend_metadata();
+        let metadata = &self.unconfirmed_spend_metadata;
+        let (expired, kept): (BTreeSet<_>, BTreeSet<_>) =
+            std::mem::take(Arc::make_mut(&mut self.unconfirmed_spend_requests))
+                .into_iter()
+                .partition(|spend| {
+                    metadata
+                        .get(&spend.address())
+                        .is_some_and(|metadata| policy.has_expired(metadata, now))
+                });
+        self.unconfirmed_spend_requests = Arc::new(kept);
+        if expired.is_empty() {
+            return Ok(vec![]);
+        }
+
+        warn!(
+            "Giving up {} unconfirmed spends older than {:?}",
+            expired.len(),
+            policy.max_age
+        );
+        // the outputs of their transactions coming back to the wallet, e.g. the change, will
+        // never exist, they are dropped along with them
+        let exclusive_access = self.lock()?;
+        let abandoned: Vec<UniquePubkey> = expired
+            .iter()
+            .flat_map(|spend| spend.spend.descendants.keys())
+            .filter(|unique_pubkey| {
+                self.watchonly_wallet
+                    .available_cash_notes()
+                    .contains_key(unique_pubkey)
+            })
+            .copied()
+            .collect();
+        if !abandoned.is_empty() {
+            self.mark_notes_as_spent(&abandoned);
+            self.remove_cash_notes_from_disk(&abandoned)?;
+            self.store(exclusive_access)?;
+        }
+        self.store_unconfirmed_spend_requests()?;
+        Ok(expired.into_iter().collect())
+    }
+
+    fn sync_unconfirmed_spend_metadata(&mut self) {
+        sync_unconfirmed_spend_metadata(
+            &mut self.unconfirmed_spend_metadata,
+            &self.unconfirmed_spend_requests,
+            SystemTime::now(),
+        );
     }
 
     /// Get confirmed spend from disk.
     pub fn get_confirmed_spend(&mut self, spend_addr: SpendAddress) -> Result<Option<SignedSpend>> {
-        get_confirmed_spend(self.watchonly_wallet.wallet_dir(), spend_addr)
+        self.watchonly_wallet
+            .wallet_store()
+            .get_confirmed_spend(spend_addr)
     }
 
     /// Check whether have the specific confirmed spend.
     pub fn has_confirmed_spend(&mut self, spend_addr: SpendAddress) -> bool {
-        has_confirmed_spend(self.watchonly_wallet.wallet_dir(), spend_addr)
-    }
-
-    /// Remove unconfirmed_spend_requests from disk.
-    fn remove_unconfirmed_spend_requests(&mut self) -> Result<()> {
-        remove_unconfirmed_spend_requests(
-            self.watchonly_wallet.wallet_dir(),
-            self.unconfirmed_spend_requests(),
-        )
+        self.watchonly_wallet
+            .wallet_store()
+            .has_confirmed_spend(spend_addr)
     }
 
     /// Remove referenced CashNotes from available_cash_notes
@@ -251,13 +554,18 @@ impl HotWallet {
         store_new_keypair(&wallet_dir, &key, password)?;
         let unconfirmed_spend_requests =
             (get_unconfirmed_spend_requests(&wallet_dir)?).unwrap_or_default();
+        let unconfirmed_spend_metadata =
+            load_unconfirmed_spend_metadata(&wallet_dir, &unconfirmed_spend_requests)?;
         let watchonly_wallet = WatchOnlyWallet::load_from(&wallet_dir, key.main_pubkey())?;
 
         Ok(Self {
             key,
             watchonly_wallet,
-            unconfirmed_spend_requests,
+            unconfirmed_spend_requests: Arc::new(unconfirmed_spend_requests),
+            unconfirmed_spend_metadata,
             authentication_manager: AuthenticationManager::new(wallet_dir),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
         })
     }
 
@@ -267,6 +575,13 @@ impl HotWallet {
         Self::load_from_path(&wallet_dir, None)
     }
 
+    /// Like `load_from`, without blocking the async runtime meanwhile.
+    #[cfg(feature = "async-io")]
+    pub async fn load_from_async(root_dir: &Path) -> Result<Self> {
+        let root_dir = root_dir.to_path_buf();
+        spawn_blocking(move || Self::load_from(&root_dir)).await
+    }
+
     /// Tries to loads a serialized wallet from a path, bailing out if it doesn't exist.
     pub fn try_load_from(root_dir: &Path) -> Result<Self> {
         let wallet_dir = root_dir.join(WALLET_DIR_NAME);
@@ -296,7 +611,21 @@ impl HotWallet {
     }
 
     pub fn unconfirmed_spend_requests_mut(&mut self) -> &mut BTreeSet<SignedSpend> {
-        &mut self.unconfirmed_spend_requests
+        Arc::make_mut(&mut self.unconfirmed_spend_requests)
+    }
+
+    /// A consistent view of the wallet as it is now on disk, its unconfirmed spends included, left
+    /// as is by its later sends and deposits.
+    ///
+    /// The wallet is reloaded under its lock first, for the view to hold the changes made by
+    /// other processes.
+    pub fn snapshot_view(&mut self) -> Result<WalletSnapshot> {
+        let _exclusive_access = self.lock()?;
+        self.reload()?;
+        Ok(self
+            .watchonly_wallet
+            .snapshot()?
+            .with_unconfirmed_spend_requests(Arc::clone(&self.unconfirmed_spend_requests)))
     }
 
     /// Moves all files for the current wallet, including keys and cashnotes
@@ -331,6 +660,124 @@ impl HotWallet {
         Ok(())
     }
 
+    /// Write a backup of the wallet to a single file at `path`, encrypted with the passphrase.
+    ///
+    /// It holds the secret key, the available cash notes and the unconfirmed spends, as of the
+    /// store of the wallet: it can be imported with [`HotWallet::import_backup`].
+    pub fn export_backup(&self, path: &Path, passphrase: &str) -> Result<()> {
+        let store = self.watchonly_wallet.wallet_store();
+        // locked for the store not to change while being backed up
+        let _exclusive_access = self.lock()?;
+
+        let mut unconfirmed_spend_requests =
+            store.load_unconfirmed_spend_requests()?.unwrap_or_default();
+        unconfirmed_spend_requests.extend(self.unconfirmed_spend_requests.iter().cloned());
+        let wallet = store.load_wallet()?.unwrap_or_default();
+        let cash_notes = match self.watchonly_wallet.dir_on_disk() {
+            // the cash notes dropped in the dir and not deposited yet are backed up as well
+            Some(wallet_dir) => load_created_cash_notes(wallet_dir)?,
+            None => {
+                let unique_pubkeys: Vec<_> = wallet.available_cash_notes.keys().copied().collect();
+                store.load_cash_notes(&unique_pubkeys)?
+            }
+        };
+        let backup = WalletBackup {
+            main_secret_key: self.key.to_bytes(),
+            wallet,
+            unconfirmed_spend_requests,
+            cash_notes,
+        };
+        backup.write(path, passphrase)?;
+        info!("Backed up wallet {:?} to {path:?}", self.address());
+        Ok(())
+    }
+
+    /// Import the backup at `path`, decrypted with the passphrase, into the wallet of `root_dir`.
+    ///
+    /// The wallet is created from the backup if there is none, with the given password if any.
+    /// Otherwise the backup is merged into it, provided it is of the same key: its available cash
+    /// notes and unconfirmed spends are added to those of the wallet, except the cash notes spent
+    /// by an unconfirmed spend or by a spend the wallet has confirmed since the backup.
+    pub fn import_backup(
+        root_dir: &Path,
+        path: &Path,
+        passphrase: &str,
+        password: Option<String>,
+    ) -> Result<Self> {
+        let backup = WalletBackup::read(path, passphrase)?;
+        let key = backup.validate()?;
+
+        let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+        let mut wallet = match get_main_pubkey(&wallet_dir)? {
+            Some(main_pubkey) if main_pubkey != key.main_pubkey() => {
+                return Err(Error::WalletBackupKeyMismatch(main_pubkey));
+            }
+            // the key of the backup is used, for an encrypted wallet not to need its password
+            Some(_) => {
+                let unconfirmed_spend_requests =
+                    get_unconfirmed_spend_requests(&wallet_dir)?.unwrap_or_default();
+                Self {
+                    unconfirmed_spend_metadata: load_unconfirmed_spend_metadata(
+                        &wallet_dir,
+                        &unconfirmed_spend_requests,
+                    )?,
+                    unconfirmed_spend_requests: Arc::new(unconfirmed_spend_requests),
+                    watchonly_wallet: WatchOnlyWallet::load_from(&wallet_dir, key.main_pubkey())?,
+                    authentication_manager: AuthenticationManager::new(wallet_dir.clone()),
+                    tx_limits: TransactionLimits::default(),
+                    approved_allowance: NanoTokens::zero(),
+                    key,
+                }
+            }
+            None => Self::create_from_key(root_dir, key, password)?,
+        };
+
+        let exclusive_access = wallet.lock()?;
+        wallet.watchonly_wallet.reload()?;
+        // the wallet may have had the spends of the backup confirmed since it was made
+        let store = wallet.watchonly_wallet.wallet_store();
+        let is_confirmed = |unique_pubkey: &UniquePubkey| {
+            store.has_confirmed_spend(SpendAddress::from_unique_pubkey(unique_pubkey))
+        };
+        Arc::make_mut(&mut wallet.unconfirmed_spend_requests).extend(
+            backup
+                .unconfirmed_spend_requests
+                .into_iter()
+                .filter(|signed_spend| !is_confirmed(&signed_spend.spend.unique_pubkey)),
+        );
+        let spent: BTreeSet<UniquePubkey> = wallet
+            .unconfirmed_spend_requests
+            .iter()
+            .map(|signed_spend| signed_spend.spend.unique_pubkey)
+            .collect();
+        let restored: Vec<CashNote> = backup
+            .cash_notes
+            .into_iter()
+            .filter(|cash_note| {
+                let unique_pubkey = cash_note.unique_pubkey();
+                backup
+                    .wallet
+                    .available_cash_notes
+                    .contains_key(&unique_pubkey)
+                    && !spent.contains(&unique_pubkey)
+                    && !is_confirmed(&unique_pubkey)
+            })
+            .collect();
+        wallet.watchonly_wallet.deposit(&restored)?;
+        store_created_cash_notes(&restored, &wallet_dir)?;
+        wallet.watchonly_wallet.store(exclusive_access)?;
+        if wallet.unconfirmed_spend_requests_exist() {
+            wallet.store_unconfirmed_spend_requests()?;
+        }
+
+        info!(
+            "Imported backup {path:?} into wallet {:?}, restoring {} cash notes",
+            wallet.address(),
+            restored.len()
+        );
+        Ok(wallet)
+    }
+
     /// Removes all files for the current wallet, including keys and cashnotes
     pub fn remove(root_dir: &Path) -> Result<()> {
         let wallet_dir = root_dir.join(WALLET_DIR_NAME);
@@ -338,51 +785,170 @@ impl HotWallet {
         Ok(())
     }
 
+    /// Record the spend as confirmed by the network, dropping it from the unconfirmed ones, e.g.
+    /// once a resend of it succeeded.
+    pub fn confirm_spend_request(&mut self, signed_spend: &SignedSpend) -> Result<()> {
+        let unique_pubkey = signed_spend.spend.unique_pubkey;
+        if let Err(error) = self.remove_cash_notes_from_disk([&unique_pubkey]) {
+            warn!("Could not clean confirmed spent cash_note {unique_pubkey:?} due to {error:?}");
+        }
+        Arc::make_mut(&mut self.unconfirmed_spend_requests)
+            .retain(|spend| spend.spend.unique_pubkey != unique_pubkey);
+
+        // the remaining ones are stored again, along with their metadata
+        self.watchonly_wallet
+            .wallet_store()
+            .confirm_unconfirmed_spend_requests(&BTreeSet::from([signed_spend.clone()]))?;
+        if self.unconfirmed_spend_requests_exist() {
+            self.store_unconfirmed_spend_requests()
+        } else {
+            self.unconfirmed_spend_metadata = Default::default();
+            match self.watchonly_wallet.dir_on_disk() {
+                Some(wallet_dir) => remove_unconfirmed_spend_metadata(wallet_dir),
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Drop the unconfirmed spend of a cash note found double spent in the network, along with the
+    /// cash note, which is not spendable anymore.
+    pub fn drop_double_spent_request(&mut self, unique_pubkey: UniquePubkey) -> Result<()> {
+        let exclusive_access = self.lock()?;
+        self.watchonly_wallet.reload()?;
+        self.mark_notes_as_spent([&unique_pubkey]);
+        self.store(exclusive_access)?;
+
+        self.clear_specific_spend_request(unique_pubkey);
+        self.store_unconfirmed_spend_requests()
+    }
+
     /// To remove a specific spend from the requests, if eg, we see one spend is _bad_
     pub fn clear_specific_spend_request(&mut self, unique_pub_key: UniquePubkey) {
         if let Err(error) = self.remove_cash_notes_from_disk(vec![&unique_pub_key]) {
             warn!("Could not clean spend {unique_pub_key:?} due to {error:?}");
         }
 
-        self.unconfirmed_spend_requests
-            .retain(|signed_spend| signed_spend.spend.unique_pubkey.ne(&unique_pub_key))
+        Arc::make_mut(&mut self.unconfirmed_spend_requests)
+            .retain(|signed_spend| signed_spend.spend.unique_pubkey.ne(&unique_pub_key));
+        self.sync_unconfirmed_spend_metadata();
+    }
+
+    /// Whether the payment cached for the given xorname is confirmed, i.e. all the spends of its
+    /// transaction were registered in the network. An unconfirmed payment can be made again.
+    pub fn is_payment_confirmed(&self, name: &XorName) -> bool {
+        match self.api().payment_spends(name) {
+            Ok(spends) => {
+                let store = self.watchonly_wallet.wallet_store();
+                !spends.is_empty() && spends.iter().all(|addr| store.has_confirmed_spend(*addr))
+            }
+            Err(_) => false,
+        }
     }
 
     /// Once spends are verified we can clear them and clean up
     pub fn clear_confirmed_spend_requests(&mut self) {
-        if let Err(error) = self.remove_cash_notes_from_disk(
-            self.unconfirmed_spend_requests
-                .iter()
-                .map(|s| &s.spend.unique_pubkey),
-        ) {
-            warn!("Could not clean confirmed spent cash_notes due to {error:?}");
-        }
+        remove_confirmed_spends(
+            self.watchonly_wallet.wallet_store().as_ref(),
+            &self.unconfirmed_spend_requests,
+        );
+
+        self.unconfirmed_spend_requests = Default::default();
+        self.unconfirmed_spend_metadata = Default::default();
+    }
 
-        // Also need to remove unconfirmed_spend_requests from disk if was pre-loaded.
-        let _ = self.remove_unconfirmed_spend_requests();
+    /// Like `clear_confirmed_spend_requests`, without blocking the async runtime meanwhile.
+    #[cfg(feature = "async-io")]
+    pub async fn clear_confirmed_spend_requests_async(&mut self) {
+        let store = self.watchonly_wallet.wallet_store();
+        let spends = Arc::clone(&self.unconfirmed_spend_requests);
+        let removed = spawn_blocking(move || {
+            remove_confirmed_spends(store.as_ref(), &spends);
+            Ok(())
+        })
+        .await;
+        if let Err(error) = removed {
+            warn!("Could not clear the confirmed spend requests due to {error:?}");
+        }
 
         self.unconfirmed_spend_requests = Default::default();
+        self.unconfirmed_spend_metadata = Default::default();
     }
 
     pub fn balance(&self) -> NanoTokens {
         self.watchonly_wallet.balance()
     }
 
+    /// Sign an arbitrary message with the wallet's main key, e.g. to prove ownership of the wallet.
+    /// The signature can be checked with `verify_message`.
+    pub fn sign_message(&self, message: &[u8]) -> bls::Signature {
+        self.key.sign(&signed_message_bytes(message))
+    }
+
+    /// Sign a transaction of the wallet, e.g. built by its watch-only wallet, once it is approved
+    /// if the wallet requires it.
     pub fn sign(&self, unsigned_tx: UnsignedTransaction) -> Result<SignedTransaction> {
-        if let Err(err) = unsigned_tx.verify() {
-            return Err(Error::CouldNotSignTransaction(format!(
-                "Failed to verify unsigned transaction: {err:?}"
-            )));
+        unsigned_tx
+            .verify_for_signing()
+            .map_err(Error::InvalidUnsignedTransaction)?;
+        if let Some(wallet_dir) = self.watchonly_wallet.dir_on_disk() {
+            check_spend_approval(wallet_dir, &self.address(), &unsigned_tx.outputs(), 0)?;
+        }
+        self.watchonly_wallet.sign_with(unsigned_tx, &self.key)
+    }
+
+    /// Have a spend of the outputs approved if the wallet requires it, before the wallet is
+    /// locked for it. The spends approved ahead are drawn from first.
+    fn approve_spend(
+        &mut self,
+        outputs: &[(NanoTokens, MainPubkey)],
+        storage_payments: usize,
+    ) -> Result<()> {
+        let amount = outgoing_amount(&self.address(), outputs);
+        if let Some(left) = self.approved_allowance.checked_sub(amount) {
+            self.approved_allowance = left;
+            return Ok(());
+        }
+        match self.watchonly_wallet.dir_on_disk() {
+            Some(wallet_dir) => {
+                check_spend_approval(wallet_dir, &self.address(), outputs, storage_payments)
+            }
+            None => Ok(()),
         }
-        let signed_tx = unsigned_tx
-            .sign(&self.key)
-            .map_err(|e| Error::CouldNotSignTransaction(e.to_string()))?;
-        if let Err(err) = signed_tx.verify() {
-            return Err(Error::CouldNotSignTransaction(format!(
-                "Failed to verify signed transaction: {err:?}"
-            )));
+    }
+
+    /// Have a spend of the outputs approved ahead if the wallet requires it, without blocking
+    /// the async runtime while the approver answers. The next spends of the wallet are then
+    /// approved up to what the outputs send out of it.
+    #[cfg(feature = "async-io")]
+    pub async fn approve_spend_async(
+        &mut self,
+        outputs: Vec<(NanoTokens, MainPubkey)>,
+        storage_payments: usize,
+    ) -> Result<()> {
+        let address = self.address();
+        let amount = outgoing_amount(&address, &outputs);
+        if let Some(wallet_dir) = self.watchonly_wallet.dir_on_disk() {
+            let wallet_dir = wallet_dir.to_path_buf();
+            spawn_blocking(move || {
+                check_spend_approval(&wallet_dir, &address, &outputs, storage_payments)
+            })
+            .await?;
         }
-        Ok(signed_tx)
+        self.approved_allowance = self
+            .approved_allowance
+            .checked_add(amount)
+            .ok_or(WalletError::TotalPriceTooHigh)?;
+        Ok(())
+    }
+
+    /// Like `approve_spend_async`, for the payments of `local_send_storage_payment_to_payees`.
+    #[cfg(feature = "async-io")]
+    pub async fn approve_storage_payment_async(
+        &mut self,
+        price_map: &QuotesByAddress,
+    ) -> Result<()> {
+        self.approve_spend_async(storage_payment_outputs(price_map), price_map.len())
+            .await
     }
 
     /// Checks whether the specified cash_note already presents
@@ -395,33 +961,86 @@ impl HotWallet {
     /// Returns all available cash_notes and an exclusive access to the wallet so no concurrent processes can
     /// get available cash_notes while we're modifying the wallet
     /// once the updated wallet is stored to disk it is safe to drop the WalletExclusiveAccess
+    ///
+    /// This reads the file of every available cash_note: to spend some of them only, pick them
+    /// among `available_cash_note_values` and read them with `load_cash_notes`.
     pub fn available_cash_notes(&mut self) -> Result<(Vec<CashNote>, WalletExclusiveAccess)> {
+        let (available, exclusive_access) = self.available_cash_note_values()?;
+        let available_cash_notes = self.load_cash_notes(available.keys())?;
+        Ok((available_cash_notes, exclusive_access))
+    }
+
+    /// Returns the values of the available cash_notes, without reading their files, and an
+    /// exclusive access to the wallet like `available_cash_notes`.
+    pub fn available_cash_note_values(
+        &mut self,
+    ) -> Result<(BTreeMap<UniquePubkey, NanoTokens>, WalletExclusiveAccess)> {
         trace!("Trying to lock wallet to get available cash_notes...");
         // lock and load from disk to make sure we're up to date and others can't modify the wallet concurrently
         let exclusive_access = self.lock()?;
         self.reload()?;
         trace!("Wallet locked and loaded!");
 
-        // get the available cash_notes
-        let mut available_cash_notes = vec![];
-        let wallet_dir = self.watchonly_wallet.wallet_dir().to_path_buf();
-        for (id, _token) in self.watchonly_wallet.available_cash_notes().iter() {
-            let held_cash_note = load_created_cash_note(id, &wallet_dir);
-            if let Some(cash_note) = held_cash_note {
-                if cash_note.derived_key(&self.key).is_ok() {
-                    available_cash_notes.push(cash_note.clone());
-                } else {
+        let available = self.watchonly_wallet.available_cash_notes().clone();
+        Ok((available, exclusive_access))
+    }
+
+    /// Reads the given cash_notes of the wallet from their files, leaving out the ones the wallet
+    /// has no file for or no key to spend.
+    pub fn load_cash_notes<'a, I>(&self, unique_pubkeys: I) -> Result<Vec<CashNote>>
+    where
+        I: IntoIterator<Item = &'a UniquePubkey>,
+    {
+        let unique_pubkeys: Vec<_> = unique_pubkeys.into_iter().copied().collect();
+        let cash_notes = self
+            .watchonly_wallet
+            .wallet_store()
+            .load_cash_notes(&unique_pubkeys)?;
+        Ok(cash_notes
+            .into_iter()
+            .filter(|cash_note| {
+                let is_spendable = cash_note.derived_key(&self.key).is_ok();
+                if !is_spendable {
                     warn!(
                         "Skipping CashNote {:?} because we don't have the key to spend it",
                         cash_note.unique_pubkey()
                     );
                 }
-            } else {
-                warn!("Skipping CashNote {:?} because we don't have it", id);
+                is_spendable
+            })
+            .collect())
+    }
+
+    /// Returns the cash_notes picked by the coin selection to spend `amount`, reading their files
+    /// only, and an exclusive access to the wallet like `available_cash_notes`.
+    fn select_cash_notes(
+        &mut self,
+        amount: NanoTokens,
+        coin_selection: &dyn CoinSelection,
+    ) -> Result<(Vec<CashNote>, WalletExclusiveAccess)> {
+        let (mut candidates, exclusive_access) = self.available_cash_note_values()?;
+        loop {
+            let selected = coin_selection.select(
+                candidates
+                    .iter()
+                    .map(|(unique_pubkey, value)| (*unique_pubkey, *value))
+                    .collect(),
+                amount,
+            );
+            let cash_notes = self.load_cash_notes(&selected)?;
+            if cash_notes.len() == selected.len() {
+                return Ok((cash_notes, exclusive_access));
             }
-        }
 
-        Ok((available_cash_notes, exclusive_access))
+            // pick again without the ones which could not be read
+            let loaded: BTreeSet<_> = cash_notes
+                .iter()
+                .map(|cash_note| cash_note.unique_pubkey())
+                .collect();
+            for unique_pubkey in selected.iter().filter(|key| !loaded.contains(key)) {
+                let _ = candidates.remove(unique_pubkey);
+            }
+        }
     }
 
     /// Remove the payment_details of the given XorName from disk.
@@ -437,40 +1056,392 @@ impl HotWallet {
         self.watchonly_wallet.build_unsigned_transaction(to, reason)
     }
 
+    pub fn build_unsigned_transaction_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        to: Vec<(NanoTokens, MainPubkey)>,
+        reason: Option<SpendReason>,
+        rng: &mut R,
+    ) -> Result<UnsignedTransaction> {
+        self.watchonly_wallet
+            .build_unsigned_transaction_with_rng(to, reason, rng)
+    }
+
     /// Make a transfer and return all created cash_notes
     pub fn local_send(
         &mut self,
         to: Vec<(NanoTokens, MainPubkey)>,
         reason: Option<SpendReason>,
     ) -> Result<Vec<CashNote>> {
-        let mut rng = &mut rand::rngs::OsRng;
+        self.local_send_with_rng(to, reason, &mut rand::rngs::OsRng)
+    }
+
+    /// Make a transfer like `local_send`, to recipients given by their pubkey or by their label
+    /// in the address book of the wallet.
+    pub fn local_send_to(
+        &mut self,
+        to: Vec<(NanoTokens, Recipient)>,
+        reason: Option<SpendReason>,
+    ) -> Result<Vec<CashNote>> {
+        let to = self.resolve_recipients(to)?;
+        self.local_send(to, reason)
+    }
+
+    /// Make the transfer paying the request, of the amount it requests, else of the given one,
+    /// to the key derived at the index it gives, if any, for the payee to verify the payment.
+    /// The hash of the request, if any, is the reason of the spends.
+    pub fn local_send_payment_request(
+        &mut self,
+        request: &PaymentRequest,
+        amount: Option<NanoTokens>,
+    ) -> Result<Vec<CashNote>> {
+        if request.is_expired() {
+            return Err(Error::PaymentRequestExpired);
+        }
+        let amount = request.amount_to_pay(amount)?;
+        let derivation_index = request
+            .derivation_index
+            .unwrap_or_else(|| DerivationIndex::random(&mut OsRng));
+        self.local_send_to_derivation_indexes(
+            vec![(amount, request.to, derivation_index)],
+            request.spend_reason(),
+            &LargestFirst,
+        )
+    }
+
+    /// Make a transfer like `local_send`, drawing the derivation indexes of the outputs from the
+    /// given `rng`, so the transfer can be reproduced. The change goes to the next index of the
+    /// change sequence of the wallet.
+    pub fn local_send_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        to: Vec<(NanoTokens, MainPubkey)>,
+        reason: Option<SpendReason>,
+        rng: &mut R,
+    ) -> Result<Vec<CashNote>> {
+        self.local_send_with_coin_selection_and_rng(to, reason, &LargestFirst, rng)
+    }
+
+    /// Make a transfer like `local_send`, spending the cash notes picked by the given coin
+    /// selection instead of the biggest first.
+    pub fn local_send_with_coin_selection(
+        &mut self,
+        to: Vec<(NanoTokens, MainPubkey)>,
+        reason: Option<SpendReason>,
+        coin_selection: &dyn CoinSelection,
+    ) -> Result<Vec<CashNote>> {
+        self.local_send_with_coin_selection_and_rng(to, reason, coin_selection, &mut OsRng)
+    }
+
+    fn local_send_with_coin_selection_and_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        to: Vec<(NanoTokens, MainPubkey)>,
+        reason: Option<SpendReason>,
+        coin_selection: &dyn CoinSelection,
+        rng: &mut R,
+    ) -> Result<Vec<CashNote>> {
         // create a unique key for each output
+        let to = to
+            .into_iter()
+            .map(|(amount, address)| (amount, address, DerivationIndex::random(rng)))
+            .collect();
+        self.local_send_to_derivation_indexes(to, reason, coin_selection)
+    }
+
+    fn local_send_to_derivation_indexes(
+        &mut self,
+        to: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
+        reason: Option<SpendReason>,
+        coin_selection: &dyn CoinSelection,
+    ) -> Result<Vec<CashNote>> {
+        let amount = to
+            .iter()
+            .try_fold(NanoTokens::zero(), |total, (amount, _, _)| {
+                total.checked_add(*amount)
+            })
+            .ok_or(WalletError::TotalPriceTooHigh)?;
+        let counterparty = match to.as_slice() {
+            [(_, recipient, _)] => Some(*recipient),
+            _ => None,
+        };
+        let outputs: Vec<_> = to
+            .iter()
+            .map(|(amount, address, _)| (*amount, *address))
+            .collect();
+        self.approve_spend(&outputs, 0)?;
+
         let to_unique_keys: Vec<_> = to
             .into_iter()
-            .map(|(amount, address)| (amount, address, DerivationIndex::random(&mut rng), false))
+            .map(|(amount, address, derivation_index)| (amount, address, derivation_index, false))
             .collect();
 
-        let (available_cash_notes, exclusive_access) = self.available_cash_notes()?;
-        println!("Available CashNotes for local send: {available_cash_notes:#?}");
+        let (inputs, exclusive_access) = self.select_cash_notes(amount, coin_selection)?;
+        debug!(
+            "Spending {} CashNotes picked by {coin_selection:?}",
+            inputs.len()
+        );
 
         let reason = reason.unwrap_or_default();
 
-        let signed_tx = SignedTransaction::new(
-            available_cash_notes,
+        let change_index = self.next_derivation_index(DerivationPurpose::Change)?;
+        let signed_tx = UnsignedTransaction::new_with_change_index(
+            inputs,
             to_unique_keys,
             self.address(),
+            change_index,
             reason,
-            &self.key,
-        )?;
+        )?
+        .sign(&self.key)?;
 
         let created_cash_notes = signed_tx.output_cashnotes.clone();
+        self.apply_local_send(
+            signed_tx,
+            exclusive_access,
+            Self::sent_entry(amount, counterparty),
+            BTreeMap::new(),
+        )?;
+
+        trace!("Releasing wallet lock"); // by dropping _exclusive_access
+        Ok(created_cash_notes)
+    }
+
+    /// Make a transfer of `amount` to `to` at most once for the given `id`, and return all
+    /// created cash_notes.
+    ///
+    /// The transaction is recorded under the id before being applied to the wallet, so calling
+    /// this again with the same id, e.g. when retrying after a crash, returns the cash_notes of
+    /// the original transfer instead of paying twice. Reusing an id for another amount or
+    /// recipient is an error, as is retrying a send whose cash notes were spent by another
+    /// transaction before it was applied.
+    pub fn send_idempotent(
+        &mut self,
+        id: &str,
+        amount: NanoTokens,
+        to: MainPubkey,
+    ) -> Result<Vec<CashNote>> {
+        let wallet_dir = self
+            .watchonly_wallet
+            .dir_on_disk()
+            .ok_or(Error::NotStoredOnDisk("idempotent sends"))?
+            .to_path_buf();
+        // a retry of a send is approved already
+        if get_idempotent_send::<IdempotentSend>(&wallet_dir, id)?.is_none() {
+            self.approve_spend(&[(amount, to)], 0)?;
+        }
+        let (available_cash_notes, exclusive_access) =
+            self.select_cash_notes(amount, &LargestFirst)?;
+
+        if let Some(send) = get_idempotent_send::<IdempotentSend>(&wallet_dir, id)? {
+            if send.amount != amount || send.to != to {
+                return Err(Error::IdempotentSendMismatch(id.to_string()));
+            }
+            let created_cash_notes = send.signed_tx.output_cashnotes.clone();
+
+            // the transaction is recorded before being applied, which may not have happened
+            let mut is_applied = true;
+            for spend in &send.signed_tx.spends {
+                is_applied &= self.unconfirmed_spend_requests.get(spend) == Some(spend)
+                    || self.get_confirmed_spend(spend.address())?.as_ref() == Some(spend);
+            }
+            if !is_applied {
+                // its cash notes may since have been spent by another transaction
+                let available_cash_notes = self.watchonly_wallet.available_cash_notes();
+                if !send
+                    .signed_tx
+                    .spends
+                    .iter()
+                    .all(|spend| available_cash_notes.contains_key(spend.unique_pubkey()))
+                {
+                    return Err(Error::IdempotentSendSuperseded(id.to_string()));
+                }
+                info!("Applying the recorded transaction of the send with id {id:?}");
+                self.apply_local_send(
+                    send.signed_tx,
+                    exclusive_access,
+                    Self::sent_entry(amount, Some(to)),
+                    BTreeMap::new(),
+                )?;
+                self.store_unconfirmed_spend_requests()?;
+            }
+            return Ok(created_cash_notes);
+        }
 
-        self.update_local_wallet(signed_tx, exclusive_access, true)?;
+        let signed_tx = self.new_signed_transaction(
+            available_cash_notes,
+            vec![(amount, to, DerivationIndex::random(&mut OsRng), false)],
+            SpendReason::default(),
+        )?;
+        let send = IdempotentSend {
+            amount,
+            to,
+            signed_tx,
+        };
+        store_idempotent_send(&wallet_dir, id, &send)?;
+
+        let created_cash_notes = send.signed_tx.output_cashnotes.clone();
+        self.apply_local_send(
+            send.signed_tx,
+            exclusive_access,
+            Self::sent_entry(amount, Some(to)),
+            BTreeMap::new(),
+        )?;
+        // a retry tells the send was applied from its spends, which must then be on disk
+        self.store_unconfirmed_spend_requests()?;
 
         trace!("Releasing wallet lock"); // by dropping _exclusive_access
         Ok(created_cash_notes)
     }
 
+    /// Apply a locally built transaction to the wallet, recording the entry of the history it
+    /// makes once the wallet is stored.
+    fn apply_local_send(
+        &mut self,
+        signed_tx: SignedTransaction,
+        exclusive_access: WalletExclusiveAccess,
+        entry: HistoryEntry,
+        storage_payments: BTreeMap<XorName, PaymentDetails>,
+    ) -> Result<()> {
+        let entry = entry.with_transaction(&signed_tx);
+        self.update_local_wallet(
+            signed_tx,
+            exclusive_access,
+            true,
+            storage_payments,
+            Some(entry),
+        )
+    }
+
+    /// The entry of the history of tokens sent to `counterparty`, the transaction to be set.
+    fn sent_entry(amount: NanoTokens, counterparty: Option<MainPubkey>) -> HistoryEntry {
+        HistoryEntry::new(
+            HistoryKind::Sent,
+            amount,
+            NanoTokens::zero(),
+            counterparty,
+            vec![],
+        )
+    }
+
+    /// Make a transfer of the whole balance, except for `keep`, to `to` and return all created cash_notes.
+    pub fn local_sweep(&mut self, to: MainPubkey, keep: NanoTokens) -> Result<Vec<CashNote>> {
+        let balance = self.balance();
+        let amount = match balance.checked_sub(keep) {
+            Some(amount) if !amount.is_zero() => amount,
+            _ => return Err(Error::NothingToSweep { balance, keep }),
+        };
+        debug!("Sweeping {amount} out of {balance} to {to:?}");
+
+        self.local_send(vec![(amount, to)], None)
+    }
+
+    /// Reissue the smallest cash notes of the wallet to itself, into bigger ones, until it holds
+    /// at most `target_note_count` of them, each transaction spending at most `max_inputs`.
+    /// The spends are stored as unconfirmed, to be sent to the network like the ones of a send.
+    ///
+    /// Returns the created cash notes, one per transaction.
+    pub fn consolidate(
+        &mut self,
+        max_inputs: usize,
+        target_note_count: usize,
+    ) -> Result<Vec<CashNote>> {
+        let mut consolidated = vec![];
+        loop {
+            let (available, exclusive_access) = self.available_cash_note_values()?;
+            let excess = available.len().saturating_sub(target_note_count.max(1));
+            let input_count = excess.saturating_add(1).min(max_inputs);
+            if excess == 0 || input_count < 2 {
+                break;
+            }
+            let mut smallest: Vec<_> = available.into_iter().collect();
+            smallest.sort_by_key(|(_, value)| *value);
+            smallest.truncate(input_count);
+            let inputs =
+                self.load_cash_notes(smallest.iter().map(|(unique_pubkey, _)| unique_pubkey))?;
+            if inputs.len() < 2 {
+                break;
+            }
+
+            let total = inputs
+                .iter()
+                .try_fold(NanoTokens::zero(), |total, cash_note| {
+                    total.checked_add(cash_note.value())
+                })
+                .ok_or(WalletError::TotalPriceTooHigh)?;
+            if total.is_zero() {
+                break;
+            }
+            debug!("Consolidating {} CashNotes worth {total}", inputs.len());
+
+            let derivation_index = self.next_derivation_index(DerivationPurpose::Change)?;
+            let mut signed_tx = UnsignedTransaction::new_with_ordered_inputs(
+                inputs,
+                vec![(total, self.address(), derivation_index, false)],
+                self.address(),
+                SpendReason::default(),
+                &mut OsRng,
+            )?
+            .sign(&self.key)?;
+            // the consolidated cash note comes back to the wallet like a change
+            signed_tx.change_cashnote = signed_tx.output_cashnotes.pop();
+            consolidated.extend(signed_tx.change_cashnote.clone());
+
+            self.update_local_wallet(signed_tx, exclusive_access, true, BTreeMap::new(), None)?;
+            // stored right away, the next transaction reloading the wallet from disk
+            self.store_unconfirmed_spend_requests()?;
+        }
+        Ok(consolidated)
+    }
+
+    /// Reissue cash notes of the wallet to itself, one new cash note for each of the `amounts`,
+    /// e.g. ten of 1_000_000 nanos, for as many payments to be made in parallel without all
+    /// waiting on the spend of one big cash note. What is left of the inputs comes back as change.
+    /// The spends are stored as unconfirmed, to be sent to the network like the ones of a send.
+    ///
+    /// Returns the created cash notes, in the order of the amounts.
+    pub fn split(&mut self, amounts: Vec<NanoTokens>) -> Result<Vec<CashNote>> {
+        if amounts.is_empty() {
+            return Ok(vec![]);
+        }
+        // one more output for the change
+        if amounts.len() >= self.tx_limits.max_outputs {
+            return Err(Error::TooManyOutputs {
+                needed: amounts.len() + 1,
+                max: self.tx_limits.max_outputs,
+            });
+        }
+        let total = amounts
+            .iter()
+            .try_fold(NanoTokens::zero(), |total, amount| {
+                total.checked_add(*amount)
+            })
+            .ok_or(WalletError::TotalPriceTooHigh)?;
+
+        let (inputs, exclusive_access) = self.select_cash_notes(total, &LargestFirst)?;
+        debug!(
+            "Splitting {} CashNotes into {} of {total} in total",
+            inputs.len(),
+            amounts.len()
+        );
+
+        let mut outputs = vec![];
+        for amount in amounts {
+            let derivation_index = self.next_derivation_index(DerivationPurpose::Change)?;
+            outputs.push((amount, self.address(), derivation_index, false));
+        }
+        let change_index = self.next_derivation_index(DerivationPurpose::Change)?;
+        let signed_tx = UnsignedTransaction::new_with_change_index(
+            inputs,
+            outputs,
+            self.address(),
+            change_index,
+            SpendReason::default(),
+        )?
+        .sign(&self.key)?;
+        let split = signed_tx.output_cashnotes.clone();
+
+        self.update_local_wallet(signed_tx, exclusive_access, true, BTreeMap::new(), None)?;
+        self.store_unconfirmed_spend_requests()?;
+        Ok(split)
+    }
+
     // Create SignedSpends directly to forward all accumulated balance to the receipient.
     #[cfg(feature = "reward-forward")]
     pub fn prepare_forward_signed_spend(
@@ -478,6 +1449,7 @@ impl HotWallet {
         to: Vec<(NanoTokens, MainPubkey)>,
         reward_tracking_reason: String,
     ) -> Result<Vec<SignedSpend>> {
+        self.approve_spend(&to, 0)?;
         let (available_cash_notes, exclusive_access) = self.available_cash_notes()?;
         debug!(
             "Available CashNotes for local send: {:#?}",
@@ -489,9 +1461,7 @@ impl HotWallet {
             Ok(spend_reason) => spend_reason,
             Err(err) => {
                 error!("Failed to generate spend_reason {err:?}");
-                return Err(Error::CouldNotSendMoney(format!(
-                    "Failed to generate spend_reason {err:?}"
-                )));
+                return Err(Error::Transfer(err));
             }
         };
 
@@ -502,16 +1472,11 @@ impl HotWallet {
             .map(|(amount, address)| (amount, address, DerivationIndex::random(&mut rng), false))
             .collect();
 
-        let signed_tx = SignedTransaction::new(
-            available_cash_notes,
-            to_unique_keys,
-            self.address(),
-            spend_reason,
-            &self.key,
-        )?;
+        let signed_tx =
+            self.new_signed_transaction(available_cash_notes, to_unique_keys, spend_reason)?;
         let signed_spends: Vec<_> = signed_tx.spends.iter().cloned().collect();
 
-        self.update_local_wallet(signed_tx, exclusive_access, false)?;
+        self.update_local_wallet(signed_tx, exclusive_access, false, BTreeMap::new(), None)?;
 
         // cash_notes better to be removed from disk
         let _ =
@@ -519,7 +1484,10 @@ impl HotWallet {
 
         // signed_spends need to be flushed to the disk as confirmed_spends as well.
         let ss_btree: BTreeSet<_> = signed_spends.iter().cloned().collect();
-        let _ = remove_unconfirmed_spend_requests(self.watchonly_wallet.wallet_dir(), &ss_btree);
+        let _ = self
+            .watchonly_wallet
+            .wallet_store()
+            .confirm_unconfirmed_spend_requests(&ss_btree);
 
         Ok(signed_spends)
     }
@@ -531,7 +1499,103 @@ impl HotWallet {
         &mut self,
         price_map: &BTreeMap<XorName, (MainPubkey, PaymentQuote, Vec<u8>)>,
     ) -> Result<(NanoTokens, NanoTokens)> {
-        let mut rng = &mut rand::thread_rng();
+        self.local_send_storage_payment_with_rng(price_map, &mut rand::thread_rng())
+    }
+
+    /// Performs the payments like `local_send_storage_payment`, drawing the derivation indexes of
+    /// the payees from the given `rng`.
+    pub fn local_send_storage_payment_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        price_map: &BTreeMap<XorName, (MainPubkey, PaymentQuote, Vec<u8>)>,
+        rng: &mut R,
+    ) -> Result<(NanoTokens, NanoTokens)> {
+        let price_map = price_map
+            .iter()
+            .map(|(xorname, payee)| (*xorname, vec![payee.clone()]))
+            .collect();
+        self.local_send_storage_payment_to_payees_with_rng(&price_map, rng)
+    }
+
+    /// Split the payments for the storage of the content addresses into the batches
+    /// `local_send_storage_payment_to_payees` pays by a transaction each, within the limits of
+    /// the wallet, e.g. to send the transaction of each to the network before paying the next.
+    pub fn storage_payment_batches(
+        &self,
+        price_map: &QuotesByAddress,
+    ) -> Result<Vec<QuotesByAddress>> {
+        Ok(split_storage_payments(price_map, self.tx_limits.max_outputs)?.into())
+    }
+
+    /// Performs the payments like `local_send_storage_payment`, paying every one of the payees
+    /// quoted for a content address, and the network royalties of each.
+    pub fn local_send_storage_payment_to_payees(
+        &mut self,
+        price_map: &QuotesByAddress,
+    ) -> Result<(NanoTokens, NanoTokens)> {
+        self.local_send_storage_payment_to_payees_with_rng(price_map, &mut rand::thread_rng())
+    }
+
+    /// Performs the payments like `local_send_storage_payment_to_payees`, drawing the derivation
+    /// indexes of the payees from the given `rng`.
+    ///
+    /// The payments are split into as many transactions as the limits of the wallet call for,
+    /// each content address being paid for within a single one. Should one of them fail, the
+    /// ones made before it stay made, their payments cached in the wallet.
+    pub fn local_send_storage_payment_to_payees_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        price_map: &QuotesByAddress,
+        rng: &mut R,
+    ) -> Result<(NanoTokens, NanoTokens)> {
+        let mut batches = split_storage_payments(price_map, self.tx_limits.max_outputs)?;
+        if batches.len() > 1 {
+            info!(
+                "Paying for {} content addresses in {} transactions",
+                price_map.len(),
+                batches.len()
+            );
+        }
+
+        let mut storage_cost = NanoTokens::zero();
+        let mut royalties_fees = NanoTokens::zero();
+        while let Some(batch) = batches.pop_front() {
+            match self.send_storage_payment_batch(&batch, rng) {
+                Ok((batch_storage_cost, batch_royalties_fees)) => {
+                    storage_cost = storage_cost
+                        .checked_add(batch_storage_cost)
+                        .ok_or(WalletError::TotalPriceTooHigh)?;
+                    royalties_fees = royalties_fees
+                        .checked_add(batch_royalties_fees)
+                        .ok_or(WalletError::TotalPriceTooHigh)?;
+                    // the next batch reloads the wallet, which would drop the spends kept in memory
+                    if !batches.is_empty() {
+                        self.store_unconfirmed_spend_requests()?;
+                    }
+                }
+                // paying for fewer records takes fewer cash notes
+                Err(Error::TooManyInputs { .. }) if batch.len() > 1 => {
+                    let mut first_half = batch;
+                    let second_half = first_half.split_off(
+                        &first_half
+                            .keys()
+                            .nth(first_half.len() / 2)
+                            .copied()
+                            .unwrap_or_default(),
+                    );
+                    batches.push_front(second_half);
+                    batches.push_front(first_half);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok((storage_cost, royalties_fees))
+    }
+
+    /// Pay for the storage of the content addresses in a single transaction.
+    fn send_storage_payment_batch<R: RngCore + CryptoRng>(
+        &mut self,
+        price_map: &QuotesByAddress,
+        rng: &mut R,
+    ) -> Result<(NanoTokens, NanoTokens)> {
         let mut storage_cost = NanoTokens::zero();
         let mut royalties_fees = NanoTokens::zero();
 
@@ -539,34 +1603,49 @@ impl HotWallet {
 
         // create random derivation indexes for recipients
         let mut recipients_by_xor = BTreeMap::new();
-        for (xorname, (main_pubkey, quote, peer_id_bytes)) in price_map.iter() {
-            let storage_payee = (
-                quote.cost,
-                *main_pubkey,
-                DerivationIndex::random(&mut rng),
-                peer_id_bytes.clone(),
-            );
-            let royalties_fee = calculate_royalties_fee(quote.cost);
-            let royalties_payee = (
-                royalties_fee,
-                *NETWORK_ROYALTIES_PK,
-                DerivationIndex::random(&mut rng),
-            );
-
-            storage_cost = storage_cost
-                .checked_add(quote.cost)
-                .ok_or(WalletError::TotalPriceTooHigh)?;
-            royalties_fees = royalties_fees
-                .checked_add(royalties_fee)
-                .ok_or(WalletError::TotalPriceTooHigh)?;
-
-            recipients_by_xor.insert(xorname, (storage_payee, royalties_payee));
+        for (xorname, payees) in price_map.iter() {
+            if payees.is_empty() {
+                return Err(Error::NoQuoteFound(*xorname));
+            }
+            let mut recipients = vec![];
+            for (main_pubkey, quote, peer_id_bytes) in payees.iter() {
+                quote.check_payable(*xorname, main_pubkey)?;
+                // a payment to an external reward address is bound to the quote it pays
+                let derivation_index = if quote.reward_address == Some(*main_pubkey) {
+                    quote.derivation_index()
+                } else {
+                    DerivationIndex::random(rng)
+                };
+                let storage_payee = (
+                    quote.cost,
+                    *main_pubkey,
+                    derivation_index,
+                    peer_id_bytes.clone(),
+                );
+                let royalties_fee = calculate_royalties_fee(quote.cost);
+                let royalties_payee = (
+                    royalties_fee,
+                    *NETWORK_ROYALTIES_PK,
+                    DerivationIndex::random(rng),
+                );
+
+                storage_cost = storage_cost
+                    .checked_add(quote.cost)
+                    .ok_or(WalletError::TotalPriceTooHigh)?;
+                royalties_fees = royalties_fees
+                    .checked_add(royalties_fee)
+                    .ok_or(WalletError::TotalPriceTooHigh)?;
+
+                recipients.push((storage_payee, royalties_payee, quote.clone()));
+            }
+            recipients_by_xor.insert(xorname, recipients);
         }
 
         // create offline transfers
         let recipients = recipients_by_xor
             .values()
-            .flat_map(|(node, roy)| {
+            .flatten()
+            .flat_map(|(node, roy, _)| {
                 vec![(node.0, node.1, node.2, false), (roy.0, roy.1, roy.2, true)]
             })
             .collect();
@@ -576,8 +1655,26 @@ impl HotWallet {
             start.elapsed()
         );
 
+        let total_cost = storage_cost
+            .checked_add(royalties_fees)
+            .ok_or(WalletError::TotalPriceTooHigh)?;
+        self.approve_spend(&storage_payment_outputs(price_map), price_map.len())?;
         let start = Instant::now();
-        let (available_cash_notes, exclusive_access) = self.available_cash_notes()?;
+        let (available_cash_notes, exclusive_access) =
+            self.select_cash_notes(total_cost, &LargestFirst)?;
+        let selected_value = available_cash_notes
+            .iter()
+            .map(|cash_note| cash_note.value().as_nano())
+            .fold(0, u64::saturating_add);
+        // short of balance, the transaction is left to report it
+        if available_cash_notes.len() > self.tx_limits.max_inputs
+            && selected_value >= total_cost.as_nano()
+        {
+            return Err(Error::TooManyInputs {
+                needed: available_cash_notes.len(),
+                max: self.tx_limits.max_inputs,
+            });
+        }
         trace!(
             "local_send_storage_payment fetched {} cashnotes in {:?}",
             available_cash_notes.len(),
@@ -587,13 +1684,8 @@ impl HotWallet {
 
         let spend_reason = Default::default();
         let start = Instant::now();
-        let signed_tx = SignedTransaction::new(
-            available_cash_notes,
-            recipients,
-            self.address(),
-            spend_reason,
-            &self.key,
-        )?;
+        let signed_tx =
+            self.new_signed_transaction(available_cash_notes, recipients, spend_reason)?;
         trace!(
             "local_send_storage_payment created offline_transfer with {} cashnotes in {:?}",
             signed_tx.output_cashnotes.len(),
@@ -601,70 +1693,85 @@ impl HotWallet {
         );
 
         let start = Instant::now();
-        // cache transfer payments in the wallet
+        // build the payments of the records, cached in the wallet along with its update
         let mut cashnotes_to_use: HashSet<CashNote> =
             signed_tx.output_cashnotes.iter().cloned().collect();
+        let mut storage_payments = BTreeMap::new();
         for (xorname, recipients_info) in recipients_by_xor {
-            let (storage_payee, royalties_payee) = recipients_info;
-            let (pay_amount, node_key, _, peer_id_bytes) = storage_payee;
-            let cash_note_for_node = cashnotes_to_use
-                .iter()
-                .find(|cash_note| {
-                    cash_note.value() == pay_amount && cash_note.main_pubkey() == &node_key
-                })
-                .ok_or(Error::CouldNotSendMoney(format!(
-                    "No cashnote found to pay node for {xorname:?}"
-                )))?
-                .clone();
-            cashnotes_to_use.remove(&cash_note_for_node);
-            let transfer_amount = cash_note_for_node.value();
-            let transfer_for_node = Transfer::transfer_from_cash_note(&cash_note_for_node)?;
-            trace!("Created transaction regarding {xorname:?} paying {transfer_amount:?} to {node_key:?}.");
-
-            let royalties_key = royalties_payee.1;
-            let royalties_amount = royalties_payee.0;
-            let cash_note_for_royalties = cashnotes_to_use
-                .iter()
-                .find(|cash_note| {
-                    cash_note.value() == royalties_amount
-                        && cash_note.main_pubkey() == &royalties_key
-                })
-                .ok_or(Error::CouldNotSendMoney(format!(
-                    "No cashnote found to pay royalties for {xorname:?}"
-                )))?
-                .clone();
-            cashnotes_to_use.remove(&cash_note_for_royalties);
-            let royalties = Transfer::royalties_transfer_from_cash_note(&cash_note_for_royalties)?;
-            let royalties_amount = cash_note_for_royalties.value();
-            trace!("Created network royalties cnr regarding {xorname:?} paying {royalties_amount:?} to {royalties_key:?}.");
-
-            let quote = price_map
-                .get(xorname)
-                .ok_or(Error::CouldNotSendMoney(format!(
-                    "No quote found for {xorname:?}"
-                )))?
-                .1
-                .clone();
-            let payment = PaymentDetails {
-                recipient: node_key,
-                peer_id_bytes,
-                transfer: (transfer_for_node, transfer_amount),
-                royalties: (royalties, royalties_amount),
-                quote,
-            };
+            let mut payments = vec![];
+            for (storage_payee, royalties_payee, quote) in recipients_info {
+                let (pay_amount, node_key, _, peer_id_bytes) = storage_payee;
+                let cash_note_for_node = cashnotes_to_use
+                    .iter()
+                    .find(|cash_note| {
+                        cash_note.value() == pay_amount && cash_note.main_pubkey() == &node_key
+                    })
+                    .ok_or(Error::PaymentCashNoteNotFound {
+                        content: *xorname,
+                        payee: "the node",
+                    })?
+                    .clone();
+                cashnotes_to_use.remove(&cash_note_for_node);
+                let transfer_amount = cash_note_for_node.value();
+                // the nodes paying their rewards to an external key cannot decrypt the payment
+                let transfer_for_node = if quote.reward_address == Some(node_key) {
+                    Transfer::unencrypted_transfer_from_cash_note(&cash_note_for_node)
+                } else {
+                    Transfer::transfer_from_cash_note(&cash_note_for_node)?
+                };
+                trace!("Created transaction regarding {xorname:?} paying {transfer_amount:?} to {node_key:?}.");
+
+                let royalties_key = royalties_payee.1;
+                let royalties_amount = royalties_payee.0;
+                let cash_note_for_royalties = cashnotes_to_use
+                    .iter()
+                    .find(|cash_note| {
+                        cash_note.value() == royalties_amount
+                            && cash_note.main_pubkey() == &royalties_key
+                    })
+                    .ok_or(Error::PaymentCashNoteNotFound {
+                        content: *xorname,
+                        payee: "the royalties",
+                    })?
+                    .clone();
+                cashnotes_to_use.remove(&cash_note_for_royalties);
+                let royalties =
+                    Transfer::royalties_transfer_from_cash_note(&cash_note_for_royalties)?;
+                let royalties_amount = cash_note_for_royalties.value();
+                trace!("Created network royalties cnr regarding {xorname:?} paying {royalties_amount:?} to {royalties_key:?}.");
+
+                payments.push(PaymentDetails {
+                    recipient: node_key,
+                    peer_id_bytes,
+                    transfer: (transfer_for_node, transfer_amount),
+                    royalties: (royalties, royalties_amount),
+                    quote,
+                    other_payees: vec![],
+                });
+            }
 
-            let _ = self
-                .watchonly_wallet
-                .insert_payment_transaction(*xorname, payment);
+            // the first payee is the one the record is uploaded to, the others are listed along
+            let mut payment = payments.remove(0);
+            payment.other_payees = payments;
+
+            let _ = storage_payments.insert(*xorname, payment);
         }
         trace!(
-            "local_send_storage_payment completed payments insertion in {:?}",
+            "local_send_storage_payment completed payments creation in {:?}",
             start.elapsed()
         );
 
+        let entry = HistoryEntry::new(
+            HistoryKind::StoragePayment,
+            storage_cost,
+            royalties_fees,
+            None,
+            vec![],
+        );
+
         // write all changes to local wallet
         let start = Instant::now();
-        self.update_local_wallet(signed_tx, exclusive_access, true)?;
+        self.apply_local_send(signed_tx, exclusive_access, entry, storage_payments)?;
         trace!(
             "local_send_storage_payment completed local wallet update in {:?}",
             start.elapsed()
@@ -680,15 +1787,49 @@ impl HotWallet {
         exclusive_access: WalletExclusiveAccess,
         insert_into_pending_spends: bool,
     ) -> Result<()> {
-        self.update_local_wallet(transfer, exclusive_access, insert_into_pending_spends)
+        self.update_local_wallet(
+            transfer,
+            exclusive_access,
+            insert_into_pending_spends,
+            BTreeMap::new(),
+            None,
+        )
     }
 
+    /// Apply a transaction of the wallet, along with the payments it makes for the storage of
+    /// records, once the hooks agreed to it.
     fn update_local_wallet(
         &mut self,
         signed_tx: SignedTransaction,
         exclusive_access: WalletExclusiveAccess,
         insert_into_pending_spends: bool,
+        storage_payments: BTreeMap<XorName, PaymentDetails>,
+        history: Option<HistoryEntry>,
     ) -> Result<()> {
+        // a change index handed out for anything else would mix the funds of both
+        if let Some(cash_note) = &signed_tx.change_cashnote {
+            let index = cash_note.derivation_index();
+            let mut registry = self.derivation_indexes()?;
+            match registry.get(&index) {
+                Some(handed_out) if handed_out.purpose == DerivationPurpose::Change => {}
+                Some(_) => return Err(Error::DerivationIndexReused(index)),
+                None => registry.register(index, DerivationPurpose::Change)?,
+            }
+        }
+
+        if let Some(hooks) = self.watchonly_wallet.hooks() {
+            hooks.on_spend(&signed_tx, &storage_payments)?;
+        }
+
+        // cache transfer payments in the wallet, along with the spends they rely on
+        let payment_spends: BTreeSet<_> = signed_tx.spends.iter().map(|s| s.address()).collect();
+        for (xorname, payment) in storage_payments {
+            let _ = self
+                .watchonly_wallet
+                .insert_payment_transaction(xorname, payment);
+            let _ = self.api().insert_payment_spends(xorname, &payment_spends);
+        }
+
         // First of all, update client local state.
         let spent_unique_pubkeys: BTreeSet<_> =
             signed_tx.spends.iter().map(|s| s.unique_pubkey()).collect();
@@ -698,7 +1839,9 @@ impl HotWallet {
 
         if let Some(cash_note) = signed_tx.change_cashnote {
             let start = Instant::now();
-            self.watchonly_wallet.deposit(&[cash_note.clone()])?;
+            // the change is part of the transaction the hooks were called with
+            self.watchonly_wallet
+                .insert_cash_notes(std::slice::from_ref(&cash_note));
             trace!(
                 "update_local_wallet completed deposit change cash_note in {:?}",
                 start.elapsed()
@@ -716,15 +1859,27 @@ impl HotWallet {
                 start.elapsed()
             );
         }
+        // the outputs to the wallet on indexes handed out for its change, e.g. those of a split,
+        // come back to it like the change
+        let returning = self.returning_outputs(&signed_tx.output_cashnotes)?;
+        if !returning.is_empty() {
+            self.watchonly_wallet.insert_cash_notes(&returning);
+            self.store_cash_notes_to_disk(&returning)?;
+        }
         if insert_into_pending_spends {
             for request in signed_tx.spends {
-                self.unconfirmed_spend_requests.insert(request);
+                let _ = self.unconfirmed_spend_metadata.insert(
+                    request.address(),
+                    UnconfirmedSpendMetadata::new(SystemTime::now()),
+                );
+                Arc::make_mut(&mut self.unconfirmed_spend_requests).insert(request);
             }
         }
 
-        // store wallet to disk
+        // store wallet to disk, then record its history before releasing the lock
         let start = Instant::now();
-        self.store(exclusive_access)?;
+        self.watchonly_wallet
+            .store_recording(exclusive_access, history)?;
         trace!(
             "update_local_wallet completed store self wallet to disk in {:?}",
             start.elapsed()
@@ -732,6 +1887,139 @@ impl HotWallet {
         Ok(())
     }
 
+    /// Build and sign a transaction spending the inputs, the biggest first, its change going to
+    /// the next index of the change sequence of the wallet.
+    fn new_signed_transaction(
+        &self,
+        mut inputs: Vec<CashNote>,
+        recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex, bool)>,
+        reason: SpendReason,
+    ) -> Result<SignedTransaction> {
+        inputs.sort_by_key(|cash_note| std::cmp::Reverse(cash_note.value()));
+        let change_index = self.next_derivation_index(DerivationPurpose::Change)?;
+        Ok(UnsignedTransaction::new_with_change_index(
+            inputs,
+            recipients,
+            self.address(),
+            change_index,
+            reason,
+        )?
+        .sign(&self.key)?)
+    }
+
+    /// The outputs paid to the wallet on a derivation index handed out for its change.
+    fn returning_outputs(&self, outputs: &[CashNote]) -> Result<Vec<CashNote>> {
+        let address = self.address();
+        let to_self: Vec<_> = outputs
+            .iter()
+            .filter(|cash_note| cash_note.main_pubkey() == &address)
+            .collect();
+        if to_self.is_empty() {
+            return Ok(vec![]);
+        }
+        let registry = self.derivation_indexes()?;
+        Ok(to_self
+            .into_iter()
+            .filter(|cash_note| {
+                registry
+                    .get(&cash_note.derivation_index())
+                    .is_some_and(|handed_out| handed_out.purpose == DerivationPurpose::Change)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Convert the wallet file to the given serialization, e.g. JSON to inspect it.
+    pub fn set_file_format(&mut self, format: WalletFileFormat) -> Result<()> {
+        self.watchonly_wallet.set_file_format(format)
+    }
+
+    /// The history of the tokens received and sent by the wallet.
+    pub fn history(&self) -> Result<History> {
+        self.watchonly_wallet.history()
+    }
+
+    /// Move the cash notes spent `older_than` ago or more out of the `cash_notes` dir and its
+    /// index, into the archive of the spent cash notes of the wallet dir. Returns the number of
+    /// cash notes archived.
+    ///
+    /// A cash note is as old as the entry of the history spending it, else as the time the
+    /// index of the cash notes found it spent, spends such as consolidations recording no history,
+    /// and older than any if it was spent before either was recorded. The cash notes of the
+    /// unconfirmed spends are kept, as these spends may have to be made again.
+    pub fn prune_spent(&mut self, older_than: Duration) -> Result<usize> {
+        // the spent cash notes are only kept in the `cash_notes` dir
+        let Some(wallet_dir) = self.watchonly_wallet.dir_on_disk().map(Path::to_path_buf) else {
+            return Ok(0);
+        };
+        // lock and load from disk, for the spends made since by other processes to be known
+        let exclusive_access = self.lock()?;
+        self.reload()?;
+
+        let cutoff = SystemTime::now()
+            .checked_sub(older_than)
+            .and_then(|cutoff| cutoff.duration_since(UNIX_EPOCH).ok())
+            .map(|cutoff| cutoff.as_secs())
+            .unwrap_or_default();
+        let index = CashNoteIndex::load(&wallet_dir)?;
+        let mut spent_at: BTreeMap<UniquePubkey, u64> = index
+            .iter()
+            .filter_map(|(unique_pubkey, metadata)| Some((*unique_pubkey, metadata.spent_at?)))
+            .collect();
+        spent_at.extend(
+            self.history()?
+                .entries()
+                .iter()
+                .filter(|entry| entry.kind != HistoryKind::Received)
+                .flat_map(|entry| entry.cash_notes.iter().map(|pk| (*pk, entry.time))),
+        );
+        let unconfirmed: BTreeSet<UniquePubkey> = self
+            .unconfirmed_spend_requests
+            .iter()
+            .map(|signed_spend| signed_spend.spend.unique_pubkey)
+            .collect();
+        let prunable: Vec<UniquePubkey> = index
+            .iter()
+            .filter(|(unique_pubkey, metadata)| {
+                metadata.status == CashNoteStatus::Spent
+                    && !unconfirmed.contains(unique_pubkey)
+                    && spent_at.get(unique_pubkey).copied().unwrap_or_default() <= cutoff
+            })
+            .map(|(unique_pubkey, _)| *unique_pubkey)
+            .collect();
+
+        let archived: Vec<ArchivedCashNote> = load_indexed_cash_notes(&wallet_dir, &prunable)?
+            .into_iter()
+            .map(|cash_note| ArchivedCashNote {
+                spent_at: spent_at
+                    .get(&cash_note.unique_pubkey())
+                    .copied()
+                    .unwrap_or_default(),
+                cash_note,
+            })
+            .collect();
+        // archived before being removed, for an interrupted pruning not to lose any
+        SpentArchive::append(&wallet_dir, &archived)?;
+        remove_cash_notes(&prunable, &wallet_dir)?;
+        std::mem::drop(exclusive_access);
+
+        info!(
+            "Archived {} spent cash notes of wallet {:?}",
+            archived.len(),
+            self.address()
+        );
+        Ok(archived.len())
+    }
+
+    /// The spent cash notes archived by [`HotWallet::prune_spent`], none for a wallet not stored
+    /// on disk.
+    pub fn spent_archive(&self) -> Result<SpentArchive> {
+        match self.watchonly_wallet.dir_on_disk() {
+            Some(wallet_dir) => SpentArchive::load(wallet_dir),
+            None => Ok(SpentArchive::default()),
+        }
+    }
+
     /// Deposit the given cash_notes on the wallet (without storing them to disk).
     pub fn deposit(&mut self, received_cash_notes: &Vec<CashNote>) -> Result<()> {
         self.watchonly_wallet.deposit(received_cash_notes)
@@ -745,16 +2033,86 @@ impl HotWallet {
             .deposit_and_store_to_disk(received_cash_notes)
     }
 
+    /// Like `deposit_and_store_to_disk`, without blocking the async runtime meanwhile.
+    #[cfg(feature = "async-io")]
+    pub async fn deposit_and_store_to_disk_async(
+        &mut self,
+        received_cash_notes: Vec<CashNote>,
+    ) -> Result<()> {
+        self.watchonly_wallet
+            .deposit_and_store_to_disk_async(received_cash_notes)
+            .await
+    }
+
     pub fn unwrap_transfer(&self, transfer: &Transfer) -> Result<Vec<CashNoteRedemption>> {
         transfer
             .cashnote_redemptions(&self.key)
-            .map_err(|_| Error::FailedToDecypherTransfer)
+            .map_err(Error::FailedToDecypherTransfer)
     }
 
     pub fn derive_key(&self, derivation_index: &DerivationIndex) -> DerivedSecretKey {
         self.key.derive_key(derivation_index)
     }
 
+    /// The address book of the wallet, naming the recipients it sends to. A wallet not stored on
+    /// disk has none.
+    pub fn address_book(&self) -> Result<AddressBook> {
+        self.watchonly_wallet.address_book()
+    }
+
+    /// The pubkeys of the recipients, looking their labels up in the address book.
+    pub fn resolve_recipients(
+        &self,
+        to: Vec<(NanoTokens, Recipient)>,
+    ) -> Result<Vec<(NanoTokens, MainPubkey)>> {
+        let address_book = match self.watchonly_wallet.dir_on_disk() {
+            Some(wallet_dir) => AddressBook::load(wallet_dir)?,
+            // only the recipients given by their pubkey can be resolved then
+            None => AddressBook::default(),
+        };
+        to.into_iter()
+            .map(|(amount, recipient)| Ok((amount, address_book.resolve(&recipient)?)))
+            .collect()
+    }
+
+    /// The registry of the derivation indexes handed out by the wallet.
+    pub fn derivation_indexes(&self) -> Result<DerivationIndexRegistry> {
+        self.watchonly_wallet.wallet_store().derivation_indexes()
+    }
+
+    /// Hand out the next derivation index of the purpose, e.g. to receive the payment of an
+    /// invoice, never handed out before.
+    pub fn next_derivation_index(&self, purpose: DerivationPurpose) -> Result<DerivationIndex> {
+        self.derivation_indexes()?
+            .next_index(&self.derivation_seed(), purpose)
+    }
+
+    /// Recover the derivation indexes of the purpose handed out before the wallet was restored
+    /// from its key, as scanned by [`DerivationIndexRegistry::scan`].
+    pub fn scan_derivation_indexes<F>(
+        &self,
+        purpose: DerivationPurpose,
+        gap_limit: u64,
+        is_used: F,
+    ) -> Result<Vec<DerivationIndex>>
+    where
+        F: FnMut(&DerivationIndex) -> Result<bool>,
+    {
+        self.derivation_indexes()?
+            .scan(&self.derivation_seed(), purpose, gap_limit, is_used)
+    }
+
+    /// The seed of the sequential derivation indexes, secret for them not to link the cash notes
+    /// of the wallet together.
+    fn derivation_seed(&self) -> Hash {
+        let input = [
+            b"derivation-indexes".as_slice(),
+            &self.key.secret_key().to_bytes(),
+        ]
+        .concat();
+        Hash::hash(&input)
+    }
+
     /// Loads a serialized wallet from a path.
     // TODO: what's the behaviour here if path has stored key and we pass one in?
     fn load_from_path_and_key(
@@ -788,33 +2146,114 @@ impl HotWallet {
         };
         let unconfirmed_spend_requests =
             (get_unconfirmed_spend_requests(wallet_dir)?).unwrap_or_default();
+        let unconfirmed_spend_metadata =
+            load_unconfirmed_spend_metadata(wallet_dir, &unconfirmed_spend_requests)?;
         let watchonly_wallet = WatchOnlyWallet::load_from(wallet_dir, key.main_pubkey())?;
 
         Ok(Self {
             key,
             watchonly_wallet,
-            unconfirmed_spend_requests,
+            unconfirmed_spend_requests: Arc::new(unconfirmed_spend_requests),
+            unconfirmed_spend_metadata,
             authentication_manager: AuthenticationManager::new(wallet_dir.to_path_buf()),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
+        })
+    }
+}
+
+/// Split the content addresses to pay for into batches whose transaction creates at most
+/// `max_outputs` cash notes, change included. Each one is paid to its payees and the royalties of
+/// each, within a single batch.
+fn split_storage_payments(
+    price_map: &QuotesByAddress,
+    max_outputs: usize,
+) -> Result<VecDeque<QuotesByAddress>> {
+    let max_payment_outputs = max_outputs.saturating_sub(1);
+    let mut batches = VecDeque::new();
+    let mut batch = BTreeMap::new();
+    let mut batch_outputs = 0;
+    for (xorname, payees) in price_map {
+        let outputs = payees.len() * 2;
+        if outputs > max_payment_outputs {
+            return Err(Error::TooManyOutputs {
+                needed: outputs + 1,
+                max: max_outputs,
+            });
+        }
+        if batch_outputs + outputs > max_payment_outputs {
+            batches.push_back(std::mem::take(&mut batch));
+            batch_outputs = 0;
+        }
+        let _ = batch.insert(*xorname, payees.clone());
+        batch_outputs += outputs;
+    }
+    if !batch.is_empty() {
+        batches.push_back(batch);
+    }
+    Ok(batches)
+}
+
+/// The outputs of the payments for the content addresses, to their payees and the royalties of
+/// each.
+fn storage_payment_outputs(price_map: &QuotesByAddress) -> Vec<(NanoTokens, MainPubkey)> {
+    price_map
+        .values()
+        .flatten()
+        .flat_map(|(main_pubkey, quote, _)| {
+            [
+                (quote.cost, *main_pubkey),
+                (calculate_royalties_fee(quote.cost), *NETWORK_ROYALTIES_PK),
+            ]
         })
+        .collect()
+}
+
+/// Remove the cash notes spent by the confirmed `spends` from the store, along with the spends
+/// themselves. The failures are only logged: the spends are confirmed either way.
+fn remove_confirmed_spends(store: &dyn WalletStore, spends: &BTreeSet<SignedSpend>) {
+    let unique_pubkeys: Vec<_> = spends.iter().map(|s| s.spend.unique_pubkey).collect();
+    if let Err(error) = store.remove_cash_notes(&unique_pubkeys) {
+        warn!("Could not clean confirmed spent cash_notes due to {error:?}");
+    }
+
+    // Also need to remove unconfirmed_spend_requests from disk if was pre-loaded.
+    let removed = match store.wallet_dir() {
+        Some(wallet_dir) => remove_unconfirmed_spend_metadata(wallet_dir),
+        None => Ok(()),
+    }
+    .and_then(|()| store.confirm_unconfirmed_spend_requests(spends));
+    if let Err(error) = removed {
+        warn!("Could not clear the confirmed spend requests due to {error:?}");
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeMap;
+    use std::collections::{BTreeMap, BTreeSet};
 
-    use super::HotWallet;
+    use super::{
+        HotWallet, IdempotentSend, PaymentRequest, Recipient, ResendPolicy, TransactionLimits,
+    };
     use crate::wallet::authentication::AuthenticationManager;
     use crate::{
         genesis::{create_first_cash_note_from_key, GENESIS_CASHNOTE_AMOUNT},
+        rng,
         wallet::{
-            data_payments::PaymentQuote, hot_wallet::WALLET_DIR_NAME, wallet_file::store_wallet,
-            watch_only::WatchOnlyWallet, KeyLessWallet,
+            data_payments::{PaymentDetails, PaymentQuote},
+            hot_wallet::WALLET_DIR_NAME,
+            wallet_file::{store_idempotent_send, store_wallet},
+            watch_only::WatchOnlyWallet,
+            DerivationPurpose, Error, HandedOutIndex, KeyLessWallet, MemoryWalletStore,
+            WalletHooks, WalletStore,
         },
-        MainSecretKey, NanoTokens, SpendAddress,
+        CashNote, DerivationIndex, Hash, MainSecretKey, NanoTokens, SignedTransaction,
+        SpendAddress, SpendReason,
     };
     use assert_fs::TempDir;
     use eyre::Result;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
     use xor_name::XorName;
 
     #[tokio::test]
@@ -826,8 +2265,7 @@ mod tests {
         let dir = create_temp_dir();
         let wallet_dir = dir.path().to_path_buf();
 
-        wallet
-            .available_cash_notes
+        Arc::make_mut(&mut wallet.available_cash_notes)
             .insert(genesis.unique_pubkey(), genesis.value());
 
         store_wallet(&wallet_dir, &wallet)?;
@@ -851,7 +2289,10 @@ mod tests {
             key,
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
+            unconfirmed_spend_metadata: Default::default(),
             authentication_manager: AuthenticationManager::new(dir.to_path_buf()),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
         };
 
         assert_eq!(main_pubkey, deposit_only.address());
@@ -879,7 +2320,10 @@ mod tests {
             key,
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
+            unconfirmed_spend_metadata: Default::default(),
             authentication_manager: AuthenticationManager::new(dir.to_path_buf()),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
         };
 
         deposit_only.deposit_and_store_to_disk(&vec![])?;
@@ -905,7 +2349,10 @@ mod tests {
             key,
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
+            unconfirmed_spend_metadata: Default::default(),
             authentication_manager: AuthenticationManager::new(dir.to_path_buf()),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
         };
 
         deposit_only.deposit_and_store_to_disk(&vec![genesis])?;
@@ -927,7 +2374,10 @@ mod tests {
             key,
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
+            unconfirmed_spend_metadata: Default::default(),
             authentication_manager: AuthenticationManager::new(dir.to_path_buf()),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
         };
 
         local_wallet.deposit_and_store_to_disk(&vec![genesis])?;
@@ -951,7 +2401,10 @@ mod tests {
             key,
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
+            unconfirmed_spend_metadata: Default::default(),
             authentication_manager: AuthenticationManager::new(dir.to_path_buf()),
+            tx_limits: TransactionLimits::default(),
+            approved_allowance: NanoTokens::zero(),
         };
 
         deposit_only.deposit_and_store_to_disk(&vec![genesis_0.clone()])?;
@@ -1043,43 +2496,716 @@ mod tests {
         Ok(())
     }
 
-    #[tokio::test]
-    async fn send_wallet_to_and_from_file() -> Result<()> {
+    #[test]
+    fn the_change_should_go_to_the_change_sequence() -> Result<()> {
         let dir = create_temp_dir();
-        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note = create_first_cash_note_from_key(&sender.key)?;
+        sender.deposit_and_store_to_disk(&vec![cash_note])?;
 
-        let new_wallet = MainSecretKey::random();
-        let mut sender = HotWallet::create_from_key(&root_dir, new_wallet, None)?;
+        let to = vec![(NanoTokens::from(100), MainSecretKey::random().main_pubkey())];
+        let _ = sender.local_send(to, None)?;
 
-        let sender_cash_note =
+        let (cash_notes, _exclusive_access) = sender.available_cash_notes()?;
+        let [change] = cash_notes.as_slice() else {
+            panic!("the change should be the only cash note left");
+        };
+        assert_eq!(
+            sender.derivation_indexes()?.get(&change.derivation_index()),
+            Some(&HandedOutIndex {
+                purpose: DerivationPurpose::Change,
+                sequence: Some(0)
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sending_to_a_label_pays_its_pubkey() -> Result<()> {
+        let dir = create_temp_dir();
+        let mut sender = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note =
             create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
-        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+        sender.deposit_and_store_to_disk(&vec![cash_note])?;
 
-        // We send to a new address.
-        let send_amount = 100;
-        let recipient_key = MainSecretKey::random();
-        let recipient_main_pubkey = recipient_key.main_pubkey();
-        let to = vec![(NanoTokens::from(send_amount), recipient_main_pubkey)];
-        let _created_cash_notes = sender.local_send(to, None)?;
+        let recipient = MainSecretKey::random().main_pubkey();
+        let to = vec![(NanoTokens::from(100), Recipient::Label("alice".to_string()))];
+        assert!(matches!(
+            sender.local_send_to(to.clone(), None),
+            Err(Error::UnknownRecipient(_))
+        ));
 
-        let deserialized = HotWallet::load_from(&root_dir)?;
+        sender.address_book()?.add("alice", recipient)?;
+        let created_cash_notes = sender.local_send_to(to, None)?;
+        assert_eq!(created_cash_notes.len(), 1);
+        assert_eq!(created_cash_notes[0].main_pubkey(), &recipient);
 
-        assert_eq!(sender.address(), deserialized.address());
-        assert_eq!(
-            GENESIS_CASHNOTE_AMOUNT - send_amount,
-            sender.balance().as_nano()
-        );
-        assert_eq!(
-            GENESIS_CASHNOTE_AMOUNT - send_amount,
-            deserialized.balance().as_nano()
-        );
+        Ok(())
+    }
 
-        assert_eq!(1, sender.watchonly_wallet.available_cash_notes().len());
+    #[cfg(unix)]
+    #[test]
+    fn spends_above_the_threshold_should_need_an_approval() -> Result<()> {
+        use crate::{ApprovalListener, SpendApprovalConfig};
 
-        assert_eq!(
-            1,
-            deserialized.watchonly_wallet.available_cash_notes().len()
-        );
+        let dir = create_temp_dir();
+        let mut sender = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![cash_note])?;
+        let wallet_dir = sender.watchonly_wallet.wallet_dir().to_path_buf();
+        let config = SpendApprovalConfig::new(&wallet_dir, NanoTokens::from(100));
+        config.store(&wallet_dir)?;
+        let recipient = MainSecretKey::random().main_pubkey();
+
+        // up to the threshold, no approval is asked
+        let _ = sender.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        // above, the spend is denied while no approver listens
+        assert!(matches!(
+            sender.local_send(vec![(NanoTokens::from(101), recipient)], None),
+            Err(Error::SpendNotApproved { .. })
+        ));
+
+        let listener = ApprovalListener::bind(&config.socket_path)?;
+        let approver = std::thread::spawn(move || -> crate::wallet::Result<Vec<NanoTokens>> {
+            let mut amounts = vec![];
+            for approved in [true, false] {
+                let pending = listener.next_request()?;
+                amounts.push(pending.request.amount);
+                pending.respond(approved)?;
+            }
+            Ok(amounts)
+        });
+        let _ = sender.local_send(vec![(NanoTokens::from(200), recipient)], None)?;
+        assert!(matches!(
+            sender.local_send(vec![(NanoTokens::from(300), recipient)], None),
+            Err(Error::SpendNotApproved { .. })
+        ));
+        let amounts = approver.join().expect("The approver not to panic")?;
+        assert_eq!(amounts, vec![NanoTokens::from(200), NanoTokens::from(300)]);
+
+        let audit_log = std::fs::read_to_string(wallet_dir.join("spend_approvals.log"))?;
+        let outcomes: Vec<_> = audit_log
+            .lines()
+            .map(|line| {
+                let record: serde_json::Value = serde_json::from_str(line)?;
+                Ok(record["outcome"].as_str().unwrap_or_default().to_string())
+            })
+            .collect::<Result<_>>()?;
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes[0], "below threshold");
+        assert!(outcomes[1].starts_with("failed"));
+        assert_eq!(outcomes[2..], ["approved", "denied"]);
+        assert_eq!(sender.balance().as_nano(), GENESIS_CASHNOTE_AMOUNT - 300);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_threshold_should_apply_to_what_leaves_the_wallet() -> Result<()> {
+        use crate::SpendApprovalConfig;
+
+        let dir = create_temp_dir();
+        let mut sender = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![cash_note])?;
+        let wallet_dir = sender.watchonly_wallet.wallet_dir().to_path_buf();
+        SpendApprovalConfig::new(&wallet_dir, NanoTokens::from(100)).store(&wallet_dir)?;
+        let recipient = MainSecretKey::random().main_pubkey();
+
+        // what the wallet sends to itself needs no approval
+        let _ = sender.local_send(vec![(NanoTokens::from(1_000), sender.address())], None)?;
+        sender.store_unconfirmed_spend_requests()?;
+        let _ = sender.split(vec![NanoTokens::from(1_000); 2])?;
+        let balance = sender.balance().as_nano();
+
+        // a payment split in two is approved like a single one
+        let _ = sender.local_send(vec![(NanoTokens::from(60), recipient)], None)?;
+        sender.store_unconfirmed_spend_requests()?;
+        assert!(matches!(
+            sender.local_send(vec![(NanoTokens::from(60), recipient)], None),
+            Err(Error::SpendNotApproved { .. })
+        ));
+        assert_eq!(sender.balance().as_nano(), balance - 60);
+
+        // as is a transaction signed apart from its building
+        let unsigned_tx =
+            sender.build_unsigned_transaction(vec![(NanoTokens::from(60), recipient)], None)?;
+        assert!(matches!(
+            sender.sign(unsigned_tx),
+            Err(Error::SpendNotApproved { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn paying_a_request_sends_the_requested_amount() -> Result<()> {
+        let dir = create_temp_dir();
+        let mut sender = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![cash_note])?;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let request: PaymentRequest = PaymentRequest::new(recipient)
+            .with_amount(NanoTokens::from(100))
+            .with_reason(Hash::hash(b"order 42"))
+            .to_string()
+            .parse()?;
+        let expired = PaymentRequest {
+            expiry: Some(std::time::UNIX_EPOCH),
+            ..request.clone()
+        };
+        assert!(matches!(
+            sender.local_send_payment_request(&expired, None),
+            Err(Error::PaymentRequestExpired)
+        ));
+
+        let created_cash_notes = sender.local_send_payment_request(&request, None)?;
+        assert_eq!(created_cash_notes.len(), 1);
+        assert_eq!(created_cash_notes[0].main_pubkey(), &recipient);
+        assert_eq!(created_cash_notes[0].value(), NanoTokens::from(100));
+        sender.store_unconfirmed_spend_requests()?;
+
+        // an invoice is paid to the key it gives the index of
+        let invoice = PaymentRequest::new(recipient)
+            .with_derivation_index(DerivationIndex([9; 32]))
+            .with_amount(NanoTokens::from(50))
+            .with_id("invoice-1")?;
+        let created_cash_notes = sender.local_send_payment_request(&invoice, None)?;
+        assert_eq!(
+            Some(created_cash_notes[0].unique_pubkey()),
+            invoice.unique_pubkey()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sending_with_the_same_seed_is_reproducible() -> Result<()> {
+        let secret_key = bls::SecretKey::random();
+        let recipient = MainSecretKey::random().main_pubkey();
+        let send = |seed: [u8; 32]| -> Result<_> {
+            let dir = create_temp_dir();
+            let mut sender = HotWallet::create_from_key(
+                dir.path(),
+                MainSecretKey::new(secret_key.clone()),
+                None,
+            )?;
+            let cash_note =
+                create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+            sender.deposit_and_store_to_disk(&vec![cash_note])?;
+            let to = vec![(NanoTokens::from(100), recipient)];
+            Ok(sender.local_send_with_rng(to, None, &mut rng::from_seed(seed))?)
+        };
+
+        assert_eq!(send([1; 32])?, send([1; 32])?);
+        assert_ne!(
+            send([1; 32])?[0].unique_pubkey(),
+            send([2; 32])?[0].unique_pubkey()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sweeping_sends_all_but_the_amount_to_keep() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let new_wallet = MainSecretKey::random();
+        let mut sender = HotWallet::create_from_key(&root_dir, new_wallet, None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let keep = 100;
+        let recipient_main_pubkey = MainSecretKey::random().main_pubkey();
+        let created_cash_notes =
+            sender.local_sweep(recipient_main_pubkey, NanoTokens::from(keep))?;
+
+        assert_eq!(1, created_cash_notes.len());
+        assert_eq!(keep, sender.balance().as_nano());
+        assert_eq!(
+            NanoTokens::from(GENESIS_CASHNOTE_AMOUNT - keep),
+            created_cash_notes[0].value()
+        );
+
+        // nothing is left to sweep once only the amount to keep remains
+        assert!(sender
+            .local_sweep(recipient_main_pubkey, NanoTokens::from(keep))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn consolidating_merges_the_smallest_cash_notes() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let receiver_dir = create_temp_dir();
+        let receiver_root_dir = receiver_dir.path().to_path_buf();
+        let mut receiver =
+            HotWallet::create_from_key(&receiver_root_dir, MainSecretKey::random(), None)?;
+        let amounts = [100, 10, 30, 20, 40];
+        let received = sender.local_send(
+            amounts
+                .iter()
+                .map(|amount| (NanoTokens::from(*amount), receiver.address()))
+                .collect(),
+            None,
+        )?;
+        receiver.deposit_and_store_to_disk(&received)?;
+        let balance = receiver.balance();
+
+        let consolidated = receiver.consolidate(3, 2)?;
+
+        // 5 notes, the 3 smallest merged into one, then the 2 smallest of the 3 left
+        assert_eq!(
+            consolidated
+                .iter()
+                .map(|cash_note| cash_note.value().as_nano())
+                .collect::<Vec<_>>(),
+            vec![60, 100]
+        );
+        assert_eq!(receiver.available_cash_notes()?.0.len(), 2);
+        assert_eq!(receiver.balance(), balance);
+        assert_eq!(receiver.unconfirmed_spend_requests().len(), 5);
+
+        // nothing more to consolidate
+        assert!(receiver.consolidate(3, 2)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn splitting_reissues_the_given_denominations_to_the_wallet() -> Result<()> {
+        let dir = create_temp_dir();
+        let mut wallet = crate::test_utils::WalletBuilder::with_balance(NanoTokens::from(1_000))
+            .build(dir.path())?;
+
+        let split = wallet.split(vec![NanoTokens::from(100); 3])?;
+
+        assert_eq!(split.len(), 3);
+        assert!(split
+            .iter()
+            .all(|cash_note| cash_note.value() == NanoTokens::from(100)
+                && cash_note.main_pubkey() == &wallet.address()));
+        let (available, _) = wallet.available_cash_notes()?;
+        let mut values: Vec<_> = available
+            .iter()
+            .map(|cash_note| cash_note.value().as_nano())
+            .collect();
+        values.sort();
+        assert_eq!(values, vec![100, 100, 100, 700]);
+        assert_eq!(wallet.balance(), NanoTokens::from(1_000));
+        assert!(!wallet.unconfirmed_spend_requests().is_empty());
+
+        // the split cash notes are kept across a reload of the wallet
+        let reloaded = HotWallet::load_from(dir.path())?;
+        assert_eq!(reloaded.balance(), NanoTokens::from(1_000));
+
+        assert!(matches!(
+            wallet.split(vec![NanoTokens::from(1_001)]),
+            Err(Error::Transfer(crate::TransferError::NotEnoughBalance(..)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn unconfirmed_spends_should_be_resent_with_a_backoff_and_pruned() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![cash_note])?;
+        // the second send spends the change of the first one
+        for _ in 0..2 {
+            let to = vec![(NanoTokens::from(100), MainSecretKey::random().main_pubkey())];
+            let _ = sender.local_send(to, None)?;
+            sender.store_unconfirmed_spend_requests()?;
+        }
+
+        let policy = ResendPolicy {
+            initial_backoff: Duration::from_secs(3600),
+            ..Default::default()
+        };
+        // due right away, then backing off once a resend failed, the other spend still due
+        let due = sender.due_unconfirmed_spend_requests(&policy);
+        assert_eq!(due.len(), 2);
+        let failed = due[0].address();
+        assert_eq!(
+            sender.record_resend_attempt(failed, Some("network error".to_string())),
+            1
+        );
+        sender.store_unconfirmed_spend_requests()?;
+        let due = sender.due_unconfirmed_spend_requests(&policy);
+        assert_eq!(due.len(), 1);
+        assert_ne!(due[0].address(), failed);
+
+        // the attempts are persisted along with the spends
+        let mut reloaded = HotWallet::load_from(&root_dir)?;
+        let metadata = reloaded.unconfirmed_spend_metadata().clone();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata[&failed].attempts, 1);
+        assert_eq!(
+            metadata[&failed].last_error.as_deref(),
+            Some("network error")
+        );
+        assert_eq!(metadata[&due[0].address()].attempts, 0);
+        assert!(reloaded.next_resend_at(&policy) <= Some(SystemTime::now()));
+        let _ = reloaded.record_resend_attempt(due[0].address(), None);
+        assert!(reloaded.next_resend_at(&policy) > Some(SystemTime::now()));
+
+        let keep = ResendPolicy::default();
+        assert!(reloaded.prune_expired_spend_requests(&keep)?.is_empty());
+        let give_up = ResendPolicy {
+            max_age: Duration::ZERO,
+            ..Default::default()
+        };
+        assert_eq!(reloaded.prune_expired_spend_requests(&give_up)?.len(), 2);
+        assert!(!reloaded.unconfirmed_spend_requests_exist());
+        // along with the change of their transactions
+        assert_eq!(reloaded.balance(), NanoTokens::zero());
+        assert_eq!(
+            HotWallet::load_from(&root_dir)?.balance(),
+            NanoTokens::zero()
+        );
+        assert!(HotWallet::load_from(&root_dir)?
+            .unconfirmed_spend_metadata()
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn confirmed_spend_requests_should_be_recorded_as_confirmed() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![cash_note])?;
+        for _ in 0..2 {
+            let to = vec![(NanoTokens::from(100), MainSecretKey::random().main_pubkey())];
+            let _ = sender.local_send(to, None)?;
+            sender.store_unconfirmed_spend_requests()?;
+        }
+        let spends: Vec<_> = sender
+            .unconfirmed_spend_requests()
+            .iter()
+            .cloned()
+            .collect();
+
+        sender.confirm_spend_request(&spends[0])?;
+        assert!(sender.has_confirmed_spend(spends[0].address()));
+        assert!(!sender.has_confirmed_spend(spends[1].address()));
+        let reloaded = HotWallet::load_from(&root_dir)?;
+        assert_eq!(
+            reloaded.unconfirmed_spend_requests(),
+            &BTreeSet::from([spends[1].clone()])
+        );
+
+        sender.confirm_spend_request(&spends[1])?;
+        assert!(sender.has_confirmed_spend(spends[1].address()));
+        assert!(!HotWallet::load_from(&root_dir)?.unconfirmed_spend_requests_exist());
+        Ok(())
+    }
+
+    #[test]
+    fn double_spent_requests_should_be_dropped_with_their_cash_note() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![cash_note.clone()])?;
+        let to = vec![(NanoTokens::from(100), MainSecretKey::random().main_pubkey())];
+        let _ = sender.local_send(to, None)?;
+        sender.store_unconfirmed_spend_requests()?;
+        // the spent cash note as if it was still available, e.g. after a restore
+        sender.deposit_and_store_to_disk(&vec![cash_note.clone()])?;
+        assert!(HotWallet::load_from(&root_dir)?
+            .watchonly_wallet
+            .available_cash_notes()
+            .contains_key(&cash_note.unique_pubkey()));
+
+        sender.drop_double_spent_request(cash_note.unique_pubkey())?;
+        assert!(!sender.unconfirmed_spend_requests_exist());
+        let reloaded = HotWallet::load_from(&root_dir)?;
+        assert!(!reloaded.unconfirmed_spend_requests_exist());
+        assert!(!reloaded
+            .watchonly_wallet
+            .available_cash_notes()
+            .contains_key(&cash_note.unique_pubkey()));
+        Ok(())
+    }
+
+    #[test]
+    fn sends_should_only_read_the_cash_notes_they_spend() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let receiver_dir = create_temp_dir();
+        let receiver_root_dir = receiver_dir.path().to_path_buf();
+        let mut receiver =
+            HotWallet::create_from_key(&receiver_root_dir, MainSecretKey::random(), None)?;
+        let received = sender.local_send(
+            [100, 10]
+                .iter()
+                .map(|amount| (NanoTokens::from(*amount), receiver.address()))
+                .collect(),
+            None,
+        )?;
+        receiver.deposit_and_store_to_disk(&received)?;
+
+        let (values, exclusive_access) = receiver.available_cash_note_values()?;
+        assert_eq!(
+            values.values().copied().collect::<BTreeSet<_>>(),
+            BTreeSet::from([NanoTokens::from(10), NanoTokens::from(100)])
+        );
+        drop(exclusive_access);
+
+        // the file of the smallest cash note is broken, but the send doesn't need it
+        let smallest = received
+            .iter()
+            .find(|cash_note| cash_note.value() == NanoTokens::from(10))
+            .expect("the cash note of 10 to be received");
+        let smallest_file = receiver
+            .watchonly_wallet
+            .wallet_dir()
+            .join("cash_notes")
+            .join(format!(
+                "{}.cash_note",
+                SpendAddress::from_unique_pubkey(&smallest.unique_pubkey()).to_hex()
+            ));
+        std::fs::write(smallest_file, "not a cash note")?;
+        assert!(matches!(
+            receiver.available_cash_notes(),
+            Err(Error::InvalidCashNoteFile { .. })
+        ));
+        let _created = receiver.local_send(
+            vec![(NanoTokens::from(50), MainSecretKey::random().main_pubkey())],
+            None,
+        )?;
+        assert_eq!(receiver.balance(), NanoTokens::from(60));
+        Ok(())
+    }
+
+    #[test]
+    fn send_failures_should_tell_their_cause() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+        let recipient = MainSecretKey::random().main_pubkey();
+
+        let too_much = NanoTokens::from(GENESIS_CASHNOTE_AMOUNT + 10);
+        match sender.local_send(vec![(too_much, recipient)], None) {
+            Err(Error::Transfer(error)) => {
+                assert_eq!(error.missing_amount(), Some(NanoTokens::from(10)))
+            }
+            other => panic!("Expected the send to lack balance, got {other:?}"),
+        }
+
+        let cash_note_file =
+            std::fs::read_dir(sender.watchonly_wallet.wallet_dir().join("cash_notes"))?
+                .next()
+                .expect("the deposited cash note to be stored")?
+                .path();
+        std::fs::write(&cash_note_file, "not a cash note")?;
+        match sender.local_send(vec![(NanoTokens::from(10), recipient)], None) {
+            Err(Error::InvalidCashNoteFile { path, source }) => {
+                assert_eq!(path, cash_note_file);
+                assert!(matches!(
+                    *source,
+                    crate::TransferError::HexDeserializationFailed(_)
+                ));
+            }
+            other => panic!("Expected the cash note file to be invalid, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sending_idempotently_pays_once_per_id() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let send_amount = NanoTokens::from(100);
+        let recipient_main_pubkey = MainSecretKey::random().main_pubkey();
+        let created_cash_notes =
+            sender.send_idempotent("payment-1", send_amount, recipient_main_pubkey)?;
+        let retried_cash_notes =
+            sender.send_idempotent("payment-1", send_amount, recipient_main_pubkey)?;
+
+        assert_eq!(created_cash_notes, retried_cash_notes);
+        assert_eq!(
+            GENESIS_CASHNOTE_AMOUNT - send_amount.as_nano(),
+            sender.balance().as_nano()
+        );
+
+        // the id cannot be reused for another transfer
+        assert!(matches!(
+            sender.send_idempotent("payment-1", NanoTokens::from(200), recipient_main_pubkey),
+            Err(Error::IdempotentSendMismatch(_))
+        ));
+
+        // while another id makes another transfer
+        let _ = sender.send_idempotent("payment-2", send_amount, recipient_main_pubkey)?;
+        assert_eq!(
+            GENESIS_CASHNOTE_AMOUNT - 2 * send_amount.as_nano(),
+            sender.balance().as_nano()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sending_idempotently_applies_a_recorded_but_unapplied_send() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        // record a send as if the process crashed before applying it
+        let send_amount = NanoTokens::from(100);
+        let recipient_main_pubkey = MainSecretKey::random().main_pubkey();
+        let (available_cash_notes, exclusive_access) = sender.available_cash_notes()?;
+        let signed_tx = SignedTransaction::new(
+            available_cash_notes,
+            vec![(
+                send_amount,
+                recipient_main_pubkey,
+                DerivationIndex::random(&mut rng::thread_rng()),
+                false,
+            )],
+            sender.address(),
+            SpendReason::default(),
+            &sender.key,
+        )?;
+        drop(exclusive_access);
+        let send = IdempotentSend {
+            amount: send_amount,
+            to: recipient_main_pubkey,
+            signed_tx,
+        };
+        store_idempotent_send(sender.watchonly_wallet.wallet_dir(), "payment-1", &send)?;
+        assert_eq!(GENESIS_CASHNOTE_AMOUNT, sender.balance().as_nano());
+
+        let created_cash_notes =
+            sender.send_idempotent("payment-1", send_amount, recipient_main_pubkey)?;
+
+        assert_eq!(send.signed_tx.output_cashnotes, created_cash_notes);
+        assert_eq!(
+            GENESIS_CASHNOTE_AMOUNT - send_amount.as_nano(),
+            sender.balance().as_nano()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sending_idempotently_fails_when_the_recorded_send_was_superseded() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        // record a send as if the process crashed before applying it
+        let send_amount = NanoTokens::from(100);
+        let recipient_main_pubkey = MainSecretKey::random().main_pubkey();
+        let (available_cash_notes, exclusive_access) = sender.available_cash_notes()?;
+        let signed_tx = SignedTransaction::new(
+            available_cash_notes,
+            vec![(
+                send_amount,
+                recipient_main_pubkey,
+                DerivationIndex::random(&mut rng::thread_rng()),
+                false,
+            )],
+            sender.address(),
+            SpendReason::default(),
+            &sender.key,
+        )?;
+        drop(exclusive_access);
+        let send = IdempotentSend {
+            amount: send_amount,
+            to: recipient_main_pubkey,
+            signed_tx,
+        };
+        store_idempotent_send(sender.watchonly_wallet.wallet_dir(), "payment-1", &send)?;
+
+        // then the same cash notes spent by a plain send, after a restart
+        let _ = sender.local_send(vec![(send_amount, recipient_main_pubkey)], None)?;
+        let balance = sender.balance();
+
+        assert!(matches!(
+            sender.send_idempotent("payment-1", send_amount, recipient_main_pubkey),
+            Err(Error::IdempotentSendSuperseded(_))
+        ));
+        assert_eq!(balance, sender.balance());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_wallet_to_and_from_file() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+
+        let new_wallet = MainSecretKey::random();
+        let mut sender = HotWallet::create_from_key(&root_dir, new_wallet, None)?;
+
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        // We send to a new address.
+        let send_amount = 100;
+        let recipient_key = MainSecretKey::random();
+        let recipient_main_pubkey = recipient_key.main_pubkey();
+        let to = vec![(NanoTokens::from(send_amount), recipient_main_pubkey)];
+        let _created_cash_notes = sender.local_send(to, None)?;
+
+        let deserialized = HotWallet::load_from(&root_dir)?;
+
+        assert_eq!(sender.address(), deserialized.address());
+        assert_eq!(
+            GENESIS_CASHNOTE_AMOUNT - send_amount,
+            sender.balance().as_nano()
+        );
+        assert_eq!(
+            GENESIS_CASHNOTE_AMOUNT - send_amount,
+            deserialized.balance().as_nano()
+        );
+
+        assert_eq!(1, sender.watchonly_wallet.available_cash_notes().len());
+
+        assert_eq!(
+            1,
+            deserialized.watchonly_wallet.available_cash_notes().len()
+        );
 
         let a_available = sender
             .watchonly_wallet
@@ -1209,6 +3335,306 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_storage_payments_are_confirmed_once_their_spends_are() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let mut rng = bls::rand::thread_rng();
+        let xor1 = XorName::random(&mut rng);
+        let xor2 = XorName::random(&mut rng);
+        let map = BTreeMap::from([
+            (
+                xor1,
+                (
+                    MainSecretKey::random().main_pubkey(),
+                    PaymentQuote::test_dummy(xor1, 100.into()),
+                    vec![],
+                ),
+            ),
+            (
+                xor2,
+                (
+                    MainSecretKey::random().main_pubkey(),
+                    PaymentQuote::test_dummy(xor2, 200.into()),
+                    vec![],
+                ),
+            ),
+        ]);
+
+        let _ = sender.local_send_storage_payment(&map)?;
+        assert!(!sender.is_payment_confirmed(&xor1));
+        assert!(!sender.is_payment_confirmed(&xor2));
+
+        sender.clear_confirmed_spend_requests();
+        assert!(sender.is_payment_confirmed(&xor1));
+        assert!(sender.is_payment_confirmed(&xor2));
+
+        // a removed payment is to be made again
+        sender.remove_payment_for_xorname(&xor1);
+        assert!(!sender.is_payment_confirmed(&xor1));
+        assert!(sender.is_payment_confirmed(&xor2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_send_storage_payment_to_external_reward_address_is_unencrypted(
+    ) -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let mut rng = bls::rand::thread_rng();
+        let own_wallet_xor = XorName::random(&mut rng);
+        let external_xor = XorName::random(&mut rng);
+        let own_wallet_key = MainSecretKey::random().main_pubkey();
+        let external_key = MainSecretKey::random().main_pubkey();
+        let mut external_quote = PaymentQuote::test_dummy(external_xor, 200.into());
+        external_quote.reward_address = Some(external_key);
+
+        let map = BTreeMap::from([
+            (
+                own_wallet_xor,
+                (
+                    own_wallet_key,
+                    PaymentQuote::test_dummy(own_wallet_xor, 100.into()),
+                    vec![],
+                ),
+            ),
+            (external_xor, (external_key, external_quote, vec![])),
+        ]);
+        let _ = sender.local_send_storage_payment(&map)?;
+
+        let payment = sender.api().get_recent_payment(&own_wallet_xor)?;
+        assert!(matches!(payment.transfer.0, crate::Transfer::Encrypted(_)));
+        let payment = sender.api().get_recent_payment(&external_xor)?;
+        let crate::Transfer::Unencrypted(redemptions) = &payment.transfer.0 else {
+            panic!("the payment to an external reward address should be unencrypted");
+        };
+        // and bound to the quote it pays
+        assert_eq!(
+            redemptions[0].derivation_index,
+            payment.quote.derivation_index()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_send_storage_payment_to_payees_pays_every_holder() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let xor = XorName::random(&mut bls::rand::thread_rng());
+        let payees = (1..=3)
+            .map(|i| {
+                (
+                    MainSecretKey::random().main_pubkey(),
+                    PaymentQuote::test_dummy(xor, (i * 100).into()),
+                    vec![i as u8],
+                )
+            })
+            .collect::<Vec<_>>();
+        let map = BTreeMap::from([(xor, payees.clone())]);
+
+        let (price, _) = sender.local_send_storage_payment_to_payees(&map)?;
+        assert_eq!(price.as_nano(), 600);
+
+        let payment = sender.api().get_recent_payment(&xor)?;
+        assert_eq!(payment.recipient, payees[0].0);
+        assert_eq!(
+            payment.all_peer_id_bytes().cloned().collect::<Vec<_>>(),
+            vec![vec![1], vec![2], vec![3]]
+        );
+
+        let payment = payment.to_payment();
+        assert_eq!(payment.transfers.len(), 6);
+        assert_eq!(
+            payment.quotes().cloned().collect::<Vec<_>>(),
+            payees
+                .into_iter()
+                .map(|(_, quote, _)| quote)
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn storage_payments_should_be_split_within_the_transaction_limits() -> Result<()> {
+        let dir = create_temp_dir();
+        let mut wallet = crate::test_utils::WalletBuilder::with_balance(NanoTokens::from(1_500))
+            .cash_notes(10)
+            .build(dir.path())?;
+
+        let mut rng = bls::rand::thread_rng();
+        let map: BTreeMap<_, _> = (0..4)
+            .map(|_| {
+                let xor = XorName::random(&mut rng);
+                let payee = (
+                    MainSecretKey::random().main_pubkey(),
+                    PaymentQuote::test_dummy(xor, 100.into()),
+                    vec![],
+                );
+                (xor, vec![payee])
+            })
+            .collect();
+
+        // two records per transaction take two cash notes of 150: one record each it is
+        wallet.set_transaction_limits(TransactionLimits {
+            max_inputs: 1,
+            max_outputs: 5,
+        });
+        let (storage_cost, royalties_fees) = wallet.local_send_storage_payment_to_payees(&map)?;
+        assert_eq!(storage_cost, NanoTokens::from(400));
+        assert_eq!(royalties_fees, NanoTokens::from(4 * 17));
+        assert_eq!(wallet.unconfirmed_spend_requests().len(), 4);
+        assert_eq!(wallet.balance(), NanoTokens::from(1_500 - 4 * 117));
+        for xor in map.keys() {
+            assert!(wallet.api().get_recent_payment(xor).is_ok());
+        }
+
+        // the payments of a record cannot be split
+        wallet.set_transaction_limits(TransactionLimits {
+            max_inputs: 1,
+            max_outputs: 2,
+        });
+        assert!(matches!(
+            wallet.local_send_storage_payment_to_payees(&map),
+            Err(Error::TooManyOutputs { needed: 3, max: 2 })
+        ));
+        Ok(())
+    }
+
+    // --------------------------------
+    // <-------> Hooks <--------->
+    // --------------------------------
+
+    /// Records the amounts deposited, and the number of payments of each spend.
+    #[derive(Default)]
+    struct RecordingHooks {
+        deposits: Mutex<Vec<NanoTokens>>,
+        spends: Mutex<Vec<usize>>,
+        fail: bool,
+    }
+
+    impl WalletHooks for RecordingHooks {
+        fn on_deposit(&self, cash_notes: &[CashNote]) -> crate::wallet::Result<()> {
+            if self.fail {
+                return Err(Error::HookFailed("ledger unavailable".to_string()));
+            }
+            let mut deposits = self.deposits.lock().expect("not poisoned");
+            deposits.extend(cash_notes.iter().map(|cash_note| cash_note.value()));
+            Ok(())
+        }
+
+        fn on_spend(
+            &self,
+            _signed_tx: &SignedTransaction,
+            storage_payments: &BTreeMap<XorName, PaymentDetails>,
+        ) -> crate::wallet::Result<()> {
+            if self.fail {
+                return Err(Error::HookFailed("ledger unavailable".to_string()));
+            }
+            let mut spends = self.spends.lock().expect("not poisoned");
+            spends.push(storage_payments.len());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hooks_should_be_called_on_deposits_and_spends() -> Result<()> {
+        let dir = create_temp_dir();
+        let mut wallet = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let hooks = Arc::new(RecordingHooks::default());
+        wallet.set_hooks(Arc::<RecordingHooks>::clone(&hooks));
+
+        let cash_note =
+            create_first_cash_note_from_key(&wallet.key).expect("Genesis creation to succeed.");
+        wallet.deposit_and_store_to_disk(&vec![cash_note.clone()])?;
+        // depositing it again does not deposit anything
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+        assert_eq!(
+            *hooks.deposits.lock().expect("not poisoned"),
+            vec![NanoTokens::from(GENESIS_CASHNOTE_AMOUNT)]
+        );
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _ = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        let xorname = XorName::random(&mut rand::thread_rng());
+        let map = BTreeMap::from([(
+            xorname,
+            (
+                recipient,
+                PaymentQuote::test_dummy(xorname, 10.into()),
+                vec![],
+            ),
+        )]);
+        let _ = wallet.local_send_storage_payment(&map)?;
+        assert_eq!(*hooks.spends.lock().expect("not poisoned"), vec![0, 1]);
+
+        // the change of the spends is not reported as a deposit
+        assert_eq!(hooks.deposits.lock().expect("not poisoned").len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn failing_hooks_should_leave_the_wallet_unchanged() -> Result<()> {
+        let failing_hooks = Arc::new(RecordingHooks {
+            fail: true,
+            ..Default::default()
+        });
+
+        let dir = create_temp_dir();
+        let mut wallet = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        wallet.set_hooks(Arc::<RecordingHooks>::clone(&failing_hooks));
+        let cash_note =
+            create_first_cash_note_from_key(&wallet.key).expect("Genesis creation to succeed.");
+        assert!(matches!(
+            wallet.deposit_and_store_to_disk(&vec![cash_note.clone()]),
+            Err(Error::HookFailed(_))
+        ));
+        assert!(matches!(
+            wallet.deposit(&vec![cash_note.clone()]),
+            Err(Error::HookFailed(_))
+        ));
+        assert_eq!(wallet.balance(), NanoTokens::zero());
+        assert_eq!(
+            HotWallet::load_from(dir.path())?.balance(),
+            NanoTokens::zero()
+        );
+
+        let dir = create_temp_dir();
+        let mut wallet = HotWallet::create_from_key(dir.path(), MainSecretKey::random(), None)?;
+        let cash_note =
+            create_first_cash_note_from_key(&wallet.key).expect("Genesis creation to succeed.");
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+        wallet.set_hooks(failing_hooks);
+        let recipient = MainSecretKey::random().main_pubkey();
+        assert!(matches!(
+            wallet.local_send(vec![(NanoTokens::from(100), recipient)], None),
+            Err(Error::HookFailed(_))
+        ));
+        let balance = NanoTokens::from(GENESIS_CASHNOTE_AMOUNT);
+        assert_eq!(wallet.balance(), balance);
+        assert_eq!(HotWallet::load_from(dir.path())?.balance(), balance);
+        Ok(())
+    }
+
     /// --------------------------------
     /// <-------> Encryption <--------->
     /// --------------------------------
@@ -1246,6 +3672,54 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_changing_and_removing_the_password_of_an_encrypted_wallet() -> Result<()> {
+        let password: &'static str = "safenetwork";
+        let new_password: &'static str = "safernetwork";
+
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let wallet_key = MainSecretKey::random();
+
+        let unencrypted_wallet = HotWallet::create_from_key(&root_dir, wallet_key, None)?;
+
+        // Neither can be done to an unencrypted wallet
+        assert!(matches!(
+            HotWallet::change_password(&root_dir, password, new_password),
+            Err(Error::WalletNotEncrypted)
+        ));
+        assert!(matches!(
+            HotWallet::decrypt(&root_dir, password),
+            Err(Error::WalletNotEncrypted)
+        ));
+
+        HotWallet::encrypt(&root_dir, password)?;
+
+        // The current password is required
+        assert!(matches!(
+            HotWallet::change_password(&root_dir, new_password, password),
+            Err(Error::WalletPasswordIncorrect)
+        ));
+        HotWallet::change_password(&root_dir, password, new_password)?;
+        assert!(HotWallet::load_encrypted_from_path(&root_dir, password.to_owned()).is_err());
+        let encrypted_wallet =
+            HotWallet::load_encrypted_from_path(&root_dir, new_password.to_owned())?;
+        assert_eq!(encrypted_wallet.address(), unencrypted_wallet.address());
+
+        assert!(matches!(
+            HotWallet::decrypt(&root_dir, password),
+            Err(Error::WalletPasswordIncorrect)
+        ));
+        HotWallet::decrypt(&root_dir, new_password)?;
+        assert!(!HotWallet::is_encrypted(&root_dir));
+        assert_eq!(
+            HotWallet::load_from(&root_dir)?.address(),
+            unencrypted_wallet.address()
+        );
+
+        Ok(())
+    }
+
     /// --------------------------------
     /// <-------> Other <--------->
     /// --------------------------------
@@ -1274,6 +3748,213 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_wallet_backup_export_and_import() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _ = sender.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        sender.store_unconfirmed_spend_requests()?;
+
+        let backup_path = dir.path().join("wallet.backup");
+        sender.export_backup(&backup_path, "passphrase")?;
+
+        assert!(matches!(
+            HotWallet::import_backup(&root_dir.join("other"), &backup_path, "wrong", None),
+            Err(Error::InvalidWalletBackup(_))
+        ));
+
+        // restored to a new wallet
+        let restored_dir = dir.path().join("restored");
+        let restored = HotWallet::import_backup(&restored_dir, &backup_path, "passphrase", None)?;
+        assert_eq!(restored.address(), sender.address());
+        assert_eq!(restored.balance(), sender.balance());
+        assert_eq!(
+            restored.unconfirmed_spend_requests(),
+            sender.unconfirmed_spend_requests()
+        );
+        let reloaded = HotWallet::load_from(&restored_dir)?;
+        assert_eq!(reloaded.balance(), sender.balance());
+        assert_eq!(
+            reloaded.unconfirmed_spend_requests(),
+            sender.unconfirmed_spend_requests()
+        );
+
+        // merged into the existing wallet, which has nothing more to restore
+        let merged = HotWallet::import_backup(&restored_dir, &backup_path, "passphrase", None)?;
+        assert_eq!(merged.balance(), sender.balance());
+
+        // the cash notes spent since the backup are not restored
+        let spender_dir = dir.path().join("spender");
+        let mut spender = HotWallet::create_from_key(&spender_dir, MainSecretKey::random(), None)?;
+        let spender_cash_note =
+            create_first_cash_note_from_key(&spender.key).expect("Genesis creation to succeed.");
+        spender.deposit_and_store_to_disk(&vec![spender_cash_note])?;
+        let earlier_backup_path = dir.path().join("earlier.backup");
+        spender.export_backup(&earlier_backup_path, "passphrase")?;
+        let _ = spender.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        spender.store_unconfirmed_spend_requests()?;
+        spender.clear_confirmed_spend_requests();
+        let merged =
+            HotWallet::import_backup(&spender_dir, &earlier_backup_path, "passphrase", None)?;
+        assert_eq!(merged.balance(), spender.balance());
+        assert!(!merged.unconfirmed_spend_requests_exist());
+
+        // but never into the wallet of another key
+        let other_dir = dir.path().join("other");
+        let other = HotWallet::create_from_key(&other_dir, MainSecretKey::random(), None)?;
+        assert!(matches!(
+            HotWallet::import_backup(&other_dir, &backup_path, "passphrase", None),
+            Err(Error::WalletBackupKeyMismatch(key)) if key == other.address()
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn in_memory_wallets_keep_their_state_in_their_store() -> Result<()> {
+        let store: Arc<dyn WalletStore> = Arc::new(MemoryWalletStore::default());
+        let secret_key = bls::SecretKey::random();
+        let mut sender =
+            HotWallet::with_store(MainSecretKey::new(secret_key.clone()), Arc::clone(&store))?;
+        assert!(sender.wo_wallet().dir_on_disk().is_none());
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = sender.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        assert!(sender.unconfirmed_spend_requests_exist());
+        sender.store_unconfirmed_spend_requests()?;
+
+        // another instance over the same store sees the send, pending confirmation
+        let reloaded =
+            HotWallet::with_store(MainSecretKey::new(secret_key.clone()), Arc::clone(&store))?;
+        assert_eq!(reloaded.balance(), sender.balance());
+        assert_eq!(
+            reloaded.unconfirmed_spend_requests(),
+            sender.unconfirmed_spend_requests()
+        );
+
+        let spent = sender
+            .unconfirmed_spend_requests()
+            .iter()
+            .map(|spend| spend.address())
+            .collect::<Vec<_>>();
+        sender.clear_confirmed_spend_requests();
+        assert!(spent
+            .iter()
+            .all(|address| store.has_confirmed_spend(*address)));
+        let reloaded = HotWallet::with_store(MainSecretKey::new(secret_key), Arc::clone(&store))?;
+        assert!(!reloaded.unconfirmed_spend_requests_exist());
+
+        // the lock of the store is held by one instance at a time
+        let exclusive_access = store.try_lock()?;
+        assert!(matches!(store.try_lock(), Err(Error::WalletLocked(_))));
+        drop(exclusive_access);
+        let _exclusive_access = store.try_lock()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn in_memory_wallets_should_keep_no_files_of_a_wallet_dir() -> Result<()> {
+        let store: Arc<dyn WalletStore> = Arc::new(MemoryWalletStore::default());
+        let secret_key = bls::SecretKey::random();
+        let mut sender =
+            HotWallet::with_store(MainSecretKey::new(secret_key.clone()), Arc::clone(&store))?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+        assert_eq!(sender.authenticate()?, None);
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        assert!(matches!(
+            sender.address_book(),
+            Err(Error::NotStoredOnDisk(_))
+        ));
+        let _ = sender.local_send_to(
+            vec![(NanoTokens::from(100), Recipient::Pubkey(recipient))],
+            None,
+        )?;
+        sender.store_unconfirmed_spend_requests()?;
+        let _ = sender.local_send(vec![(NanoTokens::from(200), recipient)], None)?;
+        sender.store_unconfirmed_spend_requests()?;
+        assert!(matches!(
+            sender.send_idempotent("payment-1", NanoTokens::from(100), recipient),
+            Err(Error::NotStoredOnDisk(_))
+        ));
+        assert_eq!(sender.prune_spent(Duration::ZERO)?, 0);
+        assert!(sender.spent_archive()?.is_empty());
+        assert!(sender.snapshot_view()?.history()?.entries().is_empty());
+
+        // the payments are kept in memory as well
+        let xorname = XorName::random(&mut rand::thread_rng());
+        let spends = BTreeSet::from([SpendAddress::from_unique_pubkey(
+            &MainSecretKey::random()
+                .main_pubkey()
+                .new_unique_pubkey(&DerivationIndex::random(&mut rand::thread_rng())),
+        )]);
+        sender.api().insert_payment_spends(xorname, &spends)?;
+        assert_eq!(sender.api().payment_spends(&xorname)?, spends);
+
+        // confirming a spend keeps the other ones unconfirmed
+        let unconfirmed = sender.unconfirmed_spend_requests().clone();
+        let confirmed = unconfirmed.iter().next().expect("a spend").clone();
+        store.confirm_unconfirmed_spend_requests(&BTreeSet::from([confirmed.clone()]))?;
+        let mut remaining = unconfirmed;
+        let _ = remaining.remove(&confirmed);
+        assert_eq!(store.load_unconfirmed_spend_requests()?, Some(remaining));
+
+        // backed up from the store
+        let dir = create_temp_dir();
+        let backup_path = dir.path().join("wallet.backup");
+        sender.export_backup(&backup_path, "passphrase")?;
+        let imported = HotWallet::import_backup(dir.path(), &backup_path, "passphrase", None)?;
+        assert_eq!(imported.balance(), sender.balance());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "async-io")]
+    #[tokio::test]
+    async fn async_variants_should_persist_like_the_blocking_ones() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut sender = HotWallet::create_from_key(&root_dir, MainSecretKey::random(), None)?;
+        let mut watch_only =
+            WatchOnlyWallet::load_from(&root_dir.join(WALLET_DIR_NAME), sender.address())?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender
+            .deposit_and_store_to_disk_async(vec![sender_cash_note])
+            .await?;
+        watch_only.reload_async().await?;
+        assert_eq!(watch_only.balance(), sender.balance());
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = sender.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        sender.store_unconfirmed_spend_requests_async().await?;
+
+        let reloaded = HotWallet::load_from_async(&root_dir).await?;
+        assert_eq!(reloaded.balance(), sender.balance());
+        assert_eq!(
+            reloaded.unconfirmed_spend_requests(),
+            sender.unconfirmed_spend_requests()
+        );
+
+        sender.clear_confirmed_spend_requests_async().await;
+        assert!(!sender.unconfirmed_spend_requests_exist());
+        let reloaded = HotWallet::load_from_async(&root_dir).await?;
+        assert!(!reloaded.unconfirmed_spend_requests_exist());
+
+        Ok(())
+    }
+
     fn create_temp_dir() -> TempDir {
         TempDir::new().expect("Should be able to create a temp dir.")
     }
@@ -17,7 +17,10 @@ use std::path::Path;
 /// Filename for storing the node's reward (BLS hex-encoded) main secret key.
 const MAIN_SECRET_KEY_FILENAME: &str = "main_secret_key";
 /// Filename for storing the node's reward (BLS hex-encoded) public key.
-const MAIN_PUBKEY_FILENAME: &str = "main_pubkey";
+pub(super) const MAIN_PUBKEY_FILENAME: &str = "main_pubkey";
+/// Prefixed to the messages signed with a wallet key, so that such a signature can never be
+/// mistaken for the signature of a spend or transaction.
+const SIGNED_MESSAGE_PREFIX: &[u8] = b"\x19SAFE Signed Message:\n";
 
 /// Writes the public address and main key (hex-encoded) to different locations at disk.
 pub(crate) fn store_new_keypair(
@@ -127,9 +130,30 @@ pub fn bls_secret_from_hex<T: AsRef<[u8]>>(hex: T) -> Result<bls::SecretKey> {
     Ok(sk)
 }
 
+/// The bytes actually signed for a `message`: the prefix, the length of the message in decimal,
+/// then the message itself.
+pub(crate) fn signed_message_bytes(message: &[u8]) -> Vec<u8> {
+    let mut bytes = SIGNED_MESSAGE_PREFIX.to_vec();
+    bytes.extend_from_slice(message.len().to_string().as_bytes());
+    bytes.extend_from_slice(message);
+    bytes
+}
+
+/// Verify a signature made with `HotWallet::sign_message`.
+pub fn verify_message(
+    main_pubkey: &MainPubkey,
+    message: &[u8],
+    signature: &bls::Signature,
+) -> bool {
+    main_pubkey.verify(signature, &signed_message_bytes(message))
+}
+
 #[cfg(test)]
 mod test {
-    use super::{get_main_key_from_disk, store_new_keypair, MainSecretKey};
+    use super::{
+        get_main_key_from_disk, signed_message_bytes, store_new_keypair, verify_message,
+        MainSecretKey,
+    };
     use assert_fs::TempDir;
     use eyre::Result;
 
@@ -144,6 +168,31 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn signed_messages_should_only_verify_with_the_signing_key() {
+        let main_key = MainSecretKey::random();
+        let message = b"I own this wallet";
+        let signature = main_key.sign(&signed_message_bytes(message));
+
+        assert!(verify_message(&main_key.main_pubkey(), message, &signature));
+        assert!(!verify_message(
+            &main_key.main_pubkey(),
+            b"I own that wallet",
+            &signature
+        ));
+        assert!(!verify_message(
+            &MainSecretKey::random().main_pubkey(),
+            message,
+            &signature
+        ));
+        // a signature of the raw message isn't valid
+        assert!(!verify_message(
+            &main_key.main_pubkey(),
+            message,
+            &main_key.sign(message)
+        ));
+    }
+
     fn create_temp_dir() -> TempDir {
         TempDir::new().expect("Should be able to create a temp dir.")
     }
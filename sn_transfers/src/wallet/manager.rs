@@ -0,0 +1,345 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The named wallets kept under one root dir, e.g. a "hot" and a "savings" wallet of a client.
+//!
+//! The default wallet is kept in the `wallet` dir of the root dir, as a single wallet always was,
+//! while each named wallet has a root dir of its own in that dir, i.e. `wallet/<name>/`, holding
+//! its own `wallet` dir along with the other files of the wallet owner. The named wallets of the
+//! former `wallets/<name>/` layout are moved there as they are used.
+
+use super::{
+    api::WALLET_DIR_NAME, keys::MAIN_PUBKEY_FILENAME, wallet_file, Error, HotWallet, Result,
+    WatchOnlyWallet,
+};
+use crate::MainSecretKey;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The subdir of the root dir the named wallets were kept in, each one in a root dir of its own.
+pub const NAMED_WALLETS_DIR_NAME: &str = "wallets";
+
+/// The name of the wallet kept directly in the root dir.
+pub const DEFAULT_WALLET_NAME: &str = "default";
+
+/// The entries a wallet keeps in its dir, which the named wallets kept along cannot be named
+/// after. The ones with a '.' are not valid names anyway.
+const RESERVED_WALLET_NAMES: &[&str] = &[
+    "address_book",
+    "cash_notes",
+    "confirmed_spends",
+    "derivation_indexes",
+    "history",
+    "idempotent_sends",
+    "main_pubkey",
+    "main_secret_key",
+    "payments",
+    "spend_approval",
+    "treasury",
+    "unconfirmed_spend_metadata",
+    "unconfirmed_spend_requests",
+    "wallet",
+    "wallet_version",
+];
+
+/// Creates, lists, opens and deletes the named wallets of a root dir.
+#[derive(Clone, Debug)]
+pub struct WalletManager {
+    root_dir: PathBuf,
+}
+
+impl WalletManager {
+    pub fn new(root_dir: &Path) -> Self {
+        Self {
+            root_dir: root_dir.to_path_buf(),
+        }
+    }
+
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    /// The root dir of the wallet with the given name, which its `wallet` dir is kept in.
+    ///
+    /// Only letters, digits, '-' and '_' are allowed in the names, for them to never escape the
+    /// root dir. A wallet of the former layout is moved to its root dir.
+    pub fn wallet_root_dir(&self, name: &str) -> Result<PathBuf> {
+        if name == DEFAULT_WALLET_NAME {
+            return Ok(self.root_dir.clone());
+        }
+        let is_valid = !name.is_empty()
+            && !RESERVED_WALLET_NAMES.contains(&name)
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_valid {
+            return Err(Error::InvalidWalletName(name.to_string()));
+        }
+        let root_dir = self.root_dir.join(WALLET_DIR_NAME).join(name);
+
+        let legacy_root_dir = self.root_dir.join(NAMED_WALLETS_DIR_NAME).join(name);
+        if legacy_root_dir.is_dir() && !root_dir.exists() {
+            info!("Moving wallet {name:?} from {legacy_root_dir:?} to {root_dir:?}");
+            fs::create_dir_all(self.root_dir.join(WALLET_DIR_NAME))?;
+            fs::rename(&legacy_root_dir, &root_dir)?;
+            // only removed once it holds no other wallet
+            let _ = fs::remove_dir(self.root_dir.join(NAMED_WALLETS_DIR_NAME));
+        }
+        Ok(root_dir)
+    }
+
+    /// Whether the wallet with the given name has been created.
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        Ok(holds_wallet(&self.wallet_root_dir(name)?))
+    }
+
+    /// The names of the wallets of the root dir, the default one first, then the named ones in
+    /// alphabetical order.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let legacy_dir = self.root_dir.join(NAMED_WALLETS_DIR_NAME);
+        if legacy_dir.is_dir() {
+            for entry in fs::read_dir(&legacy_dir)?.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Err(err) = self.wallet_root_dir(&name) {
+                    warn!("Could not move the wallet {name:?} of {legacy_dir:?}: {err:?}");
+                }
+            }
+        }
+
+        let mut names = vec![];
+        if self.exists(DEFAULT_WALLET_NAME)? {
+            names.push(DEFAULT_WALLET_NAME.to_string());
+        }
+        let mut named = self.named_wallets()?;
+        named.sort();
+        names.extend(named);
+        Ok(names)
+    }
+
+    /// Create the wallet with the given name for the key, encrypted with the password if any.
+    /// An existing wallet is never replaced.
+    pub fn create(
+        &self,
+        name: &str,
+        key: MainSecretKey,
+        password: Option<String>,
+    ) -> Result<HotWallet> {
+        if self.exists(name)? {
+            return Err(Error::WalletAlreadyExists(name.to_string()));
+        }
+        HotWallet::create_from_key(&self.wallet_root_dir(name)?, key, password)
+    }
+
+    /// Open the wallet with the given name, with its password if it is encrypted.
+    pub fn open(&self, name: &str, password: Option<String>) -> Result<HotWallet> {
+        let root_dir = self.existing_wallet_root_dir(name)?;
+        match password {
+            Some(password) => HotWallet::load_encrypted_from_path(&root_dir, password),
+            None => HotWallet::load_from(&root_dir),
+        }
+    }
+
+    /// Open the wallet with the given name as watch-only, which needs no password.
+    pub fn open_watch_only(&self, name: &str) -> Result<WatchOnlyWallet> {
+        let root_dir = self.existing_wallet_root_dir(name)?;
+        WatchOnlyWallet::load_from_path(&root_dir.join(WALLET_DIR_NAME))
+    }
+
+    /// Delete the wallet with the given name, i.e. its `wallet` dir, the other files of its root
+    /// dir being kept, as well as the named wallets kept along the default one.
+    ///
+    /// A wallet with a balance, or with spends pending, is never deleted: its funds, or the change
+    /// of its spends, would be lost.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let balance = self.open_watch_only(name)?.balance();
+        if !balance.is_zero() {
+            return Err(Error::WalletNotEmpty {
+                name: name.to_string(),
+                balance,
+            });
+        }
+        let wallet_dir = self.wallet_root_dir(name)?.join(WALLET_DIR_NAME);
+        let pending = wallet_file::get_unconfirmed_spend_requests(&wallet_dir)?.unwrap_or_default();
+        if !pending.is_empty() {
+            return Err(Error::WalletHasPendingSpends {
+                name: name.to_string(),
+                count: pending.len(),
+            });
+        }
+
+        if name == DEFAULT_WALLET_NAME {
+            let named = self.named_wallets()?;
+            for entry in fs::read_dir(&wallet_dir)?.flatten() {
+                let entry_name = entry.file_name().to_string_lossy().to_string();
+                if named.contains(&entry_name) {
+                    continue;
+                }
+                let path = entry.path();
+                if path.is_dir() {
+                    fs::remove_dir_all(path)?;
+                } else {
+                    fs::remove_file(path)?;
+                }
+            }
+        } else {
+            HotWallet::remove(&self.wallet_root_dir(name)?)?;
+        }
+        info!("Deleted wallet {name:?} of {:?}", self.root_dir);
+        Ok(())
+    }
+
+    /// The names of the named wallets, in no particular order.
+    fn named_wallets(&self) -> Result<Vec<String>> {
+        let wallet_dir = self.root_dir.join(WALLET_DIR_NAME);
+        if !wallet_dir.is_dir() {
+            return Ok(vec![]);
+        }
+        Ok(fs::read_dir(&wallet_dir)?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| {
+                !RESERVED_WALLET_NAMES.contains(&name.as_str())
+                    && holds_wallet(&wallet_dir.join(name))
+            })
+            .collect())
+    }
+
+    fn existing_wallet_root_dir(&self, name: &str) -> Result<PathBuf> {
+        if !self.exists(name)? {
+            return Err(Error::WalletNotFound(name.to_string()));
+        }
+        self.wallet_root_dir(name)
+    }
+}
+
+/// Whether a wallet was created in the root dir, its `wallet` dir holding its key.
+fn holds_wallet(root_dir: &Path) -> bool {
+    root_dir
+        .join(WALLET_DIR_NAME)
+        .join(MAIN_PUBKEY_FILENAME)
+        .is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genesis::create_first_cash_note_from_key;
+    use assert_fs::TempDir;
+    use eyre::Result;
+
+    #[test]
+    fn named_wallets_should_be_kept_apart() -> Result<()> {
+        let dir = TempDir::new()?;
+        let manager = WalletManager::new(dir.path());
+        assert!(manager.list()?.is_empty());
+        assert_eq!(manager.wallet_root_dir(DEFAULT_WALLET_NAME)?, dir.path());
+        assert!(matches!(
+            manager.wallet_root_dir("../escape"),
+            Err(Error::InvalidWalletName(_))
+        ));
+        assert!(matches!(
+            manager.wallet_root_dir(""),
+            Err(Error::InvalidWalletName(_))
+        ));
+        // nor can a named wallet take the place of a file of the default one
+        assert!(matches!(
+            manager.wallet_root_dir("cash_notes"),
+            Err(Error::InvalidWalletName(_))
+        ));
+        assert_eq!(
+            manager.wallet_root_dir("hot")?,
+            dir.path().join(WALLET_DIR_NAME).join("hot")
+        );
+
+        let hot = manager.create("hot", MainSecretKey::random(), None)?;
+        let savings = manager.create("savings", MainSecretKey::random(), Some("pwd".into()))?;
+        let default = manager.create(DEFAULT_WALLET_NAME, MainSecretKey::random(), None)?;
+        assert!(matches!(
+            manager.create("hot", MainSecretKey::random(), None),
+            Err(Error::WalletAlreadyExists(_))
+        ));
+        assert_eq!(manager.list()?, vec!["default", "hot", "savings"]);
+
+        assert_eq!(manager.open("hot", None)?.address(), hot.address());
+        assert_eq!(
+            manager.open("savings", Some("pwd".into()))?.address(),
+            savings.address()
+        );
+        assert_eq!(
+            manager.open_watch_only("savings")?.address(),
+            savings.address()
+        );
+        assert_eq!(
+            HotWallet::load_from(dir.path())?.address(),
+            default.address()
+        );
+        assert!(matches!(
+            manager.open("missing", None),
+            Err(Error::WalletNotFound(_))
+        ));
+
+        manager.delete("hot")?;
+        assert_eq!(manager.list()?, vec!["default", "savings"]);
+
+        // the named wallets kept along the default one outlive it
+        manager.delete(DEFAULT_WALLET_NAME)?;
+        assert_eq!(manager.list()?, vec!["savings"]);
+        assert_eq!(
+            manager.open("savings", Some("pwd".into()))?.address(),
+            savings.address()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wallets_of_the_former_layout_should_be_moved() -> Result<()> {
+        let dir = TempDir::new()?;
+        let manager = WalletManager::new(dir.path());
+        let legacy_root_dir = dir.path().join(NAMED_WALLETS_DIR_NAME).join("savings");
+        let wallet = HotWallet::create_from_key(&legacy_root_dir, MainSecretKey::random(), None)?;
+        std::fs::write(legacy_root_dir.join("uploaded_files"), b"kept along")?;
+
+        assert_eq!(manager.list()?, vec!["savings"]);
+        let root_dir = manager.wallet_root_dir("savings")?;
+        assert_eq!(root_dir, dir.path().join(WALLET_DIR_NAME).join("savings"));
+        assert_eq!(manager.open("savings", None)?.address(), wallet.address());
+        assert!(root_dir.join("uploaded_files").is_file());
+        assert!(!dir.path().join(NAMED_WALLETS_DIR_NAME).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn wallets_with_a_balance_should_not_be_deleted() -> Result<()> {
+        let dir = TempDir::new()?;
+        let manager = WalletManager::new(dir.path());
+        let key = MainSecretKey::random();
+        let cash_note = create_first_cash_note_from_key(&key)?;
+        let mut wallet = manager.create("savings", key, None)?;
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+
+        assert!(matches!(
+            manager.delete("savings"),
+            Err(Error::WalletNotEmpty { .. })
+        ));
+        assert_eq!(manager.list()?, vec!["savings"]);
+
+        // nor while the change of its spends is pending
+        let mut wallet = manager.open("savings", None)?;
+        let balance = wallet.balance();
+        let _ = wallet.local_send(vec![(balance, MainSecretKey::random().main_pubkey())], None)?;
+        assert!(wallet.balance().is_zero());
+        wallet.store_unconfirmed_spend_requests()?;
+        assert!(matches!(
+            manager.delete("savings"),
+            Err(Error::WalletHasPendingSpends { .. })
+        ));
+        Ok(())
+    }
+}
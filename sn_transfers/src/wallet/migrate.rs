@@ -0,0 +1,158 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The upgrade of the wallet files written by older versions.
+//!
+//! The version of the layout of the wallet file is kept aside, in the version file of the wallet
+//! dir, as the client store keeps its own: the wallet file holds the bare `KeyLessWallet` older
+//! readers expect. A dir without a version file is at version 0. Each older layout is kept here as
+//! it was, along with its conversion to the next one.
+//!
+//! A file is read at its version until the wallet is next stored, under its lock, at the current
+//! one: loading a wallet never writes to its dir.
+
+use super::{wallet_file::WalletFileFormat, Error, KeyLessWallet, Result};
+use crate::{NanoTokens, UniquePubkey};
+use serde::Deserialize;
+use std::{collections::BTreeMap, sync::Arc};
+
+/// The version of the layout of the wallet file, bumped on any incompatible change of
+/// `KeyLessWallet`, with the migration of the previous layout added here.
+pub const WALLET_FILE_VERSION: u32 = 1;
+
+/// The layout of version 0, from before the version file.
+#[derive(Deserialize)]
+struct KeyLessWalletV0 {
+    available_cash_notes: BTreeMap<UniquePubkey, NanoTokens>,
+}
+
+impl From<KeyLessWalletV0> for KeyLessWallet {
+    fn from(wallet: KeyLessWalletV0) -> Self {
+        Self {
+            available_cash_notes: Arc::new(wallet.available_cash_notes),
+        }
+    }
+}
+
+/// Decode the content of the wallet file in the given format, written at the given version of
+/// its layout, upgrading it to the current one.
+pub(super) fn decode_and_migrate(
+    content: &[u8],
+    format: WalletFileFormat,
+    version: u32,
+) -> Result<KeyLessWallet> {
+    let decoded = match version {
+        0 => format
+            .deserialize::<KeyLessWalletV0>(content)
+            .map(KeyLessWallet::from),
+        WALLET_FILE_VERSION => format.deserialize::<KeyLessWallet>(content),
+        _ => {
+            return Err(Error::InvalidWalletFile(format!(
+                "version {version} is not supported, the latest being {WALLET_FILE_VERSION}"
+            )))
+        }
+    };
+    match decoded {
+        Ok(wallet) => {
+            if version < WALLET_FILE_VERSION {
+                debug!("Read the wallet file at version {version}, to be stored at {WALLET_FILE_VERSION}");
+            }
+            Ok(wallet)
+        }
+        // the store of the wallet at the current version may have been interrupted before its
+        // version file was written
+        Err(err) if version < WALLET_FILE_VERSION => format
+            .deserialize::<KeyLessWallet>(content)
+            .map_err(|_| err),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        genesis::create_first_cash_note_from_key,
+        wallet::wallet_file::{
+            store_wallet, wallet_file_format, wallet_file_name, wallet_file_version,
+            WALLET_VERSION_FILE_NAME,
+        },
+        MainSecretKey,
+    };
+    use assert_fs::TempDir;
+    use eyre::Result;
+    use serde::Serialize;
+
+    /// The wallet as serialized at version 0, holding the given cash notes.
+    #[derive(Serialize)]
+    struct LegacyKeyLessWallet {
+        available_cash_notes: BTreeMap<UniquePubkey, NanoTokens>,
+    }
+
+    fn legacy_wallet() -> Result<LegacyKeyLessWallet> {
+        let cash_note = create_first_cash_note_from_key(&MainSecretKey::random())?;
+        Ok(LegacyKeyLessWallet {
+            available_cash_notes: BTreeMap::from([(cash_note.unique_pubkey(), cash_note.value())]),
+        })
+    }
+
+    #[test]
+    fn legacy_layouts_are_upgraded_in_every_format() -> Result<()> {
+        let legacy = legacy_wallet()?;
+        let contents = [
+            (WalletFileFormat::Legacy, rmp_serde::to_vec(&legacy)?),
+            (WalletFileFormat::MsgPack, rmp_serde::to_vec_named(&legacy)?),
+            (WalletFileFormat::Json, serde_json::to_vec(&legacy)?),
+        ];
+        for (format, content) in contents {
+            let wallet = decode_and_migrate(&content, format, 0)?;
+            assert_eq!(*wallet.available_cash_notes, legacy.available_cash_notes);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_wallet_files_are_upgraded_once_the_wallet_is_stored() -> Result<()> {
+        let legacy = legacy_wallet()?;
+        let wallet_dir = TempDir::new()?;
+        let file = rmp_serde::to_vec(&legacy)?;
+        std::fs::write(wallet_file_name(&wallet_dir), &file)?;
+
+        // loading leaves the dir as it is
+        let wallet = KeyLessWallet::load_from(&wallet_dir)?.expect("the wallet file to exist");
+        assert_eq!(*wallet.available_cash_notes, legacy.available_cash_notes);
+        assert_eq!(std::fs::read(wallet_file_name(&wallet_dir))?, file);
+        assert_eq!(wallet_file_version(&wallet_dir)?, 0);
+
+        store_wallet(&wallet_dir, &wallet)?;
+        assert_eq!(wallet_file_version(&wallet_dir)?, WALLET_FILE_VERSION);
+        assert_eq!(wallet_file_format(&wallet_dir)?, WalletFileFormat::Legacy);
+        // still readable by the versions from before the version file
+        let content = std::fs::read(wallet_file_name(&wallet_dir))?;
+        let wallet = decode_and_migrate(&content, WalletFileFormat::Legacy, 0)?;
+        assert_eq!(*wallet.available_cash_notes, legacy.available_cash_notes);
+        Ok(())
+    }
+
+    #[test]
+    fn wallet_files_of_newer_versions_are_rejected() -> Result<()> {
+        let wallet_dir = TempDir::new()?;
+        store_wallet(&wallet_dir, &KeyLessWallet::default())?;
+        assert!(KeyLessWallet::load_from(&wallet_dir)?.is_some());
+
+        std::fs::write(
+            wallet_dir.join(WALLET_VERSION_FILE_NAME),
+            (WALLET_FILE_VERSION + 1).to_string(),
+        )?;
+        assert!(matches!(
+            KeyLessWallet::load_from(&wallet_dir),
+            Err(Error::InvalidWalletFile(_))
+        ));
+        Ok(())
+    }
+}
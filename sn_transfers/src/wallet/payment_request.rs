@@ -0,0 +1,299 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A request for a payment, encoded as a single string a merchant can hand to a payer, e.g. as a
+//! QR code, for the wallet of the payer to make the transfer from.
+//!
+//! The invoices of the receipt service of `sn_client` are payment requests giving the index of
+//! the key to pay and the id of the invoice, for the merchant to verify the payment: all the
+//! payment URIs share one grammar.
+
+use super::{Error, Result};
+use crate::{DerivationIndex, Hash, MainPubkey, NanoTokens, SpendReason, UniquePubkey};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The scheme of the payment URIs.
+pub const PAYMENT_URI_SCHEME: &str = "safe-pay";
+
+/// A request for a payment to the pubkey, written as `safe-pay:<pubkey hex>[?index=<hex>&
+/// amount=<nanos>&id=<id>&reason=<hash hex>&expiry=<unix secs>]`, each parameter being optional.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentRequest {
+    pub to: MainPubkey,
+    /// The index the key paid is derived at, for the payee to tell the payment apart, else the
+    /// payer picks a random one
+    pub derivation_index: Option<DerivationIndex>,
+    /// The amount requested, else the payer picks it
+    pub amount: Option<NanoTokens>,
+    /// Identifies the request to the payee, e.g. the id of an invoice, made of letters, digits,
+    /// '-', '_' and '.'
+    pub id: Option<String>,
+    /// Identifies the payment to the payee, e.g. the hash of its order number
+    pub reason: Option<Hash>,
+    /// When the request stops being payable, with a precision of a second
+    pub expiry: Option<SystemTime>,
+}
+
+impl PaymentRequest {
+    /// A request for a payment of any amount to the pubkey.
+    pub fn new(to: MainPubkey) -> Self {
+        Self {
+            to,
+            derivation_index: None,
+            amount: None,
+            id: None,
+            reason: None,
+            expiry: None,
+        }
+    }
+
+    /// Have the payment made to the key derived at the index, which has to be handed out for
+    /// this request alone.
+    pub fn with_derivation_index(mut self, derivation_index: DerivationIndex) -> Self {
+        self.derivation_index = Some(derivation_index);
+        self
+    }
+
+    pub fn with_amount(mut self, amount: NanoTokens) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Identify the request, failing if the id has other characters than letters, digits, '-',
+    /// '_' and '.', for it to be written as is in the URI.
+    pub fn with_id(mut self, id: &str) -> Result<Self> {
+        if !is_valid_id(id) {
+            return Err(Error::InvalidPaymentRequest(id.to_string()));
+        }
+        self.id = Some(id.to_string());
+        Ok(self)
+    }
+
+    pub fn with_reason(mut self, reason: Hash) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+
+    /// Have the request expire after the duration. The expiry is rounded down to the second, as
+    /// it is encoded.
+    pub fn expiring_in(mut self, duration: Duration) -> Self {
+        let expiry = SystemTime::now() + duration;
+        let secs = expiry
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.expiry = Some(UNIX_EPOCH + Duration::from_secs(secs));
+        self
+    }
+
+    /// The key to pay, when the request gives its derivation index.
+    pub fn unique_pubkey(&self) -> Option<UniquePubkey> {
+        self.derivation_index
+            .map(|index| self.to.new_unique_pubkey(&index))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expiry
+            .is_some_and(|expiry| expiry <= SystemTime::now())
+    }
+
+    /// The amount to pay, which is the one requested, else the one given by the payer. Both
+    /// have to be the same when given.
+    pub fn amount_to_pay(&self, amount: Option<NanoTokens>) -> Result<NanoTokens> {
+        match (self.amount, amount) {
+            (Some(requested), Some(given)) if requested != given => {
+                Err(Error::PaymentRequestAmountMismatch { requested, given })
+            }
+            (Some(amount), _) | (None, Some(amount)) => Ok(amount),
+            (None, None) => Err(Error::PaymentRequestAmountMissing),
+        }
+    }
+
+    /// The reason of the spends paying the request, carrying the hash of the request in the
+    /// first bytes of a custom reason.
+    pub fn spend_reason(&self) -> Option<SpendReason> {
+        self.reason.map(|hash| {
+            let mut bytes = [0; 64];
+            bytes[..32].copy_from_slice(hash.slice());
+            SpendReason::Custom(bytes)
+        })
+    }
+}
+
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{PAYMENT_URI_SCHEME}:{}", self.to.to_hex())?;
+        let mut params = vec![];
+        if let Some(derivation_index) = self.derivation_index {
+            params.push(format!("index={}", hex::encode(derivation_index.0)));
+        }
+        if let Some(amount) = self.amount {
+            params.push(format!("amount={}", amount.as_nano()));
+        }
+        if let Some(id) = &self.id {
+            params.push(format!("id={id}"));
+        }
+        if let Some(reason) = self.reason {
+            params.push(format!("reason={}", reason.to_hex()));
+        }
+        if let Some(expiry) = self.expiry {
+            let secs = expiry
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            params.push(format!("expiry={secs}"));
+        }
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a payment URI as written by `Display`. Unknown parameters are refused, for a request
+/// not to be paid without what they would ask for.
+impl FromStr for PaymentRequest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidPaymentRequest(s.to_string());
+        let rest = s
+            .trim()
+            .strip_prefix(PAYMENT_URI_SCHEME)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .ok_or_else(invalid)?;
+        let (to, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut params = BTreeMap::new();
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_once('=').ok_or_else(invalid)?;
+            if params.insert(key, value).is_some() {
+                return Err(invalid());
+            }
+        }
+
+        let mut request = Self::new(MainPubkey::from_hex(to).map_err(|_| invalid())?);
+        for (key, value) in params {
+            match key {
+                "index" => {
+                    let index = hex::decode(value)
+                        .ok()
+                        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                        .ok_or_else(invalid)?;
+                    request.derivation_index = Some(DerivationIndex(index));
+                }
+                "id" if is_valid_id(value) => request.id = Some(value.to_string()),
+                "amount" => {
+                    let nanos = value.parse().map_err(|_| invalid())?;
+                    request.amount = Some(NanoTokens::from(nanos));
+                }
+                "reason" => request.reason = Some(Hash::from_hex(value).map_err(|_| invalid())?),
+                "expiry" => {
+                    let secs = value.parse().map_err(|_| invalid())?;
+                    request.expiry = Some(UNIX_EPOCH + Duration::from_secs(secs));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(request)
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainSecretKey;
+    use eyre::Result;
+
+    #[test]
+    fn payment_requests_should_round_trip_through_their_uri() -> Result<()> {
+        let to = MainSecretKey::random().main_pubkey();
+        let bare = PaymentRequest::new(to);
+        assert_eq!(bare.to_string(), format!("safe-pay:{}", to.to_hex()));
+        assert_eq!(bare.to_string().parse::<PaymentRequest>()?, bare);
+
+        let full = PaymentRequest::new(to)
+            .with_amount(NanoTokens::from(1500))
+            .with_reason(Hash::hash(b"order 42"))
+            .expiring_in(Duration::from_secs(3600));
+        assert_eq!(full.to_string().parse::<PaymentRequest>()?, full);
+        assert!(!full.is_expired());
+
+        assert!("safe-pay:not-a-key".parse::<PaymentRequest>().is_err());
+        assert!(format!("safe-pay:{}?amount=x", to.to_hex())
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("safe-pay:{}?tip=1", to.to_hex())
+            .parse::<PaymentRequest>()
+            .is_err());
+
+        // as written for an invoice, to be paid to the key it gives the index of
+        let index = DerivationIndex([7; 32]);
+        let invoice = PaymentRequest::new(to)
+            .with_derivation_index(index)
+            .with_amount(NanoTokens::from(1500))
+            .with_id("order-42")?;
+        let uri = invoice.to_string();
+        assert_eq!(
+            uri,
+            format!(
+                "safe-pay:{}?index={}&amount=1500&id=order-42",
+                to.to_hex(),
+                hex::encode(index.0)
+            )
+        );
+        let parsed = uri.parse::<PaymentRequest>()?;
+        assert_eq!(parsed, invoice);
+        assert_eq!(parsed.unique_pubkey(), Some(to.new_unique_pubkey(&index)));
+        assert!(format!("safe-pay:{}?index=00", to.to_hex())
+            .parse::<PaymentRequest>()
+            .is_err());
+        assert!(format!("safe-pay:{}?id=a%20b", to.to_hex())
+            .parse::<PaymentRequest>()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn the_amount_to_pay_should_be_the_requested_one() {
+        let to = MainSecretKey::random().main_pubkey();
+        let open = PaymentRequest::new(to);
+        let fixed = PaymentRequest::new(to).with_amount(NanoTokens::from(10));
+
+        assert!(matches!(
+            open.amount_to_pay(None),
+            Err(Error::PaymentRequestAmountMissing)
+        ));
+        assert_eq!(
+            open.amount_to_pay(Some(NanoTokens::from(3))).ok(),
+            Some(NanoTokens::from(3))
+        );
+        assert_eq!(fixed.amount_to_pay(None).ok(), Some(NanoTokens::from(10)));
+        assert!(matches!(
+            fixed.amount_to_pay(Some(NanoTokens::from(3))),
+            Err(Error::PaymentRequestAmountMismatch { .. })
+        ));
+
+        let expired = PaymentRequest {
+            expiry: Some(UNIX_EPOCH + Duration::from_secs(1)),
+            ..open
+        };
+        assert!(expired.is_expired());
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{history::History, Result};
+use crate::{MainPubkey, NanoTokens, SignedSpend, UniquePubkey};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+/// An immutable view of a wallet as it was when taken, for long reads such as audits, exports
+/// or the history to be consistent while the wallet goes on sending and receiving tokens.
+///
+/// Taking one is cheap: it shares the state of the wallet, which is only copied once the wallet
+/// changes it, and leaves out the history recorded after it when loading it.
+#[derive(Debug, Clone)]
+pub struct WalletSnapshot {
+    address: MainPubkey,
+    wallet_dir: Option<PathBuf>,
+    taken_at: SystemTime,
+    available_cash_notes: Arc<BTreeMap<UniquePubkey, NanoTokens>>,
+    unconfirmed_spend_requests: Arc<BTreeSet<SignedSpend>>,
+    history_log_len: u64,
+}
+
+impl WalletSnapshot {
+    pub(super) fn new(
+        address: MainPubkey,
+        wallet_dir: Option<&Path>,
+        available_cash_notes: Arc<BTreeMap<UniquePubkey, NanoTokens>>,
+    ) -> Result<Self> {
+        let history_log_len = match wallet_dir {
+            Some(wallet_dir) => History::log_len(wallet_dir)?,
+            None => 0,
+        };
+        Ok(Self {
+            address,
+            wallet_dir: wallet_dir.map(Path::to_path_buf),
+            taken_at: SystemTime::now(),
+            available_cash_notes,
+            unconfirmed_spend_requests: Default::default(),
+            history_log_len,
+        })
+    }
+
+    pub(super) fn with_unconfirmed_spend_requests(
+        mut self,
+        unconfirmed_spend_requests: Arc<BTreeSet<SignedSpend>>,
+    ) -> Self {
+        self.unconfirmed_spend_requests = unconfirmed_spend_requests;
+        self
+    }
+
+    pub fn address(&self) -> MainPubkey {
+        self.address
+    }
+
+    pub fn taken_at(&self) -> SystemTime {
+        self.taken_at
+    }
+
+    pub fn balance(&self) -> NanoTokens {
+        NanoTokens::from(
+            self.available_cash_notes
+                .values()
+                .map(|value| value.as_nano())
+                .sum::<u64>(),
+        )
+    }
+
+    pub fn available_cash_notes(&self) -> &BTreeMap<UniquePubkey, NanoTokens> {
+        &self.available_cash_notes
+    }
+
+    /// The spends not yet confirmed by the network, none for a watch-only wallet.
+    pub fn unconfirmed_spend_requests(&self) -> &BTreeSet<SignedSpend> {
+        &self.unconfirmed_spend_requests
+    }
+
+    /// The history of the wallet up to the snapshot, empty for a wallet not stored on disk.
+    pub fn history(&self) -> Result<History> {
+        match &self.wallet_dir {
+            Some(wallet_dir) => History::load_up_to(wallet_dir, self.history_log_len),
+            None => Ok(History::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_utils::WalletBuilder, HotWallet, MainSecretKey, NanoTokens};
+    use eyre::Result;
+
+    #[test]
+    fn a_snapshot_should_be_left_as_is_by_later_sends() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let snapshot = wallet.snapshot_view()?;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        assert_eq!(wallet.balance(), NanoTokens::from(900));
+        assert!(!wallet.unconfirmed_spend_requests().is_empty());
+        assert_eq!(wallet.history()?.entries().len(), 2);
+
+        assert_eq!(snapshot.balance(), NanoTokens::from(1_000));
+        assert_eq!(snapshot.available_cash_notes().len(), 1);
+        assert!(snapshot.unconfirmed_spend_requests().is_empty());
+        assert_eq!(snapshot.history()?.entries().len(), 1);
+
+        let later = wallet.snapshot_view()?;
+        assert_eq!(later.balance(), NanoTokens::from(900));
+        assert_eq!(
+            later.unconfirmed_spend_requests(),
+            wallet.unconfirmed_spend_requests()
+        );
+        assert_eq!(later.history()?.entries().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn a_snapshot_should_hold_the_changes_of_other_processes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let mut other = HotWallet::load_from(dir.path())?;
+
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        wallet.store_unconfirmed_spend_requests()?;
+
+        let snapshot = other.snapshot_view()?;
+        assert_eq!(snapshot.balance(), NanoTokens::from(900));
+        assert_eq!(
+            snapshot.unconfirmed_spend_requests(),
+            wallet.unconfirmed_spend_requests()
+        );
+        assert_eq!(snapshot.history()?.entries().len(), 2);
+        Ok(())
+    }
+}
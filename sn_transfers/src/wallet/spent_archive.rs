@@ -0,0 +1,255 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The archive of the spent cash notes pruned from a wallet, for the `cash_notes` dir and its
+//! index not to grow with every cash note the wallet ever held, while the cash notes of its
+//! history can still be looked up.
+//!
+//! It is a file of gzip members, one appended by each pruning, each holding the cash notes it
+//! archived as MsgPack and preceded by its length, for a corrupt member to be skipped and for the
+//! end of the last whole member to be found without decompressing any.
+
+use super::Result;
+use crate::{CashNote, NanoTokens, UniquePubkey};
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+const SPENT_ARCHIVE_FILE_NAME: &str = "spent_cash_notes.archive";
+const MEMBER_LEN_SIZE: usize = 4;
+
+/// A spent cash note moved out of the `cash_notes` dir.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedCashNote {
+    pub cash_note: CashNote,
+    /// When it was spent, in seconds since the Unix epoch, or 0 if it was spent before the
+    /// wallet recorded when
+    pub spent_at: u64,
+}
+
+impl ArchivedCashNote {
+    pub fn unique_pubkey(&self) -> UniquePubkey {
+        self.cash_note.unique_pubkey()
+    }
+
+    pub fn value(&self) -> NanoTokens {
+        self.cash_note.value()
+    }
+}
+
+/// The spent cash notes archived for a wallet.
+#[derive(Debug, Clone, Default)]
+pub struct SpentArchive {
+    cash_notes: BTreeMap<UniquePubkey, ArchivedCashNote>,
+}
+
+impl SpentArchive {
+    /// Load the archive of the wallet dir, empty if nothing has been archived yet.
+    pub fn load(wallet_dir: &Path) -> Result<Self> {
+        let mut archive = Self::default();
+        let path = wallet_dir.join(SPENT_ARCHIVE_FILE_NAME);
+        if !path.exists() {
+            return Ok(archive);
+        }
+
+        let bytes = fs::read(&path)?;
+        let mut pos = 0;
+        while let Some(len_bytes) = bytes.get(pos..pos + MEMBER_LEN_SIZE) {
+            let mut len = [0; MEMBER_LEN_SIZE];
+            len.copy_from_slice(len_bytes);
+            let start = pos + MEMBER_LEN_SIZE;
+            let Some(member) = bytes.get(start..start + u32::from_le_bytes(len) as usize) else {
+                break;
+            };
+            pos = start + member.len();
+
+            match decode_member(member) {
+                Ok(cash_notes) => {
+                    for archived in cash_notes {
+                        let _ = archive
+                            .cash_notes
+                            .insert(archived.unique_pubkey(), archived);
+                    }
+                }
+                Err(err) => {
+                    warn!("Skipping a corrupt member of the spent cash note archive: {err}")
+                }
+            }
+        }
+        Ok(archive)
+    }
+
+    pub fn get(&self, unique_pubkey: &UniquePubkey) -> Option<&ArchivedCashNote> {
+        self.cash_notes.get(unique_pubkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cash_notes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cash_notes.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ArchivedCashNote> {
+        self.cash_notes.values()
+    }
+
+    /// Append the cash notes to the archive of the wallet dir.
+    ///
+    /// The caller must hold the wallet lock, so that concurrent writers don't interleave members.
+    pub(super) fn append(wallet_dir: &Path, cash_notes: &[ArchivedCashNote]) -> Result<()> {
+        if cash_notes.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&rmp_serde::to_vec(cash_notes)?)?;
+        let member = encoder.finish()?;
+
+        // drop what is left of a member whose write was interrupted
+        let path = wallet_dir.join(SPENT_ARCHIVE_FILE_NAME);
+        let valid_len = Self::valid_len(&path)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() > valid_len {
+            warn!("Dropping the interrupted last member of the spent cash note archive");
+            file.set_len(valid_len)?;
+        }
+        let mut bytes = Vec::with_capacity(MEMBER_LEN_SIZE + member.len());
+        bytes.extend((member.len() as u32).to_le_bytes());
+        bytes.extend(member);
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// The length of the archive file filled by whole members, which is short of the file
+    /// length if the write of the last member was interrupted, found from their lengths alone.
+    fn valid_len(path: &Path) -> Result<u64> {
+        let mut file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+        let file_len = file.metadata()?.len();
+        let mut pos = 0;
+        let mut len = [0; MEMBER_LEN_SIZE];
+        while pos + MEMBER_LEN_SIZE as u64 <= file_len {
+            file.read_exact(&mut len)?;
+            let end = pos + MEMBER_LEN_SIZE as u64 + u64::from(u32::from_le_bytes(len));
+            if end > file_len {
+                break;
+            }
+            pos = file.seek(SeekFrom::Start(end))?;
+        }
+        Ok(pos)
+    }
+}
+
+fn decode_member(member: &[u8]) -> Result<Vec<ArchivedCashNote>> {
+    let mut decoded = vec![];
+    let _ = GzDecoder::new(member).read_to_end(&mut decoded)?;
+    Ok(rmp_serde::from_slice(&decoded)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::WalletBuilder, CashNoteIndex, MainSecretKey};
+    use eyre::Result;
+    use std::time::Duration;
+
+    #[test]
+    fn spent_cash_notes_should_be_pruned_into_the_archive() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let wallet_dir = wallet.api().wallet_dir().to_path_buf();
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _created = wallet.local_send(vec![(NanoTokens::from(100), recipient)], None)?;
+        wallet.store_unconfirmed_spend_requests()?;
+        let spent = wallet.history()?.entries()[1].cash_notes.clone();
+
+        // the cash notes of unconfirmed spends are kept
+        assert_eq!(wallet.prune_spent(Duration::ZERO)?, 0);
+        wallet.unconfirmed_spend_requests_mut().clear();
+        wallet.store_unconfirmed_spend_requests()?;
+        assert_eq!(wallet.prune_spent(Duration::from_secs(3600))?, 0);
+        assert_eq!(wallet.prune_spent(Duration::ZERO)?, spent.len());
+        assert_eq!(wallet.prune_spent(Duration::ZERO)?, 0);
+
+        let index = CashNoteIndex::load(&wallet_dir)?;
+        assert!(spent
+            .iter()
+            .all(|unique_pubkey| index.get(unique_pubkey).is_none()));
+        assert_eq!(index.balance(), NanoTokens::from(900));
+        assert_eq!(wallet.balance(), NanoTokens::from(900));
+
+        let archive = wallet.spent_archive()?;
+        assert_eq!(archive.len(), spent.len());
+        let archived = archive
+            .get(&spent[0])
+            .expect("the spent cash note to be archived");
+        assert_eq!(archived.value(), NanoTokens::from(1_000));
+        assert!(archived.spent_at > 0);
+
+        // a split records no history, its input is as old as the time it was found spent
+        let _split = wallet.split(vec![NanoTokens::from(300)])?;
+        wallet.unconfirmed_spend_requests_mut().clear();
+        wallet.store_unconfirmed_spend_requests()?;
+        assert_eq!(wallet.prune_spent(Duration::from_secs(3600))?, 0);
+        assert_eq!(wallet.prune_spent(Duration::ZERO)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn the_archive_should_survive_interrupted_and_corrupt_members() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let wallet_dir = wallet.api().wallet_dir().to_path_buf();
+        let path = wallet_dir.join(SPENT_ARCHIVE_FILE_NAME);
+        let archived = |wallet: &mut crate::HotWallet| -> Result<Vec<ArchivedCashNote>> {
+            let (cash_notes, _) = wallet.available_cash_notes()?;
+            Ok(cash_notes
+                .into_iter()
+                .map(|cash_note| ArchivedCashNote {
+                    cash_note,
+                    spent_at: 1,
+                })
+                .collect())
+        };
+        let first = archived(&mut wallet)?;
+        SpentArchive::append(&wallet_dir, &first)?;
+
+        // what is left of an interrupted write is dropped by the next one
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(&[100, 0, 0, 0, 0x1f, 0x8b, 0x08])?;
+        assert_eq!(SpentArchive::load(&wallet_dir)?.len(), first.len());
+        SpentArchive::append(&wallet_dir, &first)?;
+        assert_eq!(SpentArchive::valid_len(&path)?, fs::metadata(&path)?.len());
+
+        // a corrupt member is skipped, and kept by the next append
+        let mut bytes = fs::read(&path)?;
+        for byte in &mut bytes[MEMBER_LEN_SIZE..MEMBER_LEN_SIZE + 16] {
+            *byte = !*byte;
+        }
+        fs::write(&path, &bytes)?;
+        let other_dir = tempfile::tempdir()?;
+        let mut other =
+            WalletBuilder::with_balance(NanoTokens::from(500)).build(other_dir.path())?;
+        let second = archived(&mut other)?;
+        SpentArchive::append(&wallet_dir, &second)?;
+        assert!(fs::metadata(&path)?.len() > bytes.len() as u64);
+        let archive = SpentArchive::load(&wallet_dir)?;
+        assert_eq!(archive.len(), first.len() + second.len());
+        Ok(())
+    }
+}
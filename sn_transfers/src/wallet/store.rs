@@ -0,0 +1,350 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The storage of the state of a wallet, in the files of its dir or in memory only, e.g. for tests,
+//! WASM clients or ephemeral tools which are not to touch the disk.
+
+use super::{
+    derivation_indexes::DerivationIndexRegistry,
+    error::{Error, Result},
+    wallet_file::{
+        get_confirmed_spend, get_unconfirmed_spend_requests, has_confirmed_spend,
+        load_cash_notes_from_disk, load_indexed_cash_notes, remove_cash_notes,
+        remove_unconfirmed_spend_requests, store_created_cash_notes,
+        store_unconfirmed_spend_requests, store_wallet, wallet_lockfile_name,
+    },
+    KeyLessWallet,
+};
+use crate::{CashNote, SignedSpend, SpendAddress, UniquePubkey};
+#[cfg(not(target_arch = "wasm32"))]
+use fs2::FileExt;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+};
+
+/// The exclusive access to the state of a wallet, held until dropped.
+///
+/// This used to be an alias of the `std::fs::File` of the lock of the wallet dir. It is opaque
+/// since wallets can be stored elsewhere than in a dir, e.g. in memory: code holding one as a
+/// `File` only has to hold it as is instead.
+pub struct WalletExclusiveAccess {
+    _guard: Box<dyn Send>,
+}
+
+impl WalletExclusiveAccess {
+    /// Wrap the guard of the lock of a store, releasing the lock once dropped.
+    pub fn new<G: Send + 'static>(guard: G) -> Self {
+        Self {
+            _guard: Box::new(guard),
+        }
+    }
+}
+
+/// The storage of the state of a wallet: the `KeyLessWallet`, the files of its cash notes, the
+/// spends not yet confirmed by the network and the derivation indexes handed out.
+///
+/// The rest of what a wallet keeps, e.g. its history, the index of its cash notes or its address
+/// book, lives in the files of its dir, which only a store on disk has.
+pub trait WalletStore: Send + Sync {
+    /// The dir the state is stored in, none when it is not stored on disk.
+    fn wallet_dir(&self) -> Option<&Path>;
+
+    /// Wait for the exclusive access to the state, for other instances of the wallet not to
+    /// change it meanwhile.
+    fn lock(&self) -> Result<WalletExclusiveAccess>;
+
+    /// Get the exclusive access to the state like `lock`, failing with `Error::WalletLocked`
+    /// instead of waiting when another instance holds it.
+    fn try_lock(&self) -> Result<WalletExclusiveAccess>;
+
+    /// The wallet last stored, none if it has never been.
+    fn load_wallet(&self) -> Result<Option<KeyLessWallet>>;
+
+    fn store_wallet(&self, wallet: &KeyLessWallet) -> Result<()>;
+
+    /// Keep the cash notes, e.g. the ones deposited or the change of a transfer.
+    fn store_cash_notes(&self, cash_notes: &[&CashNote]) -> Result<()>;
+
+    /// The cash notes kept of the given unique pubkeys, leaving out the ones not kept.
+    fn load_cash_notes(&self, unique_pubkeys: &[UniquePubkey]) -> Result<Vec<CashNote>>;
+
+    /// The cash notes added to the store by others than the wallet, e.g. dropped in its dir, to
+    /// be deposited.
+    fn load_deposited_cash_notes(&self) -> Result<Vec<CashNote>>;
+
+    fn remove_cash_notes(&self, unique_pubkeys: &[UniquePubkey]) -> Result<()>;
+
+    /// The spends not yet confirmed by the network, none if they have never been stored.
+    fn load_unconfirmed_spend_requests(&self) -> Result<Option<BTreeSet<SignedSpend>>>;
+
+    fn store_unconfirmed_spend_requests(&self, spends: &BTreeSet<SignedSpend>) -> Result<()>;
+
+    /// Keep the given spends as confirmed, dropping them from the unconfirmed ones.
+    fn confirm_unconfirmed_spend_requests(&self, confirmed: &BTreeSet<SignedSpend>) -> Result<()>;
+
+    fn get_confirmed_spend(&self, spend_addr: SpendAddress) -> Result<Option<SignedSpend>>;
+
+    fn has_confirmed_spend(&self, spend_addr: SpendAddress) -> bool;
+
+    /// The registry of the derivation indexes handed out, which records them in the store.
+    fn derivation_indexes(&self) -> Result<DerivationIndexRegistry>;
+}
+
+/// The state of the wallet in the files of its dir, as wallets have always been stored.
+#[derive(Debug, Clone)]
+pub struct FileWalletStore {
+    wallet_dir: PathBuf,
+}
+
+impl FileWalletStore {
+    pub fn new(wallet_dir: &Path) -> Self {
+        Self {
+            wallet_dir: wallet_dir.to_path_buf(),
+        }
+    }
+
+    fn open_lockfile(&self) -> Result<std::fs::File> {
+        let lock = wallet_lockfile_name(&self.wallet_dir);
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(lock)?;
+        Ok(file)
+    }
+}
+
+impl WalletStore for FileWalletStore {
+    fn wallet_dir(&self) -> Option<&Path> {
+        Some(&self.wallet_dir)
+    }
+
+    // This lock prevents any other process from locking the wallet dir, effectively acts as a mutex for the wallet
+    fn lock(&self) -> Result<WalletExclusiveAccess> {
+        let file = self.open_lockfile()?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        file.lock_exclusive()?;
+        Ok(WalletExclusiveAccess::new(file))
+    }
+
+    fn try_lock(&self) -> Result<WalletExclusiveAccess> {
+        let file = self.open_lockfile()?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(err) = file.try_lock_exclusive() {
+            if err.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                return Err(Error::WalletLocked(self.wallet_dir.clone()));
+            }
+            return Err(err.into());
+        }
+        Ok(WalletExclusiveAccess::new(file))
+    }
+
+    fn load_wallet(&self) -> Result<Option<KeyLessWallet>> {
+        KeyLessWallet::load_from(&self.wallet_dir)
+    }
+
+    fn store_wallet(&self, wallet: &KeyLessWallet) -> Result<()> {
+        store_wallet(&self.wallet_dir, wallet)
+    }
+
+    fn store_cash_notes(&self, cash_notes: &[&CashNote]) -> Result<()> {
+        store_created_cash_notes(cash_notes.iter().copied(), &self.wallet_dir)
+    }
+
+    fn load_cash_notes(&self, unique_pubkeys: &[UniquePubkey]) -> Result<Vec<CashNote>> {
+        load_indexed_cash_notes(&self.wallet_dir, unique_pubkeys)
+    }
+
+    fn load_deposited_cash_notes(&self) -> Result<Vec<CashNote>> {
+        load_cash_notes_from_disk(&self.wallet_dir)
+    }
+
+    fn remove_cash_notes(&self, unique_pubkeys: &[UniquePubkey]) -> Result<()> {
+        remove_cash_notes(unique_pubkeys, &self.wallet_dir)
+    }
+
+    fn load_unconfirmed_spend_requests(&self) -> Result<Option<BTreeSet<SignedSpend>>> {
+        get_unconfirmed_spend_requests(&self.wallet_dir)
+    }
+
+    fn store_unconfirmed_spend_requests(&self, spends: &BTreeSet<SignedSpend>) -> Result<()> {
+        store_unconfirmed_spend_requests(&self.wallet_dir, spends)
+    }
+
+    fn confirm_unconfirmed_spend_requests(&self, confirmed: &BTreeSet<SignedSpend>) -> Result<()> {
+        remove_unconfirmed_spend_requests(&self.wallet_dir, confirmed)
+    }
+
+    fn get_confirmed_spend(&self, spend_addr: SpendAddress) -> Result<Option<SignedSpend>> {
+        get_confirmed_spend(&self.wallet_dir, spend_addr)
+    }
+
+    fn has_confirmed_spend(&self, spend_addr: SpendAddress) -> bool {
+        has_confirmed_spend(&self.wallet_dir, spend_addr)
+    }
+
+    fn derivation_indexes(&self) -> Result<DerivationIndexRegistry> {
+        DerivationIndexRegistry::load(&self.wallet_dir)
+    }
+}
+
+#[derive(Default)]
+struct MemoryContent {
+    wallet: Option<KeyLessWallet>,
+    cash_notes: BTreeMap<UniquePubkey, CashNote>,
+    unconfirmed_spend_requests: Option<BTreeSet<SignedSpend>>,
+    confirmed_spends: BTreeMap<SpendAddress, SignedSpend>,
+}
+
+/// The state of the wallet in memory only, gone with the store.
+///
+/// The store can be shared by several instances of the wallet within the process, its lock
+/// keeping them from changing the state at the same time.
+#[derive(Default)]
+pub struct MemoryWalletStore {
+    content: Mutex<MemoryContent>,
+    derivation_indexes: DerivationIndexRegistry,
+    locked: Arc<(Mutex<bool>, Condvar)>,
+}
+
+/// Releases the lock of a `MemoryWalletStore` once dropped.
+struct MemoryLockGuard(Arc<(Mutex<bool>, Condvar)>);
+
+impl Drop for MemoryLockGuard {
+    fn drop(&mut self) {
+        let (locked, released) = &*self.0;
+        if let Ok(mut locked) = locked.lock() {
+            *locked = false;
+        }
+        released.notify_one();
+    }
+}
+
+impl MemoryWalletStore {
+    fn content(&self) -> std::sync::MutexGuard<'_, MemoryContent> {
+        // the content is left consistent by each access, so a panic of another one can be ignored
+        self.content
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl WalletStore for MemoryWalletStore {
+    fn wallet_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    fn lock(&self) -> Result<WalletExclusiveAccess> {
+        let (locked, released) = &*self.locked;
+        let mut locked = locked
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while *locked {
+            locked = released
+                .wait(locked)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        *locked = true;
+        Ok(WalletExclusiveAccess::new(MemoryLockGuard(Arc::clone(
+            &self.locked,
+        ))))
+    }
+
+    fn try_lock(&self) -> Result<WalletExclusiveAccess> {
+        let (locked, _) = &*self.locked;
+        let mut locked = locked
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *locked {
+            return Err(Error::WalletLocked(PathBuf::new()));
+        }
+        *locked = true;
+        Ok(WalletExclusiveAccess::new(MemoryLockGuard(Arc::clone(
+            &self.locked,
+        ))))
+    }
+
+    fn load_wallet(&self) -> Result<Option<KeyLessWallet>> {
+        Ok(self.content().wallet.clone())
+    }
+
+    fn store_wallet(&self, wallet: &KeyLessWallet) -> Result<()> {
+        self.content().wallet = Some(wallet.clone());
+        Ok(())
+    }
+
+    fn store_cash_notes(&self, cash_notes: &[&CashNote]) -> Result<()> {
+        let mut content = self.content();
+        for cash_note in cash_notes {
+            let _ = content
+                .cash_notes
+                .insert(cash_note.unique_pubkey(), (*cash_note).clone());
+        }
+        Ok(())
+    }
+
+    fn load_cash_notes(&self, unique_pubkeys: &[UniquePubkey]) -> Result<Vec<CashNote>> {
+        let content = self.content();
+        Ok(unique_pubkeys
+            .iter()
+            .filter_map(|unique_pubkey| content.cash_notes.get(unique_pubkey).cloned())
+            .collect())
+    }
+
+    fn load_deposited_cash_notes(&self) -> Result<Vec<CashNote>> {
+        // only the wallet adds cash notes to the store
+        Ok(vec![])
+    }
+
+    fn remove_cash_notes(&self, unique_pubkeys: &[UniquePubkey]) -> Result<()> {
+        let mut content = self.content();
+        for unique_pubkey in unique_pubkeys {
+            let _ = content.cash_notes.remove(unique_pubkey);
+        }
+        Ok(())
+    }
+
+    fn load_unconfirmed_spend_requests(&self) -> Result<Option<BTreeSet<SignedSpend>>> {
+        Ok(self.content().unconfirmed_spend_requests.clone())
+    }
+
+    fn store_unconfirmed_spend_requests(&self, spends: &BTreeSet<SignedSpend>) -> Result<()> {
+        self.content().unconfirmed_spend_requests = Some(spends.clone());
+        Ok(())
+    }
+
+    fn confirm_unconfirmed_spend_requests(&self, confirmed: &BTreeSet<SignedSpend>) -> Result<()> {
+        let mut content = self.content();
+        for spend in confirmed {
+            let _ = content
+                .confirmed_spends
+                .insert(spend.address(), spend.clone());
+        }
+        if let Some(unconfirmed) = content.unconfirmed_spend_requests.as_mut() {
+            unconfirmed.retain(|spend| !confirmed.contains(spend));
+        }
+        Ok(())
+    }
+
+    fn get_confirmed_spend(&self, spend_addr: SpendAddress) -> Result<Option<SignedSpend>> {
+        Ok(self.content().confirmed_spends.get(&spend_addr).cloned())
+    }
+
+    fn has_confirmed_spend(&self, spend_addr: SpendAddress) -> bool {
+        self.content().confirmed_spends.contains_key(&spend_addr)
+    }
+
+    fn derivation_indexes(&self) -> Result<DerivationIndexRegistry> {
+        Ok(self.derivation_indexes.clone())
+    }
+}
@@ -0,0 +1,442 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Planned disbursements from the treasury wallet, e.g. the genesis or foundation wallet.
+//!
+//! A disbursement is planned as tranches, each an amount to a recipient not to be sent before a
+//! given time. It is only sent by [`Treasury::disburse_due`] once enough of its approvers have
+//! signed its plan, the signatures being verified again before every send, so the approvers
+//! approve exactly what will be sent. Each tranche is spent with the disbursement id in its
+//! reason, so that it can be told apart in the spend DAG.
+//!
+//! The approvals only bind this tool: whoever holds the treasury key can still send from the
+//! wallet directly. They are a safeguard against mistaken sends, not against the key holder.
+//!
+//! The disbursements are recorded in the wallet dir, together with the spends of their tranches.
+
+use super::{Error, HotWallet, Result};
+use crate::{CashNote, Hash, MainPubkey, NanoTokens, SpendAddress, SpendReason};
+use bls::{PublicKey, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+const TREASURY_FILE_NAME: &str = "treasury";
+
+/// A tranche of a disbursement, as approved.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranchePlan {
+    pub amount: NanoTokens,
+    pub recipient: MainPubkey,
+    /// Not to be sent before this time, in seconds since the Unix epoch
+    pub not_before: u64,
+}
+
+/// What the approvers of a disbursement sign.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisbursementPlan {
+    pub purpose: String,
+    /// The treasury wallet the tranches are sent from
+    pub from: MainPubkey,
+    pub tranches: Vec<TranchePlan>,
+    /// The keys that can approve the disbursement
+    pub approvers: BTreeSet<PublicKey>,
+    /// How many approvals the disbursement needs to be sent
+    pub threshold: usize,
+}
+
+impl DisbursementPlan {
+    /// The id of the disbursement, hash of its plan, which the approvers sign
+    pub fn id(&self) -> Result<Hash> {
+        Ok(Hash::hash(&rmp_serde::to_vec(self)?))
+    }
+}
+
+/// A tranche that was sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentTranche {
+    /// When it was sent, in seconds since the Unix epoch
+    pub sent_at: u64,
+    /// The spends of the treasury wallet sending it
+    pub spends: BTreeSet<SpendAddress>,
+    /// The cash note created for the recipient
+    pub cash_note: CashNote,
+}
+
+/// A disbursement, its approvals and the tranches sent so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Disbursement {
+    pub plan: DisbursementPlan,
+    approvals: BTreeMap<PublicKey, Signature>,
+    /// By index of the tranche in the plan
+    sent: BTreeMap<usize, SentTranche>,
+}
+
+impl Disbursement {
+    /// Plan a disbursement, `threshold` of the `approvers` having to approve it
+    pub fn new(
+        purpose: String,
+        from: MainPubkey,
+        tranches: Vec<TranchePlan>,
+        approvers: BTreeSet<PublicKey>,
+        threshold: usize,
+    ) -> Result<Self> {
+        if tranches.is_empty() {
+            return Err(Error::InvalidDisbursement("it has no tranche".to_string()));
+        }
+        if tranches.iter().any(|tranche| tranche.amount.is_zero()) {
+            return Err(Error::InvalidDisbursement(
+                "a tranche has an amount of zero".to_string(),
+            ));
+        }
+        if threshold == 0 || threshold > approvers.len() {
+            return Err(Error::InvalidDisbursement(format!(
+                "the threshold {threshold} must be between 1 and the {} approvers",
+                approvers.len()
+            )));
+        }
+        Ok(Self {
+            plan: DisbursementPlan {
+                purpose,
+                from,
+                tranches,
+                approvers,
+                threshold,
+            },
+            approvals: BTreeMap::new(),
+            sent: BTreeMap::new(),
+        })
+    }
+
+    pub fn id(&self) -> Result<Hash> {
+        self.plan.id()
+    }
+
+    /// The approval of `approver` for this disbursement, to be added with `add_approval`
+    pub fn sign_approval(&self, approver: &SecretKey) -> Result<Signature> {
+        Ok(approver.sign(self.id()?))
+    }
+
+    /// Add the approval of one of the approvers, checking its signature of the plan
+    pub fn add_approval(&mut self, approver: PublicKey, signature: Signature) -> Result<()> {
+        if !self.plan.approvers.contains(&approver) {
+            return Err(Error::InvalidApproval(format!(
+                "{} is not an approver",
+                approver.to_hex()
+            )));
+        }
+        if !approver.verify(&signature, self.id()?) {
+            return Err(Error::InvalidApproval(format!(
+                "the signature of {} is not of this plan",
+                approver.to_hex()
+            )));
+        }
+        let _ = self.approvals.insert(approver, signature);
+        Ok(())
+    }
+
+    /// The number of approvals by approvers of the plan, with a valid signature of it.
+    ///
+    /// The approvals are verified again here, not to trust the records loaded from disk.
+    pub fn approvals(&self) -> usize {
+        let Ok(id) = self.id() else {
+            return 0;
+        };
+        self.approvals
+            .iter()
+            .filter(|(approver, signature)| {
+                self.plan.approvers.contains(approver) && approver.verify(signature, id)
+            })
+            .count()
+    }
+
+    pub fn is_approved(&self) -> bool {
+        self.approvals() >= self.plan.threshold
+    }
+
+    pub fn sent_tranche(&self, index: usize) -> Option<&SentTranche> {
+        self.sent.get(&index)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.sent.len() == self.plan.tranches.len()
+    }
+
+    /// The indexes of the tranches due at `now` and not sent yet
+    pub fn due_tranches(&self, now: u64) -> Vec<usize> {
+        self.plan
+            .tranches
+            .iter()
+            .enumerate()
+            .filter(|(index, tranche)| tranche.not_before <= now && !self.sent.contains_key(index))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The reason of the spends of a tranche: the disbursement id, then the tranche index
+    fn tranche_reason(id: &Hash, index: usize) -> SpendReason {
+        let mut reason = [0u8; 64];
+        reason[..32].copy_from_slice(id.slice());
+        reason[32..40].copy_from_slice(&(index as u64).to_be_bytes());
+        SpendReason::Custom(reason)
+    }
+}
+
+/// The disbursements of a treasury wallet, recorded in its wallet dir.
+#[derive(Debug)]
+pub struct Treasury {
+    path: PathBuf,
+    disbursements: BTreeMap<Hash, Disbursement>,
+}
+
+impl Treasury {
+    /// Load the disbursements recorded in the wallet dir, none if there is no record yet
+    pub fn load(wallet_dir: &Path) -> Result<Self> {
+        let path = wallet_dir.join(TREASURY_FILE_NAME);
+        let disbursements = if path.exists() {
+            rmp_serde::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path,
+            disbursements,
+        })
+    }
+
+    fn store(&self) -> Result<()> {
+        fs::write(&self.path, rmp_serde::to_vec(&self.disbursements)?)?;
+        Ok(())
+    }
+
+    /// Record a new disbursement, returning its id
+    pub fn plan(&mut self, disbursement: Disbursement) -> Result<Hash> {
+        let id = disbursement.id()?;
+        if self.disbursements.contains_key(&id) {
+            return Err(Error::InvalidDisbursement(format!(
+                "{} is already planned",
+                id.to_hex()
+            )));
+        }
+        let _ = self.disbursements.insert(id, disbursement);
+        self.store()?;
+        Ok(id)
+    }
+
+    pub fn disbursement(&self, id: &Hash) -> Option<&Disbursement> {
+        self.disbursements.get(id)
+    }
+
+    pub fn disbursements(&self) -> impl Iterator<Item = (&Hash, &Disbursement)> {
+        self.disbursements.iter()
+    }
+
+    /// Record the approval of a disbursement
+    pub fn approve(&mut self, id: &Hash, approver: PublicKey, signature: Signature) -> Result<()> {
+        self.disbursements
+            .get_mut(id)
+            .ok_or(Error::DisbursementNotFound(*id))?
+            .add_approval(approver, signature)?;
+        self.store()
+    }
+
+    /// Send the tranches due at `now` of the approved disbursements from the treasury `wallet`,
+    /// returning the cash notes created for the recipients, by disbursement id and tranche index.
+    ///
+    /// As with any local send, the spends are kept by the wallet as unconfirmed, to be sent to the
+    /// Network.
+    pub fn disburse_due(
+        &mut self,
+        wallet: &mut HotWallet,
+        now: u64,
+    ) -> Result<Vec<(Hash, usize, CashNote)>> {
+        let mut created = vec![];
+        let ids: Vec<Hash> = self.disbursements.keys().copied().collect();
+        for id in ids {
+            let (due, tranches) = match self.disbursements.get(&id) {
+                Some(disbursement) if disbursement.plan.from == wallet.address() => {
+                    let due = disbursement.due_tranches(now);
+                    if !due.is_empty() && !disbursement.is_approved() {
+                        warn!(
+                            "Disbursement {} has tranches due, but only {} of its {} approvals",
+                            id.to_hex(),
+                            disbursement.approvals(),
+                            disbursement.plan.threshold
+                        );
+                        continue;
+                    }
+                    (due, disbursement.plan.tranches.clone())
+                }
+                _ => continue,
+            };
+            for index in due {
+                let tranche = &tranches[index];
+                let unconfirmed_before = wallet.unconfirmed_spend_requests().clone();
+                let cash_notes = wallet.local_send(
+                    vec![(tranche.amount, tranche.recipient)],
+                    Some(Disbursement::tranche_reason(&id, index)),
+                )?;
+                let cash_note = cash_notes
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| Error::CouldNotSendMoney("no cash note created".to_string()))?;
+                let spends = wallet
+                    .unconfirmed_spend_requests()
+                    .difference(&unconfirmed_before)
+                    .map(|spend| spend.address())
+                    .collect();
+                info!(
+                    "Sent tranche {index} of disbursement {}: {} to {:?}",
+                    id.to_hex(),
+                    tranche.amount,
+                    tranche.recipient
+                );
+                if let Some(disbursement) = self.disbursements.get_mut(&id) {
+                    let _ = disbursement.sent.insert(
+                        index,
+                        SentTranche {
+                            sent_at: now,
+                            spends,
+                            cash_note: cash_note.clone(),
+                        },
+                    );
+                }
+                // recorded as soon as sent, not to be sent again after a later failure
+                self.store()?;
+                created.push((id, index, cash_note));
+            }
+        }
+        Ok(created)
+    }
+
+    /// A CSV report of the tranches of all the disbursements, one row per tranche
+    pub fn report(&self) -> String {
+        let mut content =
+            "disbursement,purpose,tranche,recipient,amount,not_before,approvals,sent_at,spends"
+                .to_string();
+        for (id, disbursement) in &self.disbursements {
+            for (index, tranche) in disbursement.plan.tranches.iter().enumerate() {
+                let (sent_at, spends) = match disbursement.sent.get(&index) {
+                    Some(sent) => (
+                        sent.sent_at.to_string(),
+                        sent.spends
+                            .iter()
+                            .map(|spend| spend.to_hex())
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
+                    None => (String::new(), String::new()),
+                };
+                content = format!(
+                    "{content}\n{},{},{index},{},{},{},{}/{},{sent_at},{spends}",
+                    id.to_hex(),
+                    csv_escape(&disbursement.plan.purpose),
+                    tranche.recipient.to_hex(),
+                    tranche.amount.as_nano(),
+                    tranche.not_before,
+                    disbursement.approvals(),
+                    disbursement.plan.threshold,
+                );
+            }
+        }
+        content
+    }
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::WalletBuilder, MainSecretKey};
+    use eyre::Result;
+
+    #[test]
+    fn disbursement_should_only_be_sent_once_approved_and_due() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut wallet = WalletBuilder::with_balance(NanoTokens::from(1_000)).build(dir.path())?;
+        let recipient = MainSecretKey::random().main_pubkey();
+        let approvers: Vec<_> = (0..3).map(|_| SecretKey::random()).collect();
+
+        let disbursement = Disbursement::new(
+            "grants, \"round 1\"".to_string(),
+            wallet.address(),
+            vec![
+                TranchePlan {
+                    amount: NanoTokens::from(100),
+                    recipient,
+                    not_before: 10,
+                },
+                TranchePlan {
+                    amount: NanoTokens::from(200),
+                    recipient,
+                    not_before: 20,
+                },
+            ],
+            approvers.iter().map(|sk| sk.public_key()).collect(),
+            2,
+        )?;
+        let mut treasury = Treasury::load(dir.path())?;
+        let id = treasury.plan(disbursement)?;
+
+        // a signature of another plan, or by someone else, is refused
+        let outsider = SecretKey::random();
+        assert!(treasury
+            .approve(&id, outsider.public_key(), outsider.sign(id))
+            .is_err());
+        assert!(treasury
+            .approve(&id, approvers[0].public_key(), approvers[0].sign(b"other"))
+            .is_err());
+
+        treasury.approve(&id, approvers[0].public_key(), approvers[0].sign(id))?;
+        assert!(treasury.disburse_due(&mut wallet, 15)?.is_empty());
+
+        // a forged approval recorded on disk is not counted
+        if let Some(disbursement) = treasury.disbursements.get_mut(&id) {
+            let _ = disbursement
+                .approvals
+                .insert(approvers[2].public_key(), outsider.sign(id));
+        }
+        treasury.store()?;
+        let mut treasury = Treasury::load(dir.path())?;
+        assert!(treasury.disburse_due(&mut wallet, 15)?.is_empty());
+
+        treasury.approve(&id, approvers[1].public_key(), approvers[1].sign(id))?;
+
+        let sent = treasury.disburse_due(&mut wallet, 15)?;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].2.value(), NanoTokens::from(100));
+        assert!(treasury.disburse_due(&mut wallet, 15)?.is_empty());
+        assert_eq!(wallet.balance(), NanoTokens::from(900));
+
+        // the records survive a reload
+        let mut treasury = Treasury::load(dir.path())?;
+        let sent = treasury.disburse_due(&mut wallet, 20)?;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(wallet.balance(), NanoTokens::from(700));
+        let disbursement = treasury
+            .disbursement(&id)
+            .ok_or_else(|| eyre::eyre!("the disbursement to be recorded"))?;
+        assert!(disbursement.is_complete());
+        let report = treasury.report();
+        let rows: Vec<_> = report.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[1].starts_with(&format!("{},\"grants, \"\"round 1\"\"\",0,", id.to_hex())));
+        assert!(rows[1].contains(",2/2,15,"));
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! What a wallet records of its unconfirmed spends besides the spends themselves: when they were
+//! created and how their resends went, for them to be resent with a backoff and the ancient ones
+//! to be given up.
+//!
+//! It is kept in its own file next to the `unconfirmed_spend_requests` one, whose format is left
+//! as is for the wallets written before it.
+
+use super::Result;
+use crate::{SignedSpend, SpendAddress};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+const UNCONFIRMED_SPEND_METADATA_FILE_NAME: &str = "unconfirmed_spend_metadata";
+
+/// What is recorded of an unconfirmed spend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnconfirmedSpendMetadata {
+    /// When the spend was created, or first seen for those of a wallet written before it was
+    /// recorded
+    pub created_at: SystemTime,
+    /// The number of times the spend was resent
+    pub attempts: u32,
+    pub last_attempt_at: Option<SystemTime>,
+    /// The error of the last resend, if it failed
+    pub last_error: Option<String>,
+}
+
+impl UnconfirmedSpendMetadata {
+    pub fn new(created_at: SystemTime) -> Self {
+        Self {
+            created_at,
+            attempts: 0,
+            last_attempt_at: None,
+            last_error: None,
+        }
+    }
+
+    /// Record a resend of the spend, with its error if it failed.
+    pub fn record_attempt(&mut self, at: SystemTime, error: Option<String>) {
+        self.attempts = self.attempts.saturating_add(1);
+        self.last_attempt_at = Some(at);
+        self.last_error = error;
+    }
+}
+
+/// When the unconfirmed spends are resent: right away at first, then after a delay doubling
+/// with each attempt up to a maximum. The spends older than the maximum age are given up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResendPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub max_age: Duration,
+}
+
+impl Default for ResendPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(10 * 60),
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+impl ResendPolicy {
+    /// The delay after the last resend of a spend resent `attempts` times.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let doublings = attempts.saturating_sub(1).min(31);
+        self.initial_backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+
+    /// When the spend is due to be resent.
+    pub fn next_attempt_at(&self, metadata: &UnconfirmedSpendMetadata) -> SystemTime {
+        match metadata.last_attempt_at {
+            Some(last_attempt_at) => last_attempt_at + self.backoff(metadata.attempts),
+            None => metadata.created_at,
+        }
+    }
+
+    pub fn is_due(&self, metadata: &UnconfirmedSpendMetadata, now: SystemTime) -> bool {
+        self.next_attempt_at(metadata) <= now
+    }
+
+    /// Whether the spend is too old to be resent anymore.
+    pub fn has_expired(&self, metadata: &UnconfirmedSpendMetadata, now: SystemTime) -> bool {
+        now.duration_since(metadata.created_at)
+            .is_ok_and(|age| age >= self.max_age)
+    }
+}
+
+/// Load the metadata of the unconfirmed spends of the wallet dir, recording those of the spends
+/// which have none as created now.
+pub(super) fn load_unconfirmed_spend_metadata(
+    wallet_dir: &Path,
+    spends: &BTreeSet<SignedSpend>,
+) -> Result<BTreeMap<SpendAddress, UnconfirmedSpendMetadata>> {
+    let path = wallet_dir.join(UNCONFIRMED_SPEND_METADATA_FILE_NAME);
+    let mut metadata: BTreeMap<SpendAddress, UnconfirmedSpendMetadata> = if path.is_file() {
+        rmp_serde::from_slice(&fs::read(&path)?)?
+    } else {
+        BTreeMap::new()
+    };
+    sync_unconfirmed_spend_metadata(&mut metadata, spends, SystemTime::now());
+    Ok(metadata)
+}
+
+/// Have the metadata be that of the spends alone, the new ones created at `now`.
+pub(super) fn sync_unconfirmed_spend_metadata(
+    metadata: &mut BTreeMap<SpendAddress, UnconfirmedSpendMetadata>,
+    spends: &BTreeSet<SignedSpend>,
+    now: SystemTime,
+) {
+    let addresses: BTreeSet<SpendAddress> = spends.iter().map(|spend| spend.address()).collect();
+    metadata.retain(|address, _| addresses.contains(address));
+    for address in addresses {
+        let _ = metadata
+            .entry(address)
+            .or_insert_with(|| UnconfirmedSpendMetadata::new(now));
+    }
+}
+
+pub(super) fn store_unconfirmed_spend_metadata(
+    wallet_dir: &Path,
+    metadata: &BTreeMap<SpendAddress, UnconfirmedSpendMetadata>,
+) -> Result<()> {
+    let path = wallet_dir.join(UNCONFIRMED_SPEND_METADATA_FILE_NAME);
+    fs::write(path, rmp_serde::to_vec(metadata)?)?;
+    Ok(())
+}
+
+pub(super) fn remove_unconfirmed_spend_metadata(wallet_dir: &Path) -> Result<()> {
+    let path = wallet_dir.join(UNCONFIRMED_SPEND_METADATA_FILE_NAME);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resends_should_back_off_exponentially() {
+        let policy = ResendPolicy {
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+            max_age: Duration::from_secs(3600),
+        };
+        let created_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut metadata = UnconfirmedSpendMetadata::new(created_at);
+        assert!(policy.is_due(&metadata, created_at));
+
+        let mut now = created_at;
+        let mut delays = vec![];
+        for _ in 0..6 {
+            metadata.record_attempt(now, Some("network error".to_string()));
+            let next = policy.next_attempt_at(&metadata);
+            assert!(!policy.is_due(&metadata, now));
+            assert!(policy.is_due(&metadata, next));
+            delays.push(next.duration_since(now).unwrap_or_default().as_secs());
+            now = next;
+        }
+        assert_eq!(delays, vec![2, 4, 8, 16, 32, 60]);
+        assert_eq!(metadata.attempts, 6);
+        assert_eq!(metadata.last_error.as_deref(), Some("network error"));
+
+        assert!(!policy.has_expired(&metadata, created_at + Duration::from_secs(3599)));
+        assert!(policy.has_expired(&metadata, created_at + Duration::from_secs(3600)));
+        // a huge number of attempts does not overflow
+        assert_eq!(policy.backoff(u32::MAX), policy.max_backoff);
+    }
+}
@@ -7,30 +7,174 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
+    cash_note_index::CashNoteIndex,
     error::{Error, Result},
+    migrate::{decode_and_migrate, WALLET_FILE_VERSION},
     KeyLessWallet,
 };
 use crate::{CashNote, SignedSpend, SpendAddress, UniquePubkey};
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
     collections::BTreeSet,
     fs,
+    io::Read,
     path::{Path, PathBuf},
 };
+use xor_name::XorName;
 
 // Filename for storing a wallet.
 const WALLET_FILE_NAME: &str = "wallet";
+/// The version of the layout of the wallet file, see the `migrate` module
+pub(super) const WALLET_VERSION_FILE_NAME: &str = "wallet_version";
 const WALLET_LOCK_FILE_NAME: &str = "wallet.lock";
-const CASHNOTES_DIR_NAME: &str = "cash_notes";
+pub(super) const CASHNOTES_DIR_NAME: &str = "cash_notes";
 const UNCONFIRMED_TX_NAME: &str = "unconfirmed_spend_requests";
 const CONFIRMED_SPENDS_DIR_NAME: &str = "confirmed_spends";
+const IDEMPOTENT_SENDS_DIR_NAME: &str = "idempotent_sends";
+
+/// The bytes heading the MsgPack wallet files written with the field names, followed by the code
+/// of the format. JSON files are told apart by their opening brace instead, for them to stay
+/// plain JSON.
+const WALLET_FILE_MAGIC: &[u8] = b"SNWALLET";
+
+/// The serialization of the wallet file, kept by the wallet once chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalletFileFormat {
+    /// Compact MsgPack of the bare wallet, without any header, as wallet files were written
+    /// before the other formats, which keeps them readable by those older versions
+    #[default]
+    Legacy,
+    /// MsgPack with the field names, more tolerant of changes of the wallet
+    MsgPack,
+    /// JSON, to inspect the state of the wallet with standard tools
+    Json,
+}
+
+impl WalletFileFormat {
+    fn code(&self) -> Option<u8> {
+        match self {
+            Self::Legacy => None,
+            Self::MsgPack => Some(1),
+            Self::Json => None,
+        }
+    }
+
+    /// The format of the wallet file content, along with the content without its header.
+    pub(super) fn of_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let Some(rest) = bytes.strip_prefix(WALLET_FILE_MAGIC) else {
+            // a compact MsgPack wallet is an array, never heading with a brace
+            let is_json = bytes
+                .iter()
+                .find(|byte| !byte.is_ascii_whitespace())
+                .is_some_and(|byte| *byte == b'{');
+            return Ok((if is_json { Self::Json } else { Self::Legacy }, bytes));
+        };
+        match rest.split_first() {
+            Some((1, content)) => Ok((Self::MsgPack, content)),
+            // JSON behind a header, as it was first written
+            Some((2, content)) => Ok((Self::Json, content)),
+            Some((code, _)) => Err(Error::InvalidWalletFile(format!(
+                "unknown format code {code}"
+            ))),
+            None => Err(Error::InvalidWalletFile("missing format code".to_string())),
+        }
+    }
+
+    fn serialize<T: Serialize>(&self, value: &T, bytes: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Self::Legacy => {
+                let mut serialiser = rmp_serde::encode::Serializer::new(bytes);
+                value.serialize(&mut serialiser)?;
+            }
+            Self::MsgPack => bytes.extend(rmp_serde::to_vec_named(value)?),
+            Self::Json => bytes.extend(serde_json::to_vec_pretty(value)?),
+        }
+        Ok(())
+    }
+
+    pub(super) fn deserialize<T: DeserializeOwned>(&self, content: &[u8]) -> Result<T> {
+        match self {
+            Self::Legacy | Self::MsgPack => Ok(rmp_serde::from_slice(content)?),
+            Self::Json => Ok(serde_json::from_slice(content)?),
+        }
+    }
+}
+
+/// Encode the `KeyLessWallet` in the given format, with the header of the format if any, at the
+/// current version.
+pub(super) fn encode_wallet(wallet: &KeyLessWallet, format: WalletFileFormat) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    if let Some(code) = format.code() {
+        bytes.extend(WALLET_FILE_MAGIC);
+        bytes.push(code);
+    }
+    format.serialize(wallet, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode a `KeyLessWallet` written in any of the formats at the given version, returning the
+/// format it was in.
+pub(super) fn decode_wallet(
+    bytes: &[u8],
+    version: u32,
+) -> Result<(KeyLessWallet, WalletFileFormat)> {
+    let (format, content) = WalletFileFormat::of_bytes(bytes)?;
+    let wallet = decode_and_migrate(content, format, version)?;
+    Ok((wallet, format))
+}
 
-/// Writes the `KeyLessWallet` to the specified path.
+/// The version of the layout of the wallet file of the wallet dir, 0 if it has no version file.
+pub(super) fn wallet_file_version(wallet_dir: &Path) -> Result<u32> {
+    let path = wallet_dir.join(WALLET_VERSION_FILE_NAME);
+    if !path.exists() {
+        return Ok(0);
+    }
+    let found = fs::read_to_string(&path)?;
+    found
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidWalletFile(format!("invalid version {:?}", found.trim())))
+}
+
+/// The format of the wallet file of the wallet dir, the default one if there is no file yet.
+pub(super) fn wallet_file_format(wallet_dir: &Path) -> Result<WalletFileFormat> {
+    let mut header = vec![];
+    match fs::File::open(wallet_dir.join(WALLET_FILE_NAME)) {
+        Ok(file) => {
+            let _ = file
+                .take(WALLET_FILE_MAGIC.len() as u64 + 1)
+                .read_to_end(&mut header)?;
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(WalletFileFormat::default())
+        }
+        Err(err) => return Err(err.into()),
+    }
+    Ok(WalletFileFormat::of_bytes(&header)?.0)
+}
+
+/// Writes the `KeyLessWallet` to the specified path, in the format of the current file.
 pub(super) fn store_wallet(wallet_dir: &Path, wallet: &KeyLessWallet) -> Result<()> {
+    let format = wallet_file_format(wallet_dir)?;
+    store_wallet_in_format(wallet_dir, wallet, format)
+}
+
+/// Writes the `KeyLessWallet` to the specified path in the given format, which the wallet keeps
+/// from then on.
+pub(super) fn store_wallet_in_format(
+    wallet_dir: &Path,
+    wallet: &KeyLessWallet,
+    format: WalletFileFormat,
+) -> Result<()> {
     let wallet_path = wallet_dir.join(WALLET_FILE_NAME);
-    let mut file = fs::File::create(wallet_path)?;
-    let mut serialiser = rmp_serde::encode::Serializer::new(&mut file);
-    wallet.serialize(&mut serialiser)?;
+    fs::write(wallet_path, encode_wallet(wallet, format)?)?;
+    // written once the file is at the version, and only when it changes
+    if wallet_file_version(wallet_dir).ok() != Some(WALLET_FILE_VERSION) {
+        fs::write(
+            wallet_dir.join(WALLET_VERSION_FILE_NAME),
+            WALLET_FILE_VERSION.to_string(),
+        )?;
+    }
     Ok(())
 }
 
@@ -57,7 +201,8 @@ pub(super) fn store_unconfirmed_spend_requests(
     Ok(())
 }
 
-/// Remove the `unconfirmed_spend_requests` from the specified path.
+/// Record the given spends as confirmed, removing them from the `unconfirmed_spend_requests` of
+/// the specified path.
 pub(super) fn remove_unconfirmed_spend_requests(
     wallet_dir: &Path,
     unconfirmed_spend_requests: &BTreeSet<SignedSpend>,
@@ -72,10 +217,19 @@ pub(super) fn remove_unconfirmed_spend_requests(
         fs::write(spend_file_path, spend.to_bytes())?;
     }
 
+    // the spends left unconfirmed are kept
+    let mut remaining = get_unconfirmed_spend_requests(wallet_dir)?.unwrap_or_default();
+    remaining.retain(|spend| !unconfirmed_spend_requests.contains(spend));
+    if !remaining.is_empty() {
+        return store_unconfirmed_spend_requests(wallet_dir, &remaining);
+    }
+
     let unconfirmed_spend_requests_path = wallet_dir.join(UNCONFIRMED_TX_NAME);
 
     debug!("Removing unconfirmed_spend_requests from {unconfirmed_spend_requests_path:?}");
-    fs::remove_file(unconfirmed_spend_requests_path)?;
+    if unconfirmed_spend_requests_path.exists() {
+        fs::remove_file(unconfirmed_spend_requests_path)?;
+    }
     Ok(())
 }
 
@@ -125,8 +279,63 @@ pub(super) fn get_unconfirmed_spend_requests(
     Ok(Some(unconfirmed_spend_requests))
 }
 
+/// Returns the path of the record of the idempotent send with the given id.
+fn idempotent_send_path(wallet_dir: &Path, id: &str) -> PathBuf {
+    let name = hex::encode(XorName::from_content(id.as_bytes()));
+    wallet_dir.join(IDEMPOTENT_SENDS_DIR_NAME).join(name)
+}
+
+/// Writes the record of an idempotent send, named after the hash of its id.
+///
+/// The record is written to a temporary file first and then renamed, so that a crash never
+/// leaves a partial record behind.
+pub(super) fn store_idempotent_send<T: Serialize>(
+    wallet_dir: &Path,
+    id: &str,
+    send: &T,
+) -> Result<()> {
+    let path = idempotent_send_path(wallet_dir, id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+
+    let mut file = fs::File::create(&tmp_path)?;
+    let mut serialiser = rmp_serde::encode::Serializer::new(&mut file);
+    send.serialize(&mut serialiser)?;
+    file.sync_all()?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Returns `Some(T)` or None if no send was recorded with the given id.
+pub(super) fn get_idempotent_send<T: DeserializeOwned>(
+    wallet_dir: &Path,
+    id: &str,
+) -> Result<Option<T>> {
+    let path = idempotent_send_path(wallet_dir, id);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let file = fs::File::open(&path)?;
+    let send = rmp_serde::from_read(&file)?;
+
+    Ok(Some(send))
+}
+
+/// The name of the file of the cash note in the cash_notes dir.
+pub(super) fn cash_note_file_name(unique_pubkey: &UniquePubkey) -> String {
+    format!(
+        "{}.cash_note",
+        SpendAddress::from_unique_pubkey(unique_pubkey).to_hex()
+    )
+}
+
 /// Hex encode and write each `CashNote` to a separate file in respective
 /// recipient public address dir in the created cash_notes dir. Each file is named after the cash_note id.
+///
+/// They are indexed once the wallet holding them is stored, see `CashNoteIndex::sync`.
 pub(super) fn store_created_cash_notes<'a, T>(
     created_cash_notes: T,
     wallet_dir: &Path,
@@ -139,17 +348,13 @@ where
     fs::create_dir_all(&created_cash_notes_path)?;
 
     for cash_note in created_cash_notes {
-        let unique_pubkey_file_name = format!(
-            "{}.cash_note",
-            SpendAddress::from_unique_pubkey(&cash_note.unique_pubkey()).to_hex()
-        );
-
-        let cash_note_file_path = created_cash_notes_path.join(unique_pubkey_file_name);
+        let cash_note_file_path =
+            created_cash_notes_path.join(cash_note_file_name(&cash_note.unique_pubkey()));
         debug!("Writing cash_note file to: {cash_note_file_path:?}");
 
         let hex = cash_note
             .to_hex()
-            .map_err(|_| Error::FailedToHexEncodeCashNote)?;
+            .map_err(Error::FailedToHexEncodeCashNote)?;
         fs::write(cash_note_file_path, &hex)?;
     }
     Ok(())
@@ -162,25 +367,36 @@ where
 {
     // The create cash_notes dir within the wallet dir.
     let created_cash_notes_path = wallet_dir.join(CASHNOTES_DIR_NAME);
+    let mut removed = vec![];
     for cash_note_key in cash_notes {
-        let unique_pubkey_name = *SpendAddress::from_unique_pubkey(cash_note_key).xorname();
-        let unique_pubkey_file_name = format!("{}.cash_note", hex::encode(unique_pubkey_name));
-
-        let cash_note_file_path = created_cash_notes_path.join(unique_pubkey_file_name);
+        let cash_note_file_path = created_cash_notes_path.join(cash_note_file_name(cash_note_key));
         debug!("Removing cash_note file from: {:?}", cash_note_file_path);
 
-        fs::remove_file(cash_note_file_path)?;
+        let result = fs::remove_file(cash_note_file_path);
+        removed.push(cash_note_key);
+        if let Err(error) = result {
+            CashNoteIndex::remove(wallet_dir, removed)?;
+            return Err(error.into());
+        }
     }
-    Ok(())
+    CashNoteIndex::remove(wallet_dir, removed)
 }
 
-/// Loads all the cash_notes found in the cash_notes dir.
+/// Loads all the cash_notes found in the cash_notes dir, but the ones of the wallet already
+/// indexed. They are indexed once the wallet holding them is stored, for their files not to be
+/// opened again, a file dropped under another name being renamed after its cash note for it.
 pub(super) fn load_cash_notes_from_disk(wallet_dir: &Path) -> Result<Vec<CashNote>> {
-    let cash_notes_path = match std::env::var("CASHNOTES_PATH") {
-        Ok(path) => PathBuf::from(path),
-        Err(_) => wallet_dir.join(CASHNOTES_DIR_NAME),
+    let (cash_notes_path, index) = match std::env::var("CASHNOTES_PATH") {
+        Ok(path) => (PathBuf::from(path), None),
+        Err(_) => (
+            wallet_dir.join(CASHNOTES_DIR_NAME),
+            Some(CashNoteIndex::load(wallet_dir)?),
+        ),
     };
-
+    let indexed_file_names = index
+        .as_ref()
+        .map(|index| index.file_names())
+        .unwrap_or_default();
     let mut deposits = vec![];
     for entry in walkdir::WalkDir::new(&cash_notes_path)
         .into_iter()
@@ -188,58 +404,93 @@ pub(super) fn load_cash_notes_from_disk(wallet_dir: &Path) -> Result<Vec<CashNot
     {
         if entry.file_type().is_file() {
             let file_name = entry.file_name();
+            if file_name
+                .to_str()
+                .is_some_and(|name| indexed_file_names.contains(name))
+            {
+                continue;
+            }
             println!("Reading deposited tokens from {file_name:?}.");
 
-            let cash_note_data = fs::read_to_string(entry.path())?;
-            let cash_note = match CashNote::from_hex(cash_note_data.trim()) {
+            let cash_note = match read_cash_note_file(entry.path()) {
                 Ok(cash_note) => cash_note,
-                Err(_) => {
+                Err(Error::InvalidCashNoteFile { source, .. }) => {
                     println!(
-                        "This file does not appear to have valid hex-encoded CashNote data. \
-                        Skipping it."
+                        "This file does not appear to have valid hex-encoded CashNote data \
+                        ({source}). Skipping it."
                     );
                     continue;
                 }
+                Err(error) => return Err(error),
             };
 
+            let canonical_name = cash_note_file_name(&cash_note.unique_pubkey());
+            if index.is_some() && file_name.to_str() != Some(canonical_name.as_str()) {
+                fs::rename(entry.path(), cash_notes_path.join(canonical_name))?;
+            }
             deposits.push(cash_note);
         }
     }
 
-    if deposits.is_empty() {
+    if deposits.is_empty() && indexed_file_names.is_empty() {
         println!("No deposits found at {}.", cash_notes_path.display());
     }
 
     Ok(deposits)
 }
 
-/// Loads a specific cash_note from path
-pub fn load_created_cash_note(unique_pubkey: &UniquePubkey, wallet_dir: &Path) -> Option<CashNote> {
-    trace!("Loading cash_note from file with pubkey: {unique_pubkey:?}");
+/// Loads all the cash_notes of the cash_notes dir of the wallet, e.g. to back them up, failing on
+/// the first file which is not valid rather than leaving its cash note out.
+pub(super) fn load_created_cash_notes(wallet_dir: &Path) -> Result<Vec<CashNote>> {
     let created_cash_notes_path = wallet_dir.join(CASHNOTES_DIR_NAME);
-    let unique_pubkey_name = *SpendAddress::from_unique_pubkey(unique_pubkey).xorname();
-    let unique_pubkey_file_name = format!("{}.cash_note", hex::encode(unique_pubkey_name));
-    // Construct the path to the cash_note file
-    let cash_note_file_path = created_cash_notes_path.join(unique_pubkey_file_name);
-
-    // Read the cash_note data from the file
-    match fs::read_to_string(cash_note_file_path.clone()) {
-        Ok(cash_note_data) => {
-            // Convert the cash_note data from hex to CashNote
-            match CashNote::from_hex(cash_note_data.trim()) {
-                Ok(cash_note) => Some(cash_note),
-                Err(error) => {
-                    warn!("Failed to convert cash_note data from hex: {}", error);
-                    None
-                }
-            }
+    if !created_cash_notes_path.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut cash_notes = vec![];
+    for entry in fs::read_dir(created_cash_notes_path)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
         }
-        Err(error) => {
-            warn!(
-                "Failed to read cash_note file {:?}: {}",
-                cash_note_file_path, error
-            );
-            None
+        cash_notes.push(read_cash_note_file(&path)?);
+    }
+    Ok(cash_notes)
+}
+
+/// Loads the cash_notes of the given unique pubkeys only, from the files the index of the wallet
+/// locates them in, rather than reading every file of the cash_notes dir. The ones without a file
+/// are left out.
+pub(super) fn load_indexed_cash_notes<'a, I>(
+    wallet_dir: &Path,
+    unique_pubkeys: I,
+) -> Result<Vec<CashNote>>
+where
+    I: IntoIterator<Item = &'a UniquePubkey>,
+{
+    let index = CashNoteIndex::load(wallet_dir)?;
+    let created_cash_notes_path = wallet_dir.join(CASHNOTES_DIR_NAME);
+    let mut cash_notes = vec![];
+    for unique_pubkey in unique_pubkeys {
+        let path = match index.get(unique_pubkey) {
+            Some(metadata) => created_cash_notes_path.join(&metadata.file_name),
+            None => created_cash_notes_path.join(cash_note_file_name(unique_pubkey)),
+        };
+        if !path.is_file() {
+            warn!("No cash_note file found for {unique_pubkey:?}");
+            continue;
         }
+        cash_notes.push(read_cash_note_file(&path)?);
     }
+    Ok(cash_notes)
+}
+
+/// Reads the hex-encoded cash_note of a file, failing with the path of the file if its content
+/// is not a valid cash_note.
+fn read_cash_note_file(path: &Path) -> Result<CashNote> {
+    let hex = fs::read_to_string(path)?;
+    CashNote::from_hex(hex.trim()).map_err(|source| Error::InvalidCashNoteFile {
+        path: path.to_path_buf(),
+        source: Box::new(source),
+    })
 }
@@ -6,31 +6,36 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+#[cfg(feature = "async-io")]
+use super::async_io::spawn_blocking;
 use super::{
+    address_book::AddressBook,
     api::WalletApi,
+    cash_note_index::CashNoteIndex,
+    coin_selection::{CoinSelection, LargestFirst},
+    derivation_indexes::DerivationPurpose,
     error::{Error, Result},
-    hot_wallet::WalletExclusiveAccess,
+    history::{History, HistoryEntry, HistoryKind},
+    hooks::WalletHooks,
     keys::{get_main_pubkey, store_new_pubkey},
-    wallet_file::{
-        load_cash_notes_from_disk, load_created_cash_note, store_created_cash_notes, store_wallet,
-        wallet_lockfile_name,
-    },
-    KeyLessWallet,
+    store::{FileWalletStore, WalletExclusiveAccess, WalletStore},
+    wallet_file::{store_wallet, store_wallet_in_format, wallet_file_format, WalletFileFormat},
+    KeyLessWallet, WalletSnapshot,
 };
 use crate::{
-    wallet::data_payments::PaymentDetails, CashNote, DerivationIndex, MainPubkey, NanoTokens,
-    SpendReason, UniquePubkey, UnsignedTransaction,
+    rand::{CryptoRng, RngCore},
+    wallet::data_payments::PaymentDetails,
+    CashNote, DerivationIndex, MainPubkey, NanoTokens, SignedTransaction, SpendReason,
+    TransferError, UniquePubkey, UnsignedTransaction, WalletSigner,
 };
-#[cfg(not(target_arch = "wasm32"))]
-use fs2::FileExt;
 use std::{
     collections::{BTreeMap, BTreeSet},
-    fs::OpenOptions,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use xor_name::XorName;
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 /// This assumes the CashNotes are stored on disk
 pub struct WatchOnlyWallet {
     /// Main public key which owns the cash notes.
@@ -41,6 +46,12 @@ pub struct WatchOnlyWallet {
     api: WalletApi,
     /// The wallet containing all data, cash notes & transactions data that gets serialised and stored on disk.
     keyless_wallet: KeyLessWallet,
+    /// The hooks mirroring the mutations of the wallet into the storage of an application.
+    #[serde(skip)]
+    hooks: Option<Arc<dyn WalletHooks>>,
+    /// The store of the state of the wallet, if not the files of the wallet dir.
+    #[serde(skip)]
+    store: Option<Arc<dyn WalletStore>>,
 }
 
 impl WatchOnlyWallet {
@@ -51,9 +62,76 @@ impl WatchOnlyWallet {
             api: WalletApi::new_from_wallet_dir(wallet_dir),
             wallet_dir: wallet_dir.to_path_buf(),
             keyless_wallet,
+            hooks: None,
+            store: None,
         }
     }
 
+    /// Loads the wallet of the given main pub key from the store, or creates it there, without
+    /// any wallet dir if the store is not on disk.
+    pub fn with_store(main_pubkey: MainPubkey, store: Arc<dyn WalletStore>) -> Result<Self> {
+        let wallet_dir = store
+            .wallet_dir()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let keyless_wallet = match store.load_wallet()? {
+            Some(keyless_wallet) => keyless_wallet,
+            None => {
+                let keyless_wallet = KeyLessWallet::default();
+                store.store_wallet(&keyless_wallet)?;
+                keyless_wallet
+            }
+        };
+        let api = match store.wallet_dir() {
+            Some(wallet_dir) => WalletApi::new_from_wallet_dir(wallet_dir),
+            None => WalletApi::in_memory(),
+        };
+        Ok(Self {
+            main_pubkey,
+            api,
+            wallet_dir,
+            keyless_wallet,
+            hooks: None,
+            store: Some(store),
+        })
+    }
+
+    /// The store of the state of the wallet, the files of the wallet dir unless the wallet was
+    /// created with another one.
+    pub fn wallet_store(&self) -> Arc<dyn WalletStore> {
+        match &self.store {
+            Some(store) => Arc::clone(store),
+            None => Arc::new(FileWalletStore::new(&self.wallet_dir)),
+        }
+    }
+
+    /// The wallet dir, none when the state of the wallet is not stored on disk, in which case
+    /// neither are the history nor the other files of the dir kept.
+    pub fn dir_on_disk(&self) -> Option<&Path> {
+        match &self.store {
+            Some(store) if store.wallet_dir().is_none() => None,
+            _ => Some(&self.wallet_dir),
+        }
+    }
+
+    /// The address book of the wallet, naming the recipients it sends to. A wallet not stored on
+    /// disk has none.
+    pub fn address_book(&self) -> Result<AddressBook> {
+        match self.dir_on_disk() {
+            Some(wallet_dir) => AddressBook::load(wallet_dir),
+            None => Err(Error::NotStoredOnDisk("address book")),
+        }
+    }
+
+    /// Register the hooks to call on the mutations of the wallet, replacing any previous ones.
+    pub fn set_hooks(&mut self, hooks: Arc<dyn WalletHooks>) {
+        self.hooks = Some(hooks);
+    }
+
+    pub(super) fn hooks(&self) -> Option<Arc<dyn WalletHooks>> {
+        self.hooks.clone()
+    }
+
     /// Insert a payment and write it to the `payments` dir.
     /// If a prior payment has been made to the same xorname, then the new payment is pushed to the end of the list.
     pub fn insert_payment_transaction(&self, name: XorName, payment: PaymentDetails) -> Result<()> {
@@ -66,15 +144,19 @@ impl WatchOnlyWallet {
 
     /// Try to load any new cash_notes from the `cash_notes` dir in the wallet dir.
     pub fn try_load_cash_notes(&mut self) -> Result<()> {
-        let cash_notes = load_cash_notes_from_disk(&self.wallet_dir)?;
+        // lock and load from disk so the changes of other processes aren't overwritten
+        let exclusive_access = self.lock()?;
+        self.reload()?;
+
+        let cash_notes = self.wallet_store().load_deposited_cash_notes()?;
         let spent_unique_pubkeys: BTreeSet<_> = cash_notes
             .iter()
             .flat_map(|cn| cn.parent_spends.iter().map(|s| s.unique_pubkey()))
             .collect();
-        self.deposit(&cash_notes)?;
+        // these were deposited by another instance of the wallet, which called the hooks
+        self.insert_cash_notes(&cash_notes);
         self.mark_notes_as_spent(spent_unique_pubkeys);
 
-        let exclusive_access = self.lock()?;
         self.store(exclusive_access)?;
 
         Ok(())
@@ -97,6 +179,19 @@ impl WatchOnlyWallet {
         Self::load_keyless_wallet(wallet_dir, main_pubkey)
     }
 
+    /// Loads the watch-only wallet of the given main pub key from its dir in the root dir,
+    /// creating it if it doesn't exist yet.
+    pub fn load_from_root_dir(root_dir: &Path, main_pubkey: MainPubkey) -> Result<Self> {
+        Self::load_from(&Self::wallet_dir_of(root_dir, main_pubkey), main_pubkey)
+    }
+
+    /// Returns the dir in the root dir of the watch-only wallet of the given main pub key.
+    pub fn wallet_dir_of(root_dir: &Path, main_pubkey: MainPubkey) -> PathBuf {
+        let pk_hex = main_pubkey.to_hex();
+        let folder_name = format!("pk_{}_{}", &pk_hex[..6], &pk_hex[pk_hex.len() - 6..]);
+        root_dir.join(folder_name)
+    }
+
     /// Loads a serialized wallet from a given path, no additional element will
     /// be added to the provided path and strictly taken as the wallet files location.
     pub fn load_from_path(wallet_dir: &Path) -> Result<Self> {
@@ -109,10 +204,19 @@ impl WatchOnlyWallet {
         self.main_pubkey
     }
 
+    /// Derive the unique pub key of the cash_notes sent to this wallet with the given index,
+    /// which needs no secret key: the addresses to watch can be computed on a machine that never
+    /// holds it, while the spends are signed offline.
+    pub fn derive_unique_pubkey(&self, index: &DerivationIndex) -> UniquePubkey {
+        self.main_pubkey.new_unique_pubkey(index)
+    }
+
     pub fn balance(&self) -> NanoTokens {
         self.keyless_wallet.balance()
     }
 
+    /// The wallet dir, empty for a wallet whose state is not stored on disk: see `dir_on_disk`
+    /// for the files of the dir to never be looked for relatively to the current dir.
     pub fn wallet_dir(&self) -> &Path {
         &self.wallet_dir
     }
@@ -126,7 +230,25 @@ impl WatchOnlyWallet {
     where
         T: IntoIterator<Item = &'a CashNote>,
     {
-        for cash_note in received_cash_notes {
+        let received: Vec<CashNote> = received_cash_notes
+            .into_iter()
+            .filter(|cash_note| self.is_new_cash_note(cash_note))
+            .cloned()
+            .collect();
+        if let (Some(hooks), false) = (&self.hooks, received.is_empty()) {
+            hooks.on_deposit(&received)?;
+        }
+        self.insert_cash_notes(&received);
+
+        Ok(())
+    }
+
+    /// Insert the given cash_notes which are ours into the available ones, without calling the hooks.
+    pub(super) fn insert_cash_notes<'a, T>(&mut self, cash_notes: T)
+    where
+        T: IntoIterator<Item = &'a CashNote>,
+    {
+        for cash_note in cash_notes {
             let id = cash_note.unique_pubkey();
 
             if cash_note.derived_pubkey(&self.main_pubkey).is_err() {
@@ -135,10 +257,17 @@ impl WatchOnlyWallet {
             }
 
             let value = cash_note.value();
-            self.keyless_wallet.available_cash_notes.insert(id, value);
+            Arc::make_mut(&mut self.keyless_wallet.available_cash_notes).insert(id, value);
         }
+    }
 
-        Ok(())
+    /// Whether the cash_note is ours and not available in the wallet yet.
+    fn is_new_cash_note(&self, cash_note: &CashNote) -> bool {
+        cash_note.derived_pubkey(&self.main_pubkey).is_ok()
+            && !self
+                .keyless_wallet
+                .available_cash_notes
+                .contains_key(&cash_note.unique_pubkey())
     }
 
     /// Store the given cash_notes to the `cash_notes` dir in the wallet dir.
@@ -149,13 +278,27 @@ impl WatchOnlyWallet {
             return Ok(());
         }
 
-        std::fs::create_dir_all(&self.wallet_dir)?;
+        if let Some(wallet_dir) = self.dir_on_disk() {
+            std::fs::create_dir_all(wallet_dir)?;
+        }
 
         // lock and load from disk to make sure we're up to date and others can't modify the wallet concurrently
         let exclusive_access = self.lock()?;
         self.reload()?;
         trace!("Wallet locked and loaded!");
 
+        let new_cash_notes: Vec<CashNote> = received_cash_notes
+            .iter()
+            .filter(|cash_note| self.is_new_cash_note(cash_note))
+            .cloned()
+            .collect();
+        if let (Some(hooks), false) = (&self.hooks, new_cash_notes.is_empty()) {
+            hooks.on_deposit(&new_cash_notes)?;
+        }
+
+        let store = self.wallet_store();
+        let mut received = vec![];
+        let mut received_amount = NanoTokens::zero();
         for cash_note in received_cash_notes {
             let id = cash_note.unique_pubkey();
 
@@ -165,24 +308,127 @@ impl WatchOnlyWallet {
             }
 
             let value = cash_note.value();
-            self.keyless_wallet.available_cash_notes.insert(id, value);
+            if Arc::make_mut(&mut self.keyless_wallet.available_cash_notes)
+                .insert(id, value)
+                .is_none()
+            {
+                received.push(id);
+                received_amount = received_amount
+                    .checked_add(value)
+                    .ok_or(Error::TotalPriceTooHigh)?;
+            }
 
-            store_created_cash_notes([cash_note], &self.wallet_dir)?;
+            store.store_cash_notes(&[cash_note])?;
         }
 
-        self.store(exclusive_access)
+        let entry = (!received.is_empty()).then(|| {
+            HistoryEntry::new(
+                HistoryKind::Received,
+                received_amount,
+                NanoTokens::zero(),
+                None,
+                received,
+            )
+        });
+        self.store_recording(exclusive_access, entry)
+    }
+
+    /// The history of the tokens received and sent by the wallet, empty if it is not stored on
+    /// disk.
+    pub fn history(&self) -> Result<History> {
+        match self.dir_on_disk() {
+            Some(wallet_dir) => History::load(wallet_dir),
+            None => Ok(History::default()),
+        }
     }
 
     /// Reloads the wallet from disk.
     /// FIXME: this will drop any data held in memory and completely replaced with what's read fom disk.
     pub fn reload(&mut self) -> Result<()> {
-        *self = Self::load_from(&self.wallet_dir, self.main_pubkey)?;
+        if let Some(store) = &self.store {
+            self.keyless_wallet = store.load_wallet()?.unwrap_or_default();
+            return Ok(());
+        }
+        let mut reloaded = Self::load_from(&self.wallet_dir, self.main_pubkey)?;
+        reloaded.hooks = self.hooks.take();
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Like `deposit_and_store_to_disk`, without blocking the async runtime meanwhile.
+    #[cfg(feature = "async-io")]
+    pub async fn deposit_and_store_to_disk_async(
+        &mut self,
+        received_cash_notes: Vec<CashNote>,
+    ) -> Result<()> {
+        if received_cash_notes.is_empty() {
+            return Ok(());
+        }
+        let mut wallet = self.detached();
+        self.keyless_wallet = spawn_blocking(move || {
+            wallet.deposit_and_store_to_disk(&received_cash_notes)?;
+            Ok(wallet.keyless_wallet)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Like `reload`, without blocking the async runtime meanwhile.
+    #[cfg(feature = "async-io")]
+    pub async fn reload_async(&mut self) -> Result<()> {
+        let mut wallet = self.detached();
+        self.keyless_wallet = spawn_blocking(move || {
+            wallet.reload()?;
+            Ok(wallet.keyless_wallet)
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// The wallet an `_async` variant runs its operation on, sharing the store and hooks of this
+    /// one but none of its state, which the operation reloads from the store first.
+    #[cfg(feature = "async-io")]
+    fn detached(&self) -> Self {
+        Self {
+            main_pubkey: self.main_pubkey,
+            wallet_dir: self.wallet_dir.clone(),
+            api: self.api.clone(),
+            keyless_wallet: KeyLessWallet::default(),
+            hooks: self.hooks.clone(),
+            store: self.store.clone(),
+        }
+    }
+
+    /// The serialization of the wallet file.
+    pub fn file_format(&self) -> Result<WalletFileFormat> {
+        match self.dir_on_disk() {
+            Some(wallet_dir) => wallet_file_format(wallet_dir),
+            None => Ok(WalletFileFormat::default()),
+        }
+    }
+
+    /// Convert the wallet file to the given serialization, kept by the wallet from then on.
+    /// Nothing is done for a wallet which is not stored on disk.
+    pub fn set_file_format(&mut self, format: WalletFileFormat) -> Result<()> {
+        let Some(wallet_dir) = self.dir_on_disk().map(Path::to_path_buf) else {
+            return Ok(());
+        };
+        let exclusive_access = self.lock()?;
+        self.reload()?;
+        store_wallet_in_format(&wallet_dir, &self.keyless_wallet, format)?;
+        info!(
+            "Converted the wallet file of {:?} to {format:?}",
+            self.wallet_dir
+        );
+        std::mem::drop(exclusive_access);
         Ok(())
     }
 
     /// Attempts to reload the wallet from disk.
     pub fn reload_from_disk_or_recreate(&mut self) -> Result<()> {
-        std::fs::create_dir_all(&self.wallet_dir)?;
+        if let Some(wallet_dir) = self.dir_on_disk() {
+            std::fs::create_dir_all(wallet_dir)?;
+        }
         let _exclusive_access = self.lock()?;
         self.reload()?;
         Ok(())
@@ -193,13 +439,42 @@ impl WatchOnlyWallet {
         &self.keyless_wallet.available_cash_notes
     }
 
+    /// A consistent view of the wallet as it is now on disk, left as is by its later changes.
+    ///
+    /// The wallet is reloaded under its lock first, for the view to hold the changes made by
+    /// other processes, along with the whole history up to them.
+    pub fn snapshot_view(&mut self) -> Result<WalletSnapshot> {
+        let _exclusive_access = self.lock()?;
+        self.reload()?;
+        self.snapshot()
+    }
+
+    /// The view of the wallet as held in memory, to be taken under the lock once reloaded.
+    pub(super) fn snapshot(&self) -> Result<WalletSnapshot> {
+        WalletSnapshot::new(
+            self.main_pubkey,
+            self.dir_on_disk(),
+            Arc::clone(&self.keyless_wallet.available_cash_notes),
+        )
+    }
+
+    /// The index of the cash notes of the `cash_notes` dir of the wallet, which gives their
+    /// values and statuses without opening their files. Empty if it is not stored on disk.
+    pub fn cash_note_index(&self) -> Result<CashNoteIndex> {
+        match self.dir_on_disk() {
+            Some(wallet_dir) => CashNoteIndex::load(wallet_dir),
+            None => Ok(CashNoteIndex::default()),
+        }
+    }
+
     /// Remove referenced CashNotes from available_cash_notes
     pub fn mark_notes_as_spent<'a, T>(&mut self, unique_pubkeys: T)
     where
         T: IntoIterator<Item = &'a UniquePubkey>,
     {
+        let available_cash_notes = Arc::make_mut(&mut self.keyless_wallet.available_cash_notes);
         for k in unique_pubkeys {
-            self.keyless_wallet.available_cash_notes.remove(k);
+            available_cash_notes.remove(k);
         }
     }
 
@@ -208,7 +483,17 @@ impl WatchOnlyWallet {
         to: Vec<(NanoTokens, MainPubkey)>,
         reason_hash: Option<SpendReason>,
     ) -> Result<UnsignedTransaction> {
-        let mut rng = &mut rand::rngs::OsRng;
+        self.build_unsigned_transaction_with_rng(to, reason_hash, &mut rand::rngs::OsRng)
+    }
+
+    /// Build an unsigned transaction like `build_unsigned_transaction`, drawing the derivation
+    /// indexes of the outputs and change from the given `rng`.
+    pub fn build_unsigned_transaction_with_rng<R: RngCore + CryptoRng>(
+        &mut self,
+        to: Vec<(NanoTokens, MainPubkey)>,
+        reason_hash: Option<SpendReason>,
+        rng: &mut R,
+    ) -> Result<UnsignedTransaction> {
         // create a unique key for each output
         let to_unique_keys: Vec<_> = to
             .into_iter()
@@ -216,7 +501,7 @@ impl WatchOnlyWallet {
                 (
                     amount,
                     address,
-                    DerivationIndex::random(&mut rng),
+                    DerivationIndex::random(rng),
                     false, // not a change output
                 )
             })
@@ -228,16 +513,22 @@ impl WatchOnlyWallet {
         self.reload()?;
         trace!("Wallet locked and loaded!");
 
-        // get the available cash_notes
-        let mut available_cash_notes = vec![];
-        let wallet_dir = self.wallet_dir.to_path_buf();
-        for (id, _token) in self.available_cash_notes().iter() {
-            if let Some(cash_note) = load_created_cash_note(id, &wallet_dir) {
-                available_cash_notes.push(cash_note.clone());
-            } else {
-                warn!("Skipping CashNote {:?} because we don't have it", id);
-            }
-        }
+        // read the cash_notes to spend only, the biggest first as the transaction spends them
+        let amount = to_unique_keys
+            .iter()
+            .try_fold(NanoTokens::zero(), |total, (amount, ..)| {
+                total.checked_add(*amount)
+            })
+            .ok_or(Error::TotalPriceTooHigh)?;
+        let selected = LargestFirst.select(
+            self.available_cash_notes()
+                .iter()
+                .map(|(unique_pubkey, value)| (*unique_pubkey, *value))
+                .collect(),
+            amount,
+        );
+        let mut available_cash_notes = self.wallet_store().load_cash_notes(&selected)?;
+        available_cash_notes.sort_by_key(|cash_note| std::cmp::Reverse(cash_note.value()));
         debug!(
             "Available CashNotes for local send: {:#?}",
             available_cash_notes
@@ -245,12 +536,24 @@ impl WatchOnlyWallet {
 
         let reason_hash = reason_hash.unwrap_or_default();
 
-        let unsigned_transaction = UnsignedTransaction::new(
+        // without the key, the change can't go to the sequence of the wallet: a random index is
+        // recorded instead, for the change to be told apart when the transaction is applied
+        let change_index = DerivationIndex::random(rng);
+        let unsigned_transaction = UnsignedTransaction::new_with_change_index(
             available_cash_notes,
             to_unique_keys,
             self.address(),
+            change_index,
             reason_hash,
         )?;
+        if unsigned_transaction
+            .change_cashnote_without_spends
+            .is_some()
+        {
+            self.wallet_store()
+                .derivation_indexes()?
+                .register(change_index, DerivationPurpose::Change)?;
+        }
 
         info!(
             "Spending keys: {:?}",
@@ -293,13 +596,58 @@ impl WatchOnlyWallet {
             api: WalletApi::new_from_wallet_dir(wallet_dir),
             wallet_dir: wallet_dir.to_path_buf(),
             keyless_wallet,
+            hooks: None,
+            store: None,
         })
     }
 
+    /// Sign a transaction of the wallet with the signer of its key, which may hold the key
+    /// elsewhere, e.g. in an HSM or on an air-gapped device.
+    pub fn sign_with(
+        &self,
+        unsigned_tx: UnsignedTransaction,
+        signer: &dyn WalletSigner,
+    ) -> Result<SignedTransaction> {
+        if signer.main_pubkey() != self.main_pubkey {
+            return Err(Error::CouldNotSignTransaction(
+                TransferError::MainPubkeyMismatch,
+            ));
+        }
+        unsigned_tx
+            .verify_for_signing()
+            .map_err(Error::InvalidUnsignedTransaction)?;
+        let signed_tx = unsigned_tx
+            .sign_with(signer)
+            .map_err(Error::CouldNotSignTransaction)?;
+        signed_tx
+            .verify()
+            .map_err(Error::InvalidSignedTransaction)?;
+        Ok(signed_tx)
+    }
+
     // Stores the wallet to disk.
     // This requires having exclusive access to the wallet to prevent concurrent processes from writing to it
     pub(super) fn store(&self, exclusive_access: WalletExclusiveAccess) -> Result<()> {
-        store_wallet(&self.wallet_dir, &self.keyless_wallet)?;
+        self.store_recording(exclusive_access, None)
+    }
+
+    /// Store the wallet like `store`, then record the entry in its history before releasing the
+    /// lock. The tokens have moved by then, so failing to record them is only logged.
+    pub(super) fn store_recording(
+        &self,
+        exclusive_access: WalletExclusiveAccess,
+        entry: Option<HistoryEntry>,
+    ) -> Result<()> {
+        self.wallet_store().store_wallet(&self.keyless_wallet)?;
+        if let Some(wallet_dir) = self.dir_on_disk() {
+            CashNoteIndex::sync(wallet_dir, &self.keyless_wallet.available_cash_notes)?;
+            if let Some(entry) = entry {
+                let (kind, amount) = (entry.kind, entry.amount);
+                if let Err(err) = History::record(wallet_dir, entry) {
+                    warn!("Could not record the {kind:?} of {amount} in the wallet history: {err}");
+                }
+            }
+        }
         trace!("Releasing wallet lock");
         std::mem::drop(exclusive_access);
         Ok(())
@@ -308,16 +656,13 @@ impl WatchOnlyWallet {
     // Locks the wallet and returns exclusive access to the wallet
     // This lock prevents any other process from locking the wallet dir, effectively acts as a mutex for the wallet
     pub(super) fn lock(&self) -> Result<WalletExclusiveAccess> {
-        let lock = wallet_lockfile_name(&self.wallet_dir);
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(lock)?;
+        self.wallet_store().lock()
+    }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        file.lock_exclusive()?;
-        Ok(file)
+    // Locks the wallet like `lock`, failing with `Error::WalletLocked` instead of waiting when
+    // another process holds the lock
+    pub(super) fn try_lock(&self) -> Result<WalletExclusiveAccess> {
+        self.wallet_store().try_lock()
     }
 }
 
@@ -326,7 +671,7 @@ mod tests {
     use super::WatchOnlyWallet;
     use crate::{
         genesis::{create_first_cash_note_from_key, GENESIS_CASHNOTE_AMOUNT},
-        wallet::KeyLessWallet,
+        wallet::{Error, KeyLessWallet, WalletFileFormat},
         MainSecretKey, NanoTokens,
     };
     use assert_fs::TempDir;
@@ -347,6 +692,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn watchonly_wallet_derives_the_unique_pubkeys_of_its_cash_notes() -> Result<()> {
+        let main_sk = MainSecretKey::random();
+        let root_dir = TempDir::new()?;
+        let wallet = WatchOnlyWallet::load_from_root_dir(&root_dir, main_sk.main_pubkey())?;
+
+        let cash_note = create_first_cash_note_from_key(&main_sk)?;
+        assert_eq!(
+            cash_note.unique_pubkey(),
+            wallet.derive_unique_pubkey(&cash_note.derivation_index())
+        );
+
+        let reloaded = WatchOnlyWallet::load_from_root_dir(&root_dir, main_sk.main_pubkey())?;
+        assert_eq!(wallet.wallet_dir(), reloaded.wallet_dir());
+        assert!(WatchOnlyWallet::load_from(
+            reloaded.wallet_dir(),
+            MainSecretKey::random().main_pubkey()
+        )
+        .is_err());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn watchonly_wallet_to_and_from_file() -> Result<()> {
         let main_sk = MainSecretKey::random();
@@ -399,6 +767,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn watchonly_wallet_try_lock_fails_while_the_wallet_is_locked() -> Result<()> {
+        let main_pubkey = MainSecretKey::random().main_pubkey();
+        let wallet_dir = TempDir::new()?;
+        let wallet = WatchOnlyWallet::new(main_pubkey, &wallet_dir, KeyLessWallet::default());
+        let other = WatchOnlyWallet::load_from(&wallet_dir, main_pubkey)?;
+
+        let exclusive_access = wallet.lock()?;
+        assert!(matches!(other.try_lock(), Err(Error::WalletLocked(_))));
+
+        drop(exclusive_access);
+        let _exclusive_access = other.try_lock()?;
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn watchonly_wallet_reload() -> Result<()> {
         let main_sk = MainSecretKey::random();
@@ -420,4 +804,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn watchonly_wallet_keeps_the_format_of_its_file() -> Result<()> {
+        let main_sk = MainSecretKey::random();
+        let main_pubkey = main_sk.main_pubkey();
+        let wallet_dir = TempDir::new()?;
+        let mut wallet = WatchOnlyWallet::load_from(&wallet_dir, main_pubkey)?;
+        assert_eq!(wallet.file_format()?, WalletFileFormat::Legacy);
+
+        wallet.set_file_format(WalletFileFormat::Json)?;
+        wallet.deposit_and_store_to_disk(&vec![create_first_cash_note_from_key(&main_sk)?])?;
+        assert_eq!(wallet.file_format()?, WalletFileFormat::Json);
+
+        // the JSON is readable with standard tools
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(wallet_dir.join("wallet"))?)?;
+        assert_eq!(
+            json["available_cash_notes"].as_object().map(|o| o.len()),
+            Some(1)
+        );
+
+        for format in [WalletFileFormat::MsgPack, WalletFileFormat::Legacy] {
+            wallet.set_file_format(format)?;
+            let reloaded = WatchOnlyWallet::load_from(&wallet_dir, main_pubkey)?;
+            assert_eq!(reloaded.file_format()?, format);
+            assert_eq!(GENESIS_CASHNOTE_AMOUNT, reloaded.balance().as_nano());
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,245 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! UniFFI bindings of the client, to embed it in iOS and Android apps.
+//!
+//! The async methods are exported as `async` functions in Swift and `suspend` functions in
+//! Kotlin, running on a tokio runtime owned by this library. Upload progress is reported through
+//! the [`UploadListener`] callback interface, implemented on the foreign side.
+
+use sn_client::{
+    acc_packet::load_account_wallet_or_create_with_mnemonic,
+    protocol::storage::{ChunkAddress, RegisterAddress},
+    registers::Permissions,
+    transfers::{MainPubkey, NanoTokens, Transfer},
+    Client, ClientRegister, FilesApi, FilesDownload, UploadEvent, Uploader, WalletClient,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+use xor_name::XorName;
+
+uniffi::setup_scaffolding!();
+
+/// An error of the bindings, carrying the message of the underlying error.
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum SafeError {
+    // boxed, as the largest by far
+    #[error(transparent)]
+    Client(Box<sn_client::Error>),
+    #[error(transparent)]
+    Wallet(#[from] sn_client::transfers::WalletError),
+    #[error(transparent)]
+    Transfer(#[from] sn_client::transfers::TransferError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+impl From<sn_client::Error> for SafeError {
+    fn from(err: sn_client::Error) -> Self {
+        Self::Client(Box::new(err))
+    }
+}
+
+type Result<T> = std::result::Result<T, SafeError>;
+
+/// Notified of the progress of an upload, implemented by the app.
+#[uniffi::export(with_foreign)]
+pub trait UploadListener: Send + Sync {
+    /// A chunk was stored, or was already on the Network.
+    fn on_chunk_stored(&self, address: String);
+    /// A batch of chunks was paid for.
+    fn on_payment(&self, storage_cost_nanos: u64, new_balance_nanos: u64);
+}
+
+/// A client connected to the Network, with its wallet stored under `root_dir`.
+#[derive(uniffi::Object)]
+pub struct SafeClient {
+    client: Client,
+    root_dir: PathBuf,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl SafeClient {
+    /// Connect to the Network through `peers`, e.g. `/ip4/1.2.3.4/udp/1234/quic-v1/p2p/<id>`,
+    /// or through the default peers if empty.
+    #[uniffi::constructor]
+    pub async fn connect(peers: Vec<String>, root_dir: String) -> Result<Arc<Self>> {
+        let peers = peers
+            .iter()
+            .map(|peer| {
+                sn_peers_acquisition::parse_peer_addr(peer)
+                    .map_err(|err| SafeError::InvalidArgument(format!("{peer}: {err}")))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let peers = if peers.is_empty() { None } else { Some(peers) };
+        let client = Client::quick_start(peers).await?;
+        Ok(Arc::new(Self {
+            client,
+            root_dir: PathBuf::from(root_dir),
+        }))
+    }
+
+    /// Upload the file at `path`, paying from the wallet, returning its hex encoded address.
+    pub async fn upload_file(
+        &self,
+        path: String,
+        listener: Option<Arc<dyn UploadListener>>,
+    ) -> Result<String> {
+        let chunk_dir = tempfile::tempdir()?;
+        let (head_address, _data_map, _file_size, chunks) =
+            FilesApi::chunk_file(Path::new(&path), chunk_dir.path(), true)?;
+
+        let mut uploader = Uploader::new(self.client.clone(), self.root_dir.clone());
+        uploader.insert_chunk_paths(chunks);
+        if let Some(listener) = listener {
+            let mut events = uploader.get_event_receiver();
+            let _handle = tokio::spawn(async move {
+                while let Some(event) = events.recv().await {
+                    match event {
+                        UploadEvent::ChunkUploaded(address)
+                        | UploadEvent::ChunkAlreadyExistsInNetwork(address) => {
+                            listener.on_chunk_stored(address.to_hex())
+                        }
+                        UploadEvent::PaymentMade {
+                            storage_cost,
+                            new_balance,
+                            ..
+                        } => listener.on_payment(storage_cost.as_nano(), new_balance.as_nano()),
+                        _ => {}
+                    }
+                }
+            });
+        }
+        let _summary = uploader.start_upload().await?;
+        Ok(head_address.to_hex())
+    }
+
+    /// Download the file at the hex encoded `address` to `path`.
+    pub async fn download_file(&self, address: String, path: String) -> Result<()> {
+        let address = ChunkAddress::new(parse_xor_name(&address)?);
+        let files_api = FilesApi::new(self.client.clone(), self.root_dir.clone());
+        FilesDownload::new(files_api)
+            .download_file_to_path(address, None, PathBuf::from(path))
+            .await?;
+        Ok(())
+    }
+
+    /// The balance of the wallet, in nanos.
+    pub fn balance(&self) -> Result<u64> {
+        Ok(self.wallet()?.balance().as_nano())
+    }
+
+    /// The hex encoded address of the wallet, to receive tokens at.
+    pub fn address(&self) -> Result<String> {
+        Ok(self.wallet()?.address().to_hex())
+    }
+
+    /// Send `amount_nanos` to the hex encoded wallet address `to`, returning the hex encoded
+    /// transfer to hand to the recipient.
+    pub async fn send(&self, amount_nanos: u64, to: String) -> Result<String> {
+        let to = MainPubkey::from_hex(&to)?;
+        let cash_note = sn_client::send(
+            self.wallet()?,
+            NanoTokens::from(amount_nanos),
+            to,
+            &self.client,
+            true,
+        )
+        .await?;
+        Ok(Transfer::transfer_from_cash_note(&cash_note)?.to_hex()?)
+    }
+
+    /// Verify the hex encoded `transfer` with the Network and deposit it, returning the new
+    /// balance of the wallet in nanos.
+    pub async fn receive(&self, transfer: String) -> Result<u64> {
+        let transfer = Transfer::from_hex(&transfer)?;
+        let mut wallet = self.wallet()?;
+        let cash_notes = self.client.receive(&transfer, &wallet).await?;
+        wallet.deposit_and_store_to_disk(&cash_notes)?;
+        Ok(wallet.balance().as_nano())
+    }
+
+    /// Create a register named `name`, paying from the wallet.
+    pub async fn create_register(&self, name: String) -> Result<Arc<SafeRegister>> {
+        let mut wallet_client = WalletClient::new(self.client.clone(), self.wallet()?);
+        let (register, _storage_cost, _royalties_fees) = ClientRegister::create_online(
+            self.client.clone(),
+            XorName::from_content(name.as_bytes()),
+            &mut wallet_client,
+            true,
+            Permissions::default(),
+        )
+        .await?;
+        Ok(Arc::new(SafeRegister(Mutex::new(register))))
+    }
+
+    /// Get the register at the hex encoded `address` from the Network.
+    pub async fn get_register(&self, address: String) -> Result<Arc<SafeRegister>> {
+        let address = RegisterAddress::from_hex(&address)
+            .map_err(|err| SafeError::InvalidArgument(format!("{address}: {err}")))?;
+        let register = self.client.get_register(address).await?;
+        Ok(Arc::new(SafeRegister(Mutex::new(register))))
+    }
+}
+
+impl SafeClient {
+    fn wallet(&self) -> Result<sn_client::transfers::HotWallet> {
+        Ok(load_account_wallet_or_create_with_mnemonic(
+            &self.root_dir,
+            None,
+        )?)
+    }
+}
+
+/// A register on the Network.
+#[derive(uniffi::Object)]
+pub struct SafeRegister(Mutex<ClientRegister>);
+
+#[uniffi::export(async_runtime = "tokio")]
+impl SafeRegister {
+    /// The hex encoded address of the register.
+    pub async fn address(&self) -> String {
+        self.0.lock().await.address().to_hex()
+    }
+
+    /// The latest entries of the register.
+    pub async fn read(&self) -> Vec<Vec<u8>> {
+        self.0
+            .lock()
+            .await
+            .read()
+            .into_iter()
+            .map(|(_hash, entry)| entry)
+            .collect()
+    }
+
+    /// Write `entry` atop the latest entries and push it to the Network.
+    pub async fn write(&self, entry: Vec<u8>) -> Result<()> {
+        self.0
+            .lock()
+            .await
+            .write_merging_branches_online(&entry, true)
+            .await?;
+        Ok(())
+    }
+}
+
+fn parse_xor_name(hex: &str) -> Result<XorName> {
+    let bytes = hex::decode(hex)
+        .map_err(|err| SafeError::InvalidArgument(format!("{hex} is not hex: {err}")))?;
+    let bytes: [u8; xor_name::XOR_NAME_LEN] = bytes
+        .try_into()
+        .map_err(|_| SafeError::InvalidArgument(format!("{hex} is not an address")))?;
+    Ok(XorName(bytes))
+}
@@ -6,6 +6,10 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod local;
+
+pub use local::{LocalNetwork, LocalNetworkOptions, LocalNode, SAFENODE_PATH_ENV};
+
 use color_eyre::{eyre::eyre, Result};
 use libp2p::PeerId;
 use serde::{de, Deserialize, Deserializer};
@@ -0,0 +1,287 @@
+// Copyright 2024 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A local network, spawned and torn down by the tests themselves.
+//!
+//! Every node is a `safenode` process listening on localhost, with its data in a temporary
+//! directory. The genesis wallet, kept in that directory too, funds a faucet wallet once the
+//! network is up, from which the wallets handed out to the tests are funded.
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use libp2p::{identity::Keypair, multiaddr::Protocol, Multiaddr, PeerId};
+use sn_client::{
+    acc_packet::load_account_wallet_or_create_with_mnemonic, fund_faucet_from_genesis_wallet_in,
+    send, Client,
+};
+use sn_transfers::{HotWallet, NanoTokens};
+use std::{
+    net::{Ipv4Addr, UdpSocket},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use tempfile::TempDir;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// The env variable overriding the path of the `safenode` binary to run.
+pub const SAFENODE_PATH_ENV: &str = "SAFENODE_PATH";
+
+/// The options of a local network.
+#[derive(Debug, Clone)]
+pub struct LocalNetworkOptions {
+    pub node_count: usize,
+    /// The `safenode` binary, found next to the test binary when not provided.
+    pub safenode_path: Option<PathBuf>,
+    /// The delay between the start of two nodes.
+    pub node_startup_interval: Duration,
+    /// The delay between the start of the last node and the funding of the faucet.
+    pub settle_time: Duration,
+}
+
+impl Default for LocalNetworkOptions {
+    fn default() -> Self {
+        Self {
+            node_count: 25,
+            safenode_path: None,
+            node_startup_interval: Duration::from_millis(500),
+            settle_time: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A node of a local network.
+#[derive(Debug)]
+pub struct LocalNode {
+    pub peer_id: PeerId,
+    pub listen_addr: Multiaddr,
+    pub root_dir: PathBuf,
+    process: Child,
+}
+
+/// A running local network, torn down when dropped.
+pub struct LocalNetwork {
+    nodes: Vec<LocalNode>,
+    /// Holds the data of all the nodes and wallets.
+    dir: TempDir,
+    faucet_dir: PathBuf,
+    /// Guards the faucet wallet, which is loaded from disk by each use.
+    faucet_lock: Mutex<()>,
+    wallet_count: AtomicUsize,
+}
+
+impl LocalNetwork {
+    /// Spawn a local network of `node_count` nodes, with a funded faucet.
+    pub async fn spawn(node_count: usize) -> Result<Self> {
+        Self::spawn_with(LocalNetworkOptions {
+            node_count,
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn spawn_with(options: LocalNetworkOptions) -> Result<Self> {
+        if options.node_count == 0 {
+            return Err(eyre!("A local network needs at least one node"));
+        }
+        let safenode_path = match options.safenode_path {
+            Some(path) => path,
+            None => default_safenode_path()?,
+        };
+
+        let dir = tempfile::Builder::new()
+            .prefix("sn_local_network")
+            .tempdir()?;
+        info!(
+            "Spawning a local network of {} nodes in {:?}",
+            options.node_count,
+            dir.path()
+        );
+
+        // The network is built even when a node fails to start, so it is torn down on error.
+        let faucet_dir = dir.path().join("faucet");
+        let mut network = Self {
+            nodes: Vec::with_capacity(options.node_count),
+            dir,
+            faucet_dir,
+            faucet_lock: Mutex::new(()),
+            wallet_count: AtomicUsize::new(0),
+        };
+
+        for number in 1..=options.node_count {
+            let root_dir = network.dir.path().join(format!("safenode{number}"));
+            let bootstrap_peers = network.bootstrap_peers();
+            let node = spawn_node(&safenode_path, &root_dir, &bootstrap_peers)
+                .wrap_err_with(|| format!("Failed to spawn node {number}"))?;
+            debug!("Spawned node {number}: {}", node.listen_addr);
+            network.nodes.push(node);
+            tokio::time::sleep(options.node_startup_interval).await;
+        }
+        tokio::time::sleep(options.settle_time).await;
+
+        let client = network.client().await?;
+        let mut faucet = load_account_wallet_or_create_with_mnemonic(&network.faucet_dir, None)?;
+        let genesis_dir = network.dir.path().join("genesis");
+        fund_faucet_from_genesis_wallet_in(&client, &mut faucet, &genesis_dir)
+            .await
+            .wrap_err("Failed to fund the faucet from genesis")?;
+        info!("Local network of {} nodes is up", network.nodes.len());
+
+        Ok(network)
+    }
+
+    pub fn nodes(&self) -> &[LocalNode] {
+        &self.nodes
+    }
+
+    /// The peers to bootstrap from, the first node of the network.
+    pub fn bootstrap_peers(&self) -> Vec<Multiaddr> {
+        self.nodes
+            .first()
+            .map(|node| node.listen_addr.clone())
+            .into_iter()
+            .collect()
+    }
+
+    /// A new client connected to the network, with a random key.
+    pub async fn client(&self) -> Result<Client> {
+        let client = Client::new(
+            bls::SecretKey::random(),
+            Some(self.bootstrap_peers()),
+            None,
+            None,
+        )
+        .await?;
+        Ok(client)
+    }
+
+    /// A new wallet holding `amount` tokens from the faucet.
+    pub async fn funded_wallet(&self, client: &Client, amount: NanoTokens) -> Result<HotWallet> {
+        let number = self.wallet_count.fetch_add(1, Ordering::Relaxed);
+        let wallet_dir = self.dir.path().join("wallets").join(number.to_string());
+        let mut wallet = load_account_wallet_or_create_with_mnemonic(&wallet_dir, None)?;
+
+        let cash_note = {
+            let _guard = self.faucet_lock.lock().await;
+            let mut faucet = load_account_wallet_or_create_with_mnemonic(&self.faucet_dir, None)?;
+            faucet.try_load_cash_notes()?;
+            send(faucet, amount, wallet.address(), client, true).await?
+        };
+
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+        Ok(wallet)
+    }
+
+    /// A new client, with a new wallet holding `amount` tokens.
+    pub async fn client_and_funded_wallet(
+        &self,
+        amount: NanoTokens,
+    ) -> Result<(Client, HotWallet)> {
+        let client = self.client().await?;
+        let wallet = self.funded_wallet(&client, amount).await?;
+        Ok((client, wallet))
+    }
+
+    /// Kill all the nodes and remove the data of the network.
+    ///
+    /// This is also done when the network is dropped.
+    pub fn shutdown(self) {}
+}
+
+impl Drop for LocalNetwork {
+    fn drop(&mut self) {
+        info!(
+            "Tearing down the local network of {} nodes",
+            self.nodes.len()
+        );
+        for node in self.nodes.iter_mut() {
+            if let Err(err) = node.process.kill() {
+                warn!("Failed to kill node {}: {err}", node.peer_id);
+            }
+            let _ = node.process.wait();
+        }
+        // the temporary directory is removed once dropped
+    }
+}
+
+fn spawn_node(
+    safenode_path: &Path,
+    root_dir: &Path,
+    bootstrap_peers: &[Multiaddr],
+) -> Result<LocalNode> {
+    std::fs::create_dir_all(root_dir)?;
+
+    // The key is written before the node starts, for its address to be known in advance.
+    let secret_key = libp2p::identity::ed25519::SecretKey::generate();
+    std::fs::write(root_dir.join("secret-key"), secret_key.as_ref())?;
+    let keypair: Keypair = libp2p::identity::ed25519::Keypair::from(secret_key).into();
+    let peer_id = keypair.public().to_peer_id();
+
+    let port = free_udp_port()?;
+    let listen_addr = Multiaddr::from(Ipv4Addr::LOCALHOST)
+        .with(Protocol::Udp(port))
+        .with(Protocol::QuicV1)
+        .with(Protocol::P2p(peer_id));
+
+    let mut command = Command::new(safenode_path);
+    let _ = command
+        .arg("--local")
+        .args(["--ip", &Ipv4Addr::LOCALHOST.to_string()])
+        .args(["--port", &port.to_string()])
+        .arg("--root-dir")
+        .arg(root_dir)
+        .arg("--log-output-dest")
+        .arg(root_dir.join("logs"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if bootstrap_peers.is_empty() {
+        let _ = command.arg("--first");
+    }
+    for peer in bootstrap_peers {
+        let _ = command.args(["--peer", &peer.to_string()]);
+    }
+    let process = command
+        .spawn()
+        .wrap_err_with(|| format!("Failed to run {safenode_path:?}"))?;
+
+    Ok(LocalNode {
+        peer_id,
+        listen_addr,
+        root_dir: root_dir.to_path_buf(),
+        process,
+    })
+}
+
+fn free_udp_port() -> Result<u16> {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+    Ok(socket.local_addr()?.port())
+}
+
+/// The `safenode` binary of the env variable, else the one built along with the current binary.
+fn default_safenode_path() -> Result<PathBuf> {
+    if let Ok(path) = std::env::var(SAFENODE_PATH_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+
+    let binary = format!("safenode{}", std::env::consts::EXE_SUFFIX);
+    let current_exe = std::env::current_exe()?;
+    // test binaries live in the `deps` directory, next to the other binaries
+    current_exe
+        .ancestors()
+        .skip(1)
+        .take(2)
+        .map(|dir| dir.join(&binary))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            eyre!("Could not find {binary} next to {current_exe:?}, build it or set {SAFENODE_PATH_ENV}")
+        })
+}